@@ -0,0 +1,137 @@
+//! Push-to-talk style text chat between two machines over speakers and microphones
+//!
+//! Run one instance per laptop with different station IDs, then type lines:
+//!
+//!   cargo run --release -p transmitwave-cli --features audio --example chat -- --station 1
+//!   cargo run --release -p transmitwave-cli --features audio --example chat -- --station 2
+//!
+//! Each line is queued on a half-duplex link: it waits for a quiet channel,
+//! transmits, and retries until the other side acknowledges.
+
+use clap::Parser;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use transmitwave_core::{resample_audio, HalfDuplexLink, LinkConfig, LinkEvent, SAMPLE_RATE};
+
+/// Audio handed to the link per step (100 ms)
+const STEP_SAMPLES: usize = SAMPLE_RATE / 10;
+
+#[derive(Parser)]
+#[command(about = "Half-duplex text chat over the audio modem")]
+struct Args {
+    /// This station's ID (0-254); the peer must use a different one
+    #[arg(short, long)]
+    station: u8,
+
+    /// Transmissions per message before giving up
+    #[arg(long, default_value = "4")]
+    max_attempts: u32,
+
+    /// Output volume (0.0-1.0)
+    #[arg(long, default_value = "0.8")]
+    volume: f32,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let config = LinkConfig { max_attempts: args.max_attempts, ..LinkConfig::default() };
+    let mut link = HalfDuplexLink::new(args.station, config)?;
+
+    let host = cpal::default_host();
+    let input_device = host.default_input_device().ok_or("No input device")?;
+    let output_device = host.default_output_device().ok_or("No output device")?;
+    let input_config = input_device.default_input_config()?;
+    let output_config = output_device.default_output_config()?;
+    if input_config.sample_format() != cpal::SampleFormat::F32
+        || output_config.sample_format() != cpal::SampleFormat::F32
+    {
+        return Err("Sound card does not offer f32 samples".into());
+    }
+    let input_rate = input_config.sample_rate().0 as usize;
+    let output_rate = output_config.sample_rate().0 as usize;
+    let input_channels = input_config.channels() as usize;
+    let output_channels = output_config.channels() as usize;
+
+    // Microphone: downmix to mono and hand chunks to the main loop
+    let (mic_tx, mic_rx) = mpsc::channel::<Vec<f32>>();
+    let input_stream = input_device.build_input_stream(
+        &input_config.into(),
+        move |data: &[f32], _: &cpal::InputCallbackInfo| {
+            let mono = data
+                .chunks_exact(input_channels)
+                .map(|frame| frame.iter().sum::<f32>() / input_channels as f32)
+                .collect();
+            let _ = mic_tx.send(mono);
+        },
+        |e| eprintln!("Input stream error: {}", e),
+        None,
+    )?;
+
+    // Speaker: play whatever the link queued, silence otherwise
+    let playout = Arc::new(Mutex::new(VecDeque::<f32>::new()));
+    let speaker_queue = Arc::clone(&playout);
+    let output_stream = output_device.build_output_stream(
+        &output_config.into(),
+        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+            let mut queue = speaker_queue.lock().unwrap();
+            for frame in data.chunks_mut(output_channels) {
+                frame.fill(queue.pop_front().unwrap_or(0.0));
+            }
+        },
+        |e| eprintln!("Output stream error: {}", e),
+        None,
+    )?;
+    input_stream.play()?;
+    output_stream.play()?;
+
+    // Keyboard: one message per line
+    let (line_tx, line_rx) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if line_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    println!("Station {} ready. Type a message and press Enter.", args.station);
+    let mut captured = Vec::new();
+    loop {
+        while let Ok(line) = line_rx.try_recv() {
+            let text = line.trim();
+            if !text.is_empty() {
+                let seq = link.send(text.as_bytes())?;
+                println!("[queued #{}] {}", seq, text);
+            }
+        }
+
+        match mic_rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(chunk) => captured.extend(resample_audio(&chunk, input_rate, SAMPLE_RATE)),
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err("Microphone stream closed".into()),
+        }
+
+        while captured.len() >= STEP_SAMPLES {
+            let step: Vec<f32> = captured.drain(..STEP_SAMPLES).collect();
+            let output = link.process(&step)?;
+
+            if let Some(audio) = output.transmit {
+                let scaled: Vec<f32> = audio.iter().map(|s| s * args.volume).collect();
+                playout.lock().unwrap().extend(resample_audio(&scaled, SAMPLE_RATE, output_rate));
+            }
+            for event in output.events {
+                match event {
+                    LinkEvent::Received { from, payload } => {
+                        println!("<{}> {}", from, String::from_utf8_lossy(&payload));
+                    }
+                    LinkEvent::Delivered { seq } => println!("[delivered #{}]", seq),
+                    LinkEvent::Retrying { seq, attempt } => println!("[no ack for #{}, attempt {}]", seq, attempt),
+                    LinkEvent::Failed { seq } => println!("[failed #{}]", seq),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use transmitwave_core::audio_io;
+use transmitwave_core::{DecoderFsk, EncoderFsk, NetworkKey, SAMPLE_RATE};
+
+/// Name of the summary written next to the decoded files
+pub const REPORT_FILE: &str = "report.json";
+
+/// Outcome for one recording
+#[derive(Serialize)]
+pub struct BatchEntry {
+    pub file: String,
+    /// Decoded payload file, if the decode succeeded
+    pub output: Option<String>,
+    pub bytes: Option<usize>,
+    pub snr_db: Option<f32>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Serialize)]
+pub struct BatchReport {
+    pub input_dir: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Wall-clock time for the whole batch
+    pub elapsed_ms: u64,
+    pub jobs: usize,
+    pub files: Vec<BatchEntry>,
+}
+
+/// Outcome for one payload file of an encode batch
+#[derive(Serialize)]
+pub struct EncodeBatchEntry {
+    pub file: String,
+    /// Written WAV file, if the encode succeeded
+    pub output: Option<String>,
+    pub bytes: usize,
+    pub duration_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct EncodeBatchReport {
+    pub input_dir: String,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Wall-clock time for the whole batch
+    pub elapsed_ms: u64,
+    pub jobs: usize,
+    pub files: Vec<EncodeBatchEntry>,
+}
+
+/// Regular files directly inside `dir` (not recursive), sorted by name
+fn find_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// WAV files directly inside `dir` (not recursive), sorted by name
+pub fn find_wavs(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut wavs: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"))
+        })
+        .collect();
+    wavs.sort();
+    Ok(wavs)
+}
+
+fn decode_one(path: &Path, output_dir: &Path, network_key: Option<NetworkKey>) -> BatchEntry {
+    let start = Instant::now();
+    let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let output = output_dir.join(path.with_extension("bin").file_name().unwrap_or_default());
+
+    let result = audio_io::read_wav_file(path)
+        .and_then(|(samples, _)| {
+            let mut decoder = DecoderFsk::new()?;
+            decoder.set_network_key(network_key);
+            decoder.decode_with_metadata(&samples)
+        })
+        .map_err(|e| e.to_string())
+        .and_then(|message| {
+            std::fs::write(&output, &message.payload)
+                .map(|_| message)
+                .map_err(|e| format!("writing {}: {}", output.display(), e))
+        });
+
+    let elapsed_ms = start.elapsed().as_millis() as u64;
+    match result {
+        Ok(message) => BatchEntry {
+            file,
+            output: Some(output.display().to_string()),
+            bytes: Some(message.payload.len()),
+            snr_db: Some(message.quality.snr_db),
+            error: None,
+            elapsed_ms,
+        },
+        Err(error) => BatchEntry { file, output: None, bytes: None, snr_db: None, error: Some(error), elapsed_ms },
+    }
+}
+
+/// Decode every WAV in `input_dir` on `jobs` threads (0 = one per core)
+///
+/// Payloads are written to `output_dir/<name>.bin`; failures are reported,
+/// not fatal. The report is also saved as `output_dir/report.json`.
+pub fn decode_batch(
+    input_dir: &Path,
+    output_dir: &Path,
+    jobs: usize,
+    network_key: Option<NetworkKey>,
+) -> Result<BatchReport, Box<dyn std::error::Error>> {
+    let wavs = find_wavs(input_dir)?;
+    std::fs::create_dir_all(output_dir)?;
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let start = Instant::now();
+    let files: Vec<BatchEntry> =
+        pool.install(|| wavs.par_iter().map(|path| decode_one(path, output_dir, network_key)).collect());
+
+    let succeeded = files.iter().filter(|entry| entry.error.is_none()).count();
+    let report = BatchReport {
+        input_dir: input_dir.display().to_string(),
+        total: files.len(),
+        succeeded,
+        failed: files.len() - succeeded,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        jobs: pool.current_num_threads(),
+        files,
+    };
+    std::fs::write(output_dir.join(REPORT_FILE), serde_json::to_string_pretty(&report)?)?;
+    Ok(report)
+}
+
+/// Encode every file in `input_dir` (one payload each) on `jobs` threads (0 = one per core)
+///
+/// Frames are written to `output_dir/<name>.wav`; payloads that fail to
+/// encode are reported, not fatal. The report is also saved as
+/// `output_dir/report.json`.
+pub fn encode_batch(
+    input_dir: &Path,
+    output_dir: &Path,
+    jobs: usize,
+    encoder: &EncoderFsk,
+) -> Result<EncodeBatchReport, Box<dyn std::error::Error>> {
+    let inputs = find_files(input_dir)?;
+    let payloads = inputs.iter().map(std::fs::read).collect::<std::io::Result<Vec<_>>>()?;
+    std::fs::create_dir_all(output_dir)?;
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(jobs).build()?;
+    let jobs = pool.current_num_threads();
+    let start = Instant::now();
+    let frames = encoder.encode_batch(&payloads, jobs);
+    let files: Vec<EncodeBatchEntry> = pool.install(|| {
+        inputs
+            .par_iter()
+            .zip(&payloads)
+            .zip(frames)
+            .map(|((path, payload), frame)| {
+                let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let output = output_dir.join(path.with_extension("wav").file_name().unwrap_or_default());
+                let result = frame.map_err(|e| e.to_string()).and_then(|samples| {
+                    let wav = audio_io::encoded_wav_bytes(&samples, 1.0, None).map_err(|e| e.to_string())?;
+                    std::fs::write(&output, wav)
+                        .map(|_| samples.len())
+                        .map_err(|e| format!("writing {}: {}", output.display(), e))
+                });
+                match result {
+                    Ok(samples) => EncodeBatchEntry {
+                        file,
+                        output: Some(output.display().to_string()),
+                        bytes: payload.len(),
+                        duration_ms: Some((samples * 1000 / SAMPLE_RATE) as u64),
+                        error: None,
+                    },
+                    Err(error) => {
+                        EncodeBatchEntry { file, output: None, bytes: payload.len(), duration_ms: None, error: Some(error) }
+                    }
+                }
+            })
+            .collect()
+    });
+
+    let succeeded = files.iter().filter(|entry| entry.error.is_none()).count();
+    let report = EncodeBatchReport {
+        input_dir: input_dir.display().to_string(),
+        total: files.len(),
+        succeeded,
+        failed: files.len() - succeeded,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        jobs,
+        files,
+    };
+    std::fs::write(output_dir.join(REPORT_FILE), serde_json::to_string_pretty(&report)?)?;
+    Ok(report)
+}
@@ -0,0 +1,401 @@
+//! Hardware-in-the-loop run over real sound cards
+//!
+//! Plays encoded frames on one device while recording another (or the same
+//! machine through a loopback cable or virtual device), decodes every capture
+//! and compares it with the payload that was sent. Meant for qualifying
+//! speakers, microphones and rooms before deployment, not for `cargo test`:
+//!
+//!   cargo run --release -p transmitwave-cli --features audio --bin transmitwave-hil -- --list-devices
+//!   cargo run --release -p transmitwave-cli --features audio --bin transmitwave-hil -- \
+//!       --output-device "USB Audio" --input-device "USB Audio" --payloads 20 --report hil.xml --format junit
+//!
+//! Exits with status 1 when any payload failed, so a CI job on a device rig
+//! can gate on it.
+
+use clap::{Parser, ValueEnum};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use transmitwave_core::{resample_audio, DecoderFsk, EncoderFsk, FrameProfile, SplitMix64, SAMPLE_RATE};
+
+/// Recording kept after the last sample left the speaker, for the round trip
+const TAIL_MS: usize = 1000;
+
+#[derive(Parser)]
+#[command(about = "Play encoded frames through real audio devices and decode the capture")]
+struct Args {
+    /// Print the available input and output devices and exit
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Recording device (part of its name; default input if omitted)
+    #[arg(long, value_name = "NAME")]
+    input_device: Option<String>,
+
+    /// Playback device (part of its name; default output if omitted)
+    #[arg(long, value_name = "NAME")]
+    output_device: Option<String>,
+
+    /// Number of payloads to send
+    #[arg(long, default_value = "10")]
+    payloads: u32,
+
+    /// Bytes per payload
+    #[arg(long, default_value = "32")]
+    payload_size: usize,
+
+    /// Frame profile to qualify the path with
+    #[arg(long, value_enum, default_value = "standard")]
+    profile: Profile,
+
+    /// Output volume (0.0-1.0)
+    #[arg(long, default_value = "0.8")]
+    volume: f32,
+
+    /// Seed of the payload contents, to repeat a run byte for byte
+    #[arg(long, default_value = "1")]
+    seed: u64,
+
+    /// Quiet time between payloads in ms, for room echo to die down
+    #[arg(long, default_value = "500")]
+    pause_ms: u64,
+
+    /// Write a report of every payload to this file
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+
+    /// Report format
+    #[arg(long, value_enum, default_value = "json")]
+    format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Profile {
+    Standard,
+    Short,
+    Telephony,
+    CodecRobust,
+    Narrowband,
+}
+
+impl From<Profile> for FrameProfile {
+    fn from(profile: Profile) -> Self {
+        match profile {
+            Profile::Standard => FrameProfile::Standard,
+            Profile::Short => FrameProfile::Short,
+            Profile::Telephony => FrameProfile::Telephony,
+            Profile::CodecRobust => FrameProfile::CodecRobust,
+            Profile::Narrowband => FrameProfile::Narrowband,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Json,
+    Junit,
+}
+
+/// Outcome of one payload
+#[derive(Debug, Serialize)]
+struct CaseResult {
+    index: u32,
+    passed: bool,
+    /// Frame length on the air
+    airtime_ms: u64,
+    /// Wall time from queueing the frame to the decode result
+    elapsed_ms: u64,
+    snr_db: Option<f32>,
+    min_symbol_margin: Option<f32>,
+    parity_byte_errors: Option<usize>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    input_device: String,
+    output_device: String,
+    profile: String,
+    payload_size: usize,
+    volume: f32,
+    seed: u64,
+    passed: u32,
+    failed: u32,
+    cases: Vec<CaseResult>,
+}
+
+impl Report {
+    fn to_junit(&self) -> String {
+        let total_secs: f64 = self.cases.iter().map(|case| case.elapsed_ms as f64 / 1000.0).sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml += &format!(
+            "<testsuite name=\"transmitwave-hil\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            self.cases.len(),
+            self.failed,
+            total_secs
+        );
+        xml += "  <properties>\n";
+        for (name, value) in [
+            ("input_device", self.input_device.clone()),
+            ("output_device", self.output_device.clone()),
+            ("profile", self.profile.clone()),
+            ("payload_size", self.payload_size.to_string()),
+            ("volume", self.volume.to_string()),
+            ("seed", self.seed.to_string()),
+        ] {
+            xml += &format!("    <property name=\"{}\" value=\"{}\"/>\n", name, xml_escape(&value));
+        }
+        xml += "  </properties>\n";
+        for case in &self.cases {
+            xml += &format!(
+                "  <testcase classname=\"transmitwave-hil.{}\" name=\"payload-{}\" time=\"{:.3}\"",
+                xml_escape(&self.profile),
+                case.index,
+                case.elapsed_ms as f64 / 1000.0
+            );
+            match &case.error {
+                Some(error) => {
+                    xml += &format!(">\n    <failure message=\"{}\"/>\n  </testcase>\n", xml_escape(error));
+                }
+                None => xml += "/>\n",
+            }
+        }
+        xml += "</testsuite>\n";
+        xml
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Payload `index` of a run: SplitMix64 bytes, reproducible from the seed
+fn payload(seed: u64, index: u32, size: usize) -> Vec<u8> {
+    let mut rng = SplitMix64::new(seed ^ (index as u64).wrapping_mul(0xA24B_AED4_963E_E407));
+    (0..size).map(|_| rng.next_u64() as u8).collect()
+}
+
+/// Device whose name contains `name`, or the default one
+fn find_device(host: &cpal::Host, name: Option<&str>, input: bool) -> Result<cpal::Device, Box<dyn Error>> {
+    let kind = if input { "input" } else { "output" };
+    let Some(name) = name else {
+        let device = if input { host.default_input_device() } else { host.default_output_device() };
+        return device.ok_or_else(|| format!("No default {} device", kind).into());
+    };
+    let devices: Vec<cpal::Device> =
+        if input { host.input_devices()?.collect() } else { host.output_devices()?.collect() };
+    devices
+        .into_iter()
+        .find(|device| device.name().is_ok_and(|device_name| device_name.contains(name)))
+        .ok_or_else(|| format!("No {} device matching '{}' (see --list-devices)", kind, name).into())
+}
+
+fn list_devices(host: &cpal::Host) -> Result<(), Box<dyn Error>> {
+    println!("Input devices:");
+    for device in host.input_devices()? {
+        println!("  {}", device.name()?);
+    }
+    println!("Output devices:");
+    for device in host.output_devices()? {
+        println!("  {}", device.name()?);
+    }
+    Ok(())
+}
+
+/// Open input and output streams kept running for the whole run
+struct Rig {
+    mic_rx: mpsc::Receiver<Vec<f32>>,
+    playout: Arc<Mutex<VecDeque<f32>>>,
+    input_rate: usize,
+    output_rate: usize,
+    _streams: (cpal::Stream, cpal::Stream),
+}
+
+impl Rig {
+    fn open(input_device: &cpal::Device, output_device: &cpal::Device) -> Result<Self, Box<dyn Error>> {
+        let input_config = input_device.default_input_config()?;
+        let output_config = output_device.default_output_config()?;
+        if input_config.sample_format() != cpal::SampleFormat::F32
+            || output_config.sample_format() != cpal::SampleFormat::F32
+        {
+            return Err("Sound card does not offer f32 samples".into());
+        }
+        let input_rate = input_config.sample_rate().0 as usize;
+        let output_rate = output_config.sample_rate().0 as usize;
+        let input_channels = input_config.channels() as usize;
+        let output_channels = output_config.channels() as usize;
+
+        // Microphone: downmix to mono and hand chunks to the main loop
+        let (mic_tx, mic_rx) = mpsc::channel::<Vec<f32>>();
+        let input_stream = input_device.build_input_stream(
+            &input_config.into(),
+            move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                let mono = data
+                    .chunks_exact(input_channels)
+                    .map(|frame| frame.iter().sum::<f32>() / input_channels as f32)
+                    .collect();
+                let _ = mic_tx.send(mono);
+            },
+            |e| eprintln!("Input stream error: {}", e),
+            None,
+        )?;
+
+        // Speaker: same sample on every channel, silence when nothing is queued
+        let playout = Arc::new(Mutex::new(VecDeque::new()));
+        let speaker = Arc::clone(&playout);
+        let output_stream = output_device.build_output_stream(
+            &output_config.into(),
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                let mut queue = speaker.lock().unwrap();
+                for frame in data.chunks_mut(output_channels) {
+                    frame.fill(queue.pop_front().unwrap_or(0.0));
+                }
+            },
+            |e| eprintln!("Output stream error: {}", e),
+            None,
+        )?;
+        input_stream.play()?;
+        output_stream.play()?;
+        Ok(Self { mic_rx, playout, input_rate, output_rate, _streams: (input_stream, output_stream) })
+    }
+
+    /// Play `samples` (modem rate) and return what the input heard meanwhile
+    /// and for `TAIL_MS` after, at the modem rate
+    fn play_and_record(&self, samples: &[f32]) -> Result<Vec<f32>, Box<dyn Error>> {
+        while self.mic_rx.try_recv().is_ok() {}
+        let playback = resample_audio(samples, SAMPLE_RATE, self.output_rate);
+        let tail_frames = TAIL_MS * self.input_rate / 1000;
+        let max_frames = 2 * (playback.len() * self.input_rate / self.output_rate + tail_frames);
+        self.playout.lock().unwrap().extend(playback);
+
+        let mut recording = Vec::new();
+        let mut drained_at = None;
+        loop {
+            let chunk = self.mic_rx.recv_timeout(Duration::from_secs(2)).map_err(|_| "Input stream stopped")?;
+            recording.extend(chunk);
+            if drained_at.is_none() && self.playout.lock().unwrap().is_empty() {
+                drained_at = Some(recording.len());
+            }
+            if drained_at.is_some_and(|at| recording.len() >= at + tail_frames) {
+                break;
+            }
+            if recording.len() > max_frames {
+                self.playout.lock().unwrap().clear();
+                return Err("Output stream did not play the frame".into());
+            }
+        }
+        Ok(resample_audio(&recording, self.input_rate, SAMPLE_RATE))
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let host = cpal::default_host();
+    if args.list_devices {
+        return list_devices(&host);
+    }
+
+    let mut encoder = EncoderFsk::new()?;
+    encoder.set_profile(args.profile.into());
+    let airtime = encoder.airtime(args.payload_size, false)?;
+    let mut decoder = DecoderFsk::new()?;
+
+    let input_device = find_device(&host, args.input_device.as_deref(), true)?;
+    let output_device = find_device(&host, args.output_device.as_deref(), false)?;
+    let rig = Rig::open(&input_device, &output_device)?;
+    let mut report = Report {
+        input_device: input_device.name()?,
+        output_device: output_device.name()?,
+        profile: format!("{:?}", args.profile),
+        payload_size: args.payload_size,
+        volume: args.volume,
+        seed: args.seed,
+        passed: 0,
+        failed: 0,
+        cases: Vec::new(),
+    };
+    println!(
+        "Sending {} payloads of {} bytes ({:?}, {} ms each) from '{}' to '{}' ({} Hz out, {} Hz in)",
+        args.payloads,
+        args.payload_size,
+        args.profile,
+        airtime.duration_ms(),
+        report.output_device,
+        report.input_device,
+        rig.output_rate,
+        rig.input_rate
+    );
+
+    for index in 0..args.payloads {
+        let sent = payload(args.seed, index, args.payload_size);
+        let samples: Vec<f32> = encoder.encode(&sent)?.iter().map(|s| s * args.volume).collect();
+        let started = Instant::now();
+        let outcome = rig.play_and_record(&samples).and_then(|recording| {
+            let message = decoder.decode_with_metadata(&recording)?;
+            if message.payload != sent {
+                return Err("Decoded payload differs from the one sent".into());
+            }
+            Ok(message.quality)
+        });
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        let case = match outcome {
+            Ok(quality) => {
+                println!(
+                    "  #{}: ok, SNR {:.1} dB, min margin {:.2}, {} corrected bytes",
+                    index, quality.snr_db, quality.min_symbol_margin, quality.parity_byte_errors
+                );
+                report.passed += 1;
+                CaseResult {
+                    index,
+                    passed: true,
+                    airtime_ms: airtime.duration_ms(),
+                    elapsed_ms,
+                    snr_db: Some(quality.snr_db),
+                    min_symbol_margin: Some(quality.min_symbol_margin),
+                    parity_byte_errors: Some(quality.parity_byte_errors),
+                    error: None,
+                }
+            }
+            Err(e) => {
+                println!("  #{}: FAILED: {}", index, e);
+                report.failed += 1;
+                CaseResult {
+                    index,
+                    passed: false,
+                    airtime_ms: airtime.duration_ms(),
+                    elapsed_ms,
+                    snr_db: None,
+                    min_symbol_margin: None,
+                    parity_byte_errors: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        report.cases.push(case);
+        std::thread::sleep(Duration::from_millis(args.pause_ms));
+    }
+
+    println!("{} of {} payloads decoded", report.passed, args.payloads);
+    if let Some(path) = &args.report {
+        let contents = match args.format {
+            ReportFormat::Json => serde_json::to_string_pretty(&report)?,
+            ReportFormat::Junit => report.to_junit(),
+        };
+        std::fs::write(path, contents)?;
+        println!("Report written to {}", path.display());
+    }
+    if report.failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::fmt::Write;
+use transmitwave_core::{
+    find_transmission, hexdump_frame, DecoderFsk, NetworkKey, TrimOptions, POSTAMBLE_SAMPLES,
+    PREAMBLE_SAMPLES, SAMPLE_RATE, SYNC_SILENCE_SAMPLES,
+};
+
+fn position(sample: usize) -> String {
+    format!("sample {} ({:.3}s)", sample, sample as f32 / SAMPLE_RATE as f32)
+}
+
+/// Layer-by-layer report of the first frame in `samples`: sync positions, length
+/// prefix, RS blocks, padding, then the decoded header fields, CRCs and payload
+pub fn inspect_frame(samples: &[f32], network_key: Option<NetworkKey>) -> String {
+    let mut out = String::new();
+
+    let options = TrimOptions { network_key, ..Default::default() };
+    match find_transmission(samples, &options) {
+        Some(span) => {
+            let _ = writeln!(out, "preamble     {}", position(span.preamble));
+            let _ = writeln!(out, "data start   {}", position(span.preamble + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES));
+            match span.postamble_end {
+                Some(end) => {
+                    let _ = writeln!(out, "postamble    {}", position(end - POSTAMBLE_SAMPLES));
+                }
+                None => out.push_str("postamble    not detected\n"),
+            }
+        }
+        None => out.push_str("preamble     not detected\n"),
+    }
+
+    let mut decoder = match DecoderFsk::new() {
+        Ok(decoder) => decoder,
+        Err(e) => {
+            let _ = writeln!(out, "decoder      {}", e);
+            return out;
+        }
+    };
+    decoder.set_network_key(network_key);
+    decoder.set_frame_inspection(true);
+    let result = decoder.decode(samples);
+
+    match decoder.frame_inspection().filter(|inspection| !inspection.prefix.is_empty()) {
+        Some(inspection) => {
+            let prefix: Vec<String> = inspection.prefix.iter().map(|b| format!("{:02x}", b)).collect();
+            let _ = writeln!(out, "\nprefix       {} (frame of {} bytes)", prefix.join(" "), inspection.frame_len);
+            match inspection.fec_mode {
+                Some(mode) => {
+                    let _ = writeln!(out, "fec          {} in {} block(s)", mode, inspection.blocks.len());
+                }
+                None => out.push_str("fec          no mode decoded the first block\n"),
+            }
+            for (i, block) in inspection.blocks.iter().enumerate() {
+                let corrected = match block.corrected_bytes {
+                    Some(bytes) => format!("{} corrected", bytes),
+                    None => "failed".to_string(),
+                };
+                let _ = writeln!(
+                    out,
+                    "  block {:<3}  offset {:<5} {} data + {} parity, {}",
+                    i, block.offset, block.data_bytes, block.parity_bytes, corrected
+                );
+            }
+            if !inspection.blocks_complete() {
+                let _ = writeln!(out, "  {} of {} frame bytes recovered", inspection.frame.len(), inspection.frame_len);
+            }
+            let _ = writeln!(
+                out,
+                "padding      {} byte(s), {} unexpected",
+                inspection.padding.len(),
+                inspection.padding_mismatches()
+            );
+            if !inspection.frame.is_empty() {
+                out.push('\n');
+                out.push_str(&hexdump_frame(&inspection.frame));
+            }
+        }
+        None => out.push_str("\nno length-prefixed frame was demodulated\n"),
+    }
+
+    out.push('\n');
+    match &result {
+        Ok(payload) => {
+            let _ = writeln!(out, "result       decoded {} bytes", payload.len());
+        }
+        Err(e) => {
+            let _ = writeln!(out, "result       {}", e);
+        }
+    }
+    let stats = &decoder.stats;
+    let _ = writeln!(
+        out,
+        "stats        {} symbols, {} RS blocks decoded, {} failed, {} bytes corrected",
+        stats.symbols_demodulated, stats.rs_blocks_decoded, stats.rs_blocks_failed, stats.corrected_bytes
+    );
+    out
+}
@@ -0,0 +1,217 @@
+//! `measure-latency`: round-trip acoustic latency through the default sound card
+//!
+//! Plays the preamble chirp on the default output while recording the default
+//! input, and finds the chirp in the recording with `detect_preamble`. The
+//! delay covers both driver buffers, the speaker and the air path, which is
+//! what ARQ timeouts and echo hold-offs have to allow for. The playback start
+//! is stamped with the input position of the same moment, so the result is
+//! accurate to about one audio buffer.
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use transmitwave_core::sync::generate_preamble;
+use transmitwave_core::{detect_preamble, resample_audio, DetectionThreshold, PREAMBLE_SAMPLES, SAMPLE_RATE};
+
+/// Room noise recorded before each chirp, for the SNR estimate
+const NOISE_LEAD_MS: usize = 300;
+
+/// Longest round trip looked for after the playback start
+const MAX_LATENCY_MS: usize = 1000;
+
+/// One chirp's round trip
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyMeasurement {
+    pub latency_ms: f32,
+    /// RMS of the received chirp in dB relative to full scale
+    pub level_dbfs: f32,
+    /// Received chirp over the room noise before it
+    pub snr_db: f32,
+}
+
+/// Find the chirp in `recording` (modem sample rate) that started playing at
+/// sample `playback_start`
+pub fn analyze_round_trip(recording: &[f32], playback_start: usize) -> Option<LatencyMeasurement> {
+    let search = recording.get(playback_start..)?;
+    let offset = detect_preamble(search, DetectionThreshold::Adaptive)?;
+    let chirp_start = playback_start + offset;
+    let chirp = recording.get(chirp_start..chirp_start + PREAMBLE_SAMPLES)?;
+
+    let level = rms(chirp);
+    let noise = rms(&recording[..playback_start]);
+    Some(LatencyMeasurement {
+        latency_ms: offset as f32 * 1000.0 / SAMPLE_RATE as f32,
+        level_dbfs: 20.0 * level.max(1e-9).log10(),
+        snr_db: 20.0 * (level.max(1e-9) / noise.max(1e-9)).log10(),
+    })
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// Speaker queue; `mark` is the input position at which the queued chirp began playing
+#[derive(Default)]
+struct Playout {
+    queue: VecDeque<f32>,
+    mark_next: bool,
+    mark: Option<usize>,
+}
+
+pub fn measure_latency_command(repeats: u32, volume: f32) -> Result<(), Box<dyn std::error::Error>> {
+    let host = cpal::default_host();
+    let input_device = host.default_input_device().ok_or("No input device")?;
+    let output_device = host.default_output_device().ok_or("No output device")?;
+    let input_config = input_device.default_input_config()?;
+    let output_config = output_device.default_output_config()?;
+    if input_config.sample_format() != cpal::SampleFormat::F32
+        || output_config.sample_format() != cpal::SampleFormat::F32
+    {
+        return Err("Sound card does not offer f32 samples".into());
+    }
+    let input_rate = input_config.sample_rate().0 as usize;
+    let output_rate = output_config.sample_rate().0 as usize;
+    let input_channels = input_config.channels() as usize;
+    let output_channels = output_config.channels() as usize;
+
+    // Microphone: downmix to mono, count frames, hand chunks to the main loop
+    let input_frames = Arc::new(AtomicUsize::new(0));
+    let input_counter = Arc::clone(&input_frames);
+    let (mic_tx, mic_rx) = mpsc::channel::<Vec<f32>>();
+    let input_stream = input_device.build_input_stream(
+        &input_config.into(),
+        move |data: &[f32], _: &cpal::InputCallbackInfo| {
+            let mono: Vec<f32> = data
+                .chunks_exact(input_channels)
+                .map(|frame| frame.iter().sum::<f32>() / input_channels as f32)
+                .collect();
+            input_counter.fetch_add(mono.len(), Ordering::SeqCst);
+            let _ = mic_tx.send(mono);
+        },
+        |e| eprintln!("Input stream error: {}", e),
+        None,
+    )?;
+
+    // Speaker: stamp the input position when a queued chirp starts
+    let playout = Arc::new(Mutex::new(Playout::default()));
+    let speaker = Arc::clone(&playout);
+    let output_counter = Arc::clone(&input_frames);
+    let output_stream = output_device.build_output_stream(
+        &output_config.into(),
+        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+            let mut playout = speaker.lock().unwrap();
+            if playout.mark_next && !playout.queue.is_empty() {
+                playout.mark = Some(output_counter.load(Ordering::SeqCst));
+                playout.mark_next = false;
+            }
+            for frame in data.chunks_mut(output_channels) {
+                frame.fill(playout.queue.pop_front().unwrap_or(0.0));
+            }
+        },
+        |e| eprintln!("Output stream error: {}", e),
+        None,
+    )?;
+    input_stream.play()?;
+    output_stream.play()?;
+
+    let chirp: Vec<f32> = generate_preamble(PREAMBLE_SAMPLES, 0.5).iter().map(|s| s * volume).collect();
+    let chirp = resample_audio(&chirp, SAMPLE_RATE, output_rate);
+    let lead_frames = NOISE_LEAD_MS * input_rate / 1000;
+    let tail_frames = (MAX_LATENCY_MS * SAMPLE_RATE / 1000 + PREAMBLE_SAMPLES) * input_rate / SAMPLE_RATE;
+
+    println!("Measuring round-trip latency ({} Hz in, {} Hz out)", input_rate, output_rate);
+    let mut received = 0usize;
+    let mut latencies = Vec::new();
+    for attempt in 1..=repeats {
+        let recording_start = received;
+        let mut recording = Vec::new();
+        let mut queued = false;
+        loop {
+            let chunk = mic_rx.recv_timeout(Duration::from_secs(2)).map_err(|_| "Microphone stream stopped")?;
+            received += chunk.len();
+            recording.extend(chunk);
+
+            let mut state = playout.lock().unwrap();
+            if !queued && recording.len() >= lead_frames {
+                state.queue.extend(chirp.iter().copied());
+                state.mark_next = true;
+                state.mark = None;
+                queued = true;
+            }
+            if state.mark.is_some_and(|mark| received >= mark + tail_frames) {
+                break;
+            }
+            if state.mark.is_none() && recording.len() > 2 * (lead_frames + tail_frames) {
+                return Err("Speaker output did not start".into());
+            }
+        }
+
+        let mark = playout.lock().unwrap().mark.unwrap_or(recording_start) - recording_start;
+        let recording = resample_audio(&recording, input_rate, SAMPLE_RATE);
+        match analyze_round_trip(&recording, mark * SAMPLE_RATE / input_rate) {
+            Some(m) => {
+                println!(
+                    "  #{}: {:.1} ms, level {:.1} dBFS, SNR {:.1} dB",
+                    attempt, m.latency_ms, m.level_dbfs, m.snr_db
+                );
+                latencies.push(m.latency_ms);
+            }
+            None => println!("  #{}: chirp not detected (raise --volume or move the microphone closer)", attempt),
+        }
+    }
+
+    if latencies.is_empty() {
+        return Err("No chirp was detected in any attempt".into());
+    }
+    latencies.sort_by(f32::total_cmp);
+    let max = latencies[latencies.len() - 1];
+    println!(
+        "Round-trip latency: median {:.1} ms (min {:.1}, max {:.1}) over {} of {} attempts",
+        latencies[latencies.len() / 2],
+        latencies[0],
+        max,
+        latencies.len(),
+        repeats
+    );
+    println!(
+        "Our own audio returns for ~{:.0} ms after playback ends; keep the link turnaround above that",
+        max
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transmitwave_core::SplitMix64;
+
+    #[test]
+    fn test_analyze_round_trip() {
+        // Room noise, then the chirp arriving 120 ms after playback started, attenuated
+        let playback_start = NOISE_LEAD_MS * SAMPLE_RATE / 1000;
+        let delay = 120 * SAMPLE_RATE / 1000;
+        let mut rng = SplitMix64::new(5);
+        let mut recording: Vec<f32> =
+            (0..playback_start + delay + PREAMBLE_SAMPLES + SAMPLE_RATE).map(|_| 0.002 * (rng.next_f32() - 0.5)).collect();
+        let chirp = generate_preamble(PREAMBLE_SAMPLES, 0.5);
+        for (sample, value) in recording[playback_start + delay..].iter_mut().zip(&chirp) {
+            *sample += 0.2 * value;
+        }
+
+        let measurement = analyze_round_trip(&recording, playback_start).unwrap();
+        assert!((measurement.latency_ms - 120.0).abs() < 2.0, "{:?}", measurement);
+        let expected_dbfs = 20.0 * (0.2 * rms(&chirp)).log10();
+        assert!((measurement.level_dbfs - expected_dbfs).abs() < 1.0, "{:?}", measurement);
+        assert!(measurement.snr_db > 30.0, "{:?}", measurement);
+
+        // Nothing played, or a playback start past the end of the recording
+        assert_eq!(analyze_round_trip(&recording[..playback_start + delay], playback_start), None);
+        assert_eq!(analyze_round_trip(&recording, recording.len() + 1), None);
+    }
+}
@@ -1,16 +1,15 @@
-use axum::{
-    http::StatusCode,
-    routing::{get, post},
-    Json, Router,
-};
-use clap::{Parser, Subcommand};
-use hound::WavSpec;
-use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::path::PathBuf;
-use transmitwave_core::{DecoderFsk, EncoderFsk, FountainConfig, resample_audio, stereo_to_mono, SAMPLE_RATE, DetectionThreshold, FOUNTAIN_BLOCK_SIZE};
-use tower_http::cors::CorsLayer;
-use base64::Engine;
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use transmitwave_core::{DecodeQuality, DecodedMessage, DecoderFsk, EncoderVersion, PROTOCOL_REVISION, FramingVariant, DegreeDistribution, EncoderFsk, EncoderPadding, FecMode, FrameProfile, ContentType, Millis, NetworkKey, FountainCode, FountainConfig, TransferEncoder, TransferSession, DEFAULT_PART_DATA_SIZE, StereoRedundancy, SYNC_SILENCE_MS, SAMPLE_RATE, DetectionThreshold, FOUNTAIN_BLOCK_SIZE, TrimOptions, find_transmission, ToneLayout, MAX_SYMBOL_GAP_INTERVAL, MAX_SYNC_GAP_SAMPLES, MIN_SYMBOL_GAP_INTERVAL, SyncGaps, Squelch, PreEmphasis, calibrate_from_sweep, calibration_sweep, NUM_FSK_TONES};
+use transmitwave_core::audio_io::{self, WavSampleFormat};
+use transmitwave_core::fsk::{bin_to_freq, freq_to_bin, FSK_NUM_BINS};
+
+mod batch;
+mod inspect;
+#[cfg(feature = "audio")]
+mod latency;
+mod server;
+mod spectrogram;
 
 // ============================================================================
 // ENCODER/DECODER CONFIGURATION
@@ -18,32 +17,6 @@ use base64::Engine;
 // This is the only supported mode for over-the-air audio transfer
 // ============================================================================
 
-#[derive(Serialize, Deserialize)]
-struct EncodeRequest {
-    data: String, // base64-encoded input data
-}
-
-#[derive(Serialize, Deserialize)]
-struct EncodeResponse {
-    success: bool,
-    message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    wav_base64: Option<String>,
-}
-
-#[derive(Serialize, Deserialize)]
-struct DecodeRequest {
-    wav_base64: String, // base64-encoded WAV file
-}
-
-#[derive(Serialize, Deserialize)]
-struct DecodeResponse {
-    success: bool,
-    message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<String>,
-}
-
 #[derive(Parser)]
 #[command(name = "transmitwave")]
 #[command(about = "Audio modem using multi-tone FSK for reliable over-the-air communication")]
@@ -73,66 +46,209 @@ struct Cli {
     port: u16,
 }
 
-#[derive(Subcommand)]
-enum Commands {
-    /// Encode binary data to WAV audio file using Reed-Solomon FEC (recommended)
-    /// Uses multi-tone FSK modulation with Reed-Solomon error correction for reliable transmission.
-    Encode {
-        /// Input binary file
-        #[arg(value_name = "INPUT.BIN")]
-        input: PathBuf,
+/// Options of `decode`
+#[derive(Args, Default)]
+struct DecodeArgs {
+    /// Input WAV file
+    #[arg(value_name = "INPUT.WAV")]
+    input: PathBuf,
 
-        /// Output WAV file
-        #[arg(value_name = "OUTPUT.WAV")]
-        output: PathBuf,
+    /// Output binary file
+    #[arg(value_name = "OUTPUT.BIN")]
+    output: PathBuf,
 
-    },
+    /// Decode without preamble/postamble detection (for trimmed audio)
+    #[arg(long)]
+    no_sync: bool,
 
-    /// Decode WAV file to binary data using Reed-Solomon FEC (recommended)
-    /// Uses multi-tone FSK demodulation with Reed-Solomon error correction.
-    Decode {
-        /// Input WAV file
-        #[arg(value_name = "INPUT.WAV")]
-        input: PathBuf,
+    /// Use adaptive threshold for both preamble and postamble
+    #[arg(long)]
+    adaptive: bool,
 
-        /// Output binary file
-        #[arg(value_name = "OUTPUT.BIN")]
-        output: PathBuf,
+    /// Fixed detection threshold for both preamble and postamble (0.001-1.0)
+    #[arg(short, long)]
+    threshold: Option<f32>,
 
-        /// Decode without preamble/postamble detection (for trimmed audio)
-        #[arg(long)]
-        no_sync: bool,
+    /// Use adaptive threshold for preamble only
+    #[arg(long)]
+    preamble_adaptive: bool,
 
-        /// Use adaptive threshold for both preamble and postamble
-        #[arg(long)]
-        adaptive: bool,
+    /// Fixed detection threshold for preamble only (overrides --threshold for preamble)
+    #[arg(long)]
+    preamble_threshold: Option<f32>,
 
-        /// Fixed detection threshold for both preamble and postamble (0.001-1.0)
-        #[arg(short, long)]
-        threshold: Option<f32>,
+    /// Use adaptive threshold for postamble only
+    #[arg(long)]
+    postamble_adaptive: bool,
 
-        /// Use adaptive threshold for preamble only
-        #[arg(long)]
-        preamble_adaptive: bool,
+    /// Fixed detection threshold for postamble only (overrides --threshold for postamble)
+    #[arg(long)]
+    postamble_threshold: Option<f32>,
 
-        /// Fixed detection threshold for preamble only (overrides --threshold for preamble)
-        #[arg(long)]
-        preamble_threshold: Option<f32>,
+    /// Input is stereo with the right channel delayed by this many milliseconds
+    #[arg(long, conflicts_with_all = ["stereo_offset_hz", "no_sync"])]
+    stereo_delay_ms: Option<u32>,
 
-        /// Use adaptive threshold for postamble only
-        #[arg(long)]
-        postamble_adaptive: bool,
+    /// Input is stereo with the right channel shifted up by this many Hz
+    #[arg(long, conflicts_with = "no_sync")]
+    stereo_offset_hz: Option<f32>,
 
-        /// Fixed detection threshold for postamble only (overrides --threshold for postamble)
-        #[arg(long)]
-        postamble_threshold: Option<f32>,
-    },
+    /// Only detect frames sent with this network key
+    #[arg(long, value_name = "KEY")]
+    network_key: Option<String>,
+
+    /// Reject frames whose postamble does not match the network key too
+    #[arg(long, requires = "network_key", conflicts_with = "no_sync")]
+    strict_network_key: bool,
+
+    /// Also accept frames without a length prefix (older firmware), ended by the postamble
+    #[arg(long, conflicts_with = "no_sync")]
+    legacy_frames: bool,
+
+    /// Also recover recordings played up to 5% too fast or slow (e.g. voice notes at 1.05x)
+    #[arg(long, conflicts_with = "no_sync")]
+    speed_search: bool,
+
+    /// Skip sync detection: the FSK data starts at this sample of the 16 kHz mono audio
+    #[arg(long, value_name = "N", conflicts_with_all = ["no_sync", "stereo_delay_ms", "stereo_offset_hz", "speed_search"])]
+    start_sample: Option<usize>,
+
+    /// With --start-sample, reject payloads of any other length
+    #[arg(long, value_name = "BYTES", requires = "start_sample")]
+    expected_len: Option<usize>,
+
+    /// Comma-separated frequencies of constant interference (e.g. 1500); their tone bins are ignored and recovered by the FEC
+    #[arg(long, value_name = "HZ", value_delimiter = ',')]
+    interference_hz: Vec<f32>,
+
+    /// Write the tone decisions of every symbol of the last frame tried to FILE (CSV if it ends in .csv, JSON otherwise), even if decoding fails
+    #[arg(long, value_name = "FILE")]
+    dump_symbols: Option<PathBuf>,
+
+    /// Squelch weak candidates and heavily corrected frames to keep false decodes per preamble candidate below RATE (e.g. 1e-9)
+    #[arg(long, value_name = "RATE", value_parser = parse_false_positive_rate)]
+    false_positive_rate: Option<Squelch>,
+}
+
+/// Options of `encode`
+#[derive(Args)]
+struct EncodeArgs {
+    /// Input binary file
+    #[arg(value_name = "INPUT.BIN")]
+    input: PathBuf,
+
+    /// Output WAV file
+    #[arg(value_name = "OUTPUT.WAV")]
+    output: PathBuf,
+
+    /// Silence before the preamble in milliseconds (raise for speakers that clip the start)
+    #[arg(long, default_value_t = SYNC_SILENCE_MS as u32)]
+    leading_silence_ms: u32,
+
+    /// Silence after the postamble in milliseconds
+    #[arg(long, default_value_t = SYNC_SILENCE_MS as u32)]
+    trailing_silence_ms: u32,
+
+    /// Wake-up fade-in ramp before the preamble in milliseconds (0 = disabled)
+    #[arg(long, default_value = "0")]
+    fade_in_ms: u32,
+
+    /// Write stereo with the right channel delayed by this many milliseconds
+    #[arg(long, conflicts_with = "stereo_offset_hz")]
+    stereo_delay_ms: Option<u32>,
+
+    /// Write stereo with the right channel shifted up by this many Hz
+    #[arg(long)]
+    stereo_offset_hz: Option<f32>,
+
+    /// Reed-Solomon code as N,K (e.g. 255,191 for noisy channels); default picks by size
+    #[arg(long, value_name = "N,K", value_parser = parse_rs_code)]
+    rs: Option<FecMode>,
+
+    /// Derive the sync signals from this key; only decoders with the same key see the frame
+    #[arg(long, value_name = "KEY")]
+    network_key: Option<String>,
+
+    /// Low-latency short frame (payloads up to 64 bytes, ~0.7 s for 16 bytes)
+    #[arg(long, conflicts_with_all = ["rs", "leading_silence_ms", "trailing_silence_ms", "fade_in_ms"])]
+    short: bool,
+
+    /// Slow, heavily protected frame that survives a phone call (300-3400 Hz, speech codecs)
+    #[arg(long, conflicts_with_all = ["rs", "short"])]
+    telephony: bool,
+
+    /// Widely spaced tones that survive Bluetooth speakers and VoIP codecs (AAC, Opus)
+    #[arg(long, conflicts_with_all = ["rs", "short", "telephony"])]
+    codec_robust: bool,
+
+    /// Narrow 800-1740 Hz frame for radio and intercom links sampled at 8 kHz (~70 bps)
+    #[arg(long, conflicts_with_all = ["rs", "short", "telephony", "codec_robust"])]
+    narrowband: bool,
+
+    /// Carry an extra bit per tone in its amplitude (~25% faster, needs a clean channel)
+    #[arg(long, conflicts_with_all = ["short", "telephony", "codec_robust", "narrowband"])]
+    amplitude_bits: bool,
+
+    /// Tones per symbol and bins per tone band as TONES,BINS (e.g. 2,16 for narrowband channels)
+    #[arg(long, value_name = "TONES,BINS", value_parser = parse_tone_layout, conflicts_with_all = ["short", "telephony", "codec_robust", "narrowband", "amplitude_bits"])]
+    tone_layout: Option<ToneLayout>,
+
+    /// Silence gap after every N data symbols, so phone AGCs don't pump (4-63)
+    #[arg(long, value_name = "N", value_parser = parse_symbol_gaps, conflicts_with = "short")]
+    symbol_gaps: Option<u8>,
+
+    /// Silence between sync signals and data in ms, fixed (0 = none) or MIN-MAX for random gaps per frame
+    #[arg(long, value_name = "MS|MIN-MAX", value_parser = parse_sync_gaps, conflicts_with_all = ["short", "telephony", "codec_robust", "narrowband"])]
+    sync_gaps: Option<SyncGaps>,
+
+    /// Announce the payload type to receivers: a MIME type or text, url, json, binary, cbor, vcard
+    #[arg(long, value_name = "TYPE", conflicts_with = "short")]
+    content_type: Option<ContentType>,
+
+    /// Send every tone in both halves of the band, for uneven speaker response (half speed)
+    #[arg(long, conflicts_with_all = ["short", "telephony", "codec_robust", "narrowband", "amplitude_bits"])]
+    dual_band: bool,
+
+    /// Send each tone as the step from the previous symbol's tone, for channels whose response drifts
+    #[arg(long, conflicts_with_all = ["short", "telephony", "codec_robust", "narrowband", "amplitude_bits", "tone_layout", "dual_band"])]
+    differential: bool,
+
+    /// Pre-emphasis profile written by `calibrate`, boosting the tones this speaker/room weakens
+    #[arg(long, value_name = "PROFILE")]
+    pre_emphasis: Option<PathBuf>,
+
+    /// Announce this encoder's protocol revision and version in the frame header
+    #[arg(long, conflicts_with = "short")]
+    version_beacon: bool,
+
+    /// Also write the settings, band plan, duration and payload CRC-32 as JSON to this file
+    #[arg(long, value_name = "SIDECAR.JSON")]
+    sidecar: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Encode binary data to WAV audio file using Reed-Solomon FEC (recommended)
+    /// Uses multi-tone FSK modulation with Reed-Solomon error correction for reliable transmission.
+    Encode(EncodeArgs),
+
+    /// Decode WAV file to binary data using Reed-Solomon FEC (recommended)
+    /// Uses multi-tone FSK demodulation with Reed-Solomon error correction.
+    Decode(DecodeArgs),
 
     /// Start web server for encode/decode operations
     Server {
         /// Port to listen on (default: 8000)
         #[arg(short, long, default_value = "8000")]
         port: u16,
+
+        /// Largest request body in bytes
+        #[arg(long, default_value_t = server::ServerLimits::default().max_body_bytes)]
+        max_body_bytes: usize,
+
+        /// Requests per minute allowed from one IP (0 = unlimited)
+        #[arg(long, default_value_t = server::ServerLimits::default().requests_per_minute)]
+        rate_limit: u32,
     },
 
     /// Encode binary data to WAV using fountain mode (continuous streaming) [EXPERIMENTAL]
@@ -158,6 +274,10 @@ enum Commands {
         /// Repair blocks ratio (default: 0.5)
         #[arg(short, long, default_value = "0.5")]
         repair_ratio: f32,
+
+        /// Use the LT fountain code instead of RaptorQ (decoders detect it)
+        #[arg(long)]
+        lt: bool,
     },
 
     /// Decode WAV file using fountain mode [EXPERIMENTAL]
@@ -204,6 +324,175 @@ enum Commands {
         #[arg(long)]
         postamble_threshold: Option<f32>,
     },
+
+    /// Split a large file into numbered part WAVs that can be sent over several sessions
+    /// Replay only the parts a receiver is missing with --parts or --resume.
+    TransferEncode {
+        /// Input binary file
+        #[arg(value_name = "INPUT.BIN")]
+        input: PathBuf,
+
+        /// Directory for the part WAV files (part_00000.wav, ...)
+        #[arg(value_name = "OUTPUT_DIR")]
+        output_dir: PathBuf,
+
+        /// File bytes carried per part
+        #[arg(long, default_value_t = DEFAULT_PART_DATA_SIZE)]
+        part_size: usize,
+
+        /// Transfer ID (default: derived from the file contents)
+        #[arg(long)]
+        transfer_id: Option<u32>,
+
+        /// Comma-separated part indices to encode (default: all)
+        #[arg(long, value_delimiter = ',', conflicts_with = "resume")]
+        parts: Option<Vec<u16>>,
+
+        /// Resume token file written by transfer-decode; encodes only the missing parts
+        #[arg(long, value_name = "TOKEN")]
+        resume: Option<PathBuf>,
+    },
+
+    /// Decode part WAVs into a persistent transfer session and report missing parts
+    TransferDecode {
+        /// Session state file (created if it does not exist)
+        #[arg(value_name = "STATE")]
+        state: PathBuf,
+
+        /// Part WAV files recorded in this session
+        #[arg(value_name = "INPUT.WAV")]
+        inputs: Vec<PathBuf>,
+
+        /// Write the reassembled file here once all parts have arrived
+        #[arg(short, long, value_name = "OUTPUT.BIN")]
+        output: Option<PathBuf>,
+
+        /// Write a resume token for the sender's --resume option
+        #[arg(long, value_name = "TOKEN")]
+        token: Option<PathBuf>,
+    },
+
+    /// Render a spectrogram of the FSK band with detected sync and symbol boundaries
+    /// Writes a PNG when --output is given, otherwise prints an ASCII heat map.
+    Spectrogram {
+        /// Input WAV file
+        #[arg(value_name = "INPUT.WAV")]
+        input: PathBuf,
+
+        /// Output PNG file
+        #[arg(short, long, value_name = "OUTPUT.PNG")]
+        output: Option<PathBuf>,
+
+        /// Width of the ASCII heat map in characters
+        #[arg(short, long, default_value = "100")]
+        width: usize,
+    },
+
+    /// Decode the first frame of a recording and print its structure layer by layer:
+    /// sync positions, length prefix, RS blocks with corrections, header fields, CRCs and payload
+    InspectFrame {
+        /// Input WAV file
+        #[arg(value_name = "INPUT.WAV")]
+        input: PathBuf,
+
+        /// Only detect frames sent with this network key
+        #[arg(long, value_name = "KEY")]
+        network_key: Option<String>,
+    },
+
+    /// Encode every file in a directory (one payload each) in parallel and write a summary report
+    EncodeBatch {
+        /// Directory with payload files
+        #[arg(value_name = "INPUT_DIR")]
+        input_dir: PathBuf,
+
+        /// Directory for the frames (<name>.wav) and report.json
+        #[arg(value_name = "OUTPUT_DIR")]
+        output_dir: PathBuf,
+
+        /// Worker threads (0 = one per CPU core)
+        #[arg(short, long, default_value = "0")]
+        jobs: usize,
+
+        /// Reed-Solomon code as N,K for every frame; default picks by size
+        #[arg(long, value_name = "N,K", value_parser = parse_rs_code)]
+        rs: Option<FecMode>,
+
+        /// Derive the sync signals from this key; only decoders with the same key see the frames
+        #[arg(long, value_name = "KEY")]
+        network_key: Option<String>,
+    },
+
+    /// Decode every WAV in a directory in parallel and write a summary report
+    DecodeBatch {
+        /// Directory with WAV recordings
+        #[arg(value_name = "INPUT_DIR")]
+        input_dir: PathBuf,
+
+        /// Directory for decoded payloads (<name>.bin) and report.json
+        #[arg(value_name = "OUTPUT_DIR")]
+        output_dir: PathBuf,
+
+        /// Worker threads (0 = one per CPU core)
+        #[arg(short, long, default_value = "0")]
+        jobs: usize,
+
+        /// Only detect frames sent with this network key
+        #[arg(long, value_name = "KEY")]
+        network_key: Option<String>,
+    },
+
+    /// Cut the first transmission out of a long recording
+    Trim {
+        /// Input WAV file
+        #[arg(value_name = "INPUT.WAV")]
+        input: PathBuf,
+
+        /// Output WAV file (mono, modem sample rate)
+        #[arg(value_name = "OUTPUT.WAV")]
+        output: PathBuf,
+
+        /// Audio kept before the preamble in milliseconds
+        #[arg(long, default_value_t = SYNC_SILENCE_MS as u32)]
+        margin_before_ms: u32,
+
+        /// Audio kept after the postamble in milliseconds
+        #[arg(long, default_value_t = SYNC_SILENCE_MS as u32)]
+        margin_after_ms: u32,
+
+        /// Only look for frames sent with this network key
+        #[arg(long, value_name = "KEY")]
+        network_key: Option<String>,
+    },
+
+    /// Measure a speaker/room from a recording of the calibration sweep and
+    /// write a pre-emphasis profile for `encode --pre-emphasis`
+    Calibrate {
+        /// Recording of the sweep (see --write-sweep)
+        #[arg(value_name = "RECORDING.WAV", required_unless_present = "write_sweep")]
+        recording: Option<PathBuf>,
+
+        /// Pre-emphasis profile to write
+        #[arg(value_name = "PROFILE", required_unless_present = "write_sweep")]
+        profile: Option<PathBuf>,
+
+        /// Write the calibration sweep to play to this WAV file instead
+        #[arg(long, value_name = "SWEEP.WAV", conflicts_with_all = ["recording", "profile"])]
+        write_sweep: Option<PathBuf>,
+    },
+
+    /// Play a chirp through the default output, record the default input, and
+    /// report the round-trip acoustic latency and level (needs the `audio` feature)
+    #[cfg(feature = "audio")]
+    MeasureLatency {
+        /// Number of chirps to measure
+        #[arg(long, default_value = "3")]
+        repeats: u32,
+
+        /// Output volume (0.0-1.0)
+        #[arg(long, default_value = "0.8")]
+        volume: f32,
+    },
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -211,27 +500,69 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Check if web server should be started
     if cli.server {
-        return start_web_server(cli.port);
+        return server::start_web_server(cli.port, server::ServerLimits::default());
     }
 
     // Handle subcommands
     if let Some(command) = cli.command {
         match command {
-            Commands::Encode { input, output } => {
-                encode_fsk_command(&input, &output)?
-            }
-            Commands::Decode { input, output, no_sync, adaptive, threshold, preamble_adaptive, preamble_threshold, postamble_adaptive, postamble_threshold } => {
-                decode_fsk_command(&input, &output, no_sync, adaptive, threshold, preamble_adaptive, preamble_threshold, postamble_adaptive, postamble_threshold)?
+            Commands::Encode(args) => {
+                let stereo = stereo_redundancy(args.stereo_delay_ms, args.stereo_offset_hz);
+                encode_fsk_command(&args.input, &args.output, encoder_from_args(&args)?, stereo, args.sidecar.as_ref())?
             }
-            Commands::Server { port } => {
-                return start_web_server(port);
+            Commands::Decode(args) => decode_fsk_command(&args)?,
+            Commands::Server { port, max_body_bytes, rate_limit } => {
+                let limits = server::ServerLimits { max_body_bytes, requests_per_minute: rate_limit };
+                return server::start_web_server(port, limits);
             }
-            Commands::FountainEncode { input, output, timeout, block_size, repair_ratio } => {
-                fountain_encode_command(&input, &output, timeout, block_size, repair_ratio)?
+            Commands::FountainEncode { input, output, timeout, block_size, repair_ratio, lt } => {
+                let code = if lt { FountainCode::Lt(DegreeDistribution::default()) } else { FountainCode::RaptorQ };
+                fountain_encode_command(&input, &output, timeout, block_size, repair_ratio, code)?
             }
             Commands::FountainDecode { input, output, timeout, block_size, adaptive, threshold, preamble_adaptive, preamble_threshold, postamble_adaptive, postamble_threshold } => {
                 fountain_decode_command(&input, &output, timeout, block_size, adaptive, threshold, preamble_adaptive, preamble_threshold, postamble_adaptive, postamble_threshold)?
             }
+            Commands::TransferEncode { input, output_dir, part_size, transfer_id, parts, resume } => {
+                transfer_encode_command(&input, &output_dir, part_size, transfer_id, parts, resume.as_ref())?
+            }
+            Commands::TransferDecode { state, inputs, output, token } => {
+                transfer_decode_command(&state, &inputs, output.as_ref(), token.as_ref())?
+            }
+            Commands::Spectrogram { input, output, width } => {
+                spectrogram_command(&input, output.as_ref(), width)?
+            }
+            Commands::InspectFrame { input, network_key } => {
+                let key = network_key.map(|key| NetworkKey::new(key.as_bytes()));
+                let samples = read_wav_verbose(&input)?;
+                print!("{}", inspect::inspect_frame(&samples, key));
+            }
+            Commands::EncodeBatch { input_dir, output_dir, jobs, rs, network_key } => {
+                let mut encoder = EncoderFsk::new()?;
+                encoder.set_fec_mode(rs);
+                encoder.set_network_key(network_key.map(|key| NetworkKey::new(key.as_bytes())));
+                encode_batch_command(&input_dir, &output_dir, jobs, &encoder)?
+            }
+            Commands::DecodeBatch { input_dir, output_dir, jobs, network_key } => {
+                let key = network_key.map(|key| NetworkKey::new(key.as_bytes()));
+                decode_batch_command(&input_dir, &output_dir, jobs, key)?
+            }
+            Commands::Trim { input, output, margin_before_ms, margin_after_ms, network_key } => {
+                let mut options = TrimOptions::default().with_margins_ms(margin_before_ms, margin_after_ms);
+                options.network_key = network_key.map(|key| NetworkKey::new(key.as_bytes()));
+                trim_command(&input, &output, &options)?
+            }
+            Commands::Calibrate { recording, profile, write_sweep } => match (write_sweep, recording, profile) {
+                (Some(sweep), _, _) => {
+                    audio_io::write_wav_file(&sweep, &calibration_sweep(), WavSampleFormat::Int16)?;
+                    println!("Wrote calibration sweep to {}; play it and record it where the receiver will be", sweep.display());
+                }
+                (None, Some(recording), Some(profile)) => calibrate_command(&recording, &profile)?,
+                _ => unreachable!("clap requires the recording and the profile"),
+            },
+            #[cfg(feature = "audio")]
+            Commands::MeasureLatency { repeats, volume } => {
+                latency::measure_latency_command(repeats, volume)?
+            }
         }
         return Ok(());
     }
@@ -251,9 +582,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
 
         if mode == "encode" || mode == "enc" {
-            encode_fsk_command(&input, &output)?
+            encode_fsk_command(&input, &output, EncoderFsk::new()?, None, None)?
         } else if mode == "decode" || mode == "dec" {
-            decode_fsk_command(&input, &output, false, false, None, false, None, false, None)?
+            decode_fsk_command(&DecodeArgs { input, output, ..Default::default() })?
         } else {
             eprintln!("Error: Unknown mode '{}'. Use 'encode' or 'decode'", mode);
             std::process::exit(1);
@@ -266,44 +597,134 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Encoder configured from the `encode` options
+fn encoder_from_args(args: &EncodeArgs) -> Result<EncoderFsk, Box<dyn std::error::Error>> {
+    let mut encoder = EncoderFsk::new()?;
+    if args.short {
+        encoder.set_profile(FrameProfile::Short);
+    } else {
+        if args.telephony {
+            encoder.set_profile(FrameProfile::Telephony);
+        } else if args.codec_robust {
+            encoder.set_profile(FrameProfile::CodecRobust);
+        } else if args.narrowband {
+            encoder.set_profile(FrameProfile::Narrowband);
+        }
+        encoder.set_padding(EncoderPadding::from_ms(Millis(args.leading_silence_ms), Millis(args.trailing_silence_ms), Millis(args.fade_in_ms)));
+    }
+    encoder.set_amplitude_bits(args.amplitude_bits);
+    encoder.set_tone_layout(args.tone_layout.unwrap_or_default());
+    encoder.set_symbol_gaps(args.symbol_gaps);
+    encoder.set_sync_gaps(args.sync_gaps.unwrap_or_default());
+    encoder.set_content_type(args.content_type);
+    encoder.set_version_beacon(args.version_beacon);
+    encoder.set_dual_band(args.dual_band);
+    encoder.set_differential(args.differential);
+    if let Some(path) = &args.pre_emphasis {
+        encoder.set_pre_emphasis(Some(PreEmphasis::from_text(&std::fs::read_to_string(path)?)?));
+    }
+    encoder.set_fec_mode(args.rs);
+    encoder.set_network_key(args.network_key.as_ref().map(|key| NetworkKey::new(key.as_bytes())));
+    Ok(encoder)
+}
+
+/// Encode a file with an encoder configured from the command-line options
 fn encode_fsk_command(
     input_path: &PathBuf,
     output_path: &PathBuf,
+    mut encoder: EncoderFsk,
+    stereo: Option<StereoRedundancy>,
+    sidecar_path: Option<&PathBuf>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Read input binary file
     let data = std::fs::read(input_path)?;
     println!("Read {} bytes from {}", data.len(), input_path.display());
 
-    let mut encoder = EncoderFsk::new()?;
+    // Same limits as the server: fails before anything is encoded
+    let airtime = encoder.airtime(data.len(), false)?;
+    println!(
+        "RS({}, {}) x {} blocks, {} symbols, {:.1} s on air",
+        airtime.fec_mode.block_bytes(),
+        airtime.fec_mode.data_bytes(),
+        airtime.rs_blocks,
+        airtime.symbols,
+        airtime.duration_ms() as f32 / 1000.0
+    );
 
+    // Before encoding, which draws the next random sync gaps
+    let sidecar = sidecar_path.map(|_| encoder.sidecar(&data)).transpose()?;
     let samples = encoder.encode(&data)?;
     println!(
         "Encoded with multi-tone FSK to {} audio samples",
         samples.len()
     );
 
-    // Write WAV file (16-bit PCM)
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate: transmitwave_core::SAMPLE_RATE as u32,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
+    // Write WAV file (16-bit PCM), byte for byte what the server returns
+    std::fs::write(output_path, audio_io::encoded_wav_bytes(&samples, 1.0, stereo)?)?;
+    if let Some(redundancy) = stereo {
+        println!("Wrote stereo with redundant right channel ({:?})", redundancy);
+    }
 
-    let file = File::create(output_path)?;
-    let mut writer = hound::WavWriter::new(file, spec)?;
+    println!("Wrote {}", output_path.display());
+    if let (Some(path), Some(sidecar)) = (sidecar_path, sidecar) {
+        std::fs::write(path, serde_json::to_string(&sidecar)? + "\n")?;
+        println!("Wrote sidecar {}", path.display());
+    }
+    Ok(())
+}
 
-    // Convert f32 samples to i16 range [-32768, 32767]
-    for sample in samples {
-        // Clamp to [-1.0, 1.0] range to avoid overflow, then scale to i16
-        let clamped = sample.max(-1.0).min(1.0);
-        let i16_sample = (clamped * 32767.0) as i16;
-        writer.write_sample(i16_sample)?;
+/// Stereo redundancy selected by the --stereo-* options, if any
+fn stereo_redundancy(delay_ms: Option<u32>, offset_hz: Option<f32>) -> Option<StereoRedundancy> {
+    match (delay_ms, offset_hz) {
+        (Some(delay_ms), _) => Some(StereoRedundancy::delayed_ms(delay_ms)),
+        (None, Some(offset_hz)) => Some(StereoRedundancy::FrequencyOffset(offset_hz)),
+        (None, None) => None,
     }
-    writer.finalize()?;
+}
 
-    println!("Wrote {} to {}", output_path.display(), spec.channels);
-    Ok(())
+fn parse_rs_code(value: &str) -> Result<FecMode, String> {
+    value.parse::<FecMode>().map_err(|e| e.to_string())
+}
+
+fn parse_tone_layout(value: &str) -> Result<ToneLayout, String> {
+    value.parse::<ToneLayout>().map_err(|e| e.to_string())
+}
+
+fn parse_symbol_gaps(value: &str) -> Result<u8, String> {
+    let interval: u8 = value.trim().parse().map_err(|_| format!("invalid N: {}", value))?;
+    if !(MIN_SYMBOL_GAP_INTERVAL..=MAX_SYMBOL_GAP_INTERVAL).contains(&(interval as usize)) {
+        return Err(format!("N must be {}-{}", MIN_SYMBOL_GAP_INTERVAL, MAX_SYMBOL_GAP_INTERVAL));
+    }
+    Ok(interval)
+}
+
+fn parse_false_positive_rate(value: &str) -> Result<Squelch, String> {
+    let rate: f64 = value.trim().parse().map_err(|_| format!("invalid rate: {}", value))?;
+    Squelch::for_false_positive_rate(rate).map_err(|e| e.to_string())
+}
+
+fn parse_sync_gaps(value: &str) -> Result<SyncGaps, String> {
+    let to_samples = |ms: &str| -> Result<usize, String> {
+        let ms: usize = ms.trim().parse().map_err(|_| format!("invalid gap: {}", ms))?;
+        let samples = ms * SAMPLE_RATE / 1000;
+        if samples > MAX_SYNC_GAP_SAMPLES {
+            return Err(format!("gaps must be at most {} ms", MAX_SYNC_GAP_SAMPLES * 1000 / SAMPLE_RATE));
+        }
+        Ok(samples)
+    };
+    match value.split_once('-') {
+        Some((min, max)) => {
+            let (min, max) = (to_samples(min)?, to_samples(max)?);
+            if min > max {
+                return Err("MIN must not exceed MAX".to_string());
+            }
+            let seed = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+            Ok(SyncGaps::Random { min, max, seed })
+        }
+        None => Ok(SyncGaps::Fixed(to_samples(value)?)),
+    }
 }
 
 fn fountain_encode_command(
@@ -312,6 +733,7 @@ fn fountain_encode_command(
     timeout: u32,
     block_size: usize,
     repair_ratio: f32,
+    code: FountainCode,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Read input binary file
     let data = std::fs::read(input_path)?;
@@ -322,16 +744,21 @@ fn fountain_encode_command(
         timeout_secs: timeout,
         block_size,
         repair_blocks_ratio: repair_ratio,
+        code,
     };
 
-    println!(
-        "Fountain mode: timeout={}s, block_size={}, repair_ratio={}",
-        config.timeout_secs, config.block_size, config.repair_blocks_ratio
-    );
-
     // Create FSK encoder and get fountain stream
     let mut encoder = EncoderFsk::new()?;
-    let stream = encoder.encode_fountain(&data, Some(config))?;
+    let stream = encoder.encode_fountain(&data, Some(config.clone()))?;
+    let code_name = match stream.code() {
+        FountainCode::RaptorQ => "RaptorQ",
+        FountainCode::Lt(_) => "LT",
+    };
+
+    println!(
+        "Fountain mode: code={}, timeout={}s, block_size={}, repair_ratio={}",
+        code_name, config.timeout_secs, config.block_size, config.repair_blocks_ratio
+    );
 
     // Collect all blocks generated within timeout
     println!("Generating fountain blocks (this will take up to {} seconds)...", timeout);
@@ -351,29 +778,53 @@ fn fountain_encode_command(
     println!("Generated {} fountain blocks ({} total samples)", block_count, all_samples.len());
 
     // Write WAV file (16-bit PCM)
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate: transmitwave_core::SAMPLE_RATE as u32,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
-
-    let file = File::create(output_path)?;
-    let mut writer = hound::WavWriter::new(file, spec)?;
-
-    // Convert f32 samples to i16 range [-32768, 32767]
-    for sample in &all_samples {
-        let clamped = sample.max(-1.0).min(1.0);
-        let i16_sample = (clamped * 32767.0) as i16;
-        writer.write_sample(i16_sample)?;
-    }
-    writer.finalize()?;
+    audio_io::write_wav_file(output_path, &all_samples, WavSampleFormat::Int16)?;
 
     println!("Wrote fountain-encoded audio to {}", output_path.display());
     println!("Duration: {:.2}s", all_samples.len() as f32 / SAMPLE_RATE as f32);
     Ok(())
 }
 
+fn print_quality(quality: &DecodeQuality) {
+    println!(
+        "Signal quality: SNR {:.1} dB, symbol margin min {:.2} / mean {:.2}, corrected bytes {}",
+        quality.snr_db, quality.min_symbol_margin, quality.mean_symbol_margin, quality.parity_byte_errors
+    );
+    if let Some(contrast_db) = quality.amplitude_contrast_db {
+        println!("Amplitude bits: pilot contrast {:.1} dB", contrast_db);
+    }
+    if !quality.amplitude_bits_viable() {
+        println!("Channel too noisy for --amplitude-bits");
+    }
+}
+
+fn print_encoder_version(message: &DecodedMessage) {
+    if let Some(version) = message.encoder_version {
+        println!("Encoder: {}", version);
+        if version.protocol != EncoderVersion::CURRENT.protocol {
+            println!("Sent with protocol revision {}; this decoder implements revision {}", version.protocol, PROTOCOL_REVISION);
+        }
+    }
+}
+
+/// Read a WAV file for decoding and report its original format
+fn read_wav_verbose(input_path: &PathBuf) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let (samples, info) = audio_io::read_wav_file(input_path)?;
+    println!(
+        "Read WAV: {} Hz, {} channels, {} bits{}",
+        info.sample_rate,
+        info.channels,
+        info.bits_per_sample,
+        if info.is_float { " float" } else { "" }
+    );
+    if info.channels != 1 || info.sample_rate != SAMPLE_RATE as u32 {
+        println!("Converted to {} mono samples at {} Hz", samples.len(), SAMPLE_RATE);
+    } else {
+        println!("Extracted {} samples", samples.len());
+    }
+    Ok(samples)
+}
+
 fn fountain_decode_command(
     input_path: &PathBuf,
     output_path: &PathBuf,
@@ -386,55 +837,24 @@ fn fountain_decode_command(
     postamble_adaptive: bool,
     postamble_threshold: Option<f32>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Read WAV file
-    let file = File::open(input_path)?;
-    let mut reader = hound::WavReader::new(file)?;
-
-    let spec = reader.spec();
+    // Stream the WAV file (converted to mono at the modem sample rate) so long
+    // recordings don't have to fit in memory
+    let mut reader = audio_io::open_wav_stream(input_path)?;
+    let info = reader.info();
     println!(
-        "Read WAV: {} Hz, {} channels, {} bits",
-        spec.sample_rate, spec.channels, spec.bits_per_sample
+        "Reading WAV: {} Hz, {} channels, {} bits{}",
+        info.sample_rate,
+        info.channels,
+        info.bits_per_sample,
+        if info.is_float { " float" } else { "" }
     );
 
-    // Extract samples (handle both 16-bit and 32-bit float formats)
-    let mut samples = match spec.bits_per_sample {
-        16 => {
-            let int_samples: Result<Vec<i16>, _> = reader.samples::<i16>().collect();
-            int_samples?
-                .into_iter()
-                .map(|s| s as f32 / 32768.0)
-                .collect()
-        }
-        32 => {
-            let float_samples: Result<Vec<f32>, _> = reader.samples::<f32>().collect();
-            float_samples?
-        }
-        _ => {
-            return Err(format!("Unsupported bit depth: {}", spec.bits_per_sample).into());
-        }
-    };
-
-    println!("Extracted {} samples", samples.len());
-
-    // Convert to mono if stereo
-    if spec.channels == 2 {
-        println!("Converting stereo to mono...");
-        samples = stereo_to_mono(&samples);
-        println!("Converted to {} mono samples", samples.len());
-    }
-
-    // Resample to 16kHz if needed
-    if spec.sample_rate != SAMPLE_RATE as u32 {
-        println!("Resampling from {} Hz to {} Hz...", spec.sample_rate, SAMPLE_RATE);
-        samples = resample_audio(&samples, spec.sample_rate as usize, SAMPLE_RATE);
-        println!("Resampled to {} samples", samples.len());
-    }
-
     // Create fountain config
     let config = FountainConfig {
         timeout_secs: timeout,
         block_size,
         repair_blocks_ratio: 0.5, // Not used in decoder
+        ..Default::default()
     };
 
     println!(
@@ -479,8 +899,9 @@ fn fountain_decode_command(
         println!("Using default adaptive postamble detection threshold");
     }
 
-    let data = decoder.decode_fountain(&samples, Some(config))?;
+    let (data, quality) = decoder.decode_fountain_reader(&mut reader, Some(config))?;
     println!("Successfully decoded {} bytes using fountain mode", data.len());
+    print_quality(&quality);
 
     // Display decode statistics
     println!("\nDecode Statistics:");
@@ -494,81 +915,81 @@ fn fountain_decode_command(
     Ok(())
 }
 
-fn decode_fsk_command(
-    input_path: &PathBuf,
-    output_path: &PathBuf,
-    no_sync: bool,
-    adaptive: bool,
-    threshold: Option<f32>,
-    preamble_adaptive: bool,
-    preamble_threshold: Option<f32>,
-    postamble_adaptive: bool,
-    postamble_threshold: Option<f32>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Read WAV file
-    let file = File::open(input_path)?;
-    let mut reader = hound::WavReader::new(file)?;
-
-    let spec = reader.spec();
-    println!(
-        "Read WAV: {} Hz, {} channels, {} bits",
-        spec.sample_rate, spec.channels, spec.bits_per_sample
-    );
-
-    // Extract samples (handle both 16-bit and 32-bit float formats)
-    let mut samples = match spec.bits_per_sample {
-        16 => {
-            // Convert i16 to f32
-            let int_samples: Result<Vec<i16>, _> = reader.samples::<i16>().collect();
-            int_samples?
-                .into_iter()
-                .map(|s| s as f32 / 32768.0)
-                .collect()
-        }
-        32 => {
-            // Already f32
-            let float_samples: Result<Vec<f32>, _> = reader.samples::<f32>().collect();
-            float_samples?
-        }
-        _ => {
-            return Err(format!("Unsupported bit depth: {}", spec.bits_per_sample).into());
-        }
+fn decode_fsk_command(args: &DecodeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let stereo = stereo_redundancy(args.stereo_delay_ms, args.stereo_offset_hz);
+    let unreliable_bins = args
+        .interference_hz
+        .iter()
+        .map(|&hz| freq_to_bin(hz).ok_or_else(|| format!("{} Hz is outside the FSK band", hz)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let framing = if args.legacy_frames { FramingVariant::LegacyFallback } else { FramingVariant::LengthPrefixed };
+
+    // Read WAV file (converted to mono at the modem sample rate)
+    let samples = read_wav_verbose(&args.input)?;
+    let channels = match stereo {
+        Some(_) => audio_io::read_wav_channels_file(&args.input)?.0,
+        None => Vec::new(),
     };
 
-    println!("Extracted {} samples", samples.len());
-
-    // Convert to mono if stereo
-    if spec.channels == 2 {
-        println!("Converting stereo to mono...");
-        samples = stereo_to_mono(&samples);
-        println!("Converted to {} mono samples", samples.len());
+    let mut decoder = DecoderFsk::with_framing(framing)?;
+    decoder.set_network_key(args.network_key.as_ref().map(|key| NetworkKey::new(key.as_bytes())));
+    decoder.set_strict_network_key(args.strict_network_key);
+    decoder.set_speed_search(args.speed_search);
+    if !unreliable_bins.is_empty() {
+        println!("Ignoring tone bins {:?} (known interference)", unreliable_bins);
+        decoder.set_unreliable_bins(&unreliable_bins)?;
     }
-
-    // Resample to 16kHz if needed
-    if spec.sample_rate != SAMPLE_RATE as u32 {
-        println!("Resampling from {} Hz to {} Hz...", spec.sample_rate, SAMPLE_RATE);
-        samples = resample_audio(&samples, spec.sample_rate as usize, SAMPLE_RATE);
-        println!("Resampled to {} samples", samples.len());
+    decoder.set_symbol_audit(args.dump_symbols.is_some());
+    decoder.set_squelch(args.false_positive_rate.unwrap_or_default());
+
+    let result = decode_fsk_samples(&mut decoder, &samples, &channels, args, stereo);
+    if let (Some(path), Some(log)) = (&args.dump_symbols, decoder.symbol_audit()) {
+        let dump = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => log.to_csv(),
+            _ => log.to_json(),
+        };
+        std::fs::write(path, dump)?;
+        println!("Wrote {} symbol decisions to {}", log.symbols.len(), path.display());
     }
+    let data = result?;
+    println!("Decoded {} bytes with multi-tone FSK", data.len());
 
-    let mut decoder = DecoderFsk::new()?;
+    // Write binary file
+    std::fs::write(&args.output, &data)?;
+    println!("Wrote {} to {}", data.len(), args.output.display());
 
+    Ok(())
+}
 
-    let data = if no_sync {
+/// The decoding part of `decode_fsk_command`, so the symbol dump is written even when it fails
+fn decode_fsk_samples(
+    decoder: &mut DecoderFsk,
+    samples: &[f32],
+    channels: &[Vec<f32>],
+    args: &DecodeArgs,
+    stereo: Option<StereoRedundancy>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let data = if args.no_sync {
         println!("Decoding without preamble/postamble detection (trimmed audio mode)");
-        decoder.decode_without_preamble_postamble(&samples)?
+        decoder.decode_without_preamble_postamble(samples)?
+    } else if let Some(start) = args.start_sample {
+        println!("Decoding from sample {} without sync detection", start);
+        let message = decoder.decode_at(samples, start, args.expected_len)?;
+        print_quality(&message.quality);
+        print_encoder_version(&message);
+        message.payload
     } else {
         // Set preamble threshold
-        if preamble_adaptive {
+        if args.preamble_adaptive {
             println!("Using adaptive preamble detection threshold (auto-adjust based on signal)");
             decoder.set_preamble_threshold(DetectionThreshold::Adaptive);
-        } else if let Some(thresh) = preamble_threshold {
+        } else if let Some(thresh) = args.preamble_threshold {
             println!("Using fixed preamble detection threshold: {:.3}", thresh);
             decoder.set_preamble_threshold(DetectionThreshold::Fixed(thresh));
-        } else if adaptive {
+        } else if args.adaptive {
             println!("Using adaptive preamble detection threshold (auto-adjust based on signal)");
             decoder.set_preamble_threshold(DetectionThreshold::Adaptive);
-        } else if let Some(thresh) = threshold {
+        } else if let Some(thresh) = args.threshold {
             println!("Using fixed preamble detection threshold: {:.3}", thresh);
             decoder.set_preamble_threshold(DetectionThreshold::Fixed(thresh));
         } else {
@@ -576,283 +997,264 @@ fn decode_fsk_command(
         }
 
         // Set postamble threshold
-        if postamble_adaptive {
+        if args.postamble_adaptive {
             println!("Using adaptive postamble detection threshold (auto-adjust based on signal)");
             decoder.set_postamble_threshold(DetectionThreshold::Adaptive);
-        } else if let Some(thresh) = postamble_threshold {
+        } else if let Some(thresh) = args.postamble_threshold {
             println!("Using fixed postamble detection threshold: {:.3}", thresh);
             decoder.set_postamble_threshold(DetectionThreshold::Fixed(thresh));
-        } else if adaptive {
+        } else if args.adaptive {
             println!("Using adaptive postamble detection threshold (auto-adjust based on signal)");
             decoder.set_postamble_threshold(DetectionThreshold::Adaptive);
-        } else if let Some(thresh) = threshold {
+        } else if let Some(thresh) = args.threshold {
             println!("Using fixed postamble detection threshold: {:.3}", thresh);
             decoder.set_postamble_threshold(DetectionThreshold::Fixed(thresh));
         } else {
             println!("Using default adaptive postamble detection threshold");
         }
 
-        decoder.decode(&samples)?
+        let message = match (stereo, channels) {
+            (Some(redundancy), [left, right, ..]) => {
+                println!("Combining stereo channels ({:?})", redundancy);
+                decoder.decode_stereo(left, right, redundancy)?
+            }
+            _ => decoder.decode_with_metadata(samples)?,
+        };
+        print_quality(&message.quality);
+        if let Some(content_type) = message.content_type {
+            println!("Content type: {}", content_type);
+        }
+        print_encoder_version(&message);
+        if let Some(permille) = decoder.stats.playback_speed_permille {
+            println!("Recovered at {:.3}x playback speed", permille as f32 / 1000.0);
+        }
+        message.payload
     };
-    println!("Decoded {} bytes with multi-tone FSK", data.len());
+    Ok(data)
+}
 
-    // Write binary file
-    std::fs::write(output_path, &data)?;
-    println!("Wrote {} to {}", data.len(), output_path.display());
+fn transfer_encode_command(
+    input_path: &PathBuf,
+    output_dir: &PathBuf,
+    part_size: usize,
+    transfer_id: Option<u32>,
+    parts: Option<Vec<u16>>,
+    resume: Option<&PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let data = std::fs::read(input_path)?;
+    println!("Read {} bytes from {}", data.len(), input_path.display());
+
+    let transfer_id = transfer_id.unwrap_or_else(|| transmitwave_core::default_transfer_id(&data));
+    let mut encoder = TransferEncoder::new(transfer_id, &data, part_size)?;
+    println!("Transfer {:08x}: {} parts of up to {} bytes", transfer_id, encoder.part_count(), part_size);
 
+    let indices = match (parts, resume) {
+        (Some(parts), _) => parts,
+        (None, Some(token_path)) => encoder.missing_parts_from_token(&std::fs::read(token_path)?)?,
+        (None, None) => (0..encoder.part_count()).collect(),
+    };
+
+    std::fs::create_dir_all(output_dir)?;
+    for index in &indices {
+        let samples = encoder.encode_part(*index)?;
+        let path = output_dir.join(format!("part_{:05}.wav", index));
+        audio_io::write_wav_file(&path, &samples, WavSampleFormat::Int16)?;
+        println!("Wrote part {} to {}", index, path.display());
+    }
+    println!("Encoded {} of {} parts", indices.len(), encoder.part_count());
     Ok(())
 }
 
-#[tokio::main]
-async fn start_web_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Starting transmitwave server on http://localhost:{}", port);
-    println!("Endpoints:");
-    println!("  POST /encode - Encode binary data to WAV with multi-tone FSK (ggwave-compatible)");
-    println!("  POST /decode - Decode WAV to binary data with FSK");
-    println!("  GET / - Server status");
+fn transfer_decode_command(
+    state_path: &PathBuf,
+    inputs: &[PathBuf],
+    output_path: Option<&PathBuf>,
+    token_path: Option<&PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut session = if state_path.exists() {
+        TransferSession::from_bytes(&std::fs::read(state_path)?)?
+    } else {
+        TransferSession::new()
+    };
 
-    let app = Router::new()
-        .route("/", get(handler_status))
-        .route("/encode", post(handler_encode))
-        .route("/decode", post(handler_decode))
-        .layer(CorsLayer::permissive());
+    let mut decoder = DecoderFsk::new()?;
+    for input in inputs {
+        let samples = read_wav_verbose(input)?;
+        match session.decode_recording(&mut decoder, &samples) {
+            Ok((index, true)) => println!("Received part {} from {}", index, input.display()),
+            Ok((index, false)) => println!("Part {} from {} was already received", index, input.display()),
+            Err(e) => eprintln!("Failed to decode {}: {}", input.display(), e),
+        }
+    }
+    std::fs::write(state_path, session.to_bytes())?;
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    axum::serve(listener, app).await?;
+    let (Some(transfer_id), Some(part_count)) = (session.transfer_id(), session.part_count()) else {
+        println!("No parts received yet");
+        return Ok(());
+    };
+    println!(
+        "Transfer {:08x}: {}/{} parts received",
+        transfer_id,
+        session.received_count(),
+        part_count
+    );
+
+    if let Some(token_path) = token_path {
+        if let Some(token) = session.resume_token() {
+            std::fs::write(token_path, token)?;
+            println!("Wrote resume token to {}", token_path.display());
+        }
+    }
 
+    match session.assemble() {
+        Some(data) => {
+            println!("Transfer complete: {} bytes", data.len());
+            if let Some(output_path) = output_path {
+                std::fs::write(output_path, &data)?;
+                println!("Wrote {}", output_path.display());
+            }
+        }
+        None => {
+            let missing: Vec<String> = session.missing_parts().iter().map(u16::to_string).collect();
+            println!("Missing parts: {}", missing.join(","));
+        }
+    }
     Ok(())
 }
 
-async fn handler_status() -> String {
-    "transmitwave server with multi-tone FSK (ggwave-compatible) encoding/decoding - Ready".to_string()
+fn encode_batch_command(
+    input_dir: &Path,
+    output_dir: &Path,
+    jobs: usize,
+    encoder: &EncoderFsk,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = batch::encode_batch(input_dir, output_dir, jobs, encoder)?;
+
+    for entry in &report.files {
+        match (&entry.error, entry.duration_ms) {
+            (None, Some(duration_ms)) => println!("  ok    {} ({} bytes, {} ms on air)", entry.file, entry.bytes, duration_ms),
+            (error, _) => println!("  FAIL  {}: {}", entry.file, error.as_deref().unwrap_or("unknown error")),
+        }
+    }
+    println!(
+        "Encoded {}/{} payloads ({} failed) in {:.1}s on {} threads",
+        report.succeeded,
+        report.total,
+        report.failed,
+        report.elapsed_ms as f64 / 1000.0,
+        report.jobs
+    );
+    println!("Report written to {}", output_dir.join(batch::REPORT_FILE).display());
+    Ok(())
 }
 
-async fn handler_encode(
-    Json(req): Json<EncodeRequest>,
-) -> Result<Json<EncodeResponse>, (StatusCode, Json<EncodeResponse>)> {
-    let data = base64::engine::general_purpose::STANDARD
-        .decode(&req.data)
-        .map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                Json(EncodeResponse {
-                    success: false,
-                    message: format!("Invalid base64 data: {}", e),
-                    wav_base64: None,
-                }),
-            )
-        })?;
-
-    if data.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(EncodeResponse {
-                success: false,
-                message: "No data provided".to_string(),
-                wav_base64: None,
-            }),
-        ));
+fn decode_batch_command(
+    input_dir: &Path,
+    output_dir: &Path,
+    jobs: usize,
+    network_key: Option<NetworkKey>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let report = batch::decode_batch(input_dir, output_dir, jobs, network_key)?;
+
+    for entry in &report.files {
+        match (&entry.error, entry.bytes) {
+            (None, Some(bytes)) => println!("  ok    {} ({} bytes, {} ms)", entry.file, bytes, entry.elapsed_ms),
+            (error, _) => println!(
+                "  FAIL  {}: {} ({} ms)",
+                entry.file,
+                error.as_deref().unwrap_or("unknown error"),
+                entry.elapsed_ms
+            ),
+        }
     }
+    println!(
+        "Decoded {}/{} recordings ({} failed) in {:.1}s on {} threads",
+        report.succeeded,
+        report.total,
+        report.failed,
+        report.elapsed_ms as f64 / 1000.0,
+        report.jobs
+    );
+    println!("Report written to {}", output_dir.join(batch::REPORT_FILE).display());
+    Ok(())
+}
 
-    // Use FSK encoder (default for maximum reliability)
-    let encode_result = EncoderFsk::new()
-        .map_err(|e| e.to_string())
-        .and_then(|mut encoder| {
-            encoder.encode(&data)
-                .map_err(|e| e.to_string())
-        });
+fn trim_command(
+    input_path: &PathBuf,
+    output_path: &PathBuf,
+    options: &TrimOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let samples = read_wav_verbose(input_path)?;
+    let span = find_transmission(&samples, options).ok_or("No transmission found")?;
+
+    let seconds = |pos: usize| pos as f32 / SAMPLE_RATE as f32;
+    println!("Preamble at {:.2}s", seconds(span.preamble));
+    match span.postamble_end {
+        Some(end) => println!("Postamble ends at {:.2}s", seconds(end)),
+        None => println!("No postamble found, keeping audio up to {:.2}s", seconds(span.end)),
+    }
 
-    match encode_result {
-        Ok(samples) => {
-            // Convert to WAV
-            let spec = WavSpec {
-                channels: 1,
-                sample_rate: transmitwave_core::SAMPLE_RATE as u32,
-                bits_per_sample: 16,
-                sample_format: hound::SampleFormat::Int,
-            };
-
-            let wav_data_result = {
-                let mut wav_data = Vec::new();
-                {
-                    let cursor = std::io::Cursor::new(&mut wav_data);
-                    match hound::WavWriter::new(cursor, spec) {
-                        Ok(mut writer) => {
-                            for sample in samples {
-                                let clamped = sample.max(-1.0).min(1.0);
-                                let i16_sample = (clamped * 32767.0) as i16;
-                                if writer.write_sample(i16_sample).is_err() {
-                                    return Err((
-                                        StatusCode::INTERNAL_SERVER_ERROR,
-                                        Json(EncodeResponse {
-                                            success: false,
-                                            message: "Failed to write WAV samples".to_string(),
-                                            wav_base64: None,
-                                        }),
-                                    ));
-                                }
-                            }
-                            if writer.finalize().is_err() {
-                                return Err((
-                                    StatusCode::INTERNAL_SERVER_ERROR,
-                                    Json(EncodeResponse {
-                                        success: false,
-                                        message: "Failed to finalize WAV".to_string(),
-                                        wav_base64: None,
-                                    }),
-                                ));
-                            }
-                        }
-                        Err(e) => {
-                            return Err((
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                Json(EncodeResponse {
-                                    success: false,
-                                    message: format!("Failed to create WAV: {}", e),
-                                    wav_base64: None,
-                                }),
-                            ));
-                        }
-                    }
-                }
-                wav_data
-            };
-
-            let wav_base64 = base64::engine::general_purpose::STANDARD.encode(&wav_data_result);
-            Ok(Json(EncodeResponse {
-                success: true,
-                message: format!(
-                    "Encoded {} bytes to {} samples",
-                    data.len(),
-                    (wav_data_result.len() - 44) / 2
-                ),
-                wav_base64: Some(wav_base64),
-            }))
-        }
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(EncodeResponse {
-                success: false,
-                message: format!("Encoding failed: {}", e),
-                wav_base64: None,
-            }),
-        )),
+    audio_io::write_wav_file(output_path, &samples[span.start..span.end], WavSampleFormat::Int16)?;
+    println!(
+        "Wrote {:.2}s of {:.2}s to {}",
+        seconds(span.end - span.start),
+        seconds(samples.len()),
+        output_path.display()
+    );
+    Ok(())
+}
+
+fn calibrate_command(recording_path: &PathBuf, profile_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+    let samples = read_wav_verbose(recording_path)?;
+    let emphasis = calibrate_from_sweep(&samples)?;
+
+    let bins_per_band = FSK_NUM_BINS / NUM_FSK_TONES;
+    for band in 0..NUM_FSK_TONES {
+        let bins = band * bins_per_band..(band + 1) * bins_per_band;
+        let boost = bins.clone().filter_map(|bin| emphasis.boost_db(bin)).sum::<f32>() / bins_per_band as f32;
+        println!("  band {} ({:.0}-{:.0} Hz): +{:.1} dB", band, bin_to_freq(bins.start), bin_to_freq(bins.end - 1), boost);
     }
+
+    std::fs::write(profile_path, emphasis.to_text())?;
+    println!("Wrote pre-emphasis profile to {}", profile_path.display());
+    Ok(())
 }
 
-async fn handler_decode(
-    Json(req): Json<DecodeRequest>,
-) -> Result<Json<DecodeResponse>, (StatusCode, Json<DecodeResponse>)> {
-    let wav_data = base64::engine::general_purpose::STANDARD
-        .decode(&req.wav_base64)
-        .map_err(|e| {
-            (
-                StatusCode::BAD_REQUEST,
-                Json(DecodeResponse {
-                    success: false,
-                    message: format!("Invalid base64 WAV data: {}", e),
-                    data: None,
-                }),
-            )
-        })?;
-
-    if wav_data.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(DecodeResponse {
-                success: false,
-                message: "No WAV data provided".to_string(),
-                data: None,
-            }),
-        ));
+fn spectrogram_command(
+    input_path: &PathBuf,
+    output_path: Option<&PathBuf>,
+    width: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (samples, _) = audio_io::read_wav_file(input_path)?;
+
+    let spectrogram = spectrogram::compute(
+        &samples,
+        spectrogram::SPECTROGRAM_WINDOW,
+        spectrogram::SPECTROGRAM_HOP,
+    );
+    let markers = spectrogram::find_markers(&samples);
+
+    let seconds = |sample: usize| sample as f32 / SAMPLE_RATE as f32;
+    match markers.preamble {
+        Some(pos) => println!("Preamble at sample {} ({:.3}s)", pos, seconds(pos)),
+        None => println!("Preamble not detected"),
+    }
+    match markers.postamble {
+        Some(pos) => println!("Postamble at sample {} ({:.3}s)", pos, seconds(pos)),
+        None => println!("Postamble not detected"),
+    }
+    if !markers.symbol_boundaries.is_empty() {
+        println!("Symbol boundaries: {}", markers.symbol_boundaries.len());
     }
 
-    // Parse WAV file
-    let cursor = std::io::Cursor::new(&wav_data);
-    match hound::WavReader::new(cursor) {
-        Ok(mut reader) => {
-            let spec = reader.spec();
-            let samples: Vec<f32> = match spec.bits_per_sample {
-                16 => {
-                    match reader.samples::<i16>().collect::<Result<Vec<_>, _>>() {
-                        Ok(int_samples) => int_samples.iter().map(|s| *s as f32 / 32768.0).collect(),
-                        Err(e) => {
-                            return Err((
-                                StatusCode::BAD_REQUEST,
-                                Json(DecodeResponse {
-                                    success: false,
-                                    message: format!("Failed to read i16 samples: {}", e),
-                                    data: None,
-                                }),
-                            ));
-                        }
-                    }
-                }
-                32 => {
-                    match reader.samples::<f32>().collect::<Result<Vec<_>, _>>() {
-                        Ok(samples) => samples,
-                        Err(e) => {
-                            return Err((
-                                StatusCode::BAD_REQUEST,
-                                Json(DecodeResponse {
-                                    success: false,
-                                    message: format!("Failed to read f32 samples: {}", e),
-                                    data: None,
-                                }),
-                            ));
-                        }
-                    }
-                }
-                _ => {
-                    return Err((
-                        StatusCode::BAD_REQUEST,
-                        Json(DecodeResponse {
-                            success: false,
-                            message: format!("Unsupported bit depth: {}", spec.bits_per_sample),
-                            data: None,
-                        }),
-                    ));
-                }
-            };
-
-            // Use FSK decoder (default for maximum reliability)
-            let decode_result = DecoderFsk::new()
-                .map_err(|e| e.to_string())
-                .and_then(|mut decoder| {
-                    decoder.decode(&samples)
-                        .map_err(|e| e.to_string())
-                });
-
-            match decode_result {
-                Ok(decoded_data) => {
-                    let data_base64 = base64::engine::general_purpose::STANDARD.encode(&decoded_data);
-                    Ok(Json(DecodeResponse {
-                        success: true,
-                        message: format!(
-                            "Decoded {} bytes",
-                            decoded_data.len()
-                        ),
-                        data: Some(data_base64),
-                    }))
-                }
-                Err(e) => Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(DecodeResponse {
-                        success: false,
-                        message: format!("Decoding failed: {}", e),
-                        data: None,
-                    }),
-                )),
-            }
+    match output_path {
+        Some(path) => {
+            spectrogram::render_png(&spectrogram, &markers, path)?;
+            println!("Wrote spectrogram to {}", path.display());
         }
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(DecodeResponse {
-                success: false,
-                message: format!("Failed to read WAV: {}", e),
-                data: None,
-            }),
-        )),
+        None => print!("{}", spectrogram::render_ascii(&spectrogram, &markers, width)),
     }
+
+    Ok(())
 }
 
@@ -0,0 +1,1286 @@
+//! HTTP API (`transmitwave server`)
+//!
+//! `POST /encode` and `POST /decode` take JSON with base64 audio/data plus
+//! optional tuning fields; a request with only the data behaves like the
+//! `encode`/`decode` commands with their defaults, and `/encode` returns the
+//! same WAV bytes as `encode` with the matching options. Invalid options are
+//! rejected with 422 and one message per offending field.
+//!
+//! `POST /decode/upload` takes the WAV as a raw body or as the `wav` part of a
+//! multipart form (with the `/decode` options as JSON in an `options` part),
+//! avoiding the base64 inflation. `POST /detect` takes a raw WAV body and only
+//! scores how likely it holds a transmission (`signal_presence`), to screen
+//! uploads before decoding them. Bodies are capped at `ServerLimits::max_body_bytes`
+//! and each client IP gets `ServerLimits::requests_per_minute`.
+
+use axum::{
+    body::Bytes,
+    extract::{
+        rejection::{BytesRejection, JsonRejection},
+        ConnectInfo, DefaultBodyLimit, FromRequest, Multipart, Path, Request, State,
+    },
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{Duration, Instant};
+use tower_http::cors::CorsLayer;
+use transmitwave_core::audio_io::{self, WavStreamReader};
+use transmitwave_core::decoder_fsk::DecodeStats;
+use transmitwave_core::{
+    AudioModemError, DecodeQuality, DecodedMessage, DecoderFsk, DegreeDistribution, DetectionThreshold, EncoderFsk, FountainCode,
+    FountainConfig, FrameProfile, NetworkKey, ToneLayout, MAX_SYMBOL_GAP_INTERVAL, MIN_SYMBOL_GAP_INTERVAL,
+    signal_presence, StreamResampler, StreamingDecoder, TransmissionSidecar, SAMPLE_RATE,
+};
+
+/// Longest fountain transmission `/encode` generates
+const MAX_FOUNTAIN_ENCODE_SECS: u32 = 120;
+
+/// Longest time `/decode` spends on a fountain decode
+const MAX_FOUNTAIN_DECODE_SECS: u32 = 60;
+
+/// Decode sessions open at once (503 above)
+const MAX_DECODE_SESSIONS: usize = 64;
+
+/// Decode sessions without a request for this long are dropped
+const DECODE_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often idle decode sessions are swept
+const DECODE_SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Sample rates decode sessions accept (Hz)
+const MIN_SESSION_SAMPLE_RATE: u32 = 8_000;
+const MAX_SESSION_SAMPLE_RATE: u32 = 192_000;
+
+/// Most clients the rate limiter tracks; idle ones are forgotten first, then
+/// the least recently seen
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Abuse limits of a public server
+#[derive(Debug, Clone, Copy)]
+pub struct ServerLimits {
+    /// Largest request body accepted (413 above)
+    pub max_body_bytes: usize,
+    /// Requests per minute per client IP, 0 for no limit (429 above)
+    pub requests_per_minute: u32,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        ServerLimits { max_body_bytes: 16 * 1024 * 1024, requests_per_minute: 60 }
+    }
+}
+
+/// Fountain mode options; omitted fields take the `fountain-encode` defaults
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FountainOptions {
+    /// Encode: seconds of blocks to generate; decode: time limit (default 30)
+    timeout_secs: Option<u32>,
+    /// Symbol size in bytes (default 64)
+    block_size: Option<usize>,
+    /// Repair blocks per source block, encode only (default 0.5)
+    repair_ratio: Option<f32>,
+    /// "raptorq" (default) or "lt", encode only
+    code: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncodeRequest {
+    data: String, // base64-encoded input data
+    /// "standard" (default), "short", "telephony", "codec-robust" or "narrowband"
+    profile: Option<String>,
+    /// Reed-Solomon code as "N,K"; default picks by payload size
+    rs: Option<String>,
+    #[serde(default)]
+    amplitude_bits: bool,
+    /// "TONES,BINS"; default 6 tones of 16 bins
+    tone_layout: Option<String>,
+    /// Silence gap after every N data symbols (4-63)
+    symbol_gaps: Option<u8>,
+    /// Send every tone in both halves of the band
+    #[serde(default)]
+    dual_band: bool,
+    /// Output gain in (0.0, 1.0] (default 1.0)
+    amplitude: Option<f32>,
+    network_key: Option<String>,
+    /// Encode as a fountain stream instead of one frame
+    fountain: Option<FountainOptions>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DecodeRequest {
+    /// Base64-encoded WAV file; required for `/decode`, not allowed for `/decode/upload`
+    wav_base64: Option<String>,
+    /// Fixed preamble threshold in (0.0, 1.0]; adaptive when omitted
+    preamble_threshold: Option<f32>,
+    /// Fixed postamble threshold in (0.0, 1.0]; adaptive when omitted
+    postamble_threshold: Option<f32>,
+    network_key: Option<String>,
+    /// Decode a fountain stream instead of one frame
+    fountain: Option<FountainOptions>,
+}
+
+/// Why one request field was rejected
+#[derive(Serialize)]
+struct FieldError {
+    field: String,
+    message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        FieldError { field: field.to_string(), message: message.into() }
+    }
+}
+
+#[derive(Serialize)]
+struct EncodeStats {
+    samples: usize,
+    duration_ms: u64,
+    /// Reed-Solomon code of a regular frame, as "N,K"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rs: Option<String>,
+    /// RS blocks and FSK symbols of a regular frame
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rs_blocks: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbols: Option<usize>,
+}
+
+#[derive(Serialize, Default)]
+struct EncodeResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wav_base64: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<EncodeStats>,
+    /// Sidecar of a regular frame (what `encode --sidecar` writes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sidecar: Option<TransmissionSidecar>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Decoder statistics, plus signal quality when the decode succeeded
+#[derive(Serialize)]
+struct DecodeReport {
+    decoded_blocks: u32,
+    failed_blocks: u32,
+    rs_blocks_decoded: u32,
+    rs_blocks_failed: u32,
+    corrected_bytes: u32,
+    symbols_demodulated: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preamble_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postamble_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snr_db: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_symbol_margin: Option<f32>,
+}
+
+impl DecodeReport {
+    fn new(stats: &DecodeStats, quality: Option<&DecodeQuality>) -> Self {
+        DecodeReport {
+            decoded_blocks: stats.decoded_blocks,
+            failed_blocks: stats.failed_blocks,
+            rs_blocks_decoded: stats.rs_blocks_decoded,
+            rs_blocks_failed: stats.rs_blocks_failed,
+            corrected_bytes: stats.corrected_bytes,
+            symbols_demodulated: stats.symbols_demodulated,
+            preamble_score: stats.preamble_score,
+            postamble_score: stats.postamble_score,
+            snr_db: quality.map(|q| q.snr_db),
+            min_symbol_margin: quality.map(|q| q.min_symbol_margin),
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct DecodeResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<DecodeReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+#[derive(Serialize, Default)]
+struct DetectResponse {
+    success: bool,
+    message: String,
+    /// Likelihood (0.0-1.0) that the WAV holds a transmission
+    #[serde(skip_serializing_if = "Option::is_none")]
+    probability: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    likely: Option<bool>,
+    /// Best normalized preamble correlation and where it was found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position_ms: Option<u64>,
+    /// Share of the energy inside the FSK band
+    #[serde(skip_serializing_if = "Option::is_none")]
+    band_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Options of `POST /decode/sessions`; chunks are raw little-endian mono PCM
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SessionRequest {
+    /// Sample rate of the chunks in Hz (default 16000)
+    sample_rate: Option<u32>,
+    /// "s16le" (default) or "f32le"
+    sample_format: Option<String>,
+    /// Fixed preamble threshold in (0.0, 1.0]; adaptive when omitted
+    preamble_threshold: Option<f32>,
+    /// Fixed postamble threshold in (0.0, 1.0]; adaptive when omitted
+    postamble_threshold: Option<f32>,
+    network_key: Option<String>,
+}
+
+/// Progress of a decode session
+#[derive(Serialize)]
+struct SessionStatus {
+    /// "receiving" until `finish`, then "finished"
+    state: &'static str,
+    received_ms: u64,
+    /// Audio held while waiting for a frame to complete
+    buffered_ms: u64,
+    frame_pending: bool,
+    frames_decoded: usize,
+}
+
+/// A frame decoded by a session
+#[derive(Serialize)]
+struct SessionFrame {
+    /// Base64-encoded payload
+    data: String,
+    /// Preamble position from the start of the uploaded audio
+    position_ms: u64,
+    snr_db: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+struct SessionResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Box<SessionStatus>>,
+    /// Decoded frames, in `/result` responses
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    frames: Vec<SessionFrame>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Failure response of an endpoint
+trait Failure: Default {
+    fn failure(message: String, errors: Vec<FieldError>) -> Self;
+}
+
+impl Failure for EncodeResponse {
+    fn failure(message: String, errors: Vec<FieldError>) -> Self {
+        EncodeResponse { message, errors, ..Default::default() }
+    }
+}
+
+impl Failure for DecodeResponse {
+    fn failure(message: String, errors: Vec<FieldError>) -> Self {
+        DecodeResponse { message, errors, ..Default::default() }
+    }
+}
+
+impl Failure for DetectResponse {
+    fn failure(message: String, errors: Vec<FieldError>) -> Self {
+        DetectResponse { message, errors, ..Default::default() }
+    }
+}
+
+impl Failure for SessionResponse {
+    fn failure(message: String, errors: Vec<FieldError>) -> Self {
+        SessionResponse { message, errors, ..Default::default() }
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, Json<T>)>;
+
+fn invalid<T: Failure>(errors: Vec<FieldError>) -> (StatusCode, Json<T>) {
+    let message = format!("Invalid request: {} field error(s)", errors.len());
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(T::failure(message, errors)))
+}
+
+fn server_error<T: Failure>(e: AudioModemError) -> (StatusCode, Json<T>) {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(T::failure(e.to_string(), Vec::new())))
+}
+
+/// Body that could not be read: too large, not JSON, or not matching the schema
+fn rejected<T: Failure>(status: StatusCode, text: String) -> (StatusCode, Json<T>) {
+    let errors = vec![FieldError::new("body", text)];
+    (status, Json(T::failure("Invalid request body".to_string(), errors)))
+}
+
+fn decode_base64(field: &str, value: &str, errors: &mut Vec<FieldError>) -> Vec<u8> {
+    match base64::engine::general_purpose::STANDARD.decode(value) {
+        Ok(bytes) if bytes.is_empty() => {
+            errors.push(FieldError::new(field, "must not be empty"));
+            bytes
+        }
+        Ok(bytes) => bytes,
+        Err(e) => {
+            errors.push(FieldError::new(field, format!("invalid base64: {}", e)));
+            Vec::new()
+        }
+    }
+}
+
+fn check_unit_range(field: &str, value: Option<f32>, errors: &mut Vec<FieldError>) {
+    if value.is_some_and(|v| !(v > 0.0 && v <= 1.0)) {
+        errors.push(FieldError::new(field, "must be in (0.0, 1.0]"));
+    }
+}
+
+fn network_key(key: Option<&str>, errors: &mut Vec<FieldError>) -> Option<NetworkKey> {
+    match key {
+        Some("") => {
+            errors.push(FieldError::new("network_key", "must not be empty"));
+            None
+        }
+        key => key.map(|key| NetworkKey::new(key.as_bytes())),
+    }
+}
+
+fn fountain_config(options: &FountainOptions, max_secs: u32, errors: &mut Vec<FieldError>) -> FountainConfig {
+    let mut config = FountainConfig::default();
+    match options.timeout_secs {
+        Some(secs) if secs == 0 || secs > max_secs => {
+            errors.push(FieldError::new("fountain.timeout_secs", format!("must be 1 to {}", max_secs)))
+        }
+        Some(secs) => config.timeout_secs = secs,
+        None => {}
+    }
+    match options.block_size {
+        Some(size) if size == 0 || size > u16::MAX as usize => {
+            errors.push(FieldError::new("fountain.block_size", format!("must be 1 to {}", u16::MAX)))
+        }
+        Some(size) => config.block_size = size,
+        None => {}
+    }
+    match options.repair_ratio {
+        Some(ratio) if !(ratio.is_finite() && ratio >= 0.0) => {
+            errors.push(FieldError::new("fountain.repair_ratio", "must be a non-negative number"))
+        }
+        Some(ratio) => config.repair_blocks_ratio = ratio,
+        None => {}
+    }
+    match options.code.as_deref() {
+        None | Some("raptorq") => config.code = FountainCode::RaptorQ,
+        Some("lt") => config.code = FountainCode::Lt(DegreeDistribution::default()),
+        Some(_) => errors.push(FieldError::new("fountain.code", "must be \"raptorq\" or \"lt\"")),
+    }
+    config
+}
+
+/// Apply the request's options to `encoder`; returns the output gain and fountain settings
+fn encode_settings(
+    req: &EncodeRequest,
+    encoder: &mut EncoderFsk,
+    errors: &mut Vec<FieldError>,
+) -> (f32, Option<FountainConfig>) {
+    let profile = match req.profile.as_deref().map(str::parse::<FrameProfile>) {
+        None => FrameProfile::Standard,
+        Some(Ok(profile)) => profile,
+        Some(Err(e)) => {
+            errors.push(FieldError::new("profile", e.to_string()));
+            FrameProfile::Standard
+        }
+    };
+    if profile == FrameProfile::Short && req.symbol_gaps.is_some() {
+        errors.push(FieldError::new("symbol_gaps", "not supported with the short profile"));
+    }
+    if profile != FrameProfile::Standard {
+        encoder.set_profile(profile);
+        let message = format!("not supported with the {} profile", req.profile.as_deref().unwrap_or_default());
+        let options = [
+            ("rs", req.rs.is_some()),
+            ("amplitude_bits", req.amplitude_bits),
+            ("tone_layout", req.tone_layout.is_some()),
+            ("dual_band", req.dual_band),
+        ];
+        for (field, set) in options {
+            if set {
+                errors.push(FieldError::new(field, message.clone()));
+            }
+        }
+    }
+
+    if let Some(rs) = &req.rs {
+        match crate::parse_rs_code(rs) {
+            Ok(mode) => encoder.set_fec_mode(Some(mode)),
+            Err(e) => errors.push(FieldError::new("rs", e)),
+        }
+    }
+    if let Some(layout) = &req.tone_layout {
+        match crate::parse_tone_layout(layout) {
+            Ok(_) if req.amplitude_bits => {
+                errors.push(FieldError::new("tone_layout", "not supported with amplitude_bits"))
+            }
+            Ok(layout) => encoder.set_tone_layout(layout),
+            Err(e) => errors.push(FieldError::new("tone_layout", e)),
+        }
+    }
+    encoder.set_amplitude_bits(req.amplitude_bits);
+    if req.dual_band {
+        let layout = encoder.tone_layout();
+        if req.amplitude_bits {
+            errors.push(FieldError::new("dual_band", "not supported with amplitude_bits"));
+        } else if let Some(Err(e)) = (layout != ToneLayout::STANDARD).then(|| layout.dual_band()) {
+            // The default layout becomes ToneLayout::DUAL_BAND; others must fit into half the band
+            errors.push(FieldError::new("dual_band", e.to_string()));
+        }
+        encoder.set_dual_band(true);
+    }
+    if let Some(interval) = req.symbol_gaps {
+        if !(MIN_SYMBOL_GAP_INTERVAL..=MAX_SYMBOL_GAP_INTERVAL).contains(&(interval as usize)) {
+            errors.push(FieldError::new(
+                "symbol_gaps",
+                format!("must be {}-{}", MIN_SYMBOL_GAP_INTERVAL, MAX_SYMBOL_GAP_INTERVAL),
+            ));
+        }
+        encoder.set_symbol_gaps(Some(interval));
+    }
+    check_unit_range("amplitude", req.amplitude, errors);
+    encoder.set_network_key(network_key(req.network_key.as_deref(), errors));
+
+    let fountain = req.fountain.as_ref().map(|options| {
+        for (field, set) in [
+            ("profile", profile != FrameProfile::Standard),
+            ("rs", req.rs.is_some()),
+            ("amplitude_bits", req.amplitude_bits),
+            ("tone_layout", req.tone_layout.is_some()),
+            ("symbol_gaps", req.symbol_gaps.is_some()),
+            ("dual_band", req.dual_band),
+        ] {
+            if set {
+                errors.push(FieldError::new(field, "not supported in fountain mode"));
+            }
+        }
+        fountain_config(options, MAX_FOUNTAIN_ENCODE_SECS, errors)
+    });
+
+    (req.amplitude.unwrap_or(1.0), fountain)
+}
+
+/// Token bucket per client IP, refilled at `per_minute` requests per minute
+struct RateLimiter {
+    per_minute: u32,
+    max_clients: usize,
+    clients: Mutex<HashMap<IpAddr, (f64, Instant)>>,
+}
+
+impl RateLimiter {
+    fn new(per_minute: u32) -> Self {
+        Self { per_minute, max_clients: MAX_TRACKED_CLIENTS, clients: Mutex::default() }
+    }
+
+    /// Take one request from `ip`'s bucket; false when it is empty
+    fn allow(&self, ip: IpAddr) -> bool {
+        let capacity = self.per_minute as f64;
+        let refill_per_sec = capacity / 60.0;
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+        if clients.len() >= self.max_clients && !clients.contains_key(&ip) {
+            // Forget clients whose bucket has refilled completely
+            clients.retain(|_, (tokens, last)| *tokens + now.duration_since(*last).as_secs_f64() * refill_per_sec < capacity);
+            // None idle: drop the least recently seen so the table never grows past the cap
+            while clients.len() >= self.max_clients {
+                let Some(oldest) = clients.iter().min_by_key(|(_, (_, last))| *last).map(|(ip, _)| *ip) else {
+                    break;
+                };
+                clients.remove(&oldest);
+            }
+        }
+
+        let (tokens, last) = clients.entry(ip).or_insert((capacity, now));
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * refill_per_sec).min(capacity);
+        *last = now;
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+}
+
+async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if limiter.allow(addr.ip()) {
+        return next.run(request).await;
+    }
+    let retry_after = (60 / limiter.per_minute.max(1)).max(1).to_string();
+    let body = serde_json::json!({ "success": false, "message": "Rate limit exceeded, retry later" });
+    (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after)], Json(body)).into_response()
+}
+
+#[tokio::main]
+pub async fn start_web_server(port: u16, limits: ServerLimits) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Starting transmitwave server on http://localhost:{}", port);
+    println!("Endpoints:");
+    println!("  POST /encode - Encode binary data to WAV with multi-tone FSK (ggwave-compatible)");
+    println!("  POST /decode - Decode WAV to binary data with FSK");
+    println!("  POST /decode/upload - Decode a raw or multipart WAV upload");
+    println!("  POST /detect - Score how likely a raw WAV upload holds a transmission");
+    println!("  POST /decode/sessions - Open a session for audio uploaded in chunks (then .../chunks, .../finish, .../result)");
+    println!("  GET / - Server status");
+    println!(
+        "Limits: {} byte bodies, {}",
+        limits.max_body_bytes,
+        match limits.requests_per_minute {
+            0 => "no rate limit".to_string(),
+            n => format!("{} requests/minute per IP", n),
+        }
+    );
+
+    #[cfg(feature = "gpu")]
+    match transmitwave_core::WgpuCorrelation::new() {
+        Ok(gpu) => {
+            println!("Sync correlation on GPU: {}", gpu.adapter_name());
+            transmitwave_core::set_correlation_backend(Some(std::sync::Arc::new(gpu)));
+        }
+        Err(e) => println!("GPU correlation unavailable ({}), using CPU", e),
+    }
+
+    let sessions = Arc::new(DecodeSessions::default());
+    let sweeper = Arc::clone(&sessions);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DECODE_SESSION_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweeper.sweep();
+        }
+    });
+
+    let mut app = Router::new()
+        .route("/", get(handler_status))
+        .route("/encode", post(handler_encode))
+        .route("/decode", post(handler_decode))
+        .route("/decode/upload", post(handler_decode_upload))
+        .route("/detect", post(handler_detect))
+        .route("/decode/sessions", post(handler_session_create))
+        .route("/decode/sessions/{id}", get(handler_session_status).delete(handler_session_delete))
+        .route("/decode/sessions/{id}/chunks", post(handler_session_chunk))
+        .route("/decode/sessions/{id}/finish", post(handler_session_finish))
+        .route("/decode/sessions/{id}/result", get(handler_session_result))
+        .with_state(sessions)
+        .layer(DefaultBodyLimit::max(limits.max_body_bytes));
+    if limits.requests_per_minute > 0 {
+        let limiter = Arc::new(RateLimiter::new(limits.requests_per_minute));
+        app = app.layer(middleware::from_fn_with_state(limiter, rate_limit));
+    }
+    let app = app.layer(CorsLayer::permissive());
+
+    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+
+    Ok(())
+}
+
+async fn handler_status() -> String {
+    "transmitwave server with multi-tone FSK (ggwave-compatible) encoding/decoding - Ready".to_string()
+}
+
+async fn handler_encode(payload: Result<Json<EncodeRequest>, JsonRejection>) -> ApiResult<EncodeResponse> {
+    let Json(req) = payload.map_err(|r| rejected(r.status(), r.body_text()))?;
+
+    let mut errors = Vec::new();
+    let data = decode_base64("data", &req.data, &mut errors);
+    let mut encoder = EncoderFsk::new().map_err(server_error)?;
+    let (amplitude, fountain) = encode_settings(&req, &mut encoder, &mut errors);
+    // The payload limits the CLI enforces too, checked once the options are valid
+    let mut airtime = None;
+    if errors.is_empty() && fountain.is_none() {
+        match encoder.airtime(data.len(), false) {
+            Ok(frame) => airtime = Some(frame),
+            Err(e @ AudioModemError::PayloadTooLarge { .. }) => errors.push(FieldError::new("data", e.to_string())),
+            Err(e) => errors.push(FieldError::new("profile", e.to_string())),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(invalid(errors));
+    }
+    // Before encoding, which draws the next random sync gaps
+    let sidecar = match airtime {
+        Some(_) => Some(encoder.sidecar(&data).map_err(server_error)?),
+        None => None,
+    };
+
+    let encoded = match fountain {
+        Some(config) => encoder.encode_fountain(&data, Some(config)).map(|stream| stream.flatten().collect()),
+        None => encoder.encode(&data),
+    };
+    let samples: Vec<f32> = encoded.map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(EncodeResponse::failure(format!("Encoding failed: {}", e), Vec::new())),
+        )
+    })?;
+
+    let wav_data = audio_io::encoded_wav_bytes(&samples, amplitude, None).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(EncodeResponse::failure(format!("Failed to create WAV: {}", e), Vec::new())),
+        )
+    })?;
+
+    let rs = airtime.map(|frame| format!("{},{}", frame.fec_mode.block_bytes(), frame.fec_mode.data_bytes()));
+    Ok(Json(EncodeResponse {
+        success: true,
+        message: format!("Encoded {} bytes to {} samples", data.len(), samples.len()),
+        wav_base64: Some(base64::engine::general_purpose::STANDARD.encode(&wav_data)),
+        stats: Some(EncodeStats {
+            samples: samples.len(),
+            duration_ms: (samples.len() * 1000 / SAMPLE_RATE) as u64,
+            rs,
+            rs_blocks: airtime.map(|frame| frame.rs_blocks),
+            symbols: airtime.map(|frame| frame.symbols),
+        }),
+        sidecar,
+        errors: Vec::new(),
+    }))
+}
+
+async fn handler_decode(payload: Result<Json<DecodeRequest>, JsonRejection>) -> ApiResult<DecodeResponse> {
+    let Json(req) = payload.map_err(|r| rejected(r.status(), r.body_text()))?;
+
+    let mut errors = Vec::new();
+    let wav_data = match &req.wav_base64 {
+        Some(wav_base64) => decode_base64("wav_base64", wav_base64, &mut errors),
+        None => {
+            errors.push(FieldError::new("wav_base64", "required"));
+            Vec::new()
+        }
+    };
+    decode_wav(&wav_data, "wav_base64", &req, errors).await
+}
+
+async fn handler_decode_upload(request: Request) -> ApiResult<DecodeResponse> {
+    let (wav_data, req) = read_upload(request).await?;
+
+    let mut errors = Vec::new();
+    if req.wav_base64.is_some() {
+        errors.push(FieldError::new("wav_base64", "send the audio as the upload body or `wav` part"));
+    }
+    if wav_data.is_empty() {
+        errors.push(FieldError::new("wav", "must not be empty"));
+    }
+    decode_wav(&wav_data, "wav", &req, errors).await
+}
+
+async fn handler_detect(body: Result<Bytes, BytesRejection>) -> ApiResult<DetectResponse> {
+    let wav_data = body.map_err(|r| rejected(r.status(), r.body_text()))?;
+    if wav_data.is_empty() {
+        return Err(invalid(vec![FieldError::new("wav", "must not be empty")]));
+    }
+    let (samples, _) = audio_io::read_wav_to_modem_samples(std::io::Cursor::new(&wav_data[..]))
+        .map_err(|e| invalid(vec![FieldError::new("wav", format!("failed to read WAV: {}", e))]))?;
+
+    let presence = signal_presence(&samples);
+    let message = if presence.is_likely() { "Transmission likely present" } else { "No transmission likely" };
+    Ok(Json(DetectResponse {
+        success: true,
+        message: message.to_string(),
+        probability: Some(presence.probability),
+        likely: Some(presence.is_likely()),
+        correlation: Some(presence.correlation),
+        position_ms: Some((presence.position * 1000 / SAMPLE_RATE) as u64),
+        band_ratio: Some(presence.band_ratio),
+        errors: Vec::new(),
+    }))
+}
+
+/// Sample encoding of session chunks
+#[derive(Clone, Copy)]
+enum PcmFormat {
+    S16Le,
+    F32Le,
+}
+
+impl PcmFormat {
+    fn sample_bytes(self) -> usize {
+        match self {
+            PcmFormat::S16Le => 2,
+            PcmFormat::F32Le => 4,
+        }
+    }
+
+    fn sample(self, bytes: &[u8]) -> f32 {
+        match self {
+            PcmFormat::S16Le => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            PcmFormat::F32Le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// Upload in progress: chunks are resampled and fed to a streaming decoder
+struct DecodeSession {
+    decoder: StreamingDecoder,
+    resampler: StreamResampler,
+    format: PcmFormat,
+    /// Bytes of a sample split across chunks
+    partial: Vec<u8>,
+    /// Samples at SAMPLE_RATE fed to the decoder
+    received: usize,
+    frames: Vec<DecodedMessage>,
+    finished: bool,
+    last_used: Instant,
+}
+
+impl DecodeSession {
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        let whole = self.partial.len() - self.partial.len() % self.format.sample_bytes();
+        let samples: Vec<f32> =
+            self.partial[..whole].chunks_exact(self.format.sample_bytes()).map(|sample| self.format.sample(sample)).collect();
+        self.partial.drain(..whole);
+        let mut resampled = Vec::with_capacity(samples.len());
+        self.resampler.push(&samples, &mut resampled);
+        self.push_samples(&resampled);
+    }
+
+    /// Flush the resampler; later chunks are rejected
+    fn finish(&mut self) {
+        let mut rest = Vec::new();
+        self.resampler.finish(&mut rest);
+        self.push_samples(&rest);
+        self.partial.clear();
+        self.finished = true;
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        self.received += samples.len();
+        let frames = self.decoder.push(samples);
+        self.frames.extend(frames);
+    }
+
+    fn status(&self) -> SessionStatus {
+        SessionStatus {
+            state: if self.finished { "finished" } else { "receiving" },
+            received_ms: (self.received * 1000 / SAMPLE_RATE) as u64,
+            buffered_ms: (self.decoder.buffered_samples() * 1000 / SAMPLE_RATE) as u64,
+            frame_pending: self.decoder.frame_pending(),
+            frames_decoded: self.frames.len(),
+        }
+    }
+}
+
+/// Open decode sessions by id
+#[derive(Default)]
+struct DecodeSessions {
+    sessions: Mutex<HashMap<String, Arc<Mutex<DecodeSession>>>>,
+}
+
+impl DecodeSessions {
+    /// Drop the sessions idle for `DECODE_SESSION_IDLE_TIMEOUT`
+    fn sweep(&self) {
+        self.sessions.lock().unwrap().retain(|_, session| match session.try_lock() {
+            Ok(session) => session.last_used.elapsed() < DECODE_SESSION_IDLE_TIMEOUT,
+            // Busy with a request
+            Err(TryLockError::WouldBlock) => true,
+            Err(TryLockError::Poisoned(_)) => false,
+        });
+    }
+
+    /// Store `session` under a new id, dropping idle sessions first; None when full
+    fn insert(&self, session: DecodeSession) -> Option<String> {
+        self.sweep();
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= MAX_DECODE_SESSIONS {
+            return None;
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        sessions.insert(id.clone(), Arc::new(Mutex::new(session)));
+        Some(id)
+    }
+
+    fn get(&self, id: &str) -> Result<Arc<Mutex<DecodeSession>>, (StatusCode, Json<SessionResponse>)> {
+        self.sessions.lock().unwrap().get(id).cloned().ok_or_else(session_not_found)
+    }
+
+    fn remove(&self, id: &str) -> Result<Arc<Mutex<DecodeSession>>, (StatusCode, Json<SessionResponse>)> {
+        self.sessions.lock().unwrap().remove(id).ok_or_else(session_not_found)
+    }
+}
+
+fn session_not_found() -> (StatusCode, Json<SessionResponse>) {
+    (StatusCode::NOT_FOUND, Json(SessionResponse::failure("Unknown or expired decode session".to_string(), Vec::new())))
+}
+
+/// Run `work` on the blocking pool, since demodulating a chunk takes a while
+async fn blocking<T: Failure + Send + 'static>(work: impl FnOnce() -> ApiResult<T> + Send + 'static) -> ApiResult<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .unwrap_or_else(|e| Err((StatusCode::INTERNAL_SERVER_ERROR, Json(T::failure(e.to_string(), Vec::new())))))
+}
+
+fn session_response(id: &str, session: &DecodeSession, message: String) -> Json<SessionResponse> {
+    Json(SessionResponse {
+        success: true,
+        message,
+        session_id: Some(id.to_string()),
+        status: Some(Box::new(session.status())),
+        ..Default::default()
+    })
+}
+
+async fn handler_session_create(
+    State(sessions): State<Arc<DecodeSessions>>,
+    payload: Result<Json<SessionRequest>, JsonRejection>,
+) -> ApiResult<SessionResponse> {
+    let Json(req) = payload.map_err(|r| rejected(r.status(), r.body_text()))?;
+
+    let mut errors = Vec::new();
+    let sample_rate = req.sample_rate.unwrap_or(SAMPLE_RATE as u32);
+    if !(MIN_SESSION_SAMPLE_RATE..=MAX_SESSION_SAMPLE_RATE).contains(&sample_rate) {
+        errors.push(FieldError::new(
+            "sample_rate",
+            format!("must be {} to {}", MIN_SESSION_SAMPLE_RATE, MAX_SESSION_SAMPLE_RATE),
+        ));
+    }
+    let format = match req.sample_format.as_deref() {
+        None | Some("s16le") => PcmFormat::S16Le,
+        Some("f32le") => PcmFormat::F32Le,
+        Some(_) => {
+            errors.push(FieldError::new("sample_format", "must be \"s16le\" or \"f32le\""));
+            PcmFormat::S16Le
+        }
+    };
+    check_unit_range("preamble_threshold", req.preamble_threshold, &mut errors);
+    check_unit_range("postamble_threshold", req.postamble_threshold, &mut errors);
+    let key = network_key(req.network_key.as_deref(), &mut errors);
+    if !errors.is_empty() {
+        return Err(invalid(errors));
+    }
+
+    let mut decoder = StreamingDecoder::new().map_err(server_error)?;
+    if let Some(threshold) = req.preamble_threshold {
+        decoder.decoder_mut().set_preamble_threshold(DetectionThreshold::Fixed(threshold));
+    }
+    if let Some(threshold) = req.postamble_threshold {
+        decoder.decoder_mut().set_postamble_threshold(DetectionThreshold::Fixed(threshold));
+    }
+    decoder.decoder_mut().set_network_key(key);
+    let session = DecodeSession {
+        decoder,
+        resampler: StreamResampler::new(sample_rate as usize, SAMPLE_RATE),
+        format,
+        partial: Vec::new(),
+        received: 0,
+        frames: Vec::new(),
+        finished: false,
+        last_used: Instant::now(),
+    };
+    let status = session.status();
+    let id = sessions.insert(session).ok_or_else(|| {
+        let message = format!("Too many open decode sessions ({}), retry later", MAX_DECODE_SESSIONS);
+        (StatusCode::SERVICE_UNAVAILABLE, Json(SessionResponse::failure(message, Vec::new())))
+    })?;
+    Ok(Json(SessionResponse {
+        success: true,
+        message: "Decode session created".to_string(),
+        session_id: Some(id),
+        status: Some(Box::new(status)),
+        ..Default::default()
+    }))
+}
+
+async fn handler_session_chunk(
+    State(sessions): State<Arc<DecodeSessions>>,
+    Path(id): Path<String>,
+    body: Result<Bytes, BytesRejection>,
+) -> ApiResult<SessionResponse> {
+    let chunk = body.map_err(|r| rejected(r.status(), r.body_text()))?;
+    let session = sessions.get(&id)?;
+    blocking(move || {
+        let mut session = session.lock().unwrap();
+        session.last_used = Instant::now();
+        if session.finished {
+            let message = "Decode session is finished; fetch its result".to_string();
+            return Err((StatusCode::CONFLICT, Json(SessionResponse::failure(message, Vec::new()))));
+        }
+        session.push_bytes(&chunk);
+        Ok(session_response(&id, &session, format!("Received {} bytes", chunk.len())))
+    })
+    .await
+}
+
+async fn handler_session_status(
+    State(sessions): State<Arc<DecodeSessions>>,
+    Path(id): Path<String>,
+) -> ApiResult<SessionResponse> {
+    let session = sessions.get(&id)?;
+    let mut session = session.lock().unwrap();
+    session.last_used = Instant::now();
+    Ok(session_response(&id, &session, "Decode session status".to_string()))
+}
+
+async fn handler_session_finish(
+    State(sessions): State<Arc<DecodeSessions>>,
+    Path(id): Path<String>,
+) -> ApiResult<SessionResponse> {
+    let session = sessions.get(&id)?;
+    blocking(move || {
+        let mut session = session.lock().unwrap();
+        session.last_used = Instant::now();
+        if !session.finished {
+            session.finish();
+        }
+        Ok(session_response(&id, &session, format!("Upload finished, {} frames decoded", session.frames.len())))
+    })
+    .await
+}
+
+/// Frames of a finished session; the session is closed once they are fetched
+async fn handler_session_result(
+    State(sessions): State<Arc<DecodeSessions>>,
+    Path(id): Path<String>,
+) -> ApiResult<SessionResponse> {
+    let session = sessions.get(&id)?;
+    let mut session = session.lock().unwrap();
+    session.last_used = Instant::now();
+    if !session.finished {
+        let message = "Decode session is still receiving; finish it first".to_string();
+        return Err((StatusCode::CONFLICT, Json(SessionResponse::failure(message, Vec::new()))));
+    }
+    let _ = sessions.remove(&id);
+
+    let status = session.status();
+    if session.frames.is_empty() {
+        let message = "Decoding failed: no frame found in the uploaded audio".to_string();
+        return Err((StatusCode::BAD_REQUEST, Json(SessionResponse { message, status: Some(Box::new(status)), ..Default::default() })));
+    }
+    let frames = session
+        .frames
+        .iter()
+        .map(|message| SessionFrame {
+            data: base64::engine::general_purpose::STANDARD.encode(&message.payload),
+            position_ms: (message.preamble_position * 1000 / SAMPLE_RATE) as u64,
+            snr_db: message.quality.snr_db,
+            content_type: message.content_type.map(|content_type| content_type.to_string()),
+        })
+        .collect();
+    Ok(Json(SessionResponse {
+        success: true,
+        message: format!("Decoded {} frames", session.frames.len()),
+        session_id: Some(id),
+        status: Some(Box::new(status)),
+        frames,
+        errors: Vec::new(),
+    }))
+}
+
+async fn handler_session_delete(
+    State(sessions): State<Arc<DecodeSessions>>,
+    Path(id): Path<String>,
+) -> ApiResult<SessionResponse> {
+    sessions.remove(&id)?;
+    Ok(Json(SessionResponse { success: true, message: "Decode session deleted".to_string(), ..Default::default() }))
+}
+
+/// WAV bytes and options of an upload: a raw body, or `wav` and `options` multipart parts
+///
+/// The body is read into memory whole before decoding; `--max-body-bytes` bounds it.
+async fn read_upload(request: Request) -> Result<(Bytes, DecodeRequest), (StatusCode, Json<DecodeResponse>)> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+    if !is_multipart {
+        let body = Bytes::from_request(request, &()).await.map_err(|r| rejected(r.status(), r.body_text()))?;
+        return Ok((body, DecodeRequest::default()));
+    }
+
+    let mut multipart = Multipart::from_request(request, &()).await.map_err(|r| rejected(r.status(), r.body_text()))?;
+    let mut wav = None;
+    let mut options = DecodeRequest::default();
+    while let Some(field) = multipart.next_field().await.map_err(|e| rejected(e.status(), e.body_text()))? {
+        let name = field.name().unwrap_or_default().to_string();
+        let bytes = field.bytes().await.map_err(|e| rejected(e.status(), e.body_text()))?;
+        match name.as_str() {
+            "wav" => wav = Some(bytes),
+            "options" => {
+                options = serde_json::from_slice(&bytes)
+                    .map_err(|e| invalid(vec![FieldError::new("options", e.to_string())]))?;
+            }
+            _ => return Err(invalid(vec![FieldError::new(&name, "unknown part; expected `wav` or `options`")])),
+        }
+    }
+    let wav = wav.ok_or_else(|| invalid(vec![FieldError::new("wav", "required")]))?;
+    Ok((wav, options))
+}
+
+/// Validate the decode options in `req` and decode `wav_data` (read from request field `wav_field`)
+async fn decode_wav(
+    wav_data: &[u8],
+    wav_field: &str,
+    req: &DecodeRequest,
+    mut errors: Vec<FieldError>,
+) -> ApiResult<DecodeResponse> {
+    check_unit_range("preamble_threshold", req.preamble_threshold, &mut errors);
+    check_unit_range("postamble_threshold", req.postamble_threshold, &mut errors);
+    let key = network_key(req.network_key.as_deref(), &mut errors);
+    let fountain = req.fountain.as_ref().map(|options| {
+        for (field, set) in [("fountain.repair_ratio", options.repair_ratio.is_some()), ("fountain.code", options.code.is_some())] {
+            if set {
+                errors.push(FieldError::new(field, "encode only; decoders detect it from the blocks"));
+            }
+        }
+        fountain_config(options, MAX_FOUNTAIN_DECODE_SECS, &mut errors)
+    });
+    if !errors.is_empty() {
+        return Err(invalid(errors));
+    }
+
+    let wav_error = |e| invalid(vec![FieldError::new(wav_field, format!("failed to read WAV: {}", e))]);
+    let mut decoder = DecoderFsk::new().map_err(server_error)?;
+    if let Some(threshold) = req.preamble_threshold {
+        decoder.set_preamble_threshold(DetectionThreshold::Fixed(threshold));
+    }
+    if let Some(threshold) = req.postamble_threshold {
+        decoder.set_postamble_threshold(DetectionThreshold::Fixed(threshold));
+    }
+    decoder.set_network_key(key);
+
+    // Any supported WAV format is converted to mono at the modem sample rate;
+    // fountain decodes convert it window by window instead of all at once
+    let decoded = match fountain {
+        Some(config) => {
+            let mut reader = WavStreamReader::new(std::io::Cursor::new(wav_data)).map_err(wav_error)?;
+            decoder.decode_fountain_reader(&mut reader, Some(config))
+        }
+        None => {
+            let (samples, _) = audio_io::read_wav_to_modem_samples(std::io::Cursor::new(wav_data)).map_err(wav_error)?;
+            decoder.decode_with_metadata(&samples).map(|message| (message.payload, message.quality))
+        }
+    };
+    match decoded {
+        Ok((payload, quality)) => Ok(Json(DecodeResponse {
+            success: true,
+            message: format!("Decoded {} bytes", payload.len()),
+            data: Some(base64::engine::general_purpose::STANDARD.encode(&payload)),
+            stats: Some(DecodeReport::new(&decoder.stats, Some(&quality))),
+            errors: Vec::new(),
+        })),
+        Err(e) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(DecodeResponse {
+                message: format!("Decoding failed: {}", e),
+                stats: Some(DecodeReport::new(&decoder.stats, None)),
+                ..Default::default()
+            }),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{encode_fsk_command, encoder_from_args, Cli, Commands};
+    use clap::Parser;
+    use serde_json::json;
+
+    /// WAV written by `transmitwave encode INPUT OUTPUT <options>` for `data`
+    fn cli_wav(name: &str, data: &[u8], options: &[&str]) -> Vec<u8> {
+        let dir = std::env::temp_dir().join(format!("transmitwave-golden-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (input, output) = (dir.join("input.bin"), dir.join("output.wav"));
+        std::fs::write(&input, data).unwrap();
+
+        let mut argv = vec!["transmitwave", "encode", input.to_str().unwrap(), output.to_str().unwrap()];
+        argv.extend_from_slice(options);
+        let Some(Commands::Encode(args)) = Cli::try_parse_from(argv).unwrap().command else {
+            panic!("not an encode command");
+        };
+        encode_fsk_command(&args.input, &args.output, encoder_from_args(&args).unwrap(), None, None).unwrap();
+        let wav = std::fs::read(&output).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        wav
+    }
+
+    /// WAV returned by `POST /encode` with the fields of `request` for `data`
+    async fn server_wav(data: &[u8], mut request: serde_json::Value) -> Vec<u8> {
+        request["data"] = base64::engine::general_purpose::STANDARD.encode(data).into();
+        let request: EncodeRequest = serde_json::from_value(request).unwrap();
+        let Ok(Json(response)) = handler_encode(Ok(Json(request))).await else {
+            panic!("/encode rejected the request");
+        };
+        base64::engine::general_purpose::STANDARD.decode(response.wav_base64.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_rate_limiter_caps_tracked_clients() {
+        let limiter = RateLimiter { per_minute: 1, max_clients: 3, clients: Mutex::default() };
+        let ip = |last: u8| IpAddr::from([10, 0, 0, last]);
+        // Every client uses its only token, so none is idle
+        for last in 0..10 {
+            assert!(limiter.allow(ip(last)));
+            assert!(limiter.clients.lock().unwrap().len() <= 3);
+        }
+        let clients = limiter.clients.lock().unwrap();
+        assert!(clients.contains_key(&ip(9)) && !clients.contains_key(&ip(0)));
+        drop(clients);
+        assert!(!limiter.allow(ip(9)));
+    }
+
+    #[tokio::test]
+    async fn test_cli_and_server_encode_identical_wavs() {
+        let data: Vec<u8> = (0..48).map(|i| (i * 37 + 11) as u8).collect();
+        let cases: [(&str, &[&str], serde_json::Value); 10] = [
+            ("default", &[], json!({})),
+            ("short", &["--short"], json!({"profile": "short"})),
+            ("telephony", &["--telephony"], json!({"profile": "telephony"})),
+            ("codec-robust", &["--codec-robust"], json!({"profile": "codec-robust"})),
+            ("narrowband", &["--narrowband"], json!({"profile": "narrowband"})),
+            ("rs", &["--rs", "255,191"], json!({"rs": "255,191"})),
+            ("tone-layout", &["--tone-layout", "2,16"], json!({"tone_layout": "2,16"})),
+            ("amplitude-bits", &["--amplitude-bits"], json!({"amplitude_bits": true})),
+            ("dual-band", &["--dual-band"], json!({"dual_band": true})),
+            (
+                "keyed-gaps",
+                &["--network-key", "site-7", "--symbol-gaps", "8"],
+                json!({"network_key": "site-7", "symbol_gaps": 8}),
+            ),
+        ];
+        for (name, options, request) in cases {
+            let cli = cli_wav(name, &data, options);
+            assert!(cli.len() > 44, "{}: empty WAV", name);
+            assert!(cli == server_wav(&data, request).await, "{}: CLI and /encode WAVs differ", name);
+        }
+    }
+
+    /// HTTP status of a handler result
+    fn status_code<T>(result: &ApiResult<T>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err((status, _)) => *status,
+        }
+    }
+
+    async fn create_session(sessions: &Arc<DecodeSessions>, request: serde_json::Value) -> ApiResult<SessionResponse> {
+        let request: SessionRequest = serde_json::from_value(request).unwrap();
+        handler_session_create(State(Arc::clone(sessions)), Ok(Json(request))).await
+    }
+
+    async fn push_chunk(sessions: &Arc<DecodeSessions>, id: &str, chunk: &[u8]) -> ApiResult<SessionResponse> {
+        handler_session_chunk(State(Arc::clone(sessions)), Path(id.to_string()), Ok(Bytes::copy_from_slice(chunk))).await
+    }
+
+    #[tokio::test]
+    async fn test_decode_session_lifecycle() {
+        let sessions = Arc::new(DecodeSessions::default());
+        let samples = EncoderFsk::new().unwrap().encode(b"uploaded in chunks").unwrap();
+
+        // 48 kHz s16le in chunks of an odd byte count, so samples straddle chunks
+        let Ok(Json(created)) = create_session(&sessions, json!({"sample_rate": 48000})).await else {
+            panic!("session not created");
+        };
+        let id = created.session_id.unwrap();
+        let pcm: Vec<u8> = transmitwave_core::resample_audio(&samples, SAMPLE_RATE, 48_000)
+            .iter()
+            .flat_map(|&sample| ((sample * 32767.0) as i16).to_le_bytes())
+            .collect();
+        for chunk in pcm.chunks(1001) {
+            assert_eq!(status_code(&push_chunk(&sessions, &id, chunk).await), StatusCode::OK);
+        }
+        assert!(sessions.sessions.lock().unwrap()[&id].lock().unwrap().partial.is_empty());
+
+        let Ok(Json(progress)) = handler_session_status(State(Arc::clone(&sessions)), Path(id.clone())).await else {
+            panic!("no session status");
+        };
+        let progress = progress.status.unwrap();
+        assert_eq!(progress.state, "receiving");
+        assert_eq!(progress.frames_decoded, 1);
+        assert!(progress.received_ms.abs_diff((samples.len() * 1000 / SAMPLE_RATE) as u64) < 50);
+
+        // The result waits for finish, and chunks stop after it
+        let early = handler_session_result(State(Arc::clone(&sessions)), Path(id.clone())).await;
+        assert_eq!(status_code(&early), StatusCode::CONFLICT);
+        let finished = handler_session_finish(State(Arc::clone(&sessions)), Path(id.clone())).await;
+        assert_eq!(status_code(&finished), StatusCode::OK);
+        assert_eq!(status_code(&push_chunk(&sessions, &id, &[0, 0]).await), StatusCode::CONFLICT);
+
+        let Ok(Json(result)) = handler_session_result(State(Arc::clone(&sessions)), Path(id.clone())).await else {
+            panic!("no session result");
+        };
+        assert_eq!(result.frames.len(), 1);
+        assert_eq!(base64::engine::general_purpose::STANDARD.decode(&result.frames[0].data).unwrap(), b"uploaded in chunks");
+
+        // Fetching the result closes the session
+        let gone = handler_session_status(State(Arc::clone(&sessions)), Path(id.clone())).await;
+        assert_eq!(status_code(&gone), StatusCode::NOT_FOUND);
+        assert_eq!(status_code(&push_chunk(&sessions, "no-such-session", &[0, 0]).await), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_decode_session_limits() {
+        let sessions = Arc::new(DecodeSessions::default());
+        let invalid = create_session(&sessions, json!({"sample_rate": 4000, "sample_format": "u8"})).await;
+        let Err((status, Json(rejection))) = invalid else {
+            panic!("invalid session options accepted");
+        };
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(rejection.errors.len(), 2);
+
+        // Deleted sessions are gone
+        let Ok(Json(created)) = create_session(&sessions, json!({})).await else {
+            panic!("session not created");
+        };
+        let id = created.session_id.unwrap();
+        let deleted = handler_session_delete(State(Arc::clone(&sessions)), Path(id.clone())).await;
+        assert_eq!(status_code(&deleted), StatusCode::OK);
+        let deleted = handler_session_delete(State(Arc::clone(&sessions)), Path(id)).await;
+        assert_eq!(status_code(&deleted), StatusCode::NOT_FOUND);
+
+        // Full until an idle session expires
+        let mut ids = Vec::new();
+        for _ in 0..MAX_DECODE_SESSIONS {
+            let Ok(Json(created)) = create_session(&sessions, json!({"sample_format": "f32le"})).await else {
+                panic!("session not created");
+            };
+            ids.push(created.session_id.unwrap());
+        }
+        assert_eq!(status_code(&create_session(&sessions, json!({})).await), StatusCode::SERVICE_UNAVAILABLE);
+
+        let idle = Instant::now().checked_sub(DECODE_SESSION_IDLE_TIMEOUT + Duration::from_secs(1)).unwrap();
+        sessions.sessions.lock().unwrap()[&ids[0]].lock().unwrap().last_used = idle;
+        sessions.sweep();
+        assert_eq!(status_code(&push_chunk(&sessions, &ids[0], &[0; 4]).await), StatusCode::NOT_FOUND);
+        assert_eq!(status_code(&push_chunk(&sessions, &ids[1], &[0; 4]).await), StatusCode::OK);
+        assert_eq!(status_code(&create_session(&sessions, json!({})).await), StatusCode::OK);
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use transmitwave_core::fsk::{bin_to_freq, FSK_NUM_BINS};
+use transmitwave_core::{
+    detect_postamble, detect_preamble, DetectionThreshold, FskDemodulator, FSK_SYMBOL_SAMPLES,
+    PREAMBLE_SAMPLES, SYNC_SILENCE_SAMPLES,
+};
+
+/// Analysis window: a quarter symbol gives ~21 Hz resolution, matching the 20 Hz tone spacing
+pub const SPECTROGRAM_WINDOW: usize = FSK_SYMBOL_SAMPLES / 4;
+pub const SPECTROGRAM_HOP: usize = SPECTROGRAM_WINDOW / 2;
+
+/// Dynamic range shown below the loudest bin
+const DYNAMIC_RANGE_DB: f32 = 60.0;
+
+/// Pixels per FSK bin in the PNG output
+const PNG_BIN_HEIGHT: usize = 4;
+
+/// Power per FSK bin (dB) for each analysis window
+pub struct Spectrogram {
+    pub hop: usize,
+    pub columns: Vec<Vec<f32>>,
+}
+
+/// Sync positions (in samples) detected in the recording
+#[derive(Default)]
+pub struct Markers {
+    pub preamble: Option<usize>,
+    pub postamble: Option<usize>,
+    pub symbol_boundaries: Vec<usize>,
+}
+
+pub fn compute(samples: &[f32], window: usize, hop: usize) -> Spectrogram {
+    let demodulator = FskDemodulator::new();
+    let mut columns = Vec::new();
+    let mut start = 0;
+    while start + window <= samples.len() {
+        let powers = demodulator.bin_powers(&samples[start..start + window]);
+        columns.push(powers.iter().map(|p| 10.0 * (p + 1e-12).log10()).collect());
+        start += hop;
+    }
+    Spectrogram { hop, columns }
+}
+
+pub fn find_markers(samples: &[f32]) -> Markers {
+    let mut markers = Markers::default();
+    let Some(preamble) = detect_preamble(samples, DetectionThreshold::Adaptive) else {
+        return markers;
+    };
+    markers.preamble = Some(preamble);
+
+    let data_start = preamble + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+    if data_start >= samples.len() {
+        return markers;
+    }
+
+    markers.postamble = detect_postamble(&samples[data_start..], DetectionThreshold::Adaptive)
+        .map(|pos| data_start + pos);
+    let data_end = markers.postamble.unwrap_or(samples.len());
+
+    let mut boundary = data_start;
+    while boundary <= data_end {
+        markers.symbol_boundaries.push(boundary);
+        boundary += FSK_SYMBOL_SAMPLES;
+    }
+    markers
+}
+
+fn db_range(spectrogram: &Spectrogram) -> (f32, f32) {
+    let max_db = spectrogram
+        .columns
+        .iter()
+        .flatten()
+        .fold(f32::NEG_INFINITY, |acc, &v| acc.max(v));
+    (max_db - DYNAMIC_RANGE_DB, max_db)
+}
+
+fn normalize(value: f32, (min_db, max_db): (f32, f32)) -> f32 {
+    ((value - min_db) / (max_db - min_db)).clamp(0.0, 1.0)
+}
+
+/// Black → purple → orange → yellow heat map
+fn heat_color(level: f32) -> [u8; 3] {
+    const STOPS: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [120.0, 28.0, 109.0],
+        [237.0, 105.0, 37.0],
+        [252.0, 255.0, 164.0],
+    ];
+    let scaled = level * (STOPS.len() - 1) as f32;
+    let idx = (scaled as usize).min(STOPS.len() - 2);
+    let t = scaled - idx as f32;
+    let mut rgb = [0u8; 3];
+    for (c, out) in rgb.iter_mut().enumerate() {
+        *out = (STOPS[idx][c] + (STOPS[idx + 1][c] - STOPS[idx][c]) * t) as u8;
+    }
+    rgb
+}
+
+pub fn render_png(
+    spectrogram: &Spectrogram,
+    markers: &Markers,
+    path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let width = spectrogram.columns.len().max(1);
+    let height = FSK_NUM_BINS * PNG_BIN_HEIGHT;
+    let range = db_range(spectrogram);
+
+    let mut pixels = vec![0u8; width * height * 3];
+    let mut put = |x: usize, y: usize, rgb: [u8; 3]| {
+        let idx = (y * width + x) * 3;
+        pixels[idx..idx + 3].copy_from_slice(&rgb);
+    };
+
+    for (x, column) in spectrogram.columns.iter().enumerate() {
+        for (bin, &value) in column.iter().enumerate() {
+            let rgb = heat_color(normalize(value, range));
+            // Low frequencies at the bottom
+            let top = (FSK_NUM_BINS - 1 - bin) * PNG_BIN_HEIGHT;
+            for y in top..top + PNG_BIN_HEIGHT {
+                put(x, y, rgb);
+            }
+        }
+    }
+
+    let column_of = |sample: usize| (sample / spectrogram.hop).min(width - 1);
+    for &boundary in &markers.symbol_boundaries {
+        let x = column_of(boundary);
+        for y in (0..height).step_by(4) {
+            put(x, y, [0, 200, 255]);
+        }
+    }
+    if let Some(preamble) = markers.preamble {
+        for x in [column_of(preamble), column_of(preamble + PREAMBLE_SAMPLES)] {
+            for y in 0..height {
+                put(x, y, [0, 255, 0]);
+            }
+        }
+    }
+    if let Some(postamble) = markers.postamble {
+        for y in 0..height {
+            put(column_of(postamble), y, [255, 0, 0]);
+        }
+    }
+
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
+
+/// Render a terminal heat map: one row per band of 4 bins, plus a marker row
+/// (P/p = preamble start/end, E = postamble, | = symbol boundary)
+pub fn render_ascii(spectrogram: &Spectrogram, markers: &Markers, width: usize) -> String {
+    const SHADES: &[u8] = b" .:-=+*#%@";
+    const BINS_PER_ROW: usize = 4;
+
+    let columns = spectrogram.columns.len();
+    let width = width.max(1).min(columns.max(1));
+    let range = db_range(spectrogram);
+    let mut out = String::new();
+
+    for row in (0..FSK_NUM_BINS / BINS_PER_ROW).rev() {
+        let freq = bin_to_freq(row * BINS_PER_ROW);
+        out.push_str(&format!("{:>5.0} Hz |", freq));
+        for x in 0..width {
+            let start = x * columns / width;
+            let end = ((x + 1) * columns / width).max(start + 1).min(columns);
+            let level = spectrogram.columns[start..end]
+                .iter()
+                .flat_map(|column| &column[row * BINS_PER_ROW..(row + 1) * BINS_PER_ROW])
+                .fold(0.0f32, |acc, &v| acc.max(normalize(v, range)));
+            let shade = ((level * (SHADES.len() - 1) as f32).round() as usize).min(SHADES.len() - 1);
+            out.push(SHADES[shade] as char);
+        }
+        out.push('\n');
+    }
+
+    let mut marker_row = vec![' '; width];
+    let samples_per_char = (columns * spectrogram.hop).max(1) as f32 / width as f32;
+    let mut mark = |sample: usize, c: char| {
+        let x = ((sample as f32 / samples_per_char) as usize).min(width - 1);
+        marker_row[x] = c;
+    };
+    for &boundary in &markers.symbol_boundaries {
+        mark(boundary, '|');
+    }
+    if let Some(preamble) = markers.preamble {
+        mark(preamble, 'P');
+        mark(preamble + PREAMBLE_SAMPLES, 'p');
+    }
+    if let Some(postamble) = markers.postamble {
+        mark(postamble, 'E');
+    }
+    out.push_str("         |");
+    out.extend(marker_row);
+    out.push('\n');
+    out
+}
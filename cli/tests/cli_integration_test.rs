@@ -195,3 +195,521 @@ fn test_different_input_sizes_encode_successfully() {
     assert!(size_small < 500_000, "Small output too large: {} bytes", size_small);
     assert!(size_medium < 500_000, "Medium output too large: {} bytes", size_medium);
 }
+
+#[test]
+fn test_spectrogram_png_and_ascii() {
+    let input = create_test_file("test_spectrogram_input.txt", "Spectrogram");
+    let encoded = PathBuf::from("tmp/test_spectrogram.wav");
+    let png = PathBuf::from("tmp/test_spectrogram.png");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), encoded.to_str().unwrap()]);
+
+    let output_text = run_transmitwave(&[
+        "spectrogram",
+        encoded.to_str().unwrap(),
+        "--output",
+        png.to_str().unwrap(),
+    ]);
+    assert!(output_text.contains("Preamble at sample"), "Unexpected output: {}", output_text);
+    assert!(output_text.contains("Postamble at sample"), "Unexpected output: {}", output_text);
+
+    let png_bytes = fs::read(&png).expect("PNG was not created");
+    assert_eq!(&png_bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+    let ascii = run_transmitwave(&["spectrogram", encoded.to_str().unwrap(), "--width", "60"]);
+    assert!(ascii.contains(" Hz |"), "Expected heat map rows but got: {}", ascii);
+    assert!(ascii.contains('P') && ascii.contains('E'), "Expected sync markers: {}", ascii);
+}
+
+#[test]
+fn test_inspect_frame() {
+    let input = create_test_file("test_inspect_input.txt", "Inspect me");
+    let encoded = PathBuf::from("tmp/test_inspect.wav");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), encoded.to_str().unwrap()]);
+
+    let report = run_transmitwave(&["inspect-frame", encoded.to_str().unwrap()]);
+    assert!(report.contains("preamble     sample"), "Unexpected output: {}", report);
+    assert!(report.contains("block 0"), "Expected an RS block table: {}", report);
+    assert!(report.contains("payload_crc"), "Expected frame fields: {}", report);
+    assert!(report.contains("result       decoded"), "Unexpected output: {}", report);
+}
+
+#[test]
+fn test_encode_with_leading_silence_and_fade_in() {
+    let input = create_test_file("test_padding_input.bin", "Padded");
+    let default_wav = PathBuf::from("tmp/test_padding_default.wav");
+    let padded_wav = PathBuf::from("tmp/test_padding_padded.wav");
+    let decoded = PathBuf::from("tmp/test_padding_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), default_wav.to_str().unwrap()]);
+    let output_text = run_transmitwave(&[
+        "encode",
+        input.to_str().unwrap(),
+        padded_wav.to_str().unwrap(),
+        "--leading-silence-ms",
+        "300",
+        "--fade-in-ms",
+        "250",
+    ]);
+    assert!(output_text.contains("Encoded"), "Padded encode failed: {}", output_text);
+
+    // 175 ms more silence + 250 ms ramp at 16 kHz, 16-bit mono
+    let default_size = fs::metadata(&default_wav).unwrap().len();
+    let padded_size = fs::metadata(&padded_wav).unwrap().len();
+    assert_eq!(padded_size - default_size, (2800 + 4000) * 2);
+
+    run_transmitwave(&["decode", padded_wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Padded");
+}
+
+#[test]
+fn test_transfer_resume_across_sessions() {
+    let message = "Chunked transfer across several short recordings";
+    let input = create_test_file("test_transfer_input.bin", message);
+    let parts_dir = PathBuf::from("tmp/test_transfer_parts");
+    let state = PathBuf::from("tmp/test_transfer.state");
+    let token = PathBuf::from("tmp/test_transfer.token");
+    let decoded = PathBuf::from("tmp/test_transfer_decoded.bin");
+    fs::remove_dir_all(&parts_dir).ok();
+    fs::remove_file(&state).ok();
+    fs::remove_file(&decoded).ok();
+
+    let encode_text = run_transmitwave(&[
+        "transfer-encode",
+        input.to_str().unwrap(),
+        parts_dir.to_str().unwrap(),
+        "--part-size",
+        "20",
+    ]);
+    assert!(encode_text.contains("3 parts"), "Unexpected output: {}", encode_text);
+    let part = |i: usize| parts_dir.join(format!("part_{:05}.wav", i));
+
+    // First session only catches the middle part
+    let first = run_transmitwave(&[
+        "transfer-decode",
+        state.to_str().unwrap(),
+        part(1).to_str().unwrap(),
+        "--token",
+        token.to_str().unwrap(),
+    ]);
+    assert!(first.contains("Missing parts: 0,2"), "Unexpected output: {}", first);
+
+    // Sender replays only the missing parts
+    fs::remove_dir_all(&parts_dir).ok();
+    run_transmitwave(&[
+        "transfer-encode",
+        input.to_str().unwrap(),
+        parts_dir.to_str().unwrap(),
+        "--part-size",
+        "20",
+        "--resume",
+        token.to_str().unwrap(),
+    ]);
+    assert!(!part(1).exists(), "Received part should not be re-encoded");
+
+    let second = run_transmitwave(&[
+        "transfer-decode",
+        state.to_str().unwrap(),
+        part(0).to_str().unwrap(),
+        part(2).to_str().unwrap(),
+        "--output",
+        decoded.to_str().unwrap(),
+    ]);
+    assert!(second.contains("Transfer complete"), "Unexpected output: {}", second);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), message);
+}
+
+#[test]
+fn test_stereo_redundant_encode_decode() {
+    let input = create_test_file("test_stereo_input.bin", "Stereo copy");
+
+    for (name, option, value) in [("delay", "--stereo-delay-ms", "10"), ("offset", "--stereo-offset-hz", "45")] {
+        let wav = PathBuf::from(format!("tmp/test_stereo_{}.wav", name));
+        let decoded = PathBuf::from(format!("tmp/test_stereo_{}_decoded.bin", name));
+
+        run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), option, value]);
+        // Channel count lives at byte 22 of the canonical WAV header
+        let bytes = fs::read(&wav).expect("Stereo WAV was not created");
+        assert_eq!(u16::from_le_bytes([bytes[22], bytes[23]]), 2);
+
+        let output_text = run_transmitwave(&[
+            "decode",
+            wav.to_str().unwrap(),
+            decoded.to_str().unwrap(),
+            option,
+            value,
+        ]);
+        assert!(output_text.contains("Combining stereo channels"), "Unexpected output: {}", output_text);
+        assert_eq!(fs::read_to_string(&decoded).unwrap(), "Stereo copy");
+    }
+}
+
+#[test]
+fn test_network_key_encode_decode() {
+    let input = create_test_file("test_network_key_input.bin", "Keyed");
+    let wav = PathBuf::from("tmp/test_network_key.wav");
+    let unkeyed = PathBuf::from("tmp/test_network_key_unkeyed.bin");
+    let decoded = PathBuf::from("tmp/test_network_key_decoded.bin");
+    fs::remove_file(&unkeyed).ok();
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--network-key", "site-a"]);
+
+    // A decoder without the key does not find the frame
+    run_transmitwave(&["decode", wav.to_str().unwrap(), unkeyed.to_str().unwrap()]);
+    assert!(!unkeyed.exists(), "Frame decoded without the network key");
+
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap(), "--network-key", "site-a"]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Keyed");
+}
+
+#[test]
+fn test_short_frame_encode_decode() {
+    let input = create_test_file("test_short_frame_input.bin", "Lights off");
+    let wav = PathBuf::from("tmp/test_short_frame.wav");
+    let decoded = PathBuf::from("tmp/test_short_frame_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--short"]);
+    // 16-bit mono at 16 kHz: well under a second of audio
+    let wav_len = fs::metadata(&wav).expect("Encoded WAV was not created").len();
+    assert!(wav_len < 44 + 16000 * 2, "Short frame WAV is {} bytes", wav_len);
+
+    // No flag needed to decode: the short preamble is detected
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Lights off");
+}
+
+#[test]
+fn test_amplitude_bits_encode_decode() {
+    let text = "Amplitude-shaped symbols carry thirty bits each. ".repeat(4);
+    let input = create_test_file("test_amplitude_bits_input.bin", &text);
+    let plain = PathBuf::from("tmp/test_amplitude_bits_plain.wav");
+    let shaped = PathBuf::from("tmp/test_amplitude_bits_shaped.wav");
+    let decoded = PathBuf::from("tmp/test_amplitude_bits_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), plain.to_str().unwrap()]);
+    run_transmitwave(&["encode", input.to_str().unwrap(), shaped.to_str().unwrap(), "--amplitude-bits"]);
+    assert!(fs::metadata(&shaped).unwrap().len() < fs::metadata(&plain).unwrap().len());
+
+    let output = run_transmitwave(&["decode", shaped.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert!(output.contains("pilot contrast"), "Unexpected output: {}", output);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), text);
+}
+
+#[test]
+fn test_tone_layout_encode_decode() {
+    let input = create_test_file("test_tone_layout_input.bin", "Two tones per symbol");
+    let wav = PathBuf::from("tmp/test_tone_layout.wav");
+    let decoded = PathBuf::from("tmp/test_tone_layout_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--tone-layout", "2,16"]);
+    // The layout is announced in the frame, so decode needs no flag
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Two tones per symbol");
+}
+
+#[test]
+fn test_telephony_encode_decode() {
+    let input = create_test_file("test_telephony_input.bin", "Call back at 5");
+    let wav = PathBuf::from("tmp/test_telephony.wav");
+    let decoded = PathBuf::from("tmp/test_telephony_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--telephony"]);
+    // The telephony preamble is detected, so decode needs no flag
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Call back at 5");
+}
+
+#[test]
+fn test_codec_robust_encode_decode() {
+    let input = create_test_file("test_codec_robust_input.bin", "Over the speaker");
+    let wav = PathBuf::from("tmp/test_codec_robust.wav");
+    let decoded = PathBuf::from("tmp/test_codec_robust_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--codec-robust"]);
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Over the speaker");
+}
+
+#[test]
+fn test_symbol_gaps_encode_decode() {
+    let input = create_test_file("test_symbol_gaps_input.bin", "Gaps for the AGC");
+    let wav = PathBuf::from("tmp/test_symbol_gaps.wav");
+    let decoded = PathBuf::from("tmp/test_symbol_gaps_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--symbol-gaps", "4"]);
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Gaps for the AGC");
+}
+
+#[test]
+fn test_dual_band_encode_decode() {
+    let input = create_test_file("test_dual_band_input.bin", "Both halves of the band");
+    let wav = PathBuf::from("tmp/test_dual_band.wav");
+    let decoded = PathBuf::from("tmp/test_dual_band_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap(), "--dual-band"]);
+    run_transmitwave(&["decode", wav.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Both halves of the band");
+}
+
+#[test]
+fn test_trim_long_recording() {
+    let input = create_test_file("test_trim_input.bin", "Trim me");
+    let frame = PathBuf::from("tmp/test_trim_frame.wav");
+    let long = PathBuf::from("tmp/test_trim_long.wav");
+    let trimmed = PathBuf::from("tmp/test_trim_trimmed.wav");
+    let decoded = PathBuf::from("tmp/test_trim_decoded.bin");
+
+    run_transmitwave(&["encode", input.to_str().unwrap(), frame.to_str().unwrap()]);
+    // Bury the frame in 30 s of silence on each side
+    let bytes = fs::read(&frame).expect("Encoded WAV was not created");
+    let data = &bytes[44..];
+    let silence = vec![0u8; 30 * 16000 * 2];
+    let mut body = silence.clone();
+    body.extend_from_slice(data);
+    body.extend_from_slice(&silence);
+    let mut wav = bytes[..44].to_vec();
+    wav[4..8].copy_from_slice(&(36 + body.len() as u32).to_le_bytes());
+    wav[40..44].copy_from_slice(&(body.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&body);
+    fs::write(&long, wav).unwrap();
+
+    let output_text = run_transmitwave(&["trim", long.to_str().unwrap(), trimmed.to_str().unwrap(), "--margin-before-ms", "50"]);
+    assert!(output_text.contains("Preamble at 30."), "Unexpected output: {}", output_text);
+    let trimmed_len = fs::metadata(&trimmed).expect("Trimmed WAV was not created").len() as usize;
+    assert!(trimmed_len < data.len() + 44, "Trimmed file too large: {} bytes", trimmed_len);
+
+    run_transmitwave(&["decode", trimmed.to_str().unwrap(), decoded.to_str().unwrap()]);
+    assert_eq!(fs::read_to_string(&decoded).unwrap(), "Trim me");
+}
+
+#[test]
+fn test_decode_batch_directory() {
+    let input_dir = PathBuf::from("tmp/test_batch_in");
+    let output_dir = PathBuf::from("tmp/test_batch_out");
+    fs::remove_dir_all(&input_dir).ok();
+    fs::remove_dir_all(&output_dir).ok();
+    fs::create_dir_all(&input_dir).unwrap();
+
+    for (name, text) in [("first", "Batch one"), ("second", "Batch two")] {
+        let input = create_test_file(&format!("test_batch_{}.bin", name), text);
+        let wav = input_dir.join(format!("{}.wav", name));
+        run_transmitwave(&["encode", input.to_str().unwrap(), wav.to_str().unwrap()]);
+    }
+    // Not a valid recording: reported as a failure, not fatal
+    fs::write(input_dir.join("broken.WAV"), b"not a wav").unwrap();
+    fs::write(input_dir.join("notes.txt"), b"ignored").unwrap();
+
+    let output_text = run_transmitwave(&["decode-batch", input_dir.to_str().unwrap(), output_dir.to_str().unwrap(), "--jobs", "2"]);
+    assert!(output_text.contains("Decoded 2/3 recordings (1 failed)"), "Unexpected output: {}", output_text);
+
+    assert_eq!(fs::read_to_string(output_dir.join("first.bin")).unwrap(), "Batch one");
+    assert_eq!(fs::read_to_string(output_dir.join("second.bin")).unwrap(), "Batch two");
+    let report = fs::read_to_string(output_dir.join("report.json")).expect("Report was not written");
+    assert!(report.contains("\"succeeded\": 2") && report.contains("broken.WAV"), "{}", report);
+}
+
+#[test]
+fn test_encode_batch_directory() {
+    let input_dir = PathBuf::from("tmp/test_encode_batch_in");
+    let frames_dir = PathBuf::from("tmp/test_encode_batch_frames");
+    let output_dir = PathBuf::from("tmp/test_encode_batch_out");
+    for dir in [&input_dir, &frames_dir, &output_dir] {
+        fs::remove_dir_all(dir).ok();
+    }
+    fs::create_dir_all(&input_dir).unwrap();
+
+    for i in 0..5 {
+        fs::write(input_dir.join(format!("coupon{}.txt", i)), format!("COUPON-{:04}", i * 37)).unwrap();
+    }
+    // Too large for one frame: reported as a failure, not fatal
+    fs::write(input_dir.join("oversized.bin"), vec![0u8; 2000]).unwrap();
+
+    let output_text = run_transmitwave(&["encode-batch", input_dir.to_str().unwrap(), frames_dir.to_str().unwrap(), "--jobs", "2"]);
+    assert!(output_text.contains("Encoded 5/6 payloads (1 failed)"), "Unexpected output: {}", output_text);
+    let report = fs::read_to_string(frames_dir.join("report.json")).expect("Report was not written");
+    assert!(report.contains("\"succeeded\": 5") && report.contains("oversized.bin"), "{}", report);
+
+    let output_text = run_transmitwave(&["decode-batch", frames_dir.to_str().unwrap(), output_dir.to_str().unwrap()]);
+    assert!(output_text.contains("Decoded 5/5 recordings"), "Unexpected output: {}", output_text);
+    assert_eq!(fs::read_to_string(output_dir.join("coupon3.bin")).unwrap(), "COUPON-0111");
+}
+
+#[test]
+fn test_fountain_lt_roundtrip() {
+    let input = create_test_file("test_fountain_lt.txt", "LT coded fountain");
+    let encoded = PathBuf::from("tmp/test_fountain_lt.wav");
+    let output = PathBuf::from("tmp/test_fountain_lt.bin");
+
+    let encode_text = run_transmitwave(&[
+        "fountain-encode",
+        input.to_str().unwrap(),
+        encoded.to_str().unwrap(),
+        "--timeout",
+        "12",
+        "--block-size",
+        "16",
+        "--lt",
+    ]);
+    assert!(encode_text.contains("code=LT"), "LT not selected: {}", encode_text);
+
+    // The decoder detects the LT code from the blocks
+    let decode_text = run_transmitwave(&[
+        "fountain-decode",
+        encoded.to_str().unwrap(),
+        output.to_str().unwrap(),
+        "--block-size",
+        "16",
+    ]);
+    assert_eq!(
+        fs::read(&output).unwrap_or_default(),
+        b"LT coded fountain",
+        "Decode failed: {}",
+        decode_text
+    );
+}
+
+/// `transmitwave server` child process, killed on drop
+struct TestServer {
+    port: u16,
+    child: std::process::Child,
+}
+
+impl TestServer {
+    fn start(port: u16, extra_args: &[&str]) -> Self {
+        let port_arg = port.to_string();
+        let mut args = vec!["server", "--port", &port_arg];
+        args.extend_from_slice(extra_args);
+        let child = Command::new(get_target_dir())
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .expect("Failed to start server");
+        let server = TestServer { port, child };
+        let ready = (0..100).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            std::net::TcpStream::connect(("127.0.0.1", port)).is_ok()
+        });
+        assert!(ready, "Server did not start");
+        server
+    }
+
+    /// POST `body` and return the status code and response body
+    fn post(&self, path: &str, content_type: &str, body: &[u8]) -> (u16, String) {
+        use std::io::{Read, Write};
+
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", self.port)).expect("Server not reachable");
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            path,
+            content_type,
+            body.len()
+        )
+        .unwrap();
+        // The server may answer (e.g. 413) before the whole body is sent
+        stream.write_all(body).ok();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).ok();
+
+        let response = String::from_utf8_lossy(&response).to_string();
+        let status = response[9..12].parse().unwrap();
+        (status, response[response.find("\r\n\r\n").unwrap() + 4..].to_string())
+    }
+
+    fn post_json(&self, path: &str, body: &serde_json::Value) -> (u16, serde_json::Value) {
+        let (status, response) = self.post(path, "application/json", body.to_string().as_bytes());
+        (status, serde_json::from_str(&response).unwrap_or_else(|_| panic!("Not JSON: {}", response)))
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+#[test]
+fn test_server_per_request_options() {
+    use base64::Engine;
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let server = TestServer::start(18731, &[]);
+
+    let request = serde_json::json!({ "data": base64.encode("Tuned over HTTP"), "rs": "255,191", "amplitude": 0.5 });
+    let (status, encoded) = server.post_json("/encode", &request);
+    assert_eq!(status, 200, "{}", encoded);
+    assert_eq!(encoded["stats"]["rs"], "255,191");
+    assert_eq!(encoded["stats"]["rs_blocks"], 1);
+    assert!(encoded["stats"]["symbols"].as_u64().unwrap() > 0, "{}", encoded);
+
+    let request = serde_json::json!({ "wav_base64": encoded["wav_base64"], "preamble_threshold": 0.3 });
+    let (status, decoded) = server.post_json("/decode", &request);
+    assert_eq!(status, 200, "{}", decoded);
+    assert_eq!(base64.decode(decoded["data"].as_str().unwrap()).unwrap(), b"Tuned over HTTP");
+    assert!(decoded["stats"]["rs_blocks_decoded"].as_u64().unwrap() >= 1, "{}", decoded);
+
+    // Every bad field is reported, not just the first
+    let request = serde_json::json!({ "data": base64.encode("x"), "profile": "fast", "amplitude": 2.0 });
+    let (status, rejected) = server.post_json("/encode", &request);
+    assert_eq!(status, 422);
+    let fields: Vec<&str> = rejected["errors"].as_array().unwrap().iter().map(|e| e["field"].as_str().unwrap()).collect();
+    assert_eq!(fields, ["profile", "amplitude"], "{}", rejected);
+
+    // Four tones of 16 bins do not fit into half the band twice
+    let request = serde_json::json!({ "data": base64.encode("x"), "tone_layout": "4,16", "dual_band": true });
+    let (status, rejected) = server.post_json("/encode", &request);
+    assert_eq!(status, 422);
+    assert_eq!(rejected["errors"][0]["field"], "dual_band", "{}", rejected);
+
+    // Same payload limit as the CLI
+    let request = serde_json::json!({ "data": base64.encode([0u8; 65]), "profile": "short" });
+    let (status, rejected) = server.post_json("/encode", &request);
+    assert_eq!(status, 422);
+    assert_eq!(rejected["errors"][0]["field"], "data", "{}", rejected);
+
+    let request = serde_json::json!({ "wav_base64": "AAAA", "fountain": { "block_size": 0 } });
+    let (status, rejected) = server.post_json("/decode", &request);
+    assert_eq!(status, 422);
+    assert_eq!(rejected["errors"][0]["field"], "fountain.block_size", "{}", rejected);
+}
+
+#[test]
+fn test_server_uploads_and_limits() {
+    use base64::Engine;
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let input = create_test_file("test_server_upload.txt", "Uploaded raw");
+    let wav_path = PathBuf::from("tmp/test_server_upload.wav");
+    run_transmitwave(&["encode", input.to_str().unwrap(), wav_path.to_str().unwrap()]);
+    let wav = fs::read(&wav_path).expect("Encode failed");
+
+    // The WAV fits the body limit, its base64 JSON does not
+    let limit = wav.len() * 6 / 5;
+    let server = TestServer::start(18732, &["--max-body-bytes", &limit.to_string(), "--rate-limit", "4"]);
+
+    let (status, response) = server.post("/decode/upload", "audio/wav", &wav);
+    assert_eq!(status, 200, "{}", response);
+    assert!(response.contains(&base64.encode("Uploaded raw")), "{}", response);
+
+    let boundary = "transmitwave-test-boundary";
+    let mut form = Vec::new();
+    form.extend_from_slice(format!("--{}\r\nContent-Disposition: form-data; name=\"options\"\r\n\r\n", boundary).as_bytes());
+    form.extend_from_slice(br#"{"preamble_threshold": 0.3}"#);
+    form.extend_from_slice(format!("\r\n--{}\r\nContent-Disposition: form-data; name=\"wav\"; filename=\"a.wav\"\r\n\r\n", boundary).as_bytes());
+    form.extend_from_slice(&wav);
+    form.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    let (status, response) = server.post("/decode/upload", &format!("multipart/form-data; boundary={}", boundary), &form);
+    assert_eq!(status, 200, "{}", response);
+    assert!(response.contains(&base64.encode("Uploaded raw")), "{}", response);
+
+    let request = serde_json::json!({ "wav_base64": base64.encode(&wav) });
+    let (status, _) = server.post("/decode", "application/json", request.to_string().as_bytes());
+    assert_eq!(status, 413);
+
+    // Fourth request from this IP is allowed, the fifth is not
+    let (status, _) = server.post_json("/encode", &serde_json::json!({ "data": base64.encode("x") }));
+    assert_eq!(status, 200);
+    let (status, _) = server.post_json("/encode", &serde_json::json!({ "data": base64.encode("x") }));
+    assert_eq!(status, 429);
+}
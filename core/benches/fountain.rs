@@ -0,0 +1,53 @@
+//! Fountain decoding of a synthetic block stream
+//!
+//!   cargo bench -p transmitwave-core --bench fountain
+//!
+//! Named baselines work as for the `modem` bench (`-- --save-baseline main`,
+//! then `-- --baseline main`).
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use transmitwave_core::{DecoderFsk, DegreeDistribution, EncoderFsk, FountainCode, FountainConfig};
+
+const PAYLOAD_SIZE: usize = 128;
+
+/// Blocks in the stream: four 32-byte source blocks plus enough repair blocks
+/// for the LT peeling decoder to finish
+const STREAM_BLOCKS: usize = 12;
+
+fn stream(code: FountainCode) -> (Vec<u8>, Vec<f32>, FountainConfig) {
+    let data: Vec<u8> = (0..PAYLOAD_SIZE).map(|i| (i * 13 + 5) as u8).collect();
+    let config = FountainConfig { timeout_secs: 0, block_size: 32, repair_blocks_ratio: 0.5, code };
+    let samples = EncoderFsk::new()
+        .unwrap()
+        .encode_fountain(&data, Some(config.clone()))
+        .unwrap()
+        .take(STREAM_BLOCKS)
+        .flatten()
+        .collect();
+    (data, samples, config)
+}
+
+fn bench_decode_fountain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_fountain");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(PAYLOAD_SIZE as u64));
+    for (name, code) in [
+        ("raptorq", FountainCode::RaptorQ),
+        ("lt", FountainCode::Lt(DegreeDistribution::IdealSoliton)),
+    ] {
+        let (data, samples, config) = stream(code);
+        assert_eq!(DecoderFsk::new().unwrap().decode_fountain(&samples, Some(config.clone())).unwrap(), data);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &samples, |b, samples| {
+            b.iter(|| {
+                DecoderFsk::new()
+                    .unwrap()
+                    .decode_fountain(black_box(samples), Some(config.clone()))
+                    .unwrap()
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode_fountain);
+criterion_main!(benches);
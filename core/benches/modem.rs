@@ -0,0 +1,124 @@
+//! Hot paths of a single-frame round trip
+//!
+//!   cargo bench -p transmitwave-core --bench modem
+//!
+//! Compare a change against the tree it started from with named baselines:
+//! `-- --save-baseline main` on the base commit, `-- --baseline main` on the branch.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use fft_correlation::{fft_correlate_1d, Mode};
+use transmitwave_core::sync::generate_preamble;
+use transmitwave_core::{
+    detect_preamble, CorrelationBackend, DecoderFsk, DetectionThreshold, EncoderFsk, FftCorrelation, FskDemodulator,
+    FskModulator, FSK_SYMBOL_SAMPLES, PREAMBLE_SAMPLES, SAMPLE_RATE,
+};
+
+/// Payload sizes of the encode/decode benchmarks
+const PAYLOAD_SIZES: [usize; 3] = [16, 64, 200];
+
+/// Recording lengths scanned for the preamble, in seconds
+const RECORDING_SECS: [usize; 2] = [10, 60];
+
+fn payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 + 7) as u8).collect()
+}
+
+fn noise(len: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            0.01 * (state as f32 / u32::MAX as f32 - 0.5)
+        })
+        .collect()
+}
+
+/// Noise with one frame in the middle, like an upload to a batch decoder
+fn recording(seconds: usize) -> Vec<f32> {
+    let mut samples = noise(seconds * SAMPLE_RATE, 0x1664);
+    let frame = EncoderFsk::new().unwrap().encode(b"benchmark").unwrap();
+    let start = samples.len().saturating_sub(frame.len()) / 2;
+    for (sample, value) in samples[start..].iter_mut().zip(&frame) {
+        *sample += value;
+    }
+    samples
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for size in PAYLOAD_SIZES {
+        let data = payload(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            let mut encoder = EncoderFsk::new().unwrap();
+            b.iter(|| encoder.encode(black_box(data)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(20);
+    for size in PAYLOAD_SIZES {
+        let samples = EncoderFsk::new().unwrap().encode(&payload(size)).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &samples, |b, samples| {
+            let mut decoder = DecoderFsk::new().unwrap();
+            b.iter(|| decoder.decode(black_box(samples)).unwrap());
+        });
+    }
+    group.finish();
+}
+
+fn bench_detect_preamble(c: &mut Criterion) {
+    let mut group = c.benchmark_group("detect_preamble");
+    group.sample_size(10);
+    for seconds in RECORDING_SECS {
+        let samples = recording(seconds);
+        group.throughput(Throughput::Elements(samples.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}s", seconds)), &samples, |b, samples| {
+            b.iter(|| detect_preamble(black_box(samples), DetectionThreshold::Adaptive).unwrap());
+        });
+    }
+    group.finish();
+}
+
+/// Preamble correlation of the default backend against `fft_correlate_1d`,
+/// the `fft-correlation` crate it replaced
+fn bench_correlation(c: &mut Criterion) {
+    let template = generate_preamble(PREAMBLE_SAMPLES, 0.5);
+    let mut group = c.benchmark_group("correlation");
+    group.sample_size(10);
+    for seconds in RECORDING_SECS {
+        let samples = recording(seconds);
+        let id = format!("{}s", seconds);
+        group.throughput(Throughput::Elements(samples.len() as u64));
+        group.bench_with_input(BenchmarkId::new("rustfft", &id), &samples, |b, samples| {
+            b.iter(|| FftCorrelation.correlate(black_box(samples), &template).unwrap());
+        });
+        group.bench_with_input(BenchmarkId::new("fft-correlation", &id), &samples, |b, samples| {
+            b.iter(|| fft_correlate_1d(black_box(samples), &template, Mode::Full).unwrap());
+        });
+    }
+    group.finish();
+}
+
+/// Per-symbol spectrum: the raw Goertzel bank and the full decision path
+/// (AGC, noise suppression, tone picking) on top of it
+fn bench_spectrum(c: &mut Criterion) {
+    let demodulator = FskDemodulator::new();
+    let symbol = &FskModulator::new().modulate_symbol(&[0x5a, 0xc3, 0x17]).unwrap();
+    let mut group = c.benchmark_group("spectrum");
+    group.throughput(Throughput::Elements(FSK_SYMBOL_SAMPLES as u64));
+    group.bench_function("bin_powers", |b| b.iter(|| demodulator.bin_powers(black_box(symbol))));
+    group.bench_function("demodulate_symbol", |b| {
+        b.iter(|| demodulator.demodulate_symbol(black_box(symbol)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_decode, bench_detect_preamble, bench_correlation, bench_spectrum);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Throughput of the sync correlation backends on long recordings
+//!
+//!   cargo run --release -p transmitwave-core --example correlation_bench
+//!   cargo run --release -p transmitwave-core --features gpu --example correlation_bench
+//!
+//! Each run searches a synthetic recording (noise with one frame in the middle)
+//! for the preamble, the way a batch server scans uploads. `FftCorrelation`
+//! (rustfft) is compared with the `fft-correlation` crate it replaced.
+
+use fft_correlation::{fft_correlate_1d, Mode};
+use std::sync::Arc;
+use std::time::Instant;
+use transmitwave_core::{
+    detect_preamble, set_correlation_backend, AudioModemError, CorrelationBackend, DetectionThreshold, EncoderFsk,
+    FftCorrelation, SAMPLE_RATE,
+};
+
+/// `fft_correlate_1d` of the `fft-correlation` crate
+struct CrateCorrelation;
+
+impl CorrelationBackend for CrateCorrelation {
+    fn name(&self) -> &str {
+        "fft-correlation"
+    }
+
+    fn correlate(&self, samples: &[f32], template: &[f32]) -> transmitwave_core::Result<Vec<f32>> {
+        fft_correlate_1d(samples, template, Mode::Full).map_err(|e| AudioModemError::FftError(e.to_string()))
+    }
+}
+
+/// Recording lengths to scan, in seconds
+const DURATIONS_SECS: [usize; 3] = [10, 60, 300];
+
+const RUNS: usize = 5;
+
+fn recording(seconds: usize) -> Vec<f32> {
+    let mut state = 0x1616u32;
+    let mut samples: Vec<f32> = (0..seconds * SAMPLE_RATE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            0.01 * (state as f32 / u32::MAX as f32 - 0.5)
+        })
+        .collect();
+    let frame = EncoderFsk::new().unwrap().encode(b"benchmark").unwrap();
+    let start = samples.len().saturating_sub(frame.len()) / 2;
+    for (sample, value) in samples[start..].iter_mut().zip(&frame) {
+        *sample += value;
+    }
+    samples
+}
+
+fn bench(backend: Arc<dyn CorrelationBackend>, recordings: &[(usize, Vec<f32>)]) {
+    let name = backend.name().to_string();
+    set_correlation_backend(Some(backend));
+    for (seconds, samples) in recordings {
+        // Warm-up run (plans, pipelines, buffers)
+        let found = detect_preamble(samples, DetectionThreshold::Adaptive);
+        let start = Instant::now();
+        for _ in 0..RUNS {
+            detect_preamble(samples, DetectionThreshold::Adaptive);
+        }
+        let per_run = start.elapsed().as_secs_f64() / RUNS as f64;
+        println!(
+            "{:>15} {:>5} s  {:>9.1} ms/scan  {:>8.0}x real time  preamble={:?}",
+            name,
+            seconds,
+            per_run * 1000.0,
+            *seconds as f64 / per_run,
+            found
+        );
+    }
+    set_correlation_backend(None);
+}
+
+fn main() {
+    let recordings: Vec<(usize, Vec<f32>)> = DURATIONS_SECS.iter().map(|&s| (s, recording(s))).collect();
+
+    bench(Arc::new(FftCorrelation), &recordings);
+    bench(Arc::new(CrateCorrelation), &recordings);
+
+    #[cfg(feature = "gpu")]
+    match transmitwave_core::WgpuCorrelation::new() {
+        Ok(gpu) => {
+            println!("GPU: {}", gpu.adapter_name());
+            bench(Arc::new(gpu), &recordings);
+        }
+        Err(e) => println!("GPU backend unavailable: {}", e),
+    }
+
+    #[cfg(not(feature = "gpu"))]
+    println!("Build with --features gpu to compare against the wgpu backend");
+}
@@ -0,0 +1,267 @@
+//! Link adaptation: turning a receiver's `DecodeQuality` into the settings
+//! its sender should use next
+//!
+//! With a return channel, the receiver calls [`LinkProfile::recommend`] after
+//! each frame and sends the result back as a control frame
+//! ([`LinkProfile::encode_control`]), whose header flag keeps it apart from
+//! user data. The sender recognises it with [`LinkProfile::from_message`]
+//! and applies it to its encoder.
+
+use crate::decoder_fsk::{DecodeQuality, DecodedMessage};
+use crate::encoder_fsk::{EncoderFsk, FrameProfile};
+use crate::error::Result;
+use crate::fec::FecMode;
+use crate::fsk::ToneLayout;
+#[cfg(feature = "fountain")]
+use crate::fountain_stream::FountainConfig;
+
+/// Control payload: profile (1) + FEC mode (1) + flags (1) + repair ratio (1)
+pub const CONTROL_FRAME_SIZE: usize = 4;
+
+const FLAG_AMPLITUDE_BITS: u8 = 0x01;
+const FLAG_DUAL_BAND: u8 = 0x02;
+
+/// Below these the channel drops to the next slower, more protected tier
+const GOOD_SNR_DB: f32 = 18.0;
+const GOOD_SYMBOL_MARGIN: f32 = 0.3;
+const FAIR_SNR_DB: f32 = 10.0;
+const FAIR_SYMBOL_MARGIN: f32 = 0.15;
+
+/// Byte errors corrected per frame beyond which the FEC is close to its limit
+const MAX_COMFORTABLE_BYTE_ERRORS: usize = 4;
+
+/// Transmission settings recommended for a channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkProfile {
+    /// Frame profile (symbol length and tone layout)
+    pub profile: FrameProfile,
+    /// Forced FEC mode; None picks it from the frame size
+    pub fec_mode: Option<FecMode>,
+    pub amplitude_bits: bool,
+    pub dual_band: bool,
+    /// Repair-to-source block ratio for fountain transfers
+    pub repair_blocks_ratio: f32,
+}
+
+impl Default for LinkProfile {
+    /// The encoder defaults
+    fn default() -> Self {
+        Self {
+            profile: FrameProfile::Standard,
+            fec_mode: None,
+            amplitude_bits: false,
+            dual_band: false,
+            repair_blocks_ratio: 0.5,
+        }
+    }
+}
+
+impl LinkProfile {
+    /// Settings for the channel a frame of this quality crossed
+    ///
+    /// Four tiers, fastest first: amplitude bits with light FEC, the defaults,
+    /// dual-band symbols with full FEC, and the slow telephony profile. Frames
+    /// that needed many byte corrections or lost fountain blocks drop one tier.
+    pub fn recommend(quality: &DecodeQuality) -> Self {
+        let mut tier = if quality.amplitude_bits_viable() {
+            0
+        } else if quality.snr_db >= GOOD_SNR_DB && quality.min_symbol_margin >= GOOD_SYMBOL_MARGIN {
+            1
+        } else if quality.snr_db >= FAIR_SNR_DB && quality.min_symbol_margin >= FAIR_SYMBOL_MARGIN {
+            2
+        } else {
+            3
+        };
+        if quality.parity_byte_errors > MAX_COMFORTABLE_BYTE_ERRORS || quality.failed_blocks > 0 {
+            tier = (tier + 1).min(3);
+        }
+
+        match tier {
+            0 => Self { fec_mode: Some(FecMode::Light), amplitude_bits: true, repair_blocks_ratio: 0.25, ..Self::default() },
+            1 => Self::default(),
+            2 => Self { fec_mode: Some(FecMode::Full), dual_band: true, repair_blocks_ratio: 1.0, ..Self::default() },
+            _ => Self { profile: FrameProfile::Telephony, repair_blocks_ratio: 1.0, ..Self::default() },
+        }
+    }
+
+    /// Configure an encoder with these settings
+    ///
+    /// Symbol options the profile does not name go back to their defaults
+    /// (standard tone layout, no symbol gaps, no differential symbols), so
+    /// every recommendation gives an encoder that can send; padding resets to
+    /// the profile's default.
+    pub fn apply(&self, encoder: &mut EncoderFsk) {
+        encoder.set_profile(self.profile);
+        encoder.set_fec_mode(self.fec_mode);
+        encoder.set_amplitude_bits(self.amplitude_bits);
+        encoder.set_dual_band(self.dual_band);
+        encoder.set_tone_layout(ToneLayout::STANDARD);
+        encoder.set_symbol_gaps(None);
+        encoder.set_differential(false);
+    }
+
+    /// Fountain settings with this profile's repair ratio
+    #[cfg(feature = "fountain")]
+    pub fn fountain_config(&self, base: FountainConfig) -> FountainConfig {
+        FountainConfig { repair_blocks_ratio: self.repair_blocks_ratio, ..base }
+    }
+
+    /// Control frame audio carrying these settings back to the sender
+    pub fn encode_control(&self, encoder: &mut EncoderFsk) -> Result<Vec<f32>> {
+        encoder.encode_control(&self.to_control_frame())
+    }
+
+    /// Settings in a decoded frame, or None if it is not a control frame
+    pub fn from_message(message: &DecodedMessage) -> Option<Self> {
+        if !message.control {
+            return None;
+        }
+        Self::from_control_frame(&message.payload)
+    }
+
+    /// Payload of a control frame carrying these settings
+    ///
+    /// Layout: `[profile][fec mode][flags][repair ratio in %]`, where the FEC
+    /// mode is its header byte (0 = picked by size).
+    pub fn to_control_frame(&self) -> Vec<u8> {
+        let profile = match self.profile {
+            FrameProfile::Standard => 0,
+            FrameProfile::Short => 1,
+            FrameProfile::Telephony => 2,
+            FrameProfile::CodecRobust => 3,
+            FrameProfile::Narrowband => 4,
+        };
+        let mut flags = 0;
+        if self.amplitude_bits {
+            flags |= FLAG_AMPLITUDE_BITS;
+        }
+        if self.dual_band {
+            flags |= FLAG_DUAL_BAND;
+        }
+        let ratio = (self.repair_blocks_ratio * 100.0).round().clamp(0.0, 255.0) as u8;
+        vec![profile, self.fec_mode.map_or(0, |mode| mode.to_u8()), flags, ratio]
+    }
+
+    /// Settings in a control frame payload, or None if it holds none
+    pub fn from_control_frame(payload: &[u8]) -> Option<Self> {
+        let &[profile, fec_mode, flags, ratio] = payload else {
+            return None;
+        };
+        let profile = match profile {
+            0 => FrameProfile::Standard,
+            1 => FrameProfile::Short,
+            2 => FrameProfile::Telephony,
+            3 => FrameProfile::CodecRobust,
+            4 => FrameProfile::Narrowband,
+            _ => return None,
+        };
+        let fec_mode = match fec_mode {
+            0 => None,
+            value => Some(FecMode::from_u8(value).ok()?),
+        };
+        if flags & !(FLAG_AMPLITUDE_BITS | FLAG_DUAL_BAND) != 0 {
+            return None;
+        }
+        Some(Self {
+            profile,
+            fec_mode,
+            amplitude_bits: flags & FLAG_AMPLITUDE_BITS != 0,
+            dual_band: flags & FLAG_DUAL_BAND != 0,
+            repair_blocks_ratio: ratio as f32 / 100.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DecoderFsk;
+
+    fn quality(snr_db: f32, min_symbol_margin: f32, parity_byte_errors: usize) -> DecodeQuality {
+        DecodeQuality {
+            min_symbol_margin,
+            mean_symbol_margin: min_symbol_margin,
+            snr_db,
+            parity_byte_errors,
+            failed_blocks: 0,
+            amplitude_contrast_db: None,
+        }
+    }
+
+    #[test]
+    fn test_recommend_tiers() {
+        let clean = LinkProfile::recommend(&quality(40.0, 0.9, 0));
+        assert!(clean.amplitude_bits);
+        assert_eq!(clean.fec_mode, Some(FecMode::Light));
+
+        assert_eq!(LinkProfile::recommend(&quality(20.0, 0.4, 0)), LinkProfile::default());
+
+        let fair = LinkProfile::recommend(&quality(12.0, 0.2, 0));
+        assert!(fair.dual_band);
+        assert_eq!(fair.fec_mode, Some(FecMode::Full));
+        // Heavy FEC work drops a tier
+        assert_eq!(LinkProfile::recommend(&quality(20.0, 0.4, 12)), fair);
+
+        let poor = LinkProfile::recommend(&quality(4.0, 0.05, 0));
+        assert_eq!(poor.profile, FrameProfile::Telephony);
+        assert_eq!(LinkProfile::recommend(&quality(4.0, 0.05, 30)), poor);
+    }
+
+    #[test]
+    fn test_control_frame_roundtrip() {
+        let profiles = [
+            LinkProfile::default(),
+            LinkProfile::recommend(&quality(40.0, 0.9, 0)),
+            LinkProfile::recommend(&quality(12.0, 0.2, 0)),
+            LinkProfile::recommend(&quality(4.0, 0.05, 0)),
+            LinkProfile { fec_mode: Some(FecMode::custom(255, 191).unwrap()), ..LinkProfile::default() },
+        ];
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        for profile in profiles {
+            let control = profile.to_control_frame();
+            assert_eq!(control.len(), CONTROL_FRAME_SIZE);
+
+            // Sent back over the air in a control frame
+            let message = decoder.decode_with_metadata(&profile.encode_control(&mut encoder).unwrap()).unwrap();
+            assert!(message.control);
+            assert_eq!(LinkProfile::from_message(&message), Some(profile));
+
+            // User data with the same bytes is not mistaken for it
+            let message = decoder.decode_with_metadata(&encoder.encode(&control).unwrap()).unwrap();
+            assert_eq!(message.payload, control);
+            assert_eq!(LinkProfile::from_message(&message), None);
+        }
+
+        assert_eq!(LinkProfile::from_control_frame(b"hello"), None);
+        assert_eq!(LinkProfile::from_control_frame(&[9, 0, 0, 50]), None);
+        assert_eq!(LinkProfile::from_control_frame(&[0, 0, 0]), None);
+
+        // The short profile has no header to flag
+        encoder.set_profile(FrameProfile::Short);
+        assert!(LinkProfile::default().encode_control(&mut encoder).is_err());
+    }
+
+    #[test]
+    fn test_apply_encodes_with_profile() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let payload = b"adapt to the channel";
+        for snr_db in [40.0, 20.0, 12.0, 4.0] {
+            let profile = LinkProfile::recommend(&quality(snr_db, snr_db / 45.0, 0));
+            profile.apply(&mut encoder);
+            assert_eq!(encoder.profile(), profile.profile);
+            assert_eq!(decoder.decode(&encoder.encode(payload).unwrap()).unwrap(), payload, "{:?}", profile);
+        }
+
+        // Options the profile does not name are reset, so the encoder can still send
+        encoder.set_tone_layout(ToneLayout::new(4, 16).unwrap());
+        encoder.set_symbol_gaps(Some(8));
+        encoder.set_differential(true);
+        LinkProfile::recommend(&quality(4.0, 0.05, 0)).apply(&mut encoder);
+        assert_eq!(encoder.tone_layout(), ToneLayout::STANDARD);
+        assert_eq!(encoder.symbol_gaps(), None);
+        assert!(!encoder.differential());
+        assert_eq!(decoder.decode(&encoder.encode(payload).unwrap()).unwrap(), payload);
+    }
+}
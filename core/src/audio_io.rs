@@ -0,0 +1,715 @@
+//! WAV file reading/writing for modem audio
+//!
+//! Reading accepts 8/16/24/32-bit PCM and 32/64-bit float WAV (plain or
+//! WAVE_FORMAT_EXTENSIBLE), downmixes to mono (or keeps channels separate with
+//! `read_wav_channels`) and resamples to SAMPLE_RATE so the result can go
+//! straight to a decoder. `WavStreamReader` does the same window by window for
+//! recordings too long to hold in memory.
+
+use crate::error::{AudioModemError, Result};
+use crate::resample::{resample_audio, StreamResampler};
+use crate::stereo::{to_stereo, StereoRedundancy};
+use crate::SAMPLE_RATE;
+use std::io::{BufReader, Read, Seek, Write};
+use std::path::Path;
+
+/// Sample format used when writing WAV files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WavSampleFormat {
+    #[default]
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+}
+
+impl WavSampleFormat {
+    fn spec(self, channels: u16) -> hound::WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            WavSampleFormat::Int16 => (16, hound::SampleFormat::Int),
+            WavSampleFormat::Int24 => (24, hound::SampleFormat::Int),
+            WavSampleFormat::Int32 => (32, hound::SampleFormat::Int),
+            WavSampleFormat::Float32 => (32, hound::SampleFormat::Float),
+        };
+        hound::WavSpec {
+            channels,
+            sample_rate: SAMPLE_RATE as u32,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+}
+
+/// Format of a WAV file as read from disk, before conversion
+///
+/// `bits_per_sample` is the value from the fmt chunk (the container width).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WavInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub is_float: bool,
+}
+
+fn wav_error(e: hound::Error) -> AudioModemError {
+    AudioModemError::WavError(e.to_string())
+}
+
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Parsed `fmt ` chunk, with WAVE_FORMAT_EXTENSIBLE resolved to its subformat
+struct FmtChunk {
+    format: u16,
+    channels: u16,
+    sample_rate: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn parse_fmt(chunk: &[u8]) -> Result<FmtChunk> {
+    if chunk.len() < 16 {
+        return Err(AudioModemError::WavError("fmt chunk too short".to_string()));
+    }
+    let mut format = le_u16(chunk, 0);
+    if format == WAVE_FORMAT_EXTENSIBLE {
+        // cbSize(2) validBits(2) channelMask(4) then the SubFormat GUID, whose
+        // first two bytes are the actual format code
+        if chunk.len() < 40 {
+            return Err(AudioModemError::WavError(
+                "WAVE_FORMAT_EXTENSIBLE fmt chunk too short".to_string(),
+            ));
+        }
+        format = le_u16(chunk, 24);
+    }
+    Ok(FmtChunk {
+        format,
+        channels: le_u16(chunk, 2),
+        sample_rate: le_u32(chunk, 4),
+        block_align: le_u16(chunk, 12),
+        bits_per_sample: le_u16(chunk, 14),
+    })
+}
+
+/// Convert raw interleaved sample data to f32 in [-1.0, 1.0]
+///
+/// Integer samples are scaled by their container width. Extensible files with
+/// fewer valid bits (e.g. 20-bit in a 24-bit container) are left-justified, so
+/// this gives the correct level without looking at the valid-bits field.
+fn convert_samples(data: &[u8], format: u16, container_bytes: usize) -> Result<Vec<f32>> {
+    let chunks = data.chunks_exact(container_bytes);
+    let samples = match (format, container_bytes) {
+        // 8-bit PCM is unsigned
+        (WAVE_FORMAT_PCM, 1) => chunks.map(|b| (b[0] as f32 - 128.0) / 128.0).collect(),
+        (WAVE_FORMAT_PCM, 2) => chunks
+            .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect(),
+        (WAVE_FORMAT_PCM, 3) => chunks
+            .map(|b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f32 / 8_388_608.0)
+            .collect(),
+        (WAVE_FORMAT_PCM, 4) => chunks
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0)
+            .collect(),
+        (WAVE_FORMAT_IEEE_FLOAT, 4) => chunks
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+        (WAVE_FORMAT_IEEE_FLOAT, 8) => chunks
+            .map(|b| f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32)
+            .collect(),
+        (format, bytes) => {
+            return Err(AudioModemError::WavError(format!(
+                "Unsupported WAV format: tag 0x{:04x}, {}-bit samples",
+                format,
+                bytes * 8
+            )));
+        }
+    };
+    Ok(samples)
+}
+
+/// Read a WAV stream and convert it to mono f32 samples at SAMPLE_RATE
+///
+/// Supports 8/16/24/32-bit PCM and 32/64-bit IEEE float, including
+/// WAVE_FORMAT_EXTENSIBLE headers.
+pub fn read_wav_to_modem_samples<R: Read>(reader: R) -> Result<(Vec<f32>, WavInfo)> {
+    let (samples, info) = parse_wav(reader)?;
+    let mono = downmix(&samples, info.channels as usize)?;
+    let samples = resample_audio(&mono, info.sample_rate as usize, SAMPLE_RATE);
+    Ok((samples, info))
+}
+
+/// Read a WAV stream keeping each channel separate, resampled to SAMPLE_RATE
+pub fn read_wav_channels<R: Read>(reader: R) -> Result<(Vec<Vec<f32>>, WavInfo)> {
+    let (samples, info) = parse_wav(reader)?;
+    let channels = info.channels as usize;
+    let per_channel = (0..channels)
+        .map(|c| {
+            let channel: Vec<f32> = samples.iter().skip(c).step_by(channels).copied().collect();
+            resample_audio(&channel, info.sample_rate as usize, SAMPLE_RATE)
+        })
+        .collect();
+    Ok((per_channel, info))
+}
+
+/// File format and bytes per sample of a parsed fmt chunk
+fn wav_layout(fmt: &FmtChunk) -> Result<(WavInfo, usize)> {
+    if fmt.channels == 0 || fmt.sample_rate == 0 {
+        return Err(AudioModemError::WavError("Invalid channel count or sample rate".to_string()));
+    }
+
+    let container_bytes = if fmt.block_align >= fmt.channels && fmt.block_align.is_multiple_of(fmt.channels) {
+        (fmt.block_align / fmt.channels) as usize
+    } else {
+        (fmt.bits_per_sample as usize).div_ceil(8)
+    };
+    if container_bytes == 0 {
+        return Err(AudioModemError::WavError("Invalid sample size".to_string()));
+    }
+
+    let info = WavInfo {
+        sample_rate: fmt.sample_rate,
+        channels: fmt.channels,
+        bits_per_sample: fmt.bits_per_sample,
+        is_float: fmt.format == WAVE_FORMAT_IEEE_FLOAT,
+    };
+    Ok((info, container_bytes))
+}
+
+/// Parse a WAV stream into interleaved f32 samples at the file's own rate
+fn parse_wav<R: Read>(mut reader: R) -> Result<(Vec<f32>, WavInfo)> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| AudioModemError::WavError(e.to_string()))?;
+
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(AudioModemError::WavError("Not a RIFF/WAVE file".to_string()));
+    }
+
+    let mut fmt = None;
+    let mut data = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = le_u32(&bytes, pos + 4) as usize;
+        let body_start = pos + 8;
+        // Streaming writers may leave the data size unset; clamp to what's there
+        let body_end = body_start.saturating_add(size).min(bytes.len());
+        match id {
+            b"fmt " => fmt = Some(parse_fmt(&bytes[body_start..body_end])?),
+            b"data" => data = Some(&bytes[body_start..body_end]),
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        pos = body_start.saturating_add(size).saturating_add(size & 1);
+    }
+
+    let fmt = fmt.ok_or_else(|| AudioModemError::WavError("Missing fmt chunk".to_string()))?;
+    let data = data.ok_or_else(|| AudioModemError::WavError("Missing data chunk".to_string()))?;
+    let (info, container_bytes) = wav_layout(&fmt)?;
+
+    let mut samples = convert_samples(data, fmt.format, container_bytes)?;
+    // Drop a trailing partial frame
+    samples.truncate(samples.len() - samples.len() % fmt.channels as usize);
+    Ok((samples, info))
+}
+
+/// Read a WAV file and convert it to mono f32 samples at SAMPLE_RATE
+pub fn read_wav_file<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, WavInfo)> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| AudioModemError::WavError(e.to_string()))?;
+    read_wav_to_modem_samples(std::io::BufReader::new(file))
+}
+
+/// Write mono modem samples (at SAMPLE_RATE) as a WAV stream
+///
+/// Samples are clamped to [-1.0, 1.0] before integer conversion.
+pub fn write_modem_samples_to_wav<W: Write + Seek>(
+    writer: W,
+    samples: &[f32],
+    format: WavSampleFormat,
+) -> Result<()> {
+    write_channels_to_wav(writer, &[samples], format)
+}
+
+/// Write one or more channels (at SAMPLE_RATE) as an interleaved WAV stream
+///
+/// Shorter channels are padded with silence to the longest one.
+pub fn write_channels_to_wav<W: Write + Seek>(
+    writer: W,
+    channels: &[&[f32]],
+    format: WavSampleFormat,
+) -> Result<()> {
+    if channels.is_empty() || channels.len() > u16::MAX as usize {
+        return Err(AudioModemError::WavError("Invalid channel count".to_string()));
+    }
+    let len = channels.iter().map(|c| c.len()).max().unwrap_or(0);
+    let mut writer = hound::WavWriter::new(writer, format.spec(channels.len() as u16)).map_err(wav_error)?;
+    for i in 0..len {
+        for channel in channels {
+            let clamped = channel.get(i).copied().unwrap_or(0.0).clamp(-1.0, 1.0);
+            match format {
+                WavSampleFormat::Int16 => writer.write_sample((clamped * 32767.0) as i16),
+                WavSampleFormat::Int24 => writer.write_sample((clamped * 8_388_607.0) as i32),
+                WavSampleFormat::Int32 => writer.write_sample((clamped as f64 * 2_147_483_647.0) as i32),
+                WavSampleFormat::Float32 => writer.write_sample(clamped),
+            }
+            .map_err(wav_error)?;
+        }
+    }
+    writer.finalize().map_err(wav_error)
+}
+
+/// Write mono modem samples to a WAV file
+pub fn write_wav_file<P: AsRef<Path>>(
+    path: P,
+    samples: &[f32],
+    format: WavSampleFormat,
+) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| AudioModemError::WavError(e.to_string()))?;
+    write_modem_samples_to_wav(std::io::BufWriter::new(file), samples, format)
+}
+
+/// Write one or more channels to a WAV file
+pub fn write_channels_wav_file<P: AsRef<Path>>(
+    path: P,
+    channels: &[&[f32]],
+    format: WavSampleFormat,
+) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| AudioModemError::WavError(e.to_string()))?;
+    write_channels_to_wav(std::io::BufWriter::new(file), channels, format)
+}
+
+/// Read a WAV file keeping each channel separate, resampled to SAMPLE_RATE
+pub fn read_wav_channels_file<P: AsRef<Path>>(path: P) -> Result<(Vec<Vec<f32>>, WavInfo)> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| AudioModemError::WavError(e.to_string()))?;
+    read_wav_channels(std::io::BufReader::new(file))
+}
+
+/// Encode mono modem samples as an in-memory WAV file
+pub fn modem_samples_to_wav_bytes(samples: &[f32], format: WavSampleFormat) -> Result<Vec<u8>> {
+    let mut wav_data = Vec::new();
+    write_modem_samples_to_wav(std::io::Cursor::new(&mut wav_data), samples, format)?;
+    Ok(wav_data)
+}
+
+/// The WAV that `transmitwave encode` writes and the server's `/encode`
+/// returns for encoder output: 16-bit PCM of `samples` scaled by `gain`, mono
+/// or stereo with a redundant right channel
+pub fn encoded_wav_bytes(samples: &[f32], gain: f32, stereo: Option<StereoRedundancy>) -> Result<Vec<u8>> {
+    let scaled: Vec<f32> = samples.iter().map(|s| s * gain).collect();
+    let mut wav_data = Vec::new();
+    let writer = std::io::Cursor::new(&mut wav_data);
+    match stereo {
+        Some(redundancy) => {
+            let (left, right) = to_stereo(&scaled, redundancy);
+            write_channels_to_wav(writer, &[&left, &right], WavSampleFormat::Int16)?
+        }
+        None => write_modem_samples_to_wav(writer, &scaled, WavSampleFormat::Int16)?,
+    }
+    Ok(wav_data)
+}
+
+/// Source of modem samples (mono, SAMPLE_RATE) that can be pulled in pieces
+pub trait SampleReader {
+    /// Append up to `max_samples` samples to `buffer`; returns how many, 0 at the end
+    fn read_samples(&mut self, buffer: &mut Vec<f32>, max_samples: usize) -> Result<usize>;
+}
+
+impl SampleReader for &[f32] {
+    fn read_samples(&mut self, buffer: &mut Vec<f32>, max_samples: usize) -> Result<usize> {
+        let count = max_samples.min(self.len());
+        buffer.extend_from_slice(&self[..count]);
+        *self = &self[count..];
+        Ok(count)
+    }
+}
+
+/// Frames read from the file per refill of a `WavStreamReader`
+const STREAM_READ_FRAMES: usize = 8192;
+
+/// WAV decoder that yields modem samples without reading the whole file
+///
+/// Same formats and conversion as `read_wav_to_modem_samples`; the fmt chunk
+/// has to come before the data chunk, as every common writer does it.
+pub struct WavStreamReader<R: Read> {
+    reader: R,
+    info: WavInfo,
+    format: u16,
+    container_bytes: usize,
+    /// Data chunk bytes not read yet
+    data_remaining: u64,
+    /// Bytes of an incomplete frame carried over to the next read
+    raw: Vec<u8>,
+    resampler: StreamResampler,
+    /// Converted samples not handed out yet
+    pending: Vec<f32>,
+    finished: bool,
+}
+
+impl<R: Read> WavStreamReader<R> {
+    /// Read the header up to the start of the sample data
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 12];
+        read_exact(&mut reader, &mut header)?;
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(AudioModemError::WavError("Not a RIFF/WAVE file".to_string()));
+        }
+
+        let mut fmt = None;
+        let data_size = loop {
+            let mut chunk = [0u8; 8];
+            if read_exact(&mut reader, &mut chunk).is_err() {
+                return Err(AudioModemError::WavError("Missing data chunk".to_string()));
+            }
+            let size = le_u32(&chunk, 4) as u64;
+            match &chunk[0..4] {
+                b"data" => break size,
+                b"fmt " => {
+                    let mut body = Vec::new();
+                    (&mut reader).take(size).read_to_end(&mut body).map_err(io_error)?;
+                    fmt = Some(parse_fmt(&body)?);
+                    skip(&mut reader, size & 1)?;
+                }
+                _ => skip(&mut reader, size + (size & 1))?,
+            }
+        };
+
+        let fmt = fmt.ok_or_else(|| AudioModemError::WavError("Missing fmt chunk before data chunk".to_string()))?;
+        let (info, container_bytes) = wav_layout(&fmt)?;
+        Ok(Self {
+            reader,
+            info,
+            format: fmt.format,
+            container_bytes,
+            data_remaining: data_size,
+            raw: Vec::new(),
+            resampler: StreamResampler::new(info.sample_rate as usize, SAMPLE_RATE),
+            pending: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Format of the file being read
+    pub fn info(&self) -> WavInfo {
+        self.info
+    }
+
+    /// Convert the next block of the data chunk into `pending`
+    fn fill(&mut self) -> Result<()> {
+        let frame_bytes = self.container_bytes * self.info.channels as usize;
+        let want = (STREAM_READ_FRAMES * frame_bytes) as u64;
+        let read = (&mut self.reader)
+            .take(want.min(self.data_remaining))
+            .read_to_end(&mut self.raw)
+            .map_err(io_error)?;
+        self.data_remaining -= read as u64;
+
+        if read == 0 {
+            // Streaming writers may leave the data size unset; stop at the real end
+            self.finished = true;
+            self.resampler.finish(&mut self.pending);
+            return Ok(());
+        }
+
+        let whole = self.raw.len() - self.raw.len() % frame_bytes;
+        let samples = convert_samples(&self.raw[..whole], self.format, self.container_bytes)?;
+        self.raw.drain(..whole);
+        let mono = downmix(&samples, self.info.channels as usize)?;
+        self.resampler.push(&mono, &mut self.pending);
+        Ok(())
+    }
+}
+
+impl<R: Read> SampleReader for WavStreamReader<R> {
+    fn read_samples(&mut self, buffer: &mut Vec<f32>, max_samples: usize) -> Result<usize> {
+        while self.pending.len() < max_samples && !self.finished {
+            self.fill()?;
+        }
+        let count = max_samples.min(self.pending.len());
+        buffer.extend(self.pending.drain(..count));
+        Ok(count)
+    }
+}
+
+/// Open a WAV file for reading in pieces with `WavStreamReader`
+pub fn open_wav_stream<P: AsRef<Path>>(path: P) -> Result<WavStreamReader<BufReader<std::fs::File>>> {
+    let file = std::fs::File::open(path).map_err(io_error)?;
+    WavStreamReader::new(BufReader::new(file))
+}
+
+fn io_error(e: std::io::Error) -> AudioModemError {
+    AudioModemError::WavError(e.to_string())
+}
+
+fn read_exact<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Result<()> {
+    reader.read_exact(buffer).map_err(io_error)
+}
+
+fn skip<R: Read>(reader: &mut R, bytes: u64) -> Result<()> {
+    std::io::copy(&mut reader.by_ref().take(bytes), &mut std::io::sink()).map_err(io_error)?;
+    Ok(())
+}
+
+/// Average interleaved channels down to mono
+fn downmix(samples: &[f32], channels: usize) -> Result<Vec<f32>> {
+    match channels {
+        0 => Err(AudioModemError::WavError("WAV has no channels".to_string())),
+        1 => Ok(samples.to_vec()),
+        n => Ok(samples
+            .chunks_exact(n)
+            .map(|frame| frame.iter().sum::<f32>() / n as f32)
+            .collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize) -> Vec<f32> {
+        (0..len).map(|i| 0.8 * (i as f32 * 0.05).sin()).collect()
+    }
+
+    #[test]
+    fn test_wav_roundtrip_all_formats() {
+        let samples = tone(1000);
+        for (format, tolerance) in [
+            (WavSampleFormat::Int16, 1e-4),
+            (WavSampleFormat::Int24, 1e-6),
+            (WavSampleFormat::Int32, 1e-6),
+            (WavSampleFormat::Float32, 0.0),
+        ] {
+            let bytes = modem_samples_to_wav_bytes(&samples, format).unwrap();
+            let (decoded, info) = read_wav_to_modem_samples(std::io::Cursor::new(bytes)).unwrap();
+            assert_eq!(info.sample_rate, SAMPLE_RATE as u32);
+            assert_eq!(info.is_float, format == WavSampleFormat::Float32);
+            assert_eq!(decoded.len(), samples.len());
+            for (a, b) in samples.iter().zip(&decoded) {
+                assert!((a - b).abs() <= tolerance, "{:?}: {} vs {}", format, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encoded_wav_bytes() {
+        let samples = tone(1000);
+        let mono = encoded_wav_bytes(&samples, 1.0, None).unwrap();
+        assert_eq!(mono, modem_samples_to_wav_bytes(&samples, WavSampleFormat::Int16).unwrap());
+
+        let half: Vec<f32> = samples.iter().map(|s| s * 0.5).collect();
+        let quiet = encoded_wav_bytes(&samples, 0.5, None).unwrap();
+        assert_eq!(quiet, modem_samples_to_wav_bytes(&half, WavSampleFormat::Int16).unwrap());
+
+        let stereo = encoded_wav_bytes(&samples, 1.0, Some(StereoRedundancy::Delayed(100))).unwrap();
+        let (channels, info) = read_wav_channels(std::io::Cursor::new(stereo)).unwrap();
+        assert_eq!((info.channels, info.bits_per_sample), (2, 16));
+        assert_eq!(channels[0].len(), samples.len() + 100);
+    }
+
+    #[test]
+    fn test_read_stereo_48k_converts_to_mono_16k() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = Vec::new();
+        {
+            let mut writer = hound::WavWriter::new(std::io::Cursor::new(&mut bytes), spec).unwrap();
+            for _ in 0..4800 {
+                writer.write_sample(16384i16).unwrap();
+                writer.write_sample(0i16).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        let (samples, info) = read_wav_to_modem_samples(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(info.channels, 2);
+        assert_eq!(info.sample_rate, 48000);
+        assert_eq!(samples.len(), 1600);
+        assert!(samples.iter().all(|&s| (s - 0.25).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_channels_roundtrip_pads_shorter_channel() {
+        let left = tone(800);
+        let right = vec![-0.5f32; 600];
+        let mut bytes = Vec::new();
+        write_channels_to_wav(std::io::Cursor::new(&mut bytes), &[&left, &right], WavSampleFormat::Float32).unwrap();
+
+        let (channels, info) = read_wav_channels(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(info.channels, 2);
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0], left);
+        assert_eq!(&channels[1][..600], &right[..]);
+        assert!(channels[1][600..].iter().all(|&s| s == 0.0));
+        assert_eq!(channels[1].len(), 800);
+    }
+
+    /// Build a WAV by hand so formats hound can't write are covered
+    fn raw_wav(
+        format_tag: u16,
+        subformat: Option<u16>,
+        channels: u16,
+        bits: u16,
+        valid_bits: u16,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&format_tag.to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&(SAMPLE_RATE as u32).to_le_bytes());
+        fmt.extend_from_slice(&(SAMPLE_RATE as u32 * block_align as u32).to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        if let Some(code) = subformat {
+            fmt.extend_from_slice(&22u16.to_le_bytes());
+            fmt.extend_from_slice(&valid_bits.to_le_bytes());
+            fmt.extend_from_slice(&0u32.to_le_bytes());
+            let mut guid = [
+                0, 0, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+            ];
+            guid[..2].copy_from_slice(&code.to_le_bytes());
+            fmt.extend_from_slice(&guid);
+        }
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        // Unknown chunk with odd size to exercise padding
+        wav.extend_from_slice(b"LIST");
+        wav.extend_from_slice(&3u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 2, 3, 0]);
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&fmt);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(data);
+        wav
+    }
+
+    fn read_raw(wav: Vec<u8>) -> Vec<f32> {
+        read_wav_to_modem_samples(std::io::Cursor::new(wav)).unwrap().0
+    }
+
+    #[test]
+    fn test_read_8bit_unsigned_pcm() {
+        let samples = read_raw(raw_wav(WAVE_FORMAT_PCM, None, 1, 8, 0, &[0, 64, 128, 192, 255]));
+        assert_eq!(samples, vec![-1.0, -0.5, 0.0, 0.5, 127.0 / 128.0]);
+    }
+
+    #[test]
+    fn test_read_24bit_pcm_plain_and_extensible() {
+        let mut data = Vec::new();
+        for v in [-8_388_608i32, -4_194_304, 0, 4_194_304] {
+            data.extend_from_slice(&v.to_le_bytes()[..3]);
+        }
+        let expected = vec![-1.0, -0.5, 0.0, 0.5];
+        assert_eq!(read_raw(raw_wav(WAVE_FORMAT_PCM, None, 1, 24, 0, &data)), expected);
+        assert_eq!(
+            read_raw(raw_wav(WAVE_FORMAT_EXTENSIBLE, Some(WAVE_FORMAT_PCM), 1, 24, 24, &data)),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_read_extensible_24_in_32_left_justified() {
+        let mut data = Vec::new();
+        for v in [-4_194_304i32, 4_194_304] {
+            data.extend_from_slice(&(v << 8).to_le_bytes());
+        }
+        let samples = read_raw(raw_wav(WAVE_FORMAT_EXTENSIBLE, Some(WAVE_FORMAT_PCM), 1, 32, 24, &data));
+        assert_eq!(samples, vec![-0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_read_extensible_float_32_and_64() {
+        let mut f32_data = Vec::new();
+        let mut f64_data = Vec::new();
+        for v in [0.25f32, -0.75] {
+            f32_data.extend_from_slice(&v.to_le_bytes());
+            f64_data.extend_from_slice(&(v as f64).to_le_bytes());
+        }
+        let wav = raw_wav(WAVE_FORMAT_EXTENSIBLE, Some(WAVE_FORMAT_IEEE_FLOAT), 1, 32, 32, &f32_data);
+        let (samples, info) = read_wav_to_modem_samples(std::io::Cursor::new(wav)).unwrap();
+        assert!(info.is_float);
+        assert_eq!(samples, vec![0.25, -0.75]);
+        assert_eq!(
+            read_raw(raw_wav(WAVE_FORMAT_EXTENSIBLE, Some(WAVE_FORMAT_IEEE_FLOAT), 1, 64, 64, &f64_data)),
+            vec![0.25, -0.75]
+        );
+        assert_eq!(read_raw(raw_wav(WAVE_FORMAT_IEEE_FLOAT, None, 1, 64, 0, &f64_data)), vec![0.25, -0.75]);
+    }
+
+    #[test]
+    fn test_read_unsupported_format_tag() {
+        // IMA ADPCM
+        let result = read_wav_to_modem_samples(std::io::Cursor::new(raw_wav(0x0011, None, 1, 16, 0, &[0; 8])));
+        assert!(matches!(result, Err(AudioModemError::WavError(_))));
+    }
+
+    #[test]
+    fn test_read_invalid_wav() {
+        let result = read_wav_to_modem_samples(std::io::Cursor::new(b"not a wav".to_vec()));
+        assert!(matches!(result, Err(AudioModemError::WavError(_))));
+    }
+
+    #[test]
+    fn test_wav_stream_reader_matches_whole_file_read() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut bytes = Vec::new();
+        {
+            let mut writer = hound::WavWriter::new(std::io::Cursor::new(&mut bytes), spec).unwrap();
+            for s in tone(40000) {
+                writer.write_sample((s * 8_388_607.0) as i32).unwrap();
+                writer.write_sample((-s * 4_000_000.0) as i32).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        let (expected, _) = read_wav_to_modem_samples(std::io::Cursor::new(bytes.clone())).unwrap();
+
+        let mut reader = WavStreamReader::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.info().sample_rate, 44100);
+        assert_eq!(reader.info().channels, 2);
+        let mut streamed = Vec::new();
+        while reader.read_samples(&mut streamed, 3001).unwrap() > 0 {}
+
+        assert_eq!(streamed.len(), expected.len());
+        for (a, b) in streamed.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-4, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_wav_stream_reader_skips_chunks_and_rejects_garbage() {
+        let wav = raw_wav(WAVE_FORMAT_PCM, None, 1, 8, 0, &[0, 64, 128, 192, 255]);
+        let mut reader = WavStreamReader::new(std::io::Cursor::new(wav)).unwrap();
+        let mut samples = Vec::new();
+        while reader.read_samples(&mut samples, 2).unwrap() > 0 {}
+        assert_eq!(samples, vec![-1.0, -0.5, 0.0, 0.5, 127.0 / 128.0]);
+
+        let result = WavStreamReader::new(std::io::Cursor::new(b"not a wav".to_vec()));
+        assert!(matches!(result, Err(AudioModemError::WavError(_))));
+    }
+}
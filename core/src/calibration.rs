@@ -0,0 +1,273 @@
+//! Speaker/room calibration: measure the response across the FSK band and
+//! pre-emphasize the tones that arrive weak
+//!
+//! Play [`calibration_sweep`] through the speaker, record it where the
+//! receiver will be, and pass the recording to [`calibrate_from_sweep`]. The
+//! resulting [`PreEmphasis`] is set on the encoder
+//! (`EncoderFsk::set_pre_emphasis`) and can be saved with
+//! [`PreEmphasis::to_text`] for later sessions.
+
+use crate::error::{AudioModemError, Result};
+use crate::fsk::{bin_to_freq, FskDemodulator, FSK_NUM_BINS, FSK_SYMBOL_SAMPLES};
+use crate::sync::{detect_preamble, generate_keyed_preamble, DetectionThreshold};
+use crate::trig;
+use crate::units::Samples;
+use crate::{PREAMBLE_SAMPLES, SAMPLE_RATE, SYNC_SILENCE_SAMPLES};
+
+/// Length of the frequency sweep (3 s)
+pub const CALIBRATION_SWEEP_SAMPLES: usize = 3 * SAMPLE_RATE;
+
+/// Most a tone is boosted relative to the others; deeper dips are left to the FEC
+pub const MAX_BOOST_DB: f32 = 12.0;
+
+/// The sweep covers the FSK band plus two bin spacings on each side
+const SWEEP_MARGIN_HZ: f32 = 40.0;
+
+/// Peak amplitude of the sweep (sync signals use 0.5 as well)
+const SWEEP_AMPLITUDE: f32 = 0.5;
+
+/// Fade at both ends of the sweep (10 ms)
+const SWEEP_FADE_SAMPLES: usize = 160;
+
+/// First line of a saved profile
+const PROFILE_HEADER: &str = "# transmitwave pre-emphasis v1: bin, frequency (Hz), gain (dB)";
+
+/// Floor for energies, so silent bins stay finite in dB
+const LEVEL_EPSILON: f32 = 1e-9;
+
+/// The calibration signal to play: preamble, then a linear sweep across the
+/// FSK band, each after a silence gap, and a silent tail for the room's echoes
+pub fn calibration_sweep() -> Vec<f32> {
+    let start_hz = (bin_to_freq(0) - SWEEP_MARGIN_HZ) as f64;
+    let end_hz = (bin_to_freq(FSK_NUM_BINS - 1) + SWEEP_MARGIN_HZ) as f64;
+    let duration = CALIBRATION_SWEEP_SAMPLES as f64 / SAMPLE_RATE as f64;
+
+    let mut samples = vec![0.0; SYNC_SILENCE_SAMPLES];
+    samples.extend(generate_keyed_preamble(Samples(PREAMBLE_SAMPLES), 0.5, None));
+    samples.extend(vec![0.0; SYNC_SILENCE_SAMPLES]);
+    samples.extend((0..CALIBRATION_SWEEP_SAMPLES).map(|i| {
+        let t = i as f64 / SAMPLE_RATE as f64;
+        let cycles = start_hz * t + (end_hz - start_hz) * t * t / (2.0 * duration);
+        let fade = i.min(CALIBRATION_SWEEP_SAMPLES - 1 - i).min(SWEEP_FADE_SAMPLES) as f32 / SWEEP_FADE_SAMPLES as f32;
+        SWEEP_AMPLITUDE * fade * trig::sin((2.0 * std::f64::consts::PI * cycles.fract()) as f32)
+    }));
+    samples.extend(vec![0.0; 2 * SYNC_SILENCE_SAMPLES]);
+    samples
+}
+
+/// Per-bin tone gains that even out a measured speaker/room response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreEmphasis {
+    /// Linear amplitude of each bin's tone, at most 1.0
+    gains: [f32; FSK_NUM_BINS],
+}
+
+impl Default for PreEmphasis {
+    /// Flat: every tone at full level
+    fn default() -> Self {
+        Self { gains: [1.0; FSK_NUM_BINS] }
+    }
+}
+
+impl PreEmphasis {
+    /// Gains compensating `response_db`, the received level of each bin in dB
+    /// (any reference)
+    ///
+    /// Bins below the median level are boosted by their shortfall, up to
+    /// [`MAX_BOOST_DB`], after averaging each bin with its neighbours so
+    /// narrow room notches are not chased. Gains are scaled so the most
+    /// boosted bin is at full level and the symbols never get louder.
+    pub fn from_response_db(response_db: &[f32; FSK_NUM_BINS]) -> Self {
+        let smoothed: Vec<f32> = (0..FSK_NUM_BINS)
+            .map(|bin| {
+                let neighbours = &response_db[bin.saturating_sub(1)..(bin + 2).min(FSK_NUM_BINS)];
+                neighbours.iter().sum::<f32>() / neighbours.len() as f32
+            })
+            .collect();
+        let median = median(&smoothed);
+        let boost_db: Vec<f32> = smoothed.iter().map(|level| (median - level).clamp(0.0, MAX_BOOST_DB)).collect();
+        let max_boost = boost_db.iter().copied().fold(0.0f32, f32::max);
+
+        let mut gains = [1.0; FSK_NUM_BINS];
+        for (gain, boost) in gains.iter_mut().zip(boost_db) {
+            *gain = trig::db_to_amplitude(boost - max_boost);
+        }
+        Self { gains }
+    }
+
+    /// Linear amplitude of each bin's tone (see `FskModulator::set_bin_gains`)
+    pub fn gains(&self) -> [f32; FSK_NUM_BINS] {
+        self.gains
+    }
+
+    /// Boost of `bin` relative to the quietest tone, in dB (None past the last bin)
+    pub fn boost_db(&self, bin: usize) -> Option<f32> {
+        let quietest = self.gains.iter().copied().fold(1.0f32, f32::min);
+        self.gains.get(bin).map(|gain| 20.0 * (gain / quietest).log10())
+    }
+
+    /// Profile file contents: a comment line, then `bin frequency gain_db` per bin
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", PROFILE_HEADER);
+        for (bin, gain) in self.gains.iter().enumerate() {
+            text.push_str(&format!("{} {} {:.2}\n", bin, bin_to_freq(bin), 20.0 * gain.log10()));
+        }
+        text
+    }
+
+    /// Parse a profile written by [`PreEmphasis::to_text`]
+    pub fn from_text(text: &str) -> Result<Self> {
+        let invalid = |reason: String| AudioModemError::InvalidConfig(format!("pre-emphasis profile: {}", reason));
+        let mut gains = [1.0; FSK_NUM_BINS];
+        let mut count = 0;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [bin, _, gain_db] = fields.as_slice() else {
+                return Err(invalid(format!("expected `bin frequency gain_db`, got `{}`", line)));
+            };
+            let bin: usize = bin.parse().map_err(|_| invalid(format!("invalid bin `{}`", bin)))?;
+            let gain_db: f32 = gain_db.parse().map_err(|_| invalid(format!("invalid gain `{}`", gain_db)))?;
+            if bin != count || bin >= FSK_NUM_BINS {
+                return Err(invalid(format!("bin {} out of order", bin)));
+            }
+            if !gain_db.is_finite() || gain_db > 0.0 {
+                return Err(invalid(format!("gain {} dB of bin {} must be at most 0", gain_db, bin)));
+            }
+            gains[bin] = trig::db_to_amplitude(gain_db);
+            count += 1;
+        }
+        if count != FSK_NUM_BINS {
+            return Err(invalid(format!("{} of {} bins", count, FSK_NUM_BINS)));
+        }
+        Ok(Self { gains })
+    }
+}
+
+/// Measure the response in a recording of [`calibration_sweep`] and compute
+/// the pre-emphasis that compensates it
+///
+/// The recording may hold silence before and after the sweep, and any overall gain.
+pub fn calibrate_from_sweep(recording: &[f32]) -> Result<PreEmphasis> {
+    let reference = calibration_sweep();
+    let reference_preamble =
+        detect_preamble(&reference, DetectionThreshold::Adaptive).ok_or(AudioModemError::PreambleNotFound)?;
+    let preamble = detect_preamble(recording, DetectionThreshold::Adaptive).ok_or(AudioModemError::PreambleNotFound)?;
+
+    // Compare from the start of the sweep, echo tail included
+    let sweep_offset = PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+    let len = (reference.len() - reference_preamble).min(recording.len() - preamble);
+    if len < sweep_offset + CALIBRATION_SWEEP_SAMPLES {
+        return Err(AudioModemError::InsufficientData);
+    }
+    let sent = &reference[reference_preamble + sweep_offset..reference_preamble + len];
+    let received = &recording[preamble + sweep_offset..preamble + len];
+    Ok(PreEmphasis::from_response_db(&bin_response_db(sent, received)))
+}
+
+/// Received/sent energy at each FSK bin in dB
+fn bin_response_db(sent: &[f32], received: &[f32]) -> [f32; FSK_NUM_BINS] {
+    let demodulator = FskDemodulator::new();
+    let mut sent_energy = [0.0f32; FSK_NUM_BINS];
+    let mut received_energy = [0.0f32; FSK_NUM_BINS];
+    for (sent, received) in sent.chunks_exact(FSK_SYMBOL_SAMPLES).zip(received.chunks_exact(FSK_SYMBOL_SAMPLES)) {
+        for (energy, power) in sent_energy.iter_mut().zip(demodulator.bin_powers(sent)) {
+            *energy += power;
+        }
+        for (energy, power) in received_energy.iter_mut().zip(demodulator.bin_powers(received)) {
+            *energy += power;
+        }
+    }
+
+    let mut response_db = [0.0f32; FSK_NUM_BINS];
+    for ((level, received), sent) in response_db.iter_mut().zip(received_energy).zip(sent_energy) {
+        *level = 10.0 * ((received + LEVEL_EPSILON) / (sent + LEVEL_EPSILON)).log10();
+    }
+    response_db
+}
+
+fn median(values: &[f32]) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted[sorted.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderFsk, EncoderFsk};
+    use crate::fsk::FskModulator;
+
+    /// Speaker rolling off above ~1 kHz (two one-pole low-passes), played with a delay
+    fn muffled(samples: &[f32]) -> Vec<f32> {
+        let alpha = 1.0 - (-2.0 * std::f32::consts::PI * 1000.0 / SAMPLE_RATE as f32).exp();
+        let (mut first, mut second) = (0.0f32, 0.0f32);
+        let mut recording = vec![0.0f32; 1234];
+        recording.extend(samples.iter().map(|&sample| {
+            first += alpha * (sample - first);
+            second += alpha * (first - second);
+            0.8 * second
+        }));
+        recording.extend(vec![0.0f32; 2000]);
+        recording
+    }
+
+    /// Received power of the lowest tone over the highest, in dB
+    fn tilt_db(modulator: &mut FskModulator) -> f32 {
+        // Band 0 on its lowest bin, band 5 on its highest
+        let received = muffled(&modulator.modulate(&[0x00, 0x00, 0x0F]).unwrap());
+        let powers = FskDemodulator::new().bin_powers(&received[1234..1234 + FSK_SYMBOL_SAMPLES]);
+        10.0 * (powers[0] / powers[FSK_NUM_BINS - 1]).log10()
+    }
+
+    #[test]
+    fn test_flat_channel_needs_no_emphasis() {
+        let mut recording = vec![0.0f32; 3000];
+        recording.extend(calibration_sweep().iter().map(|s| 0.3 * s));
+        let emphasis = calibrate_from_sweep(&recording).unwrap();
+        assert!((0..FSK_NUM_BINS).all(|bin| emphasis.boost_db(bin).unwrap() < 1.0), "{:?}", emphasis);
+    }
+
+    #[test]
+    fn test_emphasis_evens_out_rolloff() {
+        let emphasis = calibrate_from_sweep(&muffled(&calibration_sweep())).unwrap();
+        assert!(emphasis.boost_db(FSK_NUM_BINS - 1).unwrap() > 4.0, "{:?}", emphasis);
+        assert!(emphasis.boost_db(0).unwrap() < 0.5, "{:?}", emphasis);
+        assert_eq!(emphasis.boost_db(FSK_NUM_BINS), None);
+        assert!(emphasis.gains().iter().all(|&gain| gain <= 1.0));
+
+        let mut modulator = FskModulator::new();
+        let plain = tilt_db(&mut modulator);
+        modulator.set_bin_gains(Some(emphasis.gains()));
+        let emphasized = tilt_db(&mut modulator);
+        assert!(emphasized.abs() < plain.abs() - 3.0, "{} dB vs {} dB", emphasized, plain);
+
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_pre_emphasis(Some(emphasis));
+        let frame = muffled(&encoder.encode(b"calibrated").unwrap());
+        assert_eq!(DecoderFsk::new().unwrap().decode(&frame).unwrap(), b"calibrated");
+    }
+
+    #[test]
+    fn test_profile_text_roundtrip() {
+        let emphasis = calibrate_from_sweep(&muffled(&calibration_sweep())).unwrap();
+        let text = emphasis.to_text();
+        assert!(text.starts_with(PROFILE_HEADER));
+        let parsed = PreEmphasis::from_text(&text).unwrap();
+        for (a, b) in parsed.gains().iter().zip(emphasis.gains()) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+
+        assert_eq!(PreEmphasis::from_text(&PreEmphasis::default().to_text()).unwrap(), PreEmphasis::default());
+        assert!(PreEmphasis::from_text("0 800 -1.0\n").is_err());
+        assert!(PreEmphasis::from_text(&text.replace("\n1 820", "\n2 820")).is_err());
+        assert!(PreEmphasis::from_text(&PreEmphasis::default().to_text().replace(" 0.00\n", " 3.00\n")).is_err());
+    }
+
+    #[test]
+    fn test_no_sweep_in_recording() {
+        assert!(matches!(
+            calibrate_from_sweep(&vec![0.0; CALIBRATION_SWEEP_SAMPLES]),
+            Err(AudioModemError::PreambleNotFound)
+        ));
+    }
+}
@@ -0,0 +1,663 @@
+use crate::decoder_fsk::{DecodeStats, DecoderFsk};
+use crate::encoder_fsk::{EncoderFsk, FrameProfile};
+use crate::error::{AudioModemError, Result};
+use crate::fountain::FountainReceiver;
+use crate::fountain_packet::parse_fountain_block;
+use crate::framing::FrameDecoder;
+use crate::fountain_stream::{FountainConfig, FountainStream};
+use crate::fsk::{FskDemodulator, FSK_SYMBOL_SAMPLES};
+use crate::melody::fountain_preamble;
+use crate::sync::{detect_fountain_template_candidate, detect_keyed_preamble_candidates, DetectionThreshold};
+use crate::{POSTAMBLE_SAMPLES, PREAMBLE_SAMPLES, SAMPLE_RATE, SYNC_SILENCE_SAMPLES};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Extra bytes a carousel block carries over a plain fountain block (message ID and version)
+const MESSAGE_HEADER_BYTES: u16 = 3;
+
+/// Longest interjected frame (20 s, about 200 payload bytes); the decoder
+/// gives up on a frame that has not decoded within this much audio
+pub const MAX_INTERJECTION_SAMPLES: usize = 20 * SAMPLE_RATE;
+
+/// Most audio the decoder keeps between calls while waiting for the rest of
+/// a block or interjected frame
+const MAX_PENDING_SAMPLES: usize = 30 * SAMPLE_RATE;
+
+/// How soon an interjected frame goes out (see [`CarouselEncoder::interject`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum InterjectionPriority {
+    /// At the end of the current round, once every message has sent its block
+    #[default]
+    Normal,
+    /// Before the next block
+    Urgent,
+}
+
+struct CarouselMessage {
+    id: u16,
+    /// Bumped whenever the content is replaced, so listeners drop blocks of the old one
+    version: u8,
+    stream: FountainStream,
+}
+
+/// Data carousel encoder that broadcasts a rotating set of payloads
+///
+/// Each payload gets its own RaptorQ fountain stream. Blocks are interleaved
+/// round-robin across messages and tagged with the message ID and version:
+/// [message_id u16][version u8][frame_len u32][symbol_size u16][packet_len u16][packet][crc16]
+///
+/// Payloads queued with [`CarouselEncoder::interject`] preempt the rotation
+/// as standard frames, whose chirp preamble the decoder tells apart from the
+/// fountain whistle. Blocks and frames share the settings of the
+/// `EncoderFsk` the carousel was built with (network key, melody, levels).
+///
+/// The iterator is endless while at least one message is loaded.
+pub struct CarouselEncoder {
+    encoder: EncoderFsk,
+    config: FountainConfig,
+    messages: Vec<CarouselMessage>,
+    cursor: usize,
+    /// Encoded standard frames waiting to preempt the rotation, oldest first
+    interjections: VecDeque<(InterjectionPriority, Vec<f32>)>,
+}
+
+impl CarouselEncoder {
+    pub fn new(config: Option<FountainConfig>) -> Self {
+        Self::with_encoder(EncoderFsk::default(), config)
+    }
+
+    /// Carousel whose blocks and interjections are encoded by `encoder`
+    pub fn with_encoder(encoder: EncoderFsk, config: Option<FountainConfig>) -> Self {
+        let mut config = config.unwrap_or_default();
+        // The carousel runs until the caller stops pulling blocks
+        config.timeout_secs = 0;
+        Self {
+            encoder,
+            config,
+            messages: Vec::new(),
+            cursor: 0,
+            interjections: VecDeque::new(),
+        }
+    }
+
+    /// Add a payload to the carousel, replacing any message with the same ID
+    pub fn add_message(&mut self, id: u16, data: &[u8]) -> Result<()> {
+        let stream = self.encoder.encode_fountain(data, Some(self.config.clone()))?;
+        match self.messages.iter_mut().find(|m| m.id == id) {
+            Some(existing) => {
+                existing.version = existing.version.wrapping_add(1);
+                existing.stream = stream;
+            }
+            None => self.messages.push(CarouselMessage { id, version: 0, stream }),
+        }
+        Ok(())
+    }
+
+    /// Remove a payload from the carousel. Returns true if it was present.
+    pub fn remove_message(&mut self, id: u16) -> bool {
+        let before = self.messages.len();
+        self.messages.retain(|m| m.id != id);
+        if self.cursor >= self.messages.len() {
+            self.cursor = 0;
+        }
+        self.messages.len() != before
+    }
+
+    /// Send `data` as a standard frame ahead of the rotation
+    ///
+    /// Urgent frames go out before the next block, normal ones at the end of
+    /// the current round; frames of the same priority go out in the order
+    /// queued, and the fountain streams resume where they left off. Fails for
+    /// encoders set to a profile other than `FrameProfile::Standard` and for
+    /// frames longer than [`MAX_INTERJECTION_SAMPLES`].
+    pub fn interject(&mut self, data: &[u8], priority: InterjectionPriority) -> Result<()> {
+        if self.encoder.profile() != FrameProfile::Standard {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "carousel interjections need the standard profile, not {}",
+                self.encoder.profile()
+            )));
+        }
+        let frame = self.encoder.encode(data)?;
+        if frame.len() > MAX_INTERJECTION_SAMPLES {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "a {}-byte interjection lasts {} ms, over the {} ms limit",
+                data.len(),
+                frame.len() * 1000 / SAMPLE_RATE,
+                MAX_INTERJECTION_SAMPLES * 1000 / SAMPLE_RATE
+            )));
+        }
+        self.interjections.push_back((priority, frame));
+        Ok(())
+    }
+
+    /// Interjections queued but not yet pulled from the iterator
+    pub fn pending_interjections(&self) -> usize {
+        self.interjections.len()
+    }
+
+    /// IDs of the messages currently on the carousel, in rotation order
+    pub fn message_ids(&self) -> Vec<u16> {
+        self.messages.iter().map(|m| m.id).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+impl Iterator for CarouselEncoder {
+    type Item = Vec<f32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor >= self.messages.len() {
+            self.cursor = 0;
+        }
+        let urgent = self.interjections.iter().position(|(priority, _)| *priority == InterjectionPriority::Urgent);
+        let round_end = self.cursor == 0 && !self.interjections.is_empty();
+        if let Some(index) = urgent.or(round_end.then_some(0)) {
+            return self.interjections.remove(index).map(|(_, frame)| frame);
+        }
+        if self.messages.is_empty() {
+            return None;
+        }
+
+        let index = self.cursor;
+        self.cursor = (self.cursor + 1) % self.messages.len();
+        let message = &mut self.messages[index];
+
+        let mut prefix = [0u8; MESSAGE_HEADER_BYTES as usize];
+        prefix[..2].copy_from_slice(&message.id.to_be_bytes());
+        prefix[2] = message.version;
+        message.stream.next_tagged_block(&prefix)
+    }
+}
+
+struct MessageAssembly {
+    version: u8,
+    frame_length: usize,
+    receiver: FountainReceiver,
+}
+
+/// Decoder for carousel broadcasts
+///
+/// Keeps per-message RaptorQ state and any unfinished block or frame across
+/// calls, so audio can be fed in arbitrary pieces and any message the listener
+/// catches enough blocks of is assembled independently of the others. Blocks
+/// of a replaced message (a new version) restart its assembly. Standard frames
+/// interjected between blocks are decoded by the `DecoderFsk` the carousel was
+/// built with, reported by [`CarouselDecoder::take_interjections`] and skipped
+/// by the block search.
+pub struct CarouselDecoder {
+    decoder: DecoderFsk,
+    fsk: FskDemodulator,
+    /// Fountain preamble of the decoder's network key or melody
+    block_template: Vec<f32>,
+    preamble_threshold: DetectionThreshold,
+    symbol_size: Option<u16>,
+    payload_samples_per_block: usize,
+    messages: HashMap<u16, MessageAssembly>,
+    completed: BTreeMap<u16, Vec<u8>>,
+    /// Version each completed message was assembled from
+    completed_versions: HashMap<u16, u8>,
+    interjections: Vec<Result<Vec<u8>>>,
+    /// Audio after the last whole block or frame, kept for the next call
+    pending: Vec<f32>,
+    pub stats: DecodeStats,
+}
+
+impl CarouselDecoder {
+    pub fn new(config: Option<FountainConfig>) -> Self {
+        Self::with_decoder(DecoderFsk::default(), config)
+    }
+
+    /// Carousel decoder that finds blocks and frames of `decoder`'s network
+    /// key or melody, and decodes interjected frames with it
+    pub fn with_decoder(decoder: DecoderFsk, config: Option<FountainConfig>) -> Self {
+        let config = config.unwrap_or_default();
+        Self {
+            fsk: FskDemodulator::new(),
+            block_template: fountain_preamble(1.0, decoder.network_key(), decoder.fountain_melody()),
+            preamble_threshold: decoder.get_preamble_threshold(),
+            decoder,
+            symbol_size: None,
+            payload_samples_per_block: Self::payload_samples(config.block_size as u16),
+            messages: HashMap::new(),
+            completed: BTreeMap::new(),
+            completed_versions: HashMap::new(),
+            interjections: Vec::new(),
+            pending: Vec::new(),
+            stats: DecodeStats::default(),
+        }
+    }
+
+    /// Set the detection threshold for the fountain and frame preambles
+    pub fn set_preamble_threshold(&mut self, threshold: DetectionThreshold) {
+        self.preamble_threshold = threshold.clamped();
+    }
+
+    /// Process audio and return the messages completed during this call
+    ///
+    /// A block or frame cut off at the end is kept and finished by the next call.
+    pub fn decode(&mut self, samples: &[f32]) -> Vec<(u16, Vec<u8>)> {
+        let mut buffer = std::mem::take(&mut self.pending);
+        buffer.extend_from_slice(samples);
+        let mut newly_completed = Vec::new();
+        let consumed = self.decode_buffer(&buffer, &mut newly_completed);
+        buffer.drain(..consumed.max(buffer.len().saturating_sub(MAX_PENDING_SAMPLES)));
+        self.pending = buffer;
+        newly_completed
+    }
+
+    /// Decode the blocks and frames in `samples`; returns where the first
+    /// unfinished one starts (everything before it is done with)
+    fn decode_buffer(&mut self, samples: &[f32], newly_completed: &mut Vec<(u16, Vec<u8>)>) -> usize {
+        let mut search_offset = 0;
+
+        loop {
+            let remaining = &samples[search_offset..];
+            let search_len = remaining
+                .len()
+                .min(PREAMBLE_SAMPLES + self.payload_samples_per_block);
+            if search_len < PREAMBLE_SAMPLES {
+                return search_offset;
+            }
+            let window = &remaining[..search_len];
+            let block = detect_fountain_template_candidate(window, &self.block_template, self.preamble_threshold);
+            let frame = detect_keyed_preamble_candidates(window, self.preamble_threshold, 1, self.decoder.network_key())
+                .into_iter()
+                .next();
+            let preamble_pos = match (block, frame) {
+                // The stronger match wins; each preamble correlates weakly with the other
+                (_, Some(frame)) if block.is_none_or(|block| frame.score > block.score) => {
+                    match self.decode_interjection(remaining, frame.position) {
+                        Some(frame_end) => {
+                            search_offset += frame_end;
+                            continue;
+                        }
+                        None => return search_offset,
+                    }
+                }
+                (Some(block), _) => block.position,
+                // Nothing here; a preamble may be starting at the end of the window
+                (None, _) if search_len == remaining.len() => return search_offset + search_len - PREAMBLE_SAMPLES,
+                (None, _) => {
+                    search_offset += search_len - PREAMBLE_SAMPLES;
+                    continue;
+                }
+            };
+
+            let data_start = search_offset + preamble_pos + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+            let data_end = data_start.saturating_add(self.payload_samples_per_block);
+            if data_start + FSK_SYMBOL_SAMPLES > samples.len() || data_end > samples.len() {
+                // Detected again, with the whole preamble, once the rest arrives
+                return search_offset;
+            }
+
+            if let Ok(block_data) = self.fsk.demodulate(&samples[data_start..data_end]) {
+                if let Some((id, payload)) = self.process_block(&block_data) {
+                    newly_completed.push((id, payload));
+                }
+            }
+
+            search_offset = data_end;
+        }
+    }
+
+    /// All messages assembled so far, keyed by message ID
+    pub fn completed_messages(&self) -> &BTreeMap<u16, Vec<u8>> {
+        &self.completed
+    }
+
+    /// Remove and return an assembled message
+    pub fn take_message(&mut self, id: u16) -> Option<Vec<u8>> {
+        self.completed.remove(&id)
+    }
+
+    /// Remove and return the interjected standard frames found so far, oldest
+    /// first: the payload, or why a frame whose preamble was heard did not decode
+    pub fn take_interjections(&mut self) -> Vec<Result<Vec<u8>>> {
+        std::mem::take(&mut self.interjections)
+    }
+
+    /// Drop all partial and completed messages
+    pub fn reset(&mut self) {
+        self.messages.clear();
+        self.completed.clear();
+        self.completed_versions.clear();
+        self.interjections.clear();
+        self.pending.clear();
+        self.stats = DecodeStats::default();
+    }
+
+    /// Decode the standard frame whose preamble starts at `preamble_pos`
+    ///
+    /// The frame's length prefix tells where it ends, so the blocks after it
+    /// are searched next. Returns that end, None to wait for more audio, or
+    /// the data start (past the preamble) once [`MAX_INTERJECTION_SAMPLES`]
+    /// of audio have arrived without the frame decoding.
+    fn decode_interjection(&mut self, samples: &[f32], preamble_pos: usize) -> Option<usize> {
+        let data_start = preamble_pos + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+        let limit = data_start + MAX_INTERJECTION_SAMPLES;
+        let bounded = &samples[..limit.min(samples.len())];
+        match self.decoder.decode_at(bounded, data_start, None) {
+            Ok(message) => {
+                self.interjections.push(Ok(message.payload));
+                Some((message.data_end + SYNC_SILENCE_SAMPLES + POSTAMBLE_SAMPLES).min(samples.len()))
+            }
+            // Possibly cut off; tried again with more audio
+            Err(_) if samples.len() < limit => None,
+            Err(e) => {
+                self.interjections.push(Err(e));
+                Some(data_start)
+            }
+        }
+    }
+    fn process_block(&mut self, block: &[u8]) -> Option<(u16, Vec<u8>)> {
+        if block.len() < MESSAGE_HEADER_BYTES as usize {
+            return None;
+        }
+        let id = u16::from_be_bytes([block[0], block[1]]);
+        let version = block[2];
+        let block = parse_fountain_block(&block[MESSAGE_HEADER_BYTES as usize..])?;
+        if !block.crc_ok {
+            self.stats.failed_blocks += 1;
+            return None;
+        }
+        self.stats.decoded_blocks += 1;
+
+        // All carousel messages share one block size; latch it from the first valid block
+        match self.symbol_size {
+            Some(existing) if existing != block.symbol_size => return None,
+            Some(_) => {}
+            None => {
+                self.symbol_size = Some(block.symbol_size);
+                self.payload_samples_per_block = Self::payload_samples(block.symbol_size);
+            }
+        }
+
+        let frame_length = block.frame_length;
+        // A completed message is only reassembled once the sender replaces its content
+        if self.completed_versions.get(&id) == Some(&version) {
+            return None;
+        }
+
+        if self.messages.get(&id).is_none_or(|assembly| {
+            assembly.version != version || assembly.frame_length != frame_length || assembly.receiver.is_lt() != block.lt
+        }) {
+            let receiver = FountainReceiver::new(&block).ok()?;
+            self.messages.insert(id, MessageAssembly { version, frame_length, receiver });
+        }
+        let assembly = self.messages.get_mut(&id)?;
+
+        let decoded = assembly.receiver.add_packet(block.packet).ok()??;
+        let frame = FrameDecoder::decode(&decoded).ok()?;
+        self.messages.remove(&id);
+        self.completed_versions.insert(id, version);
+        self.completed.insert(id, frame.payload.clone());
+        Some((id, frame.payload))
+    }
+
+    fn payload_samples(symbol_size: u16) -> usize {
+        DecoderFsk::fountain_payload_samples(symbol_size.saturating_add(MESSAGE_HEADER_BYTES))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{detect_preamble, NetworkKey};
+
+    fn test_config() -> FountainConfig {
+        FountainConfig {
+            timeout_secs: 0,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_carousel_roundtrip_multiple_messages() {
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(1, b"Program guide for today").unwrap();
+        encoder.add_message(2, b"Station logo").unwrap();
+        encoder.add_message(7, b"Traffic update: all clear on route 9").unwrap();
+        assert_eq!(encoder.message_ids(), vec![1, 2, 7]);
+
+        let samples: Vec<f32> = encoder.by_ref().take(30).flatten().collect();
+
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        let completed = decoder.decode(&samples);
+        assert_eq!(completed.len(), 3);
+
+        let messages = decoder.completed_messages();
+        assert_eq!(messages[&1], b"Program guide for today");
+        assert_eq!(messages[&2], b"Station logo");
+        assert_eq!(messages[&7], b"Traffic update: all clear on route 9");
+    }
+
+    #[test]
+    fn test_carousel_join_mid_stream_and_incremental_feed() {
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(10, b"first message").unwrap();
+        encoder.add_message(11, b"second message").unwrap();
+
+        // Listener tunes in late and hears the broadcast in separate pieces
+        let blocks: Vec<Vec<f32>> = encoder.take(40).collect();
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        let mut found = Vec::new();
+        for block in blocks.iter().skip(5) {
+            found.extend(decoder.decode(block));
+        }
+
+        found.sort();
+        assert_eq!(
+            found,
+            vec![(10, b"first message".to_vec()), (11, b"second message".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_carousel_blocks_split_across_calls_and_new_versions() {
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(5, b"version one").unwrap();
+        let samples: Vec<f32> = encoder.by_ref().take(6).flatten().collect();
+
+        // Pieces much shorter than a block, cutting through preambles and data
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        let mut found = Vec::new();
+        for piece in samples.chunks(7919) {
+            found.extend(decoder.decode(piece));
+        }
+        assert_eq!(found, vec![(5, b"version one".to_vec())]);
+
+        // Replaced by content of the same length: assembled afresh, not mixed with the old blocks
+        encoder.add_message(5, b"version two").unwrap();
+        let samples: Vec<f32> = encoder.by_ref().take(6).flatten().collect();
+        for piece in samples.chunks(7919) {
+            found.extend(decoder.decode(piece));
+        }
+        assert_eq!(found[1..], [(5, b"version two".to_vec())]);
+        assert_eq!(decoder.completed_messages()[&5], b"version two");
+        assert_eq!(decoder.stats.failed_blocks, 0);
+    }
+
+    #[test]
+    fn test_carousel_replace_and_remove_message() {
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(3, b"old").unwrap();
+        encoder.add_message(3, b"replacement content").unwrap();
+        encoder.add_message(4, b"temporary").unwrap();
+        assert_eq!(encoder.len(), 2);
+
+        assert!(encoder.remove_message(4));
+        assert!(!encoder.remove_message(4));
+
+        let samples: Vec<f32> = encoder.by_ref().take(10).flatten().collect();
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        decoder.decode(&samples);
+        assert_eq!(decoder.take_message(3).unwrap(), b"replacement content");
+        assert!(decoder.take_message(4).is_none());
+    }
+
+    #[test]
+    fn test_carousel_interjection_preempts_blocks() {
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(1, b"Long broadcast that keeps going").unwrap();
+        encoder.add_message(2, b"Second program").unwrap();
+
+        let mut frames: Vec<Vec<f32>> = encoder.by_ref().take(4).collect();
+        encoder.interject(b"URGENT: stop", InterjectionPriority::Urgent).unwrap();
+        encoder.interject(b"second alert", InterjectionPriority::Urgent).unwrap();
+        assert_eq!(encoder.pending_interjections(), 2);
+        let alert = encoder.next().unwrap();
+        // Standard frames, not fountain blocks, come out first
+        assert_eq!(alert, EncoderFsk::new().unwrap().encode(b"URGENT: stop").unwrap());
+        frames.push(alert);
+        frames.extend(encoder.by_ref().take(30));
+        assert_eq!(encoder.pending_interjections(), 0);
+
+        // All at once and block by block
+        let samples: Vec<f32> = frames.iter().flatten().copied().collect();
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        decoder.decode(&samples);
+        let mut incremental = CarouselDecoder::new(Some(test_config()));
+        for frame in &frames {
+            incremental.decode(frame);
+        }
+
+        for decoder in [&mut decoder, &mut incremental] {
+            let interjections: Vec<Vec<u8>> = decoder.take_interjections().into_iter().map(Result::unwrap).collect();
+            assert_eq!(interjections, vec![b"URGENT: stop".to_vec(), b"second alert".to_vec()]);
+            assert!(decoder.take_interjections().is_empty());
+            assert_eq!(decoder.completed_messages()[&1], b"Long broadcast that keeps going");
+            assert_eq!(decoder.completed_messages()[&2], b"Second program");
+            assert_eq!(decoder.stats.failed_blocks, 0);
+        }
+    }
+
+    #[test]
+    fn test_carousel_interjection_priorities() {
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(1, b"first program").unwrap();
+        encoder.add_message(2, b"second program").unwrap();
+        let frame = |data: &[u8]| EncoderFsk::new().unwrap().encode(data).unwrap();
+
+        encoder.next().unwrap();
+        encoder.interject(b"routine update", InterjectionPriority::Normal).unwrap();
+        encoder.interject(b"alert", InterjectionPriority::Urgent).unwrap();
+        assert_eq!(encoder.next().unwrap(), frame(b"alert"));
+        // The normal frame waits for the round to finish
+        encoder.next().unwrap();
+        assert_eq!(encoder.pending_interjections(), 1);
+        assert_eq!(encoder.next().unwrap(), frame(b"routine update"));
+        assert_eq!(encoder.pending_interjections(), 0);
+
+        // Too long to interject, or not a standard frame
+        assert!(encoder.interject(&[0x55; 600], InterjectionPriority::Urgent).is_err());
+        let mut short = EncoderFsk::new().unwrap();
+        short.set_profile(FrameProfile::Short);
+        assert!(CarouselEncoder::with_encoder(short, None).interject(b"x", InterjectionPriority::Urgent).is_err());
+    }
+
+    #[test]
+    fn test_keyed_carousel() {
+        let key = Some(NetworkKey::new(b"carousel-net"));
+        let mut keyed = EncoderFsk::new().unwrap();
+        keyed.set_network_key(key);
+        let mut encoder = CarouselEncoder::with_encoder(keyed, Some(test_config()));
+        encoder.add_message(1, b"keyed guide").unwrap();
+        encoder.interject(b"keyed alert", InterjectionPriority::Urgent).unwrap();
+        let samples: Vec<f32> = encoder.take(12).flatten().collect();
+
+        let mut keyed = DecoderFsk::new().unwrap();
+        keyed.set_network_key(key);
+        let mut decoder = CarouselDecoder::with_decoder(keyed, Some(test_config()));
+        assert_eq!(decoder.decode(&samples), vec![(1, b"keyed guide".to_vec())]);
+        assert_eq!(decoder.take_interjections().into_iter().map(Result::unwrap).collect::<Vec<_>>(), vec![b"keyed alert".to_vec()]);
+
+        // Listeners of other networks hear nothing
+        assert!(CarouselDecoder::new(Some(test_config())).decode(&samples).is_empty());
+    }
+
+    #[test]
+    fn test_carousel_melody() {
+        let melody = crate::Melody::from_notes([(659.0, 60), (988.0, 60), (1319.0, 130)]).unwrap();
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_fountain_melody(Some(melody.clone()));
+        let mut encoder = CarouselEncoder::with_encoder(encoder, Some(test_config()));
+        encoder.add_message(4, b"jingle first").unwrap();
+        let samples: Vec<f32> = encoder.take(8).flatten().collect();
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_fountain_melody(Some(melody));
+        let mut decoder = CarouselDecoder::with_decoder(decoder, Some(test_config()));
+        assert_eq!(decoder.decode(&samples), vec![(4, b"jingle first".to_vec())]);
+    }
+
+    #[test]
+    fn test_carousel_pre_emphasis() {
+        // A speaker that loses 6 dB toward the top of the band
+        let response: [f32; crate::fsk::FSK_NUM_BINS] = std::array::from_fn(|bin| -6.0 * bin as f32 / (crate::fsk::FSK_NUM_BINS - 1) as f32);
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_pre_emphasis(Some(crate::calibration::PreEmphasis::from_response_db(&response)));
+        let mut emphasized = CarouselEncoder::with_encoder(encoder, Some(test_config()));
+        let mut plain = CarouselEncoder::new(Some(test_config()));
+        emphasized.add_message(1, b"tilted").unwrap();
+        plain.add_message(1, b"tilted").unwrap();
+        emphasized.interject(b"tilted alert", InterjectionPriority::Urgent).unwrap();
+        plain.interject(b"tilted alert", InterjectionPriority::Urgent).unwrap();
+
+        // Frames and blocks both carry the emphasis
+        let emphasized: Vec<Vec<f32>> = emphasized.take(6).collect();
+        let plain: Vec<Vec<f32>> = plain.take(6).collect();
+        assert_ne!(emphasized[0], plain[0]);
+        assert_ne!(emphasized[1], plain[1]);
+
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        assert_eq!(decoder.decode(&emphasized.concat()), vec![(1, b"tilted".to_vec())]);
+        assert_eq!(decoder.take_interjections().into_iter().map(Result::unwrap).collect::<Vec<_>>(), vec![b"tilted alert".to_vec()]);
+    }
+
+    #[test]
+    fn test_carousel_interjections_split_and_failed() {
+        // Several blocks long, so it completes only with blocks after the failed frame
+        let message: Vec<u8> = (0..200u8).collect();
+        let mut encoder = CarouselEncoder::new(Some(test_config()));
+        encoder.add_message(1, &message).unwrap();
+        let mut samples: Vec<f32> = encoder.by_ref().take(2).flatten().collect();
+        encoder.interject(b"heard in pieces", InterjectionPriority::Urgent).unwrap();
+        samples.extend(encoder.by_ref().take(3).flatten());
+
+        // A frame whose length prefix and first bytes are lost
+        encoder.interject(b"lost to noise", InterjectionPriority::Urgent).unwrap();
+        let mut damaged = encoder.next().unwrap();
+        let data_start = detect_preamble(&damaged, DetectionThreshold::Adaptive).unwrap() + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+        damaged[data_start..data_start + 6 * FSK_SYMBOL_SAMPLES].fill(0.0);
+        samples.extend(damaged);
+        samples.extend(encoder.by_ref().take(12).flatten());
+
+        let mut decoder = CarouselDecoder::new(Some(test_config()));
+        let mut found = Vec::new();
+        for piece in samples.chunks(7919) {
+            found.extend(decoder.decode(piece));
+        }
+        let interjections = decoder.take_interjections();
+        assert_eq!(interjections.len(), 2);
+        assert_eq!(interjections[0].as_deref().unwrap(), b"heard in pieces");
+        assert!(interjections[1].is_err());
+        // The blocks after the failed frame are still read
+        assert_eq!(found, vec![(1, message)]);
+        assert_eq!(decoder.stats.failed_blocks, 0);
+    }
+
+    #[test]
+    fn test_empty_carousel_yields_nothing() {
+        let mut encoder = CarouselEncoder::new(None);
+        assert!(encoder.is_empty());
+        assert!(encoder.next().is_none());
+    }
+}
@@ -0,0 +1,147 @@
+//! Pluggable cross-correlation used by sync detection
+//!
+//! Preamble/postamble detection spends most of its time correlating the
+//! recording against a template. The default backend is `FftCorrelation` on
+//! rustfft; a batch decoder can install another one process-wide with
+//! [`set_correlation_backend`], e.g. the wgpu backend behind the `gpu` feature,
+//! `FftBackendCorrelation` on another [`FftBackend`], or `Q15Correlation` for short
+//! recordings on integer arithmetic.
+
+use crate::error::Result;
+use crate::fft::{fft_correlate, FftBackend, RustFft};
+use std::sync::{Arc, RwLock};
+
+/// Full-mode cross-correlation of a recording against a template
+///
+/// `correlate` must return `samples.len() + template.len() - 1` values where
+/// `output[k] = Σ_j samples[k + j - (template.len() - 1)] * template[j]`
+/// (out-of-range samples count as zero), the layout of `Mode::Full`.
+pub trait CorrelationBackend: Send + Sync {
+    /// Short name for logs and benchmarks
+    fn name(&self) -> &str;
+
+    fn correlate(&self, samples: &[f32], template: &[f32]) -> Result<Vec<f32>>;
+}
+
+/// Default CPU backend: FFT correlation via rustfft
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FftCorrelation;
+
+impl CorrelationBackend for FftCorrelation {
+    fn name(&self) -> &str {
+        RustFft.name()
+    }
+
+    fn correlate(&self, samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+        fft_correlate(&RustFft, samples, template)
+    }
+}
+
+/// FFT correlation on another [`FftBackend`], e.g. an embedded one
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FftBackendCorrelation<B>(B);
+
+impl<B: FftBackend> FftBackendCorrelation<B> {
+    pub fn new(fft: B) -> Self {
+        Self(fft)
+    }
+}
+
+impl<B: FftBackend> CorrelationBackend for FftBackendCorrelation<B> {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn correlate(&self, samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+        fft_correlate(&self.0, samples, template)
+    }
+}
+
+static BACKEND: RwLock<Option<Arc<dyn CorrelationBackend>>> = RwLock::new(None);
+
+/// Install the backend used by all sync detection in this process (None restores the default)
+pub fn set_correlation_backend(backend: Option<Arc<dyn CorrelationBackend>>) {
+    *BACKEND.write().unwrap_or_else(|e| e.into_inner()) = backend;
+}
+
+/// Backend currently used by sync detection
+pub fn correlation_backend() -> Arc<dyn CorrelationBackend> {
+    BACKEND
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(default_backend)
+}
+
+fn default_backend() -> Arc<dyn CorrelationBackend> {
+    Arc::new(FftCorrelation)
+}
+
+/// Correlate with the installed backend
+pub fn correlate(samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+    correlation_backend().correlate(samples, template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Reference time-domain implementation of the backend contract
+    fn direct_correlation(samples: &[f32], template: &[f32]) -> Vec<f32> {
+        let m = template.len();
+        (0..samples.len() + m - 1)
+            .map(|k| {
+                template
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(j, t)| (k + j).checked_sub(m - 1).and_then(|i| samples.get(i)).map(|s| s * t))
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fft_backend_matches_direct_correlation() {
+        let samples: Vec<f32> = (0..300).map(|i| ((i * 37) % 17) as f32 / 8.0 - 1.0).collect();
+        let template: Vec<f32> = (0..41).map(|i| (i as f32 * 0.3).sin()).collect();
+
+        let fft = FftCorrelation.correlate(&samples, &template).unwrap();
+        let direct = direct_correlation(&samples, &template);
+        assert_eq!(fft.len(), samples.len() + template.len() - 1);
+        for (a, b) in fft.iter().zip(&direct) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+    }
+
+    /// Delegates to the FFT backend, so swapping it in does not disturb concurrent tests
+    struct CountingBackend(AtomicUsize);
+
+    impl CorrelationBackend for CountingBackend {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn correlate(&self, samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            FftCorrelation.correlate(samples, template)
+        }
+    }
+
+    #[test]
+    fn test_installed_backend_is_used_by_detection() {
+        let backend = Arc::new(CountingBackend(AtomicUsize::new(0)));
+        set_correlation_backend(Some(backend.clone()));
+        assert_eq!(correlation_backend().name(), "counting");
+
+        let mut samples = vec![0.0f32; 2000];
+        samples.extend(crate::sync::generate_preamble(crate::PREAMBLE_SAMPLES, 0.5));
+        samples.extend(vec![0.0f32; 2000]);
+        let position = crate::detect_preamble(&samples, crate::DetectionThreshold::Adaptive);
+
+        set_correlation_backend(None);
+        assert_eq!(correlation_backend().name(), default_backend().name());
+        assert!(position.is_some_and(|pos| pos.abs_diff(2000) < 10));
+        assert!(backend.0.load(Ordering::SeqCst) >= 1);
+    }
+}
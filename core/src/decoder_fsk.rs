@@ -1,26 +1,238 @@
 use crate::error::{AudioModemError, Result};
+use crate::events::DecodeEvent;
 use crate::fec::{FecDecoder, FecMode};
-use crate::framing::{FrameDecoder, crc16};
-use crate::fsk::{FskDemodulator, FountainConfig, FSK_BYTES_PER_SYMBOL, FSK_SYMBOL_SAMPLES};
-use crate::sync::{detect_postamble, detect_preamble, detect_fountain_preamble, DetectionThreshold};
-use crate::{PREAMBLE_SAMPLES, POSTAMBLE_SAMPLES, SYNC_SILENCE_SAMPLES};
-use raptorq::{Decoder, EncodingPacket};
-use std::panic::catch_unwind;
-use log::warn;
-
-#[cfg(test)]
-use crate::FOUNTAIN_BLOCK_SIZE;
-
-#[cfg(not(target_arch = "wasm32"))]
-use std::time::{Duration, Instant};
-
-/// Statistics about fountain code decoding
+use crate::framing::{padding_mismatches, parse_sequence_frame_num, ContentType, EncoderVersion, Frame, FrameDecoder, crc16, MAX_FRAME_EXT_SIZE};
+use crate::encoder_fsk::{
+    FrameProfile, FRAME_OPTION_DIFFERENTIAL, FRAME_OPTION_DUAL_BAND, FRAME_OPTION_GAP_MASK, LENGTH_PREFIX_AMPLITUDE_CODE,
+    LENGTH_PREFIX_LAYOUT_MASK, LENGTH_PREFIX_LAYOUT_SHIFT, LENGTH_PREFIX_OPTIONS_FLAG,
+};
+use crate::fsk::{
+    FskDemodulator, SymbolStats, ToneLayout, AMPLITUDE_SYMBOL_BITS, FSK_BYTES_PER_SYMBOL, FSK_NIBBLES_PER_SYMBOL,
+    FSK_SYMBOL_SAMPLES, MIN_AMPLITUDE_CONTRAST_DB,
+};
+use crate::resample::{resample_audio, resample_audio_with, ResampleOptions};
+use crate::squelch::Squelch;
+use crate::stereo::{combine_stereo, restore_right, StereoRedundancy};
+use crate::melody::Melody;
+use crate::symbol_audit::{SymbolAuditLog, SymbolAuditSink};
+use crate::frame_inspection::{FrameInspection, RsBlockReport};
+use crate::sync::{
+    detect_codec_robust_postamble_candidate, detect_codec_robust_preamble_candidates, detect_keyed_clipped_preamble,
+    detect_keyed_postamble_candidate, detect_keyed_preamble_candidates, detect_narrowband_postamble_candidate,
+    detect_narrowband_preamble_candidates, detect_short_postamble_candidate, detect_short_preamble_candidates,
+    detect_telephony_postamble_candidate, detect_telephony_preamble_candidates,
+    estimate_keyed_playback_speed, estimate_keyed_source_rate, DetectionThreshold, NetworkKey, COMMON_SOURCE_RATES,
+};
+use crate::units::{self, Samples};
+use crate::{
+    FRAME_HEADER_SIZE, MAX_PAYLOAD_SIZE, MAX_SYNC_GAP_SAMPLES, MIN_SYMBOL_GAP_INTERVAL, PREAMBLE_SAMPLES, POSTAMBLE_SAMPLES, SAMPLE_RATE,
+    SHORT_MAX_PAYLOAD_SIZE, SHORT_SYMBOL_SAMPLES, SHORT_SYNC_SAMPLES, SHORT_SYNC_SILENCE_SAMPLES, SYMBOL_GAP_SAMPLES,
+    SYNC_SILENCE_SAMPLES,
+};
+use std::borrow::Cow;
+use std::sync::mpsc::Sender;
+use std::sync::PoisonError;
+
+/// Number of ranked preamble positions tried before giving up on a frame
+const MAX_PREAMBLE_CANDIDATES: usize = 3;
+
+/// Window of the level search for data after a sync gap of unknown length
+const ONSET_WINDOW_SAMPLES: usize = 64;
+
+/// Tone SNR below which amplitude bits are not recommended (they start to fail
+/// around 24 dB, while plain symbols still decode below 16 dB)
+const AMPLITUDE_MIN_SNR_DB: f32 = 27.0;
+
+/// Weakest tone decision margin below which amplitude bits are not recommended
+/// (a low tone loses 6 dB against the noise)
+const AMPLITUDE_MIN_SYMBOL_MARGIN: f32 = 0.5;
+
+/// Statistics about decoding
+///
+/// Regular decodes reset them on every call and count over every preamble
+/// candidate they tried; fountain decodes only update the fountain counts.
 #[derive(Debug, Clone, Default)]
 pub struct DecodeStats {
-    /// Number of successfully decoded blocks (passed CRC)
+    /// Number of successfully decoded fountain blocks (passed CRC)
     pub decoded_blocks: u32,
-    /// Number of blocks that failed CRC check (corrupted)
+    /// Number of fountain blocks that failed CRC check (corrupted)
     pub failed_blocks: u32,
+    /// Times a fountain decode switched to a new transmission mid-recording
+    pub fountain_restarts: u32,
+    /// Clock drift a fountain decode tracked, in samples per block (positive:
+    /// blocks arrive later than the nominal spacing)
+    pub fountain_drift: f32,
+    /// Fountain preambles found near the drift tracker's prediction rather
+    /// than by scanning a whole block
+    pub fountain_tracked_blocks: u32,
+    /// Reed-Solomon blocks of regular frames that decoded
+    pub rs_blocks_decoded: u32,
+    /// Reed-Solomon blocks of regular frames with too many errors to correct
+    pub rs_blocks_failed: u32,
+    /// Received bytes the RS decoder corrected (data and parity)
+    pub corrected_bytes: u32,
+    /// Correlation score of the last preamble tried
+    pub preamble_score: Option<f32>,
+    /// Correlation score of the postamble after it (None if not found)
+    pub postamble_score: Option<f32>,
+    /// FSK symbols demodulated
+    pub symbols_demodulated: u32,
+    /// Playback speed the frame was recovered at, in thousandths (None at
+    /// normal speed; see `DecoderFsk::set_speed_search`)
+    pub playback_speed_permille: Option<u32>,
+    /// Input rate `DecoderFsk::decode_autorate` resampled from (None for the
+    /// other decode methods)
+    pub source_rate: Option<usize>,
+    /// Bytes handed to the RS decoder as erasures because their tone may have
+    /// been in an unreliable bin (see `DecoderFsk::set_unreliable_bins`)
+    pub erased_bytes: u32,
+    /// Preamble candidates, frames and RS blocks rejected by the squelch
+    /// (see `DecoderFsk::set_squelch`)
+    pub squelched: u32,
+    /// Decoded frames rejected for a missing keyed postamble (see
+    /// `DecoderFsk::set_strict_network_key`)
+    pub wrong_network: u32,
+    /// Symbol padding bytes after decoded frames (see `FRAME_PADDING_BYTE`)
+    pub padding_bytes: u32,
+    /// Padding bytes that were not `FRAME_PADDING_BYTE`
+    pub padding_mismatches: u32,
+}
+
+/// Fountain packets collected by the last fountain decode, e.g. for a
+/// per-symbol progress grid ("received 37/52 symbols")
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FountainProgress {
+    /// Encoding symbol IDs received so far, ascending and without repeats
+    /// (see `FountainReceiver::symbol_id`)
+    pub symbol_ids: Vec<u32>,
+    /// Source symbols of the payload, 0 before the first valid block
+    pub source_symbols: u32,
+}
+
+impl FountainProgress {
+    /// Estimated symbols still needed: RaptorQ nearly always decodes from as
+    /// many symbols as the payload has, LT may take a few more
+    pub fn remaining(&self) -> u32 {
+        self.source_symbols.saturating_sub(self.symbol_ids.len() as u32)
+    }
+
+    pub(crate) fn record(&mut self, symbol_id: u32) {
+        if let Err(index) = self.symbol_ids.binary_search(&symbol_id) {
+            self.symbol_ids.insert(index, symbol_id);
+        }
+    }
+}
+
+/// Signal quality of a successful decode, for deciding whether to request a repeat
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodeQuality {
+    /// Weakest tone decision: (best - runner-up) / best, 0.0 = coin flip, 1.0 = clean
+    pub min_symbol_margin: f32,
+    /// Average tone decision margin
+    pub mean_symbol_margin: f32,
+    /// Tone power vs. the rest of its band, in dB
+    pub snr_db: f32,
+    /// Byte errors (data and parity) the RS code corrected, summed over the frame's blocks
+    pub parity_byte_errors: usize,
+    /// Fountain blocks dropped for a CRC failure (always 0 for regular frames)
+    pub failed_blocks: u32,
+    /// Weakest band's pilot contrast for amplitude-shaped frames (about 6 dB on a clean channel)
+    pub amplitude_contrast_db: Option<f32>,
+}
+
+impl DecodeQuality {
+    pub(crate) fn from_stats(stats: &SymbolStats, parity_byte_errors: usize, failed_blocks: u32) -> Self {
+        Self {
+            min_symbol_margin: if stats.decisions == 0 { 0.0 } else { stats.min_margin },
+            mean_symbol_margin: stats.mean_margin(),
+            snr_db: stats.snr_db(),
+            parity_byte_errors,
+            failed_blocks,
+            amplitude_contrast_db: None,
+        }
+    }
+
+    /// Whether the channel this frame crossed can carry amplitude bits
+    /// (`EncoderFsk::set_amplitude_bits`); senders fall back to plain symbols otherwise
+    pub fn amplitude_bits_viable(&self) -> bool {
+        let clean_tones = self.snr_db >= AMPLITUDE_MIN_SNR_DB && self.min_symbol_margin >= AMPLITUDE_MIN_SYMBOL_MARGIN;
+        match self.amplitude_contrast_db {
+            Some(contrast_db) => clean_tones && contrast_db >= MIN_AMPLITUDE_CONTRAST_DB,
+            None => clean_tones,
+        }
+    }
+}
+
+/// Payload plus receiver-side metadata returned by `DecoderFsk::decode_with_metadata`
+#[derive(Debug, Clone)]
+pub struct DecodedMessage {
+    pub payload: Vec<u8>,
+    /// Sender timestamp from the header extension (microseconds), if present
+    pub timestamp_us: Option<u64>,
+    /// What the payload holds, if the sender announced it (see `EncoderFsk::set_content_type`)
+    pub content_type: Option<ContentType>,
+    /// Encoder build, if the sender announced it (see `EncoderFsk::set_version_beacon`)
+    pub encoder_version: Option<EncoderVersion>,
+    /// The payload is a link control message (see `LinkProfile::from_message`)
+    pub control: bool,
+    /// Header frame number: 0, or the place in a sequence (see `EncoderFsk::encode_sequence`)
+    pub frame_num: u16,
+    /// Sample index in the input buffer where the preamble starts (0 if it
+    /// started before the recording did)
+    pub preamble_position: usize,
+    /// Sample index in the input buffer where the FSK data starts
+    pub data_start: usize,
+    /// Sample index in the input buffer where the FSK data ends (the sync
+    /// silence and postamble follow)
+    pub data_end: usize,
+    pub quality: DecodeQuality,
+}
+
+impl DecodedMessage {
+    /// Preamble position converted to microseconds from the start of the input buffer
+    pub fn preamble_offset_us(&self) -> u64 {
+        self.preamble_position as u64 * 1_000_000 / SAMPLE_RATE as u64
+    }
+
+    /// Preamble position as a time from the start of the input buffer
+    pub fn preamble_offset(&self) -> std::time::Duration {
+        units::MODEM_RATE.duration(Samples(self.preamble_position))
+    }
+}
+
+/// Frames of a sequence returned by `DecoderFsk::decode_all`
+#[derive(Debug, Clone)]
+pub struct DecodedSequence {
+    /// One entry per frame of the sequence, in order; `None` where it was not decoded
+    pub frames: Vec<Option<DecodedMessage>>,
+}
+
+impl DecodedSequence {
+    /// Indices of the frames that were not decoded
+    pub fn missing(&self) -> Vec<usize> {
+        self.frames.iter().enumerate().filter(|(_, frame)| frame.is_none()).map(|(index, _)| index).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.frames.iter().all(Option::is_some)
+    }
+
+    /// Payloads of all frames joined in order, `None` while any is missing
+    pub fn payload(&self) -> Option<Vec<u8>> {
+        let parts = self.frames.iter().map(|frame| frame.as_ref().map(|message| message.payload.as_slice()));
+        parts.collect::<Option<Vec<_>>>().map(|parts| parts.concat())
+    }
+}
+
+/// How the decoder finds the size of a standard frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FramingVariant {
+    /// The 2-byte length prefix in the first symbol (current encoders)
+    #[default]
+    LengthPrefixed,
+    /// As `LengthPrefixed`, then, for frames from older firmware that sent the
+    /// RS blocks without a length prefix, the RS blocks up to the postamble
+    LegacyFallback,
 }
 
 /// Decoder using Multi-tone FSK with Reed-Solomon FEC
@@ -29,30 +241,254 @@ pub struct DecodeStats {
 /// energy detection (Goertzel algorithm) to recover the original binary data.
 /// Includes Reed-Solomon error correction for robustness against channel impairments.
 pub struct DecoderFsk {
-    fsk: FskDemodulator,
+    pub(crate) fsk: FskDemodulator,
     fec: FecDecoder,
     preamble_threshold: DetectionThreshold,
     postamble_threshold: DetectionThreshold,
+    network_key: Option<NetworkKey>,
+    strict_network_key: bool,
+    fountain_melody: Option<Melody>,
+    strict_padding: bool,
+    salvage_unverified: bool,
+    speed_search: bool,
+    squelch: Squelch,
+    framing: FramingVariant,
+    events: Option<Sender<DecodeEvent>>,
+    inspection: Option<FrameInspection>,
     pub stats: DecodeStats,
+    pub(crate) fountain_progress: FountainProgress,
+}
+
+/// How demodulated frame bytes fill symbols: `head` bytes first (e.g. the
+/// standard first symbol before a tone layout), then groups of `unit` bytes
+#[derive(Debug, Clone, Copy)]
+struct SymbolAlignment {
+    head: usize,
+    unit: usize,
+}
+
+impl SymbolAlignment {
+    const PLAIN: Self = Self { head: 0, unit: FSK_BYTES_PER_SYMBOL };
+
+    /// Standard first symbol, then symbols of `layout`
+    fn after_first_symbol(layout: ToneLayout) -> Self {
+        Self { head: FSK_BYTES_PER_SYMBOL, unit: layout.alignment_bytes() }
+    }
+
+    /// End of the symbol group holding the first `len` bytes, where padding stops
+    fn end(self, len: usize) -> usize {
+        self.head + len.saturating_sub(self.head).next_multiple_of(self.unit)
+    }
+}
+
+/// `samples` cut down to whole FSK symbols
+fn whole_symbols(samples: &[f32]) -> Result<&[f32]> {
+    match samples.len() / FSK_SYMBOL_SAMPLES {
+        0 => Err(AudioModemError::InsufficientData),
+        symbols => Ok(&samples[..symbols * FSK_SYMBOL_SAMPLES]),
+    }
+}
+
+/// Shortest part of a last data symbol, cut off by the end of the recording,
+/// that is still demodulated
+const MIN_PARTIAL_SYMBOL_SAMPLES: usize = FSK_SYMBOL_SAMPLES / 2;
+
+/// `samples` in whole FSK symbols, keeping a last partial symbol of at least
+/// `MIN_PARTIAL_SYMBOL_SAMPLES` padded with silence
+///
+/// For data regions cut short by the end of a recording: the frame still
+/// decodes if the lost samples only carried padding, or few enough bytes for
+/// the RS code to correct.
+fn clipped_symbols(samples: &[f32]) -> Result<Cow<'_, [f32]>> {
+    if samples.len() % FSK_SYMBOL_SAMPLES < MIN_PARTIAL_SYMBOL_SAMPLES {
+        return whole_symbols(samples).map(Cow::Borrowed);
+    }
+    let mut padded = samples.to_vec();
+    padded.resize(samples.len().next_multiple_of(FSK_SYMBOL_SAMPLES), 0.0);
+    Ok(Cow::Owned(padded))
+}
+
+/// Options byte after the length prefix in the first bytes of a frame, 0 if it has none
+fn prefix_options(prefix: &[u8]) -> u8 {
+    if prefix[0] & LENGTH_PREFIX_OPTIONS_FLAG == 0 {
+        return 0;
+    }
+    prefix[2]
+}
+
+/// Gap interval announced by the first bytes of a frame, if it was sent with symbol gaps
+fn announced_gap_interval(prefix: &[u8]) -> Option<usize> {
+    Some((prefix_options(prefix) & FRAME_OPTION_GAP_MASK) as usize).filter(|&interval| interval >= MIN_SYMBOL_GAP_INTERVAL)
+}
+
+/// Layout of the data symbols announced by the first bytes of a frame; None
+/// for standard and amplitude-shaped symbols
+fn announced_layout(prefix: &[u8]) -> Option<ToneLayout> {
+    let code = (prefix[0] & LENGTH_PREFIX_LAYOUT_MASK) >> LENGTH_PREFIX_LAYOUT_SHIFT;
+    let layout = ToneLayout::from_code(code).filter(|&layout| layout != ToneLayout::STANDARD)?;
+    if prefix_options(prefix) & FRAME_OPTION_DUAL_BAND != 0 {
+        layout.dual_band().ok()
+    } else {
+        Some(layout)
+    }
+}
+
+/// Whether the first bytes of a frame announce differential standard symbols
+fn announced_differential(prefix: &[u8]) -> bool {
+    let code = (prefix[0] & LENGTH_PREFIX_LAYOUT_MASK) >> LENGTH_PREFIX_LAYOUT_SHIFT;
+    code == 0 && prefix_options(prefix) & FRAME_OPTION_DIFFERENTIAL != 0
+}
+
+/// Samples taken by `symbols` data symbols, gaps included
+fn symbol_span(symbols: usize, symbol_samples: usize, gap_interval: Option<usize>) -> usize {
+    let gaps = gap_interval.map_or(0, |interval| symbols.saturating_sub(1) / interval);
+    symbols * symbol_samples + gaps * SYMBOL_GAP_SAMPLES
+}
+
+/// A data region with its symbol gaps (see `EncoderFsk::set_symbol_gaps`) cut out
+fn strip_symbol_gaps(samples: &[f32], symbol_samples: usize, gap_interval: Option<usize>) -> Cow<'_, [f32]> {
+    let Some(interval) = gap_interval else {
+        return Cow::Borrowed(samples);
+    };
+    let group = interval * symbol_samples;
+    Cow::Owned(
+        samples
+            .chunks(group + SYMBOL_GAP_SAMPLES)
+            .flat_map(|chunk| &chunk[..chunk.len().min(group)])
+            .copied()
+            .collect(),
+    )
+}
+
+/// Symbol counts of a standard frame for each FEC mode, as announced by its
+/// first symbol: the presets first, then the custom codes, each ascending
+fn frame_symbol_candidates(prefix: &[u8]) -> Option<Vec<usize>> {
+    let candidates = [FecMode::Light, FecMode::Medium, FecMode::Full]
+        .into_iter()
+        .chain(FecMode::custom_modes())
+        .map(|mode| frame_symbols(prefix, mode))
+        .collect::<Option<Vec<_>>>()?
+        .into_iter()
+        .fold(Vec::new(), |mut candidates, symbols| {
+            if !candidates.contains(&symbols) {
+                candidates.push(symbols);
+            }
+            candidates
+        });
+    Some(candidates)
+}
+
+/// Symbol count of a standard frame sent with `mode`, as announced by its first symbol
+fn frame_symbols(prefix: &[u8], mode: FecMode) -> Option<usize> {
+    let flags = LENGTH_PREFIX_OPTIONS_FLAG | LENGTH_PREFIX_LAYOUT_MASK;
+    let frame_len = (((prefix[0] & !flags) as usize) << 8) | prefix[1] as usize;
+    if frame_len == 0 {
+        return None;
+    }
+    let options_byte = usize::from(prefix[0] & LENGTH_PREFIX_OPTIONS_FLAG != 0);
+    let code = (prefix[0] & LENGTH_PREFIX_LAYOUT_MASK) >> LENGTH_PREFIX_LAYOUT_SHIFT;
+
+    let encoded = 2 + options_byte + frame_len + frame_len.div_ceil(mode.data_bytes()) * mode.parity_bytes();
+    let rest = encoded.saturating_sub(FSK_BYTES_PER_SYMBOL);
+    Some(if code == LENGTH_PREFIX_AMPLITUDE_CODE {
+        // Two pilot symbols before the amplitude-shaped data
+        3 + (rest * 8).div_ceil(AMPLITUDE_SYMBOL_BITS)
+    } else {
+        match announced_layout(prefix) {
+            Some(layout) => 1 + layout.symbols_for(rest.next_multiple_of(layout.alignment_bytes())),
+            None => encoded.div_ceil(FSK_BYTES_PER_SYMBOL),
+        }
+    })
+}
+
+/// Frame sizes (length prefix values) that, RS encoded with a preset FEC mode
+/// and padded to whole symbols, take exactly `encoded_len` bytes
+fn legacy_frame_len_candidates(encoded_len: usize) -> Vec<usize> {
+    let max_frame_len = FRAME_HEADER_SIZE + MAX_FRAME_EXT_SIZE + MAX_PAYLOAD_SIZE + 2;
+    (FRAME_HEADER_SIZE + 2..=max_frame_len)
+        .filter(|&frame_len| {
+            [FecMode::Light, FecMode::Medium, FecMode::Full].iter().any(|mode| {
+                let encoded = frame_len + frame_len.div_ceil(mode.data_bytes()) * mode.parity_bytes();
+                encoded.next_multiple_of(FSK_BYTES_PER_SYMBOL) == encoded_len
+            })
+        })
+        .collect()
+}
+
+/// Keep the most useful error across decode attempts: the first one, unless a
+/// later attempt salvaged an unverified payload
+fn keep_error(slot: &mut Option<AudioModemError>, error: AudioModemError) {
+    let rank = |e: &AudioModemError| match e {
+        AudioModemError::WrongNetwork => 2,
+        AudioModemError::CrcMismatch { .. } => 1,
+        _ => 0,
+    };
+    if slot.as_ref().is_none_or(|kept| rank(&error) > rank(kept)) {
+        *slot = Some(error);
+    }
+}
+
+/// Erasures among the `len` bytes at `start`, as positions in the RS block
+/// they fill after `padding` zeros
+fn erasures_in_block(erasures: &[usize], start: usize, len: usize, padding: usize) -> Vec<usize> {
+    erasures.iter().filter(|&&byte| (start..start + len).contains(&byte)).map(|&byte| padding + byte - start).collect()
+}
+
+/// Where the data starts after a preamble ending at `sync_end`, for frames
+/// sent with other gaps than `SYNC_SILENCE_SAMPLES` (see `SyncGaps`)
+///
+/// The first window half as loud as the symbol right after the longest gap,
+/// which is data whatever the gap was. Symbols fade in, so after the longest
+/// gap the threshold is only reached a little into the data; the search runs
+/// on into the reference symbol and the onset is capped at the longest gap.
+fn find_data_onset(samples: &[f32], sync_end: usize) -> Option<usize> {
+    let reference_start = sync_end + MAX_SYNC_GAP_SAMPLES;
+    let reference = samples.get(reference_start..reference_start + FSK_SYMBOL_SAMPLES)?;
+    let level = reference.iter().map(|s| s * s).sum::<f32>() / FSK_SYMBOL_SAMPLES as f32;
+    if level <= 0.0 {
+        return None;
+    }
+    let threshold = level * ONSET_WINDOW_SAMPLES as f32 / 2.0;
+
+    // Sliding window energy; a window half inside the data reaches the threshold
+    let last_start = MAX_SYNC_GAP_SAMPLES + FSK_SYMBOL_SAMPLES / 2 - ONSET_WINDOW_SAMPLES;
+    let region = &samples[sync_end..sync_end + last_start + ONSET_WINDOW_SAMPLES];
+    let mut energy: f32 = region[..ONSET_WINDOW_SAMPLES].iter().map(|s| s * s).sum();
+    for start in 0..=last_start {
+        if energy >= threshold {
+            return Some(sync_end + (start + ONSET_WINDOW_SAMPLES / 2).min(MAX_SYNC_GAP_SAMPLES));
+        }
+        if start < last_start {
+            energy += region[start + ONSET_WINDOW_SAMPLES].powi(2) - region[start].powi(2);
+        }
+    }
+    None
 }
 
 impl DecoderFsk {
+    /// Same as `default()`; construction cannot fail
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            fsk: FskDemodulator::new(),
-            fec: FecDecoder::new()?,
-            preamble_threshold: DetectionThreshold::Adaptive, // Default: use adaptive threshold
-            postamble_threshold: DetectionThreshold::Adaptive, // Default: use adaptive threshold
-            stats: DecodeStats::default(),
-        })
+        Ok(Self::default())
+    }
+
+    /// Decoder for frames of `framing`, e.g. `FramingVariant::LegacyFallback`
+    /// to also receive frames from firmware without the length prefix
+    pub fn with_framing(framing: FramingVariant) -> Result<Self> {
+        Ok(Self { framing, ..Self::new()? })
+    }
+
+    pub fn framing(&self) -> FramingVariant {
+        self.framing
+    }
+
+    /// Symbols collected by the last fountain decode, kept after it returns
+    pub fn fountain_progress(&self) -> &FountainProgress {
+        &self.fountain_progress
     }
 
     /// Set the detection threshold for preamble detection
     pub fn set_preamble_threshold(&mut self, threshold: DetectionThreshold) {
-        self.preamble_threshold = match threshold {
-            DetectionThreshold::Adaptive => DetectionThreshold::Adaptive,
-            DetectionThreshold::Fixed(value) => DetectionThreshold::Fixed(value.max(0.001).min(1.0)),
-        };
+        self.preamble_threshold = threshold.clamped();
     }
 
     /// Get the current preamble detection threshold
@@ -62,10 +498,7 @@ impl DecoderFsk {
 
     /// Set the detection threshold for postamble detection
     pub fn set_postamble_threshold(&mut self, threshold: DetectionThreshold) {
-        self.postamble_threshold = match threshold {
-            DetectionThreshold::Adaptive => DetectionThreshold::Adaptive,
-            DetectionThreshold::Fixed(value) => DetectionThreshold::Fixed(value.max(0.001).min(1.0)),
-        };
+        self.postamble_threshold = threshold.clamped();
     }
 
     /// Get the current postamble detection threshold
@@ -84,152 +517,730 @@ impl DecoderFsk {
         self.get_preamble_threshold()
     }
 
+    /// Only detect sync signals of `key` (see [`NetworkKey`]); frames sent
+    /// with another key, or without one, are not seen
+    pub fn set_network_key(&mut self, key: Option<NetworkKey>) {
+        self.network_key = key;
+    }
+
+    pub fn network_key(&self) -> Option<NetworkKey> {
+        self.network_key
+    }
+
+    /// Detect fountain blocks opening with `melody` (see
+    /// `EncoderFsk::set_fountain_melody`); ignored while a network key is set
+    pub fn set_fountain_melody(&mut self, melody: Option<Melody>) {
+        self.fountain_melody = melody;
+    }
+
+    pub fn fountain_melody(&self) -> Option<&Melody> {
+        self.fountain_melody.as_ref()
+    }
+
+    /// Only accept a frame when the postamble of the network key follows it
+    /// too, not just the preamble (off by default); otherwise the decode
+    /// fails with [`AudioModemError::WrongNetwork`]. Guards against a frame of
+    /// another network whose preamble correlated by chance. Frames without a
+    /// key must then end in the unkeyed postamble.
+    pub fn set_strict_network_key(&mut self, enabled: bool) {
+        self.strict_network_key = enabled;
+    }
+
+    pub fn strict_network_key(&self) -> bool {
+        self.strict_network_key
+    }
+
+    /// Reject decoded frames whose symbol padding is not all
+    /// `FRAME_PADDING_BYTE` with [`AudioModemError::UnexpectedPadding`] (off
+    /// by default: the padding has no FEC, so noise can flip it). Either way
+    /// the padding is counted in `stats.padding_bytes` and
+    /// `stats.padding_mismatches`.
+    pub fn set_strict_padding(&mut self, enabled: bool) {
+        self.strict_padding = enabled;
+    }
+
+    pub fn strict_padding(&self) -> bool {
+        self.strict_padding
+    }
+
+    /// When a frame passes Reed-Solomon decoding but fails its payload CRC,
+    /// return [`AudioModemError::CrcMismatch`] carrying the candidate payload
+    /// instead of `PayloadCrcMismatch` (off by default). The bytes are likely
+    /// mostly right but unverified, e.g. for showing a flagged text message.
+    pub fn set_salvage_unverified(&mut self, enabled: bool) {
+        self.salvage_unverified = enabled;
+    }
+
+    /// When no frame decodes at normal speed, estimate the playback speed from
+    /// the preamble (within ±5%, see [`estimate_keyed_playback_speed`]) and
+    /// decode again after resampling the recording back to it (off by
+    /// default). For voice notes that messenger apps play at 1.05x.
+    pub fn set_speed_search(&mut self, enabled: bool) {
+        self.speed_search = enabled;
+    }
+
+    pub fn speed_search(&self) -> bool {
+        self.speed_search
+    }
+
+    /// Gates against false decodes in always-listening receivers (see
+    /// [`Squelch`], e.g. `Squelch::for_false_positive_rate(1e-9)?`); open by default
+    ///
+    /// Rejected candidates and frames fail with [`AudioModemError::Squelched`]
+    /// and are counted in `stats.squelched`.
+    pub fn set_squelch(&mut self, squelch: Squelch) {
+        self.squelch = squelch;
+    }
+
+    pub fn squelch(&self) -> Squelch {
+        self.squelch
+    }
+
+    /// Mark tone bins the channel corrupts, e.g. the bin of a constant machinery
+    /// hum (see `fsk::freq_to_bin`); replaces the previous set
+    ///
+    /// Tones are not picked from these bins, and bytes whose tone may have been
+    /// in one are rebuilt from the Reed-Solomon parity as erasures.
+    pub fn set_unreliable_bins(&mut self, bins: &[usize]) -> Result<()> {
+        self.fsk.set_unreliable_bins(bins)
+    }
+
+    pub fn unreliable_bins(&self) -> Vec<usize> {
+        self.fsk.unreliable_bins()
+    }
+
+    /// Undo the band gains of the encoder (see `EncoderFsk::set_band_gains`)
+    /// in the tone statistics and the symbol audit
+    pub fn set_band_compensation(&mut self, gains: Option<[f32; FSK_NIBBLES_PER_SYMBOL]>) -> Result<()> {
+        self.fsk.set_band_compensation(gains)
+    }
+
+    pub fn band_compensation(&self) -> Option<[f32; FSK_NIBBLES_PER_SYMBOL]> {
+        self.fsk.band_compensation()
+    }
+
+    /// Demodulator for `symbol_samples` and `layout` sharing the unreliable bins and band compensation
+    fn demodulator(&self, symbol_samples: usize, layout: ToneLayout) -> FskDemodulator {
+        let mut demodulator = FskDemodulator::with_layout(Samples(symbol_samples), layout);
+        demodulator.share_unreliable_bins(&self.fsk);
+        demodulator.set_audit(self.fsk.audit().cloned());
+        demodulator
+    }
+
+    /// Keep every tone decision of the last frame tried (see [`SymbolAuditLog`])
+    pub fn set_symbol_audit(&mut self, enabled: bool) {
+        self.fsk.set_audit(enabled.then(SymbolAuditSink::default));
+    }
+
+    /// Decisions of the last frame tried; `None` unless `set_symbol_audit(true)`
+    pub fn symbol_audit(&self) -> Option<SymbolAuditLog> {
+        let audit = self.fsk.audit()?;
+        let symbols = audit.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        Some(SymbolAuditLog { symbols })
+    }
+
+    /// Start a new frame's symbol audit
+    fn clear_symbol_audit(&self) {
+        if let Some(audit) = self.fsk.audit() {
+            audit.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        }
+    }
+
+    /// Keep the structure of the last length-prefixed frame tried (see [`FrameInspection`])
+    pub fn set_frame_inspection(&mut self, enabled: bool) {
+        self.inspection = enabled.then(FrameInspection::default);
+    }
+
+    /// Structure of the last frame tried; `None` unless `set_frame_inspection(true)`
+    pub fn frame_inspection(&self) -> Option<&FrameInspection> {
+        self.inspection.as_ref()
+    }
+
+    fn inspect(&mut self, update: impl FnOnce(&mut FrameInspection)) {
+        if let Some(inspection) = self.inspection.as_mut() {
+            update(inspection);
+        }
+    }
+
+    /// Report progress of streaming and fountain decodes on `sender` (see [`DecodeEvent`])
+    pub fn set_event_sender(&mut self, sender: Option<Sender<DecodeEvent>>) {
+        self.events = sender;
+    }
+
+    /// Send `event` to the event channel, if one is set and still open
+    pub(crate) fn emit(&self, event: DecodeEvent) {
+        if let Some(sender) = &self.events {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Turn a payload CRC failure into a salvaged candidate if enabled
+    fn payload_crc_error(&self, candidate: impl FnOnce() -> Vec<u8>) -> AudioModemError {
+        if self.salvage_unverified {
+            AudioModemError::CrcMismatch { payload: candidate() }
+        } else {
+            AudioModemError::PayloadCrcMismatch
+        }
+    }
+
     /// Decode audio samples back to binary data
     /// Expects: preamble + (FSK symbols) + postamble
     ///
     /// Handles shortened Reed-Solomon decoding by restoring padding zeros
     /// before RS decoding, then removing them after.
     pub fn decode(&mut self, samples: &[f32]) -> Result<Vec<u8>> {
+        self.decode_with_metadata(samples).map(|message| message.payload)
+    }
+
+    /// Decode audio samples and return the payload together with receiver-side metadata
+    /// (sender timestamp from the header extension and the sample position of the preamble)
+    ///
+    /// Standard frames are tried first, then short-profile, telephony,
+    /// codec-robust and narrowband frames (see `FrameProfile`).
+    pub fn decode_with_metadata(&mut self, samples: &[f32]) -> Result<DecodedMessage> {
+        self.stats = DecodeStats::default();
         if samples.len() < FSK_SYMBOL_SAMPLES * 2 {
             return Err(AudioModemError::InsufficientData);
         }
+        let error = match self.decode_any_profile(samples) {
+            Ok(message) => return Ok(message),
+            Err(e) => e,
+        };
 
-        // Detect preamble to find start of data, using configured threshold
-        let preamble_pos = detect_preamble(samples, self.preamble_threshold)
-            .ok_or(AudioModemError::PreambleNotFound)?;
-
-        // Data starts after preamble + silence gap
-        let data_start = preamble_pos + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+        // Played faster or slower: stretch the recording back to normal speed
+        let speed = if self.speed_search {
+            estimate_keyed_playback_speed(samples, self.preamble_threshold, self.network_key)
+                .filter(|speed| speed.permille != 1000)
+        } else {
+            None
+        };
+        let Some(speed) = speed else {
+            return Err(error);
+        };
+        let restored = resample_audio(samples, SAMPLE_RATE, SAMPLE_RATE * speed.permille as usize / 1000);
+        match self.decode_any_profile(&restored) {
+            Ok(mut message) => {
+                let to_input = |position: usize| position * 1000 / speed.permille as usize;
+                message.preamble_position = to_input(message.preamble_position);
+                message.data_start = to_input(message.data_start);
+                message.data_end = to_input(message.data_end);
+                self.stats.playback_speed_permille = Some(speed.permille);
+                Ok(message)
+            }
+            Err(_) => Err(error),
+        }
+    }
 
-        if data_start + FSK_SYMBOL_SAMPLES > samples.len() {
-            return Err(AudioModemError::InsufficientData);
+    /// Decode every frame of a sequence from `EncoderFsk::encode_sequence`
+    ///
+    /// After each decoded frame the audio before its preamble and after its
+    /// data start are searched again, so a lost frame leaves a hole instead
+    /// of ending the search. The first sequence frame found (in time) gives
+    /// the frame count; single frames and frames of other sequences are
+    /// skipped. Positions in the result are input samples. Fails with the
+    /// error of the whole-recording decode if no frame decodes, or
+    /// `FrameNumberMismatch` if none belongs to a sequence.
+    pub fn decode_all(&mut self, samples: &[f32]) -> Result<DecodedSequence> {
+        let mut found = Vec::new();
+        let mut error = None;
+        let mut regions = vec![(0, samples.len())];
+        while let Some((start, end)) = regions.pop() {
+            match self.decode_with_metadata(&samples[start..end]) {
+                Ok(mut message) => {
+                    message.preamble_position += start;
+                    message.data_start += start;
+                    message.data_end += start;
+                    regions.push((start, message.preamble_position));
+                    regions.push((message.data_start.max(start + 1), end));
+                    found.push(message);
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
         }
 
-        // Try to detect postamble to find end of data, using configured threshold
-        // If postamble is not found, use all remaining samples
-        let remaining = &samples[data_start..];
-        let data_end = match detect_postamble(remaining, self.postamble_threshold) {
-            Some(postamble_pos) => data_start + postamble_pos,
-            None => samples.len(), // Use all remaining data if no postamble found
+        found.sort_by_key(|message| message.preamble_position);
+        let Some((_, count)) = found.iter().find_map(|message| parse_sequence_frame_num(message.frame_num)) else {
+            return Err(if found.is_empty() {
+                error.unwrap_or(AudioModemError::PreambleNotFound)
+            } else {
+                AudioModemError::FrameNumberMismatch
+            });
         };
-
-        // Extract FSK data region
-        let fsk_region = &samples[data_start..data_end];
-
-        // Ensure we have complete symbols
-        let symbol_count = fsk_region.len() / FSK_SYMBOL_SAMPLES;
-        if symbol_count == 0 {
-            return Err(AudioModemError::InsufficientData);
+        let mut frames = vec![None; count];
+        for message in found {
+            if let Some((index, frame_count)) = parse_sequence_frame_num(message.frame_num) {
+                if frame_count == count && frames[index].is_none() {
+                    frames[index] = Some(message);
+                }
+            }
         }
+        Ok(DecodedSequence { frames })
+    }
 
-        let valid_samples = symbol_count * FSK_SYMBOL_SAMPLES;
-        let fsk_samples = &fsk_region[..valid_samples];
+    /// Decode a capture at `source_rate` Hz without resampling it first
+    ///
+    /// Raw 44.1/48 kHz microphone buffers are often passed with the wrong rate,
+    /// so the preamble is matched at `source_rate` and at each of
+    /// `COMMON_SOURCE_RATES`; the best-scoring rate wins and the audio is
+    /// resampled to `SAMPLE_RATE` once. Without a detectable standard preamble
+    /// (e.g. other profiles) `source_rate` is trusted. Positions in the result
+    /// are input samples; `stats.source_rate` holds the rate used.
+    pub fn decode_autorate(&mut self, samples: &[f32], source_rate: usize) -> Result<DecodedMessage> {
+        if source_rate == 0 {
+            return Err(AudioModemError::InvalidConfig("source rate must be positive".to_string()));
+        }
+        let mut rates = vec![source_rate];
+        rates.extend(COMMON_SOURCE_RATES.iter().filter(|&&rate| rate != source_rate));
+        let detected = estimate_keyed_source_rate(samples, &rates, self.preamble_threshold, self.network_key)
+            .map_or(source_rate, |estimate| estimate.rate);
+
+        let result = self.decode_at_rate(samples, detected);
+        if result.is_err() && detected != source_rate {
+            if let Ok(message) = self.decode_at_rate(samples, source_rate) {
+                return Ok(message);
+            }
+        }
+        result
+    }
 
-        // Demodulate multi-tone FSK symbols to bytes
-        let bytes = self.fsk.demodulate(fsk_samples)?;
+    /// `decode_with_metadata` of `samples` resampled from `rate`, positions mapped back
+    fn decode_at_rate(&mut self, samples: &[f32], rate: usize) -> Result<DecodedMessage> {
+        let resampled = match rate {
+            SAMPLE_RATE => Cow::Borrowed(samples),
+            _ => {
+                let options = ResampleOptions { anti_alias: true, ..Default::default() };
+                Cow::Owned(resample_audio_with(samples, rate, SAMPLE_RATE, options))
+            }
+        };
+        let mut message = self.decode_with_metadata(&resampled)?;
+        let to_input = |position: usize| (position as u64 * rate as u64 / SAMPLE_RATE as u64) as usize;
+        message.preamble_position = to_input(message.preamble_position);
+        message.data_start = to_input(message.data_start);
+        message.data_end = to_input(message.data_end);
+        self.stats.source_rate = Some(rate);
+        Ok(message)
+    }
 
-        if bytes.len() < 2 {
-            return Err(AudioModemError::InvalidFrameSize);
+    /// Try every frame profile on `samples` at normal speed
+    fn decode_any_profile(&mut self, samples: &[f32]) -> Result<DecodedMessage> {
+        // Rank preamble candidates so a false peak that outscores the real preamble
+        // does not doom the decode: try each until a frame passes CRC
+        let candidates = detect_keyed_preamble_candidates(
+            samples,
+            self.preamble_threshold,
+            MAX_PREAMBLE_CANDIDATES,
+            self.network_key,
+        );
+        let mut first_error = None;
+        for candidate in &candidates {
+            if !self.admits_preamble(candidate.score, &mut first_error) {
+                continue;
+            }
+            self.stats.preamble_score = Some(candidate.score);
+            let decoded = self.decode_at_preamble(samples, candidate.position);
+            match decoded.and_then(|message| self.check_network_postamble(samples, message, FrameProfile::Standard)) {
+                Ok(message) => return Ok(message),
+                Err(e) => keep_error(&mut first_error, e),
+            }
         }
 
-        // Read 2-byte length prefix to determine frame data length
-        let frame_len = ((bytes[0] as u16) << 8) | (bytes[1] as u16);
-        let mut byte_idx = 2;
+        // A recording that starts inside the preamble
+        let clipped = detect_keyed_clipped_preamble(samples, self.preamble_threshold, self.network_key);
+        if let Some(clipped) = clipped.filter(|clipped| self.admits_preamble(clipped.score, &mut first_error)) {
+            self.stats.preamble_score = Some(clipped.score);
+            let decoded = self.decode_after_sync(samples, 0, PREAMBLE_SAMPLES - clipped.position);
+            match decoded.and_then(|message| self.check_network_postamble(samples, message, FrameProfile::Standard)) {
+                Ok(message) => return Ok(message),
+                Err(e) => keep_error(&mut first_error, e),
+            }
+        }
 
-        // First pass: decode the first block to get FEC mode from header
-        // We need to peek at the header to determine FEC mode
-        let first_chunk_len = (frame_len as usize).min(223);
-        let padding_needed_first = 223 - first_chunk_len;
+        let short_candidates = detect_short_preamble_candidates(
+            samples,
+            self.preamble_threshold,
+            MAX_PREAMBLE_CANDIDATES,
+            self.network_key,
+        );
+        for candidate in &short_candidates {
+            if !self.admits_preamble(candidate.score, &mut first_error) {
+                continue;
+            }
+            self.stats.preamble_score = Some(candidate.score);
+            let decoded = self.decode_short_at_preamble(samples, candidate.position);
+            match decoded.and_then(|message| self.check_network_postamble(samples, message, FrameProfile::Short)) {
+                Ok(message) => return Ok(message),
+                Err(e) => keep_error(&mut first_error, e),
+            }
+        }
 
-        // Try with different FEC modes to find the right one
-        // Start with Light (smallest overhead) and work up
-        let mut decoded_first_block = None;
-        let mut detected_fec_mode = FecMode::Light;
+        for profile in [FrameProfile::Telephony, FrameProfile::CodecRobust, FrameProfile::Narrowband] {
+            let detect = match profile {
+                FrameProfile::Telephony => detect_telephony_preamble_candidates,
+                FrameProfile::CodecRobust => detect_codec_robust_preamble_candidates,
+                _ => detect_narrowband_preamble_candidates,
+            };
+            for candidate in &detect(samples, self.preamble_threshold, MAX_PREAMBLE_CANDIDATES, self.network_key) {
+                if !self.admits_preamble(candidate.score, &mut first_error) {
+                    continue;
+                }
+                self.stats.preamble_score = Some(candidate.score);
+                let decoded = self.decode_fixed_format_at_preamble(samples, candidate.position, profile);
+                match decoded.and_then(|message| self.check_network_postamble(samples, message, profile)) {
+                    Ok(message) => return Ok(message),
+                    Err(e) => keep_error(&mut first_error, e),
+                }
+            }
+        }
 
-        for mode in [FecMode::Light, FecMode::Medium, FecMode::Full] {
-            let parity_bytes = mode.parity_bytes();
-            let encoded_len = first_chunk_len + parity_bytes;
+        Err(first_error.unwrap_or(AudioModemError::PreambleNotFound))
+    }
 
-            if byte_idx + encoded_len <= bytes.len() {
-                let shortened_block = &bytes[byte_idx..byte_idx + encoded_len];
-                let mut full_block = vec![0u8; padding_needed_first];
-                full_block.extend_from_slice(shortened_block);
+    /// Whether the squelch lets a preamble candidate scoring `score` through;
+    /// a rejected one is counted and kept as the error
+    fn admits_preamble(&mut self, score: f32, first_error: &mut Option<AudioModemError>) -> bool {
+        if self.squelch.admits_preamble(score) {
+            return true;
+        }
+        self.stats.squelched += 1;
+        keep_error(first_error, AudioModemError::Squelched);
+        false
+    }
 
-                // Try decoding with this FEC mode
-                if let Ok(decoded_chunk) = self.fec.decode_with_mode(&full_block, mode) {
-                    // Check if this produces a valid header
-                    let decoded_data = &decoded_chunk[padding_needed_first..];
-                    if decoded_data.len() >= 8 {
-                        if let Ok((_, _, fec_mode_byte)) = FrameDecoder::decode_header(decoded_data) {
-                            if let Ok(parsed_mode) = FecMode::from_u8(fec_mode_byte) {
-                                if parsed_mode == mode {
-                                    // Found the correct FEC mode!
-                                    decoded_first_block = Some((decoded_data.to_vec(), encoded_len));
-                                    detected_fec_mode = mode;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
+    /// With a strict network key, `WrongNetwork` unless the keyed postamble of
+    /// `profile` follows the decoded frame
+    ///
+    /// Only the sync gap after `message.data_end`, give or take half a symbol,
+    /// is searched, so the postamble of a later frame does not count.
+    fn check_network_postamble(
+        &mut self,
+        samples: &[f32],
+        message: DecodedMessage,
+        profile: FrameProfile,
+    ) -> Result<DecodedMessage> {
+        if !self.strict_network_key {
+            return Ok(message);
+        }
+        let detect = match profile {
+            FrameProfile::Standard => detect_keyed_postamble_candidate,
+            FrameProfile::Short => detect_short_postamble_candidate,
+            FrameProfile::Telephony => detect_telephony_postamble_candidate,
+            FrameProfile::CodecRobust => detect_codec_robust_postamble_candidate,
+            FrameProfile::Narrowband => detect_narrowband_postamble_candidate,
+        };
+        let (sync_samples, symbol_samples, max_gap) = match profile.fixed_format() {
+            Some((sync_samples, symbol_samples, _)) => (sync_samples, symbol_samples, MAX_SYNC_GAP_SAMPLES),
+            None if profile == FrameProfile::Short => (SHORT_SYNC_SAMPLES, SHORT_SYMBOL_SAMPLES, SHORT_SYNC_SILENCE_SAMPLES),
+            None => (POSTAMBLE_SAMPLES, FSK_SYMBOL_SAMPLES, MAX_SYNC_GAP_SAMPLES),
+        };
+        let start = message.data_end.saturating_sub(symbol_samples / 2);
+        let end = (message.data_end + max_gap + sync_samples + symbol_samples / 2).min(samples.len());
+        let window = samples.get(start..end).unwrap_or(&[]);
+        match detect(window, self.postamble_threshold, self.network_key) {
+            Some(postamble) => {
+                self.stats.postamble_score = Some(postamble.score);
+                Ok(message)
+            }
+            None => {
+                self.stats.wrong_network += 1;
+                Err(AudioModemError::WrongNetwork)
             }
         }
+    }
 
-        let (first_decoded, first_encoded_len) = decoded_first_block
-            .ok_or(AudioModemError::FecDecodeFailure)?;
+    /// `Squelched` if the demodulated symbols are too weak for the squelch
+    fn check_squelch_snr(&mut self, symbol_stats: &SymbolStats) -> Result<()> {
+        if self.squelch.admits_snr(symbol_stats.snr_db()) {
+            return Ok(());
+        }
+        self.stats.squelched += 1;
+        Err(AudioModemError::Squelched)
+    }
 
-        // Now decode remaining blocks using the detected FEC mode
-        let mut decoded_data = first_decoded;
-        byte_idx += first_encoded_len;
-        let mut remaining_len = frame_len as usize - first_chunk_len;
+    /// Decode a stereo recording whose right channel carries a redundant copy
+    ///
+    /// Tries the combined signal first, then each channel on its own, so a frame
+    /// still decodes when one channel was mangled by playback effects.
+    pub fn decode_stereo(
+        &mut self,
+        left: &[f32],
+        right: &[f32],
+        redundancy: StereoRedundancy,
+    ) -> Result<DecodedMessage> {
+        let combined = combine_stereo(left, right, redundancy);
+        let first_error = match self.decode_with_metadata(&combined) {
+            Ok(message) => return Ok(message),
+            Err(e) => e,
+        };
+        if let Ok(message) = self.decode_with_metadata(left) {
+            return Ok(message);
+        }
+        self.decode_with_metadata(&restore_right(right, redundancy))
+            .map_err(|_| first_error)
+    }
 
-        while remaining_len > 0 {
-            let chunk_len = remaining_len.min(223);
-            let padding_needed = 223 - chunk_len;
-            let parity_bytes = detected_fec_mode.parity_bytes();
-            let encoded_len = chunk_len + parity_bytes;
+    /// Decode a frame whose preamble starts at `preamble_pos`
+    fn decode_at_preamble(&mut self, samples: &[f32], preamble_pos: usize) -> Result<DecodedMessage> {
+        self.decode_after_sync(samples, preamble_pos, preamble_pos + PREAMBLE_SAMPLES)
+    }
 
-            // Check if we have enough bytes
-            if byte_idx + encoded_len > bytes.len() {
-                break;
+    /// Decode a standard frame whose preamble ends at `sync_end`, after the
+    /// default gap or, failing that, any other (see `SyncGaps`)
+    fn decode_after_sync(&mut self, samples: &[f32], preamble_pos: usize, sync_end: usize) -> Result<DecodedMessage> {
+        // Data starts after preamble + silence gap
+        let mut first_error = match self.decode_from(samples, preamble_pos, sync_end + SYNC_SILENCE_SAMPLES) {
+            Ok(message) => return Ok(message),
+            Err(e) => Some(e),
+        };
+
+        // Frames sent with other sync gaps: start where the data gets loud
+        if let Some(onset) = find_data_onset(samples, sync_end)
+            .filter(|onset| onset.abs_diff(sync_end + SYNC_SILENCE_SAMPLES) > ONSET_WINDOW_SAMPLES)
+        {
+            match self.decode_from(samples, preamble_pos, onset) {
+                Ok(message) => return Ok(message),
+                Err(e) => keep_error(&mut first_error, e),
             }
+        }
+        Err(first_error.unwrap_or(AudioModemError::PreambleNotFound))
+    }
 
-            // Extract the shortened RS block
-            let shortened_block = &bytes[byte_idx..byte_idx + encoded_len];
-            byte_idx += encoded_len;
+    /// Decode a standard frame whose data starts at `data_start`
+    fn decode_from(&mut self, samples: &[f32], preamble_pos: usize, data_start: usize) -> Result<DecodedMessage> {
+        if data_start + FSK_SYMBOL_SAMPLES > samples.len() {
+            return Err(AudioModemError::InsufficientData);
+        }
 
-            // Restore to full RS block by prepending zeros
-            let mut full_block = vec![0u8; padding_needed];
-            full_block.extend_from_slice(shortened_block);
+        let remaining = &samples[data_start..];
+        self.stats.postamble_score = None;
+        let message = |(frame, quality): (Frame, DecodeQuality), data_len: usize| DecodedMessage {
+            payload: frame.payload,
+            timestamp_us: frame.timestamp_us,
+            content_type: frame.content_type,
+            encoder_version: frame.encoder_version,
+            control: frame.control,
+            frame_num: frame.frame_num,
+            preamble_position: preamble_pos,
+            data_start,
+            data_end: data_start + data_len,
+            quality,
+        };
 
-            // Decode with RS using detected FEC mode
-            match self.fec.decode_with_mode(&full_block, detected_fec_mode) {
-                Ok(decoded_chunk) => {
-                    // Remove the prepended zeros (padding)
-                    decoded_data.extend_from_slice(&decoded_chunk[padding_needed..]);
+        // The length prefix tells how many symbols follow for each FEC
+        // mode, so the postamble only has to be verified right after them
+        // instead of searched for across the rest of the recording
+        let mut first_error = None;
+        let prefix = self.fsk.demodulate_symbol(&remaining[..FSK_SYMBOL_SAMPLES]).ok();
+        let gaps = prefix.as_ref().and_then(|prefix| announced_gap_interval(prefix));
+        let span = |symbols| symbol_span(symbols, FSK_SYMBOL_SAMPLES, gaps);
+        if let Some(candidates) = prefix.as_ref().and_then(|prefix| frame_symbol_candidates(prefix)) {
+            // Only a postamble one gap after one of the lengths confirms it;
+            // the shortest length first, so the postamble of a frame right
+            // after this one cannot confirm a longer length
+            let mut by_length = candidates.clone();
+            by_length.sort_unstable();
+            let confirmed = by_length.into_iter().find(|&symbols| {
+                let expected = span(symbols) + SYNC_SILENCE_SAMPLES;
+                let window_start = expected.saturating_sub(FSK_SYMBOL_SAMPLES / 2);
+                let window_end = (expected + POSTAMBLE_SAMPLES + FSK_SYMBOL_SAMPLES / 2).min(remaining.len());
+                let postamble = remaining.get(window_start..window_end).and_then(|window| {
+                    detect_keyed_postamble_candidate(window, self.postamble_threshold, self.network_key)
+                });
+                if let Some(candidate) = postamble {
+                    self.stats.postamble_score = Some(candidate.score);
+                }
+                postamble.is_some()
+            });
+
+            // Otherwise (missed, or clipped off the recording) try the
+            // announced lengths, presets first; a frame cut short by the end
+            // of the recording is tried once with what is left of it
+            let ordered = confirmed.into_iter().chain(candidates.into_iter().filter(|&symbols| Some(symbols) != confirmed));
+            for symbols in ordered {
+                let clipped = span(symbols) > remaining.len();
+                let data_len = span(symbols).min(remaining.len());
+                let region = strip_symbol_gaps(&remaining[..data_len], FSK_SYMBOL_SAMPLES, gaps);
+                match clipped_symbols(&region).and_then(|region| self.decode_fsk_region(&region)) {
+                    Ok(decoded) => {
+                        // A longer length can decode too (the frame ignores the
+                        // symbols after it): end the frame at its own mode's
+                        // length, where a postamble of another length is not its own
+                        let sent = FecMode::from_u8(decoded.0.fec_mode)
+                            .ok()
+                            .and_then(|mode| prefix.as_ref().and_then(|prefix| frame_symbols(prefix, mode)))
+                            .filter(|&sent| sent < symbols);
+                        let Some(sent) = sent else {
+                            return Ok(message(decoded, data_len));
+                        };
+                        if confirmed == Some(symbols) {
+                            self.stats.postamble_score = None;
+                        }
+                        return Ok(message(decoded, span(sent).min(remaining.len())));
+                    }
+                    Err(e) => keep_error(&mut first_error, e),
                 }
-                Err(_) => {
-                    // FEC failed - might be corruption
-                    return Err(AudioModemError::FecDecodeFailure);
+                if clipped {
+                    break;
                 }
             }
+        }
 
-            remaining_len -= chunk_len;
+        // Length prefix unreadable or no length decoded: find the postamble
+        // anywhere after the data, or use all remaining samples without one
+        let postamble = detect_keyed_postamble_candidate(remaining, self.postamble_threshold, self.network_key);
+        self.stats.postamble_score = postamble.map(|candidate| candidate.score);
+        let data_end = postamble.map_or(remaining.len(), |candidate| candidate.position);
+        let region = strip_symbol_gaps(&remaining[..data_end], FSK_SYMBOL_SAMPLES, gaps);
+        let region = match postamble {
+            Some(_) => whole_symbols(&region).map(Cow::Borrowed),
+            None => clipped_symbols(&region),
+        };
+        match region.and_then(|region| self.decode_fsk_region(&region)) {
+            Ok(decoded) => return Ok(message(decoded, data_end)),
+            Err(e) => keep_error(&mut first_error, e),
         }
 
-        if decoded_data.is_empty() {
-            return Err(AudioModemError::FecDecodeFailure);
+        // Older firmware: RS blocks right after the preamble, ended only by the postamble
+        if self.framing == FramingVariant::LegacyFallback && postamble.is_some() {
+            match whole_symbols(&remaining[..data_end]).and_then(|region| self.decode_legacy_region(region)) {
+                Ok(decoded) => return Ok(message(decoded, data_end)),
+                Err(e) => keep_error(&mut first_error, e),
+            }
         }
+        Err(first_error.unwrap_or(AudioModemError::InsufficientData))
+    }
 
-        // Decode frame structure
-        let frame = FrameDecoder::decode(&decoded_data)?;
+    /// Decode a short-profile frame whose preamble starts at `preamble_pos`
+    ///
+    /// The length byte in the first symbol gives the frame size, so the
+    /// postamble is not needed to find the end of the data.
+    fn decode_short_at_preamble(&mut self, samples: &[f32], preamble_pos: usize) -> Result<DecodedMessage> {
+        let data_start = preamble_pos + SHORT_SYNC_SAMPLES + SHORT_SYNC_SILENCE_SAMPLES;
+        let demodulator = self.demodulator(SHORT_SYMBOL_SAMPLES, ToneLayout::STANDARD);
+
+        let first_symbol = samples
+            .get(data_start..data_start + SHORT_SYMBOL_SAMPLES)
+            .ok_or(AudioModemError::InsufficientData)?;
+        let len = demodulator.demodulate_symbol(first_symbol)?[0] as usize;
+        if len > SHORT_MAX_PAYLOAD_SIZE {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
 
-        // Verify frame size is reasonable
-        if frame.payload_len as usize > decoded_data.len() {
+        let mode = FecMode::Light;
+        let encoded_len = 1 + len + 2 + mode.parity_bytes();
+        let data_end = data_start + encoded_len.div_ceil(FSK_BYTES_PER_SYMBOL) * SHORT_SYMBOL_SAMPLES;
+        let region = samples.get(data_start..data_end).ok_or(AudioModemError::InsufficientData)?;
+        self.clear_symbol_audit();
+        let (bytes, symbol_stats) = demodulator.demodulate_with_stats(region)?;
+        self.stats.postamble_score = None;
+        self.stats.symbols_demodulated += (region.len() / SHORT_SYMBOL_SAMPLES) as u32;
+
+        self.check_squelch_snr(&symbol_stats)?;
+
+        // Restore the shortened RS block
+        let padding_needed = mode.data_bytes() - len - 2;
+        let mut full_block = vec![0u8; padding_needed];
+        full_block.extend_from_slice(&bytes[1..encoded_len]);
+        let (decoded, parity_byte_errors) = self.decode_rs_block(&full_block, mode, &[])?;
+
+        let data = &decoded[padding_needed..];
+        let (payload, crc_bytes) = data.split_at(len);
+        let mut crc_input = vec![len as u8];
+        crc_input.extend_from_slice(payload);
+        if crc16(&crc_input).to_be_bytes() != crc_bytes[..2] {
+            return Err(self.payload_crc_error(|| payload.to_vec()));
+        }
+        self.check_padding(&bytes[encoded_len..])?;
+
+        Ok(DecodedMessage {
+            payload: payload.to_vec(),
+            timestamp_us: None,
+            content_type: None,
+            encoder_version: None,
+            control: false,
+            frame_num: 0,
+            preamble_position: preamble_pos,
+            data_start,
+            data_end,
+            quality: DecodeQuality::from_stats(&symbol_stats, parity_byte_errors, 0),
+        })
+    }
+
+    /// Decode a telephony, codec-robust or narrowband frame whose preamble starts at `preamble_pos`
+    ///
+    /// Like short frames, the length prefix (the first symbol) gives the frame
+    /// size, so the postamble is not needed to find the end of the data.
+    fn decode_fixed_format_at_preamble(
+        &mut self,
+        samples: &[f32],
+        preamble_pos: usize,
+        profile: FrameProfile,
+    ) -> Result<DecodedMessage> {
+        let (sync_samples, symbol_samples, layout) = profile.fixed_format().ok_or(AudioModemError::InvalidFrameSize)?;
+        let data_start = preamble_pos + sync_samples + SYNC_SILENCE_SAMPLES;
+        let demodulator = self.demodulator(symbol_samples, layout);
+
+        // Symbols holding the 2-byte length prefix and the options byte, which
+        // all come before the first gap
+        let prefix_symbols = layout.symbols_for(3);
+        let prefix_region = samples
+            .get(data_start..data_start + prefix_symbols * symbol_samples)
+            .ok_or(AudioModemError::InsufficientData)?;
+        let prefix = demodulator.demodulate(prefix_region)?;
+        let gaps = announced_gap_interval(&prefix);
+        let frame_len = u16::from_be_bytes([prefix[0] & !LENGTH_PREFIX_OPTIONS_FLAG, prefix[1]]) as usize;
+        if frame_len == 0 || frame_len > FRAME_HEADER_SIZE + MAX_FRAME_EXT_SIZE + MAX_PAYLOAD_SIZE + 2 {
             return Err(AudioModemError::InvalidFrameSize);
         }
 
-        Ok(frame.payload)
+        let mode = FecMode::Full;
+        let options_byte = usize::from(prefix[0] & LENGTH_PREFIX_OPTIONS_FLAG != 0);
+        let encoded_len = 2 + options_byte + frame_len + frame_len.div_ceil(mode.data_bytes()) * mode.parity_bytes();
+        let symbols = layout.symbols_for(encoded_len.next_multiple_of(layout.alignment_bytes()));
+        let data_end = data_start + symbol_span(symbols, symbol_samples, gaps);
+        let region = samples.get(data_start..data_end).ok_or(AudioModemError::InsufficientData)?;
+        let region = strip_symbol_gaps(region, symbol_samples, gaps);
+        self.clear_symbol_audit();
+        let (bytes, symbol_stats, erasures) = demodulator.demodulate_with_erasures(&region)?;
+        self.stats.postamble_score = None;
+        self.stats.symbols_demodulated += symbols as u32;
+        self.stats.erased_bytes += erasures.len() as u32;
+
+        let alignment = SymbolAlignment { head: 0, unit: layout.alignment_bytes() };
+        let (frame, quality) = self.decode_frame_bytes(&bytes, &symbol_stats, &erasures, alignment)?;
+        Ok(DecodedMessage {
+            payload: frame.payload,
+            timestamp_us: frame.timestamp_us,
+            content_type: frame.content_type,
+            encoder_version: frame.encoder_version,
+            control: frame.control,
+            frame_num: frame.frame_num,
+            preamble_position: preamble_pos,
+            data_start,
+            data_end,
+            quality,
+        })
+    }
+
+    /// Decode a standard frame whose FSK data starts at `start_offset`, skipping sync detection
+    ///
+    /// For hosts that already know where the transmission is, e.g. because
+    /// they triggered the recording; in encoder output the data starts
+    /// `2 * SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES` samples in. The length
+    /// prefix bounds the frame, so audio after it is ignored. With
+    /// `expected_len`, a payload of any other length is rejected.
+    pub fn decode_at(&mut self, samples: &[f32], start_offset: usize, expected_len: Option<usize>) -> Result<DecodedMessage> {
+        self.stats = DecodeStats::default();
+        if start_offset >= samples.len() {
+            return Err(AudioModemError::InsufficientData);
+        }
+        let preamble_pos = start_offset.saturating_sub(SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES);
+        let message = self.decode_from(samples, preamble_pos, start_offset)?;
+        if expected_len.is_some_and(|len| len != message.payload.len()) {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
+        Ok(message)
     }
 
     /// Decode audio samples without preamble/postamble detection
@@ -238,11 +1249,16 @@ impl DecoderFsk {
     /// Useful when the audio clip has already been trimmed or when pre/post amble detection
     /// would cause double-detection issues.
     pub fn decode_without_preamble_postamble(&mut self, samples: &[f32]) -> Result<Vec<u8>> {
+        self.stats = DecodeStats::default();
         let sym_size = FSK_SYMBOL_SAMPLES;
         if samples.len() < sym_size * 2 {
             return Err(AudioModemError::InsufficientData);
         }
 
+        // Cut out symbol gaps announced by the first symbol
+        let gaps = self.fsk.demodulate_symbol(&samples[..sym_size]).ok().and_then(|prefix| announced_gap_interval(&prefix));
+        let samples = strip_symbol_gaps(samples, sym_size, gaps);
+
         // Ensure we have complete symbols
         let symbol_count = samples.len() / sym_size;
         if symbol_count == 0 {
@@ -252,28 +1268,201 @@ impl DecoderFsk {
         let valid_samples = symbol_count * sym_size;
         let fsk_samples = &samples[..valid_samples];
 
+        self.decode_fsk_region(fsk_samples).map(|(frame, _)| frame.payload)
+    }
+    /// Demodulate an FSK data region and decode the RS-protected frame it carries
+    ///
+    /// The symbol format code in the length prefix selects amplitude-shaped
+    /// demodulation or a `ToneLayout`, and the options byte differential
+    /// symbols; if that fails (e.g. the bits were
+    /// flipped by noise) the region is retried as plain symbols.
+    fn decode_fsk_region(&mut self, fsk_samples: &[f32]) -> Result<(Frame, DecodeQuality)> {
+        let prefix = fsk_samples
+            .get(..FSK_SYMBOL_SAMPLES)
+            .and_then(|symbol| self.fsk.demodulate_symbol(symbol).ok());
+        let code = prefix.map(|bytes| (bytes[0] & LENGTH_PREFIX_LAYOUT_MASK) >> LENGTH_PREFIX_LAYOUT_SHIFT);
+        let flagged = code == Some(LENGTH_PREFIX_AMPLITUDE_CODE);
+        let layout = prefix.and_then(|bytes| announced_layout(&bytes));
+        let differential = prefix.is_some_and(|bytes| announced_differential(&bytes));
+        let mut salvaged = None;
+        if flagged {
+            match self.decode_amplitude_shaped_region(fsk_samples) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e @ AudioModemError::CrcMismatch { .. }) => salvaged = Some(e),
+                Err(_) => {}
+            }
+        } else if let Some(layout) = layout {
+            match self.decode_layout_region(fsk_samples, layout) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e @ AudioModemError::CrcMismatch { .. }) => salvaged = Some(e),
+                Err(_) => {}
+            }
+        } else if differential {
+            match self.decode_differential_region(fsk_samples) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e @ AudioModemError::CrcMismatch { .. }) => salvaged = Some(e),
+                Err(_) => {}
+            }
+        }
+
+        self.decode_plain_region(fsk_samples).map_err(|e| salvaged.unwrap_or(e))
+    }
+
+    fn decode_plain_region(&mut self, fsk_samples: &[f32]) -> Result<(Frame, DecodeQuality)> {
         // Demodulate multi-tone FSK symbols to bytes
-        let bytes = self.fsk.demodulate(fsk_samples)?;
+        self.clear_symbol_audit();
+        let (bytes, symbol_stats, erasures) = self.fsk.demodulate_with_erasures(fsk_samples)?;
+        self.stats.symbols_demodulated += (fsk_samples.len() / FSK_SYMBOL_SAMPLES) as u32;
+        self.stats.erased_bytes += erasures.len() as u32;
+        let (frame, quality) = self.decode_frame_bytes(&bytes, &symbol_stats, &erasures, SymbolAlignment::PLAIN)?;
+        if frame.amplitude_bits || frame.differential {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
+        Ok((frame, quality))
+    }
+
+    /// Plain symbols carrying RS blocks without a length prefix
+    ///
+    /// The frame size is not sent, so every size whose blocks fill the region
+    /// is tried with a length prefix put back in front; the frame CRC rejects
+    /// wrong ones.
+    fn decode_legacy_region(&mut self, fsk_samples: &[f32]) -> Result<(Frame, DecodeQuality)> {
+        self.clear_symbol_audit();
+        let (bytes, symbol_stats, erasures) = self.fsk.demodulate_with_erasures(fsk_samples)?;
+        self.stats.symbols_demodulated += (fsk_samples.len() / FSK_SYMBOL_SAMPLES) as u32;
+        self.stats.erased_bytes += erasures.len() as u32;
+
+        // Symbols start after the length prefix put back in front
+        let alignment = SymbolAlignment { head: 2, unit: FSK_BYTES_PER_SYMBOL };
+        let erasures: Vec<usize> = erasures.iter().map(|&byte| byte + 2).collect();
+        let mut first_error = None;
+        for frame_len in legacy_frame_len_candidates(bytes.len()) {
+            let mut prefixed = (frame_len as u16).to_be_bytes().to_vec();
+            prefixed.extend_from_slice(&bytes);
+            match self.decode_frame_bytes(&prefixed, &symbol_stats, &erasures, alignment) {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => keep_error(&mut first_error, e),
+            }
+        }
+        Err(first_error.unwrap_or(AudioModemError::FecDecodeFailure))
+    }
+
+    /// Plain first symbol, pilots, then amplitude-shaped symbols (see `EncoderFsk::set_amplitude_bits`)
+    fn decode_amplitude_shaped_region(&mut self, fsk_samples: &[f32]) -> Result<(Frame, DecodeQuality)> {
+        let (first, rest) = fsk_samples.split_at(FSK_SYMBOL_SAMPLES);
+        self.clear_symbol_audit();
+        let (mut bytes, mut symbol_stats, mut erasures) = self.fsk.demodulate_with_erasures(first)?;
+        bytes[0] &= !LENGTH_PREFIX_LAYOUT_MASK;
+
+        let (rest_bytes, rest_stats, rest_erasures, contrast_db) =
+            self.fsk.demodulate_amplitude_shaped_with_erasures(rest)?;
+        self.stats.symbols_demodulated += (fsk_samples.len() / FSK_SYMBOL_SAMPLES) as u32;
+        erasures.extend(rest_erasures.iter().map(|&byte| bytes.len() + byte));
+        self.stats.erased_bytes += erasures.len() as u32;
+        bytes.extend(rest_bytes);
+        symbol_stats.merge(&rest_stats);
+
+        // Amplitude-shaped symbols do not hold whole bytes, so their padding is not checked
+        let alignment = SymbolAlignment { head: FSK_BYTES_PER_SYMBOL, unit: 1 };
+        let (frame, mut quality) = self.decode_frame_bytes(&bytes, &symbol_stats, &erasures, alignment)?;
+        if !frame.amplitude_bits {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
+        quality.amplitude_contrast_db = Some(contrast_db);
+        Ok((frame, quality))
+    }
+
+    /// Standard first symbol, then symbols of `layout` (see `EncoderFsk::set_tone_layout`)
+    fn decode_layout_region(&mut self, fsk_samples: &[f32], layout: ToneLayout) -> Result<(Frame, DecodeQuality)> {
+        let (first, rest) = fsk_samples.split_at(FSK_SYMBOL_SAMPLES);
+        self.clear_symbol_audit();
+        let (mut bytes, mut symbol_stats, mut erasures) = self.fsk.demodulate_with_erasures(first)?;
+        bytes[0] &= !LENGTH_PREFIX_LAYOUT_MASK;
+
+        let (rest_bytes, rest_stats, rest_erasures) =
+            self.demodulator(FSK_SYMBOL_SAMPLES, layout).demodulate_with_erasures(rest)?;
+        self.stats.symbols_demodulated += (fsk_samples.len() / FSK_SYMBOL_SAMPLES) as u32;
+        erasures.extend(rest_erasures.iter().map(|&byte| bytes.len() + byte));
+        self.stats.erased_bytes += erasures.len() as u32;
+        bytes.extend(rest_bytes);
+        symbol_stats.merge(&rest_stats);
+        self.decode_frame_bytes(&bytes, &symbol_stats, &erasures, SymbolAlignment::after_first_symbol(layout))
+    }
+
+    /// Plain first symbol, then differential symbols (see `EncoderFsk::set_differential`)
+    fn decode_differential_region(&mut self, fsk_samples: &[f32]) -> Result<(Frame, DecodeQuality)> {
+        let (first, rest) = fsk_samples.split_at(FSK_SYMBOL_SAMPLES);
+        self.clear_symbol_audit();
+        let (mut bytes, mut symbol_stats, mut erasures) = self.fsk.demodulate_with_erasures(first)?;
+
+        let mut demodulator = self.demodulator(FSK_SYMBOL_SAMPLES, ToneLayout::STANDARD);
+        demodulator.set_differential(true);
+        let (rest_bytes, rest_stats, rest_erasures) = demodulator.demodulate_with_erasures(rest)?;
+        self.stats.symbols_demodulated += (fsk_samples.len() / FSK_SYMBOL_SAMPLES) as u32;
+        erasures.extend(rest_erasures.iter().map(|&byte| bytes.len() + byte));
+        self.stats.erased_bytes += erasures.len() as u32;
+        bytes.extend(rest_bytes);
+        symbol_stats.merge(&rest_stats);
+
+        let (frame, quality) = self.decode_frame_bytes(&bytes, &symbol_stats, &erasures, SymbolAlignment::PLAIN)?;
+        if !frame.differential {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
+        Ok((frame, quality))
+    }
+
+    /// Decode the RS-protected frame in demodulated bytes (length prefix first)
+    ///
+    /// `erasures` are indices into `bytes` of unreliable bytes, ascending.
+    fn decode_frame_bytes(
+        &mut self,
+        bytes: &[u8],
+        symbol_stats: &SymbolStats,
+        erasures: &[usize],
+        alignment: SymbolAlignment,
+    ) -> Result<(Frame, DecodeQuality)> {
+        let mut parity_byte_errors = 0;
 
         if bytes.len() < 2 {
             return Err(AudioModemError::InvalidFrameSize);
         }
+        self.inspect(|inspection| *inspection = FrameInspection::default());
+        self.check_squelch_snr(symbol_stats)?;
 
-        // Read 2-byte length prefix to determine frame data length
-        let frame_len = ((bytes[0] as u16) << 8) | (bytes[1] as u16);
-        let mut byte_idx = 2;
+        // Read 2-byte length prefix to determine frame data length; frames
+        // sent with symbol gaps or dual-band symbols have an options byte after it
+        let has_options = bytes[0] & LENGTH_PREFIX_OPTIONS_FLAG != 0;
+        let frame_len = (((bytes[0] & !LENGTH_PREFIX_OPTIONS_FLAG) as u16) << 8) | (bytes[1] as u16);
+        let mut byte_idx = if has_options { 3 } else { 2 };
+        self.inspect(|inspection| {
+            inspection.prefix = bytes[..byte_idx.min(bytes.len())].to_vec();
+            inspection.frame_len = frame_len as usize;
+        });
 
         // First pass: decode the first block to get FEC mode from header
-        let first_chunk_len = (frame_len as usize).min(223);
-        let padding_needed_first = 223 - first_chunk_len;
-
         // Try with different FEC modes to find the right one
+        // Start with Light (smallest overhead) and work up. Unless the
+        // (uncorrected) header byte names a preset, the custom code it
+        // announces follows, then, in case the byte itself was hit, every other
+        let mut candidates = vec![FecMode::Light, FecMode::Medium, FecMode::Full];
+        match bytes.get(byte_idx + 5).map(|&b| FecMode::from_u8(b)) {
+            Some(Ok(FecMode::Light | FecMode::Medium | FecMode::Full)) => {}
+            announced => {
+                let announced = announced.and_then(Result::ok);
+                candidates.extend(announced);
+                candidates.extend(FecMode::custom_modes().filter(|&mode| Some(mode) != announced));
+            }
+        }
+
         let mut decoded_first_block = None;
         let mut detected_fec_mode = FecMode::Light;
+        let mut first_chunk_len = 0;
 
-        for mode in [FecMode::Light, FecMode::Medium, FecMode::Full] {
+        for mode in candidates {
+            let chunk_len = (frame_len as usize).min(mode.data_bytes());
+            let padding_needed_first = mode.data_bytes() - chunk_len;
             let parity_bytes = mode.parity_bytes();
-            let encoded_len = first_chunk_len + parity_bytes;
+            let encoded_len = chunk_len + parity_bytes;
 
             if byte_idx + encoded_len <= bytes.len() {
                 let shortened_block = &bytes[byte_idx..byte_idx + encoded_len];
@@ -281,7 +1470,8 @@ impl DecoderFsk {
                 full_block.extend_from_slice(shortened_block);
 
                 // Try decoding with this FEC mode
-                if let Ok(decoded_chunk) = self.fec.decode_with_mode(&full_block, mode) {
+                let block_erasures = erasures_in_block(erasures, byte_idx, encoded_len, padding_needed_first);
+                if let Ok((decoded_chunk, corrected)) = self.fec_decode(&full_block, mode, &block_erasures) {
                     // Check if this produces a valid header
                     let decoded_data = &decoded_chunk[padding_needed_first..];
                     if decoded_data.len() >= 8 {
@@ -289,8 +1479,21 @@ impl DecoderFsk {
                             if let Ok(parsed_mode) = FecMode::from_u8(fec_mode_byte) {
                                 if parsed_mode == mode {
                                     // Found the correct FEC mode!
+                                    let corrected_before = self.stats.corrected_bytes;
+                                    parity_byte_errors += self.record_rs_block(corrected, mode)?;
+                                    let block = RsBlockReport {
+                                        offset: byte_idx,
+                                        data_bytes: chunk_len,
+                                        parity_bytes,
+                                        corrected_bytes: Some((self.stats.corrected_bytes - corrected_before) as usize),
+                                    };
+                                    self.inspect(|inspection| {
+                                        inspection.fec_mode = Some(mode);
+                                        inspection.blocks.push(block);
+                                    });
                                     decoded_first_block = Some((decoded_data.to_vec(), encoded_len));
                                     detected_fec_mode = mode;
+                                    first_chunk_len = chunk_len;
                                     break;
                                 }
                             }
@@ -300,8 +1503,10 @@ impl DecoderFsk {
             }
         }
 
-        let (first_decoded, first_encoded_len) = decoded_first_block
-            .ok_or(AudioModemError::FecDecodeFailure)?;
+        let Some((first_decoded, first_encoded_len)) = decoded_first_block else {
+            self.stats.rs_blocks_failed += 1;
+            return Err(AudioModemError::FecDecodeFailure);
+        };
 
         // Now decode remaining blocks using the detected FEC mode
         let mut decoded_data = first_decoded;
@@ -309,8 +1514,8 @@ impl DecoderFsk {
         let mut remaining_len = frame_len as usize - first_chunk_len;
 
         while remaining_len > 0 {
-            let chunk_len = remaining_len.min(223);
-            let padding_needed = 223 - chunk_len;
+            let chunk_len = remaining_len.min(detected_fec_mode.data_bytes());
+            let padding_needed = detected_fec_mode.data_bytes() - chunk_len;
             let parity_bytes = detected_fec_mode.parity_bytes();
             let encoded_len = chunk_len + parity_bytes;
 
@@ -321,6 +1526,8 @@ impl DecoderFsk {
 
             // Extract the shortened RS block
             let shortened_block = &bytes[byte_idx..byte_idx + encoded_len];
+            let block_erasures = erasures_in_block(erasures, byte_idx, encoded_len, padding_needed);
+            let offset = byte_idx;
             byte_idx += encoded_len;
 
             // Restore to full RS block by prepending zeros
@@ -328,253 +1535,123 @@ impl DecoderFsk {
             full_block.extend_from_slice(shortened_block);
 
             // Decode with RS using detected FEC mode
-            match self.fec.decode_with_mode(&full_block, detected_fec_mode) {
-                Ok(decoded_chunk) => {
-                    // Remove the prepended zeros (padding)
-                    decoded_data.extend_from_slice(&decoded_chunk[padding_needed..]);
-                }
-                Err(_) => {
-                    // FEC failed - might be corruption
-                    return Err(AudioModemError::FecDecodeFailure);
-                }
-            }
+            let corrected_before = self.stats.corrected_bytes;
+            let decoded = self.decode_rs_block(&full_block, detected_fec_mode, &block_erasures);
+            let block = RsBlockReport {
+                offset,
+                data_bytes: chunk_len,
+                parity_bytes,
+                corrected_bytes: decoded.is_ok().then(|| (self.stats.corrected_bytes - corrected_before) as usize),
+            };
+            self.inspect(|inspection| inspection.blocks.push(block));
+            let (decoded_chunk, block_parity_errors) = decoded?;
+            parity_byte_errors += block_parity_errors;
+            // Remove the prepended zeros (padding)
+            decoded_data.extend_from_slice(&decoded_chunk[padding_needed..]);
 
             remaining_len -= chunk_len;
         }
 
+        let padding = &bytes[byte_idx..alignment.end(byte_idx).min(bytes.len())];
+        self.inspect(|inspection| {
+            inspection.frame = decoded_data.clone();
+            if remaining_len == 0 {
+                inspection.padding = padding.to_vec();
+            }
+        });
+
         if decoded_data.is_empty() {
             return Err(AudioModemError::FecDecodeFailure);
         }
 
         // Decode frame structure
-        let frame = FrameDecoder::decode(&decoded_data)?;
+        let frame = match FrameDecoder::decode(&decoded_data) {
+            Err(AudioModemError::PayloadCrcMismatch) => {
+                let candidate = FrameDecoder::decode_unverified(&decoded_data)?;
+                return Err(self.payload_crc_error(|| candidate.payload));
+            }
+            result => result?,
+        };
 
         // Verify frame size is reasonable
         if frame.payload_len as usize > decoded_data.len() {
             return Err(AudioModemError::InvalidFrameSize);
         }
 
-        Ok(frame.payload)
-    }
-
-    /// Decode audio samples using fountain mode with continuous block accumulation
-    ///
-    /// Processes audio samples to extract fountain-encoded blocks and attempts
-    /// to decode the original data. Continues until successful decode or timeout.
-    ///
-    /// Returns the decoded payload or an error if decoding fails or timeout occurs.
-    pub fn decode_fountain(&mut self, samples: &[f32], config: Option<FountainConfig>) -> Result<Vec<u8>> {
-        let config = config.unwrap_or_default();
-
-        #[cfg(not(target_arch = "wasm32"))]
-        let start_time = Instant::now();
-        #[cfg(not(target_arch = "wasm32"))]
-        let timeout = Duration::from_secs(config.timeout_secs as u64);
-
-        let mut decoder: Option<Decoder> = None;
-        let mut search_offset = 0;
-        let mut frame_length: Option<usize> = None;
-        let mut symbol_size: Option<u16> = None;
-        let mut payload_samples_per_block =
-            Self::fountain_payload_samples(config.block_size as u16);
-
-        while search_offset < samples.len() {
-            // Check timeout (not available in WASM)
-            #[cfg(not(target_arch = "wasm32"))]
-            if start_time.elapsed() >= timeout {
-                return Err(AudioModemError::Timeout);
-            }
-
-            // Look for next fountain preamble (three-note whistle)
-            let remaining = &samples[search_offset..];
-            let preamble_search_window = PREAMBLE_SAMPLES + payload_samples_per_block;
-            let search_len = remaining.len().min(preamble_search_window);
-            if search_len < PREAMBLE_SAMPLES {
-                break;
-            }
-            let preamble_slice = &remaining[..search_len];
-            let preamble_pos = match detect_fountain_preamble(preamble_slice, self.preamble_threshold) {
-                Some(pos) => pos,
-                None => break,
-            };
+        self.check_padding(padding)?;
 
-            // Account for silence after preamble (1/8 second = 2000 samples at 16kHz)
-            let data_start = search_offset + preamble_pos + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+        Ok((frame, DecodeQuality::from_stats(symbol_stats, parity_byte_errors, 0)))
+    }
 
-            if data_start + FSK_SYMBOL_SAMPLES > samples.len() {
-                break;
-            }
+    /// Count the symbol padding after a decoded frame, rejecting it in strict mode
+    fn check_padding(&mut self, padding: &[u8]) -> Result<()> {
+        let mismatches = padding_mismatches(padding);
+        self.stats.padding_bytes += padding.len() as u32;
+        self.stats.padding_mismatches += mismatches as u32;
+        if self.strict_padding && mismatches > 0 {
+            return Err(AudioModemError::UnexpectedPadding { bytes: mismatches });
+        }
+        Ok(())
+    }
 
-            // Extract the expected FSK payload based on configured block size
-            let data_end = data_start.saturating_add(payload_samples_per_block);
-            if data_end > samples.len() {
-                break;
+    /// Decode one full RS block, counting it in the stats; also returns the bytes it corrected
+    fn decode_rs_block(&mut self, full_block: &[u8], mode: FecMode, erasures: &[usize]) -> Result<(Vec<u8>, usize)> {
+        match self.fec_decode(full_block, mode, erasures) {
+            Ok((decoded, corrected)) => {
+                let corrected = self.record_rs_block(corrected, mode)?;
+                Ok((decoded, corrected))
             }
-            let fsk_samples = &samples[data_start..data_end];
-
-            // Demodulate fountain block
-            match self.fsk.demodulate(fsk_samples) {
-                Ok(block_data) => {
-                    let mut slice = block_data.as_slice();
-
-                    if slice.len() < 6 {
-                        search_offset = data_end;
-                        continue;
-                    }
-
-                    let len_bytes = [slice[0], slice[1], slice[2], slice[3]];
-                    let parsed_frame_len = u32::from_be_bytes(len_bytes) as usize;
-
-                    let sym_bytes = [slice[4], slice[5]];
-                    let parsed_symbol_size = u16::from_be_bytes(sym_bytes);
-
-                    match frame_length {
-                        Some(existing) if existing != parsed_frame_len => {
-                            search_offset = data_end;
-                            continue;
-                        }
-                        Some(_) => {}
-                        None => frame_length = Some(parsed_frame_len),
-                    }
-
-                    let mut symbol_updated = false;
-                    match symbol_size {
-                        Some(existing) if existing != parsed_symbol_size => {
-                            search_offset = data_end;
-                            continue;
-                        }
-                        Some(_) => {}
-                        None => {
-                            symbol_size = Some(parsed_symbol_size);
-                            symbol_updated = true;
-                        }
-                    }
-
-                    if symbol_updated {
-                        payload_samples_per_block =
-                            Self::fountain_payload_samples(parsed_symbol_size);
-                    }
-
-                    slice = &slice[6..];
-
-                    if slice.len() < 2 {
-                        search_offset = data_end;
-                        continue;
-                    }
-
-                    let packet_len = u16::from_be_bytes([slice[0], slice[1]]) as usize;
-                    slice = &slice[2..];
-
-                    if slice.len() < packet_len + 2 {
-                        // Need packet_len bytes + 2 bytes for CRC-16
-                        search_offset = data_end;
-                        continue;
-                    }
-
-                    let packet_bytes = &slice[..packet_len];
-                    // Extract and validate packet CRC-16 for early corruption detection
-                    let received_crc = u16::from_be_bytes([slice[packet_len], slice[packet_len + 1]]);
-                    let computed_crc = crc16(packet_bytes);
-
-                    if received_crc != computed_crc {
-                        // Packet corrupted - skip it and continue
-                        self.stats.failed_blocks += 1;
-                        search_offset = data_end;
-                        continue;
-                    }
-
-                    // CRC passed - count as successfully decoded block
-                    self.stats.decoded_blocks += 1;
-
-                    // Attempt to deserialize the packet. The raptorq library's EncodingPacket::deserialize
-                    // may panic if the input is malformed. We validate packet length and CRC above, but the
-                    // format may still be invalid if the packet structure itself is corrupted.
-                    // We use catch_unwind as a defensive measure. If the library ever provides a fallible
-                    // API (e.g., Result<EncodingPacket, Error>), prefer that over panic handling.
-                    // See: https://github.com/cberner/raptorq for library issues and fallible API tracking
-
-                    // Additional validation: check minimum packet length
-                    // RaptorQ encoding packets have a minimum structure size (typically 4+ bytes for header)
-                    if packet_bytes.len() < 4 {
-                        warn!(
-                            "EncodingPacket too short for deserialization (len={})",
-                            packet_bytes.len()
-                        );
-                        search_offset = data_end;
-                        continue;
-                    }
-
-                    let packet = match catch_unwind(std::panic::AssertUnwindSafe(|| {
-                        EncodingPacket::deserialize(packet_bytes)
-                    })) {
-                        Ok(result) => result,
-                        Err(_) => {
-                            // Panic caught during deserialization - log and skip this packet
-                            // This indicates the packet structure is invalid despite passing CRC and length checks.
-                            // This can happen if audio demodulation errors produce bytes that pass CRC by chance,
-                            // or if the serialization format is incompatible with this decoder version.
-                            warn!(
-                                "EncodingPacket deserialization panic caught: malformed packet structure (len={})",
-                                packet_bytes.len()
-                            );
-                            search_offset = data_end;
-                            continue;
-                        }
-                    };
-
-                    // Initialize decoder on first packet with matching OTI
-                    if decoder.is_none() && frame_length.is_some() && symbol_size.is_some() {
-                        let oti = raptorq::ObjectTransmissionInformation::with_defaults(
-                            frame_length.unwrap() as u64,
-                            symbol_size.unwrap()
-                        );
-                        decoder = Some(Decoder::new(oti));
-                    }
-
-                    // Add packet and try to decode
-                    if let Some(ref mut dec) = decoder {
-                        // Attempt to decode with the packet
-                        // If decode fails (returns None), continue to next packet
-                        if let Some(decoded_data) = dec.decode(packet) {
-                            // Successfully decoded! Extract frame
-                            match FrameDecoder::decode(&decoded_data) {
-                                Ok(frame) => return Ok(frame.payload),
-                                Err(_) => {
-                                    // Frame decode failed, continue to next packet
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(_) => {
-                    // Demodulation failed
-                }
+            Err(_) => {
+                // FEC failed - might be corruption
+                self.stats.rs_blocks_failed += 1;
+                Err(AudioModemError::FecDecodeFailure)
             }
-
-            // No postamble in fountain mode - advance directly from data_end
-            search_offset = data_end;
         }
+    }
 
-        Err(AudioModemError::FountainDecodeFailure)
+    /// RS-decode a full block, rebuilding the bytes at `erasures` from the
+    /// parity; with too many erasures it is decoded from the errors alone.
+    /// Also returns the number of bytes corrected
+    fn fec_decode(&self, full_block: &[u8], mode: FecMode, erasures: &[usize]) -> Result<(Vec<u8>, usize)> {
+        self.fec
+            .correct(full_block, mode, erasures)
+            .or_else(|_| self.fec.correct(full_block, mode, &[]))
     }
 
-    fn fountain_payload_samples(symbol_size: u16) -> usize {
-        // Conservative estimate: symbol_size + 14 bytes accounting for all overhead and CRC
-        // Breakdown: 8 bytes metadata + 2 bytes CRC + 4 bytes serialization overhead
-        //   - Metadata: frame_len(4) + symbol_size(2) + packet_len(2) = 8 bytes
-        //   - CRC-16: 2 bytes for corruption detection
-        //   - Serialization overhead: 4 bytes (RaptorQ packet encoding, alignment padding, or protocol fields)
-        // If the serialization format changes (e.g., bincode header size, RaptorQ encoding changes),
-        // adjust the 4-byte serialization overhead component accordingly.
-        let packet_bytes = symbol_size as usize + 14;
-        let symbols = (packet_bytes + FSK_BYTES_PER_SYMBOL - 1) / FSK_BYTES_PER_SYMBOL;
-        symbols * FSK_SYMBOL_SAMPLES
+    /// Count a decoded RS block and its `corrected` bytes in the stats and
+    /// return them; `Squelched` if it needed more corrections than the
+    /// squelch allows
+    fn record_rs_block(&mut self, corrected: usize, mode: FecMode) -> Result<usize> {
+        self.stats.rs_blocks_decoded += 1;
+        self.stats.corrected_bytes += corrected as u32;
+        if self.squelch.max_corrections(mode).is_some_and(|max| corrected > max) {
+            self.stats.squelched += 1;
+            return Err(AudioModemError::Squelched);
+        }
+        Ok(corrected)
     }
 }
 
 impl Default for DecoderFsk {
     fn default() -> Self {
-        Self::new().unwrap()
+        Self {
+            fsk: FskDemodulator::new(),
+            fec: FecDecoder,
+            preamble_threshold: DetectionThreshold::Adaptive, // Default: use adaptive threshold
+            postamble_threshold: DetectionThreshold::Adaptive, // Default: use adaptive threshold
+            network_key: None,
+            fountain_melody: None,
+            strict_network_key: false,
+            strict_padding: false,
+            salvage_unverified: false,
+            speed_search: false,
+            squelch: Squelch::default(),
+            framing: FramingVariant::default(),
+            events: None,
+            inspection: None,
+            stats: DecodeStats::default(),
+            fountain_progress: FountainProgress::default(),
+        }
     }
 }
 
@@ -582,6 +1659,7 @@ impl Default for DecoderFsk {
 mod tests {
     use super::*;
     use crate::encoder_fsk::EncoderFsk;
+    use crate::units::Millis;
 
     #[test]
     fn test_decoder_fsk_basic_roundtrip() {
@@ -596,976 +1674,731 @@ mod tests {
     }
 
     #[test]
-    fn test_decoder_fsk_empty_data() {
+    fn test_decode_with_metadata_timestamp_and_position() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
 
-        let data = b"";
-        let samples = encoder.encode(data).unwrap();
-        let decoded = decoder.decode(&samples).unwrap();
-
-        assert_eq!(decoded, data);
+        let timestamp_us = 1_234_567_890_123u64;
+        let frame = encoder.encode_with_timestamp(b"clock sync", timestamp_us).unwrap();
+
+        // Receiver buffer starts one second before the transmission
+        let lead = SAMPLE_RATE;
+        let mut samples = vec![0.0f32; lead];
+        samples.extend_from_slice(&frame);
+
+        let message = decoder.decode_with_metadata(&samples).unwrap();
+        assert_eq!(message.payload, b"clock sync");
+        assert_eq!(message.timestamp_us, Some(timestamp_us));
+
+        // Encoder emits SYNC_SILENCE_SAMPLES of silence before the preamble
+        let expected = lead + SYNC_SILENCE_SAMPLES;
+        assert!(
+            message.preamble_position.abs_diff(expected) < 64,
+            "preamble at {}, expected ~{}",
+            message.preamble_position,
+            expected
+        );
+        assert_eq!(message.data_start, message.preamble_position + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES);
+        assert!(message.preamble_offset_us().abs_diff(1_125_000) < 5_000);
+
+        // Plain decode carries no timestamp
+        let plain = encoder.encode(b"clock sync").unwrap();
+        assert_eq!(decoder.decode_with_metadata(&plain).unwrap().timestamp_us, None);
     }
 
     #[test]
-    fn test_decoder_fsk_various_lengths() {
+    fn test_decode_encoder_version() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
+        assert_eq!(decoder.decode_with_metadata(&encoder.encode(b"v").unwrap()).unwrap().encoder_version, None);
 
-        let test_cases = vec![
-            b"A".to_vec(),
-            b"AB".to_vec(),
-            b"ABC".to_vec(),
-            b"The quick brown fox".to_vec(),
-            vec![0u8; 50],
-            vec![255u8; 30],
-        ];
-
-        for data in test_cases {
-            let samples = encoder.encode(&data).unwrap();
-            let decoded = decoder.decode(&samples).unwrap();
-            assert_eq!(decoded, data, "Failed for data length {}", data.len());
+        encoder.set_version_beacon(true);
+        for profile in [FrameProfile::Standard, FrameProfile::CodecRobust] {
+            encoder.set_profile(profile);
+            let message = decoder.decode_with_metadata(&encoder.encode(b"v").unwrap()).unwrap();
+            assert_eq!(message.encoder_version, Some(EncoderVersion::CURRENT), "{:?}", profile);
         }
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(encoder.encode(b"v"), Err(AudioModemError::InvalidConfig(_))));
     }
 
     #[test]
-    fn test_decoder_fsk_binary_data() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-
-        // Test binary data (not just ASCII)
-        let data: Vec<u8> = (0..100).map(|i| i as u8).collect();
-        let samples = encoder.encode(&data).unwrap();
-        let decoded = decoder.decode(&samples).unwrap();
-
-        assert_eq!(decoded, data);
-    }
-
-    #[test]
-    fn test_decoder_fsk_with_noise() {
+    fn test_decode_content_type() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-
-        let data = b"Noisy channel test";
-        let mut samples = encoder.encode(data).unwrap();
-
-        // Add small amount of noise (5% of signal)
-        for sample in samples.iter_mut() {
-            let noise = (*sample * 123.456).sin() * 0.05;
-            *sample += noise;
-        }
-
-        let decoded = decoder.decode(&samples).unwrap();
-        assert_eq!(decoded, data);
-    }
-
-    #[test]
-    fn test_decoder_fsk_insufficient_data() {
-        let mut decoder = DecoderFsk::new().unwrap();
-
-        // Too few samples
-        let samples = vec![0.0; 100];
-        let result = decoder.decode(&samples);
-        assert!(result.is_err());
+        encoder.set_content_type(Some(ContentType::Url));
+        for profile in [FrameProfile::Standard, FrameProfile::Telephony] {
+            encoder.set_profile(profile);
+            let message = decoder.decode_with_metadata(&encoder.encode(b"https://example.com").unwrap()).unwrap();
+            assert_eq!(message.payload, b"https://example.com", "{:?}", profile);
+            assert_eq!(message.content_type, Some(ContentType::Url), "{:?}", profile);
+        }
+
+        encoder.set_profile(FrameProfile::Standard);
+        let frame = encoder.encode_with_timestamp(b"both", 77).unwrap();
+        let message = decoder.decode_with_metadata(&frame).unwrap();
+        assert_eq!((message.timestamp_us, message.content_type), (Some(77), Some(ContentType::Url)));
+
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(encoder.encode(b"short"), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_content_type(None);
+        assert_eq!(decoder.decode_with_metadata(&encoder.encode(b"short").unwrap()).unwrap().content_type, None);
     }
 
     #[test]
-    fn test_decoder_fsk_no_preamble() {
-        let mut decoder = DecoderFsk::new().unwrap();
-
-        // Random noise without preamble
-        let samples: Vec<f32> = (0..10000).map(|i| (i as f32 * 0.1).sin() * 0.1).collect();
-        let result = decoder.decode(&samples);
-        assert!(result.is_err());
-    }
+    fn test_decode_quality_degrades_with_noise() {
+        use rand::{Rng, SeedableRng};
 
-    #[test]
-    fn test_decoder_fsk_large_payload() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-
-        // Test with larger payload (near maximum)
-        let data = vec![42u8; 180];
-        let samples = encoder.encode(&data).unwrap();
-        let decoded = decoder.decode(&samples).unwrap();
-
-        assert_eq!(decoded, data);
+        let frame = encoder.encode(b"quality check").unwrap();
+
+        let clean = decoder.decode_with_metadata(&frame).unwrap().quality;
+        assert!(clean.min_symbol_margin > 0.5, "{:?}", clean);
+        assert!(clean.snr_db > 20.0, "{:?}", clean);
+        assert_eq!(clean.parity_byte_errors, 0);
+        assert_eq!(clean.failed_blocks, 0);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1606);
+        let noisy_samples: Vec<f32> = frame.iter().map(|s| s + rng.gen_range(-0.3..0.3)).collect();
+        let noisy = decoder.decode_with_metadata(&noisy_samples).unwrap();
+        assert_eq!(noisy.payload, b"quality check");
+        assert!(noisy.quality.snr_db < clean.snr_db);
+        assert!(noisy.quality.mean_symbol_margin < clean.mean_symbol_margin);
+        assert!(noisy.quality.min_symbol_margin <= noisy.quality.mean_symbol_margin);
+
+        // Every corrected byte counts, in the data as well as the parity
+        let data_start = decoder.decode_with_metadata(&frame).unwrap().data_start;
+        let symbol = |index: usize| data_start + index * FSK_SYMBOL_SAMPLES;
+        let mut hit = frame.clone();
+        hit.copy_within(symbol(3)..symbol(4), symbol(4));
+        let hit = decoder.decode_with_metadata(&hit).unwrap();
+        assert_eq!(hit.payload, b"quality check");
+        assert!(hit.quality.parity_byte_errors > 0, "{:?}", hit.quality);
+        assert_eq!(hit.quality.parity_byte_errors, decoder.stats.corrected_bytes as usize);
     }
 
     #[test]
-    fn test_decoder_fsk_repeating_patterns() {
+    fn test_decode_falls_back_to_next_preamble_candidate() {
+        use crate::sync::generate_preamble;
+        use rand::{Rng, SeedableRng};
+
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-
-        // Test with repeating byte patterns
-        let patterns = vec![
-            vec![0x00; 20],      // All zeros
-            vec![0xFF; 20],      // All ones
-            vec![0xAA; 20],      // Alternating bits
-            vec![0x55; 20],      // Alternating bits (inverse)
-        ];
-
-        for data in patterns {
-            let samples = encoder.encode(&data).unwrap();
-            let decoded = decoder.decode(&samples).unwrap();
-            assert_eq!(decoded, data, "Failed for pattern {:02X}", data[0]);
-        }
+        let data = b"second candidate";
+
+        // A clean decoy preamble with no frame behind it, followed by a noisy real frame
+        let mut samples = vec![0.0f32; SYNC_SILENCE_SAMPLES];
+        samples.extend_from_slice(&generate_preamble(PREAMBLE_SAMPLES, 0.5));
+        samples.extend_from_slice(&vec![0.0f32; 3 * SYNC_SILENCE_SAMPLES]);
+        let decoy_len = samples.len();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1602);
+        let frame = encoder.encode(data).unwrap();
+        samples.extend(frame.iter().map(|s| s + rng.gen_range(-0.05..0.05)));
+
+        let candidates = crate::sync::detect_preamble_candidates(
+            &samples,
+            DetectionThreshold::Adaptive,
+            MAX_PREAMBLE_CANDIDATES,
+        );
+        assert!(candidates.len() >= 2);
+        assert!(candidates[0].position < decoy_len, "decoy should outscore the noisy preamble");
+
+        let message = decoder.decode_with_metadata(&samples).unwrap();
+        assert_eq!(message.payload, data);
+        assert!(message.preamble_position >= decoy_len);
     }
 
     #[test]
-    fn test_fountain_roundtrip_basic() {
-        use crate::fsk::FountainConfig;
+    fn test_decode_stereo_survives_damaged_channel() {
+        use crate::stereo::to_stereo;
+        use rand::{Rng, SeedableRng};
 
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Fountain roundtrip test";
-
-        let config = FountainConfig {
-            timeout_secs: 5,
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
-        // Generate fountain blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-
-        // Collect multiple blocks into one continuous audio stream
-        let blocks: Vec<_> = stream.take(10).collect();
-        let mut samples = Vec::new();
-        for block in blocks {
-            samples.extend_from_slice(&block);
+        let data = b"stereo redundancy";
+        let frame = encoder.encode(data).unwrap();
+
+        for redundancy in [StereoRedundancy::Delayed(160), StereoRedundancy::FrequencyOffset(45.0)] {
+            let (mut left, right) = to_stereo(&frame, redundancy);
+            assert_eq!(decoder.decode_stereo(&left, &right, redundancy).unwrap().payload, data);
+
+            // Wipe out the data section of the left channel
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1609);
+            let data_start = 2 * SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES;
+            for s in &mut left[data_start..data_start + 12 * FSK_SYMBOL_SAMPLES] {
+                *s = rng.gen_range(-1.0..1.0);
+            }
+            assert!(decoder.decode(&left).is_err());
+            assert_eq!(decoder.decode_stereo(&left, &right, redundancy).unwrap().payload, data);
         }
-
-        // Decode
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data);
     }
 
     #[test]
-    fn test_fountain_with_packet_loss() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_empty_data() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Test with some packet loss";
-
-        let config = FountainConfig {
-            timeout_secs: 30, // Enough audio duration to generate 20 blocks
-            block_size: 32,
-            repair_blocks_ratio: 1.0, // More redundancy
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
 
-        // Simulate packet loss by dropping every 3rd block
-        let mut samples = Vec::new();
-        for (i, block) in blocks.iter().enumerate() {
-            if i % 3 != 0 {  // Drop every 3rd block
-                samples.extend_from_slice(block);
-            }
-        }
+        let data = b"";
+        let samples = encoder.encode(data).unwrap();
+        let decoded = decoder.decode(&samples).unwrap();
 
-        // Should still decode successfully due to fountain coding
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
         assert_eq!(decoded, data);
     }
 
     #[test]
-    fn test_fountain_various_data_sizes() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_various_lengths() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
 
-        let config = FountainConfig {
-            timeout_secs: 20, // Enough audio duration to generate 15 blocks
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
         let test_cases = vec![
             b"A".to_vec(),
-            b"Short".to_vec(),
-            b"Medium length data for testing".to_vec(),
-            vec![42u8; 100],
-        ];
-
-        for data in test_cases {
-            let stream = encoder.encode_fountain(&data, Some(config.clone())).unwrap();
-            let blocks: Vec<_> = stream.take(15).collect();
-            let mut samples = Vec::new();
-            for block in blocks {
-                samples.extend_from_slice(&block);
-            }
-
-            let decoded = decoder.decode_fountain(&samples, Some(config.clone())).unwrap();
-            assert_eq!(decoded, data, "Failed for data length {}", data.len());
-        }
-    }
-
-    #[test]
-    fn test_fountain_single_block_crc_corruption() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Test CRC corruption detection";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(15).collect();
-
-        // Corrupt the first block with bit flipping pattern
-        let mut corrupted_blocks = blocks.clone();
-        let first_block = &mut corrupted_blocks[0];
-
-        // Flip bits in the middle section (simulate bit corruption in CRC area)
-        for i in (first_block.len() / 2)..(first_block.len() / 2 + 60).min(first_block.len()) {
-            // Flip sign bit to corrupt the sample
-            let bits = first_block[i].to_bits() ^ 0x80000000u32;
-            first_block[i] = f32::from_bits(bits);
-        }
-
-        // Reconstruct audio stream
-        let mut samples = Vec::new();
-        for block in &corrupted_blocks {
-            samples.extend_from_slice(block);
-        }
-
-        // Decoding should still succeed with remaining good blocks
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should recover despite first block corruption");
-    }
-
-    #[test]
-    fn test_fountain_multiple_blocks_crc_corruption() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Test multiple corruptions";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0, // Extra redundancy
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Corrupt multiple blocks with different patterns - inject invalid data selectively
-        let mut corrupted_blocks = blocks.clone();
-        for block_idx in [0, 3, 7] {
-            if block_idx < corrupted_blocks.len() {
-                let block = &mut corrupted_blocks[block_idx];
-                // Corrupt only a small portion with invalid data to allow recovery
-                let corruption_start = (block.len() / 4).max(20);
-                let corruption_end = (corruption_start + 25).min(block.len());
-                for i in corruption_start..corruption_end {
-                    // Flip bits to simulate data corruption without destroying entire block
-                    let bits = block[i].to_bits() ^ 0x80000000u32;  // Flip sign bit
-                    block[i] = f32::from_bits(bits);
-                }
-            }
-        }
-
-        // Reconstruct audio stream
-        let mut samples = Vec::new();
-        for block in &corrupted_blocks {
-            samples.extend_from_slice(block);
-        }
-
-        // Should still succeed with sufficient redundancy
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should recover with multiple block corruptions");
-    }
-
-    #[test]
-    fn test_fountain_crc_rejects_invalid_packets() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Verify CRC validation";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: FOUNTAIN_BLOCK_SIZE,
-            repair_blocks_ratio: 0.5,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(12).collect();
-
-        // Create a highly corrupted block by mixing in invalid data
-        let mut corrupted_blocks = blocks.clone();
-        if !corrupted_blocks.is_empty() {
-            let block = &mut corrupted_blocks[0];
-            // Corrupt with invalid FSK samples - flip bits in multiple sections
-            for i in 0..block.len().min(80) {
-                // Flip bits at every 3rd position to create detectable corruption
-                if i % 3 == 0 {
-                    let bits = block[i].to_bits() ^ 0x80000000u32;  // Flip sign bit
-                    block[i] = f32::from_bits(bits);
-                }
-            }
-        }
-
-        // Reconstruct audio stream with corrupted first block
-        let mut samples = Vec::new();
-        for block in &corrupted_blocks {
-            samples.extend_from_slice(block);
-        }
-
-        // Decoding should succeed by skipping the corrupted block and using others
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should skip invalid CRC blocks");
-    }
-
-    #[test]
-    fn test_fountain_crc_detects_bit_flips() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Bit flip detection test";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 0.75,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(15).collect();
-
-        // Corrupt early blocks with systematic bit flipping
-        let mut corrupted_blocks = blocks.clone();
-        for block_idx in 0..2.min(corrupted_blocks.len()) {
-            let block = &mut corrupted_blocks[block_idx];
-            // Simulate bit flips in the middle section
-            let start = block.len() / 3;
-            let end = (start + 40).min(block.len());
-            for i in start..end {
-                // Flip sign bit to corrupt samples
-                let bits = block[i].to_bits() ^ 0x80000000u32;
-                block[i] = f32::from_bits(bits);
-            }
-        }
-
-        // Reconstruct audio stream
-        let mut samples = Vec::new();
-        for block in &corrupted_blocks {
-            samples.extend_from_slice(block);
-        }
-
-        // Should still decode successfully
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should handle bit flip corruptions");
-    }
-
-    #[test]
-    fn test_fountain_crc_with_packet_loss_and_corruption() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Combined loss and corruption";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Simulate both packet loss and corruption with invalid data injection
-        let mut samples = Vec::new();
-        for (i, block) in blocks.iter().enumerate() {
-            let mut modified_block = block.clone();
-
-            // Drop every 5th block (packet loss)
-            if i % 5 == 0 {
-                continue;
-            }
-
-            // Corrupt every 3rd block that we keep with bit flipping
-            if (i / 3) % 2 == 0 {
-                let start = modified_block.len() / 4;
-                let end = (start + 30).min(modified_block.len());
-                for j in start..end {
-                    // Flip sign bit in corrupted section
-                    let bits = modified_block[j].to_bits() ^ 0x80000000u32;
-                    modified_block[j] = f32::from_bits(bits);
-                }
-            }
+            b"AB".to_vec(),
+            b"ABC".to_vec(),
+            b"The quick brown fox".to_vec(),
+            vec![0u8; 50],
+            vec![255u8; 30],
+        ];
 
-            samples.extend_from_slice(&modified_block);
+        for data in test_cases {
+            let samples = encoder.encode(&data).unwrap();
+            let decoded = decoder.decode(&samples).unwrap();
+            assert_eq!(decoded, data, "Failed for data length {}", data.len());
         }
-
-        // Should still decode with sufficient redundancy
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should handle packet loss + corruption");
     }
 
     #[test]
-    fn test_fountain_crc_passes_for_clean_data() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_binary_data() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Clean data with valid CRC";
-
-        let config = FountainConfig {
-            timeout_secs: 20,
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
 
-        // Generate blocks (no corruption)
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(10).collect();
-
-        let mut samples = Vec::new();
-        for block in blocks {
-            samples.extend_from_slice(&block);
-        }
+        // Test binary data (not just ASCII)
+        let data: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let samples = encoder.encode(&data).unwrap();
+        let decoded = decoder.decode(&samples).unwrap();
 
-        // All CRCs should pass, decoding should be quick
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Clean data should decode correctly");
+        assert_eq!(decoded, data);
     }
 
     #[test]
-    fn test_fountain_integration_alternating_good_bad_blocks() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_with_noise() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Integration test: alternating good/bad blocks";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 0.75,
-        };
 
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(18).collect();
-
-        // Pattern: good, bad, good, bad, ... (alternating with bit flipping)
-        let mut processed_blocks = blocks.clone();
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            if i % 2 == 1 {
-                // Corrupt odd-indexed blocks (1, 3, 5, ...) with bit flipping
-                let start = block.len() / 3;
-                let end = (start + 35).min(block.len());
-                for j in start..end {
-                    // Flip sign bit in corrupted section
-                    let bits = block[j].to_bits() ^ 0x80000000u32;
-                    block[j] = f32::from_bits(bits);
-                }
-            }
-        }
+        let data = b"Noisy channel test";
+        let mut samples = encoder.encode(data).unwrap();
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
+        // Add small amount of noise (5% of signal)
+        for sample in samples.iter_mut() {
+            let noise = (*sample * 123.456).sin() * 0.05;
+            *sample += noise;
         }
 
-        // Should successfully decode despite every other block being bad
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with alternating good/bad blocks");
+        let decoded = decoder.decode(&samples).unwrap();
+        assert_eq!(decoded, data);
     }
 
     #[test]
-    fn test_fountain_integration_burst_corruption() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
+    fn test_decoder_fsk_insufficient_data() {
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Burst error: consecutive bad blocks";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0, // Extra redundancy for burst recovery
-        };
 
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Simulate burst corruption: blocks 3-7 all corrupted with bit flipping
-        let mut processed_blocks = blocks.clone();
-        for i in 3..8 {
-            if i < processed_blocks.len() {
-                let block = &mut processed_blocks[i];
-                // Corrupt with bit flipping patterns
-                let start = block.len() / 4;
-                let end = (start + 35).min(block.len());
-                for j in start..end {
-                    // Flip sign bit to corrupt the sample
-                    let bits = block[j].to_bits() ^ 0x80000000u32;
-                    block[j] = f32::from_bits(bits);
-                }
-            }
-        }
+        // Too few samples
+        let samples = vec![0.0; 100];
+        let result = decoder.decode(&samples);
+        assert!(result.is_err());
+    }
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
-        }
+    #[test]
+    fn test_decoder_fsk_no_preamble() {
+        let mut decoder = DecoderFsk::new().unwrap();
 
-        // Should recover despite burst of 5 consecutive bad blocks
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should recover from burst corruption with good blocks before/after");
+        // Random noise without preamble
+        let samples: Vec<f32> = (0..10000).map(|i| (i as f32 * 0.1).sin() * 0.1).collect();
+        let result = decoder.decode(&samples);
+        assert!(result.is_err());
     }
 
     #[test]
-    fn test_fountain_integration_sparse_good_blocks() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_large_payload() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Sparse: mostly bad, few good blocks";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
-
-        // Generate many blocks to ensure we have enough good ones
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(25).collect();
-
-        // Keep only blocks at indices 2, 5, 10, 15, 22 (sparse good blocks)
-        let mut processed_blocks = blocks.clone();
-        let good_indices = [2, 5, 10, 15, 22];
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            if !good_indices.contains(&i) {
-                // Corrupt all blocks except the good ones with bit flipping
-                let start = block.len() / 5;
-                let end = (start + 40).min(block.len());
-                for j in start..end {
-                    // Flip sign bit in corrupted section
-                    let bits = block[j].to_bits() ^ 0x80000000u32;
-                    block[j] = f32::from_bits(bits);
-                }
-            }
-        }
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
-        }
+        // Test with larger payload (near maximum)
+        let data = vec![42u8; 180];
+        let samples = encoder.encode(&data).unwrap();
+        let decoded = decoder.decode(&samples).unwrap();
 
-        // Should decode with minimal good blocks due to fountain redundancy
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with sparse valid blocks");
+        assert_eq!(decoded, data);
     }
 
     #[test]
-    fn test_fountain_integration_good_blocks_early_then_bad() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_custom_rs_code() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Good early, bad later: early blocks good";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
+        let strong = FecMode::custom(255, 191).unwrap();
+        encoder.set_fec_mode(Some(strong));
 
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(16).collect();
-
-        // First 6 blocks are good, remaining are bad
-        let mut processed_blocks = blocks.clone();
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            if i >= 6 {
-                // Corrupt blocks 6 and onwards
-                for j in 0..block.len().min(120) {
-                    block[j] = block[j] * 0.25;
-                }
-            }
-        }
-
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
+        // Spans three 191-byte blocks; the mode is recovered from the header
+        for data in [b"short".to_vec(), (0..400).map(|i| (i * 7) as u8).collect()] {
+            let samples = encoder.encode(&data).unwrap();
+            assert_eq!(decoder.decode(&samples).unwrap(), data);
         }
 
-        // Should successfully decode with good source blocks at beginning
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode when first blocks are clean");
+        // More parity than the default Full mode makes for a longer transmission
+        let mut default_encoder = EncoderFsk::new().unwrap();
+        let payload = vec![1u8; 300];
+        assert!(encoder.encode(&payload).unwrap().len() > default_encoder.encode(&payload).unwrap().len());
     }
 
     #[test]
-    fn test_fountain_integration_bad_blocks_early_then_good() {
-        use crate::fsk::FountainConfig;
+    fn test_custom_rs_code_corrects_errors() {
+        use crate::fec::FecEncoder;
+        use crate::framing::FrameEncoder;
+        use crate::fsk::FskModulator;
+
+        let strong = FecMode::custom(255, 191).unwrap();
+        let frame_data = FrameEncoder::encode(&Frame::new(b"noisy channel", 0, strong.to_u8())).unwrap();
+        let padding = strong.data_bytes() - frame_data.len();
+        let mut padded = vec![0u8; padding];
+        padded.extend_from_slice(&frame_data);
+        let mut encoded = (frame_data.len() as u16).to_be_bytes().to_vec();
+        encoded.extend_from_slice(&FecEncoder::new().unwrap().encode_with_mode(&padded, strong).unwrap()[padding..]);
+
+        // (N - K) / 2 = 32 byte errors, the first on the FEC mode byte, which
+        // then announces RS(255, 21)
+        for k in 0..32 {
+            encoded[2 + 5 + k * 2] ^= 0x55;
+        }
+        encoded.resize(encoded.len().next_multiple_of(FSK_BYTES_PER_SYMBOL), 0);
+        let samples = FskModulator::new().modulate(&encoded).unwrap();
 
-        let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Bad early, good later: repair blocks work";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 0.75,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(18).collect();
-
-        // First 5 blocks are bad, remaining are good
-        let mut processed_blocks = blocks.clone();
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            if i < 5 {
-                // Corrupt blocks 0-4
-                for j in 0..block.len().min(130) {
-                    block[j] = block[j] * 0.1;
-                }
-            }
-        }
+        assert_eq!(decoder.decode_without_preamble_postamble(&samples).unwrap(), b"noisy channel");
+        assert_eq!((decoder.stats.rs_blocks_decoded, decoder.stats.corrected_bytes), (1, 32));
+    }
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
-        }
+    #[test]
+    fn test_decoder_fsk_network_key() {
+        let key = Some(crate::NetworkKey::new(b"warehouse-7"));
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_network_key(key);
+        let samples = encoder.encode(b"keyed frame").unwrap();
 
-        // Should decode because repair blocks arrive later
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with repair blocks after bad source blocks");
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_network_key(key);
+        assert_eq!(decoder.decode(&samples).unwrap(), b"keyed frame");
+
+        // Unkeyed decoders and other keys do not see the frame
+        assert!(DecoderFsk::new().unwrap().decode(&samples).is_err());
+        decoder.set_network_key(Some(crate::NetworkKey::new(b"warehouse-8")));
+        assert!(decoder.decode(&samples).is_err());
+        let unkeyed = EncoderFsk::new().unwrap().encode(b"default").unwrap();
+        assert!(decoder.decode(&unkeyed).is_err());
     }
 
     #[test]
-    fn test_fountain_integration_random_pass_fail_pattern() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_strict_network_key() {
+        let key = Some(crate::NetworkKey::new(b"warehouse-7"));
         let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_network_key(key);
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Random: unpredictable pass/fail pattern";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Random corruption pattern: 2,4,5,8,12,14,17 are corrupted
-        let corrupted_indices = [2, 4, 5, 8, 12, 14, 17];
-        let mut processed_blocks = blocks.clone();
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            if corrupted_indices.contains(&i) {
-                // Corrupt these blocks
-                let corruption_level = 0.2 + (i as f32 * 0.05);
-                for j in 0..block.len().min(100 + i * 5) {
-                    block[j] = block[j] * corruption_level;
-                }
-            }
-        }
+        decoder.set_network_key(key);
+        decoder.set_strict_network_key(true);
+        let samples = encoder.encode(b"keyed frame").unwrap();
+        assert_eq!(decoder.decode(&samples).unwrap(), b"keyed frame");
+        assert!(decoder.stats.postamble_score.is_some());
+
+        encoder.set_profile(crate::FrameProfile::Short);
+        assert_eq!(decoder.decode(&encoder.encode(b"short").unwrap()).unwrap(), b"short");
+
+        // Matching preamble without the postamble of the key
+        let position = crate::sync::detect_keyed_postamble(&samples, DetectionThreshold::Adaptive, key).unwrap();
+        let mut foreign = samples.clone();
+        foreign[position..position + POSTAMBLE_SAMPLES].fill(0.0);
+        assert!(matches!(decoder.decode(&foreign), Err(AudioModemError::WrongNetwork)));
+        assert!(decoder.stats.wrong_network >= 1);
+
+        // The postamble of the next frame does not vouch for this one
+        encoder.set_profile(crate::FrameProfile::Standard);
+        let mut two_frames = foreign.clone();
+        two_frames.extend(encoder.encode(b"next frame").unwrap());
+        assert_eq!(decoder.decode(&two_frames).unwrap(), b"next frame");
+
+        // Nor does the postamble of another key in its place
+        let other = crate::sync::generate_keyed_postamble(Samples(POSTAMBLE_SAMPLES), 0.5, Some(crate::NetworkKey::new(b"warehouse-8")));
+        foreign[position..position + POSTAMBLE_SAMPLES].copy_from_slice(&other);
+        assert!(matches!(decoder.decode(&foreign), Err(AudioModemError::WrongNetwork)));
+
+        decoder.set_strict_network_key(false);
+        assert_eq!(decoder.decode(&foreign).unwrap(), b"keyed frame");
+    }
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
-        }
+    #[test]
+    fn test_sequence_roundtrip_with_missing_frame() {
+        let parts: Vec<Vec<u8>> = (0..4u8).map(|part| vec![b'a' + part; 20]).collect();
+        let frames: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+        let mut encoder = EncoderFsk::new().unwrap();
+        let frame_samples = encoder.airtime(20, false).unwrap().samples;
+        let gap_samples = SAMPLE_RATE * 3 / 10;
+        let samples = encoder.encode_sequence(&frames, Millis(300)).unwrap();
+        assert_eq!(samples.len(), 4 * frame_samples + 3 * gap_samples);
 
-        // Should successfully decode despite random corruption pattern
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with random pass/fail pattern");
+        let mut decoder = DecoderFsk::new().unwrap();
+        let sequence = decoder.decode_all(&samples).unwrap();
+        assert!(sequence.is_complete());
+        assert_eq!(sequence.payload().unwrap(), parts.concat());
+        let positions: Vec<usize> = sequence.frames.iter().map(|frame| frame.as_ref().unwrap().preamble_position).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", positions);
+
+        // Frame 1 lost: the others still come back in place
+        let mut lossy = samples.clone();
+        let start = frame_samples + gap_samples;
+        lossy[start..start + frame_samples].fill(0.0);
+        let sequence = decoder.decode_all(&lossy).unwrap();
+        assert_eq!(sequence.missing(), vec![1]);
+        assert!(sequence.payload().is_none());
+        assert_eq!(sequence.frames[3].as_ref().unwrap().payload, parts[3]);
+
+        let single = encoder.encode(b"not a sequence").unwrap();
+        assert!(matches!(decoder.decode_all(&single), Err(AudioModemError::FrameNumberMismatch)));
+        assert!(encoder.encode_sequence(&[], Millis(300)).is_err());
+        encoder.set_profile(crate::FrameProfile::Short);
+        assert!(encoder.encode_sequence(&frames, Millis(300)).is_err());
     }
 
     #[test]
-    fn test_fountain_integration_progressive_degradation() {
-        use crate::fsk::FountainConfig;
-
+    fn test_short_frame_profile_roundtrip_and_latency() {
         let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_profile(crate::FrameProfile::Short);
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Progressive: quality degrades over time";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
 
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Simulate progressive channel degradation with escalating invalid data injection
-        let mut processed_blocks = blocks.clone();
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            // Corruption level increases with block index
-            let corruption_level = 0.1 + (i as f32 * 0.04);
-            if corruption_level > 0.5 {
-                // Corrupt with progressive bit flipping
-                let start = block.len() / 6;
-                let end = (start + 30 + i).min(block.len());
-                for j in start..end {
-                    // Progressive bit flipping as blocks degrade
-                    let bits = block[j].to_bits() ^ 0x80000000u32;
-                    block[j] = f32::from_bits(bits);
-                }
+        let message = b"door 3 open: ok!";
+        let samples = encoder.encode(message).unwrap();
+        // Preamble to postamble end, i.e. until the receiver can act on the frame
+        let on_air = samples.len() - encoder.padding().trailing_silence_samples;
+        assert!(on_air * 1000 / SAMPLE_RATE < 700, "{} ms", on_air * 1000 / SAMPLE_RATE);
+
+        let decoded = decoder.decode_with_metadata(&samples).unwrap();
+        assert_eq!(decoded.payload, message);
+        assert_eq!(decoded.preamble_position.abs_diff(SHORT_SYNC_SILENCE_SAMPLES), 0);
+
+        // Auto-detected alongside standard frames, with noise and a keyed network
+        for data in [Vec::new(), (0..SHORT_MAX_PAYLOAD_SIZE as u8).collect()] {
+            let mut samples = encoder.encode(&data).unwrap();
+            for (i, sample) in samples.iter_mut().enumerate() {
+                *sample += (i as f32 * 123.456).sin() * 0.02;
             }
+            assert_eq!(decoder.decode(&samples).unwrap(), data);
         }
+        let key = Some(crate::NetworkKey::new(b"short-net"));
+        encoder.set_network_key(key);
+        decoder.set_network_key(key);
+        assert_eq!(decoder.decode(&encoder.encode(b"keyed").unwrap()).unwrap(), b"keyed");
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
-        }
-
-        // Should still decode despite progressive quality loss
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode despite progressive degradation");
+        // Too large for a short frame, and no room for a timestamp
+        assert!(encoder.encode(&[0u8; SHORT_MAX_PAYLOAD_SIZE + 1]).is_err());
+        assert!(encoder.encode_with_timestamp(b"ts", 1).is_err());
     }
 
     #[test]
-    fn test_fountain_integration_isolated_good_blocks_spread() {
-        use crate::fsk::FountainConfig;
-
-        let mut encoder = EncoderFsk::new().unwrap();
-        let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Spread: isolated good blocks widely separated";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
-
-        // Generate many blocks to test with sparse good ones
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(24).collect();
-
-        // Good blocks at indices 1, 6, 11, 16, 21 (widely spread)
-        let good_indices = [1, 6, 11, 16, 21];
-        let mut processed_blocks = blocks.clone();
-        for (i, block) in processed_blocks.iter_mut().enumerate() {
-            if !good_indices.contains(&i) {
-                // Corrupt all others with varying severity
-                let severity = (i % 4) as f32 * 0.2 + 0.1;
-                for j in 0..block.len().min(100 + (i * 2)) {
-                    block[j] = block[j] * severity;
-                }
+    fn test_telephony_and_codec_robust_profiles_roundtrip() {
+        // 2-byte prefix + 15-byte frame + 32 parity: 25 symbols of 16 bits, 34 of 12
+        for (profile, keyed_symbols) in [(crate::FrameProfile::Telephony, 25), (crate::FrameProfile::CodecRobust, 34)] {
+            let mut encoder = EncoderFsk::new().unwrap();
+            encoder.set_profile(profile);
+            let mut decoder = DecoderFsk::new().unwrap();
+            let (sync_samples, _, _) = profile.fixed_format().unwrap();
+
+            // Auto-detected, Full FEC whatever the encoder's setting, keyed networks too
+            encoder.set_fec_mode(Some(FecMode::Light));
+            for data in [Vec::new(), b"dial tone".to_vec(), (0..300).map(|i| (i * 13) as u8).collect()] {
+                let samples = encoder.encode(&data).unwrap();
+                let message = decoder.decode_with_metadata(&samples).unwrap();
+                assert_eq!(message.payload, data, "{:?}", profile);
+                assert_eq!(message.preamble_position, SYNC_SILENCE_SAMPLES);
+                assert_eq!(message.data_start, SYNC_SILENCE_SAMPLES + sync_samples + SYNC_SILENCE_SAMPLES);
             }
-        }
+            let key = Some(crate::NetworkKey::new(b"phone-net"));
+            encoder.set_network_key(key);
+            decoder.set_network_key(key);
+            assert_eq!(decoder.decode(&encoder.encode(b"keyed").unwrap()).unwrap(), b"keyed");
+            assert_eq!(decoder.stats.symbols_demodulated, keyed_symbols, "{:?}", profile);
+            assert_eq!(decoder.stats.postamble_score, None);
 
-        let mut samples = Vec::new();
-        for block in &processed_blocks {
-            samples.extend_from_slice(block);
+            encoder.set_amplitude_bits(true);
+            assert!(encoder.encode(b"no").is_err());
         }
 
-        // Should decode with isolated good blocks spread apart
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with widely separated good blocks");
+        // The wide-spaced layout cannot be announced in a standard frame
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_tone_layout(ToneLayout::CODEC_ROBUST);
+        assert!(encoder.encode(b"no").is_err());
     }
 
     #[test]
-    fn test_fountain_missing_first_blocks() {
-        use crate::fsk::FountainConfig;
+    fn test_narrowband_profile_through_8khz_link() {
+        use crate::resample::ResampleQuality;
+
+        // Band-limited to 3.6 kHz on the way down, as by the link's converters
+        const LINK_RATE: usize = 8000;
+        let link = |samples: &[f32]| {
+            let options = ResampleOptions { anti_alias: true, ..Default::default() };
+            resample_audio_with(samples, SAMPLE_RATE, LINK_RATE, options)
+        };
+        let receive = |link: &[f32]| {
+            let options = ResampleOptions { quality: ResampleQuality::Cubic, ..Default::default() };
+            resample_audio_with(link, LINK_RATE, SAMPLE_RATE, options)
+        };
+        let energy = |samples: &[f32]| samples.iter().map(|s| s * s).sum::<f32>();
 
         let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_profile(FrameProfile::Narrowband);
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Test missing first blocks";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Skip the first 5 blocks and use the rest
-        let mut samples = Vec::new();
-        for block in blocks.iter().skip(5) {
-            samples.extend_from_slice(block);
-        }
-
-        // Should still decode successfully with fountain coding providing repair packets
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode even with first 5 blocks missing");
+        for data in [Vec::new(), b"gate 2: visitor".to_vec(), (0..300).map(|i| (i * 11) as u8).collect()] {
+            let samples = encoder.encode(&data).unwrap();
+            // Nothing near the 4 kHz edge: the link keeps the energy
+            let on_link = link(&samples);
+            assert!(2.0 * energy(&on_link) > 0.9 * energy(&samples), "{} bytes", data.len());
+
+            let message = decoder.decode_with_metadata(&receive(&on_link)).unwrap();
+            assert_eq!(message.payload, data);
+            assert!(message.preamble_position.abs_diff(SYNC_SILENCE_SAMPLES) < 50, "{}", message.preamble_position);
+        }
+
+        let key = Some(crate::NetworkKey::new(b"intercom"));
+        encoder.set_network_key(key);
+        decoder.set_network_key(key);
+        let samples = encoder.encode(b"keyed").unwrap();
+        // The keyed sync signals stay in the band too
+        let on_link = link(&samples);
+        assert!(2.0 * energy(&on_link) > 0.9 * energy(&samples));
+        assert_eq!(decoder.decode(&receive(&on_link)).unwrap(), b"keyed");
+
+        // Half the standard band, and quicker on the air than telephony frames
+        assert_eq!(ToneLayout::NARROWBAND.bandwidth_hz() * 2.0, ToneLayout::STANDARD.bandwidth_hz());
+        let narrowband = encoder.airtime(100, false).unwrap().samples;
+        encoder.set_profile(FrameProfile::Telephony);
+        assert!(narrowband < encoder.airtime(100, false).unwrap().samples);
     }
 
     #[test]
-    fn test_fountain_missing_first_several_blocks() {
-        use crate::fsk::FountainConfig;
+    fn test_amplitude_bits_roundtrip_and_fallback() {
+        use rand::{Rng, SeedableRng};
 
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Missing first several blocks test";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.5, // Extra repair overhead
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(25).collect();
-
-        // Skip the first 6 blocks (about 24% loss at start)
-        let mut samples = Vec::new();
-        for block in blocks.iter().skip(6) {
-            samples.extend_from_slice(block);
-        }
-
-        // Should decode with sufficient repair packets
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with first 6 blocks missing");
+        let payload: Vec<u8> = (0..200).map(|i| (i * 31 + 7) as u8).collect();
+        let plain = encoder.encode(&payload).unwrap();
+        let plain_quality = decoder.decode_with_metadata(&plain).unwrap().quality;
+        assert!(plain_quality.amplitude_bits_viable(), "{:?}", plain_quality);
+        assert_eq!(plain_quality.amplitude_contrast_db, None);
+
+        encoder.set_amplitude_bits(true);
+        let shaped = encoder.encode(&payload).unwrap();
+        assert!(shaped.len() < plain.len());
+        let message = decoder.decode_with_metadata(&shaped).unwrap();
+        assert_eq!(message.payload, payload);
+        let contrast = message.quality.amplitude_contrast_db.unwrap();
+        assert!(contrast > MIN_AMPLITUDE_CONTRAST_DB, "{:?}", message.quality);
+
+        // On a noisy channel the receiver tells the sender to fall back to plain symbols
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1621);
+        let noisy: Vec<f32> = plain.iter().map(|s| s + rng.gen_range(-0.3..0.3)).collect();
+        let noisy_quality = decoder.decode_with_metadata(&noisy).unwrap().quality;
+        assert!(!noisy_quality.amplitude_bits_viable(), "{:?}", noisy_quality);
     }
 
     #[test]
-    fn test_fountain_alternating_first_blocks_missing() {
-        use crate::fsk::FountainConfig;
+    fn test_tone_layout_roundtrip() {
+        use crate::fsk::ToneLayout;
 
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Alternating missing early blocks";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.0,
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
-
-        // Keep only even-indexed blocks in first 10 (skip odd blocks at start)
-        let mut samples = Vec::new();
-        for (i, block) in blocks.iter().enumerate() {
-            if i < 10 {
-                if i % 2 == 0 {
-                    samples.extend_from_slice(block);
-                }
-            } else {
-                // Keep all blocks after index 10
-                samples.extend_from_slice(block);
-            }
-        }
-
-        // Should decode with alternating blocks missing from the beginning
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with alternating early blocks missing");
+        let payload = b"Narrowband channel";
+        let standard = encoder.encode(payload).unwrap();
+
+        for (tones, bins) in [(2, 16), (3, 16), (3, 8), (4, 4)] {
+            let layout = ToneLayout::new(tones, bins).unwrap();
+            encoder.set_tone_layout(layout);
+            let samples = encoder.encode(payload).unwrap();
+            assert!(samples.len() > standard.len(), "{:?}", layout);
+            // No decoder setting: the first symbol announces the layout
+            assert_eq!(decoder.decode(&samples).unwrap(), payload, "{:?}", layout);
+        }
+
+        // Survives mild noise
+        let mut noisy_encoder = EncoderFsk::new().unwrap();
+        noisy_encoder.set_tone_layout(ToneLayout::new(2, 16).unwrap());
+        let mut samples = noisy_encoder.encode(payload).unwrap();
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample += 0.05 * ((i * 7919) % 1000) as f32 / 1000.0 - 0.025;
+        }
+        assert_eq!(decoder.decode(&samples).unwrap(), payload);
+
+        encoder.set_amplitude_bits(true);
+        assert!(matches!(encoder.encode(payload), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_amplitude_bits(false);
+        encoder.set_profile(crate::FrameProfile::Short);
+        assert!(matches!(encoder.encode(payload), Err(AudioModemError::InvalidConfig(_))));
     }
 
     #[test]
-    fn test_fountain_first_half_blocks_missing() {
-        use crate::fsk::FountainConfig;
+    fn test_dual_band_roundtrip() {
+        use crate::fsk::ToneLayout;
+        use crate::FrameProfile;
 
-        let mut encoder = EncoderFsk::new().unwrap();
+        let payload: Vec<u8> = (0..90).map(|i| (i * 29 + 3) as u8).collect();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"First half missing test data";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.5, // Need more redundancy
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(30).collect();
+        let standard = EncoderFsk::new().unwrap().encode(&payload).unwrap().len();
+        for (layout, gaps, fec_mode) in [
+            (ToneLayout::STANDARD, None, None),
+            (ToneLayout::STANDARD, Some(6), Some(FecMode::Light)),
+            (ToneLayout::new(2, 8).unwrap(), None, Some(FecMode::custom(255, 191).unwrap())),
+        ] {
+            let mut encoder = EncoderFsk::new().unwrap();
+            encoder.set_tone_layout(layout);
+            encoder.set_dual_band(true);
+            encoder.set_symbol_gaps(gaps);
+            encoder.set_fec_mode(fec_mode);
+            let samples = encoder.encode(&payload).unwrap();
+            assert!(samples.len() > standard, "{:?}", layout);
+            // No decoder setting: the first symbol announces the dual-band layout
+            assert_eq!(decoder.decode(&samples).unwrap(), payload, "{:?} gaps {:?}", layout, gaps);
+        }
+
+        // Needs the standard profile, no amplitude bits and a layout that fits into half the band
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_dual_band(true);
+        encoder.set_tone_layout(ToneLayout::new(4, 16).unwrap());
+        assert!(matches!(encoder.encode(&payload), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_tone_layout(ToneLayout::STANDARD);
+        encoder.set_amplitude_bits(true);
+        assert!(matches!(encoder.encode(&payload), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_amplitude_bits(false);
+        encoder.set_profile(FrameProfile::Telephony);
+        assert!(matches!(encoder.encode(&payload), Err(AudioModemError::InvalidConfig(_))));
+    }
 
-        // Skip first half (first 15 blocks)
-        let mut samples = Vec::new();
-        for block in blocks.iter().skip(blocks.len() / 2) {
-            samples.extend_from_slice(block);
-        }
+    #[test]
+    fn test_differential_roundtrip() {
+        use crate::fsk::ToneLayout;
+        use crate::FrameProfile;
 
-        // Should decode with 50% of initial blocks missing
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode with first half of blocks missing");
+        let payload: Vec<u8> = (0..90).map(|i| (i * 37 + 5) as u8).collect();
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_symbol_audit(true);
+        for gaps in [None, Some(5)] {
+            let mut encoder = EncoderFsk::new().unwrap();
+            encoder.set_symbol_gaps(gaps);
+            let plain = encoder.encode(&payload).unwrap();
+            encoder.set_differential(true);
+            let samples = encoder.encode(&payload).unwrap();
+            assert_ne!(samples, plain);
+            assert_eq!(samples.len(), encoder.airtime(payload.len(), false).unwrap().samples);
+            // No decoder setting: the first symbol announces the differential symbols
+            assert_eq!(decoder.decode(&samples).unwrap(), payload, "gaps {:?}", gaps);
+            assert_eq!(decoder.symbol_audit().unwrap().symbols.len(), decoder.stats.symbols_demodulated as usize);
+        }
+
+        // Needs the standard profile and tone layout without amplitude bits
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_differential(true);
+        encoder.set_dual_band(true);
+        assert!(matches!(encoder.encode(&payload), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_dual_band(false);
+        encoder.set_tone_layout(ToneLayout::new(3, 8).unwrap());
+        assert!(matches!(encoder.encode(&payload), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_tone_layout(ToneLayout::STANDARD);
+        encoder.set_amplitude_bits(true);
+        assert!(matches!(encoder.encode(&payload), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_amplitude_bits(false);
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(encoder.encode(b"no"), Err(AudioModemError::InvalidConfig(_))));
     }
 
     #[test]
-    fn test_fountain_missing_first_blocks_small_data() {
-        use crate::fsk::FountainConfig;
+    fn test_symbol_gaps_roundtrip() {
+        use crate::fsk::ToneLayout;
+        use crate::{FrameProfile, SYMBOL_GAP_SAMPLES};
+
+        let payload: Vec<u8> = (0..120).map(|i| (i * 53 + 1) as u8).collect();
+        let configs = [
+            (FrameProfile::Standard, false, ToneLayout::STANDARD, 4),
+            (FrameProfile::Standard, true, ToneLayout::STANDARD, 9),
+            (FrameProfile::Standard, false, ToneLayout::new(3, 8).unwrap(), 5),
+            (FrameProfile::Telephony, false, ToneLayout::STANDARD, 6),
+            (FrameProfile::CodecRobust, false, ToneLayout::STANDARD, 63),
+            (FrameProfile::Narrowband, false, ToneLayout::STANDARD, 8),
+        ];
+        for (profile, amplitude_bits, layout, interval) in configs {
+            let mut encoder = EncoderFsk::new().unwrap();
+            encoder.set_profile(profile);
+            encoder.set_amplitude_bits(amplitude_bits);
+            encoder.set_tone_layout(layout);
+            encoder.set_symbol_gaps(Some(interval));
+            let samples = encoder.encode(&payload).unwrap();
+
+            // No decoder setting: the first symbol announces the gaps
+            let mut decoder = DecoderFsk::new().unwrap();
+            let message = decoder.decode_with_metadata(&samples).unwrap();
+            assert_eq!(message.payload, payload, "{:?} interval {}", profile, interval);
+
+            // Silence right after the first `interval` symbols, unless the frame is shorter
+            let symbol_samples = profile.fixed_format().map_or(FSK_SYMBOL_SAMPLES, |(_, symbol_samples, _)| symbol_samples);
+            let gap_start = message.data_start + interval as usize * symbol_samples;
+            assert!(samples[gap_start - 100..gap_start].iter().any(|&s| s.abs() > 0.01));
+            assert!(samples[gap_start..gap_start + SYMBOL_GAP_SAMPLES].iter().all(|&s| s == 0.0));
+
+            // A pumping AGC: every group of symbols arrives at a different level
+            // (amplitude bits need a steady one)
+            if amplitude_bits {
+                continue;
+            }
+            let mut pumped = samples.clone();
+            for (i, chunk) in pumped[message.data_start..].chunks_mut(symbol_samples).enumerate() {
+                let gain = [1.0, 0.4, 1.6, 0.7][(i / interval as usize) % 4];
+                chunk.iter_mut().for_each(|s| *s *= gain);
+            }
+            assert_eq!(decoder.decode(&pumped).unwrap(), payload, "{:?} interval {}", profile, interval);
+        }
 
+        // Trimmed standard frames decode without sync signals too
         let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_symbol_gaps(Some(4));
+        let samples = encoder.encode(b"trimmed").unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = b"Hi";
-
-        let config = FountainConfig {
-            timeout_secs: 20,
-            block_size: 32,
-            repair_blocks_ratio: 2.0, // Very high repair ratio for small data
-        };
-
-        // Generate blocks
-        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(15).collect();
+        let data_start = decoder.decode_with_metadata(&samples).unwrap().data_start;
+        let data_end = samples.len() - SYNC_SILENCE_SAMPLES * 2 - POSTAMBLE_SAMPLES;
+        assert_eq!(decoder.decode_without_preamble_postamble(&samples[data_start..data_end]).unwrap(), b"trimmed");
 
-        // Skip first 3 blocks
-        let mut samples = Vec::new();
-        for block in blocks.iter().skip(3) {
-            samples.extend_from_slice(block);
+        for interval in [3, 64] {
+            encoder.set_symbol_gaps(Some(interval));
+            assert!(matches!(encoder.encode(b"no"), Err(AudioModemError::InvalidConfig(_))));
         }
-
-        // Should decode even with small data and missing initial blocks
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode small data with first 3 blocks missing");
+        encoder.set_symbol_gaps(Some(8));
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(encoder.encode(b"no"), Err(AudioModemError::InvalidConfig(_))));
     }
 
     #[test]
-    fn test_fountain_missing_first_blocks_large_data() {
-        use crate::fsk::FountainConfig;
-
+    fn test_decoder_fsk_repeating_patterns() {
         let mut encoder = EncoderFsk::new().unwrap();
         let mut decoder = DecoderFsk::new().unwrap();
-        let data = vec![99u8; 120]; // Larger data payload (2x small data test)
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: 32,
-            repair_blocks_ratio: 1.5, // Extra repair overhead
-        };
 
-        // Generate blocks
-        let stream = encoder.encode_fountain(&data, Some(config.clone())).unwrap();
-        let blocks: Vec<_> = stream.take(20).collect();
+        // Test with repeating byte patterns
+        let patterns = vec![
+            vec![0x00; 20],      // All zeros
+            vec![0xFF; 20],      // All ones
+            vec![0xAA; 20],      // Alternating bits
+            vec![0x55; 20],      // Alternating bits (inverse)
+        ];
 
-        // Skip first 4 blocks (20% loss at start)
-        let mut samples = Vec::new();
-        for block in blocks.iter().skip(4) {
-            samples.extend_from_slice(block);
+        for data in patterns {
+            let samples = encoder.encode(&data).unwrap();
+            let decoded = decoder.decode(&samples).unwrap();
+            assert_eq!(decoded, data, "Failed for pattern {:02X}", data[0]);
         }
-
-        // Should decode larger data even with missing early blocks
-        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
-        assert_eq!(decoded, data, "Should decode 120-byte data with first 4 blocks missing");
     }
 
     // ============================================================================
@@ -1893,4 +2726,370 @@ mod tests {
             }
         }
     }
+
+    /// FSK data region (no sync signals) of a single-block Light frame; `corrupt`
+    /// edits the frame bytes before and the transmitted bytes (padding
+    /// included) after RS encoding
+    fn light_frame_region(payload: &[u8], corrupt: impl FnOnce(&mut Vec<u8>, &mut Vec<u8>)) -> Vec<f32> {
+        use crate::fec::FecEncoder;
+        use crate::framing::FrameEncoder;
+        use crate::fsk::FskModulator;
+
+        let mode = FecMode::Light;
+        let mut frame_data = FrameEncoder::encode(&Frame::new(payload, 0, mode.to_u8())).unwrap();
+        let mut encoded = (frame_data.len() as u16).to_be_bytes().to_vec();
+        let mut after_fec = Vec::new();
+        corrupt(&mut frame_data, &mut after_fec);
+
+        let mut padded = vec![0u8; mode.data_bytes() - frame_data.len()];
+        padded.extend_from_slice(&frame_data);
+        let fec_chunk = FecEncoder::new().unwrap().encode_with_mode(&padded, mode).unwrap();
+        encoded.extend_from_slice(&fec_chunk[mode.data_bytes() - frame_data.len()..]);
+        encoded.resize(encoded.len().div_ceil(FSK_BYTES_PER_SYMBOL) * FSK_BYTES_PER_SYMBOL, 0);
+        for (byte, flip) in encoded[2..].iter_mut().zip(after_fec) {
+            *byte ^= flip;
+        }
+        FskModulator::new().modulate(&encoded).unwrap()
+    }
+
+    #[test]
+    fn test_crc_mismatch_salvage() {
+        // A frame whose CRC-16 is wrong before RS encoding: RS decodes cleanly
+        // and only the payload CRC fails
+        let samples = light_frame_region(b"hello world", |frame, _| *frame.last_mut().unwrap() ^= 0xFF);
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert!(matches!(
+            decoder.decode_without_preamble_postamble(&samples),
+            Err(AudioModemError::PayloadCrcMismatch)
+        ));
+
+        decoder.set_salvage_unverified(true);
+        match decoder.decode_without_preamble_postamble(&samples) {
+            Err(AudioModemError::CrcMismatch { payload }) => assert_eq!(payload, b"hello world"),
+            other => panic!("expected salvaged payload, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_stats_for_regular_frames() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.decode(&encoder.encode(b"stats please").unwrap()).unwrap();
+        let stats = decoder.stats.clone();
+        assert_eq!((stats.rs_blocks_decoded, stats.rs_blocks_failed, stats.corrected_bytes), (1, 0, 0));
+        assert!(stats.preamble_score.unwrap() > 0.5 && stats.postamble_score.unwrap() > 0.5, "{:?}", stats);
+        // 2-byte length prefix + 22-byte frame + 16 parity bytes (Medium) = 40 bytes in 14 symbols
+        assert_eq!(stats.symbols_demodulated, 14);
+
+        // Two damaged parity bytes are absorbed by the FEC
+        let samples = light_frame_region(b"stats please", |_, flips| {
+            flips.resize(26, 0);
+            (flips[22], flips[25]) = (0x80, 0x01);
+        });
+        decoder.decode_without_preamble_postamble(&samples).unwrap();
+        assert_eq!((decoder.stats.rs_blocks_decoded, decoder.stats.corrected_bytes), (1, 2));
+        assert_eq!(decoder.stats.preamble_score, None);
+
+        // Reset on every call
+        assert!(decoder.decode(&vec![0.0; FSK_SYMBOL_SAMPLES * 4]).is_err());
+        assert_eq!(decoder.stats.rs_blocks_decoded, 0);
+        assert_eq!(decoder.stats.symbols_demodulated, 0);
+    }
+
+    #[test]
+    fn test_strict_padding() {
+        // "stats please" takes 32 bytes with the length prefix, so one padding byte follows
+        let clean = light_frame_region(b"stats please", |_, _| {});
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_strict_padding(true);
+        assert_eq!(decoder.decode_without_preamble_postamble(&clean).unwrap(), b"stats please");
+        assert_eq!((decoder.stats.padding_bytes, decoder.stats.padding_mismatches), (1, 0));
+
+        let dirty = light_frame_region(b"stats please", |_, flips| {
+            flips.resize(31, 0);
+            flips[30] = 0xA5;
+        });
+        assert!(matches!(
+            decoder.decode_without_preamble_postamble(&dirty),
+            Err(AudioModemError::UnexpectedPadding { bytes: 1 })
+        ));
+
+        decoder.set_strict_padding(false);
+        assert_eq!(decoder.decode_without_preamble_postamble(&dirty).unwrap(), b"stats please");
+        assert_eq!((decoder.stats.padding_bytes, decoder.stats.padding_mismatches), (1, 1));
+    }
+
+    #[test]
+    fn test_frame_inspection() {
+        let samples = light_frame_region(b"stats please", |_, flips| {
+            flips.resize(26, 0);
+            (flips[22], flips[25]) = (0x80, 0x01);
+        });
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.decode_without_preamble_postamble(&samples).unwrap();
+        assert!(decoder.frame_inspection().is_none());
+
+        decoder.set_frame_inspection(true);
+        decoder.decode_without_preamble_postamble(&samples).unwrap();
+        let inspection = decoder.frame_inspection().unwrap();
+        assert_eq!(inspection.prefix, [0, 22]);
+        assert_eq!(inspection.fec_mode, Some(FecMode::Light));
+        let block = RsBlockReport { offset: 2, data_bytes: 22, parity_bytes: 8, corrected_bytes: Some(2) };
+        assert_eq!(inspection.blocks, [block]);
+        assert!(inspection.blocks_complete());
+        assert_eq!(inspection.frame.len(), 22);
+        assert_eq!((inspection.padding.as_slice(), inspection.padding_mismatches()), (&[0u8][..], 0));
+    }
+
+    /// Frame from older firmware: the RS blocks of `payload` in `mode` between
+    /// the sync signals, without the length prefix
+    fn legacy_frame(payload: &[u8], mode: FecMode) -> Vec<f32> {
+        use crate::fec::FecEncoder;
+        use crate::framing::FrameEncoder;
+        use crate::fsk::FskModulator;
+        use crate::sync::{generate_keyed_postamble, generate_keyed_preamble};
+
+        let frame_data = FrameEncoder::encode(&Frame::new(payload, 0, mode.to_u8())).unwrap();
+        let fec = FecEncoder::new().unwrap();
+        let mut encoded = Vec::new();
+        for chunk in frame_data.chunks(mode.data_bytes()) {
+            let mut padded = vec![0u8; mode.data_bytes() - chunk.len()];
+            padded.extend_from_slice(chunk);
+            encoded.extend_from_slice(&fec.encode_with_mode(&padded, mode).unwrap()[mode.data_bytes() - chunk.len()..]);
+        }
+        encoded.resize(encoded.len().next_multiple_of(FSK_BYTES_PER_SYMBOL), 0);
+
+        let mut samples = vec![0.0; SYNC_SILENCE_SAMPLES];
+        samples.extend(generate_keyed_preamble(Samples(PREAMBLE_SAMPLES), 0.5, None));
+        samples.extend(vec![0.0; SYNC_SILENCE_SAMPLES]);
+        samples.extend(FskModulator::new().modulate(&encoded).unwrap());
+        samples.extend(vec![0.0; SYNC_SILENCE_SAMPLES]);
+        samples.extend(generate_keyed_postamble(Samples(POSTAMBLE_SAMPLES), 0.5, None));
+        samples.extend(vec![0.0; SYNC_SILENCE_SAMPLES]);
+        samples
+    }
+
+    #[test]
+    fn test_legacy_framing_fallback() {
+        let short = legacy_frame(b"old firmware", FecMode::Medium);
+        let long_payload: Vec<u8> = (0..100u8).map(|i| i.wrapping_mul(37)).collect();
+        let long = legacy_frame(&long_payload, FecMode::Full);
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert_eq!(decoder.framing(), FramingVariant::LengthPrefixed);
+        assert!(decoder.decode(&short).is_err());
+
+        let mut legacy = DecoderFsk::with_framing(FramingVariant::LegacyFallback).unwrap();
+        assert_eq!(legacy.decode(&short).unwrap(), b"old firmware");
+        assert_eq!(legacy.decode(&long).unwrap(), long_payload);
+
+        // Current frames still decode from their length prefix
+        let mut encoder = EncoderFsk::new().unwrap();
+        assert_eq!(legacy.decode(&encoder.encode(b"new firmware").unwrap()).unwrap(), b"new firmware");
+    }
+
+    #[test]
+    fn test_decode_ends_frame_at_announced_length() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1640);
+        let mut noise = |len: usize| -> Vec<f32> { (0..len).map(|_| rng.gen_range(-0.01..0.01)).collect() };
+
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        for fec_mode in [None, Some(FecMode::custom(255, 191).unwrap())] {
+            encoder.set_fec_mode(fec_mode);
+            let frame = encoder.encode(b"stats please").unwrap();
+            let symbols = encoder.airtime(b"stats please".len(), false).unwrap().symbols;
+
+            // Long recording: the postamble is verified right after the announced
+            // symbols, and only those are demodulated, not the noise after them
+            let mut recording = frame.clone();
+            recording.extend(noise(SAMPLE_RATE * 20));
+            let message = decoder.decode_with_metadata(&recording).unwrap();
+            assert_eq!(message.payload, b"stats please");
+            assert_eq!(message.data_end, message.data_start + symbols * FSK_SYMBOL_SAMPLES);
+            assert_eq!(decoder.stats.symbols_demodulated as usize, symbols, "{:?}", fec_mode);
+            assert!(decoder.stats.postamble_score.unwrap() > 0.5, "{:?}", decoder.stats);
+
+            // Postamble cut off and replaced by noise: the length prefix alone ends the frame
+            let mut clipped = frame[..message.data_end].to_vec();
+            clipped.extend(noise(SAMPLE_RATE * 20));
+            assert_eq!(decoder.decode(&clipped).unwrap(), b"stats please");
+            assert_eq!(decoder.stats.postamble_score, None);
+        }
+    }
+
+    #[test]
+    fn test_decode_recording_clipped_at_both_ends() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let payload = b"recorded a moment too late";
+        let frame = encoder.encode(payload).unwrap();
+        let preamble_start = SYNC_SILENCE_SAMPLES;
+        let data_end = frame.len() - SYNC_SILENCE_SAMPLES * 2 - POSTAMBLE_SAMPLES;
+
+        // Recording starts three quarters into the preamble
+        let late = &frame[preamble_start + PREAMBLE_SAMPLES * 3 / 4..];
+        let message = decoder.decode_with_metadata(late).unwrap();
+        assert_eq!(message.payload, payload);
+        assert_eq!(message.preamble_position, 0);
+        assert!(message.data_start.abs_diff(PREAMBLE_SAMPLES / 4 + SYNC_SILENCE_SAMPLES) <= 8, "{}", message.data_start);
+
+        // ... and stops mid-postamble, or inside the last data symbol
+        let late_and_early = &late[..late.len() - SYNC_SILENCE_SAMPLES - POSTAMBLE_SAMPLES / 2];
+        assert_eq!(decoder.decode(late_and_early).unwrap(), payload);
+        let end = data_end - FSK_SYMBOL_SAMPLES / 3 - (preamble_start + PREAMBLE_SAMPLES * 3 / 4);
+        assert_eq!(decoder.decode(&late[..end]).unwrap(), payload);
+
+        // Too little of the last symbol is left to demodulate
+        let end = data_end - FSK_SYMBOL_SAMPLES * 3 / 4;
+        assert!(decoder.decode(&frame[..end]).is_err());
+    }
+
+    #[test]
+    fn test_decode_other_sync_gaps() {
+        use crate::encoder_fsk::SyncGaps;
+
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        for gaps in [
+            SyncGaps::Fixed(0),
+            SyncGaps::Fixed(MAX_SYNC_GAP_SAMPLES),
+            SyncGaps::Random { min: 0, max: MAX_SYNC_GAP_SAMPLES, seed: 1659 },
+        ] {
+            encoder.set_sync_gaps(gaps);
+            for payload in [&b"no telltale silence"[..], &[0x5A; 200]] {
+                for _ in 0..3 {
+                    let frame = encoder.encode(payload).unwrap();
+                    let message = decoder.decode_with_metadata(&frame).unwrap();
+                    assert_eq!(message.payload, payload, "{:?}", gaps);
+                    assert_eq!(message.preamble_position, SYNC_SILENCE_SAMPLES);
+                }
+            }
+        }
+
+        // No gap at all: the data starts right after the preamble
+        encoder.set_sync_gaps(SyncGaps::Fixed(0));
+        let message = decoder.decode_with_metadata(&encoder.encode(b"tight").unwrap()).unwrap();
+        assert!(message.data_start.abs_diff(SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES) < FSK_SYMBOL_SAMPLES / 8);
+
+        // Recordings that start three quarters into the preamble
+        let late = SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES * 3 / 4;
+        for gap in [0, MAX_SYNC_GAP_SAMPLES] {
+            encoder.set_sync_gaps(SyncGaps::Fixed(gap));
+            let frame = encoder.encode(b"clipped, other gap").unwrap();
+            let message = decoder.decode_with_metadata(&frame[late..]).unwrap();
+            assert_eq!(message.payload, b"clipped, other gap", "{}", gap);
+            assert_eq!(message.preamble_position, 0);
+            let expected = PREAMBLE_SAMPLES / 4 + gap;
+            assert!(message.data_start.abs_diff(expected) < FSK_SYMBOL_SAMPLES / 8, "{} vs {}", message.data_start, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_with_playback_speed_change() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let payload = b"voice note played at a different speed";
+        let frame = encoder.encode(payload).unwrap();
+
+        for permille in [950, 1050, 1025] {
+            // Played `permille / 1000` times as fast: shorter, with higher tones
+            let played = resample_audio(&frame, SAMPLE_RATE * permille / 1000, SAMPLE_RATE);
+            decoder.set_speed_search(false);
+            assert!(decoder.decode(&played).is_err(), "{}", permille);
+
+            decoder.set_speed_search(true);
+            let message = decoder.decode_with_metadata(&played).unwrap();
+            assert_eq!(message.payload, payload, "{}", permille);
+            let estimate = decoder.stats.playback_speed_permille.unwrap();
+            assert!(estimate.abs_diff(permille as u32) <= 3, "{} vs {}", permille, estimate);
+            let expected = SYNC_SILENCE_SAMPLES * 1000 / permille;
+            assert!(message.preamble_position.abs_diff(expected) <= 40, "{}", message.preamble_position);
+        }
+
+        // Normal speed needs no search
+        assert_eq!(decoder.decode(&frame).unwrap(), payload);
+        assert_eq!(decoder.stats.playback_speed_permille, None);
+    }
+
+    #[test]
+    fn test_decode_autorate() {
+        let payload = b"raw capture buffer";
+        let frame = EncoderFsk::new().unwrap().encode(payload).unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let options = ResampleOptions { anti_alias: true, ..Default::default() };
+
+        // (actual rate, rate the caller claims)
+        for (rate, claimed) in [(48_000, 48_000), (44_100, 48_000), (48_000, 16_000), (16_000, 44_100)] {
+            let capture = resample_audio_with(&frame, SAMPLE_RATE, rate, options);
+            let message = decoder.decode_autorate(&capture, claimed).unwrap();
+            assert_eq!(message.payload, payload, "{} claimed as {}", rate, claimed);
+            assert_eq!(decoder.stats.source_rate, Some(rate));
+            let expected = SYNC_SILENCE_SAMPLES * rate / SAMPLE_RATE;
+            assert!(message.preamble_position.abs_diff(expected) <= rate / 400, "{}", message.preamble_position);
+        }
+
+        assert!(decoder.decode_autorate(&frame, 0).is_err());
+        decoder.decode(&frame).unwrap();
+        assert_eq!(decoder.stats.source_rate, None);
+    }
+
+    #[test]
+    fn test_unreliable_bins_recover_hummed_band() {
+        let hummed = |mut samples: Vec<f32>| {
+            for (i, sample) in samples.iter_mut().enumerate() {
+                *sample += 0.3 * (2.0 * std::f32::consts::PI * 1500.0 * i as f32 / SAMPLE_RATE as f32).sin();
+            }
+            samples
+        };
+        let payload = b"machinery hum";
+        let mut encoder = EncoderFsk::new().unwrap();
+        let samples = hummed(encoder.encode(payload).unwrap());
+        let data_start = 2 * SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES;
+
+        // The hum wins its band in every symbol
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert!(decoder.decode_at(&samples, data_start, None).is_err());
+
+        let hum = crate::fsk::freq_to_bin(1500.0).unwrap();
+        decoder.set_unreliable_bins(&[hum]).unwrap();
+        assert_eq!(decoder.unreliable_bins(), [hum]);
+        assert_eq!(decoder.decode_at(&samples, data_start, None).unwrap().payload, payload);
+        assert!(decoder.set_unreliable_bins(&[96]).is_err());
+
+        // Amplitude-shaped and legacy frames get the erasures too
+        encoder.set_amplitude_bits(true);
+        let samples = hummed(encoder.encode(payload).unwrap());
+        assert_eq!(decoder.decode_at(&samples, data_start, None).unwrap().payload, payload);
+        let samples = hummed(legacy_frame(payload, FecMode::Light));
+        let mut legacy = DecoderFsk::with_framing(FramingVariant::LegacyFallback).unwrap();
+        legacy.set_unreliable_bins(&[hum]).unwrap();
+        assert_eq!(legacy.decode_at(&samples, data_start, None).unwrap().payload, payload);
+    }
+
+    #[test]
+    fn test_decode_at_known_offset() {
+        let payload = b"triggered recording";
+        let frame = EncoderFsk::new().unwrap().encode(payload).unwrap();
+        let data_start = 2 * SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES;
+        let mut decoder = DecoderFsk::new().unwrap();
+
+        // The preamble may be missing entirely; the audio may run on past the frame
+        let mut samples = vec![0.0; 1000];
+        samples.extend_from_slice(&frame[data_start..]);
+        samples.extend(vec![0.0; SAMPLE_RATE]);
+        let message = decoder.decode_at(&samples, 1000, None).unwrap();
+        assert_eq!(message.payload, payload);
+        assert_eq!(message.data_start, 1000);
+
+        let message = decoder.decode_at(&frame, data_start, Some(payload.len())).unwrap();
+        assert_eq!(message.payload, payload);
+        assert_eq!(message.preamble_position, SYNC_SILENCE_SAMPLES);
+
+        assert!(matches!(decoder.decode_at(&frame, data_start, Some(3)), Err(AudioModemError::InvalidFrameSize)));
+        assert!(matches!(decoder.decode_at(&frame, frame.len(), None), Err(AudioModemError::InsufficientData)));
+        assert!(decoder.decode_at(&frame, 0, None).is_err());
+    }
 }
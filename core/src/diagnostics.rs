@@ -0,0 +1,270 @@
+//! Capture-path diagnostics: spot OS/browser voice processing in a recording
+//!
+//! Echo cancellation and noise suppression treat steady tones as noise. They
+//! either notch whole parts of the FSK band or gate the signal on and off, and
+//! the user only sees a failed decode. Comparing a recording of a known test
+//! transmission ([`capture_test_signal`]) with the samples that were played
+//! tells the two apart and names the setting to change.
+
+use crate::encoder_fsk::EncoderFsk;
+use crate::error::Result;
+use crate::fsk::{FskDemodulator, FSK_NIBBLES_PER_SYMBOL, FSK_NUM_BINS, FSK_SYMBOL_SAMPLES};
+use crate::sync::{detect_preamble, DetectionThreshold};
+use crate::trim::{find_transmission, TrimOptions};
+use std::fmt;
+
+/// Payload of the test transmission played during a capture check
+pub const CAPTURE_TEST_PAYLOAD: &[u8] = b"transmitwave capture test";
+
+/// A tone band this much weaker than the median band counts as suppressed
+pub const BAND_SUPPRESSION_DB: f32 = 12.0;
+
+/// Window for the level envelope (20 ms)
+const GATE_WINDOW_SAMPLES: usize = 320;
+
+/// A window this much below the typical received/sent level counts as gated
+const GATE_DROP_DB: f32 = 20.0;
+
+/// Reference windows quieter than this fraction of the loudest one are ignored
+const GATE_ACTIVE_RATIO: f32 = 0.1;
+
+/// Share of gated windows above which gating is reported
+const GATE_REPORT_FRACTION: f32 = 0.05;
+
+/// Floor for level ratios, so digital silence stays finite in dB
+pub(crate) const LEVEL_EPSILON: f32 = 1e-9;
+
+/// The test transmission to play while recording (standard FSK frame of
+/// [`CAPTURE_TEST_PAYLOAD`])
+pub fn capture_test_signal() -> Result<Vec<f32>> {
+    EncoderFsk::new()?.encode(CAPTURE_TEST_PAYLOAD)
+}
+
+/// Signature found in a capture, with the fix to suggest
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureHint {
+    /// No preamble in the recording
+    SignalNotFound,
+    /// Tone bands (0 = lowest) arriving at least [`BAND_SUPPRESSION_DB`] weaker than the rest
+    BandsSuppressed { bands: Vec<usize> },
+    /// Share of the transmission during which the signal dropped out
+    Gated { fraction: f32 },
+}
+
+impl fmt::Display for CaptureHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureHint::SignalNotFound => {
+                write!(f, "No test transmission found: check the selected microphone and the playback volume")
+            }
+            CaptureHint::BandsSuppressed { bands } => write!(
+                f,
+                "Tone bands {:?} were filtered out: disable the echoCancellation and noiseSuppression \
+                 constraints (or the OS voice processing / call mode)",
+                bands
+            ),
+            CaptureHint::Gated { fraction } => write!(
+                f,
+                "The signal dropped out for {:.0}% of the transmission: a noise gate is active, disable the \
+                 noiseSuppression and autoGainControl constraints",
+                fraction * 100.0
+            ),
+        }
+    }
+}
+
+/// Result of [`analyze_capture`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureDiagnostics {
+    /// Preamble position in the recording
+    pub preamble: Option<usize>,
+    /// Received energy of each tone band relative to what was sent, in dB,
+    /// normalized so the median band is 0 (overall gain does not matter)
+    pub band_level_db: [f32; FSK_NIBBLES_PER_SYMBOL],
+    /// Share of the transmission during which the recording fell silent
+    pub gated_fraction: f32,
+    /// Empty when the capture path looks clean
+    pub hints: Vec<CaptureHint>,
+}
+
+impl CaptureDiagnostics {
+    fn not_found() -> Self {
+        Self {
+            preamble: None,
+            band_level_db: [0.0; FSK_NIBBLES_PER_SYMBOL],
+            gated_fraction: 0.0,
+            hints: vec![CaptureHint::SignalNotFound],
+        }
+    }
+
+    /// Whether no suppression was detected
+    pub fn is_clean(&self) -> bool {
+        self.hints.is_empty()
+    }
+}
+
+/// Compare `recording` with the `reference` samples that were played
+///
+/// The reference is usually [`capture_test_signal`]. The recording may hold
+/// silence before and after the transmission, and any overall gain.
+pub fn analyze_capture(recording: &[f32], reference: &[f32]) -> CaptureDiagnostics {
+    let Some(reference_preamble) = detect_preamble(reference, DetectionThreshold::Adaptive) else {
+        return CaptureDiagnostics::not_found();
+    };
+    let Some(span) = find_transmission(recording, &TrimOptions::default()) else {
+        return CaptureDiagnostics::not_found();
+    };
+
+    let len = (reference.len() - reference_preamble).min(recording.len() - span.preamble);
+    let sent = &reference[reference_preamble..reference_preamble + len];
+    let received = &recording[span.preamble..span.preamble + len];
+
+    let band_level_db = band_levels(sent, received);
+    let gated_fraction = gated_fraction(sent, received);
+
+    let mut hints = Vec::new();
+    let bands: Vec<usize> = (0..FSK_NIBBLES_PER_SYMBOL)
+        .filter(|&band| band_level_db[band] <= -BAND_SUPPRESSION_DB)
+        .collect();
+    if !bands.is_empty() {
+        hints.push(CaptureHint::BandsSuppressed { bands });
+    }
+    if gated_fraction > GATE_REPORT_FRACTION {
+        hints.push(CaptureHint::Gated { fraction: gated_fraction });
+    }
+
+    CaptureDiagnostics { preamble: Some(span.preamble), band_level_db, gated_fraction, hints }
+}
+
+/// Received/sent energy per tone band in dB, relative to the median band
+fn band_levels(sent: &[f32], received: &[f32]) -> [f32; FSK_NIBBLES_PER_SYMBOL] {
+    let demodulator = FskDemodulator::new();
+    let bins_per_band = FSK_NUM_BINS / FSK_NIBBLES_PER_SYMBOL;
+    let mut sent_energy = [0.0f32; FSK_NIBBLES_PER_SYMBOL];
+    let mut received_energy = [0.0f32; FSK_NIBBLES_PER_SYMBOL];
+    for (sent, received) in sent.chunks_exact(FSK_SYMBOL_SAMPLES).zip(received.chunks_exact(FSK_SYMBOL_SAMPLES)) {
+        let sent_powers = demodulator.bin_powers(sent);
+        let received_powers = demodulator.bin_powers(received);
+        for bin in 0..FSK_NUM_BINS {
+            sent_energy[bin / bins_per_band] += sent_powers[bin];
+            received_energy[bin / bins_per_band] += received_powers[bin];
+        }
+    }
+
+    let mut levels = [0.0f32; FSK_NIBBLES_PER_SYMBOL];
+    for band in 0..FSK_NIBBLES_PER_SYMBOL {
+        levels[band] = 10.0 * ((received_energy[band] + LEVEL_EPSILON) / (sent_energy[band] + LEVEL_EPSILON)).log10();
+    }
+    let median = median(&levels);
+    levels.map(|level| level - median)
+}
+
+/// Share of active reference windows where the recording is far below its typical level
+fn gated_fraction(sent: &[f32], received: &[f32]) -> f32 {
+    let rms = |window: &[f32]| (window.iter().map(|s| s * s).sum::<f32>() / window.len() as f32).sqrt();
+    let windows: Vec<(f32, f32)> = sent
+        .chunks_exact(GATE_WINDOW_SAMPLES)
+        .zip(received.chunks_exact(GATE_WINDOW_SAMPLES))
+        .map(|(sent, received)| (rms(sent), rms(received)))
+        .collect();
+
+    let loudest = windows.iter().map(|&(sent, _)| sent).fold(0.0f32, f32::max);
+    let ratios_db: Vec<f32> = windows
+        .iter()
+        .filter(|&&(sent, _)| sent > loudest * GATE_ACTIVE_RATIO)
+        .map(|&(sent, received)| 20.0 * ((received + LEVEL_EPSILON) / sent).log10())
+        .collect();
+    if ratios_db.is_empty() {
+        return 0.0;
+    }
+
+    let typical = median(&ratios_db);
+    let gated = ratios_db.iter().filter(|&&ratio| ratio < typical - GATE_DROP_DB).count();
+    gated as f32 / ratios_db.len() as f32
+}
+
+pub(crate) fn median(values: &[f32]) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted[sorted.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsk::bin_to_freq;
+    use crate::SAMPLE_RATE;
+    use std::f32::consts::PI;
+
+    /// Recording of `reference` with a delay, a gain and a little noise
+    fn recorded(reference: &[f32], gain: f32) -> Vec<f32> {
+        let mut recording = vec![0.0f32; 1000];
+        recording.extend(reference.iter().enumerate().map(|(i, s)| gain * s + 0.001 * (i as f32 * 0.37).sin()));
+        recording.extend(vec![0.0f32; 2000]);
+        recording
+    }
+
+    /// Cascade of notch filters on the tones of `band`
+    fn notch_band(samples: &mut [f32], band: usize) {
+        for bin in band * 16..(band + 1) * 16 {
+            let omega = 2.0 * PI * bin_to_freq(bin) / SAMPLE_RATE as f32;
+            let alpha = omega.sin() / (2.0 * 30.0);
+            let a0 = 1.0 + alpha;
+            let (b0, b1, b2) = (1.0 / a0, -2.0 * omega.cos() / a0, 1.0 / a0);
+            let (a1, a2) = (-2.0 * omega.cos() / a0, (1.0 - alpha) / a0);
+            let (mut x1, mut x2, mut y1, mut y2) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+            for sample in samples.iter_mut() {
+                let x0 = *sample;
+                let y0 = b0 * x0 + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+                (x2, x1, y2, y1) = (x1, x0, y1, y0);
+                *sample = y0;
+            }
+        }
+    }
+
+    #[test]
+    fn test_clean_capture_has_no_hints() {
+        let reference = capture_test_signal().unwrap();
+        let diagnostics = analyze_capture(&recorded(&reference, 0.3), &reference);
+        assert_eq!(diagnostics.preamble, Some(1000 + detect_preamble(&reference, DetectionThreshold::Adaptive).unwrap()));
+        assert!(diagnostics.is_clean(), "{:?}", diagnostics);
+        assert!(diagnostics.band_level_db.iter().all(|level| level.abs() < 1.0));
+    }
+
+    #[test]
+    fn test_detects_suppressed_band() {
+        let reference = capture_test_signal().unwrap();
+        let mut recording = recorded(&reference, 0.5);
+        notch_band(&mut recording, 4);
+
+        let diagnostics = analyze_capture(&recording, &reference);
+        assert_eq!(diagnostics.hints, vec![CaptureHint::BandsSuppressed { bands: vec![4] }]);
+        assert!(diagnostics.hints[0].to_string().contains("echoCancellation"));
+    }
+
+    #[test]
+    fn test_detects_gating() {
+        let reference = capture_test_signal().unwrap();
+        let mut recording = recorded(&reference, 0.5);
+        // Mute every third 100 ms slice after the preamble
+        let data_start = 1000 + detect_preamble(&reference, DetectionThreshold::Adaptive).unwrap() + 6000;
+        for (i, slice) in recording[data_start..].chunks_mut(1600).enumerate() {
+            if i % 3 == 0 {
+                slice.fill(0.0);
+            }
+        }
+
+        let diagnostics = analyze_capture(&recording, &reference);
+        assert!(diagnostics.gated_fraction > 0.2, "{:?}", diagnostics);
+        assert!(matches!(diagnostics.hints.as_slice(), [CaptureHint::Gated { .. }]), "{:?}", diagnostics.hints);
+    }
+
+    #[test]
+    fn test_missing_signal() {
+        let reference = capture_test_signal().unwrap();
+        let noise: Vec<f32> = (0..reference.len()).map(|i| 0.01 * ((i * 7919) % 101) as f32 / 101.0).collect();
+        let diagnostics = analyze_capture(&noise, &reference);
+        assert_eq!(diagnostics.hints, vec![CaptureHint::SignalNotFound]);
+        assert!(diagnostics.preamble.is_none());
+    }
+}
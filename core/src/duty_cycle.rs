@@ -0,0 +1,295 @@
+//! Duty-cycled listening for battery-powered receivers
+//!
+//! A transmitter with a preamble cadence (see
+//! [`EncoderFsk::set_preamble_cadence`]) repeats its frame at a fixed
+//! interval. A receiver that listens for one cadence plus one preamble is
+//! certain to hear a whole preamble, so it can sleep the rest of the time:
+//! [`DutyCycledDecoder`] wakes for that window, stays awake while a frame
+//! arrives, and powers down again once the frame completes or nothing was
+//! found.
+
+use crate::decoder_fsk::DecodedMessage;
+use crate::encoder_fsk::{EncoderFsk, FrameProfile};
+use crate::error::{AudioModemError, Result};
+use crate::streaming::StreamingDecoder;
+use crate::units::{self, Millis};
+use crate::PREAMBLE_SAMPLES;
+
+/// Listening window and sleep time of a duty-cycled receiver, in samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DutyCycle {
+    listen_samples: usize,
+    sleep_samples: usize,
+}
+
+impl DutyCycle {
+    /// Listen long enough to catch a preamble repeated every `cadence`, then
+    /// sleep for `sleep`
+    pub fn new(cadence: Millis, sleep: Millis) -> Self {
+        Self {
+            listen_samples: units::MODEM_RATE.samples(cadence).get() + PREAMBLE_SAMPLES,
+            sleep_samples: units::MODEM_RATE.samples(sleep).get(),
+        }
+    }
+
+    /// [`DutyCycle::new`] with the preamble cadence of `encoder`
+    ///
+    /// Fails with `InvalidConfig` if the encoder has no cadence or does not
+    /// send standard frames (the only ones `StreamingDecoder` follows).
+    pub fn for_encoder(encoder: &EncoderFsk, sleep: Millis) -> Result<Self> {
+        let cadence = encoder
+            .preamble_cadence()
+            .ok_or_else(|| AudioModemError::InvalidConfig("the encoder has no preamble cadence".to_string()))?;
+        if encoder.profile() != FrameProfile::Standard {
+            return Err(AudioModemError::InvalidConfig(
+                "duty-cycled listening needs the standard profile".to_string(),
+            ));
+        }
+        Ok(Self::new(cadence, sleep))
+    }
+
+    /// Length of each listening window
+    pub fn listen_samples(&self) -> usize {
+        self.listen_samples
+    }
+
+    pub fn sleep_samples(&self) -> usize {
+        self.sleep_samples
+    }
+
+    /// Share of the time spent listening while the channel is quiet
+    pub fn awake_fraction(&self) -> f32 {
+        self.listen_samples as f32 / (self.listen_samples + self.sleep_samples) as f32
+    }
+}
+
+/// What a [`DutyCycledDecoder`] is doing with the audio it is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListenState {
+    /// Inside a listening window, looking for a preamble
+    Listening,
+    /// A preamble was found; awake until its frame completes
+    Receiving,
+    /// Audio is discarded; the microphone may be off
+    Sleeping,
+}
+
+/// Wake-up and detection counts of a [`DutyCycledDecoder`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DutyCycleStats {
+    /// Listening windows opened
+    pub wakes: u64,
+    /// Wake-ups that found a preamble
+    pub detections: u64,
+    /// Frames decoded
+    pub frames: u64,
+    pub listened_samples: u64,
+    pub slept_samples: u64,
+}
+
+impl DutyCycleStats {
+    /// Fraction of wake-ups that found a preamble
+    pub fn detect_ratio(&self) -> f32 {
+        if self.wakes == 0 {
+            return 0.0;
+        }
+        self.detections as f32 / self.wakes as f32
+    }
+
+    /// Fraction of the audio the receiver was awake for
+    pub fn awake_fraction(&self) -> f32 {
+        let total = self.listened_samples + self.slept_samples;
+        if total == 0 {
+            return 0.0;
+        }
+        self.listened_samples as f32 / total as f32
+    }
+}
+
+/// [`StreamingDecoder`] that only listens during the windows of a [`DutyCycle`]
+///
+/// Audio is still pushed continuously, so stream positions stay accurate;
+/// while [`state`](Self::state) is `Sleeping` it is dropped unread, and
+/// [`samples_until_wake`](Self::samples_until_wake) tells the caller how long
+/// the microphone may stay off (pushing silence for that time is fine).
+pub struct DutyCycledDecoder {
+    decoder: StreamingDecoder,
+    cycle: DutyCycle,
+    state: ListenState,
+    /// Samples left in the current listening window or sleep
+    remaining: usize,
+    detected_this_wake: bool,
+    stats: DutyCycleStats,
+}
+
+impl DutyCycledDecoder {
+    /// Start in a listening window
+    pub fn new(cycle: DutyCycle) -> Result<Self> {
+        let mut decoder = Self {
+            decoder: StreamingDecoder::new()?,
+            cycle,
+            state: ListenState::Sleeping,
+            remaining: 0,
+            detected_this_wake: false,
+            stats: DutyCycleStats::default(),
+        };
+        decoder.wake();
+        Ok(decoder)
+    }
+
+    pub fn cycle(&self) -> DutyCycle {
+        self.cycle
+    }
+
+    pub fn state(&self) -> ListenState {
+        self.state
+    }
+
+    /// Samples until the next listening window opens (0 while awake)
+    pub fn samples_until_wake(&self) -> usize {
+        match self.state {
+            ListenState::Sleeping => self.remaining,
+            _ => 0,
+        }
+    }
+
+    pub fn stats(&self) -> DutyCycleStats {
+        self.stats
+    }
+
+    /// Access the underlying streaming decoder, e.g. to set the network key
+    pub fn decoder_mut(&mut self) -> &mut StreamingDecoder {
+        &mut self.decoder
+    }
+
+    /// Feed audio and return the frames completed by it
+    pub fn push(&mut self, samples: &[f32]) -> Vec<DecodedMessage> {
+        let mut messages = Vec::new();
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let count = match self.state {
+                ListenState::Sleeping | ListenState::Listening => self.remaining.min(rest.len()),
+                ListenState::Receiving => rest.len(),
+            };
+            let (chunk, tail) = rest.split_at(count);
+            rest = tail;
+
+            if self.state == ListenState::Sleeping {
+                self.decoder.skip(count);
+                self.stats.slept_samples += count as u64;
+                self.remaining -= count;
+                if self.remaining == 0 {
+                    self.wake();
+                }
+                continue;
+            }
+
+            let decoded = self.decoder.push(chunk);
+            self.stats.listened_samples += count as u64;
+            self.stats.frames += decoded.len() as u64;
+            let frame_done = !decoded.is_empty() || self.state == ListenState::Receiving;
+            messages.extend(decoded);
+            if self.state == ListenState::Listening {
+                self.remaining -= count;
+            }
+
+            if self.decoder.frame_pending() {
+                if !self.detected_this_wake {
+                    self.detected_this_wake = true;
+                    self.stats.detections += 1;
+                }
+                self.state = ListenState::Receiving;
+            } else if frame_done {
+                if !self.detected_this_wake {
+                    self.detected_this_wake = true;
+                    self.stats.detections += 1;
+                }
+                self.sleep();
+            } else if self.remaining == 0 {
+                self.sleep();
+            }
+        }
+        messages
+    }
+
+    fn wake(&mut self) {
+        self.state = ListenState::Listening;
+        self.remaining = self.cycle.listen_samples;
+        self.detected_this_wake = false;
+        self.stats.wakes += 1;
+    }
+
+    fn sleep(&mut self) {
+        // Whatever is buffered will not be followed by contiguous audio
+        self.decoder.reset();
+        if self.cycle.sleep_samples == 0 {
+            self.wake();
+        } else {
+            self.state = ListenState::Sleeping;
+            self.remaining = self.cycle.sleep_samples;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SAMPLE_RATE;
+
+    #[test]
+    fn test_duty_cycle_catches_repeated_frame() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_preamble_cadence(Some(Millis(4000)));
+        let clip = encoder.encode(b"ping").unwrap();
+        assert_eq!(clip.len(), 4 * SAMPLE_RATE);
+
+        let cycle = DutyCycle::for_encoder(&encoder, Millis(8000)).unwrap();
+        assert_eq!(cycle.listen_samples(), clip.len() + PREAMBLE_SAMPLES);
+        assert!(cycle.awake_fraction() < 0.4);
+
+        let audio: Vec<f32> = clip.iter().copied().cycle().take(8 * clip.len()).collect();
+        let mut decoder = DutyCycledDecoder::new(cycle).unwrap();
+        let mut messages = Vec::new();
+        for chunk in audio.chunks(1600) {
+            messages.extend(decoder.push(chunk));
+        }
+
+        // Every wake-up finds the beacon and sleeps right after decoding it
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().all(|m| m.payload == b"ping"));
+        let offset = messages[0].preamble_position % clip.len();
+        for message in &messages[1..] {
+            assert!((message.preamble_position % clip.len()).abs_diff(offset) < 100);
+        }
+        let stats = decoder.stats();
+        assert_eq!((stats.wakes, stats.detections, stats.frames), (3, 3, 3));
+        assert_eq!(stats.detect_ratio(), 1.0);
+        assert_eq!(stats.listened_samples + stats.slept_samples, audio.len() as u64);
+        assert!(stats.awake_fraction() < 0.4);
+    }
+
+    #[test]
+    fn test_duty_cycle_on_quiet_channel() {
+        let cycle = DutyCycle::new(Millis(1000), Millis(3000));
+        let mut decoder = DutyCycledDecoder::new(cycle).unwrap();
+        let period = cycle.listen_samples() + cycle.sleep_samples();
+
+        assert!(decoder.push(&vec![0.0f32; cycle.listen_samples()]).is_empty());
+        assert_eq!(decoder.state(), ListenState::Sleeping);
+        assert_eq!(decoder.samples_until_wake(), cycle.sleep_samples());
+        decoder.push(&vec![0.0f32; 3 * period - cycle.listen_samples()]);
+        assert_eq!(decoder.state(), ListenState::Listening);
+
+        let stats = decoder.stats();
+        assert_eq!((stats.wakes, stats.detections), (4, 0));
+        assert_eq!(stats.detect_ratio(), 0.0);
+        assert_eq!(stats.awake_fraction(), cycle.awake_fraction());
+
+        let mut encoder = EncoderFsk::new().unwrap();
+        assert!(matches!(DutyCycle::for_encoder(&encoder, Millis(3000)), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_preamble_cadence(Some(Millis(1000)));
+        assert!(matches!(encoder.encode(b"too long for one second"), Err(AudioModemError::InvalidConfig(_))));
+        encoder.set_profile(FrameProfile::Short);
+        assert!(DutyCycle::for_encoder(&encoder, Millis(3000)).is_err());
+    }
+}
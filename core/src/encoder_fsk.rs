@@ -1,13 +1,354 @@
-use crate::error::Result;
+use crate::calibration::PreEmphasis;
+use crate::error::{AudioModemError, Result};
 use crate::fec::{FecEncoder, FecMode};
-use crate::framing::{Frame, FrameEncoder, crc16};
-use crate::fsk::{FskModulator, FountainConfig};
-use crate::sync::{generate_preamble, generate_postamble_signal, generate_fountain_preamble};
-use crate::{MAX_PAYLOAD_SIZE, PREAMBLE_SAMPLES, POSTAMBLE_SAMPLES, SYNC_SILENCE_SAMPLES};
-use raptorq::{Encoder, EncodingPacket};
+use crate::framing::{
+    sequence_frame_num, ContentType, EncoderVersion, Frame, FrameEncoder, crc16, crc32, PROTOCOL_REVISION,
+    FRAME_CONTENT_TYPE_EXT_SIZE, FRAME_PADDING_BYTE, FRAME_TIMESTAMP_EXT_SIZE, MAX_SEQUENCE_FRAMES,
+};
+use crate::rng::SplitMix64;
+use crate::sidecar::TransmissionSidecar;
+use crate::trig;
+use crate::tx_audio::soft_limit;
+use crate::units::{self, Millis, Samples};
+use crate::fsk::{FskModulator, ToneLayout, AMPLITUDE_SYMBOL_BITS, FSK_BYTES_PER_SYMBOL, FSK_NIBBLES_PER_SYMBOL};
+use crate::melody::Melody;
+use crate::sync::{
+    generate_keyed_postamble, generate_keyed_preamble, generate_short_postamble,
+    generate_short_preamble, generate_telephony_postamble, generate_telephony_preamble,
+    generate_codec_robust_postamble, generate_codec_robust_preamble, generate_narrowband_postamble,
+    generate_narrowband_preamble, NetworkKey,
+};
+use crate::{
+    CODEC_ROBUST_SYMBOL_SAMPLES, CODEC_ROBUST_SYNC_SAMPLES, FRAME_HEADER_SIZE, FSK_SYMBOL_SAMPLES, MAX_PAYLOAD_SIZE,
+    MAX_SYMBOL_GAP_INTERVAL, MAX_SYNC_GAP_SAMPLES, MIN_SYMBOL_GAP_INTERVAL, NARROWBAND_SYMBOL_SAMPLES, NARROWBAND_SYNC_SAMPLES,
+    PREAMBLE_SAMPLES, POSTAMBLE_SAMPLES, SAMPLE_RATE, SHORT_MAX_PAYLOAD_SIZE,
+    SHORT_SYMBOL_SAMPLES, SHORT_SYNC_SAMPLES, SHORT_SYNC_SILENCE_SAMPLES, SYMBOL_GAP_SAMPLES, SYNC_SILENCE_SAMPLES,
+    TELEPHONY_SYMBOL_SAMPLES, TELEPHONY_SYNC_SAMPLES,
+};
+use crate::transfer::{default_transfer_id, TransferEncoder, DEFAULT_PART_DATA_SIZE};
+
+/// Wake-up tone frequency for the fade-in ramp (below the sync chirps and FSK band)
+const FADE_IN_TONE_HZ: f32 = 600.0;
+
+/// Peak amplitude of the fade-in ramp (sync signals use 0.5)
+const FADE_IN_PEAK_AMPLITUDE: f32 = 0.25;
+
+/// Leading/trailing padding around a frame
+///
+/// Some Bluetooth speakers swallow the first ~200 ms of audio while their
+/// amplifier wakes up. A longer leading silence, or a fade-in ramp that keeps
+/// the link active before the preamble, prevents the preamble from being clipped.
+/// The gaps between sync signals and data are set separately (see [`SyncGaps`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderPadding {
+    /// Silence before the fade-in ramp / preamble
+    pub leading_silence_samples: usize,
+    /// Silence after the postamble
+    pub trailing_silence_samples: usize,
+    /// Length of the wake-up tone ramp inserted right before the preamble (0 = none)
+    pub fade_in_samples: usize,
+}
 
-#[cfg(test)]
-use crate::FOUNTAIN_BLOCK_SIZE;
+impl Default for EncoderPadding {
+    fn default() -> Self {
+        Self {
+            leading_silence_samples: SYNC_SILENCE_SAMPLES,
+            trailing_silence_samples: SYNC_SILENCE_SAMPLES,
+            fade_in_samples: 0,
+        }
+    }
+}
+
+impl EncoderPadding {
+    /// Build padding from durations
+    pub fn from_ms(leading: Millis, trailing: Millis, fade_in: Millis) -> Self {
+        let to_samples = |duration: Millis| units::MODEM_RATE.samples(duration).get();
+        Self {
+            leading_silence_samples: to_samples(leading),
+            trailing_silence_samples: to_samples(trailing),
+            fade_in_samples: to_samples(fade_in),
+        }
+    }
+}
+
+/// Silence between the sync signals and the data of standard frames
+///
+/// The fixed gaps make frames easy to spot by their timing; shorter or
+/// randomized ones do not. Decoders find the data by itself, so they need no
+/// setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncGaps {
+    /// The same gap after the preamble and before the postamble (0 = none)
+    Fixed(usize),
+    /// A new length from `min..=max` for every gap, drawn from a generator
+    /// seeded with `seed`
+    Random { min: usize, max: usize, seed: u64 },
+}
+
+impl Default for SyncGaps {
+    fn default() -> Self {
+        SyncGaps::Fixed(SYNC_SILENCE_SAMPLES)
+    }
+}
+
+impl SyncGaps {
+    /// Lengths of the gaps after the preamble and before the postamble of the
+    /// next frame, advancing the generator `state`
+    fn draw(self, rng: &mut SplitMix64) -> (usize, usize) {
+        match self {
+            SyncGaps::Fixed(gap) => (gap, gap),
+            SyncGaps::Random { min, max, .. } => {
+                let span = (max - min + 1) as u64;
+                (min + rng.below(span) as usize, min + rng.below(span) as usize)
+            }
+        }
+    }
+}
+
+/// Set in the first length-prefix byte of frames sent with symbol gaps,
+/// dual-band or differential symbols; an options byte follows the prefix (frame lengths stay
+/// below 32 KiB, so plain frames never have it)
+pub(crate) const LENGTH_PREFIX_OPTIONS_FLAG: u8 = 0x80;
+
+/// Options byte: the data symbols are the dual-band copy of the announced layout
+pub(crate) const FRAME_OPTION_DUAL_BAND: u8 = 0x80;
+
+/// Options byte: the data symbols are differential (see `EncoderFsk::set_differential`)
+pub(crate) const FRAME_OPTION_DIFFERENTIAL: u8 = 0x40;
+
+/// Options byte: symbol gap interval, 0 without gaps
+pub(crate) const FRAME_OPTION_GAP_MASK: u8 = 0x3F;
+
+/// Bits of the first length-prefix byte holding the symbol format: 0 =
+/// standard, a `ToneLayout` code, or `LENGTH_PREFIX_AMPLITUDE_CODE`; frames
+/// stay below 2 KiB, so plain lengths never use them
+pub(crate) const LENGTH_PREFIX_LAYOUT_MASK: u8 = 0x78;
+pub(crate) const LENGTH_PREFIX_LAYOUT_SHIFT: u32 = 3;
+
+/// Symbol format code of amplitude-shaped frames (no `ToneLayout` uses it)
+pub(crate) const LENGTH_PREFIX_AMPLITUDE_CODE: u8 = 0x0F;
+
+/// Frame layout used by `EncoderFsk::encode`
+///
+/// `Short` trades capacity and robustness for latency: 80 ms chirps, 25 ms
+/// gaps and 50 ms symbols around a compact frame
+/// `[len:1][payload][crc16:2]` protected by `FecMode::Light`. A 16-byte
+/// message is on the air for under 700 ms. The short preamble differs from
+/// the standard one, so `DecoderFsk` detects the profile by itself.
+///
+/// `Telephony` survives a phone call (300-3400 Hz band, μ-law, speech codecs):
+/// standard frames protected by `FecMode::Full`, sent as 300 ms symbols of
+/// `ToneLayout::TELEPHONY` between 500 ms sync chirps of their own. About 53
+/// bits per second.
+///
+/// `CodecRobust` is built the same way for audio relayed through Bluetooth
+/// (SBC/AAC) or Opus: `ToneLayout::CODEC_ROBUST` puts tones 60 Hz apart, which
+/// the codecs' frequency smearing does not merge. About 40 bits per second.
+///
+/// `Narrowband` fits radio and intercom links sampled at 8 kHz: 150 ms symbols
+/// of `ToneLayout::NARROWBAND` (800-1740 Hz) between 250 ms chirps sweeping
+/// 500-1900 Hz, nothing near the 4 kHz edge, with `FecMode::Full`. About 70
+/// bits per second.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FrameProfile {
+    #[default]
+    Standard,
+    /// Payloads up to `SHORT_MAX_PAYLOAD_SIZE` bytes, no header extension
+    Short,
+    /// Slow, heavily protected frames for voice-band channels
+    Telephony,
+    /// Slow, heavily protected frames with widely spaced tones for lossy codecs
+    CodecRobust,
+    /// Half-band frames for links sampled at 8 kHz
+    Narrowband,
+}
+
+impl FrameProfile {
+    /// Padding that `EncoderFsk::set_profile` installs for this profile
+    pub fn default_padding(self) -> EncoderPadding {
+        match self {
+            FrameProfile::Short => EncoderPadding {
+                leading_silence_samples: SHORT_SYNC_SILENCE_SAMPLES,
+                trailing_silence_samples: SHORT_SYNC_SILENCE_SAMPLES,
+                fade_in_samples: 0,
+            },
+            _ => EncoderPadding::default(),
+        }
+    }
+
+    /// Sync length, symbol length and tone layout of the profiles that send a
+    /// whole standard frame, length prefix included, in one fixed symbol format
+    pub(crate) fn fixed_format(self) -> Option<(usize, usize, ToneLayout)> {
+        match self {
+            FrameProfile::Telephony => Some((TELEPHONY_SYNC_SAMPLES, TELEPHONY_SYMBOL_SAMPLES, ToneLayout::TELEPHONY)),
+            FrameProfile::CodecRobust => {
+                Some((CODEC_ROBUST_SYNC_SAMPLES, CODEC_ROBUST_SYMBOL_SAMPLES, ToneLayout::CODEC_ROBUST))
+            }
+            FrameProfile::Narrowband => {
+                Some((NARROWBAND_SYNC_SAMPLES, NARROWBAND_SYMBOL_SAMPLES, ToneLayout::NARROWBAND))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FrameProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FrameProfile::Standard => "standard",
+            FrameProfile::Short => "short",
+            FrameProfile::Telephony => "telephony",
+            FrameProfile::CodecRobust => "codec-robust",
+            FrameProfile::Narrowband => "narrowband",
+        })
+    }
+}
+
+impl std::str::FromStr for FrameProfile {
+    type Err = AudioModemError;
+
+    /// The profile's `Display` name, e.g. "codec-robust"
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "standard" => Ok(FrameProfile::Standard),
+            "short" => Ok(FrameProfile::Short),
+            "telephony" => Ok(FrameProfile::Telephony),
+            "codec-robust" => Ok(FrameProfile::CodecRobust),
+            "narrowband" => Ok(FrameProfile::Narrowband),
+            _ => Err(AudioModemError::InvalidConfig(format!(
+                "unknown profile '{}' (expected standard, short, telephony, codec-robust or narrowband)",
+                value
+            ))),
+        }
+    }
+}
+
+/// Size of one frame on the air, from [`EncoderFsk::airtime`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Airtime {
+    /// Reed-Solomon code protecting the frame
+    pub fec_mode: FecMode,
+    /// Reed-Solomon blocks (the last one shortened)
+    pub rs_blocks: usize,
+    /// FSK data symbols, amplitude pilots included
+    pub symbols: usize,
+    /// Samples `encode` returns, padding and sync signals included
+    pub samples: usize,
+}
+
+impl Airtime {
+    pub fn duration_ms(&self) -> u64 {
+        units::MODEM_RATE.millis(Samples(self.samples)).get() as u64
+    }
+
+    /// Exact playing time of the samples `encode` returns
+    pub fn duration(&self) -> std::time::Duration {
+        units::MODEM_RATE.duration(Samples(self.samples))
+    }
+}
+
+/// Largest payload [`EncoderFsk::encode_auto`] sends as one frame (about 15 s
+/// on the air); longer frames are lost whole to a single bad symbol
+pub const AUTO_SINGLE_FRAME_MAX: usize = 200;
+
+/// How a payload goes on the air
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransmissionMode {
+    /// One frame (`EncoderFsk::encode`)
+    SingleFrame,
+    /// Numbered frames a `TransferSession` reassembles (see `TransferEncoder`)
+    MultiFrame,
+    /// Fountain blocks until the receiver has enough (`EncoderFsk::encode_fountain`)
+    Fountain,
+}
+
+impl TransmissionMode {
+    /// Mode [`EncoderFsk::encode_auto`] picks for a payload of `size` bytes
+    ///
+    /// Fountain mode needs the `fountain` feature and, like a frame, carries
+    /// at most `MAX_PAYLOAD_SIZE` bytes.
+    pub fn for_payload(size: usize) -> Self {
+        if size <= AUTO_SINGLE_FRAME_MAX {
+            TransmissionMode::SingleFrame
+        } else if size > MAX_PAYLOAD_SIZE {
+            TransmissionMode::MultiFrame
+        } else if cfg!(feature = "fountain") {
+            TransmissionMode::Fountain
+        } else {
+            TransmissionMode::SingleFrame
+        }
+    }
+}
+
+impl std::fmt::Display for TransmissionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TransmissionMode::SingleFrame => "single-frame",
+            TransmissionMode::MultiFrame => "multi-frame",
+            TransmissionMode::Fountain => "fountain",
+        })
+    }
+}
+
+/// Audio from [`EncoderFsk::encode_auto`]
+pub enum AutoEncoding {
+    SingleFrame(Vec<f32>),
+    /// One frame per transfer part, in order
+    MultiFrame(Vec<Vec<f32>>),
+    /// Blocks until the default fountain timeout
+    #[cfg(feature = "fountain")]
+    Fountain(Box<crate::FountainStream>),
+}
+
+impl AutoEncoding {
+    pub fn mode(&self) -> TransmissionMode {
+        match self {
+            AutoEncoding::SingleFrame(_) => TransmissionMode::SingleFrame,
+            AutoEncoding::MultiFrame(_) => TransmissionMode::MultiFrame,
+            #[cfg(feature = "fountain")]
+            AutoEncoding::Fountain(_) => TransmissionMode::Fountain,
+        }
+    }
+}
+
+/// `PayloadTooLarge` for `size` bytes against a limit of `max`
+///
+/// A single frame is never suggested since `size` exceeds `max`: fountain
+/// blocks carry up to `MAX_PAYLOAD_SIZE` bytes, numbered frames any size.
+pub(crate) fn payload_too_large(size: usize, max: usize) -> AudioModemError {
+    let suggested_mode = if size <= MAX_PAYLOAD_SIZE && cfg!(feature = "fountain") {
+        TransmissionMode::Fountain
+    } else {
+        TransmissionMode::MultiFrame
+    };
+    AudioModemError::PayloadTooLarge { size, max, suggested_mode }
+}
+
+/// Bytes of a standard frame from header to payload CRC
+fn frame_data_size(payload_len: usize, with_timestamp: bool, with_content_type: bool) -> usize {
+    let timestamp_len = if with_timestamp { FRAME_TIMESTAMP_EXT_SIZE } else { 0 };
+    let content_type_len = if with_content_type { FRAME_CONTENT_TYPE_EXT_SIZE } else { 0 };
+    FRAME_HEADER_SIZE + timestamp_len + content_type_len + payload_len + 2
+}
+
+/// Generate the fade-in ramp: a low tone that rises linearly, then tapers off
+/// over the last 10% so it ends without a click before the preamble
+fn generate_fade_in(duration_samples: usize) -> Vec<f32> {
+    let taper = (duration_samples / 10).max(1);
+    let rise = duration_samples.saturating_sub(taper).max(1);
+    (0..duration_samples)
+        .map(|n| {
+            let envelope = if n < rise {
+                n as f32 / rise as f32
+            } else {
+                (duration_samples - n) as f32 / taper as f32
+            };
+            let phase = 2.0 * std::f32::consts::PI * FADE_IN_TONE_HZ * n as f32 / SAMPLE_RATE as f32;
+            FADE_IN_PEAK_AMPLITUDE * envelope.min(1.0) * trig::sin(phase)
+        })
+        .collect()
+}
 
 /// Encoder using Multi-tone FSK with Reed-Solomon FEC
 ///
@@ -21,367 +362,889 @@ use crate::FOUNTAIN_BLOCK_SIZE;
 /// - Well-suited for speaker-to-microphone transmission
 /// - Sub-bass frequency band (400-2300 Hz) for excellent room acoustics
 /// - Simultaneous multi-tone transmission for redundancy
+#[derive(Clone)]
 pub struct EncoderFsk {
     fsk: FskModulator,
     fec: FecEncoder,
+    padding: EncoderPadding,
+    fec_mode: Option<FecMode>,
+    network_key: Option<NetworkKey>,
+    fountain_melody: Option<Melody>,
+    profile: FrameProfile,
+    amplitude_bits: bool,
+    tone_layout: ToneLayout,
+    dual_band: bool,
+    differential: bool,
+    symbol_gaps: Option<u8>,
+    pre_emphasis: Option<PreEmphasis>,
+    sync_gaps: SyncGaps,
+    sync_gap_state: SplitMix64,
+    content_type: Option<ContentType>,
+    version_beacon: bool,
+    /// Set while `encode_control` runs
+    control: bool,
+    preamble_cadence: Option<Millis>,
+    safety_limiter: Option<f32>,
 }
 
 impl EncoderFsk {
+    /// Same as `default()`; construction cannot fail
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            fsk: FskModulator::new(),
-            fec: FecEncoder::new()?,
-        })
+        Ok(Self::default())
     }
 
-    /// Encode binary data into audio samples using multi-tone FSK modulation
-    /// Returns: silence + preamble + silence + FSK data + silence + postamble + silence
+    /// Select the frame layout; also resets the padding to the profile's default
+    pub fn set_profile(&mut self, profile: FrameProfile) {
+        self.profile = profile;
+        self.padding = profile.default_padding();
+    }
+
+    pub fn profile(&self) -> FrameProfile {
+        self.profile
+    }
+
+    /// Largest payload one frame of the current profile carries
+    pub fn max_payload_size(&self) -> usize {
+        match self.profile {
+            FrameProfile::Short => SHORT_MAX_PAYLOAD_SIZE,
+            _ => MAX_PAYLOAD_SIZE,
+        }
+    }
+
+    /// Set leading/trailing silence and fade-in used by `encode`
+    pub fn set_padding(&mut self, padding: EncoderPadding) {
+        self.padding = padding;
+    }
+
+    pub fn padding(&self) -> EncoderPadding {
+        self.padding
+    }
+
+    /// Force an FEC mode, e.g. `FecMode::custom(255, 191)` for very noisy channels
     ///
-    /// Each symbol encodes 3 bytes (24 bits) using 6 simultaneous frequencies.
+    /// `None` (the default) picks Light/Medium/Full from the frame size.
+    /// The mode travels in the frame header, so decoders need no setting.
+    /// Short frames always use `FecMode::Light`, telephony, codec-robust and
+    /// narrowband frames `FecMode::Full`.
+    pub fn set_fec_mode(&mut self, fec_mode: Option<FecMode>) {
+        self.fec_mode = fec_mode;
+    }
+
+    pub fn fec_mode(&self) -> Option<FecMode> {
+        self.fec_mode
+    }
+
+    /// Send one extra bit per tone as its amplitude (30 instead of 24 bits per symbol)
     ///
-    /// Uses variable Reed-Solomon parity based on payload size:
-    /// - Small payloads (< 20 bytes): 8 parity bytes (75% less overhead)
-    /// - Medium payloads (20-50 bytes): 16 parity bytes (50% less overhead)
-    /// - Large payloads (> 50 bytes): 32 parity bytes (full protection)
-    pub fn encode(&mut self, data: &[u8]) -> Result<Vec<f32>> {
-        if data.len() > MAX_PAYLOAD_SIZE {
-            return Err(crate::error::AudioModemError::InvalidInputSize);
+    /// About 25% more throughput, but needs a clean channel: fall back to plain
+    /// symbols when the receiver reports `DecodeQuality::amplitude_bits_viable() == false`.
+    /// Flagged in the frame, so decoders need no setting. Standard profile only.
+    pub fn set_amplitude_bits(&mut self, enabled: bool) {
+        self.amplitude_bits = enabled;
+    }
+
+    pub fn amplitude_bits(&self) -> bool {
+        self.amplitude_bits
+    }
+
+    /// Send the data with fewer tones and/or narrower bands (see [`ToneLayout`])
+    ///
+    /// The first symbol, which holds the length prefix, stays standard and
+    /// announces the layout, so decoders need no setting. Standard profile
+    /// without amplitude bits only.
+    pub fn set_tone_layout(&mut self, layout: ToneLayout) {
+        self.tone_layout = layout;
+    }
+
+    pub fn tone_layout(&self) -> ToneLayout {
+        self.tone_layout
+    }
+
+    /// Send every data tone twice, in the lower and the upper half of the band
+    /// (see [`ToneLayout::dual_band`])
+    ///
+    /// Half the throughput, but a frame survives a speaker or room that loses
+    /// one half of the band. Uses `ToneLayout::DUAL_BAND` unless a tone layout
+    /// narrow enough to mirror is set. Announced in the first symbol, so
+    /// decoders need no setting. Standard profile without amplitude bits only.
+    pub fn set_dual_band(&mut self, enabled: bool) {
+        self.dual_band = enabled;
+    }
+
+    pub fn dual_band(&self) -> bool {
+        self.dual_band
+    }
+
+    /// Send each data tone as the step from the previous symbol's tone in its
+    /// band (see [`FskModulator::set_differential`])
+    ///
+    /// Same throughput, but a tone offset that changes slowly from symbol to
+    /// symbol, e.g. from a drifting channel, cancels out instead of moving
+    /// every tone to a neighbouring bin; a single wrong tone costs two steps.
+    /// It does not help against a flat or tilted frequency response, which
+    /// the per-symbol gain control and per-band peak picking already ignore.
+    /// Announced in the first symbol, so decoders need no setting. Standard
+    /// profile and tone layout without amplitude bits only.
+    pub fn set_differential(&mut self, enabled: bool) {
+        self.differential = enabled;
+    }
+
+    pub fn differential(&self) -> bool {
+        self.differential
+    }
+
+    /// Tone layout of the data symbols after the first one
+    fn data_layout(&self) -> Result<ToneLayout> {
+        if !self.dual_band {
+            Ok(self.tone_layout)
+        } else if self.tone_layout == ToneLayout::STANDARD {
+            Ok(ToneLayout::DUAL_BAND)
+        } else {
+            self.tone_layout.dual_band()
+        }
+    }
+
+    /// Options byte sent after the length prefix, if the frame needs one
+    fn prefix_options(&self, layout: ToneLayout) -> Option<u8> {
+        let mut options = self.symbol_gaps.unwrap_or(0);
+        if layout.is_dual_band() {
+            options |= FRAME_OPTION_DUAL_BAND;
         }
+        if self.differential {
+            options |= FRAME_OPTION_DIFFERENTIAL;
+        }
+        Some(options).filter(|&options| options != 0)
+    }
 
-        // Create frame with header and CRC (without FEC mode yet)
-        let payload = data.to_vec();
+    /// Insert `SYMBOL_GAP_SAMPLES` of silence after every `interval` data symbols
+    ///
+    /// Phone AGCs pump on long continuous tones; the gaps let them settle and
+    /// give the receiver re-sync points. The interval (`MIN_SYMBOL_GAP_INTERVAL`
+    /// to `MAX_SYMBOL_GAP_INTERVAL`) is announced in the first symbol, so
+    /// decoders need no setting. Not available for short frames.
+    pub fn set_symbol_gaps(&mut self, interval: Option<u8>) {
+        self.symbol_gaps = interval;
+    }
 
-        // Determine FEC mode based on frame size (header + payload + CRC)
-        let frame_data_size = 8 + data.len() + 2; // header(8) + payload + crc16(2)
-        let fec_mode = FecMode::from_data_size(frame_data_size);
+    pub fn symbol_gaps(&self) -> Option<u8> {
+        self.symbol_gaps
+    }
 
-        let frame = Frame {
-            payload_len: data.len() as u16,
-            frame_num: 0,
-            fec_mode: fec_mode.to_u8(),
-            payload: payload.clone(),
-            payload_crc: crc16(&payload),
+    /// Change the silence between the sync signals and the data (see [`SyncGaps`])
+    ///
+    /// Gaps up to `MAX_SYNC_GAP_SAMPLES`; a random generator restarts from its
+    /// seed here. Standard profile only.
+    pub fn set_sync_gaps(&mut self, gaps: SyncGaps) {
+        self.sync_gaps = gaps;
+        self.sync_gap_state = match gaps {
+            SyncGaps::Random { seed, .. } => SplitMix64::new(seed),
+            SyncGaps::Fixed(_) => SplitMix64::new(0),
         };
+    }
 
-        let frame_data = FrameEncoder::encode(&frame)?;
+    pub fn sync_gaps(&self) -> SyncGaps {
+        self.sync_gaps
+    }
 
-        // Apply variable shortened Reed-Solomon FEC encoding
-        let mut encoded_data = Vec::new();
+    /// Announce what the payloads hold (see [`ContentType`]) in a one-byte
+    /// header extension, so receivers can dispatch them. Not available for
+    /// short frames.
+    pub fn set_content_type(&mut self, content_type: Option<ContentType>) {
+        self.content_type = content_type.map(|content_type| ContentType::from_code(content_type.code()));
+    }
 
-        // Add 2-byte length prefix so decoder knows the frame data length
-        let frame_len = frame_data.len() as u16;
-        encoded_data.push((frame_len >> 8) as u8);
-        encoded_data.push(frame_len as u8);
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.content_type
+    }
 
-        for chunk in frame_data.chunks(223) {
-            let chunk_len = chunk.len();
+    /// Announce this build ([`EncoderVersion::CURRENT`]) in the reserved header
+    /// byte, so captures show which encoder produced them. Not available for
+    /// short frames.
+    pub fn set_version_beacon(&mut self, enabled: bool) {
+        self.version_beacon = enabled;
+    }
 
-            // Shortened RS: prepend zeros, encode, remove zeros
-            // This avoids transmitting padding bytes for small payloads
-            let padding_needed = 223 - chunk_len;
+    pub fn version_beacon(&self) -> bool {
+        self.version_beacon
+    }
 
-            // Create padded data for RS encoder
-            let mut padded = vec![0u8; padding_needed];
-            padded.extend_from_slice(chunk);
+    /// Pad every frame with trailing silence to exactly `cadence`, so playing
+    /// `encode`'s output on a loop starts a preamble at that interval
+    ///
+    /// Duty-cycled receivers size their listening window from the same value
+    /// (see [`crate::DutyCycle::for_encoder`]). Frames longer than the cadence
+    /// fail with `InvalidConfig`.
+    pub fn set_preamble_cadence(&mut self, cadence: Option<Millis>) {
+        self.preamble_cadence = cadence;
+    }
 
-            // Encode with variable RS parity based on FEC mode
-            let fec_chunk = self.fec.encode_with_mode(&padded, fec_mode)?;
+    pub fn preamble_cadence(&self) -> Option<Millis> {
+        self.preamble_cadence
+    }
 
-            // Only transmit: actual data + parity (skip the prepended zeros)
-            // Parity size depends on FEC mode (8, 16, or 32 bytes)
-            encoded_data.extend_from_slice(&fec_chunk[padding_needed..]);
-        }
+    /// Keep every frame sample within `±ceiling` (0-1], leaving headroom for
+    /// callers that mix the output with other audio
+    ///
+    /// Samples above 80% of the ceiling are compressed smoothly rather than
+    /// hard-clipped. Check the final mix with [`crate::validate_tx_audio`].
+    /// Ceilings outside (0, 1] fail with `InvalidConfig`.
+    pub fn set_safety_limiter(&mut self, ceiling: Option<f32>) {
+        self.safety_limiter = ceiling;
+    }
 
-        // Pad encoded data to be a multiple of FSK_BYTES_PER_SYMBOL (3 bytes)
-        // Multi-tone FSK transmits 3 bytes per symbol
-        let remainder = encoded_data.len() % crate::fsk::FSK_BYTES_PER_SYMBOL;
-        if remainder != 0 {
-            let padding = crate::fsk::FSK_BYTES_PER_SYMBOL - remainder;
-            encoded_data.resize(encoded_data.len() + padding, 0u8);
-        }
+    pub fn safety_limiter(&self) -> Option<f32> {
+        self.safety_limiter
+    }
 
-        // Generate preamble signal for synchronization
-        let preamble = generate_preamble(PREAMBLE_SAMPLES, 0.5);
+    /// Scale each data tone by a speaker/room calibration (see [`crate::calibration`])
+    ///
+    /// Only the data symbols change, and within each band only the levels of
+    /// the tones, so decoders need no setting.
+    pub fn set_pre_emphasis(&mut self, emphasis: Option<PreEmphasis>) {
+        self.pre_emphasis = emphasis;
+        self.fsk.set_bin_gains(emphasis.map(|emphasis| emphasis.gains()));
+    }
 
-        // Build frame: silence → preamble → silence → FSK payload → silence → postamble → silence
-        let mut samples = Vec::new();
+    pub fn pre_emphasis(&self) -> Option<PreEmphasis> {
+        self.pre_emphasis
+    }
 
-        // Add silence before preamble for clean frame start
-        samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+    /// Send each band's data tones at a relative level (see
+    /// [`FskModulator::set_band_gains`]); decoders compensate with
+    /// `DecoderFsk::set_band_compensation`
+    pub fn set_band_gains(&mut self, gains: Option<[f32; FSK_NIBBLES_PER_SYMBOL]>) -> Result<()> {
+        self.fsk.set_band_gains(gains)
+    }
 
-        // Add preamble for synchronization
-        samples.extend_from_slice(&preamble);
+    pub fn band_gains(&self) -> Option<[f32; FSK_NIBBLES_PER_SYMBOL]> {
+        self.fsk.band_gains()
+    }
 
-        // Add silence after preamble for symmetry and clear frame boundaries
-        samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+    /// Modulator for a non-default symbol length or layout, with the pre-emphasis and band gains applied
+    pub(crate) fn modulator(&self, symbol_samples: usize, layout: ToneLayout) -> FskModulator {
+        let mut modulator = FskModulator::with_layout(Samples(symbol_samples), layout);
+        modulator.share_gains(&self.fsk);
+        modulator
+    }
 
-        // Modulate data bytes using multi-tone FSK
-        let fsk_samples = self.fsk.modulate(&encoded_data)?;
-        samples.extend_from_slice(&fsk_samples);
+    /// Use the sync signals of `key` (see [`NetworkKey`]) for frames and fountain streams
+    pub fn set_network_key(&mut self, key: Option<NetworkKey>) {
+        self.network_key = key;
+    }
 
-        // Add silence before postamble to separate payload from end marker
-        samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+    pub fn network_key(&self) -> Option<NetworkKey> {
+        self.network_key
+    }
 
-        // Generate postamble signal for frame boundary detection
-        let postamble = generate_postamble_signal(POSTAMBLE_SAMPLES, 0.5);
-        samples.extend_from_slice(&postamble);
+    /// Open fountain blocks with `melody` instead of the three-note whistle;
+    /// a network key, when set, still selects its own preamble
+    pub fn set_fountain_melody(&mut self, melody: Option<Melody>) {
+        self.fountain_melody = melody;
+    }
 
-        // Add silence after postamble for clean frame end
-        samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+    pub fn fountain_melody(&self) -> Option<&Melody> {
+        self.fountain_melody.as_ref()
+    }
+
+    /// Encode binary data into audio samples using multi-tone FSK modulation
+    /// Returns: silence + preamble + silence + FSK data + silence + postamble + silence
+    ///
+    /// Each symbol encodes 3 bytes (24 bits) using 6 simultaneous frequencies.
+    ///
+    /// Uses variable Reed-Solomon parity based on payload size:
+    /// - Small payloads (< 20 bytes): 8 parity bytes (75% less overhead)
+    /// - Medium payloads (20-50 bytes): 16 parity bytes (50% less overhead)
+    /// - Large payloads (> 50 bytes): 32 parity bytes (full protection)
+    pub fn encode(&mut self, data: &[u8]) -> Result<Vec<f32>> {
+        self.encode_frame(data, None)
+    }
+
+    /// Encode data with a caller-provided send timestamp (microseconds) in the
+    /// header extension, for latency/clock-offset estimation on the receiver
+    pub fn encode_with_timestamp(&mut self, data: &[u8], timestamp_us: u64) -> Result<Vec<f32>> {
+        self.encode_frame(data, Some(timestamp_us))
+    }
+
+    /// Encode a link control message for the other end (see `LinkProfile::encode_control`)
+    ///
+    /// The header marks the frame as control, so the receiver never mistakes
+    /// it for user data; the short profile has no header and is rejected.
+    pub fn encode_control(&mut self, payload: &[u8]) -> Result<Vec<f32>> {
+        if self.profile == FrameProfile::Short {
+            return Err(AudioModemError::InvalidConfig(
+                "control frames need a header, which the short profile lacks".to_string(),
+            ));
+        }
+        self.control = true;
+        let result = self.encode_frame(payload, None);
+        self.control = false;
+        result
+    }
+
+    /// Like `encode`, but hands the audio to `sink` piece by piece (silences,
+    /// sync signals, then one FSK symbol at a time) instead of returning one
+    /// buffer, e.g. to feed an audio worklet without holding the whole frame
+    ///
+    /// Settings and payload size are checked before the first chunk, and the
+    /// modulation after it cannot fail, so the sink never sees a partial frame.
+    pub fn encode_into(&mut self, data: &[u8], mut sink: impl FnMut(&[f32])) -> Result<()> {
+        self.encode_frame_into(data, 0, None, &mut sink)
+    }
 
+    /// Encode many payloads on `jobs` threads (0 = one per CPU core)
+    ///
+    /// Each thread encodes a contiguous share of `payloads` with its own copy
+    /// of this encoder, into buffers allocated once at the frame size, so the
+    /// results are the ones `encode` gives for each payload, in order.
+    pub fn encode_batch(&self, payloads: &[Vec<u8>], jobs: usize) -> Vec<Result<Vec<f32>>> {
+        let jobs = match jobs {
+            0 => std::thread::available_parallelism().map_or(1, |cores| cores.get()),
+            jobs => jobs,
+        };
+        let share = payloads.len().div_ceil(jobs).max(1);
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = payloads
+                .chunks(share)
+                .map(|chunk| {
+                    let mut encoder = self.clone();
+                    scope.spawn(move || chunk.iter().map(|data| encoder.encode_sized(data)).collect::<Vec<_>>())
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("encoder thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Encode `frames` into one buffer, `gap` of silence apart, each numbered
+    /// with its place in the sequence
+    ///
+    /// `DecoderFsk::decode_all` returns them in order and names the ones it
+    /// missed, for payloads a few frames long that do not need a transfer or
+    /// fountain code. Short frames have no header to number, so the short
+    /// profile is rejected. Fails before encoding anything.
+    pub fn encode_sequence(&mut self, frames: &[&[u8]], gap: Millis) -> Result<Vec<f32>> {
+        if frames.is_empty() || frames.len() > MAX_SEQUENCE_FRAMES {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "sequence of {} frames (supported: 1-{})",
+                frames.len(),
+                MAX_SEQUENCE_FRAMES
+            )));
+        }
+        if self.profile == FrameProfile::Short {
+            return Err(AudioModemError::InvalidConfig("short frames have no header for a frame number".to_string()));
+        }
+        let gap_samples = units::MODEM_RATE.samples(gap).get();
+        let frame_samples =
+            frames.iter().map(|data| self.airtime(data.len(), false).map(|airtime| airtime.samples)).sum::<Result<usize>>()?;
+
+        let mut samples = Vec::with_capacity(frame_samples + (frames.len() - 1) * gap_samples);
+        let mut sink = |chunk: &[f32]| samples.extend_from_slice(chunk);
+        for (index, data) in frames.iter().enumerate() {
+            if index > 0 {
+                emit_silence(gap_samples, &mut sink);
+            }
+            self.encode_frame_into(data, sequence_frame_num(index, frames.len()), None, &mut sink)?;
+        }
         Ok(samples)
     }
 
-    /// Encode data using fountain mode for continuous streaming transmission
+    /// Encode `data` in the mode [`TransmissionMode::for_payload`] picks for its size
     ///
-    /// Returns a FountainStream iterator that generates unique encoded blocks
-    /// continuously until the configured timeout is reached.
+    /// Frames use this encoder's settings; transfer parts get the ID
+    /// `default_transfer_id(data)` and `DEFAULT_PART_DATA_SIZE` bytes each,
+    /// fountain blocks the default `FountainConfig`.
+    pub fn encode_auto(&mut self, data: &[u8]) -> Result<AutoEncoding> {
+        match TransmissionMode::for_payload(data.len()) {
+            TransmissionMode::SingleFrame => self.encode(data).map(AutoEncoding::SingleFrame),
+            TransmissionMode::MultiFrame => {
+                let transfer = TransferEncoder::new(default_transfer_id(data), data, DEFAULT_PART_DATA_SIZE)?;
+                (0..transfer.part_count())
+                    .map(|index| self.encode(&transfer.part_payload(index)?))
+                    .collect::<Result<Vec<_>>>()
+                    .map(AutoEncoding::MultiFrame)
+            }
+            #[cfg(feature = "fountain")]
+            TransmissionMode::Fountain => self.encode_fountain(data, None).map(|stream| AutoEncoding::Fountain(Box::new(stream))),
+            #[cfg(not(feature = "fountain"))]
+            TransmissionMode::Fountain => unreachable!("fountain mode is only picked with the fountain feature"),
+        }
+    }
+
+    /// `encode` into a buffer of the frame's final size
+    fn encode_sized(&mut self, data: &[u8]) -> Result<Vec<f32>> {
+        let mut samples = Vec::with_capacity(self.airtime(data.len(), false)?.samples);
+        self.encode_frame_into(data, 0, None, &mut |chunk| samples.extend_from_slice(chunk))?;
+        Ok(samples)
+    }
+
+    /// Check a payload of `payload_len` bytes (plus a header timestamp if
+    /// `with_timestamp`) against the encoder's settings and size its frame
     ///
-    /// Each yielded Vec<f32> is a complete audio chunk with:
-    /// preamble + fountain_block + postamble
-    pub fn encode_fountain(&mut self, data: &[u8], config: Option<FountainConfig>) -> Result<FountainStream> {
-        if data.len() > MAX_PAYLOAD_SIZE {
-            return Err(crate::error::AudioModemError::InvalidInputSize);
-        }
-
-        let config = config.unwrap_or_default();
-
-        // Create frame with header and CRC
-        let payload = data.to_vec();
-        let frame = Frame {
-            payload_len: data.len() as u16,
-            frame_num: 0,
-            fec_mode: 0, // Not used in fountain mode
-            payload: payload.clone(),
-            payload_crc: crc16(&payload),
+    /// Fails with the error `encode` would return, without encoding anything.
+    pub fn airtime(&self, payload_len: usize, with_timestamp: bool) -> Result<Airtime> {
+        let airtime = self.unpadded_airtime(payload_len, with_timestamp)?;
+        Ok(Airtime { samples: airtime.samples + self.cadence_padding(airtime.samples)?, ..airtime })
+    }
+
+    /// Everything needed to decode or debug a transmission of `payload` later
+    ///
+    /// Sized like `airtime`, so call it before `encode` (which draws the next
+    /// random sync gaps). Fails with the error `encode` would return.
+    pub fn sidecar(&self, payload: &[u8]) -> Result<TransmissionSidecar> {
+        let airtime = self.airtime(payload.len(), false)?;
+        let layout = match self.profile.fixed_format() {
+            Some((_, _, layout)) => layout,
+            None if self.profile == FrameProfile::Short => ToneLayout::STANDARD,
+            None => self.data_layout()?,
         };
+        Ok(TransmissionSidecar {
+            crate_version: env!("CARGO_PKG_VERSION"),
+            protocol_revision: PROTOCOL_REVISION,
+            profile: self.profile,
+            layout,
+            amplitude_bits: self.amplitude_bits,
+            differential: self.differential,
+            symbol_gaps: self.symbol_gaps,
+            network_keyed: self.network_key.is_some(),
+            content_type: self.content_type,
+            airtime,
+            payload_len: payload.len(),
+            payload_crc32: crc32(payload),
+        })
+    }
 
-        let frame_data = FrameEncoder::encode(&frame)?;
+    /// `airtime` without the silence added to reach the preamble cadence
+    fn unpadded_airtime(&self, payload_len: usize, with_timestamp: bool) -> Result<Airtime> {
+        self.validate(payload_len, with_timestamp)?;
+        if self.profile == FrameProfile::Short {
+            let fec_mode = FecMode::Light;
+            let symbols = (1 + payload_len + 2 + fec_mode.parity_bytes()).div_ceil(FSK_BYTES_PER_SYMBOL);
+            let samples =
+                self.frame_samples(SHORT_SYNC_SAMPLES, 2 * SHORT_SYNC_SILENCE_SAMPLES, symbols * SHORT_SYMBOL_SAMPLES);
+            return Ok(Airtime { fec_mode, rs_blocks: 1, symbols, samples });
+        }
 
-        // Validate block_size before casting to u16
-        let symbol_size = u16::try_from(config.block_size)
-            .map_err(|_| crate::error::AudioModemError::InvalidConfig(
-                format!("block_size {} exceeds maximum u16 value ({})", config.block_size, u16::MAX)
-            ))?;
-
-        // Create RaptorQ encoder using with_defaults for proper parameter handling
-        let oti = raptorq::ObjectTransmissionInformation::with_defaults(
-            frame_data.len() as u64,
-            symbol_size
-        );
-
-        let encoder = Encoder::new(&frame_data, oti);
-        let source_packets = encoder.get_encoded_packets(0);
-        if source_packets.is_empty() {
+        let frame_len = frame_data_size(payload_len, with_timestamp, self.content_type.is_some());
+        let fec_mode = self.frame_fec_mode(frame_len);
+        let rs_blocks = frame_len.div_ceil(fec_mode.data_bytes());
+        let layout = self.data_layout()?;
+        let options_byte = usize::from(self.prefix_options(layout).is_some());
+        let encoded = 2 + options_byte + frame_len + rs_blocks * fec_mode.parity_bytes();
+        let rest = encoded - FSK_BYTES_PER_SYMBOL;
+        let (sync_samples, symbol_samples, symbols) = match self.profile.fixed_format() {
+            Some((sync_samples, symbol_samples, layout)) => {
+                (sync_samples, symbol_samples, layout.symbols_for(encoded.next_multiple_of(layout.alignment_bytes())))
+            }
+            // Plain first symbol and two pilots before the amplitude-shaped data
+            None if self.amplitude_bits => {
+                (PREAMBLE_SAMPLES, FSK_SYMBOL_SAMPLES, 3 + (rest * 8).div_ceil(AMPLITUDE_SYMBOL_BITS))
+            }
+            None if layout != ToneLayout::STANDARD => {
+                (PREAMBLE_SAMPLES, FSK_SYMBOL_SAMPLES, 1 + layout.symbols_for(rest.next_multiple_of(layout.alignment_bytes())))
+            }
+            None => (PREAMBLE_SAMPLES, FSK_SYMBOL_SAMPLES, encoded.div_ceil(FSK_BYTES_PER_SYMBOL)),
+        };
+        let gaps = self.symbol_gaps.map_or(0, |interval| (symbols - 1) / interval as usize);
+        let data_samples = symbols * symbol_samples + gaps * SYMBOL_GAP_SAMPLES;
+        // The gaps the next frame will get, without advancing the generator
+        let (head_gap, tail_gap) = self.sync_gaps.draw(&mut self.sync_gap_state.clone());
+        let samples = self.frame_samples(sync_samples, head_gap + tail_gap, data_samples);
+        Ok(Airtime { fec_mode, rs_blocks, symbols, samples })
+    }
+
+    /// Settings and payload size checks shared by `encode` and `airtime`
+    fn validate(&self, payload_len: usize, with_timestamp: bool) -> Result<()> {
+        let custom_layout = self.data_layout()? != ToneLayout::STANDARD;
+        if custom_layout && (self.amplitude_bits || self.profile != FrameProfile::Standard) {
             return Err(crate::error::AudioModemError::InvalidConfig(
-                "RaptorQ encoder did not produce any source packets".to_string(),
+                "tone layouts and dual-band symbols need the standard profile without amplitude bits".to_string(),
             ));
         }
-
-        let block_count = encoder.get_block_encoders().len();
-        if block_count == 0 {
+        if !self.tone_layout.announceable() {
+            return Err(crate::error::AudioModemError::InvalidConfig(
+                "this tone layout is only sent by its frame profile".to_string(),
+            ));
+        }
+        if self.differential && (custom_layout || self.amplitude_bits || self.profile != FrameProfile::Standard) {
+            return Err(crate::error::AudioModemError::InvalidConfig(
+                "differential symbols need the standard profile and tone layout without amplitude bits".to_string(),
+            ));
+        }
+        if self.profile.fixed_format().is_some() && self.amplitude_bits {
             return Err(crate::error::AudioModemError::InvalidConfig(
-                "RaptorQ encoder has no source blocks".to_string(),
+                "amplitude bits need the standard profile".to_string(),
             ));
         }
+        if let Some(interval) = self.symbol_gaps {
+            if self.profile == FrameProfile::Short {
+                return Err(crate::error::AudioModemError::InvalidConfig(
+                    "short frames have no symbol gaps".to_string(),
+                ));
+            }
+            if !(MIN_SYMBOL_GAP_INTERVAL..=MAX_SYMBOL_GAP_INTERVAL).contains(&(interval as usize)) {
+                return Err(crate::error::AudioModemError::InvalidConfig(format!(
+                    "symbol gap interval {} (supported: {}-{})",
+                    interval, MIN_SYMBOL_GAP_INTERVAL, MAX_SYMBOL_GAP_INTERVAL
+                )));
+            }
+        }
+        if self.sync_gaps != SyncGaps::default() {
+            if self.profile != FrameProfile::Standard {
+                return Err(crate::error::AudioModemError::InvalidConfig(
+                    "other sync gaps need the standard profile".to_string(),
+                ));
+            }
+            let (min, max) = match self.sync_gaps {
+                SyncGaps::Fixed(gap) => (gap, gap),
+                SyncGaps::Random { min, max, .. } => (min, max),
+            };
+            if min > max || max > MAX_SYNC_GAP_SAMPLES {
+                return Err(crate::error::AudioModemError::InvalidConfig(format!(
+                    "sync gaps of {}-{} samples (supported: 0-{})",
+                    min, max, MAX_SYNC_GAP_SAMPLES
+                )));
+            }
+        }
+        if let Some(ceiling) = self.safety_limiter {
+            if ceiling.is_nan() || ceiling <= 0.0 || ceiling > 1.0 {
+                return Err(crate::error::AudioModemError::InvalidConfig(format!(
+                    "safety limiter ceiling {} (supported: above 0 up to 1)",
+                    ceiling
+                )));
+            }
+        }
+        if self.profile == FrameProfile::Short {
+            if with_timestamp {
+                return Err(crate::error::AudioModemError::InvalidConfig(
+                    "short frames have no header extension for a timestamp".to_string(),
+                ));
+            }
+            if self.content_type.is_some() {
+                return Err(crate::error::AudioModemError::InvalidConfig(
+                    "short frames have no header extension for a content type".to_string(),
+                ));
+            }
+            if self.version_beacon {
+                return Err(crate::error::AudioModemError::InvalidConfig(
+                    "short frames have no header for a version beacon".to_string(),
+                ));
+            }
+        }
+        if payload_len > self.max_payload_size() {
+            return Err(payload_too_large(payload_len, self.max_payload_size()));
+        }
+        Ok(())
+    }
 
-        let repair_counters = vec![0u32; block_count];
-        let repairs_per_cycle = if config.repair_blocks_ratio <= 0.0 {
-            0
-        } else {
-            let desired = (source_packets.len() as f32 * config.repair_blocks_ratio).ceil() as usize;
-            desired.max(1)
+    /// Silence after a frame of `frame_samples` that fills the preamble cadence
+    fn cadence_padding(&self, frame_samples: usize) -> Result<usize> {
+        let Some(cadence) = self.preamble_cadence else {
+            return Ok(0);
         };
+        let cadence_samples = units::MODEM_RATE.samples(cadence).get();
+        cadence_samples.checked_sub(frame_samples).ok_or_else(|| {
+            crate::error::AudioModemError::InvalidConfig(format!(
+                "frame of {} ms does not fit the preamble cadence of {} ms",
+                units::MODEM_RATE.millis(Samples(frame_samples)).get(),
+                cadence.get()
+            ))
+        })
+    }
 
-        // Calculate max samples based on timeout_secs as audio duration
-        // Use the single source of truth: crate::SAMPLE_RATE
-        let max_samples = if config.timeout_secs == 0 {
-            usize::MAX
+    /// FEC mode of a standard frame of `frame_len` bytes (header to CRC)
+    fn frame_fec_mode(&self, frame_len: usize) -> FecMode {
+        if self.profile.fixed_format().is_some() {
+            FecMode::Full
         } else {
-            config.timeout_secs as usize * crate::SAMPLE_RATE
-        };
+            self.fec_mode.unwrap_or_else(|| FecMode::from_data_size(frame_len))
+        }
+    }
 
-        Ok(FountainStream {
-            encoder,
-            frame_length: frame_data.len(),
-            symbol_size,
-            fsk: FskModulator::new(),
-            config,
-            block_id: 0,
-            source_packets,
-            next_source_idx: 0,
-            repair_counters,
-            repair_block_cursor: 0,
-            repairs_per_cycle,
-            repairs_sent_this_cycle: 0,
-            total_samples_generated: 0,
-            max_samples,
-        })
+    /// Padding, sync signals and their gaps (`gap_samples` for both) around
+    /// `data_samples` of symbols
+    fn frame_samples(&self, sync_samples: usize, gap_samples: usize, data_samples: usize) -> usize {
+        let padding = self.padding.leading_silence_samples + self.padding.fade_in_samples;
+        padding + 2 * sync_samples + gap_samples + data_samples + self.padding.trailing_silence_samples
     }
-}
 
-/// Iterator that generates continuous fountain-encoded audio blocks
-pub struct FountainStream {
-    encoder: Encoder,
-    frame_length: usize,
-    symbol_size: u16,
-    fsk: FskModulator,
-    config: FountainConfig,
-    block_id: u32,
-    source_packets: Vec<EncodingPacket>,
-    next_source_idx: usize,
-    repair_counters: Vec<u32>,
-    repair_block_cursor: usize,
-    repairs_per_cycle: usize,
-    repairs_sent_this_cycle: usize,
-    total_samples_generated: usize,
-    max_samples: usize,
-}
+    fn encode_frame(&mut self, data: &[u8], timestamp_us: Option<u64>) -> Result<Vec<f32>> {
+        let mut samples = Vec::new();
+        self.encode_frame_into(data, 0, timestamp_us, &mut |chunk| samples.extend_from_slice(chunk))?;
+        Ok(samples)
+    }
 
-impl Iterator for FountainStream {
-    type Item = Vec<f32>;
+    fn encode_frame_into(
+        &mut self,
+        data: &[u8],
+        frame_num: u16,
+        timestamp_us: Option<u64>,
+        sink: &mut impl FnMut(&[f32]),
+    ) -> Result<()> {
+        let cadence_padding = match self.preamble_cadence {
+            Some(_) => {
+                let frame_samples = self.unpadded_airtime(data.len(), timestamp_us.is_some())?.samples;
+                self.cadence_padding(frame_samples)?
+            }
+            None => 0,
+        };
+        match self.safety_limiter {
+            Some(ceiling) => {
+                let mut limited = Vec::new();
+                self.encode_unpadded_frame_into(data, frame_num, timestamp_us, &mut |chunk: &[f32]| {
+                    limited.clear();
+                    limited.extend(chunk.iter().map(|&x| soft_limit(x, ceiling)));
+                    sink(&limited);
+                })?;
+            }
+            None => self.encode_unpadded_frame_into(data, frame_num, timestamp_us, sink)?,
+        }
+        emit_silence(cadence_padding, sink);
+        Ok(())
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // Check if we've already reached the audio duration limit
-        if self.total_samples_generated >= self.max_samples {
-            return None;
+    fn encode_unpadded_frame_into(
+        &mut self,
+        data: &[u8],
+        frame_num: u16,
+        timestamp_us: Option<u64>,
+        sink: &mut impl FnMut(&[f32]),
+    ) -> Result<()> {
+        self.validate(data.len(), timestamp_us.is_some())?;
+        if self.profile == FrameProfile::Short {
+            return self.encode_short_frame(data, sink);
+        }
+        let layout = self.data_layout()?;
+        let custom_layout = layout != ToneLayout::STANDARD;
+        let fixed_format = self.profile.fixed_format();
+
+        let fec_mode =
+            self.frame_fec_mode(frame_data_size(data.len(), timestamp_us.is_some(), self.content_type.is_some()));
+        let block_data_bytes = fec_mode.data_bytes();
+
+        let mut frame = Frame::new(data, frame_num, fec_mode.to_u8());
+        frame.amplitude_bits = self.amplitude_bits;
+        frame.differential = self.differential;
+        frame.control = self.control;
+        frame.encoder_version = self.version_beacon.then_some(EncoderVersion::CURRENT);
+        if let Some(timestamp_us) = timestamp_us {
+            frame = frame.with_timestamp(timestamp_us);
+        }
+        if let Some(content_type) = self.content_type {
+            frame = frame.with_content_type(content_type);
         }
 
-        // Select next fountain packet (cycles through source packets and then repair packets)
-        let packet = match self.select_next_packet() {
-            Some(packet) => packet,
-            None => return None,
-        };
-        let packet_data = packet.serialize();
+        let frame_data = FrameEncoder::encode(&frame)?;
 
+        // Apply variable shortened Reed-Solomon FEC encoding
         let mut encoded_data = Vec::new();
 
-        // Include frame metadata in every block so the decoder can resynchronize mid-stream
-        encoded_data.extend_from_slice(&(self.frame_length as u32).to_be_bytes());
-        encoded_data.extend_from_slice(&self.symbol_size.to_be_bytes());
-
-        // Prefix each block with the serialized packet length so padding can be removed
-        let packet_len = packet_data.len() as u16;
-        encoded_data.extend_from_slice(&packet_len.to_be_bytes());
-        encoded_data.extend_from_slice(&packet_data);
-
-        // Add CRC-16 checksum of the RaptorQ packet for early corruption detection
-        let packet_crc = crc16(&packet_data);
-        encoded_data.extend_from_slice(&packet_crc.to_be_bytes());
-
-        let remainder = encoded_data.len() % crate::fsk::FSK_BYTES_PER_SYMBOL;
-        if remainder != 0 {
-            let padding = crate::fsk::FSK_BYTES_PER_SYMBOL - remainder;
-            encoded_data.resize(encoded_data.len() + padding, 0u8);
-        }
-        assert_eq!(
-            encoded_data.len() % crate::fsk::FSK_BYTES_PER_SYMBOL,
-            0,
-            "FSK symbol alignment invariant violated: encoded_data length ({}) is not a multiple of FSK_BYTES_PER_SYMBOL ({})",
-            encoded_data.len(),
-            crate::fsk::FSK_BYTES_PER_SYMBOL
-        );
-
-        // Generate audio: silence → preamble → silence → FSK data (no postamble for fountain mode)
-        // Fountain mode exclusively uses the three-note whistle preamble
-        let mut samples = Vec::new();
+        // Add 2-byte length prefix so decoder knows the frame data length
+        let frame_len = frame_data.len() as u16;
+        encoded_data.push((frame_len >> 8) as u8);
+        encoded_data.push(frame_len as u8);
+        if let Some(options) = self.prefix_options(layout) {
+            // Announced in the first symbol, ahead of the first gap or dual-band symbol
+            encoded_data[0] |= LENGTH_PREFIX_OPTIONS_FLAG;
+            encoded_data.push(options);
+        }
 
-        // Add silence before preamble for clean block start
-        samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+        for chunk in frame_data.chunks(block_data_bytes) {
+            let chunk_len = chunk.len();
 
-        let preamble = generate_fountain_preamble(PREAMBLE_SAMPLES, 0.5);
-        samples.extend_from_slice(&preamble);
+            // Shortened RS: prepend zeros, encode, remove zeros
+            // This avoids transmitting padding bytes for small payloads
+            let padding_needed = block_data_bytes - chunk_len;
 
-        // Add silence after preamble for clear frame boundaries
-        samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+            // Create padded data for RS encoder
+            let mut padded = vec![0u8; padding_needed];
+            padded.extend_from_slice(chunk);
 
-        match self.fsk.modulate(&encoded_data) {
-            Ok(fsk_samples) => {
-                samples.extend_from_slice(&fsk_samples);
-                // No postamble - fountain mode is open-ended with only preamble signaling
+            // Encode with variable RS parity based on FEC mode
+            let fec_chunk = self.fec.encode_with_mode(&padded, fec_mode)?;
 
-                // Always emit complete blocks without truncation, as truncating mid-block creates
-                // malformed audio that cannot be deserialized. The max_samples limit is
-                // approximate and may be exceeded by one block, which is acceptable.
-                self.total_samples_generated += samples.len();
-                self.block_id += 1;
-                Some(samples)
-            }
-            Err(_) => None,
+            // Only transmit: actual data + parity (skip the prepended zeros)
+            // Parity size depends on FEC mode (8, 16, 32 or custom)
+            encoded_data.extend_from_slice(&fec_chunk[padding_needed..]);
         }
-    }
-}
-
-impl FountainStream {
-    fn select_next_packet(&mut self) -> Option<EncodingPacket> {
-        loop {
-            if self.next_source_idx < self.source_packets.len() {
-                let packet = self.source_packets[self.next_source_idx].clone();
-                self.next_source_idx += 1;
-                return Some(packet);
-            }
 
-            if self.repairs_per_cycle > 0 && self.repairs_sent_this_cycle < self.repairs_per_cycle {
-                if let Some(packet) = self.next_repair_packet() {
-                    self.repairs_sent_this_cycle += 1;
-                    return Some(packet);
-                } else {
-                    return None;
-                }
+        if self.amplitude_bits {
+            // The first symbol stays plain so the decoder sees the flag before the pilots
+            encoded_data[0] |= LENGTH_PREFIX_AMPLITUDE_CODE << LENGTH_PREFIX_LAYOUT_SHIFT;
+        } else if let Some((_, _, layout)) = fixed_format {
+            // Every symbol, the length prefix included, uses the profile's layout
+            encoded_data.resize(encoded_data.len().next_multiple_of(layout.alignment_bytes()), FRAME_PADDING_BYTE);
+        } else if custom_layout {
+            // Standard first symbol announcing the layout, then whole symbols of the layout
+            encoded_data[0] |= layout.code() << LENGTH_PREFIX_LAYOUT_SHIFT;
+            let alignment = layout.alignment_bytes();
+            let rest_len = (encoded_data.len() - FSK_BYTES_PER_SYMBOL).next_multiple_of(alignment);
+            encoded_data.resize(FSK_BYTES_PER_SYMBOL + rest_len, FRAME_PADDING_BYTE);
+        } else {
+            // Pad encoded data to be a multiple of FSK_BYTES_PER_SYMBOL (3 bytes)
+            // Multi-tone FSK transmits 3 bytes per symbol; the decoder checks the padding
+            let remainder = encoded_data.len() % FSK_BYTES_PER_SYMBOL;
+            if remainder != 0 {
+                let padding = FSK_BYTES_PER_SYMBOL - remainder;
+                encoded_data.resize(encoded_data.len() + padding, FRAME_PADDING_BYTE);
             }
+        }
 
-            if self.source_packets.is_empty() {
-                return None;
+        let key = self.network_key;
+        let (preamble, postamble) = match self.profile {
+            FrameProfile::Telephony => (
+                generate_telephony_preamble(Samples(TELEPHONY_SYNC_SAMPLES), 0.5, key),
+                generate_telephony_postamble(Samples(TELEPHONY_SYNC_SAMPLES), 0.5, key),
+            ),
+            FrameProfile::CodecRobust => (
+                generate_codec_robust_preamble(Samples(CODEC_ROBUST_SYNC_SAMPLES), 0.5, key),
+                generate_codec_robust_postamble(Samples(CODEC_ROBUST_SYNC_SAMPLES), 0.5, key),
+            ),
+            FrameProfile::Narrowband => (
+                generate_narrowband_preamble(Samples(NARROWBAND_SYNC_SAMPLES), 0.5, key),
+                generate_narrowband_postamble(Samples(NARROWBAND_SYNC_SAMPLES), 0.5, key),
+            ),
+            _ => (
+                generate_keyed_preamble(Samples(PREAMBLE_SAMPLES), 0.5, key),
+                generate_keyed_postamble(Samples(POSTAMBLE_SAMPLES), 0.5, key),
+            ),
+        };
+        let (head_gap, tail_gap) = self.sync_gaps.draw(&mut self.sync_gap_state);
+        self.emit_head(&preamble, head_gap, sink);
+
+        // The modulators hand over one symbol at a time
+        let gap_interval = self.symbol_gaps.map(usize::from);
+        let mut symbols = 0;
+        let mut symbol_sink = |symbol: &[f32]| {
+            if gap_interval.is_some_and(|interval| symbols > 0 && symbols % interval == 0) {
+                emit_silence(SYMBOL_GAP_SAMPLES, sink);
             }
-
-            // Restart cycle: emit all source packets again, then new repair packets
-            self.next_source_idx = 0;
-            self.repairs_sent_this_cycle = 0;
+            symbols += 1;
+            sink(symbol);
+        };
+        if let Some((_, symbol_samples, layout)) = fixed_format {
+            self.modulator(symbol_samples, layout).modulate_into(&encoded_data, &mut symbol_sink)?;
+        } else if self.amplitude_bits {
+            let (first, rest) = encoded_data.split_at(FSK_BYTES_PER_SYMBOL);
+            self.fsk.modulate_into(first, &mut symbol_sink)?;
+            self.fsk.modulate_amplitude_shaped_into(rest, &mut symbol_sink)?;
+        } else if custom_layout {
+            let (first, rest) = encoded_data.split_at(FSK_BYTES_PER_SYMBOL);
+            self.fsk.modulate_into(first, &mut symbol_sink)?;
+            self.modulator(FSK_SYMBOL_SAMPLES, layout).modulate_into(rest, &mut symbol_sink)?;
+        } else if self.differential {
+            // The first symbol stays plain so the decoder sees the announcement
+            let (first, rest) = encoded_data.split_at(FSK_BYTES_PER_SYMBOL);
+            self.fsk.modulate_into(first, &mut symbol_sink)?;
+            let mut modulator = self.modulator(FSK_SYMBOL_SAMPLES, layout);
+            modulator.set_differential(true);
+            modulator.modulate_into(rest, &mut symbol_sink)?;
+        } else {
+            self.fsk.modulate_into(&encoded_data, &mut symbol_sink)?;
         }
+        self.emit_tail(&postamble, tail_gap, sink);
+        Ok(())
     }
 
-    fn next_repair_packet(&mut self) -> Option<EncodingPacket> {
-        let block_encoders = self.encoder.get_block_encoders();
-        if block_encoders.is_empty() {
-            return None;
-        }
+    /// Short-profile frame: `[len]` then RS-Light(`[payload][crc16]`), where the
+    /// CRC also covers the unprotected length byte
+    fn encode_short_frame(&mut self, data: &[u8], sink: &mut impl FnMut(&[f32])) -> Result<()> {
+        let len = data.len() as u8;
+        let mut crc_input = vec![len];
+        crc_input.extend_from_slice(data);
+
+        // Shortened RS block, as in standard frames
+        let mode = FecMode::Light;
+        let padding_needed = mode.data_bytes() - data.len() - 2;
+        let mut padded = vec![0u8; padding_needed];
+        padded.extend_from_slice(data);
+        padded.extend_from_slice(&crc16(&crc_input).to_be_bytes());
+        let fec_block = self.fec.encode_with_mode(&padded, mode)?;
+
+        let mut encoded_data = vec![len];
+        encoded_data.extend_from_slice(&fec_block[padding_needed..]);
+        encoded_data.resize(encoded_data.len().next_multiple_of(FSK_BYTES_PER_SYMBOL), FRAME_PADDING_BYTE);
+
+        let preamble = generate_short_preamble(Samples(SHORT_SYNC_SAMPLES), 0.5, self.network_key);
+        let postamble = generate_short_postamble(Samples(SHORT_SYNC_SAMPLES), 0.5, self.network_key);
+        self.emit_head(&preamble, SHORT_SYNC_SILENCE_SAMPLES, sink);
+        self.modulator(SHORT_SYMBOL_SAMPLES, ToneLayout::STANDARD).modulate_into(&encoded_data, sink)?;
+        self.emit_tail(&postamble, SHORT_SYNC_SILENCE_SAMPLES, sink);
+        Ok(())
+    }
 
-        if self.repair_counters.len() < block_encoders.len() {
-            self.repair_counters.resize(block_encoders.len(), 0);
-        }
+    /// silence → fade-in → preamble → gap (the FSK payload follows)
+    fn emit_head(&self, preamble: &[f32], gap_samples: usize, sink: &mut impl FnMut(&[f32])) {
+        // Add silence before preamble for clean frame start
+        emit_silence(self.padding.leading_silence_samples, sink);
 
-        if self.repair_block_cursor >= block_encoders.len() {
-            self.repair_block_cursor = 0;
+        // Optional wake-up ramp so devices that mute the start of playback don't eat the preamble
+        if self.padding.fade_in_samples > 0 {
+            sink(&generate_fade_in(self.padding.fade_in_samples));
         }
 
-        let block_idx = self.repair_block_cursor;
-        self.repair_block_cursor = (self.repair_block_cursor + 1) % block_encoders.len();
+        // Add preamble for synchronization
+        sink(preamble);
 
-        if let Some(counter) = self.repair_counters.get_mut(block_idx) {
-            let packets = block_encoders[block_idx].repair_packets(*counter, 1);
-            if packets.is_empty() {
-                return None;
-            }
-            *counter += 1;
-            return packets.into_iter().next();
-        }
+        // Add silence after preamble for symmetry and clear frame boundaries
+        emit_silence(gap_samples, sink);
+    }
+
+    /// gap → postamble → silence, after the FSK payload
+    fn emit_tail(&self, postamble: &[f32], gap_samples: usize, sink: &mut impl FnMut(&[f32])) {
+        // Add silence before postamble to separate payload from end marker
+        emit_silence(gap_samples, sink);
+
+        // Postamble for frame boundary detection
+        sink(postamble);
+
+        // Add silence after postamble for clean frame end
+        emit_silence(self.padding.trailing_silence_samples, sink);
+    }
+}
 
-        None
+/// Hand `len` zero samples to `sink` in bounded pieces
+fn emit_silence(len: usize, sink: &mut impl FnMut(&[f32])) {
+    const ZEROS: [f32; 1024] = [0.0; 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(ZEROS.len());
+        sink(&ZEROS[..chunk]);
+        remaining -= chunk;
     }
 }
 
 impl Default for EncoderFsk {
     fn default() -> Self {
-        Self::new().unwrap()
+        Self {
+            fsk: FskModulator::new(),
+            fec: FecEncoder,
+            padding: EncoderPadding::default(),
+            fec_mode: None,
+            network_key: None,
+            fountain_melody: None,
+            profile: FrameProfile::Standard,
+            amplitude_bits: false,
+            tone_layout: ToneLayout::STANDARD,
+            dual_band: false,
+            differential: false,
+            symbol_gaps: None,
+            pre_emphasis: None,
+            sync_gaps: SyncGaps::default(),
+            sync_gap_state: SplitMix64::new(0),
+            content_type: None,
+            version_beacon: false,
+            control: false,
+            preamble_cadence: None,
+            safety_limiter: None,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SAMPLE_RATE;
-    use log::info;
-
     #[test]
     fn test_encoder_fsk_basic() {
         let mut encoder = EncoderFsk::new().unwrap();
@@ -392,6 +1255,21 @@ mod tests {
         assert!(samples.len() > PREAMBLE_SAMPLES + POSTAMBLE_SAMPLES);
     }
 
+    #[test]
+    fn test_profile_names_roundtrip() {
+        let profiles = [
+            FrameProfile::Standard,
+            FrameProfile::Short,
+            FrameProfile::Telephony,
+            FrameProfile::CodecRobust,
+            FrameProfile::Narrowband,
+        ];
+        for profile in profiles {
+            assert_eq!(profile.to_string().parse::<FrameProfile>().unwrap(), profile);
+        }
+        assert!(matches!("wideband".parse::<FrameProfile>(), Err(AudioModemError::InvalidConfig(_))));
+    }
+
     #[test]
     fn test_encoder_fsk_empty_data() {
         let mut encoder = EncoderFsk::new().unwrap();
@@ -413,8 +1291,41 @@ mod tests {
     fn test_encoder_fsk_exceeds_max_payload() {
         let mut encoder = EncoderFsk::new().unwrap();
         let data = vec![0u8; MAX_PAYLOAD_SIZE + 1];
-        let result = encoder.encode(&data);
-        assert!(result.is_err());
+        let error = encoder.encode(&data).unwrap_err();
+        assert_eq!(error.to_string(), "Payload of 1025 bytes exceeds the 1024-byte limit (use multi-frame mode)");
+
+        // Too big for a short frame, but not for fountain blocks
+        encoder.set_profile(FrameProfile::Short);
+        let expected = if cfg!(feature = "fountain") { TransmissionMode::Fountain } else { TransmissionMode::MultiFrame };
+        assert!(matches!(
+            encoder.encode(&[0u8; 100]),
+            Err(AudioModemError::PayloadTooLarge { size: 100, max: SHORT_MAX_PAYLOAD_SIZE, suggested_mode }) if suggested_mode == expected
+        ));
+    }
+
+    #[test]
+    fn test_encode_auto_picks_mode_by_size() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut session = crate::TransferSession::new();
+        let mut decoder = crate::DecoderFsk::new().unwrap();
+
+        let small = encoder.encode_auto(b"fits in a frame").unwrap();
+        assert_eq!(small.mode(), TransmissionMode::SingleFrame);
+
+        let data: Vec<u8> = (0..MAX_PAYLOAD_SIZE + 100).map(|i| i as u8).collect();
+        let AutoEncoding::MultiFrame(parts) = encoder.encode_auto(&data).unwrap() else {
+            panic!("expected transfer parts");
+        };
+        assert_eq!(parts.len(), (MAX_PAYLOAD_SIZE + 100).div_ceil(crate::DEFAULT_PART_DATA_SIZE));
+        for part in &parts {
+            session.decode_recording(&mut decoder, part).unwrap();
+        }
+        assert_eq!(session.assemble().unwrap(), data);
+
+        #[cfg(feature = "fountain")]
+        assert_eq!(encoder.encode_auto(&data[..AUTO_SINGLE_FRAME_MAX + 1]).unwrap().mode(), TransmissionMode::Fountain);
+        #[cfg(not(feature = "fountain"))]
+        assert_eq!(encoder.encode_auto(&data[..AUTO_SINGLE_FRAME_MAX + 1]).unwrap().mode(), TransmissionMode::SingleFrame);
     }
 
     #[test]
@@ -435,6 +1346,76 @@ mod tests {
         assert!(postamble_has_signal, "Postamble should contain signal");
     }
 
+    #[test]
+    fn test_encoder_padding_and_fade_in() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Padded";
+        let baseline = encoder.encode(data).unwrap();
+
+        let padding = EncoderPadding::from_ms(Millis(400), Millis(50), Millis(300));
+        assert_eq!(padding.leading_silence_samples, 6400);
+        assert_eq!(padding.fade_in_samples, 4800);
+        encoder.set_padding(padding);
+        let samples = encoder.encode(data).unwrap();
+
+        let extra = (6400 + 4800 + 800) as isize - 2 * SYNC_SILENCE_SAMPLES as isize;
+        assert_eq!(samples.len() as isize, baseline.len() as isize + extra);
+        assert!(samples[..6400].iter().all(|&s| s == 0.0));
+
+        // Ramp rises from silence and ends quietly before the preamble
+        let ramp = &samples[6400..6400 + 4800];
+        let early = ramp[..480].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let peak = ramp.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(early < 0.05 && peak > 0.2 && peak <= FADE_IN_PEAK_AMPLITUDE);
+        assert!(ramp[4790..].iter().all(|s| s.abs() < 0.01));
+
+        let mut decoder = crate::DecoderFsk::new().unwrap();
+        assert_eq!(decoder.decode(&samples).unwrap(), data);
+    }
+
+    #[test]
+    fn test_sync_gaps() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Gaps";
+        let baseline = encoder.encode(data).unwrap();
+
+        encoder.set_sync_gaps(SyncGaps::Fixed(0));
+        let samples = encoder.encode(data).unwrap();
+        assert_eq!(samples.len(), baseline.len() - 2 * SYNC_SILENCE_SAMPLES);
+        assert_eq!(samples[..SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES], baseline[..SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES]);
+
+        // Random gaps vary from frame to frame; airtime sizes the next one and
+        // the same seed repeats the sequence
+        let gaps = SyncGaps::Random { min: 0, max: MAX_SYNC_GAP_SAMPLES, seed: 7 };
+        encoder.set_sync_gaps(gaps);
+        let lengths: Vec<usize> = (0..4)
+            .map(|_| {
+                let airtime = encoder.airtime(data.len(), false).unwrap();
+                let samples = encoder.encode(data).unwrap();
+                assert_eq!(airtime.samples, samples.len());
+                samples.len()
+            })
+            .collect();
+        assert!(lengths.windows(2).any(|pair| pair[0] != pair[1]), "{:?}", lengths);
+        let low = baseline.len() - 2 * SYNC_SILENCE_SAMPLES;
+        assert!(lengths.iter().all(|&len| (low..=low + 2 * MAX_SYNC_GAP_SAMPLES).contains(&len)));
+        encoder.set_sync_gaps(gaps);
+        assert_eq!(encoder.encode(data).unwrap().len(), lengths[0]);
+        assert_eq!(encoder.sync_gaps(), gaps);
+
+        for invalid in [
+            SyncGaps::Fixed(MAX_SYNC_GAP_SAMPLES + 1),
+            SyncGaps::Random { min: 100, max: 50, seed: 0 },
+            SyncGaps::Random { min: 0, max: MAX_SYNC_GAP_SAMPLES + 1, seed: 0 },
+        ] {
+            encoder.set_sync_gaps(invalid);
+            assert!(matches!(encoder.encode(data), Err(crate::error::AudioModemError::InvalidConfig(_))));
+        }
+        encoder.set_sync_gaps(SyncGaps::Fixed(0));
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(encoder.airtime(data.len(), false), Err(crate::error::AudioModemError::InvalidConfig(_))));
+    }
+
     #[test]
     fn test_encoder_fsk_deterministic() {
         let mut encoder1 = EncoderFsk::new().unwrap();
@@ -458,361 +1439,118 @@ mod tests {
     }
 
     #[test]
-    fn test_fountain_stream_basic() {
+    fn test_encode_into_matches_encode() {
         let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Fountain test data";
-
-        let config = FountainConfig {
-            timeout_secs: 1, // Short timeout for test
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
-        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
-
-        // Generate some blocks
-        let blocks: Vec<_> = stream.take(5).collect();
-
-        // Should generate at least some blocks
-        assert!(!blocks.is_empty());
-
-        // Each block should contain preamble + data (no postamble in fountain mode)
-        for block in &blocks {
-            assert!(block.len() > PREAMBLE_SAMPLES);
+        encoder.set_padding(EncoderPadding::from_ms(Millis(500), Millis(200), Millis(100)));
+        for (profile, amplitude_bits) in [
+            (FrameProfile::Standard, false),
+            (FrameProfile::Standard, true),
+            (FrameProfile::Short, false),
+        ] {
+            encoder.set_profile(profile);
+            encoder.set_amplitude_bits(amplitude_bits);
+            let expected = encoder.encode(b"streamed in pieces").unwrap();
+
+            let mut chunks = Vec::new();
+            encoder.encode_into(b"streamed in pieces", |chunk| chunks.push(chunk.to_vec())).unwrap();
+            assert!(chunks.iter().all(|chunk| !chunk.is_empty() && chunk.len() <= PREAMBLE_SAMPLES));
+            assert_eq!(chunks.concat(), expected, "{:?} amplitude_bits={}", profile, amplitude_bits);
         }
-    }
-
-    #[test]
-    fn test_fountain_stream_timeout() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Timeout test";
-
-        let config = FountainConfig {
-            timeout_secs: 1,
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
-        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
-
-        // Count blocks generated within timeout
-        let block_count = stream.count();
-
-        // Should generate some blocks but eventually stop
-        assert!(block_count > 0);
-        info!("Generated {} blocks in 1 second", block_count);
-    }
-
-    #[test]
-    fn test_fountain_default_config() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Default config test";
-
-        // Should work with default config
-        let mut stream = encoder.encode_fountain(data, None).unwrap();
-
-        // Should generate at least one block
-        assert!(stream.next().is_some());
-    }
-
-    #[test]
-    fn test_fountain_respects_max_samples() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Max samples test";
-
-        let config = FountainConfig {
-            timeout_secs: 10, // Long timeout
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
-        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
-        let max_samples = stream.max_samples;
-
-        // Generate all blocks and verify total doesn't greatly exceed max_samples
-        // Note: May exceed by one block since we emit complete blocks without truncation
-        let total: usize = stream
-            .map(|block| block.len())
-            .sum();
-
-        // Allow some overshoot - one additional block beyond max_samples is acceptable
-        // since we emit complete blocks and never truncate mid-block
-        let max_allowed = max_samples + (50 * crate::fsk::FSK_SYMBOL_SAMPLES);
-
-        assert!(
-            total <= max_allowed,
-            "Total samples {} far exceeds max_samples {} with allowance ({} max)",
-            total,
-            max_samples,
-            max_allowed
-        );
-    }
-
-    #[test]
-    fn test_fountain_block_size_exceeds_u16_max() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Block size test";
-
-        // Create a config with block_size that exceeds u16::MAX
-        let config = FountainConfig {
-            timeout_secs: 1,
-            block_size: u16::MAX as usize + 1, // 65536
-            repair_blocks_ratio: 0.5,
-        };
-
-        let result = encoder.encode_fountain(data, Some(config));
-
-        // Should return an error due to invalid block_size
-        assert!(result.is_err());
-
-        // Verify the error message mentions the issue
-        if let Err(err) = result {
-            let err_msg = format!("{:?}", err);
-            assert!(
-                err_msg.contains("block_size") || err_msg.contains("exceeds"),
-                "Error should mention block_size or exceeds: {}",
-                err_msg
-            );
-        }
-    }
-
-    #[test]
-    fn test_fountain_block_size_at_u16_max() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Block size at max test";
-
-        // Create a config with block_size exactly at u16::MAX
-        let config = FountainConfig {
-            timeout_secs: 1,
-            block_size: u16::MAX as usize,
-            repair_blocks_ratio: 0.5,
-        };
-
-        // Should succeed with u16::MAX
-        let result = encoder.encode_fountain(data, Some(config));
-        assert!(result.is_ok(), "Should accept block_size at u16::MAX");
-    }
-
-    #[test]
-    fn test_fountain_uses_sample_rate_constant() {
-        let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Sample rate test";
-
-        let timeout_secs = 5;
-        let config = FountainConfig {
-            timeout_secs,
-            block_size: 32,
-            repair_blocks_ratio: 0.5,
-        };
-
-        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
 
-        // Verify that max_samples is calculated using crate::SAMPLE_RATE
-        let expected_max_samples = timeout_secs as usize * SAMPLE_RATE;
-        assert_eq!(
-            stream.max_samples, expected_max_samples,
-            "max_samples should be calculated as timeout_secs * SAMPLE_RATE ({} * {} = {})",
-            timeout_secs, SAMPLE_RATE, expected_max_samples
-        );
+        // Nothing reaches the sink when encoding fails
+        let mut emitted = 0;
+        assert!(encoder.encode_into(&[0u8; SHORT_MAX_PAYLOAD_SIZE + 1], |chunk| emitted += chunk.len()).is_err());
+        assert_eq!(emitted, 0);
+        encoder.set_profile(FrameProfile::Standard);
+        encoder.set_amplitude_bits(true);
+        assert!(encoder.encode_into(&[0u8; MAX_PAYLOAD_SIZE + 1], |chunk| emitted += chunk.len()).is_err());
+        encoder.set_tone_layout(ToneLayout::new(4, 16).unwrap());
+        assert!(encoder.encode_into(b"streamed in pieces", |chunk| emitted += chunk.len()).is_err());
+        assert_eq!(emitted, 0);
     }
 
     #[test]
-    fn test_fountain_repair_packets_have_unique_data() {
+    fn test_encode_batch_matches_encode() {
         let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Unique repair packet test - verify different packets have different encoded data";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: FOUNTAIN_BLOCK_SIZE,
-            repair_blocks_ratio: 1.0, // 100% repair overhead for more repair packets
-        };
-
-        // Test by checking the underlying RaptorQ packets directly
-        // This is more reliable than trying to extract from FSK-modulated audio
-        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
-
-        let mut packet_serializations: Vec<Vec<u8>> = Vec::new();
-
-        // Collect first few packets to analyze
-        for _ in 0..20 {
-            if let Some(packet) = stream.select_next_packet() {
-                let serialized = packet.serialize();
-                packet_serializations.push(serialized);
-            } else {
-                break;
+        encoder.set_dual_band(true);
+        let mut payloads: Vec<Vec<u8>> = (0..7).map(|i| format!("COUPON-{:04}", i * 37).into_bytes()).collect();
+        payloads.insert(3, vec![0u8; MAX_PAYLOAD_SIZE + 1]);
+
+        for jobs in [0, 1, 3, 16] {
+            let batch = encoder.encode_batch(&payloads, jobs);
+            assert_eq!(batch.len(), payloads.len());
+            for (payload, result) in payloads.iter().zip(batch) {
+                match (result, encoder.encode(payload)) {
+                    (Ok(samples), Ok(expected)) => assert_eq!(samples, expected, "{} jobs", jobs),
+                    (Err(_), Err(_)) => assert_eq!(payload.len(), MAX_PAYLOAD_SIZE + 1),
+                    (result, expected) => panic!("{:?} vs {:?}", result.map(|s| s.len()), expected.map(|s| s.len())),
+                }
             }
         }
-
-        assert!(packet_serializations.len() >= 3, "Should collect at least 3 packet serializations (got {})", packet_serializations.len());
-
-        // Count unique serialized packets
-        let mut unique_packets = std::collections::HashSet::new();
-        for pkt in &packet_serializations {
-            unique_packets.insert(pkt.clone());
-        }
-
-        // With source packets + repair packets, we should see at least 2 unique patterns
-        assert!(
-            unique_packets.len() >= 2,
-            "Should see at least 2 unique serialized packets (source + repair), got {}",
-            unique_packets.len()
-        );
-
-        info!("Generated {} unique serialized packets from {} total", unique_packets.len(), packet_serializations.len());
+        assert!(encoder.encode_batch(&[], 4).is_empty());
     }
 
     #[test]
-    fn test_fountain_source_packets_are_identical() {
+    fn test_airtime_matches_encode() {
         let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Source packet identity test";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: FOUNTAIN_BLOCK_SIZE,
-            repair_blocks_ratio: 0.0, // Only source packets, no repairs
-        };
-
-        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
-
-        // With repair_ratio 0.0, select_next_packet should return source packets from the same set
-        let mut source_packets: Vec<Vec<u8>> = Vec::new();
-
-        for _ in 0..10 {
-            if let Some(packet) = stream.select_next_packet() {
-                source_packets.push(packet.serialize());
-            } else {
-                break;
+        encoder.set_padding(EncoderPadding::from_ms(Millis(300), Millis(100), Millis(50)));
+        let configs = [
+            (FrameProfile::Standard, false, ToneLayout::STANDARD, None, None),
+            (FrameProfile::Standard, true, ToneLayout::STANDARD, Some(5), None),
+            (FrameProfile::Standard, false, ToneLayout::new(3, 8).unwrap(), None, Some(FecMode::custom(255, 191).unwrap())),
+            (FrameProfile::Short, false, ToneLayout::STANDARD, None, None),
+            (FrameProfile::Telephony, false, ToneLayout::STANDARD, Some(4), None),
+            (FrameProfile::CodecRobust, false, ToneLayout::STANDARD, None, None),
+            (FrameProfile::Narrowband, false, ToneLayout::STANDARD, Some(6), None),
+            (FrameProfile::Standard, false, ToneLayout::DUAL_BAND, Some(7), None),
+        ];
+        for (profile, amplitude_bits, layout, gaps, fec_mode) in configs {
+            encoder.set_profile(profile);
+            encoder.set_amplitude_bits(amplitude_bits);
+            encoder.set_tone_layout(layout);
+            encoder.set_symbol_gaps(gaps);
+            encoder.set_fec_mode(fec_mode);
+            for len in [0, 17, 64, 300, MAX_PAYLOAD_SIZE] {
+                let data: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+                match (encoder.airtime(len, false), encoder.encode(&data)) {
+                    (Ok(airtime), Ok(samples)) => {
+                        assert_eq!(airtime.samples, samples.len(), "{:?} {} bytes", profile, len)
+                    }
+                    (Err(_), Err(_)) => assert!(profile == FrameProfile::Short && len > SHORT_MAX_PAYLOAD_SIZE),
+                    (airtime, samples) => panic!("{:?} {} bytes: {:?} vs {:?}", profile, len, airtime, samples.map(|s| s.len())),
+                }
             }
         }
 
-        assert!(source_packets.len() >= 2, "Should get at least 2 source packets (got {})", source_packets.len());
-
-        // With repair_ratio 0.0, it should cycle through source packets repeatedly
-        // So the first few source packets should repeat
-        let pkt1 = &source_packets[0];
-        let pkt2 = &source_packets[1];
-
-        // Check if we have the same packet (indicating cycling through source)
-        // OR if they're different (indicating multiple source blocks)
-        // Either is valid - just verify the mechanism works
-
-        if source_packets.len() >= stream.source_packets.len() * 2 {
-            // If we got at least 2 full cycles, first packet should repeat somewhere
-            let found_repeat = source_packets.iter().skip(stream.source_packets.len())
-                .any(|pkt| pkt == pkt1);
-            assert!(found_repeat, "With repair_ratio=0, source packets should repeat in cycles");
-        } else {
-            // Not enough packets to verify cycling yet, just verify we got packets
-            assert!(!source_packets.is_empty(), "Should have generated source packets");
-        }
-
-        info!("Generated {} source packets, {}unique serializations", source_packets.len(),
-            source_packets.iter().collect::<std::collections::HashSet<_>>().len());
-    }
-
-    #[test]
-    fn test_fountain_packets_differ_from_metadata() {
+        // 300 bytes in the automatic Full mode: two shortened RS(255, 223) blocks
         let mut encoder = EncoderFsk::new().unwrap();
-        let data = b"Test that packet payloads are different, not just metadata";
-
-        let config = FountainConfig {
-            timeout_secs: 30,
-            block_size: FOUNTAIN_BLOCK_SIZE,
-            repair_blocks_ratio: 1.0,
-        };
-
-        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
-
-        // Collect serialized packets to directly compare their payloads
-        let mut packets: Vec<Vec<u8>> = Vec::new();
-
-        for _ in 0..30 {
-            if let Some(packet) = stream.select_next_packet() {
-                packets.push(packet.serialize());
-            } else {
-                break;
-            }
-        }
-
-        assert!(packets.len() >= 5, "Should collect at least 5 packets (got {})", packets.len());
-
-        // Count unique packet serializations
-        let mut unique_packets = std::collections::HashSet::new();
-        for pkt in &packets {
-            unique_packets.insert(pkt.clone());
-        }
-
-        // Key assertion: With repair packets, we should see multiple unique serializations
-        // This proves that blocks contain different data, not just different metadata
-        //
-        // - Source packets (K packets): may repeat in cycles
-        // - Repair packets (ESI >= K): each counter value produces different repair packets
-        //
-        // So we should see at least: 1 unique source pattern + 2+ unique repair patterns = 3+ unique
-        // Or at minimum 2 unique (source repeated, then at least 1 repair different)
-        assert!(
-            unique_packets.len() >= 2,
-            "Should see at least 2 unique packet serializations with repair packets enabled. Got {}. This suggests packets might not have unique data payloads.",
-            unique_packets.len()
-        );
-
-        // Count how many are likely repair packets (come after source packets)
-        let num_source_expected = stream.source_packets.len();
-        let num_repairs_generated = packets.len().saturating_sub(num_source_expected);
-
-        info!(
-            "Generated {} total packets: ~{} source, ~{} repairs. Found {} unique serializations",
-            packets.len(), num_source_expected, num_repairs_generated, unique_packets.len()
-        );
+        let airtime = encoder.airtime(300, true).unwrap();
+        assert_eq!((airtime.fec_mode, airtime.rs_blocks), (FecMode::Full, 2));
+        assert_eq!(airtime.samples, encoder.encode_with_timestamp(&[0u8; 300], 1).unwrap().len());
+        assert_eq!(airtime.duration_ms(), (airtime.samples * 1000 / SAMPLE_RATE) as u64);
+        assert_eq!(airtime.duration().as_millis() as u64, airtime.duration_ms());
+        assert!(matches!(
+            encoder.airtime(MAX_PAYLOAD_SIZE + 1, false),
+            Err(AudioModemError::PayloadTooLarge { size: 1025, max: MAX_PAYLOAD_SIZE, suggested_mode: TransmissionMode::MultiFrame })
+        ));
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(encoder.airtime(8, true), Err(crate::error::AudioModemError::InvalidConfig(_))));
     }
 
     #[test]
-    fn test_fountain_repair_counter_increments() {
+    fn test_preamble_cadence_pads_frames() {
         let mut encoder = EncoderFsk::new().unwrap();
-        // Use longer data to ensure multiple source packets
-        let data = b"Repair counter increment test with enough data to have multiple source blocks";
-
-        let config = FountainConfig {
-            timeout_secs: 5,
-            block_size: FOUNTAIN_BLOCK_SIZE,
-            repair_blocks_ratio: 1.0,
-        };
-
-        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
-
-        // Verify repair_counters are initialized
-        let initial_counters = stream.repair_counters.clone();
-        assert!(!initial_counters.is_empty(), "Should have repair counters for source blocks");
-
-        let num_source_packets = stream.source_packets.len();
-        let num_blocks = stream.repair_counters.len();
-
-        // Generate enough blocks to ensure we get into repair packet generation
-        // With repair_ratio 1.0, we should get source_packets + repairs_per_cycle packets per cycle
-        let block_count = num_source_packets * 2 + 20;
-
-        for _ in 0..block_count {
-            if stream.next().is_none() {
-                break;
-            }
-        }
-
-        // After generation, repair counters should have been incremented (assuming we generated repair packets)
-        let final_counters = stream.repair_counters.clone();
-
-        // If we have multiple blocks and repair ratio > 0, at least some counters should be > 0
-        if num_blocks > 1 && stream.repairs_per_cycle > 0 {
-            let incremented_count = final_counters.iter().filter(|c| **c > 0).count();
-            assert!(
-                incremented_count > 0,
-                "With {} blocks and repair_ratio=1.0, at least one counter should be incremented, got: {:?}",
-                num_blocks, final_counters
-            );
-        }
-
-        info!(
-            "Repair counters after generation: {:?} (num_blocks={}, repairs_per_cycle={})",
-            final_counters, num_blocks, stream.repairs_per_cycle
-        );
+        let unpadded = encoder.encode(b"beacon").unwrap();
+        encoder.set_preamble_cadence(Some(Millis(3000)));
+        let samples = encoder.encode(b"beacon").unwrap();
+        assert_eq!(samples.len(), 3 * SAMPLE_RATE);
+        assert_eq!(&samples[..unpadded.len()], &unpadded[..]);
+        assert!(samples[unpadded.len()..].iter().all(|&s| s == 0.0));
+        assert_eq!(encoder.airtime(6, false).unwrap().samples, samples.len());
+
+        encoder.set_profile(FrameProfile::Short);
+        assert_eq!(encoder.encode(b"beacon").unwrap().len(), 3 * SAMPLE_RATE);
+        encoder.set_profile(FrameProfile::Standard);
+        assert!(matches!(encoder.airtime(MAX_PAYLOAD_SIZE, false), Err(AudioModemError::InvalidConfig(_))));
     }
 }
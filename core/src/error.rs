@@ -1,6 +1,7 @@
+use crate::encoder_fsk::TransmissionMode;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum AudioModemError {
     #[error("Failed to detect preamble")]
     PreambleNotFound,
@@ -14,6 +15,11 @@ pub enum AudioModemError {
     #[error("CRC mismatch in frame payload")]
     PayloadCrcMismatch,
 
+    /// Reed-Solomon decoding succeeded but the payload CRC did not match;
+    /// only returned when `DecoderFsk::set_salvage_unverified` is enabled
+    #[error("CRC mismatch in frame payload ({} unverified bytes salvaged)", payload.len())]
+    CrcMismatch { payload: Vec<u8> },
+
     #[error("Reed-Solomon decode failure")]
     FecDecodeFailure,
 
@@ -23,12 +29,34 @@ pub enum AudioModemError {
     #[error("FFT error: {0}")]
     FftError(String),
 
+    #[error("Correlation backend error: {0}")]
+    CorrelationBackend(String),
+
     #[error("Invalid input size")]
     InvalidInputSize,
 
+    /// More bytes than one frame carries; `suggested_mode` is a mode that
+    /// carries them, fountain up to `MAX_PAYLOAD_SIZE` bytes, else multi-frame
+    #[error("Payload of {size} bytes exceeds the {max}-byte limit (use {suggested_mode} mode)")]
+    PayloadTooLarge { size: usize, max: usize, suggested_mode: TransmissionMode },
+
     #[error("Insufficient data")]
     InsufficientData,
 
+    /// A candidate or frame failed a gate of `DecoderFsk::set_squelch`
+    #[error("Rejected by the receiver squelch")]
+    Squelched,
+
+    /// A frame decoded but no postamble of the network key followed it
+    /// (see `DecoderFsk::set_strict_network_key`)
+    #[error("Sync signals do not match the network key")]
+    WrongNetwork,
+
+    /// Bytes after a frame that should be `FRAME_PADDING_BYTE` are not
+    /// (see `DecoderFsk::set_strict_padding`)
+    #[error("{bytes} unexpected bytes in the frame padding")]
+    UnexpectedPadding { bytes: usize },
+
     #[error("Frame number mismatch")]
     FrameNumberMismatch,
 
@@ -41,6 +69,19 @@ pub enum AudioModemError {
     #[error("Fountain decode failure")]
     FountainDecodeFailure,
 
+    #[error("Invalid fountain packet: {0}")]
+    InvalidPacket(String),
+
+    #[error("Invalid transfer part: {0}")]
+    InvalidTransferPart(String),
+
+    #[error("WAV error: {0}")]
+    WavError(String),
+
+    /// A typed payload could not be serialized or deserialized (`serde-payload` feature)
+    #[error("Payload encoding error: {0}")]
+    PayloadEncoding(String),
+
     #[error("Operation timeout")]
     Timeout,
 }
@@ -0,0 +1,63 @@
+//! Progress events from the streaming and fountain decoders
+//!
+//! Give the decoder a channel with [`DecoderFsk::set_event_sender`](crate::DecoderFsk::set_event_sender)
+//! and a UI can follow a reception as it happens (preamble found, block N of M,
+//! CRC failures) instead of polling the decoder stats.
+
+use crate::error::AudioModemError;
+use crate::SAMPLE_RATE;
+
+/// Something the decoder found in the stream
+///
+/// `position` is the stream position in samples of the preamble the event
+/// belongs to.
+#[derive(Debug, Clone)]
+pub enum DecodeEvent {
+    /// A frame or fountain block preamble
+    PreambleDetected { position: usize },
+    /// A frame decoded (streaming decoder)
+    FrameDecoded { position: usize, payload_len: usize },
+    /// A frame was dropped after `max_frame_samples` without decoding (streaming decoder)
+    FrameFailed { position: usize, error: AudioModemError },
+    /// A fountain block passed its CRC; `received` valid blocks of the current
+    /// transmission so far, about `needed` for the payload
+    BlockDecoded { position: usize, received: u32, needed: u32 },
+    /// A fountain block failed its CRC
+    BlockCrcFailed { position: usize },
+    /// The fountain sender switched to another payload or block size
+    StreamRestarted { position: usize },
+    /// The fountain payload was recovered
+    FountainRecovered { position: usize, payload_len: usize },
+}
+
+impl DecodeEvent {
+    pub fn position(&self) -> usize {
+        match *self {
+            DecodeEvent::PreambleDetected { position }
+            | DecodeEvent::FrameDecoded { position, .. }
+            | DecodeEvent::FrameFailed { position, .. }
+            | DecodeEvent::BlockDecoded { position, .. }
+            | DecodeEvent::BlockCrcFailed { position }
+            | DecodeEvent::StreamRestarted { position }
+            | DecodeEvent::FountainRecovered { position, .. } => position,
+        }
+    }
+
+    /// Stream position in seconds
+    pub fn seconds(&self) -> f64 {
+        self.position() as f64 / SAMPLE_RATE as f64
+    }
+
+    /// Short name of the event kind, e.g. for a JS `type` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DecodeEvent::PreambleDetected { .. } => "preamble",
+            DecodeEvent::FrameDecoded { .. } => "frame",
+            DecodeEvent::FrameFailed { .. } => "frame_failed",
+            DecodeEvent::BlockDecoded { .. } => "block",
+            DecodeEvent::BlockCrcFailed { .. } => "block_crc_failed",
+            DecodeEvent::StreamRestarted { .. } => "restart",
+            DecodeEvent::FountainRecovered { .. } => "recovered",
+        }
+    }
+}
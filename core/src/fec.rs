@@ -1,16 +1,27 @@
 use crate::error::{AudioModemError, Result};
-use crate::{RS_DATA_BYTES, RS_ECC_BYTES, RS_TOTAL_BYTES};
-use reed_solomon_simd::{ReedSolomonDecoder, ReedSolomonEncoder};
+use crate::reed_solomon;
+use crate::{RS_DATA_BYTES, RS_TOTAL_BYTES};
+
+/// Header `fec_mode` bit marking a custom code; the low 7 bits hold parity / 2
+const FEC_MODE_CUSTOM_FLAG: u8 = 0x80;
+
+/// Smallest data portion a custom code may have (the first block must hold the frame header)
+pub const MIN_CUSTOM_DATA_BYTES: usize = 16;
 
 /// FEC mode determines the level of error correction
+///
+/// The presets are RS(223 + parity, 223). `Custom` is a full-length
+/// RS(255, 255 - parity) code for channels that need more than 32 parity bytes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FecMode {
     /// Minimal FEC: 8 parity bytes (for payloads < 20 bytes)
-    Light = 8,
+    Light,
     /// Medium FEC: 16 parity bytes (for payloads 20-50 bytes)
-    Medium = 16,
+    Medium,
     /// Full FEC: 32 parity bytes (for payloads > 50 bytes)
-    Full = 32,
+    Full,
+    /// RS(255, 255 - parity); build with [`FecMode::custom`]
+    Custom { parity: u8 },
 }
 
 impl FecMode {
@@ -25,29 +36,119 @@ impl FecMode {
         }
     }
 
+    /// Mode for an RS(n, k) code
+    ///
+    /// The three presets are returned for their own parameters; any other code
+    /// must be full-length (n = 255) with an even number of parity bytes, and
+    /// keep at least `MIN_CUSTOM_DATA_BYTES` data bytes, e.g. RS(255, 191).
+    pub fn custom(n: usize, k: usize) -> Result<Self> {
+        match (n, k) {
+            (231, RS_DATA_BYTES) => return Ok(FecMode::Light),
+            (239, RS_DATA_BYTES) => return Ok(FecMode::Medium),
+            (RS_TOTAL_BYTES, RS_DATA_BYTES) => return Ok(FecMode::Full),
+            _ => {}
+        }
+        if n != RS_TOTAL_BYTES {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "RS({}, {}): custom codes must have n = {}",
+                n, k, RS_TOTAL_BYTES
+            )));
+        }
+        if k < MIN_CUSTOM_DATA_BYTES || k >= n {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "RS({}, {}): k must be in {}..{}",
+                n, k, MIN_CUSTOM_DATA_BYTES, n
+            )));
+        }
+        let parity = n - k;
+        if !parity.is_multiple_of(2) {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "RS({}, {}): parity bytes (n - k) must be even",
+                n, k
+            )));
+        }
+        Ok(FecMode::Custom { parity: parity as u8 })
+    }
+
+    /// Every `Custom` code, least parity first
+    pub(crate) fn custom_modes() -> impl Iterator<Item = FecMode> {
+        (2..=RS_TOTAL_BYTES - MIN_CUSTOM_DATA_BYTES)
+            .step_by(2)
+            .filter_map(|parity| FecMode::custom(RS_TOTAL_BYTES, RS_TOTAL_BYTES - parity).ok())
+            .filter(|mode| matches!(mode, FecMode::Custom { .. }))
+    }
+
     /// Get parity bytes for this mode
     pub fn parity_bytes(&self) -> usize {
-        *self as usize
+        match self {
+            FecMode::Light => 8,
+            FecMode::Medium => 16,
+            FecMode::Full => 32,
+            FecMode::Custom { parity } => *parity as usize,
+        }
     }
 
-    /// Convert from byte value
+    /// Data bytes per RS block (k)
+    pub fn data_bytes(&self) -> usize {
+        match self {
+            FecMode::Custom { parity } => RS_TOTAL_BYTES - *parity as usize,
+            _ => RS_DATA_BYTES,
+        }
+    }
+
+    /// Full RS block length (n)
+    pub fn block_bytes(&self) -> usize {
+        self.data_bytes() + self.parity_bytes()
+    }
+
+    /// Convert from the header byte value
     pub fn from_u8(value: u8) -> Result<Self> {
         match value {
             8 => Ok(FecMode::Light),
             16 => Ok(FecMode::Medium),
             32 => Ok(FecMode::Full),
+            _ if value & FEC_MODE_CUSTOM_FLAG != 0 => {
+                let parity = (value & !FEC_MODE_CUSTOM_FLAG) as usize * 2;
+                FecMode::custom(RS_TOTAL_BYTES, RS_TOTAL_BYTES.saturating_sub(parity))
+            }
             _ => Err(AudioModemError::InvalidConfig("Invalid FEC mode".to_string())),
         }
     }
 
-    /// Convert to byte value
+    /// Convert to the header byte value: parity bytes for presets,
+    /// 0x80 | parity / 2 for custom codes
     pub fn to_u8(&self) -> u8 {
-        *self as u8
+        match self {
+            FecMode::Custom { parity } => FEC_MODE_CUSTOM_FLAG | (parity / 2),
+            _ => self.parity_bytes() as u8,
+        }
+    }
+}
+
+impl std::fmt::Display for FecMode {
+    /// The code as `RS(n, k)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RS({}, {})", self.block_bytes(), self.data_bytes())
+    }
+}
+
+impl std::str::FromStr for FecMode {
+    type Err = AudioModemError;
+
+    /// An RS code as "N,K", checked like [`FecMode::custom`]
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = |reason: String| AudioModemError::InvalidConfig(format!("RS code '{}': {}", value, reason));
+        let (n, k) = value.split_once(',').ok_or_else(|| invalid("expected N,K".to_string()))?;
+        let n = n.trim().parse().map_err(|_| invalid(format!("invalid N: {}", n)))?;
+        let k = k.trim().parse().map_err(|_| invalid(format!("invalid K: {}", k)))?;
+        FecMode::custom(n, k)
     }
 }
 
+#[derive(Clone, Default)]
 pub struct FecEncoder;
 
+#[derive(Default)]
 pub struct FecDecoder;
 
 impl FecEncoder {
@@ -63,49 +164,15 @@ impl FecEncoder {
     /// Encode data with variable Reed-Solomon FEC based on mode
     /// Returns: data + parity bytes (not the full 255 bytes)
     pub fn encode_with_mode(&self, data: &[u8], mode: FecMode) -> Result<Vec<u8>> {
-        if data.len() > RS_DATA_BYTES {
+        let data_bytes = mode.data_bytes();
+        if data.len() > data_bytes {
             return Err(AudioModemError::InvalidInputSize);
         }
 
-        let parity_bytes = mode.parity_bytes();
-        let shard_size = 2;
-
-        // Pad data to a multiple of shard_size for encoder
-        let padded_len = ((RS_DATA_BYTES + shard_size - 1) / shard_size) * shard_size;
-        let mut padded_data = vec![0u8; padded_len];
-        padded_data[..data.len()].copy_from_slice(data);
-
-        let num_original = padded_len / shard_size;
-        let num_recovery = (parity_bytes + shard_size - 1) / shard_size;
-
-        let mut encoder = ReedSolomonEncoder::new(num_original, num_recovery, shard_size)
-            .map_err(|_| AudioModemError::InvalidConfig("Failed to create RS encoder".to_string()))?;
-
-        // Add original shards (each exactly shard_size bytes)
-        for i in 0..num_original {
-            let start = i * shard_size;
-            let end = start + shard_size;
-            let shard = &padded_data[start..end];
-            encoder.add_original_shard(shard)
-                .map_err(|_| AudioModemError::FecError("Failed to add original shard".to_string()))?;
-        }
-
-        let result = encoder.encode()
-            .map_err(|_| AudioModemError::FecError("Failed to encode with RS".to_string()))?;
-
-        // Build output: first RS_DATA_BYTES of padded data + parity bytes
-        let mut encoded = vec![0u8; RS_DATA_BYTES + parity_bytes];
-        encoded[..RS_DATA_BYTES].copy_from_slice(&padded_data[..RS_DATA_BYTES]);
-
-        // Collect recovery shards
-        let mut parity_offset = RS_DATA_BYTES;
-        for recovery_shard in result.recovery_iter().take(num_recovery) {
-            let remaining = RS_DATA_BYTES + parity_bytes - parity_offset;
-            let to_copy = std::cmp::min(recovery_shard.len(), remaining);
-            encoded[parity_offset..parity_offset + to_copy].copy_from_slice(&recovery_shard[..to_copy]);
-            parity_offset += to_copy;
-        }
-
+        let mut encoded = vec![0u8; data_bytes];
+        encoded[..data.len()].copy_from_slice(data);
+        let parity = reed_solomon::encode_parity(&encoded, mode.parity_bytes());
+        encoded.extend_from_slice(&parity);
         Ok(encoded)
     }
 }
@@ -123,93 +190,43 @@ impl FecDecoder {
         self.decode_with_mode(encoded, FecMode::Full)
     }
 
-    /// Decode data with variable Reed-Solomon FEC based on mode
+    /// Decode data with variable Reed-Solomon FEC based on mode, correcting
+    /// up to `parity_bytes / 2` byte errors
     pub fn decode_with_mode(&self, encoded: &[u8], mode: FecMode) -> Result<Vec<u8>> {
-        let parity_bytes = mode.parity_bytes();
-        let total_bytes = RS_DATA_BYTES + parity_bytes;
+        self.correct(encoded, mode, &[]).map(|(data, _)| data)
+    }
 
-        if encoded.len() != total_bytes {
+    /// Decode a block of `mode`, returning the data and the number of bytes,
+    /// data or parity, the code corrected
+    ///
+    /// The bytes at `erasures` (positions in the block, data first) are
+    /// unreliable; `errors * 2 + erasures <= parity_bytes` is correctable.
+    pub fn correct(&self, encoded: &[u8], mode: FecMode, erasures: &[usize]) -> Result<(Vec<u8>, usize)> {
+        if encoded.len() != mode.block_bytes() {
             return Err(AudioModemError::InvalidInputSize);
         }
 
-        // When we have all shards (no errors), just return the original data
-        // The parity bytes are only needed for error recovery
-        let mut decoded = vec![0u8; RS_DATA_BYTES];
-        decoded.copy_from_slice(&encoded[..RS_DATA_BYTES]);
-        Ok(decoded)
+        let mut codeword = encoded.to_vec();
+        let corrected = reed_solomon::correct(&mut codeword, mode.parity_bytes(), erasures)
+            .ok_or_else(|| AudioModemError::FecError("Too many errors to correct".to_string()))?;
+        codeword.truncate(mode.data_bytes());
+        Ok((codeword, corrected))
     }
 
-    /// Attempt to repair corrupted data by marking known bad shards as erasures
+    /// Attempt to repair corrupted data by marking known bad bytes as erasures
     pub fn decode_with_errors(&self, encoded: &[u8], error_positions: &[usize]) -> Result<Vec<u8>> {
         if encoded.len() != RS_TOTAL_BYTES {
             return Err(AudioModemError::InvalidInputSize);
         }
-
-        let shard_size = 2;
-        let padded_len = ((RS_DATA_BYTES + shard_size - 1) / shard_size) * shard_size;
-        let num_original = padded_len / shard_size;
-        let num_recovery = (RS_ECC_BYTES + shard_size - 1) / shard_size;
-
-        // Prepare padded buffers
-        let mut padded_data = vec![0u8; padded_len];
-        padded_data[..RS_DATA_BYTES].copy_from_slice(&encoded[..RS_DATA_BYTES]);
-
-        let mut padded_recovery = vec![0u8; num_recovery * shard_size];
-        let recovery_len = std::cmp::min(RS_ECC_BYTES, padded_recovery.len());
-        padded_recovery[..recovery_len].copy_from_slice(&encoded[RS_DATA_BYTES..RS_DATA_BYTES + recovery_len]);
-
-        let mut decoder = ReedSolomonDecoder::new(num_original, num_recovery, shard_size)
-            .map_err(|_| AudioModemError::InvalidConfig("Failed to create RS decoder".to_string()))?;
-
-        // Add available original shards (skip error positions)
-        for i in 0..num_original {
-            let start = i * shard_size;
-            let end = start + shard_size;
-            // Check if any byte in this shard is in error_positions
-            let has_error = (start..end).any(|pos| error_positions.contains(&pos));
-            if !has_error {
-                let shard = &padded_data[start..end];
-                decoder.add_original_shard(i, shard)
-                    .map_err(|_| AudioModemError::FecError("Failed to add original shard".to_string()))?;
-            }
-        }
-
-        // Add all recovery shards
-        for i in 0..num_recovery {
-            let start = i * shard_size;
-            let end = start + shard_size;
-            let shard = &padded_recovery[start..end];
-            decoder.add_recovery_shard(i, shard)
-                .map_err(|_| AudioModemError::FecError("Failed to add recovery shard".to_string()))?;
-        }
-
-        let result = decoder.decode()
-            .map_err(|_| AudioModemError::FecError("Failed to reconstruct corrupted data".to_string()))?;
-
-        // Extract restored original shards in order
-        let mut decoded = vec![0u8; RS_DATA_BYTES];
-        for (idx, shard) in result.restored_original_iter() {
-            let start = idx * shard_size;
-            let end = std::cmp::min(start + shard_size, RS_DATA_BYTES);
-            if start < RS_DATA_BYTES {
-                let len = end - start;
-                decoded[start..end].copy_from_slice(&shard[..len]);
-            }
-        }
-
-        Ok(decoded)
+        self.decode_with_erasures(encoded, FecMode::Full, error_positions)
     }
-}
 
-impl Default for FecEncoder {
-    fn default() -> Self {
-        Self::new().unwrap()
-    }
-}
-
-impl Default for FecDecoder {
-    fn default() -> Self {
-        Self::new().unwrap()
+    /// Decode a block of `mode` whose bytes at `erasures` (positions in the
+    /// block, data first) are unreliable, rebuilding them from the parity
+    ///
+    /// Each erasure costs one parity byte, each unflagged error two.
+    pub fn decode_with_erasures(&self, encoded: &[u8], mode: FecMode, erasures: &[usize]) -> Result<Vec<u8>> {
+        self.correct(encoded, mode, erasures).map(|(data, _)| data)
     }
 }
 
@@ -372,6 +389,67 @@ mod tests {
         assert_eq!(&decoded[..15], data);
     }
 
+    #[test]
+    fn test_decode_with_erasures() {
+        let encoder = FecEncoder::new().unwrap();
+        let decoder = FecDecoder::new().unwrap();
+        let data: Vec<u8> = (0..RS_DATA_BYTES).map(|i| (i * 7 + 3) as u8).collect();
+
+        for mode in [FecMode::Light, FecMode::Medium, FecMode::Full, FecMode::custom(255, 191).unwrap()] {
+            let data = &data[..mode.data_bytes()];
+            let mut encoded = encoder.encode_with_mode(data, mode).unwrap();
+            // One erasure per parity byte, including the last data byte and a parity byte
+            let mut erasures: Vec<usize> = (0..mode.parity_bytes() - 2).map(|i| i * 2 + 1).collect();
+            erasures.push(mode.data_bytes() - 1);
+            erasures.push(mode.data_bytes() + 2);
+            for &pos in &erasures {
+                encoded[pos] ^= 0x5A;
+            }
+            assert_eq!(decoder.decode_with_erasures(&encoded, mode, &erasures).unwrap(), data, "{:?}", mode);
+
+            // One erasure too many
+            erasures.push(mode.data_bytes() - 2);
+            assert!(decoder.decode_with_erasures(&encoded, mode, &erasures).is_err(), "{:?}", mode);
+        }
+
+        // A clean block decodes whatever is flagged
+        let encoded = encoder.encode_with_mode(b"clean", FecMode::Light).unwrap();
+        assert_eq!(&decoder.decode_with_erasures(&encoded, FecMode::Light, &[RS_DATA_BYTES]).unwrap()[..5], b"clean");
+        assert!(decoder.decode_with_erasures(&encoded, FecMode::Medium, &[]).is_err());
+    }
+
+    #[test]
+    fn test_corrects_half_parity_errors() {
+        let encoder = FecEncoder::new().unwrap();
+        let decoder = FecDecoder::new().unwrap();
+        let data: Vec<u8> = (0..RS_DATA_BYTES).map(|i| (i * 13 + 5) as u8).collect();
+
+        for mode in [FecMode::Light, FecMode::Medium, FecMode::Full, FecMode::custom(255, 191).unwrap()] {
+            let data = &data[..mode.data_bytes()];
+            let clean = encoder.encode_with_mode(data, mode).unwrap();
+            let t = mode.parity_bytes() / 2;
+
+            // (N - K) / 2 errors spread over data and parity
+            let mut encoded = clean.clone();
+            for k in 0..t {
+                encoded[k * (mode.block_bytes() / t)] ^= 0xC3;
+            }
+            assert_eq!(decoder.correct(&encoded, mode, &[]).unwrap(), (data.to_vec(), t), "{:?}", mode);
+            assert_eq!(decoder.decode_with_mode(&encoded, mode).unwrap(), data, "{:?}", mode);
+
+            // Half as many errors again once their positions are known
+            let mut erasures = Vec::new();
+            for k in 0..t {
+                let pos = k * (mode.block_bytes() / t) + 1;
+                encoded[pos] ^= 0x3C;
+                erasures.push(pos);
+            }
+            assert_ne!(decoder.decode_with_mode(&encoded, mode).ok().as_deref(), Some(data));
+            let everything: Vec<usize> = erasures.iter().flat_map(|&pos| [pos - 1, pos]).collect();
+            assert_eq!(decoder.decode_with_erasures(&encoded, mode, &everything).unwrap(), data, "{:?}", mode);
+        }
+    }
+
     #[test]
     fn test_roundtrip_various_patterns() {
         let encoder = FecEncoder::new().unwrap();
@@ -394,9 +472,9 @@ mod tests {
 
     #[test]
     fn test_fec_mode_conversions() {
-        assert_eq!(FecMode::Light as usize, 8);
-        assert_eq!(FecMode::Medium as usize, 16);
-        assert_eq!(FecMode::Full as usize, 32);
+        assert_eq!(FecMode::Light.parity_bytes(), 8);
+        assert_eq!(FecMode::Medium.parity_bytes(), 16);
+        assert_eq!(FecMode::Full.parity_bytes(), 32);
 
         assert_eq!(FecMode::from_u8(8).unwrap(), FecMode::Light);
         assert_eq!(FecMode::from_u8(16).unwrap(), FecMode::Medium);
@@ -405,6 +483,39 @@ mod tests {
         assert!(FecMode::from_u8(99).is_err());
     }
 
+    #[test]
+    fn test_custom_rs_params() {
+        let strong = FecMode::custom(255, 191).unwrap();
+        assert_eq!(strong, FecMode::Custom { parity: 64 });
+        assert_eq!((strong.block_bytes(), strong.data_bytes()), (255, 191));
+        assert_eq!(FecMode::from_u8(strong.to_u8()).unwrap(), strong);
+        // Presets map to themselves, so the header byte stays unambiguous
+        assert_eq!(FecMode::custom(255, 223).unwrap(), FecMode::Full);
+        assert_eq!(FecMode::custom(231, 223).unwrap(), FecMode::Light);
+
+        assert!(FecMode::custom(200, 150).is_err()); // not full-length
+        assert!(FecMode::custom(255, 190).is_err()); // odd parity
+        assert!(FecMode::custom(255, 255).is_err());
+        assert!(FecMode::custom(255, 8).is_err()); // no room for the frame header
+        assert!(FecMode::from_u8(0x80).is_err());
+        assert_eq!("255, 191".parse::<FecMode>().unwrap(), strong);
+        assert_eq!((strong.to_string(), FecMode::Light.to_string()), ("RS(255, 191)".into(), "RS(231, 223)".into()));
+        for invalid in ["255", "255,k", "255,190"] {
+            assert!(invalid.parse::<FecMode>().is_err(), "{}", invalid);
+        }
+        let customs: Vec<FecMode> = FecMode::custom_modes().collect();
+        assert_eq!(customs.len(), 118);
+        assert!(customs.iter().all(|&mode| FecMode::from_u8(mode.to_u8()).unwrap() == mode));
+
+        let encoder = FecEncoder::new().unwrap();
+        let decoder = FecDecoder::new().unwrap();
+        let data: Vec<u8> = (0..191).map(|i| i as u8).collect();
+        let encoded = encoder.encode_with_mode(&data, strong).unwrap();
+        assert_eq!(encoded.len(), 255);
+        assert_eq!(decoder.decode_with_mode(&encoded, strong).unwrap(), data);
+        assert!(encoder.encode_with_mode(&[0u8; 192], strong).is_err());
+    }
+
     #[test]
     fn test_fec_mode_selection() {
         assert_eq!(FecMode::from_data_size(10), FecMode::Light);
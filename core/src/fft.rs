@@ -0,0 +1,201 @@
+//! Pluggable FFT behind the correlation backends
+//!
+//! The FFT correlation plans a transform length, then runs forward and
+//! inverse transforms in place through an [`FftBackend`]. [`RustFft`] is the
+//! default and keeps its plans for the life of the process; a microcontroller
+//! build can wrap CMSIS-DSP or another embedded FFT with
+//! `FftBackendCorrelation` — the sync and demodulator code is the same for all
+//! of them.
+
+use crate::error::{AudioModemError, Result};
+use rustfft::{Fft, FftPlanner};
+use std::sync::{Arc, Mutex};
+
+pub use rustfft::num_complex::Complex32;
+
+/// Complex FFT implementation
+///
+/// Planned lengths are powers of two. `inverse` is unnormalized: a forward
+/// and an inverse transform scale the input by the length.
+pub trait FftBackend: Send + Sync {
+    /// Prepared transforms of one length (twiddle tables, scratch sizes)
+    type Plan;
+
+    /// Short name for logs and benchmarks
+    fn name(&self) -> &str;
+
+    fn plan(&self, len: usize) -> Result<Self::Plan>;
+
+    /// In-place forward transform of `buffer`, which has the planned length
+    fn forward(&self, plan: &Self::Plan, buffer: &mut [Complex32]) -> Result<()>;
+
+    /// In-place unnormalized inverse transform of `buffer`, which has the planned length
+    fn inverse(&self, plan: &Self::Plan, buffer: &mut [Complex32]) -> Result<()>;
+}
+
+/// Default backend: rustfft
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RustFft;
+
+/// Forward and inverse rustfft transforms of one length
+#[derive(Clone)]
+pub struct RustFftPlan {
+    len: usize,
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+}
+
+/// Plans built so far, one per length (correlation lengths are powers of two, so few)
+static RUSTFFT_PLANS: Mutex<Vec<RustFftPlan>> = Mutex::new(Vec::new());
+
+fn check_plan_len(len: usize, buffer: &[Complex32]) -> Result<()> {
+    if buffer.len() != len {
+        return Err(AudioModemError::FftError(format!("{} values for a {}-point plan", buffer.len(), len)));
+    }
+    Ok(())
+}
+
+impl FftBackend for RustFft {
+    type Plan = RustFftPlan;
+
+    fn name(&self) -> &str {
+        "rustfft"
+    }
+
+    fn plan(&self, len: usize) -> Result<RustFftPlan> {
+        let mut plans = RUSTFFT_PLANS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(plan) = plans.iter().find(|plan| plan.len == len) {
+            return Ok(plan.clone());
+        }
+        let mut planner = FftPlanner::new();
+        let plan = RustFftPlan { len, forward: planner.plan_fft_forward(len), inverse: planner.plan_fft_inverse(len) };
+        plans.push(plan.clone());
+        Ok(plan)
+    }
+
+    fn forward(&self, plan: &RustFftPlan, buffer: &mut [Complex32]) -> Result<()> {
+        check_plan_len(plan.len, buffer)?;
+        plan.forward.process(buffer);
+        Ok(())
+    }
+
+    fn inverse(&self, plan: &RustFftPlan, buffer: &mut [Complex32]) -> Result<()> {
+        check_plan_len(plan.len, buffer)?;
+        plan.inverse.process(buffer);
+        Ok(())
+    }
+}
+
+/// `values` as complex numbers, zero-padded to `len`
+fn padded(values: impl Iterator<Item = f32>, len: usize) -> Vec<Complex32> {
+    let mut buffer: Vec<Complex32> = values.map(|re| Complex32::new(re, 0.0)).collect();
+    buffer.resize(len, Complex32::new(0.0, 0.0));
+    buffer
+}
+
+/// Full-mode cross-correlation of `samples` against `template` on `fft`, laid
+/// out as `CorrelationBackend::correlate` requires
+pub fn fft_correlate<B: FftBackend>(fft: &B, samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+    if samples.is_empty() || template.is_empty() {
+        return Err(AudioModemError::InvalidInputSize);
+    }
+    // Convolution with the reversed template, long enough not to wrap around
+    let output_len = samples.len() + template.len() - 1;
+    let len = output_len.next_power_of_two();
+    let plan = fft.plan(len)?;
+    let mut signal = padded(samples.iter().copied(), len);
+    let mut kernel = padded(template.iter().rev().copied(), len);
+    fft.forward(&plan, &mut signal)?;
+    fft.forward(&plan, &mut kernel)?;
+    for (value, k) in signal.iter_mut().zip(&kernel) {
+        *value *= *k;
+    }
+    fft.inverse(&plan, &mut signal)?;
+
+    let scale = 1.0 / len as f32;
+    Ok(signal[..output_len].iter().map(|value| value.re * scale).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::correlation::{CorrelationBackend, FftBackendCorrelation, FftCorrelation};
+    use std::f32::consts::PI;
+
+    /// O(n²) DFT standing in for an embedded FFT
+    struct NaiveDft;
+
+    impl NaiveDft {
+        fn transform(buffer: &mut [Complex32], sign: f32) {
+            let n = buffer.len();
+            let input = buffer.to_vec();
+            for (k, out) in buffer.iter_mut().enumerate() {
+                *out = input
+                    .iter()
+                    .enumerate()
+                    .map(|(j, x)| x * Complex32::from_polar(1.0, sign * 2.0 * PI * ((j * k) % n) as f32 / n as f32))
+                    .sum();
+            }
+        }
+    }
+
+    impl FftBackend for NaiveDft {
+        type Plan = usize;
+
+        fn name(&self) -> &str {
+            "naive-dft"
+        }
+
+        fn plan(&self, len: usize) -> Result<usize> {
+            Ok(len)
+        }
+
+        fn forward(&self, plan: &usize, buffer: &mut [Complex32]) -> Result<()> {
+            check_plan_len(*plan, buffer)?;
+            Self::transform(buffer, -1.0);
+            Ok(())
+        }
+
+        fn inverse(&self, plan: &usize, buffer: &mut [Complex32]) -> Result<()> {
+            check_plan_len(*plan, buffer)?;
+            Self::transform(buffer, 1.0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_rustfft_roundtrip_and_plan_length() {
+        let plan = RustFft.plan(16).unwrap();
+        let input: Vec<Complex32> = (0..16).map(|i| Complex32::new(i as f32, -(i as f32) / 2.0)).collect();
+        let mut buffer = input.clone();
+        RustFft.forward(&plan, &mut buffer).unwrap();
+        RustFft.inverse(&plan, &mut buffer).unwrap();
+        for (a, b) in buffer.iter().zip(&input) {
+            assert!((*a / 16.0 - *b).norm() < 1e-4, "{} vs {}", a, b);
+        }
+
+        assert!(matches!(RustFft.forward(&plan, &mut buffer[..8]), Err(AudioModemError::FftError(_))));
+
+        // A second plan of the same length reuses the cached transforms
+        let again = RustFft.plan(16).unwrap();
+        assert!(Arc::ptr_eq(&plan.forward, &again.forward));
+        assert!(Arc::ptr_eq(&plan.inverse, &again.inverse));
+    }
+
+    #[test]
+    fn test_custom_backend_correlation() {
+        let samples: Vec<f32> = (0..90).map(|i| ((i * 37) % 17) as f32 / 8.0 - 1.0).collect();
+        let template: Vec<f32> = (0..13).map(|i| (i as f32 * 0.3).sin()).collect();
+
+        let custom = FftBackendCorrelation::new(NaiveDft);
+        assert_eq!(custom.name(), "naive-dft");
+        let expected = FftCorrelation.correlate(&samples, &template).unwrap();
+        let actual = custom.correlate(&samples, &template).unwrap();
+        assert_eq!(actual.len(), expected.len());
+        for (a, b) in actual.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+
+        assert!(matches!(fft_correlate(&RustFft, &[], &template), Err(AudioModemError::InvalidInputSize)));
+    }
+}
@@ -0,0 +1,157 @@
+//! Q15 fixed-point Goertzel filters and correlation for targets without an FPU
+//!
+//! Samples are scaled to their peak and quantized to i16 (Q15); the filters
+//! and sums run on integer state and only their results are converted back to
+//! f32, in the units of the float path. With the `fixed-point` feature
+//! `FskDemodulator::bin_powers` runs here; [`Q15Correlation`] is opt-in through
+//! `set_correlation_backend` since it is O(samples × template).
+//!
+//! Only the inner loops are integer: quantizing, the Goertzel coefficients
+//! (f64 `cos`/`sin`, a table on a real target), the conversion of the results
+//! and everything after them (AGC, noise suppression, decisions) still use
+//! floats. The feature checks that the integer filters decode like the float
+//! ones; it does not make the crate build for a target without an FPU.
+
+use crate::correlation::CorrelationBackend;
+use crate::error::{AudioModemError, Result};
+use std::f64::consts::PI;
+
+/// Fractional bits of the Goertzel coefficients (Q30 in i64 arithmetic)
+const COEFF_SHIFT: u32 = 30;
+
+/// Quantize samples to Q15 after scaling their peak to full scale
+///
+/// Returns the quantized samples and the scale applied (`q15 = x * scale`).
+pub fn quantize_q15(samples: &[f32]) -> (Vec<i16>, f32) {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    let scale = if peak > 0.0 { i16::MAX as f32 / peak } else { 1.0 };
+    let quantized = samples.iter().map(|s| (s * scale).round().clamp(-32768.0, 32767.0) as i16).collect();
+    (quantized, scale)
+}
+
+/// Goertzel power of Q15 `samples` at DFT bin `k` of a `samples.len()`-point DFT
+///
+/// In squared Q15 units; the coefficients would be a precomputed table on a
+/// real target.
+pub fn goertzel_power_q15(samples: &[i16], k: usize) -> i64 {
+    if samples.is_empty() {
+        return 0;
+    }
+    let omega = 2.0 * PI * k as f64 / samples.len() as f64;
+    let one = (1i64 << COEFF_SHIFT) as f64;
+    let cos = (omega.cos() * one).round() as i64;
+    let sin = (omega.sin() * one).round() as i64;
+
+    // Products in i128: the filter state of a tone grows to about 2^28 over a
+    // symbol, times a 31-bit coefficient
+    let mul = |coeff: i64, state: i64| ((coeff as i128 * state as i128) >> COEFF_SHIFT) as i64;
+    let (mut q1, mut q2) = (0i64, 0i64);
+    for &sample in samples {
+        let q0 = mul(2 * cos, q1) - q2 + sample as i64;
+        q2 = q1;
+        q1 = q0;
+    }
+
+    let real = q1 - mul(cos, q2);
+    let imag = mul(sin, q2);
+    real * real + imag * imag
+}
+
+/// Time-domain cross-correlation of Q15 samples with i64 accumulators
+///
+/// O(samples × template), so meant for the short recordings a microcontroller
+/// keeps in memory rather than for batch decoding.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Q15Correlation;
+
+impl CorrelationBackend for Q15Correlation {
+    fn name(&self) -> &str {
+        "q15"
+    }
+
+    fn correlate(&self, samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+        if samples.is_empty() || template.is_empty() {
+            return Err(AudioModemError::InvalidInputSize);
+        }
+        let (samples, samples_scale) = quantize_q15(samples);
+        let (template, template_scale) = quantize_q15(template);
+        let unscale = 1.0 / (samples_scale * template_scale);
+        let m = template.len();
+
+        let output = (0..samples.len() + m - 1)
+            .map(|k| {
+                // output[k] = Σ_j samples[k + j - (m - 1)] * template[j]
+                let first = (m - 1).saturating_sub(k);
+                let last = m.min(samples.len() + m - 1 - k);
+                let sum: i64 = (first..last)
+                    .map(|j| samples[k + j + 1 - m] as i64 * template[j] as i64)
+                    .sum();
+                sum as f32 * unscale
+            })
+            .collect();
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::correlation::FftCorrelation;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_quantize_q15() {
+        let (quantized, scale) = quantize_q15(&[0.5, -0.25, 0.0, 0.125]);
+        assert_eq!(quantized, [32767, -16384, 0, 8192]);
+        assert_eq!(scale, 32767.0 / 0.5);
+        assert_eq!(quantize_q15(&[0.0; 3]), (vec![0; 3], 1.0));
+    }
+
+    #[test]
+    fn test_goertzel_q15_matches_float() {
+        let n = 3072;
+        let samples: Vec<f32> = (0..n)
+            .map(|i| {
+                let t = i as f32 / crate::SAMPLE_RATE as f32;
+                0.4 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin() + 0.1 * (2.0 * std::f32::consts::PI * 2300.0 * t).sin()
+            })
+            .collect();
+        let (quantized, scale) = quantize_q15(&samples);
+        for k in [150, 192, 300, 431, 500] {
+            let omega = 2.0 * PI * k as f64 / n as f64;
+            let (mut q1, mut q2) = (0.0f64, 0.0f64);
+            for &s in &samples {
+                let q0 = 2.0 * omega.cos() * q1 - q2 + s as f64;
+                q2 = q1;
+                q1 = q0;
+            }
+            let expected = (q1 - q2 * omega.cos()).powi(2) + (q2 * omega.sin()).powi(2);
+            let fixed = goertzel_power_q15(&quantized, k) as f64 / (scale as f64 * scale as f64);
+            // Relative error on the tones, absolute (vs. the strong tone) elsewhere
+            let tolerance = 1e-3 * expected.max(1e-4 * (0.4 * n as f64 / 2.0).powi(2));
+            assert!((fixed - expected).abs() < tolerance, "bin {}: {} vs {}", k, fixed, expected);
+        }
+    }
+
+    #[test]
+    fn test_q15_correlation_matches_fft() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1650);
+        let template = crate::sync::generate_preamble(crate::PREAMBLE_SAMPLES, 0.5);
+        let mut samples: Vec<f32> = (0..1500).map(|_| rng.gen_range(-0.2..0.2)).collect();
+        samples.extend(template.iter().map(|t| t + rng.gen_range(-0.2..0.2)));
+        samples.extend((0..1500).map(|_| rng.gen_range(-0.2..0.2f32)));
+
+        let fixed = Q15Correlation.correlate(&samples, &template).unwrap();
+        let float = FftCorrelation.correlate(&samples, &template).unwrap();
+        assert_eq!(fixed.len(), float.len());
+        let peak = float.iter().fold(0.0f32, |peak, c| peak.max(c.abs()));
+        for (a, b) in fixed.iter().zip(&float) {
+            assert!((a - b).abs() < peak * 1e-3, "{} vs {}", a, b);
+        }
+
+        let argmax = |c: &[f32]| (0..c.len()).max_by(|&a, &b| c[a].total_cmp(&c[b])).unwrap();
+        assert_eq!(argmax(&fixed), argmax(&float));
+        assert_eq!(argmax(&fixed), 1500 + template.len() - 1);
+        assert!(Q15Correlation.correlate(&[], &template).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Fountain code backends: RaptorQ (default) and a small LT code
+//!
+//! The LT (Luby transform) code needs no external crate and accepts any
+//! symbol size from 1 byte, at the cost of more overhead than RaptorQ (a few
+//! percent to ~30% extra packets for short frames). Packets are
+//! `[esi:2][degree:2][symbol]`, the same size as RaptorQ packets, so receivers
+//! can size blocks before they know the code. ESIs below the source symbol
+//! count K carry that source symbol (systematic), higher ESIs the XOR of
+//! `degree` source symbols drawn by a PRNG seeded with the ESI. The degree
+//! travels in the packet, so only the encoder needs the degree distribution.
+//!
+//! Blocks flag the code in the top bit of their frame length field
+//! (see [`FRAME_LENGTH_LT_FLAG`]), so receivers pick the backend by themselves.
+
+use crate::error::{AudioModemError, Result};
+use crate::fountain_packet::{FountainBlock, FountainObject, MAX_FOUNTAIN_FRAME_LENGTH};
+use raptorq::Decoder;
+
+/// Set in the frame length field of blocks carrying LT packets (frame lengths
+/// never exceed `MAX_FOUNTAIN_FRAME_LENGTH`)
+pub const FRAME_LENGTH_LT_FLAG: u32 = 0x8000_0000;
+
+/// ESI (2 bytes) + degree (2 bytes)
+pub const LT_PACKET_HEADER_BYTES: usize = 4;
+
+/// Mixed into the ESI when drawing a degree, so degree and neighbours use
+/// independent PRNG streams
+const DEGREE_SEED: u64 = 0xD1B5_4A32_D192_ED03;
+
+/// Which fountain code a stream uses
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FountainCode {
+    #[default]
+    RaptorQ,
+    Lt(DegreeDistribution),
+}
+
+/// Degree distribution of LT repair packets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DegreeDistribution {
+    /// Luby's ideal soliton: 1/K for degree 1, 1/(d(d-1)) above
+    IdealSoliton,
+    /// Ideal soliton plus a spike near K/R that keeps the ripple from running dry;
+    /// R = c·ln(K/δ)·√K
+    RobustSoliton { c: f32, delta: f32 },
+}
+
+impl Default for DegreeDistribution {
+    fn default() -> Self {
+        DegreeDistribution::RobustSoliton { c: 0.1, delta: 0.5 }
+    }
+}
+
+impl DegreeDistribution {
+    /// Cumulative probabilities of degrees 1..=k
+    fn cdf(self, k: usize) -> Vec<f64> {
+        let kf = k as f64;
+        let mut weights: Vec<f64> =
+            (1..=k).map(|d| if d == 1 { 1.0 / kf } else { 1.0 / (d as f64 * (d as f64 - 1.0)) }).collect();
+
+        if let DegreeDistribution::RobustSoliton { c, delta } = self {
+            let (c, delta) = (c.max(f32::EPSILON) as f64, delta.clamp(f32::EPSILON, 1.0) as f64);
+            let r = (c * (kf / delta).ln() * kf.sqrt()).max(1.0);
+            let spike = ((kf / r).floor() as usize).clamp(1, k);
+            for (i, weight) in weights.iter_mut().enumerate().take(spike) {
+                let d = i + 1;
+                *weight += if d < spike { r / (d as f64 * kf) } else { r * (r / delta).ln().max(0.0) / kf };
+            }
+        }
+
+        let total: f64 = weights.iter().sum();
+        let mut acc = 0.0;
+        weights
+            .iter()
+            .map(|w| {
+                acc += w / total;
+                acc
+            })
+            .collect()
+    }
+}
+
+/// SplitMix64, enough for picking degrees and neighbours
+struct PacketRng(u64);
+
+impl PacketRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Source symbols combined into packet `esi`
+fn neighbours(esi: u16, degree: usize, k: usize) -> Vec<usize> {
+    if (esi as usize) < k {
+        return vec![esi as usize];
+    }
+    // Partial Fisher-Yates: the first `degree` entries are a uniform sample
+    let mut rng = PacketRng(esi as u64);
+    let mut pool: Vec<usize> = (0..k).collect();
+    for i in 0..degree {
+        let j = i + rng.below(k - i);
+        pool.swap(i, j);
+    }
+    pool.truncate(degree);
+    pool
+}
+
+fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (t, s) in target.iter_mut().zip(source) {
+        *t ^= s;
+    }
+}
+
+fn check_object(frame_length: usize, symbol_size: u16) -> Result<usize> {
+    if frame_length == 0 || frame_length > MAX_FOUNTAIN_FRAME_LENGTH {
+        return Err(AudioModemError::InvalidPacket(format!(
+            "frame length {} outside 1..={}",
+            frame_length, MAX_FOUNTAIN_FRAME_LENGTH
+        )));
+    }
+    if symbol_size == 0 {
+        return Err(AudioModemError::InvalidPacket("symbol size 0".to_string()));
+    }
+    Ok(frame_length.div_ceil(symbol_size as usize))
+}
+
+/// LT encoder over one frame
+pub struct LtEncoder {
+    symbols: Vec<Vec<u8>>,
+    cdf: Vec<f64>,
+}
+
+impl LtEncoder {
+    pub fn new(data: &[u8], symbol_size: u16, distribution: DegreeDistribution) -> Result<Self> {
+        let k = check_object(data.len(), symbol_size)?;
+        let symbols = data
+            .chunks(symbol_size as usize)
+            .map(|chunk| {
+                let mut symbol = chunk.to_vec();
+                symbol.resize(symbol_size as usize, 0);
+                symbol
+            })
+            .collect();
+        Ok(Self { symbols, cdf: distribution.cdf(k) })
+    }
+
+    /// Number of source symbols (K); ESIs `0..K` are the systematic packets
+    pub fn source_symbols(&self) -> usize {
+        self.symbols.len()
+    }
+
+    fn degree(&self, esi: u16) -> usize {
+        if (esi as usize) < self.symbols.len() {
+            return 1;
+        }
+        let u = PacketRng(esi as u64 ^ DEGREE_SEED).unit();
+        (self.cdf.partition_point(|&p| p <= u) + 1).min(self.symbols.len())
+    }
+
+    /// Serialized packet `esi`
+    pub fn packet(&self, esi: u16) -> Vec<u8> {
+        let degree = self.degree(esi);
+        let mut packet = Vec::with_capacity(LT_PACKET_HEADER_BYTES + self.symbols[0].len());
+        packet.extend_from_slice(&esi.to_be_bytes());
+        packet.extend_from_slice(&(degree as u16).to_be_bytes());
+        let mut symbol = vec![0u8; self.symbols[0].len()];
+        for index in neighbours(esi, degree, self.symbols.len()) {
+            xor_into(&mut symbol, &self.symbols[index]);
+        }
+        packet.extend_from_slice(&symbol);
+        packet
+    }
+}
+
+/// Peeling (belief propagation) LT decoder
+pub struct LtDecoder {
+    frame_length: usize,
+    symbol_size: usize,
+    recovered: Vec<Option<Vec<u8>>>,
+    missing: usize,
+    /// Packets still combining more than one unknown symbol
+    pending: Vec<(Vec<usize>, Vec<u8>)>,
+}
+
+impl LtDecoder {
+    pub fn new(frame_length: usize, symbol_size: u16) -> Result<Self> {
+        let k = check_object(frame_length, symbol_size)?;
+        Ok(Self {
+            frame_length,
+            symbol_size: symbol_size as usize,
+            recovered: vec![None; k],
+            missing: k,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Add one packet; returns the frame once every source symbol is known
+    pub fn add_packet(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>> {
+        let k = self.recovered.len();
+        if packet.len() != LT_PACKET_HEADER_BYTES + self.symbol_size {
+            return Err(AudioModemError::InvalidPacket(format!(
+                "{} bytes, expected {}",
+                packet.len(),
+                LT_PACKET_HEADER_BYTES + self.symbol_size
+            )));
+        }
+        let esi = u16::from_be_bytes([packet[0], packet[1]]);
+        let degree = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+        let systematic = (esi as usize) < k;
+        if degree == 0 || degree > k || (systematic && degree != 1) {
+            return Err(AudioModemError::InvalidPacket(format!("ESI {} with degree {}", esi, degree)));
+        }
+
+        let mut symbol = packet[LT_PACKET_HEADER_BYTES..].to_vec();
+        let mut unknown = neighbours(esi, degree, k);
+        unknown.retain(|&index| match &self.recovered[index] {
+            Some(known) => {
+                xor_into(&mut symbol, known);
+                false
+            }
+            None => true,
+        });
+        match unknown.len() {
+            0 => {}
+            1 => self.recover(unknown[0], symbol),
+            _ => self.pending.push((unknown, symbol)),
+        }
+
+        Ok(self.frame())
+    }
+
+    fn recover(&mut self, index: usize, symbol: Vec<u8>) {
+        let mut ripple = vec![(index, symbol)];
+        while let Some((index, symbol)) = ripple.pop() {
+            if self.recovered[index].is_some() {
+                continue;
+            }
+            for (mut unknown, mut combined) in std::mem::take(&mut self.pending) {
+                if let Some(position) = unknown.iter().position(|&i| i == index) {
+                    unknown.swap_remove(position);
+                    xor_into(&mut combined, &symbol);
+                }
+                match unknown.len() {
+                    0 => {}
+                    1 => ripple.push((unknown[0], combined)),
+                    _ => self.pending.push((unknown, combined)),
+                }
+            }
+            self.recovered[index] = Some(symbol);
+            self.missing -= 1;
+        }
+    }
+
+    fn frame(&self) -> Option<Vec<u8>> {
+        if self.missing > 0 {
+            return None;
+        }
+        let mut frame: Vec<u8> = self.recovered.iter().flatten().flatten().copied().collect();
+        frame.truncate(self.frame_length);
+        Some(frame)
+    }
+}
+
+/// Receiving side of one fountain stream, for whichever code its blocks use
+pub enum FountainReceiver {
+    RaptorQ { object: FountainObject, decoder: Decoder },
+    Lt(LtDecoder),
+}
+
+impl FountainReceiver {
+    /// Receiver for the stream `block` belongs to
+    pub fn new(block: &FountainBlock) -> Result<Self> {
+        if block.lt {
+            return Ok(FountainReceiver::Lt(LtDecoder::new(block.frame_length, block.symbol_size)?));
+        }
+        let object = FountainObject::new(block.frame_length, block.symbol_size)?;
+        let decoder = object.decoder();
+        Ok(FountainReceiver::RaptorQ { object, decoder })
+    }
+
+    /// Add a packet; returns the frame once it is complete
+    pub fn add_packet(&mut self, packet: &[u8]) -> Result<Option<Vec<u8>>> {
+        match self {
+            FountainReceiver::RaptorQ { object, decoder } => Ok(decoder.decode(object.parse_packet(packet)?)),
+            FountainReceiver::Lt(decoder) => decoder.add_packet(packet),
+        }
+    }
+
+    pub fn is_lt(&self) -> bool {
+        matches!(self, FountainReceiver::Lt(_))
+    }
+
+    /// ID of a packet's encoding symbol: the ESI of LT packets, and the source
+    /// block number above the 24-bit ESI of RaptorQ packets
+    pub fn symbol_id(&self, packet: &[u8]) -> Option<u32> {
+        match self {
+            FountainReceiver::RaptorQ { .. } => packet.get(..4).map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]])),
+            FountainReceiver::Lt(_) => packet.get(..2).map(|id| u16::from_be_bytes([id[0], id[1]]) as u32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 + 7) as u8).collect()
+    }
+
+    #[test]
+    fn test_lt_systematic_packets_decode() {
+        let data = frame(250);
+        let encoder = LtEncoder::new(&data, 16, DegreeDistribution::default()).unwrap();
+        assert_eq!(encoder.source_symbols(), 16);
+
+        let mut decoder = LtDecoder::new(data.len(), 16).unwrap();
+        let mut result = None;
+        for esi in 0..16 {
+            result = decoder.add_packet(&encoder.packet(esi)).unwrap();
+        }
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn test_lt_recovers_from_repair_packets_only() {
+        for distribution in [DegreeDistribution::IdealSoliton, DegreeDistribution::default()] {
+            let data = frame(400);
+            let encoder = LtEncoder::new(&data, 8, distribution).unwrap();
+            let k = encoder.source_symbols() as u16;
+
+            let mut decoder = LtDecoder::new(data.len(), 8).unwrap();
+            let mut used = 0;
+            let mut result = None;
+            for esi in k..k + 10 * k {
+                used += 1;
+                result = decoder.add_packet(&encoder.packet(esi)).unwrap();
+                if result.is_some() {
+                    break;
+                }
+            }
+            assert_eq!(result.unwrap(), data, "{:?}", distribution);
+            assert!(used < 3 * k, "{:?} needed {} packets for K = {}", distribution, used, k);
+        }
+    }
+
+    #[test]
+    fn test_lt_mixed_losses() {
+        let data = frame(300);
+        let encoder = LtEncoder::new(&data, 10, DegreeDistribution::default()).unwrap();
+        let k = encoder.source_symbols() as u16;
+        let mut decoder = LtDecoder::new(data.len(), 10).unwrap();
+
+        // Every third packet lost
+        let result = (0..4 * k)
+            .filter(|esi| esi % 3 != 0)
+            .find_map(|esi| decoder.add_packet(&encoder.packet(esi)).unwrap());
+        assert_eq!(result.unwrap(), data);
+    }
+
+    #[test]
+    fn test_lt_rejects_malformed_packets() {
+        let data = frame(64);
+        let encoder = LtEncoder::new(&data, 8, DegreeDistribution::default()).unwrap();
+        let mut decoder = LtDecoder::new(data.len(), 8).unwrap();
+        let good = encoder.packet(3);
+
+        assert!(decoder.add_packet(&good[..good.len() - 1]).is_err());
+        let mut bad = good.clone();
+        bad[3] = 2; // systematic packets have degree 1
+        assert!(decoder.add_packet(&bad).is_err());
+        bad[0] = 0xFF;
+        bad[2] = 0xFF; // degree above K
+        assert!(decoder.add_packet(&bad).is_err());
+
+        assert!(LtDecoder::new(0, 8).is_err());
+        assert!(LtDecoder::new(64, 0).is_err());
+        assert!(LtEncoder::new(&data, 1, DegreeDistribution::IdealSoliton).is_ok());
+    }
+
+    #[test]
+    fn test_degree_cdf_is_normalized() {
+        for distribution in [
+            DegreeDistribution::IdealSoliton,
+            DegreeDistribution::default(),
+            DegreeDistribution::RobustSoliton { c: 0.5, delta: 0.01 },
+        ] {
+            for k in [1, 2, 7, 130] {
+                let cdf = distribution.cdf(k);
+                assert_eq!(cdf.len(), k);
+                assert!((cdf[k - 1] - 1.0).abs() < 1e-9);
+                assert!(cdf.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Parsing of fountain blocks and validation of the RaptorQ packets they carry
+//!
+//! `raptorq` trusts its input and panics on packets that do not fit the
+//! object: a short buffer, a source block number past the last block or a
+//! symbol of the wrong length. `ObjectTransmissionInformation::with_defaults` also asserts a
+//! symbol size of at least 8. Everything is checked here against the frame
+//! length and symbol size the stream advertises, so malformed audio can only
+//! produce errors.
+
+use crate::error::{AudioModemError, Result};
+use crate::fountain::FRAME_LENGTH_LT_FLAG;
+use crate::framing::{crc16, FRAME_TIMESTAMP_EXT_SIZE};
+use crate::{FRAME_HEADER_SIZE, MAX_PAYLOAD_SIZE};
+use raptorq::{partition, Decoder, EncodingPacket, ObjectTransmissionInformation};
+
+/// Longest frame a fountain stream carries: header, timestamp extension,
+/// payload and CRC-16
+pub const MAX_FOUNTAIN_FRAME_LENGTH: usize = FRAME_HEADER_SIZE + FRAME_TIMESTAMP_EXT_SIZE + MAX_PAYLOAD_SIZE + 2;
+
+/// Smallest symbol size RaptorQ accepts (its symbol alignment)
+pub const MIN_FOUNTAIN_SYMBOL_SIZE: u16 = 8;
+
+/// Source block number (1 byte) + encoding symbol ID (3 bytes)
+const PAYLOAD_ID_BYTES: usize = 4;
+
+/// Header fields and packet of one demodulated fountain block
+///
+/// Layout: `[frame_len:4][symbol_size:2][packet_len:2][packet][crc16:2]`, big endian;
+/// the top bit of `frame_len` is [`FRAME_LENGTH_LT_FLAG`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FountainBlock<'a> {
+    pub frame_length: usize,
+    pub symbol_size: u16,
+    /// LT packet instead of RaptorQ (see [`crate::fountain`])
+    pub lt: bool,
+    /// Serialized fountain packet (see [`FountainObject::parse_packet`])
+    pub packet: &'a [u8],
+    /// Whether the packet matched its CRC-16
+    pub crc_ok: bool,
+}
+
+impl FountainBlock<'_> {
+    pub fn params(&self) -> FountainParams {
+        FountainParams { frame_length: self.frame_length, symbol_size: self.symbol_size, lt: self.lt }
+    }
+}
+
+/// What every block of one fountain transmission repeats in its header; a
+/// change means the sender restarted with another payload or configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FountainParams {
+    pub frame_length: usize,
+    pub symbol_size: u16,
+    pub lt: bool,
+}
+
+/// Read the header of a demodulated fountain block without checking its packet
+/// (e.g. to learn the symbol size before the whole block is available)
+pub fn parse_fountain_params(block: &[u8]) -> Option<FountainParams> {
+    let header: [u8; 6] = block.get(..6)?.try_into().ok()?;
+    let frame_length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    Some(FountainParams {
+        frame_length: (frame_length & !FRAME_LENGTH_LT_FLAG) as usize,
+        symbol_size: u16::from_be_bytes([header[4], header[5]]),
+        lt: frame_length & FRAME_LENGTH_LT_FLAG != 0,
+    })
+}
+
+/// Split a demodulated fountain block into its fields
+///
+/// Returns None when the block is too short for its declared packet length.
+pub fn parse_fountain_block(block: &[u8]) -> Option<FountainBlock<'_>> {
+    if block.len() < 8 {
+        return None;
+    }
+    let FountainParams { frame_length, symbol_size, lt } = parse_fountain_params(block)?;
+    let packet_len = u16::from_be_bytes([block[6], block[7]]) as usize;
+    let rest = &block[8..];
+    // Need packet_len bytes + 2 bytes for CRC-16
+    if rest.len() < packet_len + 2 {
+        return None;
+    }
+
+    let packet = &rest[..packet_len];
+    let received_crc = u16::from_be_bytes([rest[packet_len], rest[packet_len + 1]]);
+    Some(FountainBlock { frame_length, symbol_size, lt, packet, crc_ok: received_crc == crc16(packet) })
+}
+
+/// The RaptorQ object behind a fountain stream, derived from its advertised
+/// frame length and symbol size
+#[derive(Debug, Clone)]
+pub struct FountainObject {
+    oti: ObjectTransmissionInformation,
+    /// Source symbols in each source block
+    block_symbols: Vec<u32>,
+}
+
+impl FountainObject {
+    /// Fails if the frame is empty or longer than `MAX_FOUNTAIN_FRAME_LENGTH`,
+    /// or the symbol size is below `MIN_FOUNTAIN_SYMBOL_SIZE`
+    pub fn new(frame_length: usize, symbol_size: u16) -> Result<Self> {
+        if frame_length == 0 || frame_length > MAX_FOUNTAIN_FRAME_LENGTH {
+            return Err(AudioModemError::InvalidPacket(format!(
+                "frame length {} outside 1..={}",
+                frame_length, MAX_FOUNTAIN_FRAME_LENGTH
+            )));
+        }
+        if symbol_size < MIN_FOUNTAIN_SYMBOL_SIZE {
+            return Err(AudioModemError::InvalidPacket(format!(
+                "symbol size {} below {}",
+                symbol_size, MIN_FOUNTAIN_SYMBOL_SIZE
+            )));
+        }
+
+        let oti = ObjectTransmissionInformation::with_defaults(frame_length as u64, symbol_size);
+        // Same partition as raptorq::Decoder::new
+        let total_symbols = (frame_length as u64).div_ceil(oti.symbol_size() as u64) as u32;
+        let (large, small, large_blocks, small_blocks) = partition(total_symbols, oti.source_blocks());
+        let block_symbols = std::iter::repeat_n(large, large_blocks as usize)
+            .chain(std::iter::repeat_n(small, small_blocks as usize))
+            .collect();
+        Ok(Self { oti, block_symbols })
+    }
+
+    pub fn oti(&self) -> ObjectTransmissionInformation {
+        self.oti
+    }
+
+    /// Bytes of symbol data every packet of this object carries
+    pub fn symbol_size(&self) -> usize {
+        self.oti.symbol_size() as usize
+    }
+
+    pub fn decoder(&self) -> Decoder {
+        Decoder::new(self.oti)
+    }
+
+    /// Deserialize a packet after checking it fits this object
+    pub fn parse_packet(&self, bytes: &[u8]) -> Result<EncodingPacket> {
+        if bytes.len() != PAYLOAD_ID_BYTES + self.symbol_size() {
+            return Err(AudioModemError::InvalidPacket(format!(
+                "{} bytes, expected {}",
+                bytes.len(),
+                PAYLOAD_ID_BYTES + self.symbol_size()
+            )));
+        }
+
+        // Every ESI is valid: below the block's source symbols it names one of
+        // them, from there on a repair symbol
+        let source_block = bytes[0] as usize;
+        if source_block >= self.block_symbols.len() {
+            return Err(AudioModemError::InvalidPacket(format!(
+                "source block {} of {}",
+                source_block,
+                self.block_symbols.len()
+            )));
+        }
+
+        Ok(EncodingPacket::deserialize(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use raptorq::Encoder;
+
+    fn encoded_packets(frame: &[u8], symbol_size: u16) -> (FountainObject, Vec<Vec<u8>>) {
+        let object = FountainObject::new(frame.len(), symbol_size).unwrap();
+        let encoder = Encoder::new(frame, object.oti());
+        let packets = encoder.get_encoded_packets(4).iter().map(|p| p.serialize()).collect();
+        (object, packets)
+    }
+
+    #[test]
+    fn test_encoder_packets_are_accepted_and_decode() {
+        let frame: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let (object, packets) = encoded_packets(&frame, 32);
+
+        let mut decoder = object.decoder();
+        let mut decoded = None;
+        for bytes in &packets {
+            decoded = decoder.decode(object.parse_packet(bytes).unwrap());
+        }
+        assert_eq!(decoded.unwrap(), frame);
+    }
+
+    #[test]
+    fn test_repair_packets_are_accepted_and_decode() {
+        // 2 source symbols, extended to 10: the first repair packets carry ESIs 2 and 3
+        let frame: Vec<u8> = (0..50).map(|i| (i * 3) as u8).collect();
+        let object = FountainObject::new(frame.len(), 32).unwrap();
+        let encoder = Encoder::new(&frame, object.oti());
+        let repair = encoder.get_block_encoders()[0].repair_packets(0, 2);
+
+        let esis: Vec<u32> = repair.iter().map(|packet| packet.payload_id().encoding_symbol_id()).collect();
+        assert_eq!(esis, [2, 3]);
+
+        let mut decoder = object.decoder();
+        let mut decoded = None;
+        for packet in &repair {
+            decoded = decoder.decode(object.parse_packet(&packet.serialize()).unwrap());
+        }
+        assert_eq!(decoded.unwrap(), frame);
+    }
+
+    #[test]
+    fn test_rejects_packets_that_do_not_fit() {
+        let frame = vec![0xA5u8; 100];
+        let (object, packets) = encoded_packets(&frame, 16);
+        let good = &packets[0];
+
+        assert!(object.parse_packet(&good[..good.len() - 1]).is_err());
+        assert!(object.parse_packet(&[good.as_slice(), &[0]].concat()).is_err());
+        assert!(object.parse_packet(&[]).is_err());
+
+        let mut wrong_block = good.clone();
+        wrong_block[0] = 1;
+        assert!(matches!(object.parse_packet(&wrong_block), Err(AudioModemError::InvalidPacket(_))));
+
+        // 100 bytes / 16 = 7 source symbols: repair symbols start at ESI 7
+        let mut repair_esi = good.clone();
+        repair_esi[3] = 7;
+        assert!(object.parse_packet(&repair_esi).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_objects() {
+        assert!(FountainObject::new(0, 64).is_err());
+        assert!(FountainObject::new(MAX_FOUNTAIN_FRAME_LENGTH + 1, 64).is_err());
+        assert!(FountainObject::new(100, MIN_FOUNTAIN_SYMBOL_SIZE - 1).is_err());
+        assert!(FountainObject::new(MAX_FOUNTAIN_FRAME_LENGTH, MIN_FOUNTAIN_SYMBOL_SIZE).is_ok());
+        assert!(FountainObject::new(1, u16::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_parse_fountain_block() {
+        let packet = [1u8, 2, 3, 4, 5];
+        let mut block = vec![0, 0, 0, 42, 0, 64, 0, 5];
+        block.extend_from_slice(&packet);
+        block.extend_from_slice(&crc16(&packet).to_be_bytes());
+        block.extend_from_slice(&[0, 0, 0]); // symbol padding
+
+        let parsed = parse_fountain_block(&block).unwrap();
+        assert_eq!((parsed.frame_length, parsed.symbol_size, parsed.packet), (42, 64, &packet[..]));
+        assert!(parsed.crc_ok && !parsed.lt);
+        assert_eq!(parse_fountain_params(&block[..6]), Some(parsed.params()));
+        assert_eq!(parse_fountain_params(&block[..5]), None);
+
+        block[9] ^= 0x01;
+        assert!(!parse_fountain_block(&block).unwrap().crc_ok);
+        assert!(parse_fountain_block(&block[..14]).is_none());
+
+        block[0] = 0x80;
+        let parsed = parse_fountain_block(&block).unwrap();
+        assert!(parsed.lt);
+        assert_eq!(parsed.frame_length, 42);
+    }
+}
@@ -0,0 +1,2488 @@
+//! Fountain-mode audio streams
+//!
+//! [`EncoderFsk::encode_fountain`] turns a payload into an endless sequence of
+//! whistle-preambled blocks, each carrying one RaptorQ or LT packet, and
+//! [`DecoderFsk::decode_fountain`] collects blocks until the payload can be
+//! rebuilt. Only built with the `fountain` feature.
+
+use crate::audio_io::SampleReader;
+use crate::decoder_fsk::{DecodeQuality, DecoderFsk, FountainProgress};
+use crate::encoder_fsk::EncoderFsk;
+use crate::error::{AudioModemError, Result};
+use crate::events::DecodeEvent;
+use crate::fountain::{DegreeDistribution, FountainCode, FountainReceiver, LtEncoder, FRAME_LENGTH_LT_FLAG};
+use crate::fountain_packet::{parse_fountain_block, parse_fountain_params, FountainObject, FountainParams};
+use crate::framing::{crc16, Frame, FrameDecoder, FrameEncoder};
+use crate::fsk::{FskModulator, SymbolStats, ToneLayout, FSK_BYTES_PER_SYMBOL, FSK_SYMBOL_SAMPLES};
+use crate::streaming::CarrierSense;
+use crate::units::{self, Millis};
+use crate::melody::fountain_preamble;
+use crate::sync::detect_fountain_template;
+use crate::{FOUNTAIN_BLOCK_SIZE, MAX_PAYLOAD_SIZE, PREAMBLE_SAMPLES, SYNC_SILENCE_SAMPLES};
+use log::{info, warn};
+use raptorq::{Encoder, EncodingPacket};
+use std::sync::{Arc, Mutex};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+
+/// Consecutive valid blocks of another transmission after which a fountain
+/// decode drops what it collected and follows the new one
+pub const FOUNTAIN_RESTART_BLOCKS: usize = 3;
+
+/// Samples either side of its predicted position searched first for the next
+/// fountain preamble
+pub const FOUNTAIN_DRIFT_WINDOW_SAMPLES: usize = SYNC_SILENCE_SAMPLES / 4;
+
+/// Share of each prediction error folded into the drift estimate
+const DRIFT_GAIN: f32 = 0.5;
+
+/// Configuration for fountain mode streaming
+#[derive(Debug, Clone, PartialEq)]
+pub struct FountainConfig {
+    /// Timeout for sender to keep transmitting (in seconds)
+    pub timeout_secs: u32,
+    /// Size of each fountain block in bytes (before fountain encoding)
+    pub block_size: usize,
+    /// Ratio of repair blocks to source blocks (e.g., 0.5 = 50% overhead)
+    pub repair_blocks_ratio: f32,
+    /// Fountain code used by the encoder; decoders detect it from the blocks
+    pub code: FountainCode,
+}
+
+impl Default for FountainConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            block_size: FOUNTAIN_BLOCK_SIZE,
+            repair_blocks_ratio: 0.5,
+            code: FountainCode::RaptorQ,
+        }
+    }
+}
+
+/// Answer of a listen-before-talk hook before each fountain block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelAccess {
+    /// Send the next block now
+    Clear,
+    /// Channel in use: emit this many samples of silence and ask again on the next call
+    Defer(usize),
+    /// End the stream
+    Stop,
+}
+
+/// Channel-activity hook consulted by [`FountainStream`] before each block
+pub type ChannelCheck = Box<dyn FnMut() -> ChannelAccess + Send>;
+
+impl EncoderFsk {
+    /// Encode data using fountain mode for continuous streaming transmission
+    ///
+    /// Returns a FountainStream iterator that generates unique encoded blocks
+    /// continuously until the configured timeout is reached.
+    ///
+    /// Each yielded Vec<f32> is a complete audio chunk with:
+    /// preamble + fountain_block + postamble
+    pub fn encode_fountain(&mut self, data: &[u8], config: Option<FountainConfig>) -> Result<FountainStream> {
+        if data.len() > MAX_PAYLOAD_SIZE {
+            return Err(crate::encoder_fsk::payload_too_large(data.len(), MAX_PAYLOAD_SIZE));
+        }
+
+        let config = config.unwrap_or_default();
+
+        // Create frame with header and CRC
+        let frame = Frame::new(data, 0, 0); // FEC mode is not used in fountain mode
+
+        let frame_data = FrameEncoder::encode(&frame)?;
+
+        // Validate block_size before casting to u16
+        let symbol_size = u16::try_from(config.block_size)
+            .map_err(|_| crate::error::AudioModemError::InvalidConfig(
+                format!("block_size {} exceeds maximum u16 value ({})", config.block_size, u16::MAX)
+            ))?;
+
+        let source = match (config.code, FountainObject::new(frame_data.len(), symbol_size)) {
+            (FountainCode::RaptorQ, Ok(object)) => FountainSource::raptorq(&frame_data, &object, config.repair_blocks_ratio)?,
+            (FountainCode::RaptorQ, Err(e)) => {
+                // RaptorQ rejects symbols below 8 bytes; the LT code takes any size
+                warn!("RaptorQ unavailable ({}), falling back to the LT code", e);
+                FountainSource::lt(&frame_data, symbol_size, DegreeDistribution::default(), config.repair_blocks_ratio)?
+            }
+            (FountainCode::Lt(distribution), _) => {
+                FountainSource::lt(&frame_data, symbol_size, distribution, config.repair_blocks_ratio)?
+            }
+        };
+
+        // Calculate max samples based on timeout_secs as audio duration
+        // Use the single source of truth: crate::SAMPLE_RATE
+        let max_samples = if config.timeout_secs == 0 {
+            usize::MAX
+        } else {
+            config.timeout_secs as usize * crate::SAMPLE_RATE
+        };
+
+        Ok(FountainStream {
+            source,
+            frame_length: frame_data.len(),
+            symbol_size,
+            fsk: self.modulator(FSK_SYMBOL_SAMPLES, ToneLayout::STANDARD),
+            config,
+            block_id: 0,
+            total_samples_generated: 0,
+            max_samples,
+            preamble: fountain_preamble(0.5, self.network_key(), self.fountain_melody()),
+            channel_check: None,
+            deferred_samples: 0,
+        })
+    }
+}
+
+/// Packet source of a fountain stream
+///
+/// Both codes send every source packet once per cycle, followed by
+/// `repairs_per_cycle` new repair packets.
+enum FountainSource {
+    RaptorQ {
+        encoder: Encoder,
+        source_packets: Vec<EncodingPacket>,
+        repair_counters: Vec<u32>,
+        repair_block_cursor: usize,
+        cycle: PacketCycle,
+    },
+    Lt {
+        encoder: LtEncoder,
+        next_repair_esi: u16,
+        cycle: PacketCycle,
+    },
+}
+
+/// Position within the source/repair rotation
+struct PacketCycle {
+    source_count: usize,
+    repairs_per_cycle: usize,
+    next_source_idx: usize,
+    repairs_sent_this_cycle: usize,
+}
+
+impl PacketCycle {
+    fn new(source_count: usize, repair_blocks_ratio: f32) -> Self {
+        let repairs_per_cycle = if repair_blocks_ratio <= 0.0 {
+            0
+        } else {
+            let desired = (source_count as f32 * repair_blocks_ratio).ceil() as usize;
+            desired.max(1)
+        };
+        Self { source_count, repairs_per_cycle, next_source_idx: 0, repairs_sent_this_cycle: 0 }
+    }
+
+    /// Source packet index to send next, or None when a repair packet is due
+    fn next_source(&mut self) -> Option<usize> {
+        if self.next_source_idx >= self.source_count
+            && (self.repairs_per_cycle == 0 || self.repairs_sent_this_cycle >= self.repairs_per_cycle)
+        {
+            // Restart cycle: emit all source packets again, then new repair packets
+            self.next_source_idx = 0;
+            self.repairs_sent_this_cycle = 0;
+        }
+        if self.next_source_idx < self.source_count {
+            self.next_source_idx += 1;
+            return Some(self.next_source_idx - 1);
+        }
+        self.repairs_sent_this_cycle += 1;
+        None
+    }
+}
+
+impl FountainSource {
+    fn raptorq(frame_data: &[u8], object: &FountainObject, repair_blocks_ratio: f32) -> Result<Self> {
+        let encoder = Encoder::new(frame_data, object.oti());
+        let source_packets = encoder.get_encoded_packets(0);
+        if source_packets.is_empty() {
+            return Err(crate::error::AudioModemError::InvalidConfig(
+                "RaptorQ encoder did not produce any source packets".to_string(),
+            ));
+        }
+
+        let block_count = encoder.get_block_encoders().len();
+        if block_count == 0 {
+            return Err(crate::error::AudioModemError::InvalidConfig(
+                "RaptorQ encoder has no source blocks".to_string(),
+            ));
+        }
+
+        let cycle = PacketCycle::new(source_packets.len(), repair_blocks_ratio);
+        Ok(FountainSource::RaptorQ {
+            encoder,
+            source_packets,
+            repair_counters: vec![0u32; block_count],
+            repair_block_cursor: 0,
+            cycle,
+        })
+    }
+
+    fn lt(frame_data: &[u8], symbol_size: u16, distribution: DegreeDistribution, repair_blocks_ratio: f32) -> Result<Self> {
+        let encoder = LtEncoder::new(frame_data, symbol_size, distribution)
+            .map_err(|e| crate::error::AudioModemError::InvalidConfig(e.to_string()))?;
+        let cycle = PacketCycle::new(encoder.source_symbols(), repair_blocks_ratio);
+        Ok(FountainSource::Lt { next_repair_esi: encoder.source_symbols() as u16, encoder, cycle })
+    }
+
+    fn next_packet(&mut self) -> Option<Vec<u8>> {
+        match self {
+            FountainSource::RaptorQ { encoder, source_packets, repair_counters, repair_block_cursor, cycle } => {
+                match cycle.next_source() {
+                    Some(index) => Some(source_packets[index].serialize()),
+                    None => next_repair_packet(encoder, repair_counters, repair_block_cursor).map(|p| p.serialize()),
+                }
+            }
+            FountainSource::Lt { encoder, next_repair_esi, cycle } => match cycle.next_source() {
+                Some(index) => Some(encoder.packet(index as u16)),
+                None => {
+                    // Repair ESIs wrap after 65535 (days of audio)
+                    let esi = *next_repair_esi;
+                    *next_repair_esi = next_repair_esi.checked_add(1).unwrap_or(encoder.source_symbols() as u16);
+                    Some(encoder.packet(esi))
+                }
+            },
+        }
+    }
+}
+
+/// Next RaptorQ repair packet, rotating over the source blocks
+fn next_repair_packet(
+    encoder: &Encoder,
+    repair_counters: &mut Vec<u32>,
+    repair_block_cursor: &mut usize,
+) -> Option<EncodingPacket> {
+    let block_encoders = encoder.get_block_encoders();
+    if block_encoders.is_empty() {
+        return None;
+    }
+
+    if repair_counters.len() < block_encoders.len() {
+        repair_counters.resize(block_encoders.len(), 0);
+    }
+
+    if *repair_block_cursor >= block_encoders.len() {
+        *repair_block_cursor = 0;
+    }
+
+    let block_idx = *repair_block_cursor;
+    *repair_block_cursor = (*repair_block_cursor + 1) % block_encoders.len();
+
+    if let Some(counter) = repair_counters.get_mut(block_idx) {
+        let packets = block_encoders[block_idx].repair_packets(*counter, 1);
+        if packets.is_empty() {
+            return None;
+        }
+        *counter += 1;
+        return packets.into_iter().next();
+    }
+
+    None
+}
+
+/// Iterator that generates continuous fountain-encoded audio blocks
+pub struct FountainStream {
+    source: FountainSource,
+    frame_length: usize,
+    symbol_size: u16,
+    fsk: FskModulator,
+    config: FountainConfig,
+    block_id: u32,
+    total_samples_generated: usize,
+    max_samples: usize,
+    /// Sync signal opening every block (whistle, melody or keyed)
+    preamble: Vec<f32>,
+    channel_check: Option<ChannelCheck>,
+    deferred_samples: usize,
+}
+
+impl Iterator for FountainStream {
+    type Item = Vec<f32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Check if we've already reached the audio duration limit
+        if self.total_samples_generated >= self.max_samples {
+            return None;
+        }
+
+        // Ask the listen-before-talk hook before taking a packet, so a deferred
+        // block is sent unchanged later
+        if let Some(check) = self.channel_check.as_mut() {
+            match check() {
+                ChannelAccess::Clear => {}
+                ChannelAccess::Defer(samples) => {
+                    // Deferrals count toward the timeout, so a channel that never
+                    // clears still ends the stream
+                    let samples = samples.max(1);
+                    self.total_samples_generated += samples;
+                    self.deferred_samples += samples;
+                    return Some(vec![0.0; samples]);
+                }
+                ChannelAccess::Stop => return None,
+            }
+        }
+
+        // Select next fountain packet (cycles through source packets and then repair packets)
+        let packet_data = self.next_packet()?;
+
+        // Include frame metadata in every block so the decoder can resynchronize mid-stream
+        let header = self.block_header();
+
+        match modulate_fountain_block(&mut self.fsk, &header, &packet_data, &self.preamble) {
+            Ok(samples) => {
+                // Always emit complete blocks without truncation, as truncating mid-block creates
+                // malformed audio that cannot be deserialized. The max_samples limit is
+                // approximate and may be exceeded by one block, which is acceptable.
+                self.total_samples_generated += samples.len();
+                self.block_id += 1;
+                Some(samples)
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Build the audio for one fountain block:
+/// silence → whistle preamble → silence → FSK([header][packet_len][packet][crc16])
+///
+/// No postamble is emitted - fountain mode is open-ended with only preamble signaling.
+pub(crate) fn modulate_fountain_block(
+    fsk: &mut FskModulator,
+    header: &[u8],
+    packet_data: &[u8],
+    preamble: &[f32],
+) -> Result<Vec<f32>> {
+    let mut encoded_data = Vec::with_capacity(header.len() + packet_data.len() + 4);
+    encoded_data.extend_from_slice(header);
+
+    // Prefix each block with the serialized packet length so padding can be removed
+    let packet_len = packet_data.len() as u16;
+    encoded_data.extend_from_slice(&packet_len.to_be_bytes());
+    encoded_data.extend_from_slice(packet_data);
+
+    // Add CRC-16 checksum of the RaptorQ packet for early corruption detection
+    let packet_crc = crc16(packet_data);
+    encoded_data.extend_from_slice(&packet_crc.to_be_bytes());
+
+    let remainder = encoded_data.len() % crate::fsk::FSK_BYTES_PER_SYMBOL;
+    if remainder != 0 {
+        let padding = crate::fsk::FSK_BYTES_PER_SYMBOL - remainder;
+        encoded_data.resize(encoded_data.len() + padding, 0u8);
+    }
+    assert_eq!(
+        encoded_data.len() % crate::fsk::FSK_BYTES_PER_SYMBOL,
+        0,
+        "FSK symbol alignment invariant violated: encoded_data length ({}) is not a multiple of FSK_BYTES_PER_SYMBOL ({})",
+        encoded_data.len(),
+        crate::fsk::FSK_BYTES_PER_SYMBOL
+    );
+
+    // Fountain mode exclusively uses the three-note whistle preamble (or a melody or the keyed one)
+    let mut samples = Vec::new();
+
+    // Add silence before preamble for clean block start
+    samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+
+    samples.extend_from_slice(preamble);
+
+    // Add silence after preamble for clear frame boundaries
+    samples.extend_from_slice(&vec![0.0f32; SYNC_SILENCE_SAMPLES]);
+
+    let fsk_samples = fsk.modulate(&encoded_data)?;
+    samples.extend_from_slice(&fsk_samples);
+
+    Ok(samples)
+}
+
+impl FountainStream {
+    /// `[frame_len:4][symbol_size:2]` block header, with the LT flag when applicable
+    pub(crate) fn block_header(&self) -> [u8; 6] {
+        let mut frame_length = self.frame_length as u32;
+        if matches!(self.source, FountainSource::Lt { .. }) {
+            frame_length |= FRAME_LENGTH_LT_FLAG;
+        }
+        let mut header = [0u8; 6];
+        header[..4].copy_from_slice(&frame_length.to_be_bytes());
+        header[4..].copy_from_slice(&self.symbol_size.to_be_bytes());
+        header
+    }
+
+    /// Fountain code actually in use (RaptorQ falls back to LT for tiny symbols)
+    pub fn code(&self) -> FountainCode {
+        match self.source {
+            FountainSource::RaptorQ { .. } => FountainCode::RaptorQ,
+            FountainSource::Lt { .. } => FountainCode::Lt(match self.config.code {
+                FountainCode::Lt(distribution) => distribution,
+                FountainCode::RaptorQ => DegreeDistribution::default(),
+            }),
+        }
+    }
+
+    /// Next serialized packet (cycles through source packets and then repair packets)
+    pub(crate) fn next_packet(&mut self) -> Option<Vec<u8>> {
+        self.source.next_packet()
+    }
+
+    /// Audio of the next block with `prefix` ahead of the block header, in
+    /// the stream's preamble and tone levels (for the carousel's message tags)
+    pub(crate) fn next_tagged_block(&mut self, prefix: &[u8]) -> Option<Vec<f32>> {
+        let packet_data = self.next_packet()?;
+        let mut header = prefix.to_vec();
+        header.extend_from_slice(&self.block_header());
+        modulate_fountain_block(&mut self.fsk, &header, &packet_data, &self.preamble).ok()
+    }
+
+    /// Consult `check` before each block, e.g. to share the channel politely
+    ///
+    /// On [`ChannelAccess::Defer`] `next` returns that much silence instead of
+    /// a block and the same block is offered on a later call; the silence
+    /// counts toward the timeout. None removes the hook.
+    pub fn set_channel_check(&mut self, check: Option<ChannelCheck>) {
+        self.channel_check = check;
+    }
+
+    /// Defer blocks by `backoff` while `sense` hears the channel busy
+    ///
+    /// `sense` is shared with whoever feeds it microphone audio; a poisoned
+    /// lock reads as a clear channel.
+    pub fn listen_before_talk(&mut self, sense: Arc<Mutex<CarrierSense>>, backoff: Millis) {
+        let backoff = units::MODEM_RATE.samples(backoff).get().max(1);
+        self.set_channel_check(Some(Box::new(move || {
+            if sense.lock().is_ok_and(|sense| sense.is_busy()) {
+                ChannelAccess::Defer(backoff)
+            } else {
+                ChannelAccess::Clear
+            }
+        })));
+    }
+
+    /// Samples of silence emitted so far while waiting for a clear channel
+    pub fn deferred_samples(&self) -> usize {
+        self.deferred_samples
+    }
+
+    /// Samples emitted so far, blocks and deferral silence
+    pub fn samples_generated(&self) -> usize {
+        self.total_samples_generated
+    }
+
+    /// Samples after which the stream ends, None without a timeout
+    pub fn sample_limit(&self) -> Option<usize> {
+        (self.max_samples != usize::MAX).then_some(self.max_samples)
+    }
+}
+
+impl DecoderFsk {
+    /// Decode audio samples using fountain mode with continuous block accumulation
+    ///
+    /// Processes audio samples to extract fountain-encoded blocks and attempts
+    /// to decode the original data. Continues until successful decode or timeout.
+    ///
+    /// Returns the decoded payload or an error if decoding fails or timeout occurs.
+    pub fn decode_fountain(&mut self, samples: &[f32], config: Option<FountainConfig>) -> Result<Vec<u8>> {
+        self.decode_fountain_with_quality(samples, config).map(|(payload, _)| payload)
+    }
+
+    /// Fountain decode that also reports signal quality over the blocks that passed CRC
+    pub fn decode_fountain_with_quality(
+        &mut self,
+        samples: &[f32],
+        config: Option<FountainConfig>,
+    ) -> Result<(Vec<u8>, DecodeQuality)> {
+        let config = config.unwrap_or_default();
+        let mut search = FountainSearch::new(&config);
+        self.decode_fountain_window(SampleWindow::Slice(samples), &config, &mut search)
+    }
+
+    /// Fountain decode pulling audio from `reader` as it goes
+    ///
+    /// Only about one block of audio is held at a time, so recordings far
+    /// larger than memory can be decoded. Stops reading once the data is
+    /// recovered.
+    pub fn decode_fountain_reader<R: SampleReader>(
+        &mut self,
+        reader: &mut R,
+        config: Option<FountainConfig>,
+    ) -> Result<(Vec<u8>, DecodeQuality)> {
+        let config = config.unwrap_or_default();
+        let mut search = FountainSearch::new(&config);
+        let window = SampleWindow::Reader { reader, buffer: Vec::new(), base: 0, eof: false };
+        self.decode_fountain_window(window, &config, &mut search)
+    }
+
+    /// Add `samples` to the fountain decode of `listener`
+    ///
+    /// Returns the payload once enough blocks have arrived, else
+    /// `FountainDecodeFailure` until more audio is pushed. The listener keeps
+    /// the packets received so far but only the audio of a block still
+    /// arriving; use a new one for the next payload.
+    pub fn decode_fountain_push(
+        &mut self,
+        listener: &mut FountainListener,
+        samples: &[f32],
+    ) -> Result<(Vec<u8>, DecodeQuality)> {
+        listener.buffer.extend_from_slice(samples);
+        let FountainListener { config, search, buffer, base } = listener;
+        self.decode_fountain_window(SampleWindow::Pushed { buffer, base }, config, search)
+    }
+
+    fn decode_fountain_window(
+        &mut self,
+        mut samples: SampleWindow<'_>,
+        config: &FountainConfig,
+        search: &mut FountainSearch,
+    ) -> Result<(Vec<u8>, DecodeQuality)> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let start_time = Instant::now();
+        #[cfg(not(target_arch = "wasm32"))]
+        let timeout = Duration::from_secs(config.timeout_secs as u64);
+        #[cfg(target_arch = "wasm32")]
+        let _ = config;
+
+        let template = fountain_preamble(1.0, self.network_key(), self.fountain_melody());
+        if !search.started {
+            search.started = true;
+            self.fountain_progress = FountainProgress::default();
+            self.stats.fountain_drift = 0.0;
+            self.stats.fountain_tracked_blocks = 0;
+        }
+
+        loop {
+            // Check timeout (not available in WASM)
+            #[cfg(not(target_arch = "wasm32"))]
+            if start_time.elapsed() >= timeout {
+                return Err(AudioModemError::Timeout);
+            }
+            samples.discard_before(search.search_offset);
+            // Where this block starts over if pushed audio runs out partway
+            let (block_offset, block_tracker) = (search.search_offset, search.tracker);
+
+            // Look for next fountain preamble (three-note whistle), first close
+            // to where the block spacing so far puts it
+            let payload_samples_per_block = Self::fountain_payload_samples(search.expected_symbol_size);
+            let tracked = match search.tracker.window(search.search_offset) {
+                Some((start, end)) => {
+                    if samples.pending(end) {
+                        break;
+                    }
+                    let slice = samples.get(start, end)?;
+                    detect_fountain_template(slice, &template, self.get_preamble_threshold()).map(|pos| start + pos)
+                }
+                None => None,
+            };
+            let block_position = match tracked {
+                Some(position) => {
+                    search.tracker.found(position);
+                    self.stats.fountain_drift = search.tracker.drift;
+                    self.stats.fountain_tracked_blocks += 1;
+                    position
+                }
+                None => {
+                    // Lost track (a gap in the stream, or the first block): scan a whole block
+                    search.tracker.lost();
+                    let preamble_search_end = search.search_offset + PREAMBLE_SAMPLES + payload_samples_per_block;
+                    if samples.pending(preamble_search_end) {
+                        search.tracker = block_tracker;
+                        break;
+                    }
+                    let preamble_slice = samples.get(search.search_offset, preamble_search_end)?;
+                    if preamble_slice.len() < PREAMBLE_SAMPLES {
+                        break;
+                    }
+                    match detect_fountain_template(preamble_slice, &template, self.get_preamble_threshold()) {
+                        Some(pos) => search.search_offset + pos,
+                        // Nothing in the whole window: pushed audio moves on to the next one
+                        None if samples.is_pushed() => {
+                            search.search_offset = preamble_search_end - PREAMBLE_SAMPLES;
+                            continue;
+                        }
+                        None => break,
+                    }
+                }
+            };
+
+            // Account for silence after preamble (1/8 second = 2000 samples at 16kHz)
+            let data_start = block_position + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+
+            // Extract the expected FSK payload based on the current block size
+            let data_end = data_start.saturating_add(payload_samples_per_block);
+            if samples.pending(data_end) {
+                search.tracker = block_tracker;
+                break;
+            }
+            self.emit(DecodeEvent::PreambleDetected { position: block_position });
+            let region = samples.get(data_start, data_end)?;
+            if region.len() < payload_samples_per_block {
+                break;
+            }
+
+            // Demodulate fountain block; no postamble in fountain mode, so the
+            // search continues right after it
+            search.search_offset = data_end;
+            search.tracker.expect_after(data_end);
+            let Ok((mut block_data, mut block_stats)) = self.fsk.demodulate_with_stats(region) else {
+                continue;
+            };
+
+            // A header announcing another block size: re-read the block at that size
+            if let Some(announced) = parse_fountain_params(&block_data) {
+                if announced.symbol_size != search.expected_symbol_size {
+                    let announced_samples = Self::fountain_payload_samples(announced.symbol_size);
+                    let announced_end = data_start.saturating_add(announced_samples);
+                    if samples.pending(announced_end) {
+                        (search.search_offset, search.tracker) = (block_offset, block_tracker);
+                        break;
+                    }
+                    let region = samples.get(data_start, announced_end)?;
+                    if region.len() < announced_samples {
+                        continue;
+                    }
+                    let Ok(reread) = self.fsk.demodulate_with_stats(region) else {
+                        continue;
+                    };
+                    (block_data, block_stats) = reread;
+                    search.search_offset = announced_end;
+                    search.tracker.expect_after(announced_end);
+                }
+            }
+
+            let Some(block) = parse_fountain_block(&block_data) else {
+                continue;
+            };
+            if !block.crc_ok {
+                // Packet corrupted - skip it and continue
+                self.stats.failed_blocks += 1;
+                search.failed_blocks += 1;
+                self.emit(DecodeEvent::BlockCrcFailed { position: block_position });
+                continue;
+            }
+
+            // CRC passed - count as successfully decoded block
+            self.stats.decoded_blocks += 1;
+            let params = block.params();
+            let mut packets = vec![(block.packet.to_vec(), block_stats)];
+            match search.stream {
+                Some(current) if current == params => search.restart_blocks.clear(),
+                Some(current) => {
+                    // The sender may have restarted with another payload or
+                    // block size: switch once its blocks keep coming
+                    if search.restart_blocks.first().is_some_and(|(pending, _, _)| *pending != params) {
+                        search.restart_blocks.clear();
+                    }
+                    search.restart_blocks.push((params, block.packet.to_vec(), block_stats));
+                    if search.restart_blocks.len() < FOUNTAIN_RESTART_BLOCKS {
+                        continue;
+                    }
+                    info!("Fountain stream restarted: {:?} -> {:?}", current, params);
+                    self.stats.fountain_restarts += 1;
+                    search.decoder = None;
+                    search.symbol_stats = SymbolStats::default();
+                    search.failed_blocks = 0;
+                    search.received_blocks = 0;
+                    self.fountain_progress = FountainProgress::default();
+                    self.emit(DecodeEvent::StreamRestarted { position: block_position });
+                    packets = search.restart_blocks.drain(..).map(|(_, packet, stats)| (packet, stats)).collect();
+                }
+                None => {}
+            }
+
+            // Set up the fountain decoder from the first valid block of the stream
+            if search.decoder.is_none() {
+                match FountainReceiver::new(&block) {
+                    Ok(receiver) => search.decoder = Some(receiver),
+                    Err(e) => {
+                        warn!("Skipping fountain block: {}", e);
+                        continue;
+                    }
+                }
+                search.stream = Some(params);
+                search.expected_symbol_size = params.symbol_size;
+            }
+
+            // Add packets and try to decode
+            let Some(receiver) = search.decoder.as_mut() else { continue };
+            let needed = params.frame_length.div_ceil(params.symbol_size.max(1) as usize) as u32;
+            self.fountain_progress.source_symbols = needed;
+            for (packet, stats) in packets {
+                search.symbol_stats.merge(&stats);
+                let decoded = match receiver.add_packet(&packet) {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        warn!("Skipping fountain packet: {}", e);
+                        continue;
+                    }
+                };
+                search.received_blocks += 1;
+                if let Some(symbol_id) = receiver.symbol_id(&packet) {
+                    self.fountain_progress.record(symbol_id);
+                }
+                self.emit(DecodeEvent::BlockDecoded { position: block_position, received: search.received_blocks, needed });
+                // If decode fails (returns None), continue to next packet
+                if let Some(decoded_data) = decoded {
+                    // Successfully decoded! Extract frame; if that fails, continue to next packet
+                    if let Ok(frame) = FrameDecoder::decode(&decoded_data) {
+                        let quality = DecodeQuality::from_stats(&search.symbol_stats, 0, search.failed_blocks);
+                        self.emit(DecodeEvent::FountainRecovered { position: block_position, payload_len: frame.payload.len() });
+                        return Ok((frame.payload, quality));
+                    }
+                }
+            }
+        }
+
+        Err(AudioModemError::FountainDecodeFailure)
+    }
+
+    pub(crate) fn fountain_payload_samples(symbol_size: u16) -> usize {
+        // Conservative estimate: symbol_size + 14 bytes accounting for all overhead and CRC
+        // Breakdown: 8 bytes metadata + 2 bytes CRC + 4 bytes serialization overhead
+        //   - Metadata: frame_len(4) + symbol_size(2) + packet_len(2) = 8 bytes
+        //   - CRC-16: 2 bytes for corruption detection
+        //   - Serialization overhead: 4 bytes (RaptorQ packet encoding, alignment padding, or protocol fields)
+        // If the serialization format changes (e.g., bincode header size, RaptorQ encoding changes),
+        // adjust the 4-byte serialization overhead component accordingly.
+        let packet_bytes = symbol_size as usize + 14;
+        let symbols = packet_bytes.div_ceil(FSK_BYTES_PER_SYMBOL);
+        symbols * FSK_SYMBOL_SAMPLES
+    }
+}
+
+/// Where the next fountain block should start, from how the previous ones arrived
+///
+/// Sender and receiver clocks differ slightly, so blocks drift off the
+/// nominal spacing over a long stream. The tracker predicts each preamble
+/// after the block before it plus the drift measured so far, and smooths in
+/// the error of every preamble found near its prediction.
+#[derive(Debug, Default, Clone, Copy)]
+struct BlockTracker {
+    /// Predicted position of the next preamble, None when lost
+    predicted: Option<usize>,
+    /// The prediction before rounding, which errors are measured against
+    exact: f32,
+    /// Offset of preambles from the nominal spacing, in samples per block
+    drift: f32,
+}
+
+impl BlockTracker {
+    /// Predict the next preamble after a block whose audio ends at `data_end`
+    fn expect_after(&mut self, data_end: usize) {
+        self.exact = data_end as f32 + SYNC_SILENCE_SAMPLES as f32 + self.drift;
+        self.predicted = Some(self.exact.round().max(0.0) as usize);
+    }
+
+    /// Samples to search around the prediction, starting no earlier than `from`
+    fn window(&self, from: usize) -> Option<(usize, usize)> {
+        let predicted = self.predicted?;
+        let start = predicted.saturating_sub(FOUNTAIN_DRIFT_WINDOW_SAMPLES).max(from);
+        Some((start, predicted + FOUNTAIN_DRIFT_WINDOW_SAMPLES + PREAMBLE_SAMPLES))
+    }
+
+    /// The next preamble was found at `position` inside the window
+    fn found(&mut self, position: usize) {
+        // Against the rounded prediction, a drift of k + 0.5 samples would see no error
+        if self.predicted.take().is_some() {
+            self.drift += DRIFT_GAIN * (position as f32 - self.exact);
+        }
+    }
+
+    /// The next preamble was not near its prediction; the drift estimate is kept
+    fn lost(&mut self) {
+        self.predicted = None;
+    }
+}
+
+/// Where a fountain decode stands: the blocks collected and where to look next
+struct FountainSearch {
+    started: bool,
+    decoder: Option<FountainReceiver>,
+    search_offset: usize,
+    stream: Option<FountainParams>,
+    expected_symbol_size: u16,
+    /// Valid blocks of another transmission, collected until it counts as a restart
+    restart_blocks: Vec<(FountainParams, Vec<u8>, SymbolStats)>,
+    symbol_stats: SymbolStats,
+    failed_blocks: u32,
+    /// Valid blocks of the current transmission, for progress events
+    received_blocks: u32,
+    tracker: BlockTracker,
+}
+
+impl FountainSearch {
+    fn new(config: &FountainConfig) -> Self {
+        FountainSearch {
+            started: false,
+            decoder: None,
+            search_offset: 0,
+            stream: None,
+            expected_symbol_size: config.block_size as u16,
+            restart_blocks: Vec::new(),
+            symbol_stats: SymbolStats::default(),
+            failed_blocks: 0,
+            received_blocks: 0,
+            tracker: BlockTracker::default(),
+        }
+    }
+}
+
+/// Fountain decode fed audio as it arrives (see [`DecoderFsk::decode_fountain_push`])
+pub struct FountainListener {
+    config: FountainConfig,
+    search: FountainSearch,
+    /// Samples from `base` on that the search has not passed
+    buffer: Vec<f32>,
+    base: usize,
+}
+
+impl FountainListener {
+    pub fn new(config: Option<FountainConfig>) -> Self {
+        let config = config.unwrap_or_default();
+        let search = FountainSearch::new(&config);
+        FountainListener { config, search, buffer: Vec::new(), base: 0 }
+    }
+
+    /// Samples held until the search reaches them
+    pub fn buffered_samples(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+/// Audio seen by the fountain decoder: a slice, a reader buffered one window
+/// at a time, or the audio pushed to a [`FountainListener`] so far
+enum SampleWindow<'a> {
+    Slice(&'a [f32]),
+    Reader {
+        reader: &'a mut dyn SampleReader,
+        /// Samples from `base` on
+        buffer: Vec<f32>,
+        base: usize,
+        eof: bool,
+    },
+    Pushed {
+        /// Samples from `base` on
+        buffer: &'a mut Vec<f32>,
+        base: &'a mut usize,
+    },
+}
+
+impl SampleWindow<'_> {
+    /// Samples `start..end`, shorter when the audio ends first
+    fn get(&mut self, start: usize, end: usize) -> Result<&[f32]> {
+        match self {
+            SampleWindow::Slice(samples) => Ok(&samples[start.min(samples.len())..end.min(samples.len())]),
+            SampleWindow::Reader { reader, buffer, base, eof } => {
+                while *base + buffer.len() < end && !*eof {
+                    let wanted = end - (*base + buffer.len());
+                    *eof = reader.read_samples(buffer, wanted)? == 0;
+                }
+                let from = start.saturating_sub(*base).min(buffer.len());
+                let to = end.saturating_sub(*base).clamp(from, buffer.len());
+                Ok(&buffer[from..to])
+            }
+            SampleWindow::Pushed { buffer, base } => {
+                let from = start.saturating_sub(**base).min(buffer.len());
+                let to = end.saturating_sub(**base).clamp(from, buffer.len());
+                Ok(&buffer[from..to])
+            }
+        }
+    }
+
+    /// Whether samples up to `end` may still be pushed (pushed audio only;
+    /// the other windows end where their audio does)
+    fn pending(&self, end: usize) -> bool {
+        matches!(self, SampleWindow::Pushed { buffer, base } if **base + buffer.len() < end)
+    }
+
+    fn is_pushed(&self) -> bool {
+        matches!(self, SampleWindow::Pushed { .. })
+    }
+
+    /// Let go of the samples before `start`; they won't be asked for again
+    fn discard_before(&mut self, start: usize) {
+        let (buffer, base) = match self {
+            SampleWindow::Slice(_) => return,
+            SampleWindow::Reader { buffer, base, .. } => (buffer, base),
+            SampleWindow::Pushed { buffer, base } => (&mut **buffer, &mut **base),
+        };
+        let drop = start.saturating_sub(*base).min(buffer.len());
+        buffer.drain(..drop);
+        *base += drop;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::NetworkKey;
+    use crate::units::Samples;
+    use crate::SAMPLE_RATE;
+
+    /// Source packet count, repair counters and repairs per cycle of a RaptorQ stream
+    fn raptorq_state(stream: &FountainStream) -> (usize, Vec<u32>, usize) {
+        match &stream.source {
+            FountainSource::RaptorQ { source_packets, repair_counters, cycle, .. } => {
+                (source_packets.len(), repair_counters.clone(), cycle.repairs_per_cycle)
+            }
+            FountainSource::Lt { .. } => panic!("expected a RaptorQ stream"),
+        }
+    }
+
+    #[test]
+    fn test_fountain_network_key() {
+        let key = Some(NetworkKey::new(b"warehouse-7"));
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_network_key(key);
+        let mut decoder = DecoderFsk::new().unwrap();
+        // Fountain blocks use the keyed fountain preamble
+        let config = FountainConfig { timeout_secs: 5, block_size: 32, repair_blocks_ratio: 0.5, ..Default::default() };
+        let stream: Vec<f32> =
+            encoder.encode_fountain(b"keyed fountain", Some(config.clone())).unwrap().take(10).flatten().collect();
+        decoder.set_network_key(key);
+        assert_eq!(decoder.decode_fountain(&stream, Some(config.clone())).unwrap(), b"keyed fountain");
+        assert!(DecoderFsk::new().unwrap().decode_fountain(&stream, Some(config)).is_err());
+    }
+
+    #[test]
+    fn test_fountain_melody_preamble() {
+        let melody = crate::Melody::builder().note(659.0, Millis(60)).note(988.0, Millis(60)).note(1319.0, Millis(130)).build().unwrap();
+        let config = FountainConfig { timeout_secs: 5, block_size: 32, ..Default::default() };
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_fountain_melody(Some(melody.clone()));
+        let stream: Vec<f32> =
+            encoder.encode_fountain(b"brand jingle", Some(config.clone())).unwrap().take(10).flatten().collect();
+        // Each block opens with the melody after the leading silence
+        let preamble = &stream[SYNC_SILENCE_SAMPLES..SYNC_SILENCE_SAMPLES + PREAMBLE_SAMPLES];
+        assert_eq!(preamble, melody.generate(Samples(PREAMBLE_SAMPLES), 0.5).as_slice());
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_fountain_melody(Some(melody));
+        assert_eq!(decoder.decode_fountain(&stream, Some(config)).unwrap(), b"brand jingle");
+    }
+
+    #[test]
+    fn test_fountain_stream_basic() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Fountain test data";
+
+        let config = FountainConfig {
+            timeout_secs: 1, // Short timeout for test
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        // Generate some blocks
+        let blocks: Vec<_> = stream.take(5).collect();
+
+        // Should generate at least some blocks
+        assert!(!blocks.is_empty());
+
+        // Each block should contain preamble + data (no postamble in fountain mode)
+        for block in &blocks {
+            assert!(block.len() > PREAMBLE_SAMPLES);
+        }
+    }
+
+    #[test]
+    fn test_fountain_stream_timeout() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Timeout test";
+
+        let config = FountainConfig {
+            timeout_secs: 1,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        // Count blocks generated within timeout
+        let block_count = stream.count();
+
+        // Should generate some blocks but eventually stop
+        assert!(block_count > 0);
+        info!("Generated {} blocks in 1 second", block_count);
+    }
+
+    #[test]
+    fn test_fountain_default_config() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Default config test";
+
+        // Should work with default config
+        let mut stream = encoder.encode_fountain(data, None).unwrap();
+
+        // Should generate at least one block
+        assert!(stream.next().is_some());
+    }
+
+    #[test]
+    fn test_block_tracker_follows_drift() {
+        let mut tracker = BlockTracker::default();
+        assert_eq!(tracker.window(0), None);
+
+        // Every block arrives 8 samples later than the nominal spacing
+        let mut data_end = 10_000;
+        for _ in 0..8 {
+            tracker.expect_after(data_end);
+            let (start, end) = tracker.window(data_end).unwrap();
+            let actual = data_end + SYNC_SILENCE_SAMPLES + 8;
+            assert!(start <= actual && actual + PREAMBLE_SAMPLES <= end);
+            tracker.found(actual);
+            data_end = actual + 20_000;
+        }
+        assert!((tracker.drift - 8.0).abs() < 0.1, "drift {}", tracker.drift);
+
+        // Losing track keeps the estimate for the next prediction
+        tracker.expect_after(data_end);
+        tracker.lost();
+        assert_eq!(tracker.window(data_end), None);
+        tracker.expect_after(data_end);
+        assert_eq!(tracker.predicted, Some(data_end + SYNC_SILENCE_SAMPLES + 8));
+    }
+
+    #[test]
+    fn test_fountain_decode_with_clock_drift() {
+        // 3.6 s blocks: a minute of audio holds the sixteen taken
+        let config = FountainConfig { timeout_secs: 60, block_size: 32, ..Default::default() };
+        // Several source symbols, so the tracker sees a run of blocks
+        let data = b"sender clock runs slow ".repeat(8);
+        let blocks: Vec<Vec<f32>> =
+            EncoderFsk::new().unwrap().encode_fountain(&data, Some(config.clone())).unwrap().take(16).collect();
+        // Recorded 500 ppm fast: every block is longer than nominal
+        let drifted = crate::resample::resample_audio(&blocks.concat(), SAMPLE_RATE, SAMPLE_RATE + 8);
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert_eq!(decoder.decode_fountain(&drifted, Some(config)).unwrap(), data);
+        // Every block after the first was found near its prediction, which
+        // followed the stretch of each block
+        let stats = &decoder.stats;
+        assert!(stats.decoded_blocks > 1);
+        assert_eq!(stats.fountain_tracked_blocks + 1, stats.decoded_blocks + stats.failed_blocks, "{:?}", stats);
+        let expected_drift = (blocks[0].len() * 8) as f32 / SAMPLE_RATE as f32;
+        assert!((stats.fountain_drift - expected_drift).abs() < 3.0, "drift {} vs {}", stats.fountain_drift, expected_drift);
+    }
+
+    #[test]
+    fn test_fountain_channel_check_defers_blocks() {
+        let config = FountainConfig { timeout_secs: 10, block_size: 32, ..Default::default() };
+        let data = b"wait for a clear channel";
+        let expected: Vec<Vec<f32>> =
+            EncoderFsk::new().unwrap().encode_fountain(data, Some(config.clone())).unwrap().take(2).collect();
+
+        // Busy for the first two calls, then clear
+        let mut stream = EncoderFsk::new().unwrap().encode_fountain(data, Some(config)).unwrap();
+        let mut calls = 0;
+        stream.set_channel_check(Some(Box::new(move || {
+            calls += 1;
+            if calls <= 2 {
+                ChannelAccess::Defer(800)
+            } else {
+                ChannelAccess::Clear
+            }
+        })));
+        assert_eq!(stream.next().unwrap(), vec![0.0; 800]);
+        assert_eq!(stream.next().unwrap(), vec![0.0; 800]);
+        assert_eq!(stream.deferred_samples(), 1600);
+        // No block was skipped while waiting
+        assert_eq!(stream.next().unwrap(), expected[0]);
+        assert_eq!(stream.next().unwrap(), expected[1]);
+
+        stream.set_channel_check(Some(Box::new(|| ChannelAccess::Stop)));
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_fountain_listen_before_talk() {
+        // Long enough for a whole block and many deferrals after it
+        let config = FountainConfig { timeout_secs: 10, block_size: 32, ..Default::default() };
+        let mut stream = EncoderFsk::new().unwrap().encode_fountain(b"polite", Some(config)).unwrap();
+        let sense = Arc::new(Mutex::new(CarrierSense::new(Millis(200))));
+        stream.listen_before_talk(sense.clone(), Millis(100));
+
+        // Another station is transmitting
+        let other = EncoderFsk::new().unwrap().encode(b"someone else").unwrap();
+        sense.lock().unwrap().push(&other[..other.len() / 2]);
+        assert_eq!(stream.next().unwrap().len(), SAMPLE_RATE / 10);
+
+        // Quiet for longer than the hangover
+        sense.lock().unwrap().push(&vec![0.0005f32; SAMPLE_RATE / 2]);
+        assert!(stream.next().unwrap().len() > PREAMBLE_SAMPLES);
+
+        // A channel that never clears still ends at the timeout
+        sense.lock().unwrap().push(&other[..other.len() / 2]);
+        assert!(stream.by_ref().all(|chunk| chunk.len() == SAMPLE_RATE / 10));
+        assert!(stream.deferred_samples() > SAMPLE_RATE);
+    }
+
+    #[test]
+    fn test_fountain_respects_max_samples() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Max samples test";
+
+        let config = FountainConfig {
+            timeout_secs: 10, // Long timeout
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
+        let max_samples = stream.max_samples;
+
+        // Generate all blocks and verify total doesn't greatly exceed max_samples
+        // Note: May exceed by one block since we emit complete blocks without truncation
+        let total: usize = stream
+            .map(|block| block.len())
+            .sum();
+
+        // Allow some overshoot - one additional block beyond max_samples is acceptable
+        // since we emit complete blocks and never truncate mid-block
+        let max_allowed = max_samples + (50 * crate::fsk::FSK_SYMBOL_SAMPLES);
+
+        assert!(
+            total <= max_allowed,
+            "Total samples {} far exceeds max_samples {} with allowance ({} max)",
+            total,
+            max_samples,
+            max_allowed
+        );
+    }
+
+    #[test]
+    fn test_fountain_block_size_exceeds_u16_max() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Block size test";
+
+        // Create a config with block_size that exceeds u16::MAX
+        let config = FountainConfig {
+            timeout_secs: 1,
+            block_size: u16::MAX as usize + 1, // 65536
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let result = encoder.encode_fountain(data, Some(config));
+
+        // Should return an error due to invalid block_size
+        assert!(result.is_err());
+
+        // Verify the error message mentions the issue
+        if let Err(err) = result {
+            let err_msg = format!("{:?}", err);
+            assert!(
+                err_msg.contains("block_size") || err_msg.contains("exceeds"),
+                "Error should mention block_size or exceeds: {}",
+                err_msg
+            );
+        }
+    }
+
+    #[test]
+    fn test_fountain_block_size_at_u16_max() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Block size at max test";
+
+        // Create a config with block_size exactly at u16::MAX
+        let config = FountainConfig {
+            timeout_secs: 1,
+            block_size: u16::MAX as usize,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        // Should succeed with u16::MAX
+        let result = encoder.encode_fountain(data, Some(config));
+        assert!(result.is_ok(), "Should accept block_size at u16::MAX");
+    }
+
+    #[test]
+    fn test_fountain_uses_sample_rate_constant() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Sample rate test";
+
+        let timeout_secs = 5;
+        let config = FountainConfig {
+            timeout_secs,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        // Verify that max_samples is calculated using crate::SAMPLE_RATE
+        let expected_max_samples = timeout_secs as usize * SAMPLE_RATE;
+        assert_eq!(
+            stream.max_samples, expected_max_samples,
+            "max_samples should be calculated as timeout_secs * SAMPLE_RATE ({} * {} = {})",
+            timeout_secs, SAMPLE_RATE, expected_max_samples
+        );
+    }
+
+    #[test]
+    fn test_fountain_stream_progress() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let config = FountainConfig { timeout_secs: 3, block_size: 16, ..Default::default() };
+        let mut stream = encoder.encode_fountain(b"Progress", Some(config)).unwrap();
+        assert_eq!(stream.sample_limit(), Some(3 * SAMPLE_RATE));
+        assert_eq!(stream.samples_generated(), 0);
+
+        let block = stream.next().unwrap();
+        assert_eq!(stream.samples_generated(), block.len());
+        let total: usize = block.len() + stream.by_ref().map(|block| block.len()).sum::<usize>();
+        assert_eq!(stream.samples_generated(), total);
+
+        let config = FountainConfig { timeout_secs: 0, block_size: 16, ..Default::default() };
+        let stream = encoder.encode_fountain(b"Progress", Some(config)).unwrap();
+        assert_eq!(stream.sample_limit(), None);
+    }
+
+    #[test]
+    fn test_fountain_repair_packets_have_unique_data() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Unique repair packet test - verify different packets have different encoded data";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: FOUNTAIN_BLOCK_SIZE,
+            repair_blocks_ratio: 1.0, // 100% repair overhead for more repair packets
+            ..Default::default()
+        };
+
+        // Test by checking the underlying RaptorQ packets directly
+        // This is more reliable than trying to extract from FSK-modulated audio
+        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        let mut packet_serializations: Vec<Vec<u8>> = Vec::new();
+
+        // Collect first few packets to analyze
+        for _ in 0..20 {
+            if let Some(packet) = stream.next_packet() {
+                packet_serializations.push(packet);
+            } else {
+                break;
+            }
+        }
+
+        assert!(packet_serializations.len() >= 3, "Should collect at least 3 packet serializations (got {})", packet_serializations.len());
+
+        // Count unique serialized packets
+        let mut unique_packets = std::collections::HashSet::new();
+        for pkt in &packet_serializations {
+            unique_packets.insert(pkt.clone());
+        }
+
+        // With source packets + repair packets, we should see at least 2 unique patterns
+        assert!(
+            unique_packets.len() >= 2,
+            "Should see at least 2 unique serialized packets (source + repair), got {}",
+            unique_packets.len()
+        );
+
+        info!("Generated {} unique serialized packets from {} total", unique_packets.len(), packet_serializations.len());
+    }
+
+    #[test]
+    fn test_fountain_source_packets_are_identical() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Source packet identity test";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: FOUNTAIN_BLOCK_SIZE,
+            repair_blocks_ratio: 0.0, // Only source packets, no repairs
+            ..Default::default()
+        };
+
+        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        // With repair_ratio 0.0, next_packet should return source packets from the same set
+        let mut source_packets: Vec<Vec<u8>> = Vec::new();
+
+        for _ in 0..10 {
+            if let Some(packet) = stream.next_packet() {
+                source_packets.push(packet);
+            } else {
+                break;
+            }
+        }
+
+        assert!(source_packets.len() >= 2, "Should get at least 2 source packets (got {})", source_packets.len());
+
+        // With repair_ratio 0.0, it should cycle through source packets repeatedly
+        // So the first few source packets should repeat
+        let pkt1 = &source_packets[0];
+        let pkt2 = &source_packets[1];
+
+        // Check if we have the same packet (indicating cycling through source)
+        // OR if they're different (indicating multiple source blocks)
+        // Either is valid - just verify the mechanism works
+
+        let (num_source, _, _) = raptorq_state(&stream);
+        if source_packets.len() >= num_source * 2 {
+            // If we got at least 2 full cycles, first packet should repeat somewhere
+            let found_repeat = source_packets.iter().skip(num_source)
+                .any(|pkt| pkt == pkt1);
+            assert!(found_repeat, "With repair_ratio=0, source packets should repeat in cycles");
+        } else {
+            // Not enough packets to verify cycling yet, just verify we got packets
+            assert!(!source_packets.is_empty(), "Should have generated source packets");
+        }
+
+        info!("Generated {} source packets, {}unique serializations", source_packets.len(),
+            source_packets.iter().collect::<std::collections::HashSet<_>>().len());
+    }
+
+    #[test]
+    fn test_fountain_packets_differ_from_metadata() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let data = b"Test that packet payloads are different, not just metadata";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: FOUNTAIN_BLOCK_SIZE,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        // Collect serialized packets to directly compare their payloads
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+
+        for _ in 0..30 {
+            if let Some(packet) = stream.next_packet() {
+                packets.push(packet);
+            } else {
+                break;
+            }
+        }
+
+        assert!(packets.len() >= 5, "Should collect at least 5 packets (got {})", packets.len());
+
+        // Count unique packet serializations
+        let mut unique_packets = std::collections::HashSet::new();
+        for pkt in &packets {
+            unique_packets.insert(pkt.clone());
+        }
+
+        // Key assertion: With repair packets, we should see multiple unique serializations
+        // This proves that blocks contain different data, not just different metadata
+        //
+        // - Source packets (K packets): may repeat in cycles
+        // - Repair packets (ESI >= K): each counter value produces different repair packets
+        //
+        // So we should see at least: 1 unique source pattern + 2+ unique repair patterns = 3+ unique
+        // Or at minimum 2 unique (source repeated, then at least 1 repair different)
+        assert!(
+            unique_packets.len() >= 2,
+            "Should see at least 2 unique packet serializations with repair packets enabled. Got {}. This suggests packets might not have unique data payloads.",
+            unique_packets.len()
+        );
+
+        // Count how many are likely repair packets (come after source packets)
+        let (num_source_expected, _, _) = raptorq_state(&stream);
+        let num_repairs_generated = packets.len().saturating_sub(num_source_expected);
+
+        info!(
+            "Generated {} total packets: ~{} source, ~{} repairs. Found {} unique serializations",
+            packets.len(), num_source_expected, num_repairs_generated, unique_packets.len()
+        );
+    }
+
+    #[test]
+    fn test_fountain_repair_counter_increments() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        // Use longer data to ensure multiple source packets
+        let data = b"Repair counter increment test with enough data to have multiple source blocks";
+
+        let config = FountainConfig {
+            timeout_secs: 5,
+            block_size: FOUNTAIN_BLOCK_SIZE,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        let mut stream = encoder.encode_fountain(data, Some(config)).unwrap();
+
+        // Verify repair_counters are initialized
+        let (num_source_packets, initial_counters, _) = raptorq_state(&stream);
+        assert!(!initial_counters.is_empty(), "Should have repair counters for source blocks");
+
+        let num_blocks = initial_counters.len();
+
+        // Generate enough blocks to ensure we get into repair packet generation
+        // With repair_ratio 1.0, we should get source_packets + repairs_per_cycle packets per cycle
+        let block_count = num_source_packets * 2 + 20;
+
+        for _ in 0..block_count {
+            if stream.next().is_none() {
+                break;
+            }
+        }
+
+        // After generation, repair counters should have been incremented (assuming we generated repair packets)
+        let (_, final_counters, repairs_per_cycle) = raptorq_state(&stream);
+
+        // If we have multiple blocks and repair ratio > 0, at least some counters should be > 0
+        if num_blocks > 1 && repairs_per_cycle > 0 {
+            let incremented_count = final_counters.iter().filter(|c| **c > 0).count();
+            assert!(
+                incremented_count > 0,
+                "With {} blocks and repair_ratio=1.0, at least one counter should be incremented, got: {:?}",
+                num_blocks, final_counters
+            );
+        }
+
+        info!(
+            "Repair counters after generation: {:?} (num_blocks={}, repairs_per_cycle={})",
+            final_counters, num_blocks, repairs_per_cycle
+        );
+    }
+
+    #[test]
+    fn test_fountain_roundtrip_basic() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Fountain roundtrip test";
+
+        let config = FountainConfig {
+            timeout_secs: 5,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        // Generate fountain blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+
+        // Collect multiple blocks into one continuous audio stream
+        let blocks: Vec<_> = stream.take(10).collect();
+        let mut samples = Vec::new();
+        for block in blocks {
+            samples.extend_from_slice(&block);
+        }
+
+        // Decode
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_fountain_events() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let config = FountainConfig {
+            timeout_secs: 5,
+            block_size: 16,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+        let data = b"Fountain progress events";
+        let samples: Vec<f32> = encoder.encode_fountain(data, Some(config.clone())).unwrap().take(8).flatten().collect();
+
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_event_sender(Some(sender));
+        assert_eq!(decoder.decode_fountain(&samples, Some(config)).unwrap(), data);
+
+        let events: Vec<DecodeEvent> = events.try_iter().collect();
+        assert_eq!(events[0].kind(), "preamble");
+        let blocks: Vec<(u32, u32)> = events
+            .iter()
+            .filter_map(|e| match *e {
+                DecodeEvent::BlockDecoded { received, needed, .. } => Some((received, needed)),
+                _ => None,
+            })
+            .collect();
+        assert!(!blocks.is_empty());
+        assert!(blocks.iter().enumerate().all(|(i, &(received, _))| received == i as u32 + 1));
+        assert!(blocks.iter().all(|&(_, needed)| needed >= 2));
+        assert!(matches!(events.last(), Some(DecodeEvent::FountainRecovered { payload_len, .. }) if *payload_len == data.len()));
+        assert!(events.windows(2).all(|w| w[0].position() <= w[1].position()));
+    }
+
+    #[test]
+    fn test_fountain_progress() {
+        // Blocks are 2.4 s long: room for all ten
+        let config = FountainConfig { timeout_secs: 30, block_size: 16, ..Default::default() };
+        let data = b"Fountain symbol map, drawn one received packet at a time";
+        let blocks: Vec<Vec<f32>> =
+            EncoderFsk::new().unwrap().encode_fountain(data, Some(config.clone())).unwrap().take(10).collect();
+
+        // Two source symbols, one of them twice: not enough yet
+        let mut decoder = DecoderFsk::new().unwrap();
+        let partial: Vec<f32> = [&blocks[0], &blocks[1], &blocks[0]].into_iter().flatten().copied().collect();
+        assert!(decoder.decode_fountain(&partial, Some(config.clone())).is_err());
+        let progress = decoder.fountain_progress().clone();
+        assert_eq!(progress.symbol_ids, [0, 1]);
+        assert_eq!(progress.source_symbols, (crate::FRAME_HEADER_SIZE + data.len() + 2).div_ceil(16) as u32);
+        assert_eq!(progress.remaining(), progress.source_symbols - 2);
+
+        let all: Vec<f32> = blocks.concat();
+        assert_eq!(decoder.decode_fountain(&all, Some(config)).unwrap(), data);
+        assert_eq!(decoder.fountain_progress().remaining(), 0);
+        assert!(decoder.fountain_progress().symbol_ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_fountain_lt_roundtrip() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"LT fountain without RaptorQ";
+        let config = FountainConfig {
+            timeout_secs: 0,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            code: FountainCode::Lt(DegreeDistribution::IdealSoliton),
+        };
+
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        assert!(matches!(stream.code(), FountainCode::Lt(DegreeDistribution::IdealSoliton)));
+        // Lose the first two blocks so repair packets are needed
+        let samples: Vec<f32> = stream.skip(2).take(12).flatten().collect();
+
+        // The decoder detects the code from the blocks
+        let decoder_config = FountainConfig { timeout_secs: 10, code: FountainCode::RaptorQ, ..config };
+        let decoded = decoder.decode_fountain(&samples, Some(decoder_config)).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_fountain_falls_back_to_lt_for_tiny_symbols() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let config = FountainConfig { timeout_secs: 10, block_size: 4, ..Default::default() };
+
+        let stream = encoder.encode_fountain(b"tiny", Some(FountainConfig { timeout_secs: 0, ..config.clone() })).unwrap();
+        assert!(matches!(stream.code(), FountainCode::Lt(_)));
+        let samples: Vec<f32> = stream.take(12).flatten().collect();
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert_eq!(decoder.decode_fountain(&samples, Some(config)).unwrap(), b"tiny");
+    }
+
+    #[test]
+    fn test_fountain_decode_with_quality() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let config = FountainConfig {
+            timeout_secs: 5,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let samples: Vec<f32> = encoder
+            .encode_fountain(b"Fountain quality", Some(config.clone()))
+            .unwrap()
+            .take(10)
+            .flatten()
+            .collect();
+
+        let (payload, quality) = decoder.decode_fountain_with_quality(&samples, Some(config)).unwrap();
+        assert_eq!(payload, b"Fountain quality");
+        assert!(quality.min_symbol_margin > 0.5, "{:?}", quality);
+        assert!(quality.snr_db > 20.0, "{:?}", quality);
+        assert_eq!(quality.failed_blocks, 0);
+    }
+
+    #[test]
+    fn test_fountain_decode_from_wav_stream() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let config = FountainConfig {
+            timeout_secs: 5,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+        let samples: Vec<f32> = encoder
+            .encode_fountain(b"Streamed from disk", Some(config.clone()))
+            .unwrap()
+            .take(10)
+            .flatten()
+            .collect();
+
+        let mut in_memory = DecoderFsk::new().unwrap();
+        let expected = in_memory.decode_fountain_with_quality(&samples, Some(config.clone())).unwrap();
+
+        let wav = crate::modem_samples_to_wav_bytes(&samples, crate::WavSampleFormat::Float32).unwrap();
+        let mut reader = crate::WavStreamReader::new(std::io::Cursor::new(wav)).unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let (payload, quality) = decoder.decode_fountain_reader(&mut reader, Some(config.clone())).unwrap();
+        assert_eq!(payload, b"Streamed from disk");
+        assert_eq!(quality, expected.1);
+        assert_eq!(decoder.stats.decoded_blocks, in_memory.stats.decoded_blocks);
+
+        // Audio without a transmission runs out cleanly
+        let mut silence: &[f32] = &[0.0; 40000];
+        let result = DecoderFsk::new().unwrap().decode_fountain_reader(&mut silence, Some(config));
+        assert!(matches!(result, Err(AudioModemError::FountainDecodeFailure)));
+    }
+
+    #[test]
+    fn test_fountain_decode_pushed_chunks() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let config = FountainConfig { block_size: 32, ..Default::default() };
+        let mut samples = vec![0.0; SAMPLE_RATE];
+        samples.extend(encoder.encode_fountain(b"Pushed in chunks", Some(config.clone())).unwrap().take(10).flatten());
+        let mut in_memory = DecoderFsk::new().unwrap();
+        let expected = in_memory.decode_fountain_with_quality(&samples, Some(config.clone())).unwrap();
+
+        // Chunks that split preambles and blocks; only about a block stays buffered
+        let chunk = 5003;
+        let block_samples = PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES + DecoderFsk::fountain_payload_samples(32);
+        let mut listener = FountainListener::new(Some(config));
+        let mut decoder = DecoderFsk::new().unwrap();
+        let mut recovered = None;
+        for piece in samples.chunks(chunk) {
+            match decoder.decode_fountain_push(&mut listener, piece) {
+                Ok(result) => {
+                    recovered = Some(result);
+                    break;
+                }
+                Err(e) => assert!(matches!(e, AudioModemError::FountainDecodeFailure), "{}", e),
+            }
+            assert!(listener.buffered_samples() <= 2 * block_samples + chunk, "{} buffered", listener.buffered_samples());
+        }
+        assert_eq!(recovered.unwrap(), expected);
+        assert_eq!(decoder.stats.decoded_blocks, in_memory.stats.decoded_blocks);
+        assert_eq!(decoder.fountain_progress(), in_memory.fountain_progress());
+    }
+
+    #[test]
+    fn test_fountain_with_packet_loss() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Test with some packet loss";
+
+        let config = FountainConfig {
+            timeout_secs: 30, // Enough audio duration to generate 20 blocks
+            block_size: 32,
+            repair_blocks_ratio: 1.0, // More redundancy
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Simulate packet loss by dropping every 3rd block
+        let mut samples = Vec::new();
+        for (i, block) in blocks.iter().enumerate() {
+            if i % 3 != 0 {  // Drop every 3rd block
+                samples.extend_from_slice(block);
+            }
+        }
+
+        // Should still decode successfully due to fountain coding
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_fountain_various_data_sizes() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+
+        let config = FountainConfig {
+            timeout_secs: 20, // Enough audio duration to generate 15 blocks
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        let test_cases = vec![
+            b"A".to_vec(),
+            b"Short".to_vec(),
+            b"Medium length data for testing".to_vec(),
+            vec![42u8; 100],
+        ];
+
+        for data in test_cases {
+            let stream = encoder.encode_fountain(&data, Some(config.clone())).unwrap();
+            let blocks: Vec<_> = stream.take(15).collect();
+            let mut samples = Vec::new();
+            for block in blocks {
+                samples.extend_from_slice(&block);
+            }
+
+            let decoded = decoder.decode_fountain(&samples, Some(config.clone())).unwrap();
+            assert_eq!(decoded, data, "Failed for data length {}", data.len());
+        }
+    }
+
+    #[test]
+    fn test_fountain_single_block_crc_corruption() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Test CRC corruption detection";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(15).collect();
+
+        // Corrupt the first block with bit flipping pattern
+        let mut corrupted_blocks = blocks.clone();
+        let first_block = &mut corrupted_blocks[0];
+
+        // Flip bits in the middle section (simulate bit corruption in CRC area)
+        for i in (first_block.len() / 2)..(first_block.len() / 2 + 60).min(first_block.len()) {
+            // Flip sign bit to corrupt the sample
+            let bits = first_block[i].to_bits() ^ 0x80000000u32;
+            first_block[i] = f32::from_bits(bits);
+        }
+
+        // Reconstruct audio stream
+        let mut samples = Vec::new();
+        for block in &corrupted_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Decoding should still succeed with remaining good blocks
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should recover despite first block corruption");
+    }
+
+    #[test]
+    fn test_fountain_multiple_blocks_crc_corruption() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Test multiple corruptions";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0, // Extra redundancy
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Corrupt multiple blocks with different patterns - inject invalid data selectively
+        let mut corrupted_blocks = blocks.clone();
+        for block_idx in [0, 3, 7] {
+            if block_idx < corrupted_blocks.len() {
+                let block = &mut corrupted_blocks[block_idx];
+                // Corrupt only a small portion with invalid data to allow recovery
+                let corruption_start = (block.len() / 4).max(20);
+                let corruption_end = (corruption_start + 25).min(block.len());
+                for i in corruption_start..corruption_end {
+                    // Flip bits to simulate data corruption without destroying entire block
+                    let bits = block[i].to_bits() ^ 0x80000000u32;  // Flip sign bit
+                    block[i] = f32::from_bits(bits);
+                }
+            }
+        }
+
+        // Reconstruct audio stream
+        let mut samples = Vec::new();
+        for block in &corrupted_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should still succeed with sufficient redundancy
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should recover with multiple block corruptions");
+    }
+
+    #[test]
+    fn test_fountain_crc_rejects_invalid_packets() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Verify CRC validation";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: FOUNTAIN_BLOCK_SIZE,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(12).collect();
+
+        // Create a highly corrupted block by mixing in invalid data
+        let mut corrupted_blocks = blocks.clone();
+        if !corrupted_blocks.is_empty() {
+            let block = &mut corrupted_blocks[0];
+            // Corrupt with invalid FSK samples - flip bits in multiple sections
+            for i in 0..block.len().min(80) {
+                // Flip bits at every 3rd position to create detectable corruption
+                if i % 3 == 0 {
+                    let bits = block[i].to_bits() ^ 0x80000000u32;  // Flip sign bit
+                    block[i] = f32::from_bits(bits);
+                }
+            }
+        }
+
+        // Reconstruct audio stream with corrupted first block
+        let mut samples = Vec::new();
+        for block in &corrupted_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Decoding should succeed by skipping the corrupted block and using others
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should skip invalid CRC blocks");
+    }
+
+    #[test]
+    fn test_fountain_crc_detects_bit_flips() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Bit flip detection test";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 0.75,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(15).collect();
+
+        // Corrupt early blocks with systematic bit flipping
+        let mut corrupted_blocks = blocks.clone();
+        for block_idx in 0..2.min(corrupted_blocks.len()) {
+            let block = &mut corrupted_blocks[block_idx];
+            // Simulate bit flips in the middle section
+            let start = block.len() / 3;
+            let end = (start + 40).min(block.len());
+            for i in start..end {
+                // Flip sign bit to corrupt samples
+                let bits = block[i].to_bits() ^ 0x80000000u32;
+                block[i] = f32::from_bits(bits);
+            }
+        }
+
+        // Reconstruct audio stream
+        let mut samples = Vec::new();
+        for block in &corrupted_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should still decode successfully
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should handle bit flip corruptions");
+    }
+
+    #[test]
+    fn test_fountain_crc_with_packet_loss_and_corruption() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Combined loss and corruption";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Simulate both packet loss and corruption with invalid data injection
+        let mut samples = Vec::new();
+        for (i, block) in blocks.iter().enumerate() {
+            let mut modified_block = block.clone();
+
+            // Drop every 5th block (packet loss)
+            if i % 5 == 0 {
+                continue;
+            }
+
+            // Corrupt every 3rd block that we keep with bit flipping
+            if (i / 3) % 2 == 0 {
+                let start = modified_block.len() / 4;
+                let end = (start + 30).min(modified_block.len());
+                for j in start..end {
+                    // Flip sign bit in corrupted section
+                    let bits = modified_block[j].to_bits() ^ 0x80000000u32;
+                    modified_block[j] = f32::from_bits(bits);
+                }
+            }
+
+            samples.extend_from_slice(&modified_block);
+        }
+
+        // Should still decode with sufficient redundancy
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should handle packet loss + corruption");
+    }
+
+    #[test]
+    fn test_fountain_crc_passes_for_clean_data() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Clean data with valid CRC";
+
+        let config = FountainConfig {
+            timeout_secs: 20,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        // Generate blocks (no corruption)
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(10).collect();
+
+        let mut samples = Vec::new();
+        for block in blocks {
+            samples.extend_from_slice(&block);
+        }
+
+        // All CRCs should pass, decoding should be quick
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Clean data should decode correctly");
+    }
+
+    #[test]
+    fn test_fountain_integration_alternating_good_bad_blocks() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Integration test: alternating good/bad blocks";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 0.75,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(18).collect();
+
+        // Pattern: good, bad, good, bad, ... (alternating with bit flipping)
+        let mut processed_blocks = blocks.clone();
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            if i % 2 == 1 {
+                // Corrupt odd-indexed blocks (1, 3, 5, ...) with bit flipping
+                let start = block.len() / 3;
+                let end = (start + 35).min(block.len());
+                for j in start..end {
+                    // Flip sign bit in corrupted section
+                    let bits = block[j].to_bits() ^ 0x80000000u32;
+                    block[j] = f32::from_bits(bits);
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should successfully decode despite every other block being bad
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with alternating good/bad blocks");
+    }
+
+    #[test]
+    fn test_fountain_integration_burst_corruption() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Burst error: consecutive bad blocks";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0, // Extra redundancy for burst recovery
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Simulate burst corruption: blocks 3-7 all corrupted with bit flipping
+        let mut processed_blocks = blocks.clone();
+        for i in 3..8 {
+            if i < processed_blocks.len() {
+                let block = &mut processed_blocks[i];
+                // Corrupt with bit flipping patterns
+                let start = block.len() / 4;
+                let end = (start + 35).min(block.len());
+                for j in start..end {
+                    // Flip sign bit to corrupt the sample
+                    let bits = block[j].to_bits() ^ 0x80000000u32;
+                    block[j] = f32::from_bits(bits);
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should recover despite burst of 5 consecutive bad blocks
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should recover from burst corruption with good blocks before/after");
+    }
+
+    #[test]
+    fn test_fountain_integration_sparse_good_blocks() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Sparse: mostly bad, few good blocks";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate many blocks to ensure we have enough good ones
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(25).collect();
+
+        // Keep only blocks at indices 2, 5, 10, 15, 22 (sparse good blocks)
+        let mut processed_blocks = blocks.clone();
+        let good_indices = [2, 5, 10, 15, 22];
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            if !good_indices.contains(&i) {
+                // Corrupt all blocks except the good ones with bit flipping
+                let start = block.len() / 5;
+                let end = (start + 40).min(block.len());
+                for j in start..end {
+                    // Flip sign bit in corrupted section
+                    let bits = block[j].to_bits() ^ 0x80000000u32;
+                    block[j] = f32::from_bits(bits);
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode with minimal good blocks due to fountain redundancy
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with sparse valid blocks");
+    }
+
+    #[test]
+    fn test_fountain_integration_good_blocks_early_then_bad() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Good early, bad later: early blocks good";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 0.5,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(16).collect();
+
+        // First 6 blocks are good, remaining are bad
+        let mut processed_blocks = blocks.clone();
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            if i >= 6 {
+                // Corrupt blocks 6 and onwards
+                for j in 0..block.len().min(120) {
+                    block[j] = block[j] * 0.25;
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should successfully decode with good source blocks at beginning
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode when first blocks are clean");
+    }
+
+    #[test]
+    fn test_fountain_integration_bad_blocks_early_then_good() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Bad early, good later: repair blocks work";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 0.75,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(18).collect();
+
+        // First 5 blocks are bad, remaining are good
+        let mut processed_blocks = blocks.clone();
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            if i < 5 {
+                // Corrupt blocks 0-4
+                for j in 0..block.len().min(130) {
+                    block[j] = block[j] * 0.1;
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode because repair blocks arrive later
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with repair blocks after bad source blocks");
+    }
+
+    #[test]
+    fn test_fountain_integration_random_pass_fail_pattern() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Random: unpredictable pass/fail pattern";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Random corruption pattern: 2,4,5,8,12,14,17 are corrupted
+        let corrupted_indices = [2, 4, 5, 8, 12, 14, 17];
+        let mut processed_blocks = blocks.clone();
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            if corrupted_indices.contains(&i) {
+                // Corrupt these blocks
+                let corruption_level = 0.2 + (i as f32 * 0.05);
+                for j in 0..block.len().min(100 + i * 5) {
+                    block[j] = block[j] * corruption_level;
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should successfully decode despite random corruption pattern
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with random pass/fail pattern");
+    }
+
+    #[test]
+    fn test_fountain_integration_progressive_degradation() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Progressive: quality degrades over time";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Simulate progressive channel degradation with escalating invalid data injection
+        let mut processed_blocks = blocks.clone();
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            // Corruption level increases with block index
+            let corruption_level = 0.1 + (i as f32 * 0.04);
+            if corruption_level > 0.5 {
+                // Corrupt with progressive bit flipping
+                let start = block.len() / 6;
+                let end = (start + 30 + i).min(block.len());
+                for j in start..end {
+                    // Progressive bit flipping as blocks degrade
+                    let bits = block[j].to_bits() ^ 0x80000000u32;
+                    block[j] = f32::from_bits(bits);
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should still decode despite progressive quality loss
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode despite progressive degradation");
+    }
+
+    #[test]
+    fn test_fountain_integration_isolated_good_blocks_spread() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Spread: isolated good blocks widely separated";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate many blocks to test with sparse good ones
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(24).collect();
+
+        // Good blocks at indices 1, 6, 11, 16, 21 (widely spread)
+        let good_indices = [1, 6, 11, 16, 21];
+        let mut processed_blocks = blocks.clone();
+        for (i, block) in processed_blocks.iter_mut().enumerate() {
+            if !good_indices.contains(&i) {
+                // Corrupt all others with varying severity
+                let severity = (i % 4) as f32 * 0.2 + 0.1;
+                for j in 0..block.len().min(100 + (i * 2)) {
+                    block[j] = block[j] * severity;
+                }
+            }
+        }
+
+        let mut samples = Vec::new();
+        for block in &processed_blocks {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode with isolated good blocks spread apart
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with widely separated good blocks");
+    }
+
+    #[test]
+    fn test_fountain_missing_first_blocks() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Test missing first blocks";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Skip the first 5 blocks and use the rest
+        let mut samples = Vec::new();
+        for block in blocks.iter().skip(5) {
+            samples.extend_from_slice(block);
+        }
+
+        // Should still decode successfully with fountain coding providing repair packets
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode even with first 5 blocks missing");
+    }
+
+    #[test]
+    fn test_fountain_missing_first_several_blocks() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Missing first several blocks test";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.5, // Extra repair overhead
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(25).collect();
+
+        // Skip the first 6 blocks (about 24% loss at start)
+        let mut samples = Vec::new();
+        for block in blocks.iter().skip(6) {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode with sufficient repair packets
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with first 6 blocks missing");
+    }
+
+    #[test]
+    fn test_fountain_alternating_first_blocks_missing() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Alternating missing early blocks";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.0,
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Keep only even-indexed blocks in first 10 (skip odd blocks at start)
+        let mut samples = Vec::new();
+        for (i, block) in blocks.iter().enumerate() {
+            if i < 10 {
+                if i % 2 == 0 {
+                    samples.extend_from_slice(block);
+                }
+            } else {
+                // Keep all blocks after index 10
+                samples.extend_from_slice(block);
+            }
+        }
+
+        // Should decode with alternating blocks missing from the beginning
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with alternating early blocks missing");
+    }
+
+    #[test]
+    fn test_fountain_first_half_blocks_missing() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"First half missing test data";
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.5, // Need more redundancy
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(30).collect();
+
+        // Skip first half (first 15 blocks)
+        let mut samples = Vec::new();
+        for block in blocks.iter().skip(blocks.len() / 2) {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode with 50% of initial blocks missing
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode with first half of blocks missing");
+    }
+
+    #[test]
+    fn test_fountain_missing_first_blocks_small_data() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = b"Hi";
+
+        let config = FountainConfig {
+            timeout_secs: 20,
+            block_size: 32,
+            repair_blocks_ratio: 2.0, // Very high repair ratio for small data
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(15).collect();
+
+        // Skip first 3 blocks
+        let mut samples = Vec::new();
+        for block in blocks.iter().skip(3) {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode even with small data and missing initial blocks
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode small data with first 3 blocks missing");
+    }
+
+    #[test]
+    fn test_fountain_missing_first_blocks_large_data() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let data = vec![99u8; 120]; // Larger data payload (2x small data test)
+
+        let config = FountainConfig {
+            timeout_secs: 30,
+            block_size: 32,
+            repair_blocks_ratio: 1.5, // Extra repair overhead
+            ..Default::default()
+        };
+
+        // Generate blocks
+        let stream = encoder.encode_fountain(&data, Some(config.clone())).unwrap();
+        let blocks: Vec<_> = stream.take(20).collect();
+
+        // Skip first 4 blocks (20% loss at start)
+        let mut samples = Vec::new();
+        for block in blocks.iter().skip(4) {
+            samples.extend_from_slice(block);
+        }
+
+        // Should decode larger data even with missing early blocks
+        let decoded = decoder.decode_fountain(&samples, Some(config)).unwrap();
+        assert_eq!(decoded, data, "Should decode 120-byte data with first 4 blocks missing");
+    }
+
+    #[test]
+    fn test_fountain_follows_sender_restart() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let first = FountainConfig { block_size: 32, ..Default::default() };
+        let second = FountainConfig { block_size: 48, ..Default::default() };
+
+        // Two blocks of an abandoned transmission, then the sender starts over
+        // with another payload and block size
+        let abandoned = encoder.encode_fountain(&[7u8; 120], Some(first.clone())).unwrap();
+        let mut samples: Vec<f32> = abandoned.take(2).flatten().collect();
+        samples.extend(encoder.encode_fountain(b"after the restart", Some(second)).unwrap().take(6).flatten());
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert_eq!(decoder.decode_fountain(&samples, Some(first.clone())).unwrap(), b"after the restart");
+        assert_eq!(decoder.stats.fountain_restarts, 1);
+
+        // A lone block of another transmission does not interrupt the current one
+        let data = vec![42u8; 120];
+        let blocks: Vec<Vec<f32>> = encoder.encode_fountain(&data, Some(first.clone())).unwrap().take(12).collect();
+        let stray = encoder.encode_fountain(b"stray", Some(first.clone())).unwrap().next().unwrap();
+        let mut samples = blocks[0].clone();
+        samples.extend_from_slice(&stray);
+        samples.extend(blocks[1..].iter().flatten());
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert_eq!(decoder.decode_fountain(&samples, Some(first)).unwrap(), data);
+        assert_eq!(decoder.stats.fountain_restarts, 0);
+    }
+}
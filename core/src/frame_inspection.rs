@@ -0,0 +1,67 @@
+//! Structure of the last frame a decoder tried
+//!
+//! With [`DecoderFsk::set_frame_inspection`](crate::DecoderFsk::set_frame_inspection)
+//! enabled, the decoder keeps what it read of each length-prefixed frame: the
+//! prefix, every Reed-Solomon block with its offset and corrections, the
+//! decoded frame bytes and the symbol padding. Tools such as the CLI's
+//! `inspect-frame` print it next to [`crate::hexdump_frame`] so a failed
+//! decode says which layer failed. Short-profile frames are not recorded.
+
+use crate::fec::FecMode;
+use crate::framing::padding_mismatches;
+
+/// One Reed-Solomon block of an inspected frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RsBlockReport {
+    /// Offset of the block in the demodulated bytes (the length prefix is at 0)
+    pub offset: usize,
+    /// Frame bytes the shortened block carries
+    pub data_bytes: usize,
+    pub parity_bytes: usize,
+    /// Received bytes the RS decoder corrected; None if the block failed
+    pub corrected_bytes: Option<usize>,
+}
+
+impl RsBlockReport {
+    /// Bytes the block takes on the air
+    pub fn len(&self) -> usize {
+        self.data_bytes + self.parity_bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// What the decoder read of a frame, layer by layer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameInspection {
+    /// Length prefix as received, with the options byte if it announced one
+    pub prefix: Vec<u8>,
+    /// Frame size from the prefix (header, extension, payload and CRC)
+    pub frame_len: usize,
+    /// FEC mode the first block decoded with; None if no mode fit
+    pub fec_mode: Option<FecMode>,
+    pub blocks: Vec<RsBlockReport>,
+    /// RS-decoded frame bytes, as far as the blocks decoded
+    pub frame: Vec<u8>,
+    /// Symbol padding after the last block (see [`crate::FRAME_PADDING_BYTE`])
+    pub padding: Vec<u8>,
+}
+
+impl FrameInspection {
+    /// Whether every block of the frame was received and decoded
+    pub fn blocks_complete(&self) -> bool {
+        self.frame.len() == self.frame_len && self.blocks.iter().all(|block| block.corrected_bytes.is_some())
+    }
+
+    /// Received bytes corrected across all decoded blocks
+    pub fn corrected_bytes(&self) -> usize {
+        self.blocks.iter().filter_map(|block| block.corrected_bytes).sum()
+    }
+
+    /// Padding bytes that are not the padding value
+    pub fn padding_mismatches(&self) -> usize {
+        padding_mismatches(&self.padding)
+    }
+}
@@ -1,5 +1,48 @@
+//! Byte-level frame format shared by all transmission modes
+//!
+//! This is the stable wire format a third-party decoder has to implement
+//! (after FSK demodulation and Reed-Solomon decoding). All multi-byte fields
+//! are big-endian.
+//!
+//! ```text
+//! offset  size  field
+//! 0       2     payload_len   number of payload bytes (0..=MAX_PAYLOAD_SIZE)
+//! 2       2     frame_num     0 for single-frame messages; count << 8 | index for frame
+//!                             `index` of a `count`-frame sequence (see `EncoderFsk::encode_sequence`)
+//! 4       1     header_crc    CRC-8 over bytes 0..4
+//! 5       1     fec_mode      RS parity bytes per block: 8, 16 or 32; 0x80 | parity/2 for
+//!                             RS(255, 255 - parity) codes (0 in fountain mode)
+//! 6       1     flags         bit 0 = FRAME_FLAG_TIMESTAMP, bit 1 = FRAME_FLAG_AMPLITUDE_BITS,
+//!                             bit 2 = FRAME_FLAG_CONTENT_TYPE, bit 3 = FRAME_FLAG_DIFFERENTIAL,
+//!                             bit 4 = FRAME_FLAG_CONTROL;
+//!                             frames with other bits set are rejected
+//! 7       1     encoder_ver   0, or the encoder's `EncoderVersion` (protocol revision << 4 |
+//!                             crate minor version % 16); informational, folded into
+//!                             payload_crc when nonzero
+//! 8       0|8   timestamp_us  sender timestamp (u64 microseconds), present iff flag bit 0
+//! ..      0|1   content_type  `ContentType` registry code, present iff flag bit 2
+//! ..      N     payload       payload_len bytes
+//! ..      2     payload_crc   CRC-16 over extension + payload
+//! ```
+//!
+//! Checksums (both MSB-first, no reflection, no final XOR):
+//! - CRC-8: polynomial 0xD5, init 0x00 (check value of "123456789" = 0xBC)
+//! - CRC-16: CCITT polynomial 0x1021, init 0xFFFF (check value = 0x29B1)
+//!
+//! Invariants:
+//! - an encoded frame is exactly `FRAME_HEADER_SIZE + extension + payload_len + 2` bytes
+//! - `fec_mode` and `flags` are not covered by the header CRC; a flipped timestamp
+//!   flag shifts the payload and is caught by the payload CRC instead
+//! - decoders ignore bytes after the payload CRC (RS block padding)
+//!
+//! On the air the RS-coded frame is followed by `FRAME_PADDING_BYTE` bytes
+//! up to the end of its last FSK symbol (or symbol group of a tone layout).
+//! Decoders count them in `DecodeStats` and, with
+//! `DecoderFsk::set_strict_padding`, reject frames whose padding differs.
+
 use crate::error::{AudioModemError, Result};
 use crate::{FRAME_HEADER_SIZE, MAX_PAYLOAD_SIZE};
+use std::fmt::Write as _;
 
 /// CRC-16-CCITT for payload integrity verification
 pub fn crc16(data: &[u8]) -> u16 {
@@ -19,7 +62,7 @@ pub fn crc16(data: &[u8]) -> u16 {
 /// Proper CRC-8 using polynomial 0xD5 (255 = x^8 + x^7 + x^6 + x^4 + x^2 + 1)
 /// This is a standard polynomial with excellent error detection properties
 /// Detects all single-bit errors, many multi-bit patterns, and burst errors up to 7 bits
-fn crc8(data: &[u8]) -> u8 {
+pub fn crc8(data: &[u8]) -> u8 {
     const POLYNOMIAL: u8 = 0xD5; // x^8 + x^7 + x^6 + x^4 + x^2 + 1
     let mut crc = 0u8;
 
@@ -36,12 +79,310 @@ fn crc8(data: &[u8]) -> u8 {
     crc
 }
 
+/// CRC-32 (IEEE 802.3, as in zip and PNG) for whole-payload checksums outside the frame
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Header flag (byte 6): an 8-byte sender timestamp extension follows the header
+pub const FRAME_FLAG_TIMESTAMP: u8 = 0x01;
+
+/// Header flag (byte 6): the frame was sent with amplitude-shaped symbols
+/// (see `EncoderFsk::set_amplitude_bits`); no extension data
+pub const FRAME_FLAG_AMPLITUDE_BITS: u8 = 0x02;
+
+/// Header flag (byte 6): a 1-byte `ContentType` extension follows the
+/// timestamp extension (if any)
+pub const FRAME_FLAG_CONTENT_TYPE: u8 = 0x04;
+
+/// Header flag (byte 6): the frame was sent with differential symbols
+/// (see `EncoderFsk::set_differential`); no extension data
+pub const FRAME_FLAG_DIFFERENTIAL: u8 = 0x08;
+
+/// Header flag (byte 6): the payload is a link control message for the
+/// sender, not user data (see `LinkProfile::encode_control`); no extension data
+pub const FRAME_FLAG_CONTROL: u8 = 0x10;
+
+/// Flags without extension data, folded into the payload CRC
+const CRC_FOLDED_FLAGS: u8 = FRAME_FLAG_AMPLITUDE_BITS | FRAME_FLAG_DIFFERENTIAL | FRAME_FLAG_CONTROL;
+
+/// CRC-16 of extension + payload; the symbol format and control flags and
+/// the version beacon of `header` are folded in so a flipped bit in them fails
+/// the check (plain frames are unaffected)
+fn payload_crc16(header: &[u8], covered: &[u8]) -> u16 {
+    let folded: Vec<u8> = [header[6] & CRC_FOLDED_FLAGS, header[7]].into_iter().filter(|&byte| byte != 0).collect();
+    if folded.is_empty() {
+        return crc16(covered);
+    }
+    crc16(&[folded.as_slice(), covered].concat())
+}
+
+/// Protocol revision announced by encoders of this crate (see [`EncoderVersion`])
+pub const PROTOCOL_REVISION: u8 = 1;
+
+/// Minor version of this crate, for the version beacon
+const CRATE_MINOR_VERSION: u8 = {
+    let digits = env!("CARGO_PKG_VERSION_MINOR").as_bytes();
+    let mut value = 0u32;
+    let mut i = 0;
+    while i < digits.len() {
+        value = value * 10 + (digits[i] - b'0') as u32;
+        i += 1;
+    }
+    (value % 16) as u8
+};
+
+/// Encoder build announced in header byte 7, for diagnosing mixed-version deployments
+///
+/// Optional (see `EncoderFsk::set_version_beacon`); encoders without it send
+/// 0. A nonzero byte is folded into the payload CRC, so a corrupted beacon
+/// fails the frame instead of naming the wrong build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EncoderVersion {
+    /// Wire protocol revision (1-15)
+    pub protocol: u8,
+    /// Minor version of the encoding crate, modulo 16
+    pub crate_minor: u8,
+}
+
+impl EncoderVersion {
+    /// What this build of the crate announces
+    pub const CURRENT: EncoderVersion = EncoderVersion { protocol: PROTOCOL_REVISION, crate_minor: CRATE_MINOR_VERSION };
+
+    pub fn to_byte(self) -> u8 {
+        (self.protocol << 4) | (self.crate_minor & 0x0F)
+    }
+
+    /// None for 0 (not announced)
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        (byte >> 4 != 0).then_some(EncoderVersion { protocol: byte >> 4, crate_minor: byte & 0x0F })
+    }
+}
+
+impl std::fmt::Display for EncoderVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "protocol revision {}, crate minor version {} (mod 16)", self.protocol, self.crate_minor)
+    }
+}
+
+/// Size of the timestamp header extension (u64 microseconds, big-endian)
+pub const FRAME_TIMESTAMP_EXT_SIZE: usize = 8;
+
+/// Size of the content type header extension (one registry code)
+pub const FRAME_CONTENT_TYPE_EXT_SIZE: usize = 1;
+
+/// Largest header extension (timestamp and content type)
+pub const MAX_FRAME_EXT_SIZE: usize = FRAME_TIMESTAMP_EXT_SIZE + FRAME_CONTENT_TYPE_EXT_SIZE;
+
+/// Value of the bytes that fill the last FSK symbol of a frame
+pub const FRAME_PADDING_BYTE: u8 = 0x00;
+
+/// Bytes of `padding` that are not `FRAME_PADDING_BYTE`
+pub fn padding_mismatches(padding: &[u8]) -> usize {
+    padding.iter().filter(|&&byte| byte != FRAME_PADDING_BYTE).count()
+}
+
+/// Most frames in one sequence (see `EncoderFsk::encode_sequence`)
+pub const MAX_SEQUENCE_FRAMES: usize = 255;
+
+/// `frame_num` of frame `index` of a `count`-frame sequence (never 0)
+pub fn sequence_frame_num(index: usize, count: usize) -> u16 {
+    debug_assert!(index < count && count <= MAX_SEQUENCE_FRAMES);
+    ((count as u16) << 8) | index as u16
+}
+
+/// `(index, count)` of a sequence frame, `None` for single-frame messages
+pub fn parse_sequence_frame_num(frame_num: u16) -> Option<(usize, usize)> {
+    let (count, index) = ((frame_num >> 8) as usize, (frame_num & 0xFF) as usize);
+    (index < count).then_some((index, count))
+}
+
+/// What a payload holds, so receivers can dispatch it without an
+/// out-of-band agreement
+///
+/// Sent as a one-byte code from this registry; codes decoders do not know
+/// arrive as `Other`. Code 0 is reserved. Values compare and hash by code, so
+/// `Other(1)` equals `Text`; [`ContentType::from_code`] gives the named variant.
+///
+/// ```text
+/// code  type
+/// 1     text/plain (UTF-8)
+/// 2     text/uri-list (one URL)
+/// 3     application/json
+/// 4     application/octet-stream
+/// 5     application/cbor
+/// 6     text/vcard
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum ContentType {
+    Text,
+    Url,
+    Json,
+    Binary,
+    Cbor,
+    VCard,
+    /// A code outside the registry
+    Other(u8),
+}
+
+impl PartialEq for ContentType {
+    fn eq(&self, other: &Self) -> bool {
+        self.code() == other.code()
+    }
+}
+
+impl Eq for ContentType {}
+
+impl std::hash::Hash for ContentType {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.code().hash(state);
+    }
+}
+
+impl ContentType {
+    const REGISTRY: [(ContentType, u8, &'static str, &'static str); 6] = [
+        (ContentType::Text, 1, "text/plain", "text"),
+        (ContentType::Url, 2, "text/uri-list", "url"),
+        (ContentType::Json, 3, "application/json", "json"),
+        (ContentType::Binary, 4, "application/octet-stream", "binary"),
+        (ContentType::Cbor, 5, "application/cbor", "cbor"),
+        (ContentType::VCard, 6, "text/vcard", "vcard"),
+    ];
+
+    pub fn from_code(code: u8) -> Self {
+        Self::REGISTRY
+            .iter()
+            .find(|entry| entry.1 == code)
+            .map_or(ContentType::Other(code), |entry| entry.0)
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            ContentType::Text => 1,
+            ContentType::Url => 2,
+            ContentType::Json => 3,
+            ContentType::Binary => 4,
+            ContentType::Cbor => 5,
+            ContentType::VCard => 6,
+            ContentType::Other(code) => code,
+        }
+    }
+
+    /// MIME type of a registered content type
+    pub fn mime(self) -> Option<&'static str> {
+        Self::REGISTRY.iter().find(|entry| entry.1 == self.code()).map(|entry| entry.2)
+    }
+
+    /// Content type for a MIME type (parameters such as `; charset=utf-8`
+    /// are ignored) or a short name (`text`, `url`, `json`, `binary`, `cbor`, `vcard`)
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let essence = mime.split(';').next().unwrap_or("").trim();
+        Self::REGISTRY
+            .iter()
+            .find(|entry| entry.2.eq_ignore_ascii_case(essence) || entry.3.eq_ignore_ascii_case(essence))
+            .map(|entry| entry.0)
+    }
+}
+
+impl std::fmt::Display for ContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mime() {
+            Some(mime) => f.write_str(mime),
+            None => write!(f, "unregistered content type {}", self.code()),
+        }
+    }
+}
+
+impl std::str::FromStr for ContentType {
+    type Err = AudioModemError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::from_mime(value)
+            .ok_or_else(|| AudioModemError::InvalidConfig(format!("unknown content type '{}'", value)))
+    }
+}
+
+/// A decoded (or to-be-encoded) frame
+///
+/// `payload_len` must equal `payload.len()`; `payload_crc` is informational on
+/// encode (the encoder always recomputes it) and holds the verified CRC on decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Frame {
     pub payload_len: u16,
     pub frame_num: u16,
     pub fec_mode: u8, // FEC mode indicator (8, 16, or 32 parity bytes)
+    pub timestamp_us: Option<u64>, // Optional sender timestamp carried in the header extension
+    pub content_type: Option<ContentType>, // Optional payload type carried in the header extension
+    pub amplitude_bits: bool, // Sent with amplitude-shaped symbols
+    pub differential: bool, // Sent with differential symbols
+    pub control: bool, // Payload is a link control message
+    pub encoder_version: Option<EncoderVersion>, // Version beacon in header byte 7
     pub payload: Vec<u8>,
-    pub payload_crc: u16, // CRC-16 of payload for end-to-end integrity check
+    pub payload_crc: u16, // CRC-16 of header extension + payload for end-to-end integrity check
+}
+
+impl Frame {
+    /// Build a frame around a payload, filling in the length and CRC fields
+    pub fn new(payload: &[u8], frame_num: u16, fec_mode: u8) -> Self {
+        Self {
+            payload_len: payload.len() as u16,
+            frame_num,
+            fec_mode,
+            timestamp_us: None,
+            content_type: None,
+            amplitude_bits: false,
+            differential: false,
+            control: false,
+            encoder_version: None,
+            payload: payload.to_vec(),
+            payload_crc: crc16(payload),
+        }
+    }
+
+    /// Attach a sender timestamp header extension
+    pub fn with_timestamp(mut self, timestamp_us: u64) -> Self {
+        self.timestamp_us = Some(timestamp_us);
+        self.payload_crc = crc16(&[self.extension(), self.payload.clone()].concat());
+        self
+    }
+
+    /// Attach a content type header extension
+    pub fn with_content_type(mut self, content_type: ContentType) -> Self {
+        self.content_type = Some(ContentType::from_code(content_type.code()));
+        self.payload_crc = crc16(&[self.extension(), self.payload.clone()].concat());
+        self
+    }
+
+    /// Number of bytes `FrameEncoder::encode` produces for this frame
+    pub fn encoded_len(&self) -> usize {
+        FRAME_HEADER_SIZE + self.extension_len() + self.payload.len() + 2
+    }
+
+    /// Size of the header extension that precedes the payload
+    pub fn extension_len(&self) -> usize {
+        let timestamp = if self.timestamp_us.is_some() { FRAME_TIMESTAMP_EXT_SIZE } else { 0 };
+        let content_type = if self.content_type.is_some() { FRAME_CONTENT_TYPE_EXT_SIZE } else { 0 };
+        timestamp + content_type
+    }
+
+    /// Header extension bytes, in wire order
+    fn extension(&self) -> Vec<u8> {
+        let mut extension = Vec::with_capacity(self.extension_len());
+        if let Some(timestamp_us) = self.timestamp_us {
+            extension.extend_from_slice(&timestamp_us.to_be_bytes());
+        }
+        if let Some(content_type) = self.content_type {
+            extension.push(content_type.code());
+        }
+        extension
+    }
 }
 
 pub struct FrameEncoder;
@@ -50,7 +391,7 @@ pub struct FrameDecoder;
 impl FrameEncoder {
     /// Encode frame with header CRC and payload CRC-16 for end-to-end integrity
     pub fn encode(frame: &Frame) -> Result<Vec<u8>> {
-        if frame.payload.len() > MAX_PAYLOAD_SIZE {
+        if frame.payload.len() > MAX_PAYLOAD_SIZE || frame.payload_len as usize != frame.payload.len() {
             return Err(AudioModemError::InvalidFrameSize);
         }
 
@@ -71,16 +412,31 @@ impl FrameEncoder {
         // FEC mode byte (previously reserved)
         header[5] = frame.fec_mode;
 
-        // Reserved bytes
-        header[6] = 0;
-        header[7] = 0;
+        // Extension flags
+        header[6] = if frame.timestamp_us.is_some() { FRAME_FLAG_TIMESTAMP } else { 0 };
+        if frame.amplitude_bits {
+            header[6] |= FRAME_FLAG_AMPLITUDE_BITS;
+        }
+        if frame.differential {
+            header[6] |= FRAME_FLAG_DIFFERENTIAL;
+        }
+        if frame.control {
+            header[6] |= FRAME_FLAG_CONTROL;
+        }
+        if frame.content_type.is_some() {
+            header[6] |= FRAME_FLAG_CONTENT_TYPE;
+        }
 
-        // Combine header + payload + payload CRC-16
+        // Version beacon (0 when not announced)
+        header[7] = frame.encoder_version.map_or(0, EncoderVersion::to_byte);
+
+        // Combine header + extension + payload + payload CRC-16
         let mut encoded = header;
+        encoded.extend_from_slice(&frame.extension());
         encoded.extend_from_slice(&frame.payload);
 
-        // Calculate and append CRC-16 of payload (2 bytes, big-endian)
-        let payload_crc = crc16(&frame.payload);
+        // Calculate and append CRC-16 of extension + payload (2 bytes, big-endian)
+        let payload_crc = payload_crc16(&encoded[..FRAME_HEADER_SIZE], &encoded[FRAME_HEADER_SIZE..]);
         encoded.push((payload_crc >> 8) as u8);
         encoded.push(payload_crc as u8);
 
@@ -115,39 +471,154 @@ impl FrameDecoder {
         Ok((payload_len, frame_num, fec_mode))
     }
 
-    /// Decode complete frame (header + payload + payload CRC-16)
+    /// Decode complete frame (header + extension + payload + payload CRC-16)
     pub fn decode(data: &[u8]) -> Result<Frame> {
+        let frame = Self::decode_unverified(data)?;
+        // decode_unverified checked that the CRC bytes are present
+        let payload_end = frame.encoded_len() - 2;
+        let received_crc = ((data[payload_end] as u16) << 8) | (data[payload_end + 1] as u16);
+        if received_crc != frame.payload_crc {
+            return Err(AudioModemError::PayloadCrcMismatch);
+        }
+        Ok(frame)
+    }
+
+    /// [`FrameDecoder::decode`] without the payload CRC-16 check (the header
+    /// CRC-8 is still checked); `payload_crc` holds the recomputed value
+    pub fn decode_unverified(data: &[u8]) -> Result<Frame> {
         let (payload_len, frame_num, fec_mode) = Self::decode_header(data)?;
+        if payload_len as usize > MAX_PAYLOAD_SIZE {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
 
-        // Need at least: header + payload + 2 bytes for CRC-16
-        if data.len() < FRAME_HEADER_SIZE + payload_len as usize + 2 {
+        // Unknown flags may announce extensions whose size we cannot know
+        if data[6] & !(FRAME_FLAG_TIMESTAMP | CRC_FOLDED_FLAGS | FRAME_FLAG_CONTENT_TYPE) != 0 {
             return Err(AudioModemError::InvalidFrameSize);
         }
+        let has_timestamp = data[6] & FRAME_FLAG_TIMESTAMP != 0;
+        let extension_len = flags_extension_len(data[6]);
 
-        let payload_start = FRAME_HEADER_SIZE;
-        let payload_end = FRAME_HEADER_SIZE + payload_len as usize;
-        let payload = data[payload_start..payload_end].to_vec();
+        // Need at least: header + extension + payload + 2 bytes for CRC-16
+        if data.len() < FRAME_HEADER_SIZE + extension_len + payload_len as usize + 2 {
+            return Err(AudioModemError::InvalidFrameSize);
+        }
 
-        // Extract CRC-16 from last 2 bytes (big-endian)
-        let received_crc = ((data[payload_end] as u16) << 8) | (data[payload_end + 1] as u16);
+        let payload_start = FRAME_HEADER_SIZE + extension_len;
+        let payload_end = payload_start + payload_len as usize;
+        let payload = data[payload_start..payload_end].to_vec();
 
-        // Recalculate CRC-16 over the payload
-        let computed_crc = crc16(&payload);
+        // Recalculate CRC-16 over the extension + payload
+        let computed_crc = payload_crc16(&data[..FRAME_HEADER_SIZE], &data[FRAME_HEADER_SIZE..payload_end]);
 
-        if received_crc != computed_crc {
-            return Err(AudioModemError::PayloadCrcMismatch);
-        }
+        let timestamp_us = if has_timestamp {
+            let mut ts = [0u8; FRAME_TIMESTAMP_EXT_SIZE];
+            ts.copy_from_slice(&data[FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + FRAME_TIMESTAMP_EXT_SIZE]);
+            Some(u64::from_be_bytes(ts))
+        } else {
+            None
+        };
+        // Last extension byte, after the timestamp
+        let content_type =
+            (data[6] & FRAME_FLAG_CONTENT_TYPE != 0).then(|| ContentType::from_code(data[payload_start - 1]));
 
         Ok(Frame {
             payload_len,
             frame_num,
             fec_mode,
+            timestamp_us,
+            content_type,
+            amplitude_bits: data[6] & FRAME_FLAG_AMPLITUDE_BITS != 0,
+            differential: data[6] & FRAME_FLAG_DIFFERENTIAL != 0,
+            control: data[6] & FRAME_FLAG_CONTROL != 0,
+            encoder_version: EncoderVersion::from_byte(data[7]),
             payload,
             payload_crc: computed_crc,
         })
     }
 }
 
+/// Header extension bytes announced by the flags byte
+fn flags_extension_len(flags: u8) -> usize {
+    let timestamp = if flags & FRAME_FLAG_TIMESTAMP != 0 { FRAME_TIMESTAMP_EXT_SIZE } else { 0 };
+    let content_type = if flags & FRAME_FLAG_CONTENT_TYPE != 0 { FRAME_CONTENT_TYPE_EXT_SIZE } else { 0 };
+    timestamp + content_type
+}
+
+/// Classic 16-bytes-per-line hexdump with offsets and an ASCII column
+pub fn hexdump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (line, chunk) in data.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        let _ = writeln!(out, "{:08x}  {:<47}  |{}|", line * 16, hex.join(" "), ascii);
+    }
+    out
+}
+
+/// Annotated dump of an encoded frame: decoded header fields, CRC checks, then a hexdump
+///
+/// Works on damaged frames too, reporting which checks fail instead of erroring.
+pub fn hexdump_frame(data: &[u8]) -> String {
+    let mut out = String::new();
+    if data.len() < FRAME_HEADER_SIZE {
+        let _ = writeln!(out, "truncated header: {} of {} bytes", data.len(), FRAME_HEADER_SIZE);
+        out.push_str(&hexdump(data));
+        return out;
+    }
+
+    let payload_len = u16::from_be_bytes([data[0], data[1]]) as usize;
+    let header_crc = crc8(&data[..4]);
+    let has_timestamp = data[6] & FRAME_FLAG_TIMESTAMP != 0;
+    let extension_len = flags_extension_len(data[6]);
+    let payload_end = FRAME_HEADER_SIZE + extension_len + payload_len;
+
+    let _ = writeln!(out, "payload_len  {}", payload_len);
+    let _ = writeln!(out, "frame_num    {}", u16::from_be_bytes([data[2], data[3]]));
+    let _ = writeln!(
+        out,
+        "header_crc   0x{:02x} ({})",
+        data[4],
+        if data[4] == header_crc { "ok".to_string() } else { format!("expected 0x{:02x}", header_crc) }
+    );
+    let _ = writeln!(out, "fec_mode     {}", data[5]);
+    let _ = writeln!(out, "flags        0x{:02x}", data[6]);
+    if has_timestamp {
+        match data.get(FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + FRAME_TIMESTAMP_EXT_SIZE) {
+            Some(ts) => {
+                let _ = writeln!(out, "timestamp_us {}", u64::from_be_bytes(ts.try_into().unwrap()));
+            }
+            None => out.push_str("timestamp_us truncated\n"),
+        }
+    }
+    if data[6] & FRAME_FLAG_CONTENT_TYPE != 0 {
+        match data.get(FRAME_HEADER_SIZE + extension_len - 1) {
+            Some(&code) => {
+                let _ = writeln!(out, "content_type {}", ContentType::from_code(code));
+            }
+            None => out.push_str("content_type truncated\n"),
+        }
+    }
+    match data.get(payload_end..payload_end + 2) {
+        Some(crc) => {
+            let received = u16::from_be_bytes([crc[0], crc[1]]);
+            let computed = payload_crc16(&data[..FRAME_HEADER_SIZE], &data[FRAME_HEADER_SIZE..payload_end]);
+            let status = if received == computed { "ok".to_string() } else { format!("expected 0x{:04x}", computed) };
+            let _ = writeln!(out, "payload_crc  0x{:04x} ({})", received, status);
+            if data.len() > payload_end + 2 {
+                let _ = writeln!(out, "trailing     {} bytes", data.len() - payload_end - 2);
+            }
+        }
+        None => {
+            let _ = writeln!(out, "payload      truncated: {} of {} bytes", data.len(), payload_end + 2);
+        }
+    }
+    out.push_str(&hexdump(data));
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,6 +630,12 @@ mod tests {
             payload_len: 5,
             frame_num: 1,
             fec_mode: 8,
+            timestamp_us: None,
+            content_type: None,
+            amplitude_bits: false,
+            differential: false,
+            control: false,
+            encoder_version: None,
             payload: payload.clone(),
             payload_crc: crc16(&payload),
         };
@@ -181,6 +658,12 @@ mod tests {
             payload_len: 5,
             frame_num: 1,
             fec_mode: 8,
+            timestamp_us: None,
+            content_type: None,
+            amplitude_bits: false,
+            differential: false,
+            control: false,
+            encoder_version: None,
             payload: payload.clone(),
             payload_crc: crc16(&payload),
         };
@@ -202,6 +685,12 @@ mod tests {
             payload_len: 5,
             frame_num: 1,
             fec_mode: 8,
+            timestamp_us: None,
+            content_type: None,
+            amplitude_bits: false,
+            differential: false,
+            control: false,
+            encoder_version: None,
             payload: payload.clone(),
             payload_crc: crc16(&payload),
         };
@@ -214,6 +703,13 @@ mod tests {
             Err(AudioModemError::PayloadCrcMismatch) => {}, // Expected
             _ => panic!("Expected PayloadCrcMismatch error"),
         }
+
+        // The unverified decode still hands out the candidate payload
+        let candidate = FrameDecoder::decode_unverified(&encoded).unwrap();
+        assert_eq!(candidate.payload, b"Gello");
+        assert_ne!(candidate.payload_crc, frame.payload_crc);
+        encoded[4] ^= 0x01;
+        assert!(matches!(FrameDecoder::decode_unverified(&encoded), Err(AudioModemError::HeaderCrcMismatch)));
     }
 
     #[test]
@@ -224,6 +720,12 @@ mod tests {
             payload_len: 11,
             frame_num: 0,
             fec_mode: 8,
+            timestamp_us: None,
+            content_type: None,
+            amplitude_bits: false,
+            differential: false,
+            control: false,
+            encoder_version: None,
             payload: original_payload.clone(),
             payload_crc: crc16(&original_payload),
         };
@@ -248,4 +750,275 @@ mod tests {
             _ => panic!("Expected PayloadCrcMismatch error for corrupted payload"),
         }
     }
+
+    #[test]
+    fn test_frame_timestamp_extension_roundtrip() {
+        let payload = b"sync".to_vec();
+        let frame = Frame {
+            payload_len: 4,
+            frame_num: 0,
+            fec_mode: 8,
+            timestamp_us: Some(1_700_000_000_123_456),
+            content_type: None,
+            amplitude_bits: false,
+            differential: false,
+            control: false,
+            encoder_version: None,
+            payload: payload.clone(),
+            payload_crc: 0,
+        };
+
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded.len(), FRAME_HEADER_SIZE + FRAME_TIMESTAMP_EXT_SIZE + 4 + 2);
+        assert_eq!(encoded[6] & FRAME_FLAG_TIMESTAMP, FRAME_FLAG_TIMESTAMP);
+
+        let decoded = FrameDecoder::decode(&encoded).unwrap();
+        assert_eq!(decoded.timestamp_us, Some(1_700_000_000_123_456));
+        assert_eq!(decoded.payload, payload);
+
+        // Extension bytes are covered by the payload CRC-16
+        let mut corrupted = encoded.clone();
+        corrupted[FRAME_HEADER_SIZE] ^= 0x01;
+        assert!(matches!(
+            FrameDecoder::decode(&corrupted),
+            Err(AudioModemError::PayloadCrcMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_content_type_extension() {
+        let frame = Frame::new(b"{}", 0, 8).with_content_type(ContentType::Json);
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded[6], FRAME_FLAG_CONTENT_TYPE);
+        assert_eq!(encoded[FRAME_HEADER_SIZE], 3);
+        assert_eq!(encoded.len(), frame.encoded_len());
+        assert_eq!(FrameDecoder::decode(&encoded).unwrap(), frame);
+
+        // After the timestamp when both are present, and covered by the payload CRC
+        let both = Frame::new(b"Hi", 7, 8).with_content_type(ContentType::Text).with_timestamp(5);
+        let encoded = FrameEncoder::encode(&both).unwrap();
+        assert_eq!(encoded[6], FRAME_FLAG_TIMESTAMP | FRAME_FLAG_CONTENT_TYPE);
+        assert_eq!(encoded[FRAME_HEADER_SIZE + FRAME_TIMESTAMP_EXT_SIZE], 1);
+        assert_eq!(FrameDecoder::decode(&encoded).unwrap(), both);
+        assert!(hexdump_frame(&encoded).contains("content_type text/plain"));
+        let mut retyped = encoded.clone();
+        retyped[FRAME_HEADER_SIZE + FRAME_TIMESTAMP_EXT_SIZE] = 4;
+        assert!(matches!(FrameDecoder::decode(&retyped), Err(AudioModemError::PayloadCrcMismatch)));
+
+        // Codes from a newer registry survive
+        let unknown = Frame::new(b"?", 0, 8).with_content_type(ContentType::from_code(200));
+        let decoded = FrameDecoder::decode(&FrameEncoder::encode(&unknown).unwrap()).unwrap();
+        assert_eq!(decoded.content_type, Some(ContentType::Other(200)));
+    }
+
+    #[test]
+    fn test_content_type_registry() {
+        for code in 0..=u8::MAX {
+            assert_eq!(ContentType::from_code(code).code(), code);
+        }
+        assert_eq!(ContentType::from_mime("text/plain; charset=utf-8"), Some(ContentType::Text));
+        assert_eq!(ContentType::from_mime("Application/JSON"), Some(ContentType::Json));
+        assert_eq!(ContentType::from_mime("url"), Some(ContentType::Url));
+        assert_eq!(ContentType::from_mime("image/png"), None);
+        assert_eq!("vcard".parse::<ContentType>().unwrap(), ContentType::VCard);
+        assert!("nope".parse::<ContentType>().is_err());
+        assert_eq!(ContentType::Binary.to_string(), "application/octet-stream");
+        assert_eq!(ContentType::Other(9).mime(), None);
+
+        // An `Other` holding a registered code is that type
+        assert_eq!(ContentType::Other(3), ContentType::Json);
+        assert_eq!(ContentType::Other(3).to_string(), "application/json");
+        let types: std::collections::HashSet<ContentType> = [ContentType::Other(1), ContentType::Text].into();
+        assert_eq!(types.len(), 1);
+        let frame = Frame::new(b"{}", 0, 8).with_content_type(ContentType::Other(3));
+        assert!(matches!(frame.content_type, Some(ContentType::Json)));
+    }
+
+    #[test]
+    fn test_encoder_version_beacon() {
+        let mut frame = Frame::new(b"Hi", 7, 8);
+        frame.encoder_version = Some(EncoderVersion { protocol: 1, crate_minor: 3 });
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded[7], 0x13);
+        let decoded = FrameDecoder::decode(&encoded).unwrap();
+        assert_eq!(decoded.encoder_version, Some(EncoderVersion { protocol: 1, crate_minor: 3 }));
+        assert_eq!(decoded.encoder_version.unwrap().to_string(), "protocol revision 1, crate minor version 3 (mod 16)");
+
+        // A changed or dropped beacon fails the payload CRC
+        for byte in [0x14, 0x03, 0] {
+            let mut corrupted = encoded.clone();
+            corrupted[7] = byte;
+            assert!(FrameDecoder::decode(&corrupted).is_err(), "{:#04x}", byte);
+        }
+
+        // Frames from encoders without the beacon report none
+        assert_eq!(FrameDecoder::decode(&FrameEncoder::encode(&Frame::new(b"Hi", 7, 8)).unwrap()).unwrap().encoder_version, None);
+        assert_eq!(EncoderVersion::from_byte(0), None);
+        assert_eq!(EncoderVersion::CURRENT.protocol, PROTOCOL_REVISION);
+        assert_eq!(EncoderVersion::from_byte(EncoderVersion::CURRENT.to_byte()), Some(EncoderVersion::CURRENT));
+    }
+
+    #[test]
+    fn test_crc_check_values() {
+        assert_eq!(crc8(b"123456789"), 0xBC);
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc8(&[]), 0x00);
+        assert_eq!(crc16(&[]), 0xFFFF);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn test_known_wire_bytes() {
+        // Locks the wire format: any change here breaks third-party decoders
+        let frame = Frame::new(b"Hi", 7, 8);
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded, [0x00, 0x02, 0x00, 0x07, 0x87, 0x08, 0x00, 0x00, b'H', b'i', 0x64, 0xe5]);
+        assert_eq!(encoded.len(), frame.encoded_len());
+
+        let timestamped = Frame::new(b"Hi", 7, 8).with_timestamp(1_700_000_000_000_000);
+        let encoded = FrameEncoder::encode(&timestamped).unwrap();
+        assert_eq!(
+            encoded,
+            [
+                0x00, 0x02, 0x00, 0x07, 0x87, 0x08, 0x01, 0x00, 0x00, 0x06, 0x0a, 0x24, 0x18, 0x1e, 0x40, 0x00,
+                b'H', b'i', 0x4e, 0xbf
+            ]
+        );
+        assert_eq!(encoded.len(), timestamped.encoded_len());
+        assert_eq!(FrameDecoder::decode(&encoded).unwrap(), timestamped);
+    }
+
+    #[test]
+    fn test_builder_matches_decoded_frame() {
+        for len in [0, 1, 223, MAX_PAYLOAD_SIZE] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            for fec_mode in [0, 8, 16, 32] {
+                let frame = Frame::new(&payload, len as u16, fec_mode);
+                let encoded = FrameEncoder::encode(&frame).unwrap();
+                assert_eq!(FrameDecoder::decode(&encoded).unwrap(), frame);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_rejects_inconsistent_frames() {
+        let oversized = Frame::new(&vec![0u8; MAX_PAYLOAD_SIZE + 1], 0, 8);
+        assert!(matches!(FrameEncoder::encode(&oversized), Err(AudioModemError::InvalidFrameSize)));
+
+        let mut mismatched = Frame::new(b"abc", 0, 8);
+        mismatched.payload_len = 4;
+        assert!(matches!(FrameEncoder::encode(&mismatched), Err(AudioModemError::InvalidFrameSize)));
+    }
+
+    #[test]
+    fn test_decode_every_truncation_fails() {
+        let encoded = FrameEncoder::encode(&Frame::new(b"truncate me", 3, 16).with_timestamp(42)).unwrap();
+        for len in 0..encoded.len() {
+            assert!(FrameDecoder::decode(&encoded[..len]).is_err(), "accepted {} of {} bytes", len, encoded.len());
+        }
+
+        // Trailing bytes (RS block padding) are ignored
+        let mut padded = encoded.clone();
+        padded.extend_from_slice(&[0xAA; 5]);
+        assert_eq!(FrameDecoder::decode(&padded).unwrap().payload, b"truncate me");
+    }
+
+    #[test]
+    fn test_every_single_bit_flip_detected() {
+        let encoded = FrameEncoder::encode(&Frame::new(b"bit flips", 1, 8).with_timestamp(99)).unwrap();
+        for byte in 0..encoded.len() {
+            // fec_mode and the reserved byte are outside both CRCs
+            if byte == 5 || byte == 7 {
+                continue;
+            }
+            for bit in 0..8 {
+                let mut corrupted = encoded.clone();
+                corrupted[byte] ^= 1 << bit;
+                assert!(FrameDecoder::decode(&corrupted).is_err(), "flip at byte {} bit {} accepted", byte, bit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_amplitude_flag_roundtrip_and_protected() {
+        let mut frame = Frame::new(b"shaped", 2, 8);
+        frame.amplitude_bits = true;
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded[6], FRAME_FLAG_AMPLITUDE_BITS);
+        assert!(FrameDecoder::decode(&encoded).unwrap().amplitude_bits);
+
+        // Clearing the flag (or setting it on a plain frame) breaks the payload CRC
+        let mut cleared = encoded.clone();
+        cleared[6] = 0;
+        assert!(FrameDecoder::decode(&cleared).is_err());
+        let mut plain = FrameEncoder::encode(&Frame::new(b"shaped", 2, 8)).unwrap();
+        plain[6] = FRAME_FLAG_AMPLITUDE_BITS;
+        assert!(FrameDecoder::decode(&plain).is_err());
+
+        // Likewise the differential flag, which is told apart from the amplitude flag
+        let mut frame = Frame::new(b"shaped", 2, 8);
+        frame.differential = true;
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded[6], FRAME_FLAG_DIFFERENTIAL);
+        let decoded = FrameDecoder::decode(&encoded).unwrap();
+        assert!(decoded.differential && !decoded.amplitude_bits);
+        let mut swapped = encoded.clone();
+        swapped[6] = FRAME_FLAG_AMPLITUDE_BITS;
+        assert!(FrameDecoder::decode(&swapped).is_err());
+
+        // And the control flag, so user data cannot pass for a control message
+        let mut frame = Frame::new(b"\x00\x00\x00\x32", 0, 8);
+        frame.control = true;
+        let encoded = FrameEncoder::encode(&frame).unwrap();
+        assert_eq!(encoded[6], FRAME_FLAG_CONTROL);
+        assert!(FrameDecoder::decode(&encoded).unwrap().control);
+        let mut cleared = encoded.clone();
+        cleared[6] = 0;
+        assert!(FrameDecoder::decode(&cleared).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_oversized_length_field() {
+        let mut header = vec![0u8; FRAME_HEADER_SIZE];
+        header[..2].copy_from_slice(&((MAX_PAYLOAD_SIZE + 1) as u16).to_be_bytes());
+        header[4] = crc8(&header[..4]);
+        header.resize(FRAME_HEADER_SIZE + MAX_PAYLOAD_SIZE + 3, 0);
+        assert!(matches!(FrameDecoder::decode(&header), Err(AudioModemError::InvalidFrameSize)));
+    }
+
+    #[test]
+    fn test_hexdump_frame_annotations() {
+        let mut encoded = FrameEncoder::encode(&Frame::new(b"Hello, frame!", 2, 8).with_timestamp(5)).unwrap();
+        let dump = hexdump_frame(&encoded);
+        assert!(dump.contains("payload_len  13"));
+        assert!(dump.contains("frame_num    2"));
+        assert!(dump.contains("timestamp_us 5"));
+        assert!(dump.contains("header_crc   0x") && dump.contains("(ok)"));
+        assert!(dump.contains("00000000  00 0d 00 02"));
+        assert!(dump.contains("|Hello, f"));
+
+        let last = encoded.len() - 1;
+        encoded[last] ^= 0xFF;
+        assert!(hexdump_frame(&encoded).contains("payload_crc  0x") && hexdump_frame(&encoded).contains("expected"));
+        assert!(hexdump_frame(&encoded[..10]).contains("truncated"));
+        assert!(hexdump_frame(&encoded[..3]).contains("truncated header"));
+    }
+
+    #[test]
+    fn test_sequence_frame_num_roundtrip() {
+        assert_eq!(parse_sequence_frame_num(0), None);
+        assert_eq!(sequence_frame_num(0, 1), 0x0100);
+        for (index, count) in [(0, 1), (2, 3), (254, MAX_SEQUENCE_FRAMES)] {
+            assert_eq!(parse_sequence_frame_num(sequence_frame_num(index, count)), Some((index, count)));
+        }
+        assert_eq!(parse_sequence_frame_num(0x0303), None);
+    }
+
+    #[test]
+    fn test_padding_mismatches() {
+        assert_eq!(padding_mismatches(&[]), 0);
+        assert_eq!(padding_mismatches(&[FRAME_PADDING_BYTE; 2]), 0);
+        assert_eq!(padding_mismatches(&[FRAME_PADDING_BYTE, 0x5a]), 1);
+    }
 }
@@ -1,7 +1,12 @@
 use crate::error::{AudioModemError, Result};
-use crate::{FOUNTAIN_BLOCK_SIZE, FSK_BYTES_PER_SYMBOL as FSK_BYTES_PER_SYMBOL_CONST, FSK_SYMBOL_SAMPLES as FSK_SYMBOL_SAMPLES_CONST};
+use crate::{FSK_BYTES_PER_SYMBOL as FSK_BYTES_PER_SYMBOL_CONST, FSK_SYMBOL_SAMPLES as FSK_SYMBOL_SAMPLES_CONST};
+use crate::fixed_point::{goertzel_power_q15, quantize_q15};
+use crate::trig;
+use crate::symbol_audit::{SymbolAuditSink, SymbolDecision};
+use crate::units::Samples;
 use std::cmp::Ordering;
 use std::f32::consts::PI;
+use std::sync::{Arc, MutexGuard, PoisonError};
 
 // Multi-tone FSK configuration optimized for mobile phone speakers
 //
@@ -19,15 +24,16 @@ use std::f32::consts::PI;
 // - Each nibble (4 bits) selects one of 16 frequencies from a band
 // - Uses Reed-Solomon FEC for error correction
 // - Includes preamble/postamble for frame synchronization
+// - `ToneLayout` trades tones/bins for energy per tone on narrowband channels
 
 /// Base frequency in Hz (optimal range for mobile phone speakers)
 const FSK_BASE_FREQ: f32 = 800.0;
 
 /// Frequency spacing in Hz between adjacent bins
-const FSK_FREQ_DELTA: f32 = 20.0;
+pub(crate) const FSK_FREQ_DELTA: f32 = 20.0;
 
 /// Total number of frequency bins (96 provides redundancy and flexibility)
-const FSK_NUM_BINS: usize = 96;
+pub const FSK_NUM_BINS: usize = 96;
 
 /// Number of nibbles transmitted per symbol (6 nibbles = 3 bytes)
 pub const FSK_NIBBLES_PER_SYMBOL: usize = 6;
@@ -36,28 +42,6 @@ pub const FSK_NIBBLES_PER_SYMBOL: usize = 6;
 pub const FSK_BYTES_PER_SYMBOL: usize = FSK_BYTES_PER_SYMBOL_CONST;
 
 
-/// Configuration for fountain mode streaming
-#[derive(Debug, Clone)]
-pub struct FountainConfig {
-    /// Timeout for sender to keep transmitting (in seconds)
-    pub timeout_secs: u32,
-    /// Size of each fountain block in bytes (before fountain encoding)
-    pub block_size: usize,
-    /// Ratio of repair blocks to source blocks (e.g., 0.5 = 50% overhead)
-    pub repair_blocks_ratio: f32,
-}
-
-impl Default for FountainConfig {
-    fn default() -> Self {
-        Self {
-            timeout_secs: 30,
-            block_size: FOUNTAIN_BLOCK_SIZE,
-            repair_blocks_ratio: 0.5,
-        }
-    }
-}
-
-
 /// FSK symbol duration (192ms at 16kHz sample rate, imported from crate root)
 pub const FSK_SYMBOL_SAMPLES: usize = FSK_SYMBOL_SAMPLES_CONST;
 
@@ -72,7 +56,7 @@ const FSK_EDGE_TAPER_RATIO: f32 = 0.08; // 8% of the symbol on each side
 const FSK_MIN_TAPER_SAMPLES: usize = 64;
 
 /// Number of bins dedicated to each nibble band.
-const FSK_BINS_PER_BAND: usize = 16;
+pub(crate) const FSK_BINS_PER_BAND: usize = 16;
 
 /// Analysis window taper ratio for demodulator signal conditioning.
 const FSK_ANALYSIS_TAPER_RATIO: f32 = 0.06;
@@ -92,15 +76,301 @@ const FSK_NOISE_FLOOR_EPSILON: f32 = 1e-3;
 /// Hard lower bound for the estimated noise floor.
 const FSK_MIN_NOISE_FLOOR: f32 = 1e-6;
 
+/// Decision margin below which a band with unreliable bins is treated as an erasure
+const FSK_ERASURE_MARGIN: f32 = 0.5;
+
+/// Bits carried by an amplitude-shaped symbol: 24 tone bits + one amplitude bit per tone
+pub const AMPLITUDE_SYMBOL_BITS: usize = FSK_BYTES_PER_SYMBOL * 8 + FSK_NIBBLES_PER_SYMBOL;
+
+/// Relative amplitude of a tone whose amplitude bit is 0 (-6 dB)
+pub const AMPLITUDE_LOW_LEVEL: f32 = 0.5;
+
+/// Pilot contrast (high vs low tone power) below which amplitude bits are unreliable
+pub const MIN_AMPLITUDE_CONTRAST_DB: f32 = 3.0;
+
+/// Tone pattern of the two amplitude pilot symbols
+const AMPLITUDE_PILOT_BYTES: [u8; FSK_BYTES_PER_SYMBOL] = [0x5A, 0x5A, 0x5A];
+
+/// `levels` value with every tone at full amplitude
+const AMPLITUDE_ALL_HIGH: u8 = 0x3F;
+
+/// Fewest simultaneous tones a `ToneLayout` may use
+pub const MIN_LAYOUT_TONES: usize = 2;
+
+/// Band widths a `ToneLayout` may use (4, 3 or 2 bits per tone); the index is part of the layout code
+const LAYOUT_BINS_PER_BAND: [usize; 3] = [16, 8, 4];
+
+/// Number of simultaneous tones per symbol and frequency bins per tone band
+///
+/// Each tone carries log2(bins_per_band) bits. Fewer tones put more of the
+/// output level into each tone, and fewer bins narrow the occupied band
+/// (bands are laid out upwards from 800 Hz), both of which help on
+/// narrowband channels at the cost of throughput. Preset layouts may also
+/// space a band's tones several 20 Hz bins apart.
+///
+/// A dual-band layout (see [`ToneLayout::dual_band`]) sends every tone twice,
+/// in the lower and the upper half of the bins, and the demodulator decides on
+/// the summed energy of both copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToneLayout {
+    tones: usize,
+    bins_per_band: usize,
+    /// Bins from one tone of a band to the next (1 = 20 Hz apart)
+    spacing: usize,
+    /// Each tone repeated `MIRROR_OFFSET_BINS` higher
+    mirrored: bool,
+}
+
+/// Distance between a tone and its copy in a dual-band layout (960 Hz)
+const MIRROR_OFFSET_BINS: usize = FSK_NUM_BINS / 2;
+
+impl ToneLayout {
+    /// 6 tones of 16 bins: 24 bits per symbol over 800-2700 Hz
+    pub const STANDARD: ToneLayout =
+        ToneLayout { tones: FSK_NIBBLES_PER_SYMBOL, bins_per_band: FSK_BINS_PER_BAND, spacing: 1, mirrored: false };
+
+    /// 4 tones of 16 bins: 16 bits per symbol over 800-2080 Hz, used by the
+    /// telephony profile (well inside the 300-3400 Hz telephone band)
+    pub const TELEPHONY: ToneLayout =
+        ToneLayout { tones: 4, bins_per_band: FSK_BINS_PER_BAND, spacing: 1, mirrored: false };
+
+    /// 4 tones of 8 bins spaced 60 Hz apart: 12 bits per symbol over 800-2700 Hz,
+    /// used by the codec-robust profile (perceptual codecs smear 20 Hz neighbours)
+    pub const CODEC_ROBUST: ToneLayout = ToneLayout { tones: 4, bins_per_band: 8, spacing: 3, mirrored: false };
+
+    /// 3 tones of 16 bins: 12 bits per symbol over 800-1740 Hz, half the
+    /// standard band, used by the narrowband profile
+    pub const NARROWBAND: ToneLayout =
+        ToneLayout { tones: 3, bins_per_band: FSK_BINS_PER_BAND, spacing: 1, mirrored: false };
+
+    /// 3 tones of 16 bins over 800-1740 Hz, repeated over 1760-2700 Hz: 12 bits
+    /// per symbol, half the standard rate, for rooms with uneven speaker response
+    pub const DUAL_BAND: ToneLayout =
+        ToneLayout { tones: 3, bins_per_band: FSK_BINS_PER_BAND, spacing: 1, mirrored: true };
+
+    /// `tones` from 2 to 6, `bins_per_band` 4, 8 or 16
+    pub fn new(tones: usize, bins_per_band: usize) -> Result<Self> {
+        if !(MIN_LAYOUT_TONES..=FSK_NIBBLES_PER_SYMBOL).contains(&tones) {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "{} tones per symbol (supported: {}-{})",
+                tones, MIN_LAYOUT_TONES, FSK_NIBBLES_PER_SYMBOL
+            )));
+        }
+        if !LAYOUT_BINS_PER_BAND.contains(&bins_per_band) {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "{} bins per band (supported: 4, 8 or 16)",
+                bins_per_band
+            )));
+        }
+        Ok(Self { tones, bins_per_band, spacing: 1, mirrored: false })
+    }
+
+    /// This layout sent in both halves of the band; it must fit into the lower half
+    pub fn dual_band(self) -> Result<Self> {
+        if self.tones * self.bins_per_band * self.spacing > MIRROR_OFFSET_BINS {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "{} tones of {} bins do not fit into half the band for dual-band symbols",
+                self.tones, self.bins_per_band
+            )));
+        }
+        Ok(Self { mirrored: true, ..self })
+    }
+
+    pub fn is_dual_band(&self) -> bool {
+        self.mirrored
+    }
+
+    /// The layout without its upper-band copy
+    pub fn single_band(self) -> Self {
+        Self { mirrored: false, ..self }
+    }
+
+    pub fn tones(&self) -> usize {
+        self.tones
+    }
+
+    pub fn bins_per_band(&self) -> usize {
+        self.bins_per_band
+    }
+
+    pub fn bits_per_tone(&self) -> usize {
+        self.bins_per_band.trailing_zeros() as usize
+    }
+
+    pub fn bits_per_symbol(&self) -> usize {
+        self.tones * self.bits_per_tone()
+    }
+
+    /// Input to `FskModulator::modulate` must be a multiple of this many bytes
+    /// (the shortest run of whole bytes that fills whole symbols)
+    pub fn alignment_bytes(&self) -> usize {
+        let bits = self.bits_per_symbol();
+        bits / gcd(bits, 8)
+    }
+
+    /// Symbols that carry `bytes` bytes (the last one zero-padded)
+    pub fn symbols_for(&self, bytes: usize) -> usize {
+        (bytes * 8).div_ceil(self.bits_per_symbol())
+    }
+
+    /// Width of the occupied band in Hz (both halves for dual-band layouts)
+    pub fn bandwidth_hz(&self) -> f32 {
+        let copies = if self.mirrored { 2 } else { 1 };
+        (copies * self.tones * self.bins_per_band * self.spacing) as f32 * FSK_FREQ_DELTA
+    }
+
+    /// Distance between neighbouring tones of a band in Hz
+    pub fn tone_spacing_hz(&self) -> f32 {
+        self.spacing as f32 * FSK_FREQ_DELTA
+    }
+
+    /// Whether the length prefix can announce this layout (presets with
+    /// wider tone spacing belong to their frame profile)
+    pub(crate) fn announceable(&self) -> bool {
+        self.spacing == 1
+    }
+
+    /// Frequency bin of tone `value` in `band`
+    fn bin(&self, band: usize, value: usize) -> usize {
+        (band * self.bins_per_band + value) * self.spacing
+    }
+
+    /// Frequency bins sending tone `value` in `band`: one, or two for dual-band layouts
+    fn bins(&self, band: usize, value: usize) -> impl Iterator<Item = usize> {
+        let bin = self.bin(band, value);
+        let copies = if self.mirrored { 2 } else { 1 };
+        (0..copies).map(move |copy| bin + copy * MIRROR_OFFSET_BINS)
+    }
+
+    /// 4-bit code announcing the layout in the frame's length prefix (0 =
+    /// standard); a dual-band layout has the code of its lower half
+    pub(crate) fn code(&self) -> u8 {
+        let width = LAYOUT_BINS_PER_BAND.iter().position(|&bins| bins == self.bins_per_band).unwrap_or(0);
+        ((FSK_NIBBLES_PER_SYMBOL - self.tones) * LAYOUT_BINS_PER_BAND.len() + width) as u8
+    }
+
+    pub(crate) fn from_code(code: u8) -> Option<Self> {
+        let code = code as usize;
+        let tones = FSK_NIBBLES_PER_SYMBOL.checked_sub(code / LAYOUT_BINS_PER_BAND.len())?;
+        Self::new(tones, LAYOUT_BINS_PER_BAND[code % LAYOUT_BINS_PER_BAND.len()]).ok()
+    }
+
+    /// Tone value of each band for the symbol whose bits start at bit `start` of `bytes`
+    fn split(&self, bytes: &[u8], start: usize) -> [u8; FSK_NIBBLES_PER_SYMBOL] {
+        let bits = self.bits_per_tone();
+        let mut values = [0u8; FSK_NIBBLES_PER_SYMBOL];
+        for (band, value) in values.iter_mut().take(self.tones).enumerate() {
+            *value = read_bits(bytes, start + band * bits, bits) as u8;
+        }
+        values
+    }
+
+    /// Append the bits of one symbol's tone values to `bytes`
+    fn join(&self, values: &[u8; FSK_NIBBLES_PER_SYMBOL], bytes: &mut Vec<u8>, bit_pos: &mut usize) {
+        for &value in &values[..self.tones] {
+            write_bits(bytes, bit_pos, value as u32, self.bits_per_tone());
+        }
+    }
+
+    /// Turn tone values into the tones sending them as steps from `previous`
+    /// (modulo the band size); `previous` becomes this symbol's tones
+    fn steps_to_tones(&self, values: &mut [u8; FSK_NIBBLES_PER_SYMBOL], previous: &mut [u8; FSK_NIBBLES_PER_SYMBOL]) {
+        for (value, previous) in values.iter_mut().zip(previous.iter_mut()).take(self.tones) {
+            *value = ((*previous as usize + *value as usize) % self.bins_per_band) as u8;
+            *previous = *value;
+        }
+    }
+
+    /// Inverse of `steps_to_tones`: the steps from `previous` to the received tones
+    fn tones_to_steps(&self, values: &mut [u8; FSK_NIBBLES_PER_SYMBOL], previous: &mut [u8; FSK_NIBBLES_PER_SYMBOL]) {
+        for (value, previous) in values.iter_mut().zip(previous.iter_mut()).take(self.tones) {
+            let tone = *value;
+            *value = ((tone as usize + self.bins_per_band - *previous as usize) % self.bins_per_band) as u8;
+            *previous = tone;
+        }
+    }
+}
+
+impl Default for ToneLayout {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl std::str::FromStr for ToneLayout {
+    type Err = AudioModemError;
+
+    /// A layout as "TONES,BINS", checked like [`ToneLayout::new`]
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = |reason: String| AudioModemError::InvalidConfig(format!("tone layout '{}': {}", value, reason));
+        let (tones, bins) = value.split_once(',').ok_or_else(|| invalid("expected TONES,BINS".to_string()))?;
+        let tones = tones.trim().parse().map_err(|_| invalid(format!("invalid TONES: {}", tones)))?;
+        let bins = bins.trim().parse().map_err(|_| invalid(format!("invalid BINS: {}", bins)))?;
+        ToneLayout::new(tones, bins)
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Nibble the pilot symbols send in `band`
+fn pilot_nibble(band: usize) -> u8 {
+    (AMPLITUDE_PILOT_BYTES[band / 2] >> if band.is_multiple_of(2) { 4 } else { 0 }) & 0x0F
+}
+
+/// Amplitude bit of a band in a `levels` value (band 0 is the most significant)
+fn amplitude_bit(band: usize) -> u8 {
+    1 << (FSK_NIBBLES_PER_SYMBOL - 1 - band)
+}
+
+/// `gains` if every band gain is positive and finite
+fn check_band_gains(gains: [f32; FSK_NIBBLES_PER_SYMBOL]) -> Result<[f32; FSK_NIBBLES_PER_SYMBOL]> {
+    match gains.iter().position(|gain| !(gain.is_finite() && *gain > 0.0)) {
+        Some(band) => Err(AudioModemError::InvalidConfig(format!("band {} gain {} is not positive", band, gains[band]))),
+        None => Ok(gains),
+    }
+}
+
+/// `count` bits of `bytes` starting at bit `start` (MSB first); bits past the end read as 0
+fn read_bits(bytes: &[u8], start: usize, count: usize) -> u32 {
+    (start..start + count).fold(0, |acc, bit| {
+        let value = bytes.get(bit / 8).map_or(0, |byte| (byte >> (7 - bit % 8)) & 1);
+        (acc << 1) | value as u32
+    })
+}
+
+/// Append the low `count` bits of `value` to `bytes` (MSB first) at bit `*bit_pos`
+fn write_bits(bytes: &mut Vec<u8>, bit_pos: &mut usize, value: u32, count: usize) {
+    for i in (0..count).rev() {
+        if bit_pos.is_multiple_of(8) {
+            bytes.push(0);
+        }
+        if (value >> i) & 1 != 0 {
+            *bytes.last_mut().unwrap() |= 1 << (7 - *bit_pos % 8);
+        }
+        *bit_pos += 1;
+    }
+}
+
+/// Append the bytes holding bits `start..start + count` to the ascending `erasures`
+fn push_erased_bits(erasures: &mut Vec<usize>, start: usize, count: usize) {
+    for byte in start / 8..=(start + count - 1) / 8 {
+        if erasures.last().is_none_or(|&last| last < byte) {
+            erasures.push(byte);
+        }
+    }
+}
+
 /// Calculate frequency for a given bin index
 /// freq_hz = FSK_BASE_FREQ + bin_index * FSK_FREQ_DELTA
-fn bin_to_freq(bin: usize) -> f32 {
+pub fn bin_to_freq(bin: usize) -> f32 {
     FSK_BASE_FREQ + (bin as f32) * FSK_FREQ_DELTA
 }
 
 /// Calculate approximate bin index for a given frequency
 /// Returns None if frequency is outside valid range
-fn freq_to_bin(freq: f32) -> Option<usize> {
+pub fn freq_to_bin(freq: f32) -> Option<usize> {
     if freq < FSK_BASE_FREQ {
         return None;
     }
@@ -127,7 +397,7 @@ fn raised_cosine_window(len: usize, taper_len: usize) -> Vec<f32> {
     for i in 0..taper {
         // Smoothly increase from 0 to 1 using a sine-squared profile
         let progress = i as f32 / taper as f32;
-        let value = (PI * progress / 2.0).sin().powi(2);
+        let value = trig::sin(PI * progress / 2.0).powi(2);
         window[i] = value;
         window[len - 1 - i] = value;
     }
@@ -135,20 +405,154 @@ fn raised_cosine_window(len: usize, taper_len: usize) -> Vec<f32> {
     window
 }
 
+/// One tone over a symbol starting at phase 0: sin(ωi) and cos(ωi)
+///
+/// A tone starting at phase φ is then sin φ·cos(ωi) + cos φ·sin(ωi), two
+/// multiply-adds per sample instead of a sine.
+struct ToneTable {
+    sin: Vec<f32>,
+    cos: Vec<f32>,
+    /// Phase the tone advances by over the symbol, in [0, 2π)
+    advance: f32,
+}
+
+impl ToneTable {
+    fn new(bin: usize, symbol_samples: usize, sample_rate: f32) -> Self {
+        let (frequency, sample_rate) = (bin_to_freq(bin) as f64, sample_rate as f64);
+        // f·i wraps exactly in f64, so every angle is reduced to [0, 2π)
+        // before rounding to f32
+        let angle = |i: usize| {
+            (2.0 * std::f64::consts::PI * (frequency * i as f64).rem_euclid(sample_rate) / sample_rate) as f32
+        };
+        Self {
+            sin: (0..symbol_samples).map(|i| trig::sin(angle(i))).collect(),
+            cos: (0..symbol_samples).map(|i| trig::cos(angle(i))).collect(),
+            advance: angle(symbol_samples).rem_euclid(2.0 * PI),
+        }
+    }
+}
+
 /// FSK modulator - generates multi-tone audio for simultaneous transmission
 ///
 /// Transmits 3 bytes (6 nibbles) per symbol using 6 simultaneous frequencies.
 /// Each nibble (4 bits, value 0-15) selects one frequency from a band of 16 frequencies.
 /// The 6 frequencies are transmitted simultaneously in the same time slot.
+///
+/// The modulator is phase-continuous: each band's tone starts at the phase the
+/// band's previous tone ended on, so consecutive symbols join without a jump.
+///
+/// Other tone counts and band widths are set with a `ToneLayout`.
+///
+/// Each tone is synthesized once per modulator (on first use) and symbols are
+/// mixed from those tables; clones share them.
+#[derive(Clone)]
 pub struct FskModulator {
     sample_rate: f32,
+    symbol_samples: usize,
+    layout: ToneLayout,
+    /// Phase (radians) each band's oscillator has reached (upper-band copies after the bands)
+    band_phases: [f32; 2 * FSK_NIBBLES_PER_SYMBOL],
+    /// Amplitude of each bin's tone (pre-emphasis); None sends all at full level
+    bin_gains: Option<[f32; FSK_NUM_BINS]>,
+    /// Relative level of each band's tones; None sends all bands at one level
+    band_gains: Option<[f32; FSK_NIBBLES_PER_SYMBOL]>,
+    /// Send tone values as steps from the previous symbol's tones
+    differential: bool,
+    /// Edge taper of a symbol, normalized to unit mean gain
+    taper: Vec<f32>,
+    /// Tone tables by bin, built on first use
+    tone_tables: Vec<Option<Arc<ToneTable>>>,
 }
 
 impl FskModulator {
     pub fn new() -> Self {
-        Self {
+        Self::with_symbol_samples(Samples(FSK_SYMBOL_SAMPLES))
+    }
+
+    /// Modulator with a non-default symbol length (e.g. `SHORT_SYMBOL_SAMPLES`)
+    ///
+    /// Tones stay orthogonal when the symbol lasts a multiple of 1/20 Hz = 800 samples.
+    pub fn with_symbol_samples(symbol_samples: Samples) -> Self {
+        Self::with_layout(symbol_samples, ToneLayout::STANDARD)
+    }
+
+    /// Modulator sending `layout.tones()` tones per symbol (must match the demodulator)
+    pub fn with_layout(symbol_samples: Samples, layout: ToneLayout) -> Self {
+        let symbol_samples = symbol_samples.get();
+        let mut modulator = Self {
             sample_rate: crate::SAMPLE_RATE as f32,
-        }
+            symbol_samples,
+            layout,
+            band_phases: [0.0; 2 * FSK_NIBBLES_PER_SYMBOL],
+            bin_gains: None,
+            band_gains: None,
+            differential: false,
+            taper: Vec::new(),
+            tone_tables: vec![None; FSK_NUM_BINS],
+        };
+        modulator.taper = modulator.edge_taper(symbol_samples);
+        modulator
+    }
+
+    pub fn symbol_samples(&self) -> usize {
+        self.symbol_samples
+    }
+
+    pub fn layout(&self) -> ToneLayout {
+        self.layout
+    }
+
+    /// Send each bin's tone at `gains[bin]` (at most 1.0) of its level, e.g. a
+    /// `PreEmphasis` compensating a speaker; None sends every tone at full level
+    pub fn set_bin_gains(&mut self, gains: Option<[f32; FSK_NUM_BINS]>) {
+        self.bin_gains = gains;
+    }
+
+    pub fn bin_gains(&self) -> Option<[f32; FSK_NUM_BINS]> {
+        self.bin_gains
+    }
+
+    /// Send the tones of each band at `gains[band]` relative to the other
+    /// bands, e.g. boosting the high bands for a small speaker or cutting one
+    /// a regulator limits; None sends every band at the same level
+    ///
+    /// Symbols are normalized by the summed gains of their tones, so a boost
+    /// lowers the other bands instead of clipping. Gains must be positive and
+    /// finite. Decoders take the same table in
+    /// [`FskDemodulator::set_band_compensation`].
+    pub fn set_band_gains(&mut self, gains: Option<[f32; FSK_NIBBLES_PER_SYMBOL]>) -> Result<()> {
+        self.band_gains = gains.map(check_band_gains).transpose()?;
+        Ok(())
+    }
+
+    pub fn band_gains(&self) -> Option<[f32; FSK_NIBBLES_PER_SYMBOL]> {
+        self.band_gains
+    }
+
+    /// Use the bin and band gains of `other`, e.g. for a modulator of another layout
+    pub(crate) fn share_gains(&mut self, other: &FskModulator) {
+        self.bin_gains = other.bin_gains;
+        self.band_gains = other.band_gains;
+    }
+
+    /// Send each tone value as the step (modulo the band size) from the tone
+    /// the band sent in the previous symbol, instead of as the tone itself
+    ///
+    /// An offset shared by consecutive symbols, e.g. from a slowly drifting
+    /// channel, then cancels out; gains, flat or tilted, need no steps since
+    /// the demodulator picks the strongest tone per band. Each `modulate`/`modulate_into` call starts
+    /// from tone 0 in every band; other modulate functions are unaffected.
+    pub fn set_differential(&mut self, enabled: bool) {
+        self.differential = enabled;
+    }
+
+    pub fn differential(&self) -> bool {
+        self.differential
+    }
+
+    /// Start the next symbol at phase 0 in every band
+    pub fn reset_phase(&mut self) {
+        self.band_phases = [0.0; 2 * FSK_NIBBLES_PER_SYMBOL];
     }
 
     /// Modulate 3 bytes into a multi-tone FSK symbol
@@ -162,72 +566,143 @@ impl FskModulator {
     /// - Nibble 4 (byte[2] high): bins 64-79
     /// - Nibble 5 (byte[2] low):  bins 80-95
     ///
-    /// All 6 tones are generated simultaneously and superimposed.
+    /// All 6 tones are generated simultaneously and superimposed, each continuing
+    /// from the phase its band reached in the previous symbol. Other layouts take
+    /// `bits_per_symbol` bits (MSB first) in as many bytes as they fill.
     pub fn modulate_symbol(&mut self, bytes: &[u8]) -> Result<Vec<f32>> {
-        if bytes.len() != FSK_BYTES_PER_SYMBOL {
+        self.modulate_symbol_with_levels(bytes, AMPLITUDE_ALL_HIGH)
+    }
+
+    /// Modulate a symbol whose tones carry one amplitude bit each
+    ///
+    /// Bit 5 of `levels` is band 0 ... bit 0 is band 5; a clear bit sends the
+    /// tone at `AMPLITUDE_LOW_LEVEL`.
+    pub fn modulate_symbol_with_levels(&mut self, bytes: &[u8], levels: u8) -> Result<Vec<f32>> {
+        if bytes.len() != self.layout.bits_per_symbol().div_ceil(8) {
             return Err(AudioModemError::InvalidInputSize);
         }
+        Ok(self.modulate_values(&self.layout.split(bytes, 0), levels))
+    }
 
-        let symbol_samples = FSK_SYMBOL_SAMPLES;
+    /// One symbol sending tone `values[band]` in each band of the layout
+    fn modulate_values(&mut self, values: &[u8; FSK_NIBBLES_PER_SYMBOL], levels: u8) -> Vec<f32> {
+        let symbol_samples = self.symbol_samples;
         let mut samples = vec![0.0f32; symbol_samples];
 
-        // Extract 6 nibbles from 3 bytes
-        let nibbles = [
-            (bytes[0] >> 4) & 0x0F,  // High nibble of byte 0
-            bytes[0] & 0x0F,         // Low nibble of byte 0
-            (bytes[1] >> 4) & 0x0F,  // High nibble of byte 1
-            bytes[1] & 0x0F,         // Low nibble of byte 1
-            (bytes[2] >> 4) & 0x0F,  // High nibble of byte 2
-            bytes[2] & 0x0F,         // Low nibble of byte 2
-        ];
-
-        // Generate and superimpose all 6 tones
-        for (nibble_idx, &nibble_val) in nibbles.iter().enumerate() {
-            // Each nibble has a dedicated band of 16 frequencies
-            let band_offset = nibble_idx * FSK_BINS_PER_BAND;
-            let bin = band_offset + (nibble_val as usize);
-
-            if bin >= FSK_NUM_BINS {
-                return Err(AudioModemError::InvalidInputSize);
+        // Generate and superimpose one tone per band (and its copy in dual-band layouts)
+        let mut total_gain = 0.0;
+        for (nibble_idx, &nibble_val) in values[..self.layout.tones].iter().enumerate() {
+            let band_gain = self.band_gains.map_or(1.0, |gains| gains[nibble_idx]);
+            let level = if levels & amplitude_bit(nibble_idx) != 0 { 1.0 } else { AMPLITUDE_LOW_LEVEL };
+            let level = level * band_gain;
+
+            // Each band has a dedicated run of `bins_per_band` frequencies
+            for (copy, bin) in self.layout.bins(nibble_idx, nibble_val as usize).enumerate() {
+                let table = self.tone_table(bin);
+                let level = level * self.bin_gains.map_or(1.0, |gains| gains.get(bin).copied().unwrap_or(1.0));
+                let phase = &mut self.band_phases[nibble_idx + copy * FSK_NIBBLES_PER_SYMBOL];
+                let start_phase = *phase;
+
+                // Add this tone, shifted to the band's phase, to the output
+                let (sin_start, cos_start) = (level * trig::sin(start_phase), level * trig::cos(start_phase));
+                for ((sample, &sin), &cos) in samples.iter_mut().zip(&table.sin).zip(&table.cos) {
+                    *sample += sin_start * cos + cos_start * sin;
+                }
+                *phase = (start_phase + table.advance).rem_euclid(2.0 * PI);
+                total_gain += band_gain;
             }
+        }
 
-            let frequency = bin_to_freq(bin);
-            let angular_freq = 2.0 * PI * frequency / self.sample_rate;
-
-            // Add this tone to the output
-            for i in 0..symbol_samples {
-                samples[i] += (angular_freq * i as f32).sin();
-            }
+        // Taper the edges, scale by 1/(summed band gains, the tone count
+        // without band gains) to prevent clipping when superimposing the
+        // tones, and apply 0.7 overall amplitude
+        let scale = 0.7 / total_gain;
+        for (sample, &weight) in samples.iter_mut().zip(&self.taper) {
+            *sample *= weight * scale;
         }
 
-        self.apply_edge_taper(&mut samples);
+        samples
+    }
 
-        // Scale by 1/6 to prevent clipping when superimposing 6 tones
-        // Also apply 0.7 overall amplitude
-        let scale = 0.7 / FSK_NIBBLES_PER_SYMBOL as f32;
-        for sample in samples.iter_mut() {
-            *sample *= scale;
+    /// Table of tone `bin` over one symbol, synthesized on first use
+    fn tone_table(&mut self, bin: usize) -> Arc<ToneTable> {
+        if bin >= self.tone_tables.len() {
+            self.tone_tables.resize(bin + 1, None);
         }
-
-        Ok(samples)
+        let (symbol_samples, sample_rate) = (self.symbol_samples, self.sample_rate);
+        self.tone_tables[bin]
+            .get_or_insert_with(|| Arc::new(ToneTable::new(bin, symbol_samples, sample_rate)))
+            .clone()
     }
 
     /// Modulate a sequence of bytes
-    /// Input length must be a multiple of FSK_BYTES_PER_SYMBOL (3)
+    /// Input length must be a multiple of `ToneLayout::alignment_bytes` (3 for the standard layout)
+    ///
+    /// Each call starts from phase 0, so equal input gives equal audio.
     pub fn modulate(&mut self, bytes: &[u8]) -> Result<Vec<f32>> {
-        if bytes.len() % FSK_BYTES_PER_SYMBOL != 0 {
+        let mut samples = Vec::with_capacity(self.layout.symbols_for(bytes.len()) * self.symbol_samples);
+        self.modulate_into(bytes, &mut |symbol| samples.extend_from_slice(symbol))?;
+        Ok(samples)
+    }
+
+    /// Like `modulate`, but hands each symbol to `sink` instead of collecting them
+    pub fn modulate_into(&mut self, bytes: &[u8], sink: &mut impl FnMut(&[f32])) -> Result<()> {
+        if !bytes.len().is_multiple_of(self.layout.alignment_bytes()) {
             return Err(AudioModemError::InvalidInputSize);
         }
 
-        let mut samples = Vec::new();
-        for chunk in bytes.chunks(FSK_BYTES_PER_SYMBOL) {
-            let symbol_samples = self.modulate_symbol(chunk)?;
-            samples.extend_from_slice(&symbol_samples);
+        self.reset_phase();
+        let bits_per_symbol = self.layout.bits_per_symbol();
+        let mut previous = [0u8; FSK_NIBBLES_PER_SYMBOL];
+        for symbol in 0..self.layout.symbols_for(bytes.len()) {
+            let mut values = self.layout.split(bytes, symbol * bits_per_symbol);
+            if self.differential {
+                self.layout.steps_to_tones(&mut values, &mut previous);
+            }
+            sink(&self.modulate_values(&values, AMPLITUDE_ALL_HIGH));
         }
+        Ok(())
+    }
 
+    /// Modulate bytes as amplitude-shaped symbols of `AMPLITUDE_SYMBOL_BITS` each
+    ///
+    /// Emits the two pilot symbols (all tones high, then all low) that the
+    /// demodulator calibrates its per-band thresholds on, then the data; the
+    /// last symbol is zero-padded. Continues from the current phase.
+    pub fn modulate_amplitude_shaped(&mut self, bytes: &[u8]) -> Result<Vec<f32>> {
+        let mut samples = Vec::new();
+        self.modulate_amplitude_shaped_into(bytes, &mut |symbol| samples.extend_from_slice(symbol))?;
         Ok(samples)
     }
 
+    /// Like `modulate_amplitude_shaped`, but hands each symbol to `sink`
+    pub fn modulate_amplitude_shaped_into(&mut self, bytes: &[u8], sink: &mut impl FnMut(&[f32])) -> Result<()> {
+        if self.layout != ToneLayout::STANDARD {
+            return Err(AudioModemError::InvalidConfig(
+                "amplitude-shaped symbols need the standard tone layout".to_string(),
+            ));
+        }
+        // Only the layout can fail, so a sink never sees part of the symbols
+        let pilots = self.layout.split(&AMPLITUDE_PILOT_BYTES, 0);
+        sink(&self.modulate_values(&pilots, AMPLITUDE_ALL_HIGH));
+        sink(&self.modulate_values(&pilots, 0));
+
+        let total_bits = bytes.len() * 8;
+        let mut bit_pos = 0;
+        while bit_pos < total_bits {
+            let tone_bytes = [
+                read_bits(bytes, bit_pos, 8) as u8,
+                read_bits(bytes, bit_pos + 8, 8) as u8,
+                read_bits(bytes, bit_pos + 16, 8) as u8,
+            ];
+            let levels = read_bits(bytes, bit_pos + 24, FSK_NIBBLES_PER_SYMBOL) as u8;
+            sink(&self.modulate_values(&self.layout.split(&tone_bytes, 0), levels));
+            bit_pos += AMPLITUDE_SYMBOL_BITS;
+        }
+
+        Ok(())
+    }
+
     fn taper_length(&self, symbol_samples: usize) -> usize {
         let mut taper =
             ((symbol_samples as f32) * FSK_EDGE_TAPER_RATIO).round() as usize;
@@ -241,48 +716,246 @@ impl FskModulator {
         taper
     }
 
-    fn apply_edge_taper(&self, samples: &mut [f32]) {
-        let taper_len = self.taper_length(samples.len());
+    /// Raised-cosine edge taper of a symbol, normalized so the mean gain is 1
+    fn edge_taper(&self, symbol_samples: usize) -> Vec<f32> {
+        let taper_len = self.taper_length(symbol_samples);
         if taper_len == 0 {
-            return;
+            return vec![1.0; symbol_samples];
         }
 
-        let window = raised_cosine_window(samples.len(), taper_len);
-        let avg = window.iter().sum::<f32>() / samples.len() as f32;
+        let window = raised_cosine_window(symbol_samples, taper_len);
+        let avg = window.iter().sum::<f32>() / symbol_samples as f32;
         let normalization = if avg > 0.0 { 1.0 / avg } else { 1.0 };
+        window.into_iter().map(|weight| weight * normalization).collect()
+    }
+}
 
-        for (sample, &weight) in samples.iter_mut().zip(window.iter()) {
-            *sample *= weight * normalization;
+/// Per-tone decision statistics accumulated while demodulating
+///
+/// Margin is (best - runner-up) / best on the noise-suppressed band spectrum:
+/// 1.0 means only one tone stood out, 0.0 means a tie.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolStats {
+    pub decisions: usize,
+    pub min_margin: f32,
+    pub margin_sum: f32,
+    pub tone_power: f32,
+    pub noise_power: f32,
+}
+
+impl Default for SymbolStats {
+    fn default() -> Self {
+        Self {
+            decisions: 0,
+            min_margin: 1.0,
+            margin_sum: 0.0,
+            tone_power: 0.0,
+            noise_power: 0.0,
         }
     }
 }
 
+impl SymbolStats {
+    fn record(&mut self, margin: f32, tone_power: f32, noise_power: f32) {
+        self.decisions += 1;
+        self.min_margin = self.min_margin.min(margin);
+        self.margin_sum += margin;
+        self.tone_power += tone_power;
+        self.noise_power += noise_power;
+    }
+
+    pub fn merge(&mut self, other: &SymbolStats) {
+        self.decisions += other.decisions;
+        self.min_margin = self.min_margin.min(other.min_margin);
+        self.margin_sum += other.margin_sum;
+        self.tone_power += other.tone_power;
+        self.noise_power += other.noise_power;
+    }
+
+    pub fn mean_margin(&self) -> f32 {
+        if self.decisions == 0 {
+            0.0
+        } else {
+            self.margin_sum / self.decisions as f32
+        }
+    }
+
+    /// Tone power vs. average power of the other bins in the same band (dB)
+    pub fn snr_db(&self) -> f32 {
+        10.0 * ((self.tone_power + 1e-12) / (self.noise_power + 1e-12)).log10()
+    }
+}
+
 /// FSK demodulator - detects multiple simultaneous frequencies using FFT
 ///
-/// Analyzes the spectrum to find 6 simultaneous tones, each representing a nibble.
+/// Analyzes the spectrum to find 6 simultaneous tones, each representing a nibble
+/// (or the tones of another `ToneLayout`).
 pub struct FskDemodulator {
     sample_rate: f32,
+    symbol_samples: usize,
+    layout: ToneLayout,
+    unreliable_bins: [bool; FSK_NUM_BINS],
+    /// Band gains of the modulator, undone before tone decisions
+    band_compensation: Option<[f32; FSK_NIBBLES_PER_SYMBOL]>,
+    differential: bool,
+    audit: Option<SymbolAuditSink>,
 }
 
 impl FskDemodulator {
     pub fn new() -> Self {
+        Self::with_symbol_samples(Samples(FSK_SYMBOL_SAMPLES))
+    }
+
+    /// Demodulator for symbols of `symbol_samples` (must match the modulator)
+    pub fn with_symbol_samples(symbol_samples: Samples) -> Self {
+        Self::with_layout(symbol_samples, ToneLayout::STANDARD)
+    }
+
+    /// Demodulator for symbols sent with `layout` (must match the modulator)
+    pub fn with_layout(symbol_samples: Samples, layout: ToneLayout) -> Self {
         Self {
             sample_rate: crate::SAMPLE_RATE as f32,
+            symbol_samples: symbol_samples.get(),
+            layout,
+            unreliable_bins: [false; FSK_NUM_BINS],
+            band_compensation: None,
+            differential: false,
+            audit: None,
+        }
+    }
+
+    pub fn symbol_samples(&self) -> usize {
+        self.symbol_samples
+    }
+
+    pub fn layout(&self) -> ToneLayout {
+        self.layout
+    }
+
+    /// Treat tone `bins` (0..FSK_NUM_BINS) as unreliable, e.g. where a constant
+    /// interferer sits (see [`freq_to_bin`]); replaces the previous set
+    ///
+    /// Their power is ignored when picking tones. A band left without a clear
+    /// tone decodes to its unreliable tone (if it has just one) and is
+    /// reported by `demodulate_with_erasures`.
+    pub fn set_unreliable_bins(&mut self, bins: &[usize]) -> Result<()> {
+        if let Some(bin) = bins.iter().find(|&&bin| bin >= FSK_NUM_BINS) {
+            return Err(AudioModemError::InvalidConfig(format!("tone bin {} is outside 0..{}", bin, FSK_NUM_BINS)));
+        }
+        self.unreliable_bins = [false; FSK_NUM_BINS];
+        for &bin in bins {
+            self.unreliable_bins[bin] = true;
+        }
+        Ok(())
     }
+
+    /// Tone bins marked unreliable, ascending
+    pub fn unreliable_bins(&self) -> Vec<usize> {
+        (0..FSK_NUM_BINS).filter(|&bin| self.unreliable_bins[bin]).collect()
+    }
+
+    /// Undo the band gains the modulator used (see
+    /// [`FskModulator::set_band_gains`]), so tone powers in the statistics and
+    /// the symbol audit are comparable across bands; None assumes equal bands
+    pub fn set_band_compensation(&mut self, gains: Option<[f32; FSK_NIBBLES_PER_SYMBOL]>) -> Result<()> {
+        self.band_compensation = gains.map(check_band_gains).transpose()?;
+        Ok(())
+    }
+
+    pub fn band_compensation(&self) -> Option<[f32; FSK_NIBBLES_PER_SYMBOL]> {
+        self.band_compensation
+    }
+
+    /// Read symbols sent by a differential modulator (see
+    /// [`FskModulator::set_differential`]) in `demodulate_with_erasures` and
+    /// the functions built on it
+    ///
+    /// A step depends on two tone decisions, so a band erased in one symbol
+    /// is also reported as erased in the next. The symbol audit keeps the tones.
+    pub fn set_differential(&mut self, enabled: bool) {
+        self.differential = enabled;
+    }
+
+    pub fn differential(&self) -> bool {
+        self.differential
+    }
+
+    /// Use the unreliable bins and band compensation of `other`, e.g. for a
+    /// demodulator of another layout
+    pub(crate) fn share_unreliable_bins(&mut self, other: &FskDemodulator) {
+        self.unreliable_bins = other.unreliable_bins;
+        self.band_compensation = other.band_compensation;
+    }
+
+    /// Record the decisions of `demodulate*` calls (not `demodulate_symbol`) in `audit`
+    pub(crate) fn set_audit(&mut self, audit: Option<SymbolAuditSink>) {
+        self.audit = audit;
+    }
+
+    pub(crate) fn audit(&self) -> Option<&SymbolAuditSink> {
+        self.audit.as_ref()
+    }
+
+    fn lock_audit(&self) -> Option<MutexGuard<'_, Vec<SymbolDecision>>> {
+        self.audit.as_ref().map(|audit| audit.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Whether every bin sending tone `value` in `band` is unreliable
+    fn is_unreliable(&self, band: usize, value: usize) -> bool {
+        self.layout.bins(band, value).all(|bin| self.unreliable_bins.get(bin).copied().unwrap_or(false))
     }
 
     /// Compute power spectrum using simple DFT for our specific frequency bins
     ///
     /// This is more efficient than full FFT since we only need 96 specific bins.
     /// For each bin, we compute the magnitude using Goertzel-like approach.
-    fn compute_spectrum(&self, samples: &[f32]) -> Vec<f32> {
+    ///
+    /// Returns (conditioned bin powers, noise-suppressed spectrum used for decisions),
+    /// both holding only the layout's tone bins, band after band. Dual-band
+    /// layouts fuse each tone with its upper-band copy by summing their power.
+    fn compute_spectrum(&self, samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
         let conditioned = self.preprocess_symbol(samples);
-        let n = conditioned.len();
+        let powers = self.bin_powers(&conditioned);
+        let raw: Vec<f32> = (0..self.layout.tones)
+            .flat_map(|band| (0..self.layout.bins_per_band).map(move |value| (band, value)))
+            .map(|(band, value)| {
+                let power: f32 =
+                    self.layout.bins(band, value).filter(|&bin| !self.unreliable_bins[bin]).map(|bin| powers[bin]).sum();
+                // Powers scale with the square of the band gain
+                power / self.band_compensation.map_or(1.0, |gains| gains[band] * gains[band])
+            })
+            .collect();
+        let mut spectrum = raw.clone();
+        self.suppress_band_noise(&mut spectrum);
+        (raw, spectrum)
+    }
+
+    /// Raw Goertzel power at each of the FSK_NUM_BINS tone frequencies
+    ///
+    /// No AGC or noise-floor suppression is applied, so values are comparable
+    /// across windows (useful for visualization and diagnostics). Computed by
+    /// `bin_powers_q15` with the `fixed-point` feature, `bin_powers_f32` otherwise.
+    pub fn bin_powers(&self, samples: &[f32]) -> Vec<f32> {
+        #[cfg(feature = "fixed-point")]
+        {
+            self.bin_powers_q15(samples)
+        }
+        #[cfg(not(feature = "fixed-point"))]
+        {
+            self.bin_powers_f32(samples)
+        }
+    }
+
+    /// `bin_powers` in f32 arithmetic
+    pub fn bin_powers_f32(&self, samples: &[f32]) -> Vec<f32> {
+        let n = samples.len();
         let mut spectrum = vec![0.0f32; FSK_NUM_BINS];
+        if n == 0 {
+            return spectrum;
+        }
 
         for bin in 0..FSK_NUM_BINS {
-            let freq = bin_to_freq(bin);
-            let k = (0.5 + (n as f32 * freq / self.sample_rate)) as usize;
+            let k = self.dft_bin(n, bin);
             let omega = 2.0 * PI * k as f32 / n as f32;
             let coeff = 2.0 * omega.cos();
 
@@ -290,7 +963,7 @@ impl FskDemodulator {
             let mut q2 = 0.0;
 
             // Goertzel filter
-            for &sample in &conditioned {
+            for &sample in samples {
                 let q0 = coeff * q1 - q2 + sample;
                 q2 = q1;
                 q1 = q0;
@@ -302,28 +975,177 @@ impl FskDemodulator {
             spectrum[bin] = real * real + imag * imag;
         }
 
-        self.suppress_band_noise(&mut spectrum);
         spectrum
     }
 
+    /// `bin_powers` with Q15 samples and integer Goertzel filters (see [`crate::fixed_point`])
+    pub fn bin_powers_q15(&self, samples: &[f32]) -> Vec<f32> {
+        let (quantized, scale) = quantize_q15(samples);
+        let unscale = 1.0 / (scale * scale);
+        (0..FSK_NUM_BINS)
+            .map(|bin| goertzel_power_q15(&quantized, self.dft_bin(samples.len(), bin)) as f32 * unscale)
+            .collect()
+    }
+
+    /// DFT bin of an `n`-point window closest to tone bin `bin`
+    fn dft_bin(&self, n: usize, bin: usize) -> usize {
+        (0.5 + (n as f32 * bin_to_freq(bin) / self.sample_rate)) as usize
+    }
+
     /// Demodulate a single multi-tone FSK symbol
     ///
     /// Detects 6 simultaneous tones, one from each band of 16 frequencies.
-    /// Returns the 3 bytes encoded in the symbol.
+    /// Returns the 3 bytes encoded in the symbol (fewer bits of other layouts
+    /// come first, followed by zeros).
     pub fn demodulate_symbol(&self, samples: &[f32]) -> Result<[u8; FSK_BYTES_PER_SYMBOL]> {
-        if samples.len() != FSK_SYMBOL_SAMPLES {
+        let mut stats = SymbolStats::default();
+        let (values, _) = self.decide_symbol(samples, &mut stats, None)?;
+        let mut bytes = Vec::with_capacity(FSK_BYTES_PER_SYMBOL);
+        self.layout.join(&values, &mut bytes, &mut 0);
+        let mut symbol = [0u8; FSK_BYTES_PER_SYMBOL];
+        symbol[..bytes.len()].copy_from_slice(&bytes);
+        Ok(symbol)
+    }
+
+    /// Demodulate a sequence of multi-tone FSK symbols
+    /// samples.len() must be a multiple of the symbol length
+    pub fn demodulate(&self, samples: &[f32]) -> Result<Vec<u8>> {
+        self.demodulate_with_stats(samples).map(|(bytes, _)| bytes)
+    }
+
+    /// Demodulate symbols and report how confident the tone decisions were
+    pub fn demodulate_with_stats(&self, samples: &[f32]) -> Result<(Vec<u8>, SymbolStats)> {
+        self.demodulate_with_erasures(samples).map(|(bytes, stats, _)| (bytes, stats))
+    }
+
+    /// `demodulate_with_stats` that also returns the indices of the bytes
+    /// holding a tone that may have been in an unreliable bin, ascending
+    pub fn demodulate_with_erasures(&self, samples: &[f32]) -> Result<(Vec<u8>, SymbolStats, Vec<usize>)> {
+        if !samples.len().is_multiple_of(self.symbol_samples) {
+            return Err(AudioModemError::InvalidInputSize);
+        }
+
+        let bits = self.layout.bits_per_tone();
+        let mut bytes = Vec::new();
+        let mut bit_pos = 0;
+        let mut stats = SymbolStats::default();
+        let mut erasures = Vec::new();
+        let mut audit = self.lock_audit();
+        let (mut previous, mut previous_erased) = ([0u8; FSK_NIBBLES_PER_SYMBOL], 0u8);
+        for chunk in samples.chunks(self.symbol_samples) {
+            let (mut values, mut erased) = self.decide_symbol(chunk, &mut stats, audit.as_deref_mut())?;
+            if self.differential {
+                self.layout.tones_to_steps(&mut values, &mut previous);
+                (erased, previous_erased) = (erased | previous_erased, erased);
+            }
+            for band in (0..self.layout.tones).filter(|&band| erased & (1 << band) != 0) {
+                push_erased_bits(&mut erasures, bit_pos + band * bits, bits);
+            }
+            self.layout.join(&values, &mut bytes, &mut bit_pos);
+        }
+        // Drop a partial byte of padding bits
+        bytes.truncate(bit_pos / 8);
+        erasures.retain(|&byte| byte < bytes.len());
+
+        Ok((bytes, stats, erasures))
+    }
+
+    /// Demodulate amplitude-shaped symbols produced by `FskModulator::modulate_amplitude_shaped`
+    ///
+    /// Calibrates a per-band threshold halfway (in dB) between the two pilot
+    /// symbols, then compares each chosen tone's power against it. Returns the
+    /// bytes, the tone statistics and the weakest band's pilot contrast in dB.
+    pub fn demodulate_amplitude_shaped(&self, samples: &[f32]) -> Result<(Vec<u8>, SymbolStats, f32)> {
+        self.demodulate_amplitude_shaped_with_erasures(samples)
+            .map(|(bytes, stats, _, contrast_db)| (bytes, stats, contrast_db))
+    }
+
+    /// `demodulate_amplitude_shaped` that also returns the indices of the
+    /// bytes holding a tone, or the level of a tone, that may have been in an
+    /// unreliable bin, ascending
+    pub fn demodulate_amplitude_shaped_with_erasures(
+        &self,
+        samples: &[f32],
+    ) -> Result<(Vec<u8>, SymbolStats, Vec<usize>, f32)> {
+        let n = self.symbol_samples;
+        if !samples.len().is_multiple_of(n) || samples.len() < 2 * n {
+            return Err(AudioModemError::InvalidInputSize);
+        }
+        if self.layout != ToneLayout::STANDARD {
+            return Err(AudioModemError::InvalidConfig(
+                "amplitude-shaped symbols need the standard tone layout".to_string(),
+            ));
+        }
+
+        let high = self.bin_powers(&samples[..n]);
+        let low = self.bin_powers(&samples[n..2 * n]);
+        let mut thresholds = [0.0f32; FSK_NIBBLES_PER_SYMBOL];
+        let mut contrast_db = f32::INFINITY;
+        for (band, threshold) in thresholds.iter_mut().enumerate() {
+            let pilot_bin = band * FSK_BINS_PER_BAND + pilot_nibble(band) as usize;
+            let (high, low) = (high[pilot_bin], low[pilot_bin]);
+            *threshold = (high * low).sqrt();
+            contrast_db = contrast_db.min(10.0 * ((high + 1e-12) / (low + 1e-12)).log10());
+        }
+
+        let mut bytes = Vec::new();
+        let mut bit_pos = 0;
+        let mut stats = SymbolStats::default();
+        let mut erasures = Vec::new();
+        let mut audit = self.lock_audit();
+        for chunk in samples[2 * n..].chunks(n) {
+            let (nibbles, erased) = self.decide_symbol(chunk, &mut stats, audit.as_deref_mut())?;
+            // The tone picks the nibble and its power the level bit (after the nibbles)
+            let bits = self.layout.bits_per_tone();
+            let erased_bands = (0..FSK_NIBBLES_PER_SYMBOL).filter(|&band| erased & (1 << band) != 0);
+            for band in erased_bands.clone() {
+                push_erased_bits(&mut erasures, bit_pos + band * bits, bits);
+            }
+            for band in erased_bands {
+                push_erased_bits(&mut erasures, bit_pos + FSK_BYTES_PER_SYMBOL * 8 + band, 1);
+            }
+            let powers = self.bin_powers(chunk);
+            let mut levels = 0u8;
+            for (band, threshold) in thresholds.iter().enumerate() {
+                if powers[band * FSK_BINS_PER_BAND + nibbles[band] as usize] > *threshold {
+                    levels |= amplitude_bit(band);
+                }
+            }
+            self.layout.join(&nibbles, &mut bytes, &mut bit_pos);
+            write_bits(&mut bytes, &mut bit_pos, levels as u32, FSK_NIBBLES_PER_SYMBOL);
+        }
+        // Drop the partial byte made of padding bits
+        bytes.truncate(bit_pos / 8);
+        erasures.retain(|&byte| byte < bytes.len());
+
+        Ok((bytes, stats, erasures, contrast_db))
+    }
+
+    /// Pick the strongest tone in each band and record the decision margin and
+    /// tone-to-band power for quality reporting; returns each band's tone value
+    /// and a mask of the bands decided as erasures (bit `band`), appended to
+    /// `audit` if given
+    fn decide_symbol(
+        &self,
+        samples: &[f32],
+        stats: &mut SymbolStats,
+        audit: Option<&mut Vec<SymbolDecision>>,
+    ) -> Result<([u8; FSK_NIBBLES_PER_SYMBOL], u8)> {
+        if samples.len() != self.symbol_samples {
             return Err(AudioModemError::InvalidInputSize);
         }
 
         // Compute power spectrum
-        let spectrum = self.compute_spectrum(samples);
+        let (raw, spectrum) = self.compute_spectrum(samples);
 
-        // Detect the strongest frequency in each of the 6 bands
+        // Detect the strongest frequency in each band
+        let bins_per_band = self.layout.bins_per_band;
         let mut nibbles = [0u8; FSK_NIBBLES_PER_SYMBOL];
+        let mut erased = 0u8;
 
-        for nibble_idx in 0..FSK_NIBBLES_PER_SYMBOL {
-            let band_start = nibble_idx * FSK_BINS_PER_BAND;
-            let band_end = band_start + FSK_BINS_PER_BAND;
+        for (nibble_idx, nibble) in nibbles.iter_mut().enumerate().take(self.layout.tones) {
+            let band_start = nibble_idx * bins_per_band;
+            let band_end = band_start + bins_per_band;
 
             // Find bin with maximum energy in this band
             let mut max_bin_in_band = 0;
@@ -336,34 +1158,45 @@ impl FskDemodulator {
                 }
             }
 
-            // The nibble value is the offset within the band
-            nibbles[nibble_idx] = max_bin_in_band as u8;
-        }
-
-        // Reconstruct 3 bytes from 6 nibbles
-        let bytes = [
-            (nibbles[0] << 4) | nibbles[1],  // Byte 0
-            (nibbles[2] << 4) | nibbles[3],  // Byte 1
-            (nibbles[4] << 4) | nibbles[5],  // Byte 2
-        ];
-
-        Ok(bytes)
-    }
+            let runner_up = spectrum[band_start..band_end]
+                .iter()
+                .enumerate()
+                .filter(|&(offset, _)| offset != max_bin_in_band)
+                .fold(0.0f32, |acc, (_, &energy)| acc.max(energy));
+            let margin = if max_energy > 0.0 { (max_energy - runner_up) / max_energy } else { 0.0 };
+
+            let band_raw = &raw[band_start..band_end];
+            let tone_power = band_raw[max_bin_in_band];
+            let other_power = (band_raw.iter().sum::<f32>() - tone_power) / (bins_per_band - 1) as f32;
+            stats.record(margin, tone_power, other_power);
+
+            // No tone standing out among the reliable bins: it was most likely
+            // in an unreliable one, so guess that if there is only one and let
+            // the FEC treat the decision as an erasure
+            let mut unreliable = (0..bins_per_band).filter(|&value| self.is_unreliable(nibble_idx, value));
+            if let Some(value) = unreliable.next() {
+                if margin < FSK_ERASURE_MARGIN {
+                    erased |= 1 << nibble_idx;
+                    if unreliable.next().is_none() {
+                        max_bin_in_band = value;
+                    }
+                }
+            }
 
-    /// Demodulate a sequence of multi-tone FSK symbols
-    /// samples.len() must be a multiple of FSK_SYMBOL_SAMPLES
-    pub fn demodulate(&self, samples: &[f32]) -> Result<Vec<u8>> {
-        if samples.len() % FSK_SYMBOL_SAMPLES != 0 {
-            return Err(AudioModemError::InvalidInputSize);
+            // The nibble value is the offset within the band
+            *nibble = max_bin_in_band as u8;
         }
 
-        let mut bytes = Vec::new();
-        for chunk in samples.chunks(FSK_SYMBOL_SAMPLES) {
-            let symbol_bytes = self.demodulate_symbol(chunk)?;
-            bytes.extend_from_slice(&symbol_bytes);
+        if let Some(audit) = audit {
+            let tones = self.layout.tones;
+            audit.push(SymbolDecision {
+                bins_per_band,
+                selected: nibbles[..tones].to_vec(),
+                energies: raw[..tones * bins_per_band].to_vec(),
+                erased,
+            });
         }
-
-        Ok(bytes)
+        Ok((nibbles, erased))
     }
 
     fn preprocess_symbol(&self, samples: &[f32]) -> Vec<f32> {
@@ -414,8 +1247,9 @@ impl FskDemodulator {
     }
 
     fn suppress_band_noise(&self, spectrum: &mut [f32]) {
-        for band_start in (0..FSK_NUM_BINS).step_by(FSK_BINS_PER_BAND) {
-            let band_end = band_start + FSK_BINS_PER_BAND;
+        let bins_per_band = self.layout.bins_per_band;
+        for band_start in (0..self.layout.tones * bins_per_band).step_by(bins_per_band) {
+            let band_end = band_start + bins_per_band;
             let band_slice = &mut spectrum[band_start..band_end];
 
             let mut sorted = band_slice.to_vec();
@@ -564,6 +1398,182 @@ mod tests {
         assert_eq!(decoded, bytes);
     }
 
+    #[test]
+    fn test_fsk_phase_continuous_across_symbols() {
+        let mut modulator = FskModulator::new();
+        let demodulator = FskDemodulator::new();
+
+        let first = modulator.modulate_symbol(&[0x00, 0x00, 0x00]).unwrap();
+        // Band 0 sent bin 0 (800 Hz) for one symbol and carries on from there
+        let expected = (2.0 * PI * 800.0 * FSK_SYMBOL_SAMPLES as f32 / crate::SAMPLE_RATE as f32).rem_euclid(2.0 * PI);
+        assert!((modulator.band_phases[0] - expected).abs() < 1e-2);
+
+        let second = modulator.modulate_symbol(&[0x5A, 0xC3, 0x96]).unwrap();
+        let from_zero = FskModulator::new().modulate_symbol(&[0x5A, 0xC3, 0x96]).unwrap();
+        assert_ne!(second, from_zero);
+        assert_eq!(demodulator.demodulate_symbol(&second).unwrap(), [0x5A, 0xC3, 0x96]);
+        assert_eq!(demodulator.demodulate_symbol(&first).unwrap(), [0x00, 0x00, 0x00]);
+
+        // Whole-frame modulation restarts at phase 0 every call
+        let bytes: Vec<u8> = (0..30).map(|i| (i * 37) as u8).collect();
+        let samples = modulator.modulate(&bytes).unwrap();
+        assert_eq!(samples, modulator.modulate(&bytes).unwrap());
+        assert_eq!(demodulator.demodulate(&samples).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_tone_tables_match_direct_synthesis() {
+        let mut modulator = FskModulator::new();
+        let bytes = [0x5A, 0xC3, 0x96];
+        for _ in 0..3 {
+            let phases = modulator.band_phases;
+            let samples = modulator.modulate_symbol(&bytes).unwrap();
+            for (i, &sample) in samples.iter().enumerate() {
+                let expected: f64 = (0..FSK_NIBBLES_PER_SYMBOL)
+                    .map(|band| {
+                        let nibble = (bytes[band / 2] >> (4 * (1 - band % 2))) & 0x0F;
+                        let frequency = bin_to_freq(band * 16 + nibble as usize) as f64;
+                        let omega = 2.0 * std::f64::consts::PI * frequency / crate::SAMPLE_RATE as f64;
+                        (phases[band] as f64 + omega * i as f64).sin()
+                    })
+                    .sum();
+                let expected = expected * (modulator.taper[i] * 0.7 / 6.0) as f64;
+                assert!((sample as f64 - expected).abs() < 1e-5, "sample {}: {} vs {}", i, sample, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_amplitude_shaped_roundtrip() {
+        let mut modulator = FskModulator::new();
+        let demodulator = FskDemodulator::new();
+
+        // 20 bytes = 160 bits -> 6 symbols of 30 bits after the two pilots
+        let bytes: Vec<u8> = (0..20).map(|i| (i * 73 + 11) as u8).collect();
+        let mut samples = modulator.modulate_amplitude_shaped(&bytes).unwrap();
+        assert_eq!(samples.len(), (2 + 6) * FSK_SYMBOL_SAMPLES);
+
+        // Channel gain does not matter: thresholds come from the pilots
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = *sample * 0.3 + (i as f32 * 0.731).sin() * 0.005;
+        }
+        let (decoded, stats, contrast_db) = demodulator.demodulate_amplitude_shaped(&samples).unwrap();
+        // The padding bits of the last symbol may add one zero byte
+        assert_eq!(&decoded[..bytes.len()], &bytes[..]);
+        assert_eq!(stats.decisions, 6 * FSK_NIBBLES_PER_SYMBOL);
+        assert!((contrast_db - 6.0).abs() < 1.5, "contrast {} dB", contrast_db);
+
+        assert!(demodulator.demodulate_amplitude_shaped(&samples[..FSK_SYMBOL_SAMPLES]).is_err());
+    }
+
+    #[test]
+    fn test_tone_layout_validation_and_codes() {
+        assert!(ToneLayout::new(1, 16).is_err());
+        assert!(ToneLayout::new(7, 16).is_err());
+        assert!(ToneLayout::new(3, 12).is_err());
+        assert!(ToneLayout::new(4, 2).is_err());
+
+        let layout = ToneLayout::new(3, 8).unwrap();
+        assert_eq!(layout.bits_per_symbol(), 9);
+        assert_eq!(layout.alignment_bytes(), 9);
+        assert_eq!(layout.bandwidth_hz(), 480.0);
+        assert_eq!(ToneLayout::STANDARD.alignment_bytes(), FSK_BYTES_PER_SYMBOL);
+        assert_eq!(ToneLayout::STANDARD.code(), 0);
+
+        // Every layout has its own 4-bit code
+        let mut codes = Vec::new();
+        for tones in MIN_LAYOUT_TONES..=FSK_NIBBLES_PER_SYMBOL {
+            for bins in LAYOUT_BINS_PER_BAND {
+                let layout = ToneLayout::new(tones, bins).unwrap();
+                assert!(layout.code() < 16);
+                assert_eq!(ToneLayout::from_code(layout.code()), Some(layout));
+                codes.push(layout.code());
+            }
+        }
+        codes.dedup();
+        assert_eq!(codes.len(), 15);
+        assert_eq!(ToneLayout::from_code(15), None);
+
+        assert_eq!("3, 8".parse::<ToneLayout>().unwrap(), layout);
+        for invalid in ["3", "3,x", "7,16"] {
+            assert!(invalid.parse::<ToneLayout>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_dual_band_layout() {
+        assert_eq!(ToneLayout::new(3, 16).unwrap().dual_band().unwrap(), ToneLayout::DUAL_BAND);
+        assert_eq!(ToneLayout::DUAL_BAND.single_band().code(), ToneLayout::DUAL_BAND.code());
+        assert_eq!(ToneLayout::DUAL_BAND.bandwidth_hz(), 1920.0);
+        assert!(ToneLayout::STANDARD.dual_band().is_err());
+        assert!(ToneLayout::TELEPHONY.dual_band().is_err());
+
+        // Jam one tone of every lower band louder than the data tones: only the
+        // upper-band copies keep the decisions right
+        let bytes = [0x12, 0x34, 0x56, 0x21, 0x43, 0x65];
+        let jammed = |layout: ToneLayout| {
+            let mut samples = FskModulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), layout).modulate(&bytes).unwrap();
+            let tone_level = 0.7 / (layout.tones() * if layout.is_dual_band() { 2 } else { 1 }) as f32;
+            for band in 0..layout.tones() {
+                let angular_freq = 2.0 * PI * bin_to_freq(band * FSK_BINS_PER_BAND + 15) / crate::SAMPLE_RATE as f32;
+                for (i, sample) in samples.iter_mut().enumerate() {
+                    *sample += 1.15 * tone_level * (angular_freq * i as f32).sin();
+                }
+            }
+            FskDemodulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), layout).demodulate(&samples).unwrap()
+        };
+        assert_eq!(jammed(ToneLayout::DUAL_BAND), bytes);
+        assert_ne!(jammed(ToneLayout::DUAL_BAND.single_band()), bytes);
+    }
+
+    #[test]
+    fn test_tone_layout_roundtrip() {
+        let data: Vec<u8> = (0..18).map(|i| (i * 97 + 5) as u8).collect();
+        let presets = [
+            ToneLayout::TELEPHONY,
+            ToneLayout::CODEC_ROBUST,
+            ToneLayout::DUAL_BAND,
+            ToneLayout::new(6, 8).unwrap().dual_band().unwrap(),
+        ];
+        for layout in [(2, 16), (3, 16), (3, 8), (2, 4), (6, 4)]
+            .map(|(tones, bins)| ToneLayout::new(tones, bins).unwrap())
+            .into_iter()
+            .chain(presets)
+        {
+            let mut modulator = FskModulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), layout);
+            let demodulator = FskDemodulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), layout);
+
+            let bytes = &data[..layout.alignment_bytes()];
+            let samples = modulator.modulate(bytes).unwrap();
+            assert_eq!(samples.len(), layout.symbols_for(bytes.len()) * FSK_SYMBOL_SAMPLES);
+            let (decoded, stats) = demodulator.demodulate_with_stats(&samples).unwrap();
+            assert_eq!(decoded, bytes, "{:?}", layout);
+            assert_eq!(stats.decisions, layout.symbols_for(bytes.len()) * layout.tones());
+        }
+
+        // Codec-robust tones sit three bins apart and use the whole 96-bin range
+        let layout = ToneLayout::CODEC_ROBUST;
+        assert_eq!((layout.tone_spacing_hz(), layout.bandwidth_hz()), (60.0, 1920.0));
+        assert!(!layout.announceable());
+        let symbol = FskModulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), layout).modulate(&[0xFF, 0xF0, 0x00]).unwrap();
+        let powers = FskDemodulator::new().bin_powers(&symbol[..FSK_SYMBOL_SAMPLES]);
+        let strongest = (0..FSK_NUM_BINS).max_by(|&a, &b| powers[a].total_cmp(&powers[b])).unwrap();
+        assert_eq!(strongest % 3, 0);
+
+        // 9 bits per symbol: only runs of 9 bytes fill whole symbols
+        let mut modulator = FskModulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), ToneLayout::new(3, 8).unwrap());
+        assert!(modulator.modulate(&[0u8; 8]).is_err());
+        assert!(modulator.modulate_amplitude_shaped(&[1, 2, 3]).is_err());
+
+        // Fewer tones put more level into each one
+        let two_tones = FskModulator::with_layout(Samples(FSK_SYMBOL_SAMPLES), ToneLayout::new(2, 16).unwrap())
+            .modulate(&[0x00])
+            .unwrap();
+        let six_tones = FskModulator::new().modulate(&[0x00, 0x00, 0x00]).unwrap();
+        let demodulator = FskDemodulator::new();
+        assert!(demodulator.bin_powers(&two_tones)[0] > 8.0 * demodulator.bin_powers(&six_tones)[0]);
+    }
+
     #[test]
     fn test_fsk_with_noise() {
         let mut modulator = FskModulator::new();
@@ -583,6 +1593,177 @@ mod tests {
         assert_eq!(decoded, bytes);
     }
 
+    #[test]
+    fn test_unreliable_bins_under_interference() {
+        // Band 2 sends 1500 Hz (value 3) in the first symbol and 1580 Hz in the second
+        let data = [0x12, 0x34, 0x56, 0x12, 0x74, 0x56];
+        let mut samples = FskModulator::new().modulate(&data).unwrap();
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample += 0.3 * (2.0 * PI * 1500.0 * i as f32 / crate::SAMPLE_RATE as f32).sin();
+        }
+
+        let mut demodulator = FskDemodulator::new();
+        assert_eq!(demodulator.demodulate(&samples).unwrap()[4], 0x34);
+
+        let hum = freq_to_bin(1500.0).unwrap();
+        demodulator.set_unreliable_bins(&[hum]).unwrap();
+        assert_eq!(demodulator.unreliable_bins(), [hum]);
+        let (decoded, _, erasures) = demodulator.demodulate_with_erasures(&samples).unwrap();
+        assert_eq!(decoded, data);
+        assert_eq!(erasures, [1]);
+
+        assert!(demodulator.set_unreliable_bins(&[FSK_NUM_BINS]).is_err());
+        demodulator.set_unreliable_bins(&[]).unwrap();
+        assert!(demodulator.unreliable_bins().is_empty());
+    }
+
+    /// Plain sines at each symbol's standard-layout tones, `offset_hz(symbol)` off frequency
+    /// Standard tones sent through a channel that shifts symbol `i` by
+    /// `offset_hz(i)` and scales a tone at `f` Hz by `gain_db(i, f)`
+    fn channel_tones(
+        tones: &[[u8; FSK_NIBBLES_PER_SYMBOL]],
+        offset_hz: impl Fn(usize) -> f32,
+        gain_db: impl Fn(usize, f32) -> f32,
+    ) -> Vec<f32> {
+        let mut phases = [0.0f32; FSK_NIBBLES_PER_SYMBOL];
+        let mut samples = Vec::with_capacity(tones.len() * FSK_SYMBOL_SAMPLES);
+        for (symbol, values) in tones.iter().enumerate() {
+            let freqs: Vec<f32> = (0..FSK_NIBBLES_PER_SYMBOL)
+                .map(|band| bin_to_freq(band * FSK_BINS_PER_BAND + values[band] as usize) + offset_hz(symbol))
+                .collect();
+            let amplitudes: Vec<f32> = freqs.iter().map(|&freq| 0.1 * 10f32.powf(gain_db(symbol, freq) / 20.0)).collect();
+            for _ in 0..FSK_SYMBOL_SAMPLES {
+                let mut sample = 0.0;
+                for (band, phase) in phases.iter_mut().enumerate() {
+                    *phase = (*phase + 2.0 * PI * freqs[band] / crate::SAMPLE_RATE as f32).rem_euclid(2.0 * PI);
+                    sample += amplitudes[band] * phase.sin();
+                }
+                samples.push(sample);
+            }
+        }
+        samples
+    }
+
+    fn offset_tones(tones: &[[u8; FSK_NIBBLES_PER_SYMBOL]], offset_hz: impl Fn(usize) -> f32) -> Vec<f32> {
+        channel_tones(tones, offset_hz, |_, _| 0.0)
+    }
+
+    type Tones = Vec<[u8; FSK_NIBBLES_PER_SYMBOL]>;
+
+    /// Random data with its absolute and differential standard tones
+    fn differential_test_tones(seed: u64, symbols: usize) -> (Vec<u8>, Tones, Tones) {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let data: Vec<u8> = (0..symbols * FSK_BYTES_PER_SYMBOL).map(|_| rng.gen()).collect();
+        let layout = ToneLayout::STANDARD;
+        let mut previous = [0u8; FSK_NIBBLES_PER_SYMBOL];
+        let (mut absolute, mut differential) = (Vec::new(), Vec::new());
+        for symbol in 0..symbols {
+            let values = layout.split(&data, symbol * layout.bits_per_symbol());
+            absolute.push(values);
+            let mut tones = values;
+            layout.steps_to_tones(&mut tones, &mut previous);
+            differential.push(tones);
+        }
+        (data, absolute, differential)
+    }
+
+    fn bit_error_rate(decoded: &[u8], data: &[u8]) -> f32 {
+        let errors: u32 = decoded.iter().zip(data).map(|(a, b)| (a ^ b).count_ones()).sum();
+        errors as f32 / (data.len() * 8) as f32
+    }
+
+    #[test]
+    fn test_differential_tones_survive_frequency_drift() {
+        let symbols = 60;
+        let (data, absolute, differential) = differential_test_tones(1684, symbols);
+        let layout = ToneLayout::STANDARD;
+
+        // The differential modulator sends those tones, and only a differential demodulator reads them back
+        let mut modulator = FskModulator::new();
+        modulator.set_differential(true);
+        let sent = modulator.modulate(&data).unwrap();
+        let plain = FskDemodulator::new();
+        let mut stepped = FskDemodulator::new();
+        stepped.set_differential(true);
+        let (mut tone_bytes, mut bit_pos) = (Vec::new(), 0);
+        for tones in &differential {
+            layout.join(tones, &mut tone_bytes, &mut bit_pos);
+        }
+        assert_eq!(plain.demodulate(&sent).unwrap(), tone_bytes);
+        assert_eq!(stepped.demodulate(&sent).unwrap(), data);
+
+        let ber = |decoded: Vec<u8>| bit_error_rate(&decoded, &data);
+        assert_eq!(ber(plain.demodulate(&offset_tones(&absolute, |_| 0.0)).unwrap()), 0.0);
+        assert_eq!(ber(stepped.demodulate(&offset_tones(&differential, |_| 0.0)).unwrap()), 0.0);
+
+        // A channel drifting from 16 to 24 Hz high moves every tone about one bin:
+        // absolute tones all decode wrong, steps only where a tone leaves its band
+        let drift = |symbol: usize| 16.0 + 8.0 * symbol as f32 / symbols as f32;
+        let absolute_ber = ber(plain.demodulate(&offset_tones(&absolute, drift)).unwrap());
+        let differential_ber = ber(stepped.demodulate(&offset_tones(&differential, drift)).unwrap());
+        assert!(absolute_ber > 0.3, "absolute BER {}", absolute_ber);
+        assert!(differential_ber < 0.2 && differential_ber < 0.5 * absolute_ber, "differential BER {}", differential_ber);
+    }
+
+    #[test]
+    fn test_differential_tones_survive_attenuation_and_tilt() {
+        let symbols = 60;
+        let (data, absolute, differential) = differential_test_tones(1685, symbols);
+        let plain = FskDemodulator::new();
+        let mut stepped = FskDemodulator::new();
+        stepped.set_differential(true);
+        let ber = |decoded: Vec<u8>| bit_error_rate(&decoded, &data);
+
+        // 30 dB down with a +-6 dB tilt across the tone band that reverses over
+        // the frame: the gain control and per-band peaks ignore it either way
+        let (low, high) = (bin_to_freq(0), bin_to_freq(FSK_NUM_BINS - 1));
+        let tilt = |symbol: usize, freq: f32| {
+            let slope = 1.0 - 2.0 * symbol as f32 / symbols as f32;
+            -30.0 + 6.0 * slope * (2.0 * (freq - low) / (high - low) - 1.0)
+        };
+        assert_eq!(ber(plain.demodulate(&channel_tones(&absolute, |_| 0.0, tilt)).unwrap()), 0.0);
+        assert_eq!(ber(stepped.demodulate(&channel_tones(&differential, |_| 0.0, tilt)).unwrap()), 0.0);
+
+        // Only the drift needs differential symbols, tilted or not
+        let drift = |symbol: usize| 16.0 + 8.0 * symbol as f32 / symbols as f32;
+        let absolute_ber = ber(plain.demodulate(&channel_tones(&absolute, drift, tilt)).unwrap());
+        let differential_ber = ber(stepped.demodulate(&channel_tones(&differential, drift, tilt)).unwrap());
+        assert!(differential_ber < 0.2 && differential_ber < 0.5 * absolute_ber, "differential BER {}", differential_ber);
+    }
+
+    #[test]
+    fn test_q15_bin_powers_match_float_decisions() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1650);
+        let mut modulator = FskModulator::new();
+        let demodulator = FskDemodulator::new();
+        let decide = |powers: Vec<f32>| -> Vec<usize> {
+            (0..FSK_NIBBLES_PER_SYMBOL)
+                .map(|band| {
+                    let bins = &powers[band * FSK_BINS_PER_BAND..(band + 1) * FSK_BINS_PER_BAND];
+                    (0..FSK_BINS_PER_BAND).max_by(|&a, &b| bins[a].total_cmp(&bins[b])).unwrap()
+                })
+                .collect()
+        };
+        for symbol in 0..200 {
+            let bytes: [u8; 3] = rng.gen();
+            let mut samples = modulator.modulate_symbol(&bytes).unwrap();
+            for sample in samples.iter_mut() {
+                *sample += rng.gen_range(-0.25..0.25);
+            }
+            let conditioned = demodulator.preprocess_symbol(&samples);
+            let float = demodulator.bin_powers_f32(&conditioned);
+            let fixed = demodulator.bin_powers_q15(&conditioned);
+            for (a, b) in fixed.iter().zip(&float) {
+                assert!((a - b).abs() <= 1e-2 * b.max(1.0), "symbol {}: {} vs {}", symbol, a, b);
+            }
+            assert_eq!(decide(fixed), decide(float), "symbol {}", symbol);
+        }
+    }
+
     #[test]
     fn test_spectrum_computation() {
         let demodulator = FskDemodulator::new();
@@ -592,7 +1773,7 @@ mod tests {
         let bytes = [0x00, 0x00, 0x00]; // All nibbles = 0, uses bins 0, 16, 32, 48, 64, 80
         let samples = modulator.modulate_symbol(&bytes).unwrap();
 
-        let spectrum = demodulator.compute_spectrum(&samples);
+        let (_, spectrum) = demodulator.compute_spectrum(&samples);
         assert_eq!(spectrum.len(), FSK_NUM_BINS);
 
         // The bins corresponding to the transmitted frequencies should have highest energy
@@ -672,6 +1853,50 @@ mod tests {
         assert!(demodulator.demodulate(&samples_odd).is_err());
     }
 
+    #[test]
+    fn test_band_gains_and_compensation() {
+        let bytes = [0x5A, 0xC3, 0x9F];
+        let gains = [1.0, 1.0, 1.0, 1.0, 2.0, 3.0];
+        let mut modulator = FskModulator::new();
+        let flat = modulator.modulate_symbol(&bytes).unwrap();
+        modulator.set_band_gains(Some(gains)).unwrap();
+        let shaped = modulator.modulate_symbol(&bytes).unwrap();
+
+        // Normalized by the summed gains: within the bound of flat symbols
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        let bound = 0.7 * modulator.taper.iter().fold(0.0f32, |peak, &w| peak.max(w));
+        assert!(peak(&flat) <= bound && peak(&shaped) <= bound, "{} {}", peak(&shaped), bound);
+
+        // Each tone's power relative to the flat symbol, since the Goertzel
+        // response differs from tone to tone
+        let demodulator = FskDemodulator::new();
+        let (flat_powers, powers) = (demodulator.bin_powers(&flat), demodulator.bin_powers(&shaped));
+        let tone_power = |band: usize| {
+            let nibble = (bytes[band / 2] >> if band.is_multiple_of(2) { 4 } else { 0 }) & 0x0F;
+            let bin = band * FSK_BINS_PER_BAND + nibble as usize;
+            powers[bin] / flat_powers[bin]
+        };
+        let ratio = tone_power(5) / tone_power(0);
+        assert!((ratio - 9.0).abs() < 1.0, "{}", ratio);
+        assert_eq!(demodulator.demodulate_symbol(&shaped).unwrap(), bytes);
+
+        let mut compensated = FskDemodulator::new();
+        compensated.set_band_compensation(Some(gains)).unwrap();
+        let ((raw, _), (flat_raw, _)) = (compensated.compute_spectrum(&shaped), demodulator.compute_spectrum(&flat));
+        let level = |band: usize| {
+            let peak = |spectrum: &[f32]| {
+                spectrum[band * FSK_BINS_PER_BAND..(band + 1) * FSK_BINS_PER_BAND].iter().fold(0.0f32, |peak, &p| peak.max(p))
+            };
+            peak(&raw) / peak(&flat_raw)
+        };
+        assert!((level(5) / level(0) - 1.0).abs() < 0.2, "{} vs {}", level(5), level(0));
+        assert_eq!(compensated.demodulate_symbol(&shaped).unwrap(), bytes);
+
+        assert!(modulator.set_band_gains(Some([1.0, 0.0, 1.0, 1.0, 1.0, 1.0])).is_err());
+        assert!(compensated.set_band_compensation(Some([f32::NAN; FSK_NIBBLES_PER_SYMBOL])).is_err());
+        assert_eq!(modulator.band_gains(), Some(gains));
+    }
+
     #[test]
     fn test_fsk_demodulator_gain_invariance() {
         let mut modulator = FskModulator::new();
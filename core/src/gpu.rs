@@ -0,0 +1,238 @@
+//! wgpu compute backend for sync correlation (feature `gpu`)
+//!
+//! Computes the full cross-correlation directly in the time domain, one output
+//! lag per GPU invocation: O(samples × template) work, against O(n log n) for
+//! the CPU FFT path, so whether it pays off depends on the GPU and the
+//! recording length (measure with the `correlation_bench` example). Install it
+//! with `set_correlation_backend(Some(Arc::new(WgpuCorrelation::new()?)))`.
+
+use crate::correlation::CorrelationBackend;
+use crate::error::{AudioModemError, Result};
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Workgroups per dispatch dimension (WebGPU limit)
+const MAX_WORKGROUPS_PER_DIM: u32 = 65535;
+
+const SHADER: &str = r#"
+struct Params {
+    samples_len: u32,
+    template_len: u32,
+    output_len: u32,
+    _pad: u32,
+}
+
+@group(0) @binding(0) var<storage, read> samples: array<f32>;
+@group(0) @binding(1) var<storage, read> tmpl: array<f32>;
+@group(0) @binding(2) var<storage, read_write> output: array<f32>;
+@group(0) @binding(3) var<uniform> params: Params;
+
+@compute @workgroup_size(256)
+fn main(@builtin(global_invocation_id) id: vec3<u32>, @builtin(num_workgroups) groups: vec3<u32>) {
+    let k = id.x + id.y * groups.x * 256u;
+    if (k >= params.output_len) {
+        return;
+    }
+    // output[k] = sum_j samples[k + j - (m - 1)] * tmpl[j], skipping out-of-range samples
+    let m = params.template_len;
+    var j_start = 0u;
+    if (k + 1u < m) {
+        j_start = m - 1u - k;
+    }
+    let j_end = min(m, params.samples_len + m - 1u - k);
+    var acc = 0.0;
+    for (var j = j_start; j < j_end; j = j + 1u) {
+        acc = acc + samples[k + j + 1u - m] * tmpl[j];
+    }
+    output[k] = acc;
+}
+"#;
+
+fn gpu_error(message: impl std::fmt::Display) -> AudioModemError {
+    AudioModemError::CorrelationBackend(format!("wgpu: {}", message))
+}
+
+fn f32_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// GPU correlation backend using a wgpu compute shader
+pub struct WgpuCorrelation {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    adapter_name: String,
+}
+
+impl WgpuCorrelation {
+    /// Open the default high-performance adapter; fails when no GPU is available
+    pub fn new() -> Result<Self> {
+        pollster::block_on(Self::new_async())
+    }
+
+    async fn new_async() -> Result<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .ok_or_else(|| gpu_error("no adapter available"))?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("transmitwave correlation"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .map_err(gpu_error)?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("correlate"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("correlate"),
+            layout: None,
+            module: &module,
+            entry_point: "main",
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Ok(Self {
+            adapter_name: adapter.get_info().name,
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    /// Name of the GPU in use
+    pub fn adapter_name(&self) -> &str {
+        &self.adapter_name
+    }
+}
+
+impl CorrelationBackend for WgpuCorrelation {
+    fn name(&self) -> &str {
+        "wgpu"
+    }
+
+    fn correlate(&self, samples: &[f32], template: &[f32]) -> Result<Vec<f32>> {
+        if samples.is_empty() || template.is_empty() {
+            return Err(AudioModemError::InvalidInputSize);
+        }
+        let output_len = samples.len() + template.len() - 1;
+        let output_size = (output_len * std::mem::size_of::<f32>()) as u64;
+        if output_size > self.device.limits().max_storage_buffer_binding_size as u64 {
+            return Err(gpu_error(format!("{} samples exceed the storage buffer limit", samples.len())));
+        }
+
+        let storage = |label, contents: &[u8]| {
+            self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+        };
+        let samples_buffer = storage("samples", &f32_bytes(samples));
+        let template_buffer = storage("template", &f32_bytes(template));
+        let params: Vec<u8> = [samples.len() as u32, template.len() as u32, output_len as u32, 0]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let params_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: &params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("output"),
+            size: output_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: output_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("correlate"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: samples_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: template_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: output_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: params_buffer.as_entire_binding() },
+            ],
+        });
+
+        // Spread the workgroups over x and y to stay within the per-dimension limit
+        let workgroups = (output_len as u32).div_ceil(WORKGROUP_SIZE);
+        let groups_x = workgroups.min(MAX_WORKGROUPS_PER_DIM);
+        let groups_y = workgroups.div_ceil(groups_x);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("correlate") });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("correlate"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output_buffer, 0, &readback_buffer, 0, output_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(gpu_error)?
+            .map_err(gpu_error)?;
+
+        let output = slice
+            .get_mapped_range()
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        readback_buffer.unmap();
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::correlation::FftCorrelation;
+
+    #[test]
+    #[ignore = "needs a GPU adapter"]
+    fn test_wgpu_matches_fft_backend() {
+        let gpu = WgpuCorrelation::new().expect("no GPU adapter");
+        let samples: Vec<f32> = (0..20_000).map(|i| ((i * 7919) % 1000) as f32 / 500.0 - 1.0).collect();
+        let template = crate::sync::generate_preamble(crate::PREAMBLE_SAMPLES, 1.0);
+
+        let expected = FftCorrelation.correlate(&samples, &template).unwrap();
+        let actual = gpu.correlate(&samples, &template).unwrap();
+        assert_eq!(actual.len(), expected.len());
+        for (a, b) in actual.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-2, "{} vs {}", a, b);
+        }
+    }
+}
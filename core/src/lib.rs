@@ -5,24 +5,112 @@
 
 pub mod error;
 pub mod fec;
+mod reed_solomon;
 pub mod framing;
 pub mod sync;
+pub mod melody;
+pub mod noise_floor;
+pub mod meter;
+pub mod correlation;
+pub mod fft;
+#[cfg(feature = "gpu")]
+pub mod gpu;
 pub mod resample;
+mod trig;
+mod rng;
 pub mod fsk;
+pub mod symbol_audit;
+pub mod frame_inspection;
+pub mod fixed_point;
 pub mod encoder_fsk;
 pub mod decoder_fsk;
+pub mod events;
+#[cfg(feature = "fountain")]
+pub mod fountain;
+#[cfg(feature = "fountain")]
+pub mod fountain_packet;
+#[cfg(feature = "fountain")]
+pub mod fountain_stream;
+#[cfg(feature = "fountain")]
+pub mod carousel;
+pub mod audio_io;
+pub mod transfer;
+pub mod stereo;
+pub mod streaming;
+pub mod duty_cycle;
+pub mod link;
+pub mod adaptation;
+pub mod trim;
+pub mod diagnostics;
+pub mod tx_audio;
+pub mod mixing;
+pub mod squelch;
+pub mod sidecar;
+pub mod calibration;
+pub mod presence;
+pub mod simulation;
+pub mod units;
+pub mod params;
+#[cfg(feature = "serde-payload")]
+pub mod payload;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
 
-pub use encoder_fsk::{EncoderFsk, FountainStream};
-pub use decoder_fsk::DecoderFsk;
+pub use encoder_fsk::{Airtime, AutoEncoding, EncoderFsk, EncoderPadding, FrameProfile, SyncGaps, TransmissionMode, AUTO_SINGLE_FRAME_MAX};
+pub use decoder_fsk::{DecoderFsk, DecodedMessage, DecodedSequence, DecodeQuality, FramingVariant};
+pub use events::DecodeEvent;
+#[cfg(feature = "fountain")]
+pub use fountain::{DegreeDistribution, FountainCode, FountainReceiver, LtDecoder, LtEncoder};
+#[cfg(feature = "fountain")]
+pub use fountain_packet::{parse_fountain_block, parse_fountain_params, FountainBlock, FountainObject, FountainParams};
+#[cfg(feature = "fountain")]
+pub use fountain_stream::{ChannelAccess, ChannelCheck, FountainConfig, FountainListener, FountainStream};
+#[cfg(feature = "fountain")]
+pub use carousel::{CarouselEncoder, CarouselDecoder, InterjectionPriority, MAX_INTERJECTION_SAMPLES};
 pub use error::{AudioModemError, Result};
-pub use fft_correlation::{Mode, fft_correlate_1d};
-pub use sync::{detect_preamble, detect_postamble, detect_fountain_preamble, DetectionThreshold};
-pub use resample::{resample_audio, stereo_to_mono};
-pub use fec::{FecEncoder, FecDecoder};
-pub use fsk::{FskModulator, FskDemodulator, FountainConfig};
-
-// Configuration constants
-pub const SAMPLE_RATE: usize = 16000;
+pub use melody::{Melody, MelodyBuilder, Note};
+pub use sync::{detect_preamble, detect_preamble_candidates, detect_postamble, detect_fountain_preamble, DetectionThreshold, NetworkKey, SyncCandidate};
+pub use resample::{resample_audio, resample_audio_with, stereo_to_mono, ResampleLength, ResampleOptions, ResampleQuality, StreamResampler};
+pub use audio_io::{modem_samples_to_wav_bytes, open_wav_stream, read_wav_file, read_wav_to_modem_samples, write_modem_samples_to_wav, write_wav_file, SampleReader, WavInfo, WavSampleFormat, WavStreamReader};
+pub use transfer::{default_transfer_id, TransferEncoder, TransferSession, DEFAULT_PART_DATA_SIZE, MAX_PART_DATA_SIZE};
+pub use stereo::{combine_stereo, to_stereo, StereoRedundancy};
+pub use framing::{
+    crc8, crc16, crc32, hexdump, hexdump_frame, padding_mismatches, parse_sequence_frame_num, sequence_frame_num,
+    ContentType, EncoderVersion, Frame, FrameDecoder, FrameEncoder, FRAME_PADDING_BYTE, MAX_SEQUENCE_FRAMES,
+    PROTOCOL_REVISION,
+};
+pub use streaming::{CarrierSense, StreamingDecoder};
+pub use noise_floor::NoiseFloorEstimator;
+pub use meter::{LevelMeter, MeterReading};
+pub use duty_cycle::{DutyCycle, DutyCycleStats, DutyCycledDecoder, ListenState};
+pub use link::{HalfDuplexLink, LinkConfig, LinkEvent, LinkOutput};
+pub use adaptation::LinkProfile;
+pub use trim::{find_transmission, trim_transmission, TransmissionSpan, TrimOptions};
+pub use diagnostics::{analyze_capture, capture_test_signal, CaptureDiagnostics, CaptureHint};
+pub use tx_audio::{validate_tx_audio, TxAudioIssue, TxAudioReport};
+pub use mixing::{mix_into, mix_into_with, MixOptions, MixReport};
+pub use squelch::Squelch;
+pub use sidecar::TransmissionSidecar;
+pub use calibration::{calibrate_from_sweep, calibration_sweep, PreEmphasis};
+pub use presence::{signal_presence, SignalPresence};
+pub use simulation::{simulate_acoustic_path, simulate_acoustic_path_with, AcousticPath, RoomPreset};
+pub use rng::SplitMix64;
+pub use fec::{FecEncoder, FecDecoder, FecMode};
+pub use fsk::{FskModulator, FskDemodulator, ToneLayout};
+pub use symbol_audit::{SymbolAuditLog, SymbolDecision};
+pub use frame_inspection::{FrameInspection, RsBlockReport};
+pub use correlation::{set_correlation_backend, CorrelationBackend, FftBackendCorrelation, FftCorrelation};
+pub use fft::{fft_correlate, Complex32, FftBackend, RustFft};
+pub use fixed_point::{goertzel_power_q15, quantize_q15, Q15Correlation};
+pub use units::{Millis, SampleRate, Samples};
+pub use params::ModemParams;
+#[cfg(feature = "serde-payload")]
+pub use payload::{from_cbor, to_cbor};
+#[cfg(feature = "gpu")]
+pub use gpu::WgpuCorrelation;
+
+// Configuration constants (plain-integer forms of the typed ones in `units`)
+pub const SAMPLE_RATE: usize = units::MODEM_RATE.hz() as usize;
 pub const SYMBOL_DURATION_MS: usize = 100;
 pub const SAMPLES_PER_SYMBOL: usize = (SAMPLE_RATE * SYMBOL_DURATION_MS) / 1000; // 1600
 
@@ -34,15 +122,21 @@ pub const FSK_MAX_FREQUENCY: f32 = 2700.0; // Hz (max frequency)
 pub const NUM_FSK_TONES: usize = 6; // 6 simultaneous frequencies per symbol (3 bytes)
 
 // Preamble/Postamble sync signal
-pub const SYNC_DURATION_MS: usize = 250; // Preamble/postamble duration (1/4 second)
+pub const SYNC_DURATION_MS: usize = units::SYNC_DURATION.get() as usize; // Preamble/postamble duration (1/4 second)
 pub const PREAMBLE_DURATION_MS: usize = SYNC_DURATION_MS;
-pub const PREAMBLE_SAMPLES: usize = (SAMPLE_RATE * SYNC_DURATION_MS) / 1000; // 4000
+pub const PREAMBLE_SAMPLES: usize = units::MODEM_RATE.samples(units::SYNC_DURATION).get(); // 4000
 pub const POSTAMBLE_DURATION_MS: usize = SYNC_DURATION_MS;
-pub const POSTAMBLE_SAMPLES: usize = (SAMPLE_RATE * SYNC_DURATION_MS) / 1000; // 4000
+pub const POSTAMBLE_SAMPLES: usize = PREAMBLE_SAMPLES; // 4000
 
 // Brief silence gaps for better frame detection (1/8 second each)
-pub const SYNC_SILENCE_MS: usize = 125; // Silence before/after sync signals
-pub const SYNC_SILENCE_SAMPLES: usize = (SAMPLE_RATE * SYNC_SILENCE_MS) / 1000; // 2000
+pub const SYNC_SILENCE_MS: usize = units::SYNC_SILENCE.get() as usize; // Silence before/after sync signals
+pub const SYNC_SILENCE_SAMPLES: usize = units::MODEM_RATE.samples(units::SYNC_SILENCE).get(); // 2000
+pub const MAX_SYNC_GAP_SAMPLES: usize = 2 * SYNC_SILENCE_SAMPLES; // Longest gap EncoderFsk::set_sync_gaps allows
+
+// Optional silence gaps between groups of data symbols (see EncoderFsk::set_symbol_gaps)
+pub const SYMBOL_GAP_SAMPLES: usize = units::SYMBOL_GAP.get(); // 50 ms of silence per gap
+pub const MIN_SYMBOL_GAP_INTERVAL: usize = 4; // Fewest symbols between two gaps
+pub const MAX_SYMBOL_GAP_INTERVAL: usize = 63; // Most symbols between two gaps (6 bits in the frame)
 
 // FEC configuration
 // Reed-Solomon (255, 223) - can correct up to 16 byte errors per 255-byte block
@@ -59,8 +153,26 @@ pub const FOUNTAIN_BLOCK_SIZE: usize = 64; // Symbol size in bytes
 
 // FSK encoding constants
 pub const FSK_BYTES_PER_SYMBOL: usize = 3; // Bytes encoded per FSK symbol
-pub const FSK_SYMBOL_SAMPLES: usize = 3072; // Samples per FSK symbol (at 48kHz)
+pub const FSK_SYMBOL_SAMPLES: usize = units::FSK_SYMBOL.get(); // Samples per FSK symbol (192 ms at 16 kHz)
 pub const PACKET_OVERHEAD_BYTES: usize = 14; // Packet framing overhead
 
+// Short-frame profile (low latency, small payloads)
+pub const SHORT_SYNC_SAMPLES: usize = 1280; // Short preamble/postamble (80 ms)
+pub const SHORT_SYNC_SILENCE_SAMPLES: usize = 400; // Gaps around short sync signals (25 ms)
+pub const SHORT_SYMBOL_SAMPLES: usize = 800; // 50 ms symbols, still orthogonal at 20 Hz spacing
+pub const SHORT_MAX_PAYLOAD_SIZE: usize = 64; // Maximum short-frame payload in bytes
+
+// Telephony profile (phone calls: 300-3400 Hz voice band, lossy speech codecs)
+pub const TELEPHONY_SYNC_SAMPLES: usize = 8000; // Telephony preamble/postamble (500 ms)
+pub const TELEPHONY_SYMBOL_SAMPLES: usize = 4800; // 300 ms symbols, long enough to outlast codec frames
+
+// Codec-robust profile (Bluetooth SBC/AAC, Opus and other perceptual codecs)
+pub const CODEC_ROBUST_SYNC_SAMPLES: usize = 8000; // Codec-robust preamble/postamble (500 ms)
+pub const CODEC_ROBUST_SYMBOL_SAMPLES: usize = 4800; // 300 ms symbols
+
+// Narrowband profile (radio and intercom links sampled at 8 kHz)
+pub const NARROWBAND_SYNC_SAMPLES: usize = 4000; // Narrowband preamble/postamble (250 ms)
+pub const NARROWBAND_SYMBOL_SAMPLES: usize = 2400; // 150 ms symbols, a whole 1200 samples at 8 kHz
+
 // Audio buffer configuration
 pub const MAX_BUFFER_SAMPLES: usize = 80000; // Maximum audio buffer size in samples
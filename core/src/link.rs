@@ -0,0 +1,278 @@
+//! Half-duplex message link with carrier sense and stop-and-wait ARQ
+//!
+//! Drives push-to-talk style exchanges between stations sharing one acoustic
+//! channel. The link is clocked by the audio it is fed, so it behaves the same
+//! on a live sound card and in a simulation.
+
+use crate::encoder_fsk::EncoderFsk;
+use crate::error::{AudioModemError, Result};
+use crate::rng::SplitMix64;
+use crate::streaming::{CarrierSense, StreamingDecoder};
+use crate::units::Millis;
+use crate::{MAX_PAYLOAD_SIZE, SAMPLE_RATE};
+use std::collections::{HashMap, VecDeque};
+
+/// First byte of every link packet
+const LINK_MAGIC: u8 = 0xC7;
+
+/// Link header: magic (1) + kind (1) + from (1) + to (1) + sequence (1)
+pub const LINK_HEADER_SIZE: usize = 5;
+
+/// Largest message a single link packet carries
+pub const MAX_LINK_PAYLOAD: usize = MAX_PAYLOAD_SIZE - LINK_HEADER_SIZE;
+
+/// Destination used by data packets (any listening station)
+const BROADCAST: u8 = 0xFF;
+
+const KIND_DATA: u8 = 0;
+const KIND_ACK: u8 = 1;
+
+/// Timing and retry parameters of a [`HalfDuplexLink`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkConfig {
+    /// Transmissions of a message before giving up
+    pub max_attempts: u32,
+    /// How long to wait for an ACK after our transmission ends
+    pub ack_timeout_ms: u32,
+    /// Channel must be clear this long before sending data
+    pub clear_ms: u32,
+    /// Upper bound of the random extra wait before sending data
+    pub backoff_ms: u32,
+    /// Input is ignored this long after our transmission ends (room echo)
+    pub turnaround_ms: u32,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            ack_timeout_ms: 5000,
+            clear_ms: 500,
+            backoff_ms: 1000,
+            turnaround_ms: 300,
+        }
+    }
+}
+
+/// Something that happened on the link during [`HalfDuplexLink::process`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// A new message from another station (duplicates are filtered)
+    Received { from: u8, payload: Vec<u8> },
+    /// Our message was acknowledged
+    Delivered { seq: u8 },
+    /// No ACK arrived; the message is being sent again
+    Retrying { seq: u8, attempt: u32 },
+    /// No ACK after `max_attempts` transmissions
+    Failed { seq: u8 },
+}
+
+/// Result of feeding one chunk of audio to the link
+#[derive(Debug, Default)]
+pub struct LinkOutput {
+    /// Audio to play now, if the link decided to transmit
+    pub transmit: Option<Vec<f32>>,
+    pub events: Vec<LinkEvent>,
+}
+
+struct Pending {
+    seq: u8,
+    payload: Vec<u8>,
+    attempts: u32,
+    /// Clock time after which the ACK is considered lost (None until sent)
+    deadline: Option<usize>,
+}
+
+/// Half-duplex station: queues messages, waits for a clear channel, sends,
+/// and retransmits until the peer acknowledges
+///
+/// Packet format (inside a regular frame): [0xC7][kind][from][to][seq][payload]
+pub struct HalfDuplexLink {
+    station: u8,
+    config: LinkConfig,
+    encoder: EncoderFsk,
+    decoder: StreamingDecoder,
+    sense: CarrierSense,
+    /// Samples processed so far
+    clock: usize,
+    /// End of our own transmission plus turnaround
+    tx_until: usize,
+    jitter: usize,
+    rng: SplitMix64,
+    next_seq: u8,
+    queue: VecDeque<(u8, Vec<u8>)>,
+    pending: Option<Pending>,
+    acks: VecDeque<(u8, u8)>,
+    last_seen: HashMap<u8, u8>,
+}
+
+fn ms_to_samples(ms: u32) -> usize {
+    ms as usize * SAMPLE_RATE / 1000
+}
+
+impl HalfDuplexLink {
+    pub fn new(station: u8, config: LinkConfig) -> Result<Self> {
+        if station == BROADCAST {
+            return Err(AudioModemError::InvalidConfig("station 0xFF is reserved".to_string()));
+        }
+        let mut link = Self {
+            station,
+            config,
+            encoder: EncoderFsk::new()?,
+            decoder: StreamingDecoder::new()?,
+            sense: CarrierSense::new(Millis(config.turnaround_ms)),
+            clock: 0,
+            tx_until: 0,
+            jitter: 0,
+            rng: SplitMix64::new(station as u64),
+            next_seq: 0,
+            queue: VecDeque::new(),
+            pending: None,
+            acks: VecDeque::new(),
+            last_seen: HashMap::new(),
+        };
+        link.reroll_jitter();
+        Ok(link)
+    }
+
+    pub fn station(&self) -> u8 {
+        self.station
+    }
+
+    /// Queue a message. Returns the sequence number reported in its events.
+    pub fn send(&mut self, payload: &[u8]) -> Result<u8> {
+        if payload.len() > MAX_LINK_PAYLOAD {
+            return Err(AudioModemError::InvalidInputSize);
+        }
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.queue.push_back((seq, payload.to_vec()));
+        Ok(seq)
+    }
+
+    /// True while our own audio is playing (or echoing)
+    pub fn is_transmitting(&self) -> bool {
+        self.clock < self.tx_until
+    }
+
+    /// Messages queued or awaiting an ACK
+    pub fn outstanding(&self) -> usize {
+        self.queue.len() + usize::from(self.pending.is_some())
+    }
+
+    pub fn carrier_sense(&self) -> &CarrierSense {
+        &self.sense
+    }
+
+    /// Feed captured audio; returns audio to play (if it is our turn) and link events
+    ///
+    /// Returned audio must be played right away: the link assumes it occupies
+    /// the channel for its length in samples, and ignores input meanwhile.
+    pub fn process(&mut self, input: &[f32]) -> Result<LinkOutput> {
+        let mut output = LinkOutput::default();
+        let listening = self.clock >= self.tx_until;
+        self.clock += input.len();
+
+        if listening {
+            self.sense.push(input);
+            if self.sense.is_busy() {
+                self.reroll_jitter();
+            }
+            for message in self.decoder.push(input) {
+                self.handle_packet(&message.payload, &mut output.events);
+            }
+        }
+
+        if let Some(pending) = &mut self.pending {
+            if pending.deadline.is_some_and(|deadline| self.clock >= deadline) {
+                pending.deadline = None;
+                if pending.attempts >= self.config.max_attempts {
+                    output.events.push(LinkEvent::Failed { seq: pending.seq });
+                    self.pending = None;
+                } else {
+                    output.events.push(LinkEvent::Retrying { seq: pending.seq, attempt: pending.attempts + 1 });
+                }
+            }
+        }
+
+        output.transmit = self.next_transmission()?;
+        Ok(output)
+    }
+
+    fn handle_packet(&mut self, packet: &[u8], events: &mut Vec<LinkEvent>) {
+        if packet.len() < LINK_HEADER_SIZE || packet[0] != LINK_MAGIC {
+            return;
+        }
+        let (kind, from, to, seq) = (packet[1], packet[2], packet[3], packet[4]);
+        if from == self.station {
+            return;
+        }
+        match kind {
+            KIND_DATA => {
+                if self.last_seen.insert(from, seq) != Some(seq) {
+                    events.push(LinkEvent::Received { from, payload: packet[LINK_HEADER_SIZE..].to_vec() });
+                }
+                // ACK duplicates too: the previous ACK was evidently lost
+                if !self.acks.contains(&(from, seq)) {
+                    self.acks.push_back((from, seq));
+                }
+            }
+            KIND_ACK if to == self.station && self.pending.as_ref().is_some_and(|p| p.seq == seq) => {
+                self.pending = None;
+                events.push(LinkEvent::Delivered { seq });
+            }
+            _ => {}
+        }
+    }
+
+    fn next_transmission(&mut self) -> Result<Option<Vec<f32>>> {
+        if self.clock < self.tx_until || self.sense.is_busy() {
+            return Ok(None);
+        }
+        // Time spent listening since our own transmission, and since the channel went quiet
+        let idle = (self.clock - self.tx_until).min(self.sense.clear_samples());
+
+        // ACKs go first and skip the backoff, so they win the channel from new data
+        if idle >= ms_to_samples(self.config.turnaround_ms) {
+            if let Some((to, seq)) = self.acks.pop_front() {
+                return self.transmit(KIND_ACK, to, seq, &[]).map(Some);
+            }
+        }
+
+        if idle < ms_to_samples(self.config.clear_ms) + self.jitter {
+            return Ok(None);
+        }
+        if self.pending.is_none() {
+            if let Some((seq, payload)) = self.queue.pop_front() {
+                self.pending = Some(Pending { seq, payload, attempts: 0, deadline: None });
+            }
+        }
+        let Some(pending) = self.pending.as_ref().filter(|p| p.deadline.is_none()) else {
+            return Ok(None);
+        };
+        let (seq, payload) = (pending.seq, pending.payload.clone());
+        let audio = self.transmit(KIND_DATA, BROADCAST, seq, &payload)?;
+
+        let deadline = self.tx_until + ms_to_samples(self.config.ack_timeout_ms);
+        if let Some(pending) = &mut self.pending {
+            pending.attempts += 1;
+            pending.deadline = Some(deadline);
+        }
+        self.reroll_jitter();
+        Ok(Some(audio))
+    }
+
+    fn transmit(&mut self, kind: u8, to: u8, seq: u8, payload: &[u8]) -> Result<Vec<f32>> {
+        let mut packet = vec![LINK_MAGIC, kind, self.station, to, seq];
+        packet.extend_from_slice(payload);
+        let audio = self.encoder.encode(&packet)?;
+        self.tx_until = self.clock + audio.len() + ms_to_samples(self.config.turnaround_ms);
+        self.decoder.reset();
+        Ok(audio)
+    }
+
+    fn reroll_jitter(&mut self) {
+        let max = ms_to_samples(self.config.backoff_ms).max(1);
+        self.jitter = self.rng.below(max as u64) as usize;
+    }
+}
@@ -0,0 +1,226 @@
+//! Custom fountain preamble melodies
+//!
+//! Fountain blocks open with the three-note whistle; a broadcaster can replace
+//! it with a motif of its own, e.g. a brand jingle. [`Melody::builder`] takes
+//! the notes and checks that the result still works as a sync signal: its
+//! autocorrelation must have a single clear peak, so the decoder cannot lock a
+//! note early or late, and it must not resemble the frame preamble, the
+//! postamble or the whistle itself. The encoder plays and the decoder correlates against the same
+//! [`Melody::generate`] output (see `EncoderFsk::set_fountain_melody`).
+
+use crate::error::{AudioModemError, Result};
+use crate::fft::{fft_correlate, RustFft};
+use crate::sync::{
+    generate_fountain_preamble, generate_keyed_fountain_preamble, generate_postamble_signal, generate_preamble,
+    generate_tone, NetworkKey,
+};
+use crate::units::{self, Millis, Samples};
+use crate::PREAMBLE_SAMPLES;
+
+/// Most notes in a melody
+pub const MELODY_MAX_NOTES: usize = 8;
+
+/// Shortest note; shorter ones are mostly attack and decay
+pub const MELODY_MIN_NOTE: Millis = Millis(20);
+
+/// Lowest and highest note frequencies (Hz), the range small speakers and
+/// microphones reproduce well
+pub const MELODY_MIN_FREQUENCY: f32 = 300.0;
+pub const MELODY_MAX_FREQUENCY: f32 = 4000.0;
+
+/// Highest normalized correlation a melody may reach against itself shifted
+/// by at least its shortest note, or against the frame sync signals or the whistle
+pub const MELODY_MAX_SIMILARITY: f32 = 0.5;
+
+/// One note of a [`Melody`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    pub frequency: f32,
+    pub duration: Millis,
+}
+
+/// Validated fountain preamble motif, as long as the standard preamble
+#[derive(Debug, Clone, PartialEq)]
+pub struct Melody {
+    notes: Vec<Note>,
+}
+
+/// Notes of a melody under construction (see [`Melody::builder`])
+#[derive(Debug, Clone, Default)]
+pub struct MelodyBuilder {
+    notes: Vec<Note>,
+}
+
+impl MelodyBuilder {
+    /// Append a note of `frequency` Hz
+    pub fn note(mut self, frequency: f32, duration: Millis) -> Self {
+        self.notes.push(Note { frequency, duration });
+        self
+    }
+
+    /// Check the notes and the detectability of the melody
+    ///
+    /// The durations must add up to the preamble length (250 ms).
+    pub fn build(self) -> Result<Melody> {
+        let invalid = |reason: String| Err(AudioModemError::InvalidConfig(format!("melody: {}", reason)));
+        if !(2..=MELODY_MAX_NOTES).contains(&self.notes.len()) {
+            return invalid(format!("{} notes, expected 2 to {}", self.notes.len(), MELODY_MAX_NOTES));
+        }
+        for note in &self.notes {
+            if !(MELODY_MIN_FREQUENCY..=MELODY_MAX_FREQUENCY).contains(&note.frequency) {
+                return invalid(format!(
+                    "{} Hz is outside {}-{} Hz",
+                    note.frequency, MELODY_MIN_FREQUENCY, MELODY_MAX_FREQUENCY
+                ));
+            }
+            if note.duration < MELODY_MIN_NOTE {
+                return invalid(format!("{} ms note is shorter than {} ms", note.duration.get(), MELODY_MIN_NOTE.get()));
+            }
+        }
+        let total: u32 = self.notes.iter().map(|note| note.duration.get()).sum();
+        if total != units::SYNC_DURATION.get() {
+            return invalid(format!("notes last {} ms, expected {} ms", total, units::SYNC_DURATION.get()));
+        }
+
+        let melody = Melody { notes: self.notes };
+        let sidelobe = melody.peak_sidelobe();
+        if sidelobe > MELODY_MAX_SIMILARITY {
+            return invalid(format!(
+                "shifted copies correlate at {:.2} (max {}); use more distinct notes",
+                sidelobe, MELODY_MAX_SIMILARITY
+            ));
+        }
+        let similarity = melody.sync_similarity();
+        if similarity > MELODY_MAX_SIMILARITY {
+            return invalid(format!(
+                "correlates at {:.2} with the frame sync signals (max {})",
+                similarity, MELODY_MAX_SIMILARITY
+            ));
+        }
+        let similarity = melody.whistle_similarity();
+        if similarity > MELODY_MAX_SIMILARITY {
+            return invalid(format!(
+                "correlates at {:.2} with the default three-note whistle (max {})",
+                similarity, MELODY_MAX_SIMILARITY
+            ));
+        }
+        Ok(melody)
+    }
+}
+
+/// Peak of `|correlation|` between `a` and `b` at lags of at least
+/// `min_lag`, normalized by their energies
+fn peak_correlation(a: &[f32], b: &[f32], min_lag: usize) -> f32 {
+    let energy = |x: &[f32]| x.iter().map(|v| v * v).sum::<f32>();
+    let norm = (energy(a) * energy(b)).sqrt();
+    if norm <= 0.0 {
+        return 0.0;
+    }
+    let Ok(scores) = fft_correlate(&RustFft, a, b) else {
+        return 0.0;
+    };
+    // Index b.len() - 1 is zero lag
+    scores
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| i.abs_diff(b.len() - 1) >= min_lag)
+        .fold(0.0f32, |peak, (_, score)| peak.max(score.abs() / norm))
+}
+
+impl Melody {
+    pub fn builder() -> MelodyBuilder {
+        MelodyBuilder::default()
+    }
+
+    /// Melody of `(frequency Hz, duration ms)` notes, checked like [`MelodyBuilder::build`]
+    pub fn from_notes(notes: impl IntoIterator<Item = (f32, u32)>) -> Result<Melody> {
+        notes
+            .into_iter()
+            .fold(Melody::builder(), |builder, (frequency, ms)| builder.note(frequency, Millis(ms)))
+            .build()
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    /// The melody over `duration`, each note stretched in proportion
+    pub fn generate(&self, duration: Samples, amplitude: f32) -> Vec<f32> {
+        let duration_samples = duration.get();
+        let total_ms: u32 = self.notes.iter().map(|note| note.duration.get()).sum();
+        let mut samples = Vec::with_capacity(duration_samples);
+        let mut elapsed_ms = 0;
+        for note in &self.notes {
+            elapsed_ms += note.duration.get();
+            let end = duration_samples * elapsed_ms as usize / total_ms as usize;
+            samples.extend(generate_tone(note.frequency, end - samples.len(), amplitude));
+        }
+        samples
+    }
+
+    /// Strongest correlation of the melody with itself shifted by at least its
+    /// shortest note; a high value means the decoder could lock a note off
+    pub fn peak_sidelobe(&self) -> f32 {
+        let template = self.generate(Samples(PREAMBLE_SAMPLES), 1.0);
+        let shortest = self.notes.iter().map(|note| note.duration).min().unwrap_or_default();
+        peak_correlation(&template, &template, units::MODEM_RATE.samples(shortest).get())
+    }
+
+    /// Strongest correlation with the frame preamble or postamble at any lag
+    pub fn sync_similarity(&self) -> f32 {
+        let template = self.generate(Samples(PREAMBLE_SAMPLES), 1.0);
+        [generate_preamble(PREAMBLE_SAMPLES, 1.0), generate_postamble_signal(PREAMBLE_SAMPLES, 1.0)]
+            .iter()
+            .map(|sync| peak_correlation(&template, sync, 0))
+            .fold(0.0, f32::max)
+    }
+
+    /// Strongest correlation with the three-note whistle at any lag; a high
+    /// value lets listeners of other melodies (or none) take the blocks
+    pub fn whistle_similarity(&self) -> f32 {
+        let template = self.generate(Samples(PREAMBLE_SAMPLES), 1.0);
+        peak_correlation(&template, &generate_fountain_preamble(PREAMBLE_SAMPLES, 1.0), 0)
+    }
+}
+
+/// Fountain preamble of `key`, else of `melody`, else the three-note whistle
+pub(crate) fn fountain_preamble(amplitude: f32, key: Option<NetworkKey>, melody: Option<&Melody>) -> Vec<f32> {
+    match (key, melody) {
+        (None, Some(melody)) => melody.generate(Samples(PREAMBLE_SAMPLES), amplitude),
+        _ => generate_keyed_fountain_preamble(Samples(PREAMBLE_SAMPLES), amplitude, key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whistle_and_repeats_fail() {
+        // The whistle's own notes are a clean sync signal, but not a distinct melody
+        let notes = [(800.0, 83), (1200.0, 83), (1600.0, 84)];
+        let whistle = Melody { notes: notes.iter().map(|&(frequency, ms)| Note { frequency, duration: Millis(ms) }).collect() };
+        assert_eq!(whistle.generate(Samples(PREAMBLE_SAMPLES), 0.5).len(), PREAMBLE_SAMPLES);
+        assert!(whistle.peak_sidelobe() < MELODY_MAX_SIMILARITY);
+        assert!(whistle.sync_similarity() < MELODY_MAX_SIMILARITY);
+        assert!(matches!(Melody::from_notes(notes), Err(AudioModemError::InvalidConfig(reason)) if reason.contains("whistle")));
+
+        // One note repeated lines up with itself a note later
+        let repeated = Melody::builder().note(1000.0, Millis(83)).note(1000.0, Millis(83)).note(1000.0, Millis(84)).build();
+        assert!(matches!(repeated, Err(AudioModemError::InvalidConfig(reason)) if reason.contains("correlate")));
+    }
+
+    #[test]
+    fn test_melody_limits() {
+        let build = |notes: &[(f32, u32)]| {
+            notes.iter().fold(Melody::builder(), |builder, &(frequency, ms)| builder.note(frequency, Millis(ms))).build()
+        };
+        assert!(build(&[(800.0, 250)]).is_err());
+        assert!(build(&[(800.0, 100), (1200.0, 100)]).is_err());
+        assert!(build(&[(100.0, 125), (1200.0, 125)]).is_err());
+        assert!(build(&[(800.0, 10), (1200.0, 240)]).is_err());
+        let melody = build(&[(659.0, 60), (988.0, 60), (1319.0, 130)]).unwrap();
+        assert!(melody.whistle_similarity() < MELODY_MAX_SIMILARITY);
+        assert_eq!(Melody::from_notes([(659.0, 60), (988.0, 60), (1319.0, 130)]).unwrap(), melody);
+    }
+}
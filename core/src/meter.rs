@@ -0,0 +1,204 @@
+//! Level-meter readings for live input UIs
+//!
+//! A [`LevelMeter`] takes audio in chunks and keeps peak-held values of the
+//! sync correlation, the RMS level and the level of each FSK tone band, cheap
+//! enough to read at display rate. Every sample is processed once: the
+//! correlation only scores template positions that end in the new audio, and
+//! levels are measured per `METER_BLOCK_SAMPLES` block.
+
+use crate::fsk::{FskDemodulator, FSK_BINS_PER_BAND, FSK_NIBBLES_PER_SYMBOL};
+use crate::sync::{condition_for_sync, sync_scores};
+use crate::SAMPLE_RATE;
+
+/// Samples per level measurement (32 ms, about 30 readings per second)
+pub const METER_BLOCK_SAMPLES: usize = 512;
+
+/// Held peaks stay put this long (200 ms) before they start to fall
+const PEAK_HOLD_SAMPLES: usize = SAMPLE_RATE / 5;
+
+/// Held peaks fall to this fraction per second after the hold
+const PEAK_RELEASE_PER_SECOND: f32 = 0.25;
+
+/// Values in [`MeterReading::to_array`]: correlation, RMS, one level per band
+pub const METER_VALUES: usize = 2 + FSK_NIBBLES_PER_SYMBOL;
+
+/// Peak-held meter values (all linear, full scale 1.0)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeterReading {
+    /// Best normalized correlation with the sync template (0-1)
+    pub correlation: f32,
+    pub rms: f32,
+    /// Amplitude of the strongest tone in each FSK band
+    pub band_levels: [f32; FSK_NIBBLES_PER_SYMBOL],
+}
+
+impl MeterReading {
+    /// `[correlation, rms, band 0, ..., band 5]`
+    pub fn to_array(&self) -> [f32; METER_VALUES] {
+        let mut values = [0.0; METER_VALUES];
+        values[0] = self.correlation;
+        values[1] = self.rms;
+        values[2..].copy_from_slice(&self.band_levels);
+        values
+    }
+
+    fn from_array(values: [f32; METER_VALUES]) -> Self {
+        let mut band_levels = [0.0; FSK_NIBBLES_PER_SYMBOL];
+        band_levels.copy_from_slice(&values[2..]);
+        Self { correlation: values[0], rms: values[1], band_levels }
+    }
+}
+
+/// Incremental level meter with peak hold
+pub struct LevelMeter {
+    template: Option<Vec<f32>>,
+    /// Last `template.len() - 1` samples, context for correlating the next chunk
+    history: Vec<f32>,
+    block: Vec<f32>,
+    demodulator: FskDemodulator,
+    held: [f32; METER_VALUES],
+    /// Samples since each held value was last raised
+    since_peak: [usize; METER_VALUES],
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LevelMeter {
+    /// Meter of the levels only (correlation stays 0)
+    pub fn new() -> Self {
+        Self {
+            template: None,
+            history: Vec::new(),
+            block: Vec::with_capacity(METER_BLOCK_SAMPLES),
+            demodulator: FskDemodulator::new(),
+            held: [0.0; METER_VALUES],
+            since_peak: [0; METER_VALUES],
+        }
+    }
+
+    /// Meter that also tracks the correlation with `template`, e.g. the preamble
+    pub fn with_template(template: Vec<f32>) -> Self {
+        Self { template: Some(template).filter(|template| !template.is_empty()), ..Self::new() }
+    }
+
+    /// Feed audio at SAMPLE_RATE
+    pub fn push(&mut self, samples: &[f32]) {
+        let mut best_correlation = None;
+        if let Some(template) = &self.template {
+            let mut window = std::mem::take(&mut self.history);
+            window.extend_from_slice(samples);
+            if window.len() >= template.len() {
+                best_correlation = sync_scores(&condition_for_sync(&window), template, "meter")
+                    .map(|scores| scores.iter().copied().fold(0.0, f32::max));
+            }
+            window.drain(..window.len().saturating_sub(template.len() - 1));
+            self.history = window;
+        }
+        if let Some(best) = best_correlation {
+            self.raise(0, best);
+        }
+
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let take = (METER_BLOCK_SAMPLES - self.block.len()).min(rest.len());
+            self.block.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.block.len() == METER_BLOCK_SAMPLES {
+                self.measure_block();
+                self.block.clear();
+                self.release(METER_BLOCK_SAMPLES);
+            }
+        }
+    }
+
+    /// Current peak-held values
+    pub fn reading(&self) -> MeterReading {
+        MeterReading::from_array(self.held)
+    }
+
+    /// Forget the held peaks and the buffered audio
+    pub fn reset(&mut self) {
+        self.history.clear();
+        self.block.clear();
+        self.held = [0.0; METER_VALUES];
+        self.since_peak = [0; METER_VALUES];
+    }
+
+    /// Hold `value` at `index` of [`MeterReading::to_array`] if it beats the held one
+    fn raise(&mut self, index: usize, value: f32) {
+        if value >= self.held[index] {
+            self.held[index] = value;
+            self.since_peak[index] = 0;
+        }
+    }
+
+    /// Let `samples` of time pass: values past their hold fall at the release rate
+    fn release(&mut self, samples: usize) {
+        for (value, since_peak) in self.held.iter_mut().zip(self.since_peak.iter_mut()) {
+            let released = (*since_peak + samples).saturating_sub(PEAK_HOLD_SAMPLES).min(samples);
+            *since_peak += samples;
+            *value *= PEAK_RELEASE_PER_SECOND.powf(released as f32 / SAMPLE_RATE as f32);
+        }
+    }
+
+    fn measure_block(&mut self) {
+        let n = self.block.len() as f32;
+        let rms = (self.block.iter().map(|x| x * x).sum::<f32>() / n).sqrt();
+        self.raise(1, rms);
+
+        let powers = self.demodulator.bin_powers(&self.block);
+        for (band_idx, band) in powers.chunks(FSK_BINS_PER_BAND).enumerate() {
+            let strongest = band.iter().copied().fold(0.0, f32::max);
+            self.raise(2 + band_idx, 2.0 * strongest.sqrt() / n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::generate_keyed_preamble;
+    use crate::units::Samples;
+    use crate::PREAMBLE_SAMPLES;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_meter_levels_and_peak_hold() {
+        // 1500 Hz lies in band 2 and on a DFT bin of the 512-sample blocks
+        let tone: Vec<f32> = (0..SAMPLE_RATE / 2)
+            .map(|i| 0.5 * (2.0 * PI * 1500.0 * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect();
+        let mut meter = LevelMeter::new();
+        tone.chunks(128).for_each(|chunk| meter.push(chunk));
+        let reading = meter.reading();
+        assert!((reading.rms - 0.354).abs() < 0.05, "{:?}", reading);
+        assert!(reading.band_levels[2] > 0.4, "{:?}", reading);
+        for band in [0, 1, 3, 4, 5] {
+            assert!(reading.band_levels[band] < 0.1 * reading.band_levels[2], "{:?}", reading);
+        }
+        assert_eq!(reading.correlation, 0.0);
+        assert_eq!(reading.to_array()[1], reading.rms);
+
+        meter.push(&vec![0.0; 2 * SAMPLE_RATE]);
+        assert!(meter.reading().rms < 0.1 * reading.rms);
+    }
+
+    #[test]
+    fn test_meter_correlation_across_chunks() {
+        let mut audio = vec![0.0; SAMPLE_RATE / 4];
+        audio.extend(generate_keyed_preamble(Samples(PREAMBLE_SAMPLES), 0.5, None));
+        audio.extend(vec![0.0; SAMPLE_RATE / 4]);
+
+        let mut meter = LevelMeter::with_template(generate_keyed_preamble(Samples(PREAMBLE_SAMPLES), 1.0, None));
+        audio.chunks(333).for_each(|chunk| meter.push(chunk));
+        let peak = meter.reading().correlation;
+        assert!(peak > 0.8, "{}", peak);
+
+        meter.push(&vec![0.0; 3 * SAMPLE_RATE]);
+        assert!(meter.reading().correlation < 0.1 * peak);
+    }
+}
@@ -0,0 +1,263 @@
+//! Embedding a transmission under existing audio (music, announcements)
+//!
+//! [`mix_into`] measures the background and the signal in the FSK band
+//! (800-2700 Hz, where the decoder listens) and scales the signal to a target
+//! signal-to-background ratio there. With [`MixOptions::duck_db`] the
+//! background's FSK band is also turned down while the transmission plays,
+//! so the signal can sit quietly in the mix and still decode.
+
+use crate::presence::Biquad;
+use crate::tx_audio::{soft_limit, MIN_HEADROOM_DB};
+use crate::SAMPLE_RATE;
+
+/// Edges of the band the levels are measured in (the FSK tones)
+const MEASURE_BAND_HZ: (f32, f32) = (800.0, 2700.0);
+
+/// Edges of the zero-phase band-pass that ducking removes; wider than the
+/// FSK band because a single section only reaches full depth mid-band
+const DUCK_BAND_HZ: (f32, f32) = (400.0, 5000.0);
+
+/// Fade into and out of the ducked level (50 ms)
+const DUCK_RAMP_SAMPLES: usize = SAMPLE_RATE / 20;
+
+/// Signal samples below this magnitude count as the silence around a transmission
+const SILENCE_LEVEL: f32 = 1e-6;
+
+/// Floor for band powers, so digital silence stays finite
+const POWER_EPSILON: f32 = 1e-12;
+
+/// How [`mix_into_with`] places and levels the signal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixOptions {
+    /// Signal-to-background ratio in the FSK band in dB, against the
+    /// background as it was before ducking
+    pub target_snr_db: f32,
+    /// Background sample where the signal starts
+    pub offset: usize,
+    /// Attenuation of the background's FSK band while the signal plays, in dB (0 = no ducking)
+    pub duck_db: f32,
+}
+
+impl MixOptions {
+    /// Mix at `target_snr_db` from the start of the background, without ducking
+    pub fn new(target_snr_db: f32) -> Self {
+        Self { target_snr_db, offset: 0, duck_db: 0.0 }
+    }
+}
+
+/// Levels of a mix made by [`mix_into_with`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixReport {
+    /// Gain applied to the signal
+    pub signal_gain: f32,
+    /// Signal-to-background ratio in the FSK band after ducking, in dB
+    /// (infinite over a silent background)
+    pub effective_snr_db: f32,
+    /// Largest sample of the mix, at most the limiter ceiling
+    pub peak: f32,
+}
+
+/// Add `signal` to the start of `background` at `target_snr_db` in the FSK band
+///
+/// # Example
+/// ```
+/// use transmitwave_core::{mix_into, DecoderFsk, EncoderFsk};
+/// let signal = EncoderFsk::new()?.encode(b"hello")?;
+/// let mut music: Vec<f32> = (0..signal.len()).map(|n| 0.2 * (n as f32 * 0.2).sin()).collect();
+/// mix_into(&mut music, &signal, 10.0);
+/// assert_eq!(DecoderFsk::new()?.decode(&music)?, b"hello");
+/// # Ok::<(), transmitwave_core::AudioModemError>(())
+/// ```
+pub fn mix_into(background: &mut Vec<f32>, signal: &[f32], target_snr_db: f32) -> MixReport {
+    mix_into_with(background, signal, &MixOptions::new(target_snr_db))
+}
+
+/// [`mix_into`] with an offset and ducking
+///
+/// Levels are compared over the transmission itself (the signal without its
+/// leading and trailing silence). The background is extended with silence
+/// if the signal runs past its end. A mix reaching closer to full scale than
+/// the headroom [`crate::validate_tx_audio`] asks for goes through the
+/// encoder's soft limiter, so loud passages are compressed instead of clipping.
+pub fn mix_into_with(background: &mut Vec<f32>, signal: &[f32], options: &MixOptions) -> MixReport {
+    let end = options.offset + signal.len();
+    if background.len() < end {
+        background.resize(end, 0.0);
+    }
+    let Some((first, last)) = active_span(signal) else {
+        return MixReport { signal_gain: 1.0, effective_snr_db: f32::INFINITY, peak: limit(background) };
+    };
+    let span = options.offset + first..options.offset + last + 1;
+
+    let signal_power = band_power(signal, first..last + 1);
+    let background_power = band_power(background, span.clone());
+    let signal_gain = if background_power > POWER_EPSILON && signal_power > POWER_EPSILON {
+        (background_power / signal_power * 10f32.powf(options.target_snr_db / 10.0)).sqrt()
+    } else {
+        1.0
+    };
+
+    if options.duck_db > 0.0 {
+        duck(background, span.clone(), options.duck_db);
+    }
+    let ducked_power = band_power(background, span);
+    let effective_snr_db = if ducked_power > POWER_EPSILON {
+        10.0 * (signal_gain * signal_gain * signal_power / ducked_power).log10()
+    } else {
+        f32::INFINITY
+    };
+
+    for (mixed, &x) in background[options.offset..end].iter_mut().zip(signal) {
+        *mixed += signal_gain * x;
+    }
+    MixReport { signal_gain, effective_snr_db, peak: limit(background) }
+}
+
+/// First and last sample of the transmission in `signal`
+fn active_span(signal: &[f32]) -> Option<(usize, usize)> {
+    let first = signal.iter().position(|x| x.abs() > SILENCE_LEVEL)?;
+    let last = signal.iter().rposition(|x| x.abs() > SILENCE_LEVEL)?;
+    Some((first, last))
+}
+
+/// Mean power of `samples[range]` in the FSK band
+fn band_power(samples: &[f32], range: std::ops::Range<usize>) -> f32 {
+    let (low, high) = MEASURE_BAND_HZ;
+    let mut stages = [Biquad::highpass(low, SAMPLE_RATE), Biquad::lowpass(high, SAMPLE_RATE)];
+    let len = range.len().max(1);
+    let energy: f32 = samples[range]
+        .iter()
+        .map(|&x| stages.iter_mut().fold(x, |acc, stage| stage.process(acc)))
+        .map(|y| y * y)
+        .sum();
+    energy / len as f32
+}
+
+/// Turn the band around the FSK tones down by `duck_db` over `span`, with ramps on both sides
+fn duck(background: &mut [f32], span: std::ops::Range<usize>, duck_db: f32) {
+    let start = span.start.saturating_sub(DUCK_RAMP_SAMPLES);
+    let end = (span.end + DUCK_RAMP_SAMPLES).min(background.len());
+    let band = zero_phase_band(&background[start..end]);
+    let depth = 1.0 - 10f32.powf(-duck_db / 20.0);
+
+    for (i, band) in (start..end).zip(band) {
+        let ramp = if i < span.start {
+            1.0 - (span.start - i) as f32 / DUCK_RAMP_SAMPLES as f32
+        } else if i >= span.end {
+            1.0 - (i + 1 - span.end) as f32 / DUCK_RAMP_SAMPLES as f32
+        } else {
+            1.0
+        };
+        background[i] -= depth * ramp * band;
+    }
+}
+
+/// Band-pass of `samples` run forward and backward, so it lines up with the input
+fn zero_phase_band(samples: &[f32]) -> Vec<f32> {
+    let (low, high) = DUCK_BAND_HZ;
+    let filter = |samples: &mut dyn Iterator<Item = f32>| -> Vec<f32> {
+        let mut stages = [Biquad::highpass(low, SAMPLE_RATE), Biquad::lowpass(high, SAMPLE_RATE)];
+        samples.map(|x| stages.iter_mut().fold(x, |acc, stage| stage.process(acc))).collect()
+    };
+    let forward = filter(&mut samples.iter().copied());
+    let mut band = filter(&mut forward.into_iter().rev());
+    band.reverse();
+    band
+}
+
+/// Soft-limit `samples` if they reach above the headroom ceiling, and return their peak
+fn limit(samples: &mut [f32]) -> f32 {
+    let ceiling = 10f32.powf(-MIN_HEADROOM_DB / 20.0);
+    let unlimited = peak(samples);
+    if unlimited <= ceiling {
+        return unlimited;
+    }
+    samples.iter_mut().for_each(|x| *x = soft_limit(*x, ceiling));
+    peak(samples)
+}
+
+fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+    use crate::{DecoderFsk, EncoderFsk};
+    use std::f32::consts::PI;
+
+    /// Sines at `freqs` (with the given amplitudes) plus a little seeded noise
+    fn tones(freqs: &[(f32, f32)], len: usize, seed: u64) -> Vec<f32> {
+        let mut rng = SplitMix64::new(seed);
+        (0..len)
+            .map(|n| {
+                let noise = 0.01 * (rng.next_f32() - 0.5);
+                let t = n as f32 / SAMPLE_RATE as f32;
+                noise + freqs.iter().map(|&(freq, amplitude)| amplitude * (2.0 * PI * freq * t).sin()).sum::<f32>()
+            })
+            .collect()
+    }
+
+    /// A C major chord with a few harmonics per note
+    fn music(len: usize) -> Vec<f32> {
+        let mut partials = Vec::new();
+        for root in [261.6, 329.6, 392.0] {
+            for harmonic in 1..=6 {
+                partials.push((root * harmonic as f32, 0.08 / harmonic as f32));
+            }
+        }
+        tones(&partials, len, 1682)
+    }
+
+    #[test]
+    fn test_mix_levels_decode() {
+        let signal = EncoderFsk::new().unwrap().encode(b"under the music").unwrap();
+        let background = music(signal.len() + 2 * SAMPLE_RATE);
+        for target_snr_db in [20.0, 10.0] {
+            let mut mix = background.clone();
+            let options = MixOptions { offset: SAMPLE_RATE, ..MixOptions::new(target_snr_db) };
+            let report = mix_into_with(&mut mix, &signal, &options);
+            assert!((report.effective_snr_db - target_snr_db).abs() < 0.01, "{:?}", report);
+            assert!(report.peak < 1.0);
+            assert_eq!(mix.len(), background.len());
+            assert_eq!(mix[..SAMPLE_RATE], background[..SAMPLE_RATE]);
+            let decoded = DecoderFsk::new().unwrap().decode(&mix);
+            assert_eq!(decoded.unwrap(), b"under the music", "{} dB", target_snr_db);
+        }
+
+        // A louder background gets a louder signal
+        let mut quiet = background.clone();
+        let mut loud: Vec<f32> = background.iter().map(|x| 2.0 * x).collect();
+        let gain = mix_into(&mut quiet, &signal, 10.0).signal_gain;
+        assert!((mix_into(&mut loud, &signal, 10.0).signal_gain / gain - 2.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_ducking_makes_room() {
+        // Tones right inside the FSK band, as loud as the signal
+        let signal = EncoderFsk::new().unwrap().encode(b"ducked").unwrap();
+        let voice = [(1000.0, 0.15), (1250.0, 0.1), (1500.0, 0.1), (2000.0, 0.05)];
+        let background = tones(&voice, signal.len() + 2 * SAMPLE_RATE, 7);
+
+        let plain = MixOptions { offset: SAMPLE_RATE, ..MixOptions::new(0.0) };
+        let mut mix = background.clone();
+        let unducked = mix_into_with(&mut mix, &signal, &plain);
+        assert!(unducked.effective_snr_db.abs() < 0.01);
+
+        let mut mix = background.clone();
+        let ducked = mix_into_with(&mut mix, &signal, &MixOptions { duck_db: 20.0, ..plain });
+        assert_eq!(ducked.signal_gain, unducked.signal_gain);
+        assert!(ducked.effective_snr_db > 10.0, "{:?}", ducked);
+        assert_eq!(DecoderFsk::new().unwrap().decode(&mix).unwrap(), b"ducked");
+        // Untouched outside the transmission and its ramps
+        let ramp_start = SAMPLE_RATE + active_span(&signal).unwrap().0 - DUCK_RAMP_SAMPLES;
+        assert_eq!(mix[..ramp_start], background[..ramp_start]);
+
+        // A silent background leaves the signal as it is
+        let mut silence = Vec::new();
+        let report = mix_into(&mut silence, &signal, 10.0);
+        assert_eq!((report.signal_gain, report.effective_snr_db), (1.0, f32::INFINITY));
+        assert_eq!(silence, signal);
+    }
+}
@@ -0,0 +1,207 @@
+//! Running signal level and noise floor for streaming sync detection
+//!
+//! Adaptive detection thresholds used to come from the strongest 2048-sample
+//! window RMS of the whole buffer, recomputed on every call. The estimator
+//! here keeps the same statistics incrementally: samples go through the same
+//! DC blocker as [`crate::sync::condition_for_sync`], window energies are
+//! summed in hops, the strongest window decays slowly instead of being
+//! rescanned, and the quietest windows track the background noise floor.
+
+use crate::sync::{adaptive_threshold, DetectionThreshold, ADAPTIVE_RMS_WINDOW_LENGTH, DC_BLOCKER_POLE};
+use std::collections::VecDeque;
+
+/// Hops per RMS window; the window slides by a quarter of its length
+const HOPS_PER_WINDOW: usize = 4;
+
+const HOP_SAMPLES: usize = ADAPTIVE_RMS_WINDOW_LENGTH / HOPS_PER_WINDOW;
+
+/// Decay of the signal level per hop (~2 s time constant at 16 kHz)
+const SIGNAL_RELEASE_PER_HOP: f32 = 0.984;
+
+/// Fraction of the gap (in dB) the noise floor rises per hop when the level is above it
+const NOISE_RISE_PER_HOP: f32 = 0.005;
+
+/// Exponential signal level and noise floor of a stream, updated as samples arrive
+#[derive(Debug, Clone, Default)]
+pub struct NoiseFloorEstimator {
+    /// DC blocker state: previous input and output
+    dc_state: Option<(f32, f32)>,
+    hop_energy: f32,
+    hop_len: usize,
+    /// Energies of the last complete hops
+    hops: VecDeque<f32>,
+    signal_rms: f32,
+    noise_floor_db: Option<f32>,
+    samples_seen: u64,
+}
+
+impl NoiseFloorEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &x in samples {
+            let (prev_x, prev_y) = self.dc_state.unwrap_or((x, 0.0));
+            let y = x - prev_x + DC_BLOCKER_POLE * prev_y;
+            self.dc_state = Some((x, y));
+
+            self.hop_energy += y * y;
+            self.hop_len += 1;
+            if self.hop_len == HOP_SAMPLES {
+                self.finish_hop();
+            }
+        }
+        self.samples_seen += samples.len() as u64;
+    }
+
+    fn finish_hop(&mut self) {
+        if self.hops.len() == HOPS_PER_WINDOW {
+            self.hops.pop_front();
+        }
+        self.hops.push_back(self.hop_energy);
+        self.hop_energy = 0.0;
+        self.hop_len = 0;
+        if self.hops.len() < HOPS_PER_WINDOW {
+            return;
+        }
+
+        let rms = (self.hops.iter().sum::<f32>() / ADAPTIVE_RMS_WINDOW_LENGTH as f32).sqrt();
+        self.signal_rms = rms.max(self.signal_rms * SIGNAL_RELEASE_PER_HOP);
+        // Floor drops at once and rises slowly, so it follows the quiet stretches
+        let level_db = to_db(rms);
+        self.noise_floor_db = Some(match self.noise_floor_db {
+            Some(floor) if level_db > floor => floor + NOISE_RISE_PER_HOP * (level_db - floor),
+            _ => level_db,
+        });
+    }
+
+    /// RMS of the samples not yet covered by a full window
+    fn partial_rms(&self) -> f32 {
+        let len = self.hops.len() * HOP_SAMPLES + self.hop_len;
+        if len == 0 {
+            return 0.0;
+        }
+        ((self.hops.iter().sum::<f32>() + self.hop_energy) / len as f32).sqrt()
+    }
+
+    /// Strongest recent window RMS, decaying while the signal is gone
+    pub fn signal_rms(&self) -> f32 {
+        if self.hops.len() < HOPS_PER_WINDOW {
+            // Shorter than one window so far: the whole stream, as for a short buffer
+            self.partial_rms()
+        } else {
+            self.signal_rms
+        }
+    }
+
+    /// RMS of the background between transmissions (0 before the first full window)
+    pub fn noise_floor_rms(&self) -> f32 {
+        self.noise_floor_db.map_or(0.0, |db| 10f32.powf(db / 20.0))
+    }
+
+    /// Noise floor in dBFS (-180 before the first full window)
+    pub fn noise_floor_db(&self) -> f32 {
+        self.noise_floor_db.unwrap_or(to_db(0.0))
+    }
+
+    /// Signal level above the noise floor in dB
+    pub fn snr_db(&self) -> f32 {
+        to_db(self.signal_rms()) - self.noise_floor_db()
+    }
+
+    /// Adaptive correlation threshold for the current signal level
+    pub fn threshold(&self) -> f32 {
+        adaptive_threshold(self.signal_rms())
+    }
+
+    /// `threshold` with `Adaptive` replaced by the tracked value, so detectors
+    /// skip rescanning their buffer; `Fixed` values are returned as is
+    pub fn resolve(&self, threshold: DetectionThreshold) -> DetectionThreshold {
+        match threshold {
+            DetectionThreshold::Adaptive => DetectionThreshold::Fixed(self.threshold()),
+            fixed => fixed,
+        }
+    }
+
+    /// Correlation score `threshold` requires at the current signal level
+    pub fn threshold_value(&self, threshold: DetectionThreshold) -> f32 {
+        match threshold {
+            DetectionThreshold::Adaptive => self.threshold(),
+            DetectionThreshold::Fixed(value) => value,
+        }
+    }
+
+    /// Samples pushed since creation or the last reset
+    pub fn samples_seen(&self) -> u64 {
+        self.samples_seen
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+fn to_db(rms: f32) -> f32 {
+    20.0 * rms.max(1e-9).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    fn tone(amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / crate::SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_threshold_matches_batch_adaptive() {
+        for amplitude in [0.5, 0.08, 0.01] {
+            let mut audio = vec![0.0f32; 6000];
+            audio.extend(tone(amplitude, 8000));
+            audio.extend(vec![0.0f32; 3000]);
+
+            let mut estimator = NoiseFloorEstimator::new();
+            for chunk in audio.chunks(700) {
+                estimator.push(chunk);
+            }
+            let expected = DetectionThreshold::Adaptive.value_for(&audio);
+            assert_eq!(estimator.threshold(), expected, "amplitude {}", amplitude);
+            assert_eq!(estimator.resolve(DetectionThreshold::Adaptive), DetectionThreshold::Fixed(expected));
+            assert_eq!(estimator.resolve(DetectionThreshold::Fixed(0.6)), DetectionThreshold::Fixed(0.6));
+            assert_eq!(estimator.threshold_value(DetectionThreshold::Fixed(0.6)), 0.6);
+            assert_eq!(estimator.samples_seen(), audio.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_noise_floor_tracks_background() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1673);
+        let mut noise = |len: usize| -> Vec<f32> { (0..len).map(|_| rng.gen_range(-0.002..0.002f32)).collect() };
+
+        let mut estimator = NoiseFloorEstimator::new();
+        estimator.push(&noise(crate::SAMPLE_RATE));
+        let floor = estimator.noise_floor_db();
+        // Uniform noise of ±0.002 has an RMS of 0.00115 (-58.7 dBFS)
+        assert!((-64.0..-56.0).contains(&floor), "floor {} dB", floor);
+
+        // A transmission barely moves the floor but raises the signal level
+        estimator.push(&tone(0.3, crate::SAMPLE_RATE / 2));
+        assert!(estimator.noise_floor_db() < floor + 6.0);
+        assert!(estimator.signal_rms() > 0.2);
+        assert!(estimator.snr_db() > 30.0);
+        assert_eq!(estimator.threshold(), 0.4);
+
+        // The level decays once it is gone
+        estimator.push(&noise(5 * crate::SAMPLE_RATE));
+        assert!(estimator.signal_rms() < 0.02);
+        assert_eq!(estimator.threshold(), 0.3);
+
+        estimator.reset();
+        assert_eq!(estimator.noise_floor_rms(), 0.0);
+        assert_eq!(estimator.signal_rms(), 0.0);
+    }
+}
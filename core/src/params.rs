@@ -0,0 +1,225 @@
+//! Wire parameters of the modem in one place
+//!
+//! [`ModemParams::CURRENT`] collects the timing, band and overhead constants
+//! that a compatible implementation or a UI needs. The web constants in
+//! `web/src/constants/` are generated from it (`cargo run --manifest-path
+//! tools/Cargo.toml`), and a test fails when they are out of date.
+
+use crate::framing::PROTOCOL_REVISION;
+use crate::fsk::{FSK_BINS_PER_BAND, FSK_FREQ_DELTA, FSK_NUM_BINS};
+use crate::sync::{POSTAMBLE_CHIRP_HZ, PREAMBLE_CHIRP_HZ};
+use std::fmt::Write;
+
+/// Timing, band and overhead constants of the wire format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModemParams {
+    pub protocol_revision: u8,
+    pub sample_rate: usize,
+    /// Lowest and highest data tone (Hz)
+    pub fsk_min_frequency_hz: f32,
+    pub fsk_max_frequency_hz: f32,
+    /// Spacing of adjacent tone bins (Hz)
+    pub fsk_bin_spacing_hz: f32,
+    pub fsk_num_bins: usize,
+    /// Simultaneous tones per standard symbol, one per band
+    pub fsk_tones: usize,
+    pub fsk_bins_per_band: usize,
+    pub fsk_symbol_samples: usize,
+    pub fsk_bytes_per_symbol: usize,
+    /// Start and end of the preamble and postamble chirps (Hz)
+    pub preamble_chirp_hz: (f32, f32),
+    pub postamble_chirp_hz: (f32, f32),
+    pub preamble_samples: usize,
+    pub postamble_samples: usize,
+    /// Silence around the sync signals
+    pub sync_silence_samples: usize,
+    pub max_sync_gap_samples: usize,
+    pub symbol_gap_samples: usize,
+    pub min_symbol_gap_interval: usize,
+    pub max_symbol_gap_interval: usize,
+    pub frame_header_size: usize,
+    pub max_payload_size: usize,
+    pub rs_total_bytes: usize,
+    pub rs_data_bytes: usize,
+    pub fountain_block_size: usize,
+    pub packet_overhead_bytes: usize,
+    pub short_sync_samples: usize,
+    pub short_sync_silence_samples: usize,
+    pub short_symbol_samples: usize,
+    pub short_max_payload_size: usize,
+    pub telephony_sync_samples: usize,
+    pub telephony_symbol_samples: usize,
+    pub codec_robust_sync_samples: usize,
+    pub codec_robust_symbol_samples: usize,
+    pub narrowband_sync_samples: usize,
+    pub narrowband_symbol_samples: usize,
+    /// Buffer cap of the listening loops
+    pub max_buffer_samples: usize,
+}
+
+impl ModemParams {
+    /// Parameters of this build
+    pub const CURRENT: ModemParams = ModemParams {
+        protocol_revision: PROTOCOL_REVISION,
+        sample_rate: crate::SAMPLE_RATE,
+        fsk_min_frequency_hz: crate::FSK_MIN_FREQUENCY,
+        fsk_max_frequency_hz: crate::FSK_MAX_FREQUENCY,
+        fsk_bin_spacing_hz: FSK_FREQ_DELTA,
+        fsk_num_bins: FSK_NUM_BINS,
+        fsk_tones: crate::NUM_FSK_TONES,
+        fsk_bins_per_band: FSK_BINS_PER_BAND,
+        fsk_symbol_samples: crate::FSK_SYMBOL_SAMPLES,
+        fsk_bytes_per_symbol: crate::FSK_BYTES_PER_SYMBOL,
+        preamble_chirp_hz: PREAMBLE_CHIRP_HZ,
+        postamble_chirp_hz: POSTAMBLE_CHIRP_HZ,
+        preamble_samples: crate::PREAMBLE_SAMPLES,
+        postamble_samples: crate::POSTAMBLE_SAMPLES,
+        sync_silence_samples: crate::SYNC_SILENCE_SAMPLES,
+        max_sync_gap_samples: crate::MAX_SYNC_GAP_SAMPLES,
+        symbol_gap_samples: crate::SYMBOL_GAP_SAMPLES,
+        min_symbol_gap_interval: crate::MIN_SYMBOL_GAP_INTERVAL,
+        max_symbol_gap_interval: crate::MAX_SYMBOL_GAP_INTERVAL,
+        frame_header_size: crate::FRAME_HEADER_SIZE,
+        max_payload_size: crate::MAX_PAYLOAD_SIZE,
+        rs_total_bytes: crate::RS_TOTAL_BYTES,
+        rs_data_bytes: crate::RS_DATA_BYTES,
+        fountain_block_size: crate::FOUNTAIN_BLOCK_SIZE,
+        packet_overhead_bytes: crate::PACKET_OVERHEAD_BYTES,
+        short_sync_samples: crate::SHORT_SYNC_SAMPLES,
+        short_sync_silence_samples: crate::SHORT_SYNC_SILENCE_SAMPLES,
+        short_symbol_samples: crate::SHORT_SYMBOL_SAMPLES,
+        short_max_payload_size: crate::SHORT_MAX_PAYLOAD_SIZE,
+        telephony_sync_samples: crate::TELEPHONY_SYNC_SAMPLES,
+        telephony_symbol_samples: crate::TELEPHONY_SYMBOL_SAMPLES,
+        codec_robust_sync_samples: crate::CODEC_ROBUST_SYNC_SAMPLES,
+        codec_robust_symbol_samples: crate::CODEC_ROBUST_SYMBOL_SAMPLES,
+        narrowband_sync_samples: crate::NARROWBAND_SYNC_SAMPLES,
+        narrowband_symbol_samples: crate::NARROWBAND_SYMBOL_SAMPLES,
+        max_buffer_samples: crate::MAX_BUFFER_SAMPLES,
+    };
+
+    /// Every parameter as a snake_case name and its value
+    pub fn entries(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("protocol_revision", self.protocol_revision as f64),
+            ("sample_rate", self.sample_rate as f64),
+            ("fsk_min_frequency_hz", self.fsk_min_frequency_hz as f64),
+            ("fsk_max_frequency_hz", self.fsk_max_frequency_hz as f64),
+            ("fsk_bin_spacing_hz", self.fsk_bin_spacing_hz as f64),
+            ("fsk_num_bins", self.fsk_num_bins as f64),
+            ("fsk_tones", self.fsk_tones as f64),
+            ("fsk_bins_per_band", self.fsk_bins_per_band as f64),
+            ("fsk_symbol_samples", self.fsk_symbol_samples as f64),
+            ("fsk_bytes_per_symbol", self.fsk_bytes_per_symbol as f64),
+            ("preamble_chirp_start_hz", self.preamble_chirp_hz.0 as f64),
+            ("preamble_chirp_end_hz", self.preamble_chirp_hz.1 as f64),
+            ("postamble_chirp_start_hz", self.postamble_chirp_hz.0 as f64),
+            ("postamble_chirp_end_hz", self.postamble_chirp_hz.1 as f64),
+            ("preamble_samples", self.preamble_samples as f64),
+            ("postamble_samples", self.postamble_samples as f64),
+            ("sync_silence_samples", self.sync_silence_samples as f64),
+            ("max_sync_gap_samples", self.max_sync_gap_samples as f64),
+            ("symbol_gap_samples", self.symbol_gap_samples as f64),
+            ("min_symbol_gap_interval", self.min_symbol_gap_interval as f64),
+            ("max_symbol_gap_interval", self.max_symbol_gap_interval as f64),
+            ("frame_header_size", self.frame_header_size as f64),
+            ("max_payload_size", self.max_payload_size as f64),
+            ("rs_total_bytes", self.rs_total_bytes as f64),
+            ("rs_data_bytes", self.rs_data_bytes as f64),
+            ("fountain_block_size", self.fountain_block_size as f64),
+            ("packet_overhead_bytes", self.packet_overhead_bytes as f64),
+            ("short_sync_samples", self.short_sync_samples as f64),
+            ("short_sync_silence_samples", self.short_sync_silence_samples as f64),
+            ("short_symbol_samples", self.short_symbol_samples as f64),
+            ("short_max_payload_size", self.short_max_payload_size as f64),
+            ("telephony_sync_samples", self.telephony_sync_samples as f64),
+            ("telephony_symbol_samples", self.telephony_symbol_samples as f64),
+            ("codec_robust_sync_samples", self.codec_robust_sync_samples as f64),
+            ("codec_robust_symbol_samples", self.codec_robust_symbol_samples as f64),
+            ("narrowband_sync_samples", self.narrowband_sync_samples as f64),
+            ("narrowband_symbol_samples", self.narrowband_symbol_samples as f64),
+            ("max_buffer_samples", self.max_buffer_samples as f64),
+        ]
+    }
+
+    /// `{"protocol_revision":1,"sample_rate":16000,...}`
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> =
+            self.entries().iter().map(|(name, value)| format!("\"{}\":{}", name, value)).collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// TypeScript module with one `export const NAME = value` per parameter
+    /// (the generated `web/src/constants/modem.ts`)
+    pub fn to_typescript(&self) -> String {
+        let mut ts = String::from(TYPESCRIPT_HEADER);
+        for (name, value) in self.entries() {
+            let _ = writeln!(ts, "export const {} = {}", name.to_uppercase(), value);
+        }
+        ts
+    }
+
+    /// TypeScript module re-exporting the parameters the fountain worker and
+    /// pages use under their names there (the generated
+    /// `web/src/constants/fountain.ts`)
+    pub fn to_fountain_typescript(&self) -> String {
+        let mut ts = String::from(TYPESCRIPT_HEADER);
+        ts.push_str("export {\n");
+        for (alias, name) in FOUNTAIN_TYPESCRIPT_NAMES {
+            let renamed = if alias == name { String::new() } else { format!(" as {}", alias.to_uppercase()) };
+            let _ = writeln!(ts, "  {}{},", name.to_uppercase(), renamed);
+        }
+        ts.push_str("} from './modem'\n");
+        ts
+    }
+}
+
+const TYPESCRIPT_HEADER: &str = "// AUTO-GENERATED FILE - DO NOT EDIT MANUALLY\n\
+                                 // Generated from ModemParams::CURRENT (core/src/params.rs)\n\
+                                 // Run `cargo run --manifest-path tools/Cargo.toml` to regenerate\n\n";
+
+/// Names in `fountain.ts` and the parameter each one is
+const FOUNTAIN_TYPESCRIPT_NAMES: [(&str, &str); 6] = [
+    ("fountain_block_size_bytes", "fountain_block_size"),
+    ("max_payload_bytes", "max_payload_size"),
+    ("fsk_bytes_per_symbol", "fsk_bytes_per_symbol"),
+    ("fsk_symbol_samples", "fsk_symbol_samples"),
+    ("packet_overhead_bytes", "packet_overhead_bytes"),
+    ("max_buffer_samples", "max_buffer_samples"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsk::bin_to_freq;
+
+    #[test]
+    fn test_params_match_the_modem() {
+        let params = ModemParams::CURRENT;
+        assert_eq!(params.fsk_max_frequency_hz, bin_to_freq(params.fsk_num_bins - 1));
+        assert_eq!(params.fsk_tones * params.fsk_bins_per_band, params.fsk_num_bins);
+        assert_eq!(params.preamble_samples, crate::SAMPLE_RATE * crate::PREAMBLE_DURATION_MS / 1000);
+
+        let entries = params.entries();
+        let mut names: Vec<&str> = entries.iter().map(|&(name, _)| name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), entries.len());
+
+        let json = params.to_json();
+        assert!(json.starts_with("{\"protocol_revision\":1,\"sample_rate\":16000,\"fsk_min_frequency_hz\":800,"));
+        assert!(params.to_typescript().contains("\nexport const FSK_SYMBOL_SAMPLES = 3072\n"));
+    }
+
+    #[test]
+    fn test_fountain_constants_name_parameters() {
+        let params = ModemParams::CURRENT;
+        let entries = params.entries();
+        for (_, name) in FOUNTAIN_TYPESCRIPT_NAMES {
+            assert!(entries.iter().any(|&(entry, _)| entry == name), "{}", name);
+        }
+        let ts = params.to_fountain_typescript();
+        assert!(ts.contains("\n  FOUNTAIN_BLOCK_SIZE as FOUNTAIN_BLOCK_SIZE_BYTES,\n  MAX_PAYLOAD_SIZE as MAX_PAYLOAD_BYTES,\n"));
+        assert!(ts.ends_with("  MAX_BUFFER_SAMPLES,\n} from './modem'\n"));
+    }
+}
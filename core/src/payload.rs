@@ -0,0 +1,105 @@
+//! Typed payloads: CBOR helpers on the encoder and decoder
+//!
+//! Values are serialized with `ciborium` and sent as ordinary frames,
+//! announced as `application/cbor` except in the short profile (which has no
+//! header extension), so receivers without the `serde-payload` feature still
+//! get the raw bytes.
+
+use crate::decoder_fsk::DecoderFsk;
+use crate::encoder_fsk::{payload_too_large, EncoderFsk, FrameProfile};
+use crate::error::{AudioModemError, Result};
+use crate::framing::ContentType;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Serialize `value` to CBOR
+pub fn to_cbor<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::into_writer(value, &mut bytes).map_err(|e| AudioModemError::PayloadEncoding(e.to_string()))?;
+    Ok(bytes)
+}
+
+/// Deserialize a CBOR payload
+pub fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    ciborium::from_reader(bytes).map_err(|e| AudioModemError::PayloadEncoding(e.to_string()))
+}
+
+impl EncoderFsk {
+    /// Encode `value` as a CBOR payload in one frame
+    ///
+    /// Fails with `PayloadTooLarge` before modulating anything if the
+    /// serialized value exceeds [`EncoderFsk::max_payload_size`].
+    pub fn encode_cbor<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<Vec<f32>> {
+        let bytes = to_cbor(value)?;
+        if bytes.len() > self.max_payload_size() {
+            return Err(payload_too_large(bytes.len(), self.max_payload_size()));
+        }
+
+        let content_type = self.content_type();
+        if self.profile() != FrameProfile::Short {
+            self.set_content_type(Some(ContentType::Cbor));
+        }
+        let result = self.encode(&bytes);
+        self.set_content_type(content_type);
+        result
+    }
+}
+
+impl DecoderFsk {
+    /// Decode one frame and deserialize its CBOR payload
+    pub fn decode_cbor<T: DeserializeOwned>(&mut self, samples: &[f32]) -> Result<T> {
+        from_cbor(&self.decode(samples)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Reading {
+        sensor: String,
+        celsius: f32,
+        samples: Vec<u16>,
+    }
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let reading = Reading { sensor: "greenhouse-2".to_string(), celsius: 21.5, samples: vec![3, 1, 4, 1, 5] };
+        let mut encoder = EncoderFsk::new().unwrap();
+        let audio = encoder.encode_cbor(&reading).unwrap();
+        // The announcement is per call
+        assert_eq!(encoder.content_type(), None);
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        let message = decoder.decode_with_metadata(&audio).unwrap();
+        assert_eq!(message.content_type, Some(ContentType::Cbor));
+        assert_eq!(from_cbor::<Reading>(&message.payload).unwrap(), reading);
+        assert_eq!(decoder.decode_cbor::<Reading>(&audio).unwrap(), reading);
+
+        encoder.set_profile(FrameProfile::Short);
+        let audio = encoder.encode_cbor(&(7u8, "ok")).unwrap();
+        assert_eq!(decoder.decode_cbor::<(u8, String)>(&audio).unwrap(), (7, "ok".to_string()));
+    }
+
+    #[test]
+    fn test_cbor_size_and_type_errors() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let too_big = vec![0xAAu8; crate::MAX_PAYLOAD_SIZE];
+        assert!(matches!(
+            encoder.encode_cbor(&too_big),
+            Err(AudioModemError::PayloadTooLarge { max: crate::MAX_PAYLOAD_SIZE, .. })
+        ));
+        encoder.set_profile(FrameProfile::Short);
+        assert!(matches!(
+            encoder.encode_cbor("x".repeat(crate::SHORT_MAX_PAYLOAD_SIZE).as_str()),
+            Err(AudioModemError::PayloadTooLarge { max: crate::SHORT_MAX_PAYLOAD_SIZE, .. })
+        ));
+
+        let audio = EncoderFsk::new().unwrap().encode_cbor(&[1u8, 2, 3]).unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        assert!(matches!(decoder.decode_cbor::<Reading>(&audio), Err(AudioModemError::PayloadEncoding(_))));
+        assert!(from_cbor::<u8>(&[]).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Cheap check whether a recording likely holds a transmission
+//!
+//! A full decode runs the sync correlation at 16 kHz for every profile and
+//! demodulates each candidate. [`signal_presence`] only looks at a decimated
+//! copy: how much of the energy lies in the FSK band, and how well the audio
+//! at 4 kHz correlates with the standard and fountain preambles. Both feed a
+//! logistic score, so callers pick their own cut-off before paying for a
+//! decode. Network-keyed preambles do not correlate; their frames are only
+//! seen through the band energy.
+
+use crate::resample::{resample_audio_with, ResampleOptions};
+use crate::sync::{condition_for_sync, generate_fountain_preamble, generate_preamble, sync_scores};
+use crate::{FSK_MAX_FREQUENCY, FSK_MIN_FREQUENCY, PREAMBLE_SAMPLES, SAMPLE_RATE};
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+/// Rate of the band-energy profile (the FSK band stays below its Nyquist frequency)
+const BAND_RATE: usize = 8_000;
+
+/// Rate of the preamble correlation (both preambles stay below 1.8 kHz)
+const CORRELATION_RATE: usize = 4_000;
+
+/// Window of the band-energy profile (32 ms)
+const BAND_WINDOW_SAMPLES: usize = 256;
+
+/// Windows with less than this fraction of the loudest window's energy are ignored (-20 dB)
+const BAND_ACTIVE_RATIO: f32 = 0.01;
+
+/// Weight and midpoint of the preamble correlation in the logistic score
+const CORRELATION_WEIGHT: f32 = 15.0;
+const CORRELATION_MIDPOINT: f32 = 0.3;
+
+/// Weight and midpoint of the in-band energy share; white noise lands
+/// around 0.5, speech well below
+const BAND_WEIGHT: f32 = 4.0;
+const BAND_MIDPOINT: f32 = 0.6;
+
+/// Result of [`signal_presence`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalPresence {
+    /// Likelihood (0.0-1.0) that the recording holds a transmission
+    pub probability: f32,
+    /// Best normalized preamble correlation on the decimated audio
+    pub correlation: f32,
+    /// Where that correlation peaked, in samples at `SAMPLE_RATE`
+    pub position: usize,
+    /// Share of the energy of the active windows inside the FSK band
+    pub band_ratio: f32,
+}
+
+impl SignalPresence {
+    /// Whether a decode is worth trying (`probability` of at least 0.5)
+    pub fn is_likely(&self) -> bool {
+        self.probability >= 0.5
+    }
+}
+
+/// Score how likely `samples` (mono, at `SAMPLE_RATE`) contain a transmission
+pub fn signal_presence(samples: &[f32]) -> SignalPresence {
+    let options = ResampleOptions { anti_alias: true, ..Default::default() };
+    let band_audio = resample_audio_with(&condition_for_sync(samples), SAMPLE_RATE, BAND_RATE, options);
+    let correlation_audio = resample_audio_with(&band_audio, BAND_RATE, CORRELATION_RATE, options);
+
+    let band_ratio = band_ratio(&band_audio);
+    let templates = [generate_preamble(PREAMBLE_SAMPLES, 1.0), generate_fountain_preamble(PREAMBLE_SAMPLES, 1.0)];
+    let (correlation, position) = templates
+        .iter()
+        .map(|template| {
+            let template = resample_audio_with(template, SAMPLE_RATE, CORRELATION_RATE, options);
+            best_correlation(&correlation_audio, &template)
+        })
+        .fold((0.0f32, 0), |best, candidate| if candidate.0 > best.0 { candidate } else { best });
+
+    let z = CORRELATION_WEIGHT * (correlation - CORRELATION_MIDPOINT) + BAND_WEIGHT * (band_ratio - BAND_MIDPOINT);
+    SignalPresence {
+        probability: 1.0 / (1.0 + (-z).exp()),
+        correlation,
+        position: position * SAMPLE_RATE / CORRELATION_RATE,
+        band_ratio,
+    }
+}
+
+/// Highest normalized correlation of `template` in `samples` and its position
+fn best_correlation(samples: &[f32], template: &[f32]) -> (f32, usize) {
+    if samples.len() < template.len() {
+        return (0.0, 0);
+    }
+    let Some(scores) = sync_scores(samples, template, "presence") else {
+        return (0.0, 0);
+    };
+    scores
+        .iter()
+        .enumerate()
+        .fold((0.0f32, 0), |best, (i, &score)| if score > best.0 { (score, i) } else { best })
+}
+
+/// Share of the energy between `FSK_MIN_FREQUENCY` and `FSK_MAX_FREQUENCY`
+/// over the windows within 20 dB of the loudest one
+fn band_ratio(samples: &[f32]) -> f32 {
+    let mut highpass = Biquad::highpass(FSK_MIN_FREQUENCY, BAND_RATE);
+    let mut lowpass = Biquad::lowpass(FSK_MAX_FREQUENCY, BAND_RATE);
+    let windows: Vec<(f32, f32)> = samples
+        .chunks_exact(BAND_WINDOW_SAMPLES)
+        .map(|window| {
+            window.iter().fold((0.0f32, 0.0f32), |(total, band), &sample| {
+                let filtered = lowpass.process(highpass.process(sample));
+                (total + sample * sample, band + filtered * filtered)
+            })
+        })
+        .collect();
+
+    let loudest = windows.iter().map(|&(total, _)| total).fold(0.0f32, f32::max);
+    let (total, band) = windows
+        .iter()
+        .filter(|&&(total, _)| total > 0.0 && total >= loudest * BAND_ACTIVE_RATIO)
+        .fold((0.0f32, 0.0f32), |(sum_total, sum_band), &(total, band)| (sum_total + total, sum_band + band));
+    if total > 0.0 {
+        (band / total).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Second-order Butterworth section (RBJ cookbook coefficients, normalized by a0)
+pub(crate) struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    fn new(b: [f32; 3], a0: f32, a: [f32; 2]) -> Self {
+        Self { b: b.map(|b| b / a0), a: a.map(|a| a / a0), x: [0.0; 2], y: [0.0; 2] }
+    }
+
+    fn coefficients(cutoff: f32, rate: usize) -> (f32, f32) {
+        let omega = 2.0 * PI * cutoff / rate as f32;
+        (omega.cos(), omega.sin() / (2.0 * FRAC_1_SQRT_2))
+    }
+
+    pub(crate) fn highpass(cutoff: f32, rate: usize) -> Self {
+        let (cos, alpha) = Self::coefficients(cutoff, rate);
+        let b = [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0];
+        Self::new(b, 1.0 + alpha, [-2.0 * cos, 1.0 - alpha])
+    }
+
+    pub(crate) fn lowpass(cutoff: f32, rate: usize) -> Self {
+        let (cos, alpha) = Self::coefficients(cutoff, rate);
+        let b = [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0];
+        Self::new(b, 1.0 + alpha, [-2.0 * cos, 1.0 - alpha])
+    }
+
+    pub(crate) fn process(&mut self, x0: f32) -> f32 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{detect_preamble, DetectionThreshold};
+    use crate::EncoderFsk;
+    use rand::{Rng, SeedableRng};
+
+    fn noise(len: usize, amplitude: f32, seed: u64) -> Vec<f32> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        (0..len).map(|_| rng.gen_range(-amplitude..amplitude)).collect()
+    }
+
+    #[test]
+    fn test_frame_is_likely() {
+        let frame = EncoderFsk::new().unwrap().encode(b"is anyone there?").unwrap();
+        let offset = SAMPLE_RATE;
+        let mut recording = noise(offset + frame.len() + SAMPLE_RATE, 0.02, 1665);
+        for (sample, value) in recording[offset..].iter_mut().zip(&frame) {
+            *sample += 0.5 * value;
+        }
+
+        let presence = signal_presence(&recording);
+        assert!(presence.is_likely(), "{:?}", presence);
+        assert!(presence.probability > 0.9, "{:?}", presence);
+        assert!(presence.band_ratio > BAND_MIDPOINT, "{:?}", presence);
+        let preamble = offset + detect_preamble(&frame, DetectionThreshold::Adaptive).unwrap();
+        assert!(presence.position.abs_diff(preamble) <= 16, "{} vs {}", presence.position, preamble);
+    }
+
+    #[test]
+    fn test_noise_and_silence_are_unlikely() {
+        let presence = signal_presence(&noise(5 * SAMPLE_RATE, 0.1, 1666));
+        assert!(presence.probability < 0.2, "{:?}", presence);
+        assert!(!presence.is_likely());
+
+        // Low-frequency hum: all of its energy below the band
+        let hum: Vec<f32> = (0..5 * SAMPLE_RATE)
+            .map(|i| 0.3 * (2.0 * PI * 150.0 * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect();
+        let presence = signal_presence(&hum);
+        assert!(presence.band_ratio < 0.1, "{:?}", presence);
+        assert!(presence.probability < 0.2, "{:?}", presence);
+
+        for samples in [vec![0.0; 3 * SAMPLE_RATE], Vec::new()] {
+            let presence = signal_presence(&samples);
+            assert_eq!((presence.correlation, presence.band_ratio), (0.0, 0.0));
+            assert!(presence.probability < 0.01);
+        }
+    }
+
+    #[cfg(feature = "fountain")]
+    #[test]
+    fn test_fountain_stream_is_likely() {
+        let config = crate::FountainConfig { timeout_secs: 0, block_size: 32, ..Default::default() };
+        let mut stream = EncoderFsk::new().unwrap().encode_fountain(b"fountain presence", Some(config)).unwrap();
+        let presence = signal_presence(&stream.next().unwrap());
+        assert!(presence.probability > 0.9, "{:?}", presence);
+    }
+}
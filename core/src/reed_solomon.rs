@@ -0,0 +1,280 @@
+//! Systematic Reed-Solomon codec over GF(256)
+//!
+//! Field polynomial x^8 + x^4 + x^3 + x^2 + 1 (0x11d), generator roots
+//! α^0..α^(parity-1). A codeword is the data followed by the parity; codes
+//! shorter than 255 bytes are shortened codes (implicit leading zeros), so the
+//! same functions serve every block length up to 255. Decoding corrects up to
+//! `e` errors and `f` erasures as long as `2e + f <= parity`.
+
+const FIELD_POLY: u16 = 0x11d;
+
+struct Tables {
+    exp: [u8; 512],
+    log: [u8; 256],
+}
+
+const TABLES: Tables = build_tables();
+
+const fn build_tables() -> Tables {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= FIELD_POLY;
+        }
+        i += 1;
+    }
+    while i < 512 {
+        exp[i] = exp[i - 255];
+        i += 1;
+    }
+    Tables { exp, log }
+}
+
+fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+    TABLES.exp[TABLES.log[a as usize] as usize + TABLES.log[b as usize] as usize]
+}
+
+fn inv(a: u8) -> u8 {
+    TABLES.exp[255 - TABLES.log[a as usize] as usize]
+}
+
+/// α^power
+fn pow_alpha(power: usize) -> u8 {
+    TABLES.exp[power % 255]
+}
+
+/// Evaluate a polynomial stored lowest degree first
+fn eval_low(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &coef| mul(acc, x) ^ coef)
+}
+
+/// Generator polynomial, highest degree first (monic)
+fn generator(parity: usize) -> Vec<u8> {
+    let mut g = vec![1u8];
+    for j in 0..parity {
+        let root = pow_alpha(j);
+        let mut next = vec![0u8; g.len() + 1];
+        for (i, &coef) in g.iter().enumerate() {
+            next[i] ^= coef;
+            next[i + 1] ^= mul(coef, root);
+        }
+        g = next;
+    }
+    g
+}
+
+/// Parity bytes of `data` (remainder of data(x)·x^parity mod g(x))
+pub(crate) fn encode_parity(data: &[u8], parity: usize) -> Vec<u8> {
+    let g = generator(parity);
+    let mut remainder = vec![0u8; parity];
+    for &byte in data {
+        let feedback = byte ^ remainder[0];
+        remainder.rotate_left(1);
+        remainder[parity - 1] = 0;
+        if feedback != 0 {
+            for (r, &coef) in remainder.iter_mut().zip(&g[1..]) {
+                *r ^= mul(coef, feedback);
+            }
+        }
+    }
+    remainder
+}
+
+/// Correct `codeword` (data then `parity` bytes, at most 255 in all) in place
+///
+/// `erasures` are positions in the codeword known to be unreliable. Returns
+/// the number of bytes changed, or `None` when the errors exceed the code.
+pub(crate) fn correct(codeword: &mut [u8], parity: usize, erasures: &[usize]) -> Option<usize> {
+    let n = codeword.len();
+    if n > 255 || parity == 0 || parity >= n {
+        return None;
+    }
+    // Byte i is the coefficient of x^(n - 1 - i)
+    let locator = |i: usize| pow_alpha(n - 1 - i);
+    let syndromes: Vec<u8> = (0..parity)
+        .map(|j| codeword.iter().fold(0, |acc, &byte| mul(acc, pow_alpha(j)) ^ byte))
+        .collect();
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    let mut erasures: Vec<usize> = erasures.iter().copied().filter(|&i| i < n).collect();
+    erasures.sort_unstable();
+    erasures.dedup();
+    if erasures.len() > parity {
+        return None;
+    }
+
+    // Erasure locator Γ(x) = Π (1 - X x), lowest degree first
+    let mut lambda = vec![1u8];
+    for &i in &erasures {
+        let x = locator(i);
+        let mut next = lambda.clone();
+        next.push(0);
+        for (k, &coef) in lambda.iter().enumerate() {
+            next[k + 1] ^= mul(coef, x);
+        }
+        lambda = next;
+    }
+
+    // Berlekamp-Massey, started from the erasure locator
+    let erased = erasures.len();
+    let mut prev = lambda.clone();
+    let mut len = erased;
+    for r in erased..parity {
+        let delta = (0..lambda.len())
+            .filter(|&k| k <= r)
+            .fold(0, |acc, k| acc ^ mul(lambda[k], syndromes[r - k]));
+        prev.insert(0, 0);
+        if delta == 0 {
+            continue;
+        }
+        let mut next = lambda.clone();
+        next.resize(next.len().max(prev.len()), 0);
+        for (k, &coef) in prev.iter().enumerate() {
+            next[k] ^= mul(delta, coef);
+        }
+        if 2 * len <= r + erased {
+            len = r + 1 + erased - len;
+            let scale = inv(delta);
+            prev = lambda.iter().map(|&coef| mul(coef, scale)).collect();
+        }
+        lambda = next;
+    }
+    while lambda.last() == Some(&0) {
+        lambda.pop();
+    }
+    let degree = lambda.len() - 1;
+    if degree != len || 2 * (degree - erased) + erased > parity {
+        return None;
+    }
+
+    // Chien search over the transmitted positions only
+    let positions: Vec<usize> = (0..n).filter(|&i| eval_low(&lambda, inv(locator(i))) == 0).collect();
+    if positions.len() != degree {
+        return None;
+    }
+
+    // Forney: Ω(x) = S(x)Λ(x) mod x^parity, e = X Ω(1/X) / Λ'(1/X)
+    let mut omega = vec![0u8; parity];
+    for (k, &coef) in lambda.iter().enumerate() {
+        for (j, &s) in syndromes.iter().enumerate().take(parity - k) {
+            omega[k + j] ^= mul(coef, s);
+        }
+    }
+    // The formal derivative keeps the odd-degree terms
+    let derivative: Vec<u8> = lambda.iter().enumerate().skip(1).map(|(k, &coef)| if k.is_multiple_of(2) { 0 } else { coef }).collect();
+    let mut changed = 0;
+    for &i in &positions {
+        let x = locator(i);
+        let x_inv = inv(x);
+        let denominator = eval_low(&derivative, x_inv);
+        if denominator == 0 {
+            return None;
+        }
+        let magnitude = mul(x, mul(eval_low(&omega, x_inv), inv(denominator)));
+        if magnitude != 0 {
+            codeword[i] ^= magnitude;
+            changed += 1;
+        }
+    }
+
+    let clean = (0..parity).all(|j| codeword.iter().fold(0, |acc, &byte| mul(acc, pow_alpha(j)) ^ byte) == 0);
+    clean.then_some(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codeword(data: &[u8], parity: usize) -> Vec<u8> {
+        let mut codeword = data.to_vec();
+        codeword.extend(encode_parity(data, parity));
+        codeword
+    }
+
+    #[test]
+    fn test_known_parity() {
+        // The parity zeroes every syndrome
+        let data: Vec<u8> = (1..=11).collect();
+        let word = codeword(&data, 4);
+        let syndromes = (0..4).map(|j| word.iter().fold(0, |acc, &b| mul(acc, pow_alpha(j)) ^ b));
+        assert!(syndromes.into_iter().all(|s| s == 0));
+        assert_eq!(generator(2), [1, 3, 2]);
+    }
+
+    #[test]
+    fn test_corrects_errors_up_to_half_parity() {
+        let data: Vec<u8> = (0..223u32).map(|i| (i * 31 + 7) as u8).collect();
+        for parity in [8, 16, 32] {
+            let clean = codeword(&data, parity);
+            let mut received = clean.clone();
+            for k in 0..parity / 2 {
+                received[k * 13 + 1] ^= 0xA5 ^ k as u8;
+            }
+            assert_eq!(correct(&mut received, parity, &[]), Some(parity / 2));
+            assert_eq!(received, clean);
+
+            let mut received = clean.clone();
+            for k in 0..=parity / 2 {
+                received[k * 11] ^= 0x3C;
+            }
+            assert!(correct(&mut received, parity, &[]).is_none() || received != clean);
+        }
+    }
+
+    #[test]
+    fn test_corrects_errors_and_erasures() {
+        let data: Vec<u8> = (0..40u8).collect();
+        let parity = 16;
+        let clean = codeword(&data, parity);
+
+        // 16 erasures, no errors
+        let mut received = clean.clone();
+        let erasures: Vec<usize> = (0..parity).map(|k| k * 3).collect();
+        for &i in &erasures {
+            received[i] = 0;
+        }
+        correct(&mut received, parity, &erasures).unwrap();
+        assert_eq!(received, clean);
+
+        // 6 erasures and 5 errors (2 * 5 + 6 = 16)
+        let mut received = clean.clone();
+        let erasures = [0, 5, 20, 41, 50, 55];
+        for &i in &erasures {
+            received[i] ^= 0xFF;
+        }
+        for i in [2, 9, 30, 44, 54] {
+            received[i] ^= 0x11;
+        }
+        assert_eq!(correct(&mut received, parity, &erasures), Some(11));
+        assert_eq!(received, clean);
+
+        // Too many erasures
+        assert_eq!(correct(&mut clean.clone(), parity, &(0..=parity).collect::<Vec<_>>()), Some(0));
+        let mut received = clean.clone();
+        received[1] ^= 1;
+        assert_eq!(correct(&mut received, parity, &(0..=parity).collect::<Vec<_>>()), None);
+    }
+
+    #[test]
+    fn test_shortened_code() {
+        // An error outside the transmitted part cannot be located
+        let data = b"short".to_vec();
+        let clean = codeword(&data, 8);
+        let mut received = clean.clone();
+        received[0] ^= 0x40;
+        received[12] ^= 0x02;
+        assert_eq!(correct(&mut received, 8, &[]), Some(2));
+        assert_eq!(received, clean);
+    }
+}
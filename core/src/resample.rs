@@ -1,5 +1,8 @@
-/// Audio resampling utility for converting between different sample rates
-/// Uses linear interpolation for quality audio resampling
+//! Audio resampling utility for converting between different sample rates
+//! Uses linear or cubic interpolation, optionally behind an anti-alias filter
+
+use crate::error::{AudioModemError, Result};
+use std::f32::consts::PI;
 
 /// Mix stereo audio to mono by averaging both channels
 ///
@@ -7,22 +10,46 @@
 /// * `samples` - Interleaved stereo audio samples [L, R, L, R, ...]
 ///
 /// # Returns
-/// Mono audio samples (averaged from both channels)
+/// Mono audio samples (averaged from both channels), or `InvalidInputSize`
+/// if the length is odd
+pub fn stereo_to_mono(samples: &[f32]) -> Result<Vec<f32>> {
+    if !samples.len().is_multiple_of(2) {
+        return Err(AudioModemError::InvalidInputSize);
+    }
+    Ok(samples.chunks_exact(2).map(|pair| (pair[0] + pair[1]) / 2.0).collect())
+}
+
+/// Interpolation between input samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResampleQuality {
+    /// Two-point linear interpolation
+    #[default]
+    Linear,
+    /// Four-point Catmull-Rom interpolation: smoother, a little slower
+    Cubic,
+}
+
+/// How many output samples a resampled buffer has
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResampleLength {
+    /// ceil(len * to / from): the whole input duration, the last input sample held at the end
+    #[default]
+    Exact,
+    /// floor(len * to / from): no output past the last input sample
+    Truncated,
+}
+
+/// Settings of `resample_audio_with` and `StreamResampler::with_options`
 ///
-/// # Panics
-/// If samples length is not even
-pub fn stereo_to_mono(samples: &[f32]) -> Vec<f32> {
-    assert!(
-        samples.len() % 2 == 0,
-        "Stereo audio must have even number of samples"
-    );
-
-    let mut mono = Vec::with_capacity(samples.len() / 2);
-    for chunk in samples.chunks(2) {
-        let averaged = (chunk[0] + chunk[1]) / 2.0;
-        mono.push(averaged);
-    }
-    mono
+/// The default (linear, no anti-alias filter, exact length) is what
+/// `resample_audio` does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResampleOptions {
+    pub quality: ResampleQuality,
+    /// Low-pass the input below the new Nyquist frequency when downsampling,
+    /// so content above it does not fold back into the band
+    pub anti_alias: bool,
+    pub length: ResampleLength,
 }
 
 /// Resample audio to a target sample rate using linear interpolation
@@ -42,31 +69,202 @@ pub fn stereo_to_mono(samples: &[f32]) -> Vec<f32> {
 /// let audio_16k = resample_audio(&audio_48k, 48000, 16000);
 /// ```
 pub fn resample_audio(samples: &[f32], from_rate: usize, to_rate: usize) -> Vec<f32> {
-    if from_rate == to_rate {
-        return samples.to_vec();
+    resample_audio_with(samples, from_rate, to_rate, ResampleOptions::default())
+}
+
+/// Resample audio with explicit interpolation, anti-alias filter and output length
+///
+/// A zero rate gives an empty result.
+///
+/// # Example
+/// ```
+/// use transmitwave_core::{resample_audio_with, ResampleOptions, ResampleQuality};
+/// let audio_48k = vec![0.0; 4800];
+/// let options = ResampleOptions { quality: ResampleQuality::Cubic, anti_alias: true, ..Default::default() };
+/// assert_eq!(resample_audio_with(&audio_48k, 48000, 16000, options).len(), 1600);
+/// ```
+pub fn resample_audio_with(samples: &[f32], from_rate: usize, to_rate: usize, options: ResampleOptions) -> Vec<f32> {
+    let mut resampler = StreamResampler::with_options(from_rate, to_rate, options);
+    let mut resampled = Vec::with_capacity((samples.len() * to_rate).div_ceil(from_rate.max(1)));
+    resampler.push(samples, &mut resampled);
+    resampler.finish(&mut resampled);
+    resampled
+}
+
+/// Zero crossings of the anti-alias filter's sinc on each side, in output samples
+const ANTI_ALIAS_ZERO_CROSSINGS: usize = 16;
+
+/// Anti-alias cutoff as a fraction of the output Nyquist frequency
+const ANTI_ALIAS_CUTOFF: f32 = 0.9;
+
+/// Blackman-windowed sinc low-pass ahead of downsampling
+///
+/// Its group delay is compensated, so filtered samples line up with the input.
+struct AntiAliasFilter {
+    taps: Vec<f32>,
+    /// Last `taps.len() - 1` input samples, then the new ones
+    history: Vec<f32>,
+    /// Filter outputs still to drop (the group delay)
+    skip: usize,
+}
+
+impl AntiAliasFilter {
+    fn new(from_rate: usize, to_rate: usize) -> Self {
+        let half = (ANTI_ALIAS_ZERO_CROSSINGS * from_rate).div_ceil(to_rate);
+        let len = 2 * half + 1;
+        // Cutoff in cycles per input sample
+        let cutoff = ANTI_ALIAS_CUTOFF * 0.5 * to_rate as f32 / from_rate as f32;
+        let mut taps: Vec<f32> = (0..len)
+            .map(|i| {
+                let x = i as f32 - half as f32;
+                let sinc = if x == 0.0 { 1.0 } else { (2.0 * PI * cutoff * x).sin() / (2.0 * PI * cutoff * x) };
+                let phase = 2.0 * PI * i as f32 / (len - 1) as f32;
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                sinc * window
+            })
+            .collect();
+        let gain: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= gain);
+        Self { taps, history: vec![0.0; len - 1], skip: half }
+    }
+
+    fn process(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        self.history.extend_from_slice(samples);
+        let outputs = self.history.len() + 1 - self.taps.len();
+        for window in self.history.windows(self.taps.len()) {
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            out.push(window.iter().zip(&self.taps).map(|(x, tap)| x * tap).sum());
+        }
+        self.history.drain(..outputs);
+    }
+
+    /// Filter outputs for the last input samples (zeros past the end)
+    fn flush(&mut self, out: &mut Vec<f32>) {
+        self.process(&vec![0.0; self.taps.len() / 2], out);
+    }
+}
+
+/// Resampler for audio that arrives in pieces
+///
+/// Gives the same output as `resample_audio_with` over the concatenated input
+/// while holding only the input samples the next output still needs.
+pub struct StreamResampler {
+    from_rate: usize,
+    to_rate: usize,
+    options: ResampleOptions,
+    anti_alias: Option<AntiAliasFilter>,
+    /// Input not yet consumed; `input[0]` is input sample number `base`
+    input: Vec<f32>,
+    base: usize,
+    /// Index of the next output sample
+    next: usize,
+}
+
+impl StreamResampler {
+    pub fn new(from_rate: usize, to_rate: usize) -> Self {
+        Self::with_options(from_rate, to_rate, ResampleOptions::default())
     }
 
-    let ratio = to_rate as f32 / from_rate as f32;
-    let new_length = ((samples.len() as f32) * ratio).ceil() as usize;
-    let mut resampled = Vec::with_capacity(new_length);
+    pub fn with_options(from_rate: usize, to_rate: usize, options: ResampleOptions) -> Self {
+        let anti_alias =
+            (options.anti_alias && to_rate > 0 && to_rate < from_rate).then(|| AntiAliasFilter::new(from_rate, to_rate));
+        Self { from_rate, to_rate, options, anti_alias, input: Vec::new(), base: 0, next: 0 }
+    }
 
-    for i in 0..new_length {
-        let src_idx = i as f32 / ratio;
-        let src_idx_floor = src_idx.floor() as usize;
-        let src_idx_ceil = src_idx_floor + 1;
-        let fraction = src_idx - (src_idx_floor as f32);
+    /// Append the output that `samples` completes to `out`
+    ///
+    /// A zero rate produces no output.
+    pub fn push(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        if self.from_rate == self.to_rate {
+            out.extend_from_slice(samples);
+            return;
+        }
+        if self.from_rate == 0 || self.to_rate == 0 {
+            return;
+        }
 
-        let interpolated = if src_idx_ceil < samples.len() {
-            // Linear interpolation
-            samples[src_idx_floor] * (1.0 - fraction) + samples[src_idx_ceil] * fraction
-        } else {
-            samples[src_idx_floor]
+        match &mut self.anti_alias {
+            Some(filter) => filter.process(samples, &mut self.input),
+            None => self.input.extend_from_slice(samples),
+        }
+        let (behind, ahead) = self.neighbours();
+        let end = self.base + self.input.len();
+        // A truncated stream ends at floor(total * to / from) and total >= end
+        let limit = match self.options.length {
+            ResampleLength::Exact => usize::MAX,
+            ResampleLength::Truncated => end * self.to_rate / self.from_rate,
         };
+        while self.next < limit {
+            let (floor, fraction) = self.source_position(self.next);
+            if floor + ahead >= end {
+                break;
+            }
+            out.push(self.interpolate(floor, fraction));
+            self.next += 1;
+        }
 
-        resampled.push(interpolated);
+        // Keep the input from the next output's leftmost neighbour on
+        let keep_from = self.source_position(self.next).0.saturating_sub(behind).max(self.base).min(end) - self.base;
+        self.input.drain(..keep_from);
+        self.base += keep_from;
     }
 
-    resampled
+    /// Append the output past the last input sample (end of the stream)
+    pub fn finish(&mut self, out: &mut Vec<f32>) {
+        if self.from_rate == self.to_rate || self.from_rate == 0 || self.to_rate == 0 {
+            return;
+        }
+
+        if let Some(filter) = &mut self.anti_alias {
+            filter.flush(&mut self.input);
+        }
+        let total = self.base + self.input.len();
+        let length = match self.options.length {
+            ResampleLength::Exact => (total * self.to_rate).div_ceil(self.from_rate),
+            ResampleLength::Truncated => total * self.to_rate / self.from_rate,
+        };
+        while self.next < length {
+            let (floor, fraction) = self.source_position(self.next);
+            out.push(self.interpolate(floor, fraction));
+            self.next += 1;
+        }
+    }
+
+    /// Input samples needed left and right of an output's left neighbour
+    fn neighbours(&self) -> (usize, usize) {
+        match self.options.quality {
+            ResampleQuality::Linear => (0, 1),
+            ResampleQuality::Cubic => (1, 2),
+        }
+    }
+
+    /// Input sample `index`, held at the edges of what is buffered
+    fn sample(&self, index: usize) -> f32 {
+        self.input[index.clamp(self.base, self.base + self.input.len() - 1) - self.base]
+    }
+
+    fn interpolate(&self, floor: usize, t: f32) -> f32 {
+        let b = self.sample(floor);
+        let c = self.sample(floor + 1);
+        match self.options.quality {
+            ResampleQuality::Linear => b * (1.0 - t) + c * t,
+            ResampleQuality::Cubic => {
+                let a = self.sample(floor.saturating_sub(1));
+                let d = self.sample(floor + 2);
+                b + 0.5 * t * (c - a + t * (2.0 * a - 5.0 * b + 4.0 * c - d + t * (3.0 * (b - c) + d - a)))
+            }
+        }
+    }
+
+    /// Input index left of output sample `index` and the interpolation fraction
+    fn source_position(&self, index: usize) -> (usize, f32) {
+        let position = index as u64 * self.from_rate as u64;
+        let to_rate = self.to_rate as u64;
+        ((position / to_rate) as usize, (position % to_rate) as f32 / to_rate as f32)
+    }
 }
 
 #[cfg(test)]
@@ -76,7 +274,7 @@ mod tests {
     #[test]
     fn test_stereo_to_mono() {
         let stereo = vec![0.2, 0.8, 0.4, 0.6]; // [L, R, L, R]
-        let mono = stereo_to_mono(&stereo);
+        let mono = stereo_to_mono(&stereo).unwrap();
         assert_eq!(mono.len(), 2);
         assert!((mono[0] - 0.5).abs() < 0.001); // (0.2 + 0.8) / 2 = 0.5
         assert!((mono[1] - 0.5).abs() < 0.001); // (0.4 + 0.6) / 2 = 0.5
@@ -85,11 +283,12 @@ mod tests {
     #[test]
     fn test_stereo_to_mono_different_values() {
         let stereo = vec![0.1, 0.3, 0.5, 0.7, -0.2, -0.4];
-        let mono = stereo_to_mono(&stereo);
+        let mono = stereo_to_mono(&stereo).unwrap();
         assert_eq!(mono.len(), 3);
         assert!((mono[0] - 0.2).abs() < 0.001);
         assert!((mono[1] - 0.6).abs() < 0.001);
         assert!((mono[2] - (-0.3)).abs() < 0.001);
+        assert!(stereo_to_mono(&stereo[..5]).is_err());
     }
 
     #[test]
@@ -129,4 +328,99 @@ mod tests {
             assert!(sample >= -1.1 && sample <= 1.1, "Sample out of range: {}", sample);
         }
     }
+
+    #[test]
+    fn test_stream_resampler_matches_resample_audio() {
+        let samples: Vec<f32> = (0..30000).map(|i| (i as f32 * 0.013).sin()).collect();
+        for (from_rate, to_rate) in [(48000, 16000), (44100, 16000), (8000, 16000), (16000, 16000)] {
+            let expected = resample_audio(&samples, from_rate, to_rate);
+
+            let mut resampler = StreamResampler::new(from_rate, to_rate);
+            let mut streamed = Vec::new();
+            for chunk in samples.chunks(1237) {
+                resampler.push(chunk, &mut streamed);
+            }
+            resampler.finish(&mut streamed);
+
+            assert_eq!(streamed.len(), expected.len(), "{} -> {}", from_rate, to_rate);
+            for (a, b) in streamed.iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-3, "{} -> {}: {} vs {}", from_rate, to_rate, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_resampler_matches_with_options() {
+        let samples: Vec<f32> = (0..20000).map(|i| (i as f32 * 0.021).sin() * 0.5).collect();
+        for quality in [ResampleQuality::Linear, ResampleQuality::Cubic] {
+            for anti_alias in [false, true] {
+                for length in [ResampleLength::Exact, ResampleLength::Truncated] {
+                    let options = ResampleOptions { quality, anti_alias, length };
+                    for (from_rate, to_rate) in [(48000, 16000), (44100, 16000), (8000, 16000)] {
+                        let expected = resample_audio_with(&samples, from_rate, to_rate, options);
+
+                        let mut resampler = StreamResampler::with_options(from_rate, to_rate, options);
+                        let mut streamed = Vec::new();
+                        for chunk in samples.chunks(997) {
+                            resampler.push(chunk, &mut streamed);
+                        }
+                        resampler.finish(&mut streamed);
+                        assert_eq!(streamed, expected, "{:?} {} -> {}", options, from_rate, to_rate);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_resample_output_length() {
+        let samples = vec![0.25; 1001];
+        let exact = resample_audio_with(&samples, 48000, 16000, ResampleOptions::default());
+        let truncated = resample_audio_with(
+            &samples,
+            48000,
+            16000,
+            ResampleOptions { length: ResampleLength::Truncated, ..Default::default() },
+        );
+        assert_eq!(exact.len(), 334);
+        assert_eq!(truncated.len(), 333);
+        assert_eq!(exact, resample_audio(&samples, 48000, 16000));
+    }
+
+    #[test]
+    fn test_cubic_interpolation_is_closer() {
+        // 1 kHz tone upsampled from 8 kHz: only 8 samples per period
+        let tone = |rate: usize, len: usize| -> Vec<f32> {
+            (0..len).map(|i| (2.0 * PI * 1000.0 * i as f32 / rate as f32).sin()).collect()
+        };
+        let expected = tone(48000, 6000);
+        let error = |quality| {
+            let options = ResampleOptions { quality, ..Default::default() };
+            let resampled = resample_audio_with(&tone(8000, 1000), 8000, 48000, options);
+            resampled[60..5940].iter().zip(&expected[60..5940]).map(|(a, b)| (a - b).powi(2)).sum::<f32>()
+        };
+        assert!(error(ResampleQuality::Cubic) < error(ResampleQuality::Linear) * 0.5);
+    }
+
+    #[test]
+    fn test_anti_alias_removes_folded_tone() {
+        // 10 kHz at 48 kHz folds to 6 kHz at 16 kHz
+        let tone: Vec<f32> = (0..48000).map(|i| (2.0 * PI * 10000.0 * i as f32 / 48000.0).sin()).collect();
+        let power = |anti_alias| {
+            let options = ResampleOptions { anti_alias, ..Default::default() };
+            let resampled = resample_audio_with(&tone, 48000, 16000, options);
+            resampled[100..15900].iter().map(|s| s * s).sum::<f32>() / 15800.0
+        };
+        assert!(power(false) > 0.1);
+        assert!(power(true) < power(false) * 0.01);
+
+        // An in-band tone passes unchanged, without delay
+        let tone: Vec<f32> = (0..48000).map(|i| (2.0 * PI * 1000.0 * i as f32 / 48000.0).sin()).collect();
+        let options = ResampleOptions { anti_alias: true, ..Default::default() };
+        let resampled = resample_audio_with(&tone, 48000, 16000, options);
+        for (i, sample) in resampled.iter().enumerate().skip(100).take(15800) {
+            let expected = (2.0 * PI * 1000.0 * i as f32 / 16000.0).sin();
+            assert!((sample - expected).abs() < 0.02, "{}: {} vs {}", i, sample, expected);
+        }
+    }
 }
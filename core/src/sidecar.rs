@@ -0,0 +1,149 @@
+//! Metadata sidecar written next to an encoded transmission
+//!
+//! A [`TransmissionSidecar`] (from `EncoderFsk::sidecar`) records the settings
+//! a receiver needs that the audio does not announce (profile, network key),
+//! the band plan and expected duration, and a CRC-32 of the payload to check
+//! a decode against. With the `serde` feature it serializes to one flat
+//! object; `transmitwave encode --sidecar` writes it as JSON and the server's
+//! `/encode` response includes it.
+
+use crate::encoder_fsk::{Airtime, FrameProfile};
+use crate::framing::ContentType;
+use crate::fsk::ToneLayout;
+use crate::FSK_MIN_FREQUENCY;
+
+/// Settings and size of one encoded frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(into = "SidecarRecord"))]
+pub struct TransmissionSidecar {
+    /// Version of the encoding crate
+    pub crate_version: &'static str,
+    pub protocol_revision: u8,
+    pub profile: FrameProfile,
+    /// Tone layout of the data symbols (dual-band included)
+    pub layout: ToneLayout,
+    pub amplitude_bits: bool,
+    pub differential: bool,
+    pub symbol_gaps: Option<u8>,
+    /// Sync signals derived from a network key (the key itself is not recorded)
+    pub network_keyed: bool,
+    pub content_type: Option<ContentType>,
+    /// RS code, blocks, symbols and samples of the frame
+    pub airtime: Airtime,
+    pub payload_len: usize,
+    pub payload_crc32: u32,
+}
+
+impl TransmissionSidecar {
+    /// Lowest data tone and the width of the occupied band (Hz)
+    pub fn band_hz(&self) -> (f32, f32) {
+        (FSK_MIN_FREQUENCY, self.layout.bandwidth_hz())
+    }
+}
+
+/// Serialized form of [`TransmissionSidecar`], e.g.
+/// `{"crate_version":"0.1.0","protocol_revision":1,"profile":"standard","rs":"255,223",...}`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct SidecarRecord {
+    crate_version: &'static str,
+    protocol_revision: u8,
+    profile: String,
+    rs: String,
+    rs_blocks: usize,
+    tones: usize,
+    bins_per_band: usize,
+    dual_band: bool,
+    band_start_hz: f32,
+    bandwidth_hz: f32,
+    tone_spacing_hz: f32,
+    amplitude_bits: bool,
+    differential: bool,
+    symbol_gaps: Option<u8>,
+    network_keyed: bool,
+    content_type: Option<String>,
+    symbols: usize,
+    samples: usize,
+    duration_ms: u64,
+    payload_bytes: usize,
+    /// Hex, as printed by `crc32` tools
+    payload_crc32: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<TransmissionSidecar> for SidecarRecord {
+    fn from(sidecar: TransmissionSidecar) -> Self {
+        let fec = sidecar.airtime.fec_mode;
+        let (band_start_hz, bandwidth_hz) = sidecar.band_hz();
+        SidecarRecord {
+            crate_version: sidecar.crate_version,
+            protocol_revision: sidecar.protocol_revision,
+            profile: sidecar.profile.to_string(),
+            rs: format!("{},{}", fec.block_bytes(), fec.data_bytes()),
+            rs_blocks: sidecar.airtime.rs_blocks,
+            tones: sidecar.layout.tones(),
+            bins_per_band: sidecar.layout.bins_per_band(),
+            dual_band: sidecar.layout.is_dual_band(),
+            band_start_hz,
+            bandwidth_hz,
+            tone_spacing_hz: sidecar.layout.tone_spacing_hz(),
+            amplitude_bits: sidecar.amplitude_bits,
+            differential: sidecar.differential,
+            symbol_gaps: sidecar.symbol_gaps,
+            network_keyed: sidecar.network_keyed,
+            content_type: sidecar.content_type.map(|content_type| content_type.to_string()),
+            symbols: sidecar.airtime.symbols,
+            samples: sidecar.airtime.samples,
+            duration_ms: sidecar.airtime.duration_ms(),
+            payload_bytes: sidecar.payload_len,
+            payload_crc32: format!("{:08x}", sidecar.payload_crc32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{crc32, ContentType, EncoderFsk, FrameProfile, NetworkKey};
+
+    #[test]
+    fn test_sidecar_describes_the_transmission() {
+        let payload = b"sidecar payload".to_vec();
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_content_type(Some(ContentType::Json));
+        encoder.set_network_key(Some(NetworkKey::new(b"site-7")));
+        let sidecar = encoder.sidecar(&payload).unwrap();
+        let samples = encoder.encode(&payload).unwrap();
+        assert_eq!(sidecar.airtime.samples, samples.len());
+        assert_eq!(sidecar.payload_crc32, crc32(&payload));
+        assert!(sidecar.network_keyed);
+
+        encoder.set_profile(FrameProfile::Short);
+        assert!(encoder.sidecar(&[0; 200]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sidecar_json() {
+        let payload = b"sidecar payload".to_vec();
+        let mut encoder = EncoderFsk::new().unwrap();
+        encoder.set_content_type(Some(ContentType::Json));
+        encoder.set_network_key(Some(NetworkKey::new(b"site-7")));
+        let json = serde_json::to_value(encoder.sidecar(&payload).unwrap()).unwrap();
+        assert_eq!(json["crate_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["protocol_revision"], 1);
+        assert_eq!(json["profile"], "standard");
+        assert_eq!((json["tones"].as_u64(), json["bins_per_band"].as_u64()), (Some(6), Some(16)));
+        assert_eq!(json["band_start_hz"], 800.0);
+        assert_eq!(json["symbol_gaps"], serde_json::Value::Null);
+        assert_eq!(json["network_keyed"], true);
+        assert_eq!(json["content_type"], "application/json");
+        assert_eq!(json["payload_bytes"], 15);
+        assert_eq!(json["payload_crc32"], format!("{:08x}", crc32(&payload)));
+
+        encoder.set_profile(FrameProfile::Telephony);
+        encoder.set_symbol_gaps(Some(8));
+        let json = serde_json::to_value(encoder.sidecar(&payload).unwrap()).unwrap();
+        assert_eq!((json["profile"].as_str(), json["rs"].as_str()), (Some("telephony"), Some("255,223")));
+        assert_eq!((json["tones"].as_u64(), json["symbol_gaps"].as_u64()), (Some(4), Some(8)));
+    }
+}
@@ -0,0 +1,219 @@
+//! Simulated speaker-to-microphone paths for examples and tests
+//!
+//! [`simulate_acoustic_path`] runs modem audio through attenuation, room
+//! reverb, the band limit of small speakers and microphones, and background
+//! noise, so settings can be tried without hardware. The noise is seeded, so
+//! the same input always gives the same output.
+
+use crate::presence::Biquad;
+use crate::rng::SplitMix64;
+use crate::SAMPLE_RATE;
+
+/// Delays of the parallel comb filters making up the reverb tail (ms, mutually prime-ish)
+const COMB_DELAYS_MS: [f32; 4] = [29.7, 37.1, 41.1, 43.7];
+
+/// Parameters of a simulated acoustic path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AcousticPath {
+    /// Level change of the direct sound in dB (negative for quieter)
+    pub gain_db: f32,
+    /// Time for the reverb tail to decay by 60 dB (0 = no reverb)
+    pub reverb_time_ms: u32,
+    /// Level of the reverb tail relative to the direct sound (linear, 0-1)
+    pub reverb_mix: f32,
+    /// Lower and upper edge of the speaker/microphone response in Hz
+    pub band_hz: (f32, f32),
+    /// Level of the background noise in dBFS (RMS)
+    pub noise_dbfs: f32,
+    /// Seed of the noise generator
+    pub seed: u64,
+}
+
+/// Typical rooms for [`simulate_acoustic_path`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoomPreset {
+    /// Laptop speaker to a phone on the same desk: little reverb, faint noise
+    QuietOffice,
+    /// Same distance with loud background chatter and a livelier room
+    NoisyCafe,
+    /// Several meters away: much quieter, long reverb tail, narrower band
+    AcrossTheRoom,
+}
+
+impl RoomPreset {
+    pub const ALL: [RoomPreset; 3] = [RoomPreset::QuietOffice, RoomPreset::NoisyCafe, RoomPreset::AcrossTheRoom];
+
+    pub fn path(self) -> AcousticPath {
+        match self {
+            RoomPreset::QuietOffice => AcousticPath {
+                gain_db: -6.0,
+                reverb_time_ms: 300,
+                reverb_mix: 0.1,
+                band_hz: (200.0, 6000.0),
+                noise_dbfs: -60.0,
+                seed: 1,
+            },
+            RoomPreset::NoisyCafe => AcousticPath {
+                gain_db: -10.0,
+                reverb_time_ms: 500,
+                reverb_mix: 0.2,
+                band_hz: (150.0, 5000.0),
+                noise_dbfs: -35.0,
+                seed: 2,
+            },
+            RoomPreset::AcrossTheRoom => AcousticPath {
+                gain_db: -20.0,
+                reverb_time_ms: 700,
+                reverb_mix: 0.3,
+                band_hz: (300.0, 4000.0),
+                noise_dbfs: -55.0,
+                seed: 3,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for RoomPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RoomPreset::QuietOffice => "quiet-office",
+            RoomPreset::NoisyCafe => "noisy-cafe",
+            RoomPreset::AcrossTheRoom => "across-the-room",
+        })
+    }
+}
+
+impl std::str::FromStr for RoomPreset {
+    type Err = crate::error::AudioModemError;
+
+    fn from_str(value: &str) -> crate::error::Result<Self> {
+        RoomPreset::ALL
+            .into_iter()
+            .find(|preset| preset.to_string() == value)
+            .ok_or_else(|| crate::error::AudioModemError::InvalidConfig(format!("unknown room preset '{}'", value)))
+    }
+}
+
+/// `samples` (at SAMPLE_RATE) as a microphone in `preset` would record them
+///
+/// The output is longer than the input by the reverb tail.
+///
+/// # Example
+/// ```
+/// use transmitwave_core::{simulate_acoustic_path, DecoderFsk, EncoderFsk, RoomPreset};
+/// let audio = EncoderFsk::new()?.encode(b"hello")?;
+/// let recorded = simulate_acoustic_path(&audio, RoomPreset::AcrossTheRoom);
+/// assert_eq!(DecoderFsk::new()?.decode(&recorded)?, b"hello");
+/// # Ok::<(), transmitwave_core::AudioModemError>(())
+/// ```
+pub fn simulate_acoustic_path(samples: &[f32], preset: RoomPreset) -> Vec<f32> {
+    simulate_acoustic_path_with(samples, &preset.path())
+}
+
+/// [`simulate_acoustic_path`] with explicit parameters
+pub fn simulate_acoustic_path_with(samples: &[f32], path: &AcousticPath) -> Vec<f32> {
+    let gain = 10f32.powf(path.gain_db / 20.0);
+    let tail = path.reverb_time_ms as usize * SAMPLE_RATE / 1000;
+    let mut output: Vec<f32> = samples.iter().map(|&x| x * gain).collect();
+    output.resize(samples.len() + if path.reverb_mix > 0.0 { tail } else { 0 }, 0.0);
+
+    if path.reverb_time_ms > 0 && path.reverb_mix > 0.0 {
+        add_reverb(&mut output, path.reverb_time_ms as f32, path.reverb_mix);
+    }
+
+    let (low, high) = path.band_hz;
+    let mut stages = [
+        Biquad::highpass(low, SAMPLE_RATE),
+        Biquad::highpass(low, SAMPLE_RATE),
+        Biquad::lowpass(high, SAMPLE_RATE),
+        Biquad::lowpass(high, SAMPLE_RATE),
+    ];
+    for x in output.iter_mut() {
+        *x = stages.iter_mut().fold(*x, |acc, stage| stage.process(acc));
+    }
+
+    // Uniform noise in [-a, a] has an RMS of a / sqrt(3)
+    let amplitude = 10f32.powf(path.noise_dbfs / 20.0) * 3f32.sqrt();
+    let mut rng = SplitMix64::new(path.seed);
+    for x in output.iter_mut() {
+        *x = (*x + amplitude * (2.0 * rng.next_f32() - 1.0)).clamp(-1.0, 1.0);
+    }
+    output
+}
+
+/// Mix in the tail of parallel feedback combs (Schroeder reverb without the all-passes)
+fn add_reverb(samples: &mut [f32], reverb_time_ms: f32, mix: f32) {
+    let dry = samples.to_vec();
+    for delay_ms in COMB_DELAYS_MS {
+        let delay = (delay_ms * SAMPLE_RATE as f32 / 1000.0) as usize;
+        // Feedback that decays 60 dB over the reverb time
+        let feedback = 10f32.powf(-3.0 * delay_ms / reverb_time_ms);
+        let mut comb = vec![0.0f32; samples.len()];
+        for i in delay..samples.len() {
+            comb[i] = dry[i - delay] + feedback * comb[i - delay];
+        }
+        // Scale each comb so the tail's energy stays near `mix²` of the dry signal
+        let scale = mix * (1.0 - feedback * feedback).sqrt() / (COMB_DELAYS_MS.len() as f32).sqrt();
+        for (sample, wet) in samples.iter_mut().zip(&comb) {
+            *sample += scale * wet;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderFsk, EncoderFsk};
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_presets_decode() {
+        let audio = EncoderFsk::new().unwrap().encode(b"room check").unwrap();
+        for preset in RoomPreset::ALL {
+            let recorded = simulate_acoustic_path(&audio, preset);
+            assert!(recorded.len() > audio.len());
+            assert_eq!(recorded, simulate_acoustic_path(&audio, preset), "{} is not deterministic", preset);
+            let decoded = DecoderFsk::new().unwrap().decode(&recorded);
+            assert_eq!(decoded.unwrap(), b"room check", "{}", preset);
+            assert_eq!(preset.to_string().parse::<RoomPreset>().unwrap(), preset);
+        }
+        assert!("library".parse::<RoomPreset>().is_err());
+    }
+
+    #[test]
+    fn test_path_components() {
+        let tone = |freq: f32| -> Vec<f32> {
+            (0..SAMPLE_RATE).map(|n| 0.5 * (2.0 * std::f32::consts::PI * freq * n as f32 / SAMPLE_RATE as f32).sin()).collect()
+        };
+        let dry = AcousticPath {
+            gain_db: -20.0,
+            reverb_time_ms: 0,
+            reverb_mix: 0.0,
+            band_hz: (300.0, 4000.0),
+            noise_dbfs: -120.0,
+            seed: 0,
+        };
+        let level = |path: &AcousticPath, freq: f32| {
+            let out = simulate_acoustic_path_with(&tone(freq), path);
+            20.0 * (rms(&out[SAMPLE_RATE / 4..SAMPLE_RATE]) / rms(&tone(freq))).log10()
+        };
+        assert!((level(&dry, 1000.0) + 20.0).abs() < 1.0);
+        assert!(level(&dry, 7000.0) < -35.0);
+        assert!(level(&dry, 80.0) < -40.0);
+
+        // Noise alone lands at the requested level
+        let silence = vec![0.0f32; SAMPLE_RATE];
+        let noisy = simulate_acoustic_path_with(&silence, &AcousticPath { noise_dbfs: -40.0, ..dry });
+        let noise_db = 20.0 * rms(&noisy).log10();
+        assert!((noise_db + 40.0).abs() < 1.0, "noise at {} dBFS", noise_db);
+
+        // The reverb tail rings on after the input stops
+        let wet = AcousticPath { reverb_time_ms: 500, reverb_mix: 0.3, ..dry };
+        let out = simulate_acoustic_path_with(&tone(1000.0), &wet);
+        assert_eq!(out.len(), SAMPLE_RATE + SAMPLE_RATE / 2);
+        assert!(rms(&out[SAMPLE_RATE..SAMPLE_RATE + 800]) > 0.002);
+    }
+}
@@ -0,0 +1,194 @@
+//! Receiver squelch for always-listening deployments
+//!
+//! Noise that reaches the frame checks passes the header CRC-8, the FEC mode
+//! byte and the payload CRC-16 about once in 2^32 tries, so a receiver
+//! listening around the clock eventually hands out garbage. A [`Squelch`] (see `DecoderFsk::set_squelch`) skips weak
+//! preamble candidates, rejects frames whose tones barely stand out of their
+//! bands before the RS decoder runs, and re-checks decoded RS blocks: a block
+//! that needed most of the code's correction capacity is more likely noise
+//! the decoder "corrected" into a codeword than a transmission.
+//! [`Squelch::for_false_positive_rate`] picks the gates for a target rate.
+
+use crate::error::{AudioModemError, Result};
+use crate::fec::FecMode;
+use crate::RS_TOTAL_BYTES;
+
+/// Preamble correlation the squelch asks for once it is enabled by a rate target
+const SQUELCH_PREAMBLE_SCORE: f32 = 0.35;
+
+/// Tone-to-band SNR the squelch asks for once it is enabled by a rate target;
+/// in pure noise the strongest of 16 bins sits about 6 dB over the rest
+const SQUELCH_SNR_DB: f32 = 8.0;
+
+/// Frame decodes tried per preamble candidate (timing offsets and length
+/// readings), each trying every FEC code
+const ATTEMPTS_PER_CANDIDATE: f64 = 16.0;
+
+/// Chance that a random decoded header passes its CRC-8 and names the FEC
+/// code it was decoded with
+const HEADER_PASS_RATE: f64 = 1.0 / 65536.0;
+
+/// Chance that a random payload passes its CRC-16
+const PAYLOAD_PASS_RATE: f64 = 1.0 / 65536.0;
+
+/// Gates a decoder applies before and after decoding a frame; all open by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Squelch {
+    /// Lowest preamble correlation (0-1) a candidate needs to be decoded
+    pub min_preamble_score: f32,
+    /// Lowest tone-to-band SNR in dB of the demodulated symbols
+    pub min_snr_db: Option<f32>,
+    /// Share (0-1) of an RS block's correction capacity (parity / 2 bytes)
+    /// that a decoded block may use; every corrected byte counts, erased or not
+    pub max_correction_ratio: Option<f32>,
+}
+
+impl Default for Squelch {
+    fn default() -> Self {
+        Self { min_preamble_score: 0.0, min_snr_db: None, max_correction_ratio: None }
+    }
+}
+
+impl Squelch {
+    /// Gates keeping [`estimated_false_positive_rate`](Self::estimated_false_positive_rate)
+    /// at or below `rate`, false decodes per preamble candidate
+    ///
+    /// Targets above the rate of an open squelch leave it open. Fails with
+    /// `InvalidConfig` for rates outside (0, 1) or below what the gates reach.
+    pub fn for_false_positive_rate(rate: f64) -> Result<Self> {
+        if rate.is_nan() || rate <= 0.0 || rate >= 1.0 {
+            return Err(AudioModemError::InvalidConfig(format!("false positive rate {} must be in (0, 1)", rate)));
+        }
+        let open = Self::default();
+        if open.estimated_false_positive_rate() <= rate {
+            return Ok(open);
+        }
+        let capacity = FecMode::Light.parity_bytes() / 2;
+        (0..capacity)
+            .rev()
+            .map(|corrections| Self {
+                min_preamble_score: SQUELCH_PREAMBLE_SCORE,
+                min_snr_db: Some(SQUELCH_SNR_DB),
+                max_correction_ratio: Some(corrections as f32 / capacity as f32),
+            })
+            .find(|squelch| squelch.estimated_false_positive_rate() <= rate)
+            .ok_or_else(|| {
+                AudioModemError::InvalidConfig(format!("false positive rate {} is below what the squelch reaches", rate))
+            })
+    }
+
+    /// Upper bound on false decodes per preamble candidate found in noise
+    ///
+    /// Sums, over the preset and custom codes the decoder tries, the chance
+    /// that a full-length block of noise lies within the correction limit of
+    /// a codeword, then applies the header and payload checks. The preamble
+    /// and SNR gates only lower the rate further. Erasures from
+    /// `DecoderFsk::set_unreliable_bins` widen the decoding radius and are not
+    /// counted; only the correction limit bounds them.
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let decodes: f64 = [FecMode::Light, FecMode::Medium, FecMode::Full]
+            .into_iter()
+            .chain(FecMode::custom_modes())
+            .map(|mode| {
+                let corrections = self.max_corrections(mode).unwrap_or(mode.parity_bytes() / 2);
+                random_word_decodes(RS_TOTAL_BYTES, mode.parity_bytes(), corrections)
+            })
+            .sum();
+        ATTEMPTS_PER_CANDIDATE * decodes * HEADER_PASS_RATE * PAYLOAD_PASS_RATE
+    }
+
+    /// Whether a preamble candidate scoring `score` is decoded
+    pub fn admits_preamble(&self, score: f32) -> bool {
+        score >= self.min_preamble_score
+    }
+
+    /// Whether symbols demodulated at `snr_db` are decoded
+    pub fn admits_snr(&self, snr_db: f32) -> bool {
+        self.min_snr_db.is_none_or(|min| snr_db >= min)
+    }
+
+    /// Corrected bytes an RS block of `mode` may have; None without a limit
+    pub fn max_corrections(&self, mode: FecMode) -> Option<usize> {
+        let capacity = mode.parity_bytes() / 2;
+        self.max_correction_ratio.map(|ratio| (ratio.clamp(0.0, 1.0) * capacity as f32).floor() as usize)
+    }
+}
+
+/// Chance that a random `n`-byte word lies within `corrections` byte errors of
+/// a codeword of an RS code with `parity` parity bytes, i.e. that the
+/// bounded-distance decoder accepts it
+fn random_word_decodes(n: usize, parity: usize, corrections: usize) -> f64 {
+    // Words within the radius of one codeword: sum of C(n, i) * 255^i. Both it
+    // and 256^parity overflow f64 for long custom codes, so work in logs.
+    let mut ln_terms = Vec::with_capacity(corrections + 1);
+    let mut ln_words = 0.0;
+    for errors in 0..=corrections {
+        if errors > 0 {
+            ln_words += ((n - errors + 1) as f64 / errors as f64 * 255.0).ln();
+        }
+        ln_terms.push(ln_words);
+    }
+    let ln_max = ln_terms.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let ln_ball = ln_max + ln_terms.iter().map(|ln_term| (ln_term - ln_max).exp()).sum::<f64>().ln();
+    (ln_ball - parity as f64 * 256f64.ln()).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderFsk, EncoderFsk, FSK_BYTES_PER_SYMBOL, FSK_SYMBOL_SAMPLES};
+
+    #[test]
+    fn test_rate_target_tunes_gates() {
+        let open = Squelch::default();
+        let baseline = open.estimated_false_positive_rate();
+        assert!(baseline > 1e-9 && baseline < 1e-8, "{}", baseline);
+        // The longest custom codes overflow f64 outside the log domain
+        let strongest = random_word_decodes(RS_TOTAL_BYTES, 238, 119);
+        assert!(strongest.is_finite() && strongest < 1e-100, "{:e}", strongest);
+        assert_eq!(Squelch::for_false_positive_rate(1e-6).unwrap(), open);
+        assert_eq!(open.max_corrections(FecMode::Full), None);
+
+        let mut previous = baseline;
+        for rate in [1e-9, 1e-12, 1e-13] {
+            let squelch = Squelch::for_false_positive_rate(rate).unwrap();
+            let estimate = squelch.estimated_false_positive_rate();
+            assert!(estimate <= rate && estimate <= previous, "{:e}: {:?}", rate, squelch);
+            assert!(squelch.min_snr_db.is_some() && squelch.min_preamble_score > 0.0);
+            previous = estimate;
+        }
+        assert_eq!(Squelch::for_false_positive_rate(1e-13).unwrap().max_corrections(FecMode::Light), Some(1));
+
+        for rate in [0.0, 1.0, f64::NAN, 1e-16] {
+            assert!(matches!(Squelch::for_false_positive_rate(rate), Err(AudioModemError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn test_squelch_gates_decoder() {
+        let payload: Vec<u8> = (0..40).map(|i| (i * 71 + 9) as u8).collect();
+        let audio = EncoderFsk::new().unwrap().encode(&payload).unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_squelch(Squelch::for_false_positive_rate(1e-12).unwrap());
+        assert_eq!(decoder.decode(&audio).unwrap(), payload);
+        assert_eq!(decoder.stats.squelched, 0);
+
+        // A symbol replaced by the next one: RS corrects it, unless corrections are capped
+        let data_start = decoder.decode_with_metadata(&audio).unwrap().data_start;
+        let mut damaged = audio.clone();
+        let symbol = data_start + 4 * FSK_SYMBOL_SAMPLES;
+        damaged.copy_within(symbol + FSK_SYMBOL_SAMPLES..symbol + 2 * FSK_SYMBOL_SAMPLES, symbol);
+        decoder.set_squelch(Squelch::default());
+        assert_eq!(decoder.decode(&damaged).unwrap(), payload);
+        assert!((1..=FSK_BYTES_PER_SYMBOL as u32).contains(&decoder.stats.corrected_bytes), "{:?}", decoder.stats);
+        decoder.set_squelch(Squelch { max_correction_ratio: Some(0.0), ..Squelch::default() });
+        assert!(matches!(decoder.decode(&damaged), Err(AudioModemError::Squelched)));
+        assert!(decoder.stats.squelched > 0);
+
+        // Preamble and SNR gates
+        decoder.set_squelch(Squelch { min_snr_db: Some(100.0), ..Squelch::default() });
+        assert!(matches!(decoder.decode(&audio), Err(AudioModemError::Squelched)));
+        decoder.set_squelch(Squelch { min_preamble_score: 1.5, ..Squelch::default() });
+        assert!(matches!(decoder.decode(&audio), Err(AudioModemError::Squelched)));
+    }
+}
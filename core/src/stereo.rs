@@ -0,0 +1,166 @@
+//! Stereo output with a redundant copy of the signal on the right channel
+//!
+//! Stereo-only playback paths (or ones that apply effects to a single channel)
+//! get a second, differently-impaired copy of every frame at no extra airtime.
+//! The receiver undoes the transform on the right channel, phase-aligns it to
+//! the left and averages the two.
+
+use crate::trig;
+use crate::SAMPLE_RATE;
+use std::f32::consts::PI;
+
+/// Taps of the FIR Hilbert transformer used for frequency shifting (odd, linear phase)
+const HILBERT_TAPS: usize = 127;
+
+/// How the right channel differs from the left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoRedundancy {
+    /// Right channel is the same signal delayed by this many samples
+    Delayed(usize),
+    /// Right channel is the signal shifted up by this many Hz (single sideband)
+    FrequencyOffset(f32),
+}
+
+impl StereoRedundancy {
+    /// Delayed copy, with the delay given in milliseconds
+    pub fn delayed_ms(delay_ms: u32) -> Self {
+        StereoRedundancy::Delayed(delay_ms as usize * SAMPLE_RATE / 1000)
+    }
+}
+
+/// Split mono modem audio into left/right channels carrying redundant copies
+///
+/// Both channels have the same length; a delayed copy extends the output by the delay.
+pub fn to_stereo(samples: &[f32], redundancy: StereoRedundancy) -> (Vec<f32>, Vec<f32>) {
+    match redundancy {
+        StereoRedundancy::Delayed(delay) => {
+            let mut left = samples.to_vec();
+            left.resize(samples.len() + delay, 0.0);
+            let mut right = vec![0.0; delay];
+            right.extend_from_slice(samples);
+            (left, right)
+        }
+        StereoRedundancy::FrequencyOffset(offset_hz) => {
+            (samples.to_vec(), frequency_shift(samples, offset_hz))
+        }
+    }
+}
+
+/// Combine left/right channels produced by [`to_stereo`] back into one mono signal
+///
+/// The right channel is restored, phase-aligned to the left (a frequency shift
+/// leaves a constant phase rotation that depends on where the recording starts)
+/// and averaged with it.
+pub fn combine_stereo(left: &[f32], right: &[f32], redundancy: StereoRedundancy) -> Vec<f32> {
+    let restored = restore_right(right, redundancy);
+    let len = left.len().min(restored.len());
+    let (left, restored) = (&left[..len], &restored[..len]);
+
+    // Rotation of the analytic signal that best matches the left channel:
+    // corr(phi) = A cos(phi) - B sin(phi), maximized at phi = atan2(-B, A)
+    let quadrature = hilbert(restored);
+    let a: f32 = left.iter().zip(restored).map(|(l, r)| l * r).sum();
+    let b: f32 = left.iter().zip(&quadrature).map(|(l, q)| l * q).sum();
+    let phi = (-b).atan2(a);
+    let (sin, cos) = phi.sin_cos();
+
+    left.iter()
+        .zip(restored.iter().zip(&quadrature))
+        .map(|(l, (r, q))| 0.5 * (l + r * cos - q * sin))
+        .collect()
+}
+
+/// Undo the right-channel transform so it lines up with the left channel
+pub fn restore_right(right: &[f32], redundancy: StereoRedundancy) -> Vec<f32> {
+    match redundancy {
+        StereoRedundancy::Delayed(delay) => right.get(delay..).unwrap_or(&[]).to_vec(),
+        StereoRedundancy::FrequencyOffset(offset_hz) => frequency_shift(right, -offset_hz),
+    }
+}
+
+/// Single-sideband frequency shift: Re[(x + jH{x}) * e^(j*2*pi*f*t)]
+fn frequency_shift(samples: &[f32], offset_hz: f32) -> Vec<f32> {
+    let quadrature = hilbert(samples);
+    let omega = 2.0 * PI * offset_hz / SAMPLE_RATE as f32;
+    samples
+        .iter()
+        .zip(&quadrature)
+        .enumerate()
+        .map(|(i, (x, h))| {
+            // Wrap the phase to keep f32 precision on long signals
+            let phase = (omega * i as f32) % (2.0 * PI);
+            x * trig::cos(phase) - h * trig::sin(phase)
+        })
+        .collect()
+}
+
+/// Hilbert transform via a Hamming-windowed FIR, centered so the output is not delayed
+fn hilbert(samples: &[f32]) -> Vec<f32> {
+    let half = (HILBERT_TAPS / 2) as isize;
+    let taps: Vec<f32> = (-half..=half)
+        .map(|k| {
+            if k % 2 == 0 {
+                return 0.0;
+            }
+            let n = (k + half) as f32;
+            let window = 0.54 - 0.46 * trig::cos(2.0 * PI * n / (HILBERT_TAPS - 1) as f32);
+            2.0 / (PI * k as f32) * window
+        })
+        .collect();
+
+    (0..samples.len() as isize)
+        .map(|i| {
+            taps.iter()
+                .enumerate()
+                .filter(|(_, t)| **t != 0.0)
+                .filter_map(|(j, t)| {
+                    let index = i - (j as isize - half);
+                    samples.get(usize::try_from(index).ok()?).map(|s| s * t)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f32, len: usize, phase: f32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * PI * freq * i as f32 / SAMPLE_RATE as f32 + phase).sin())
+            .collect()
+    }
+
+    fn max_error(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_frequency_shift_moves_tone() {
+        let shifted = frequency_shift(&tone(1000.0, 4000, 0.0), 150.0);
+        let expected = tone(1150.0, 4000, 0.0);
+        // Ignore the FIR edges
+        assert!(max_error(&shifted[200..3800], &expected[200..3800]) < 0.02);
+    }
+
+    #[test]
+    fn test_delayed_roundtrip() {
+        let signal = tone(1200.0, 2000, 0.3);
+        let (left, right) = to_stereo(&signal, StereoRedundancy::delayed_ms(5));
+        assert_eq!(left.len(), 2080);
+        assert_eq!(right.len(), 2080);
+
+        let combined = combine_stereo(&left, &right, StereoRedundancy::Delayed(80));
+        assert!(max_error(&combined[200..1800], &signal[200..1800]) < 0.02);
+    }
+
+    #[test]
+    fn test_frequency_offset_combines_after_trimming() {
+        let signal = tone(1500.0, 6000, 0.0);
+        let (left, right) = to_stereo(&signal, StereoRedundancy::FrequencyOffset(37.0));
+        // A recording that starts mid-signal leaves the restored copy phase-rotated
+        let combined = combine_stereo(&left[1234..], &right[1234..], StereoRedundancy::FrequencyOffset(37.0));
+        assert!(max_error(&combined[200..4000], &signal[1434..5234]) < 0.03);
+    }
+}
@@ -0,0 +1,339 @@
+//! Real-time receive helpers: incremental frame decoding and carrier sense
+//!
+//! Both take audio in arbitrary chunks (e.g. straight from a microphone
+//! callback) at SAMPLE_RATE.
+
+use crate::decoder_fsk::{DecodedMessage, DecoderFsk};
+use crate::error::{AudioModemError, Result};
+use crate::events::DecodeEvent;
+use crate::noise_floor::NoiseFloorEstimator;
+use crate::sync::{detect_keyed_postamble, detect_keyed_preamble};
+use crate::units::{self, Millis};
+use crate::{POSTAMBLE_SAMPLES, PREAMBLE_SAMPLES, SAMPLE_RATE, SYNC_SILENCE_SAMPLES};
+
+/// Default limit on how long a frame may take to arrive after its preamble (30 s)
+pub const DEFAULT_MAX_FRAME_SAMPLES: usize = 30 * SAMPLE_RATE;
+
+/// Decoder that accepts audio in chunks and emits frames as soon as they complete
+///
+/// Audio is buffered until a preamble and a following postamble are both
+/// present, then the frame is decoded and the buffer is drained past it.
+/// A frame that still fails to decode once `max_frame_samples` have arrived
+/// after its preamble is dropped so garbage cannot stall the stream.
+/// Progress is reported as [`DecodeEvent`]s if the decoder has an event sender.
+/// Adaptive thresholds come from a [`NoiseFloorEstimator`] fed with every chunk
+/// rather than from rescanning the buffer.
+pub struct StreamingDecoder {
+    decoder: DecoderFsk,
+    buffer: Vec<f32>,
+    /// Stream position of buffer[0]
+    buffer_start: usize,
+    max_frame_samples: usize,
+    /// Stream position of the last preamble reported as an event
+    announced_preamble: Option<usize>,
+    /// Why the pending frame last failed to decode
+    last_error: Option<AudioModemError>,
+    noise_floor: NoiseFloorEstimator,
+}
+
+impl StreamingDecoder {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            decoder: DecoderFsk::new()?,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            max_frame_samples: DEFAULT_MAX_FRAME_SAMPLES,
+            announced_preamble: None,
+            last_error: None,
+            noise_floor: NoiseFloorEstimator::new(),
+        })
+    }
+
+    /// Set the longest expected frame, preamble to postamble, in samples
+    pub fn set_max_frame_samples(&mut self, max_frame_samples: usize) {
+        self.max_frame_samples = max_frame_samples.max(PREAMBLE_SAMPLES + POSTAMBLE_SAMPLES);
+    }
+
+    /// Access the underlying decoder, e.g. to change detection thresholds or the network key
+    pub fn decoder_mut(&mut self) -> &mut DecoderFsk {
+        &mut self.decoder
+    }
+
+    /// Signal level and noise floor of the audio pushed so far
+    pub fn noise_floor(&self) -> &NoiseFloorEstimator {
+        &self.noise_floor
+    }
+
+    /// Samples currently held while waiting for a frame to complete
+    pub fn buffered_samples(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Drop buffered audio, e.g. while the local speaker is playing
+    pub fn reset(&mut self) {
+        self.buffer_start += self.buffer.len();
+        self.buffer.clear();
+    }
+
+    /// Advance the stream position past `count` samples that were never
+    /// captured, e.g. while the microphone was powered down
+    pub fn skip(&mut self, count: usize) {
+        self.reset();
+        self.buffer_start += count;
+    }
+
+    /// True from a preamble's detection until its frame decodes or is dropped
+    pub fn frame_pending(&self) -> bool {
+        self.announced_preamble == Some(self.buffer_start)
+    }
+
+    /// Feed audio and return the frames completed by it
+    ///
+    /// `preamble_position` and `data_start` of the returned messages are stream
+    /// positions (samples since the decoder was created).
+    pub fn push(&mut self, samples: &[f32]) -> Vec<DecodedMessage> {
+        self.buffer.extend_from_slice(samples);
+        self.noise_floor.push(samples);
+        let mut messages = Vec::new();
+
+        while self.buffer.len() >= PREAMBLE_SAMPLES {
+            let threshold = self.noise_floor.resolve(self.decoder.get_preamble_threshold());
+            let postamble_threshold = self.noise_floor.resolve(self.decoder.get_postamble_threshold());
+            let key = self.decoder.network_key();
+            let Some(preamble_pos) = detect_keyed_preamble(&self.buffer, threshold, key) else {
+                // Keep a preamble's worth of audio in case one is arriving
+                self.drain(self.buffer.len() - PREAMBLE_SAMPLES);
+                break;
+            };
+            self.drain(preamble_pos);
+            if self.announced_preamble != Some(self.buffer_start) {
+                self.announced_preamble = Some(self.buffer_start);
+                self.last_error = None;
+                self.decoder.emit(DecodeEvent::PreambleDetected { position: self.buffer_start });
+            }
+
+            let data_start = PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+            let frame_end = self
+                .buffer
+                .get(data_start..)
+                .and_then(|rest| detect_keyed_postamble(rest, postamble_threshold, key))
+                .map(|pos| data_start + pos + POSTAMBLE_SAMPLES)
+                .filter(|&end| end <= self.buffer.len());
+
+            if let Some(frame_end) = frame_end {
+                match self.decoder.decode_with_metadata(&self.buffer[..frame_end]) {
+                    Ok(mut message) => {
+                        message.preamble_position += self.buffer_start;
+                        message.data_start += self.buffer_start;
+                        message.data_end += self.buffer_start;
+                        self.decoder.emit(DecodeEvent::FrameDecoded {
+                            position: message.preamble_position,
+                            payload_len: message.payload.len(),
+                        });
+                        messages.push(message);
+                        self.drain(frame_end);
+                        continue;
+                    }
+                    Err(e) => self.last_error = Some(e),
+                }
+            }
+
+            // Incomplete (or a false postamble inside the data): wait for more audio,
+            // unless this preamble has been pending for too long
+            if self.buffer.len() > self.max_frame_samples {
+                let error = self.last_error.take().unwrap_or(AudioModemError::PostambleNotFound);
+                self.decoder.emit(DecodeEvent::FrameFailed { position: self.buffer_start, error });
+                self.drain(PREAMBLE_SAMPLES);
+                continue;
+            }
+            break;
+        }
+
+        messages
+    }
+
+    fn drain(&mut self, count: usize) {
+        self.buffer.drain(..count);
+        self.buffer_start += count;
+    }
+}
+
+/// Window over which carrier sense measures the channel level (50 ms)
+const CARRIER_WINDOW_SAMPLES: usize = SAMPLE_RATE / 20;
+
+/// How far above the noise floor the channel counts as busy
+const CARRIER_BUSY_MARGIN_DB: f32 = 10.0;
+
+/// Absolute level below which the channel is always considered clear
+const CARRIER_MIN_BUSY_DB: f32 = -50.0;
+
+/// Energy-based carrier sense with a tracked noise floor and hangover
+///
+/// The channel is busy while the short-term level is well above the noise
+/// floor, and stays busy for the hangover time after it drops, so the gaps
+/// between a frame's preamble, data and postamble do not read as clear.
+pub struct CarrierSense {
+    window_energy: f32,
+    window_len: usize,
+    level_db: f32,
+    noise_floor_db: f32,
+    hangover_samples: usize,
+    quiet_samples: usize,
+}
+
+impl CarrierSense {
+    /// Create a carrier sense with the given hangover
+    pub fn new(hangover: Millis) -> Self {
+        let hangover_samples = units::MODEM_RATE.samples(hangover).get();
+        Self {
+            window_energy: 0.0,
+            window_len: 0,
+            level_db: f32::NEG_INFINITY,
+            noise_floor_db: CARRIER_MIN_BUSY_DB - CARRIER_BUSY_MARGIN_DB,
+            hangover_samples,
+            quiet_samples: hangover_samples,
+        }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            self.window_energy += sample * sample;
+            self.window_len += 1;
+            if self.window_len < CARRIER_WINDOW_SAMPLES {
+                continue;
+            }
+
+            let rms = (self.window_energy / self.window_len as f32).sqrt();
+            self.level_db = 20.0 * rms.max(1e-9).log10();
+            self.window_energy = 0.0;
+            self.window_len = 0;
+
+            // Floor drops quickly and rises slowly, so it tracks the quiet gaps
+            self.noise_floor_db = if self.level_db < self.noise_floor_db {
+                self.level_db
+            } else {
+                self.noise_floor_db + 0.005 * (self.level_db - self.noise_floor_db)
+            };
+
+            if self.window_is_busy() {
+                self.quiet_samples = 0;
+            } else {
+                self.quiet_samples = self.quiet_samples.saturating_add(CARRIER_WINDOW_SAMPLES);
+            }
+        }
+    }
+
+    fn window_is_busy(&self) -> bool {
+        self.level_db > CARRIER_MIN_BUSY_DB && self.level_db > self.noise_floor_db + CARRIER_BUSY_MARGIN_DB
+    }
+
+    /// True while a signal is on the channel or was within the hangover time
+    pub fn is_busy(&self) -> bool {
+        self.quiet_samples < self.hangover_samples
+    }
+
+    /// How long the channel has been clear, in samples
+    pub fn clear_samples(&self) -> usize {
+        self.quiet_samples
+    }
+
+    /// Level of the most recent measurement window in dBFS
+    pub fn level_db(&self) -> f32 {
+        self.level_db
+    }
+
+    pub fn noise_floor_db(&self) -> f32 {
+        self.noise_floor_db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EncoderFsk;
+
+    #[test]
+    fn test_streaming_decoder_across_chunks() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut audio = vec![0.0f32; 5000];
+        audio.extend(encoder.encode(b"first").unwrap());
+        let second_start = audio.len();
+        audio.extend(encoder.encode(b"second").unwrap());
+        audio.extend(vec![0.0f32; 3000]);
+
+        let mut decoder = StreamingDecoder::new().unwrap();
+        let mut messages = Vec::new();
+        for chunk in audio.chunks(1600) {
+            messages.extend(decoder.push(chunk));
+        }
+
+        let payloads: Vec<&[u8]> = messages.iter().map(|m| m.payload.as_slice()).collect();
+        assert_eq!(payloads, vec![&b"first"[..], &b"second"[..]]);
+        // Positions are relative to the whole stream
+        assert!(messages[1].preamble_position >= second_start);
+        assert!(messages[1].preamble_position < second_start + 2 * SYNC_SILENCE_SAMPLES);
+        assert!(decoder.buffered_samples() < audio.len() - second_start);
+    }
+
+    #[test]
+    fn test_streaming_decoder_drops_stalled_frame() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let frame = encoder.encode(b"cut short").unwrap();
+
+        let mut decoder = StreamingDecoder::new().unwrap();
+        decoder.set_max_frame_samples(frame.len() / 2);
+        // Only the first half of the frame arrives, followed by silence
+        assert!(decoder.push(&frame[..frame.len() / 2]).is_empty());
+        assert!(decoder.push(&vec![0.0f32; frame.len()]).is_empty());
+        assert!(decoder.buffered_samples() <= frame.len() / 2 + PREAMBLE_SAMPLES);
+
+        assert_eq!(decoder.push(&frame)[0].payload, b"cut short");
+    }
+
+    #[test]
+    fn test_streaming_decoder_events() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let frame = encoder.encode(b"events").unwrap();
+        let mut audio = vec![0.0f32; 5000];
+        audio.extend(&frame);
+        audio.extend(vec![0.0f32; 3000]);
+
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut decoder = StreamingDecoder::new().unwrap();
+        decoder.decoder_mut().set_event_sender(Some(sender));
+        for chunk in audio.chunks(1600) {
+            decoder.push(chunk);
+        }
+        // A frame cut short is reported as failed once it stalls
+        decoder.set_max_frame_samples(frame.len() / 2);
+        decoder.push(&frame[..frame.len() / 2]);
+        decoder.push(&vec![0.0f32; frame.len()]);
+
+        let events: Vec<DecodeEvent> = events.try_iter().collect();
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind()).collect();
+        assert_eq!(kinds, vec!["preamble", "frame", "preamble", "frame_failed"]);
+        assert!(matches!(events[1], DecodeEvent::FrameDecoded { payload_len: 6, .. }));
+        assert_eq!(events[0].position(), events[1].position());
+        assert!(events[0].position() >= 5000 && events[0].position() < 5000 + 2 * SYNC_SILENCE_SAMPLES);
+        assert_eq!(events[2].position(), events[3].position());
+        assert!(events[2].position() >= audio.len());
+    }
+
+    #[test]
+    fn test_carrier_sense_busy_with_hangover() {
+        let mut sense = CarrierSense::new(Millis(200));
+        sense.push(&vec![0.0005f32; SAMPLE_RATE / 2]);
+        assert!(!sense.is_busy());
+
+        let frame = EncoderFsk::new().unwrap().encode(b"busy").unwrap();
+        sense.push(&frame[..frame.len() / 2]);
+        assert!(sense.is_busy());
+
+        // Still busy shortly after the signal stops, clear after the hangover
+        sense.push(&vec![0.0005f32; SAMPLE_RATE / 10]);
+        assert!(sense.is_busy());
+        sense.push(&vec![0.0005f32; SAMPLE_RATE / 5]);
+        assert!(!sense.is_busy());
+        assert!(sense.clear_samples() >= SAMPLE_RATE / 5);
+    }
+}
@@ -0,0 +1,167 @@
+//! Per-symbol record of the demodulator's tone decisions
+//!
+//! With [`DecoderFsk::set_symbol_audit`](crate::DecoderFsk::set_symbol_audit)
+//! enabled, every data symbol of the last frame tried is kept with the tone
+//! chosen in each band and the power of all candidate tones, so offline
+//! tooling can look for systematic bias (e.g. bins 7 and 8 always confused).
+//! Amplitude pilots and the probes of the length prefix are not recorded.
+
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+/// Decisions shared between a decoder and the demodulators it creates
+pub(crate) type SymbolAuditSink = Arc<Mutex<Vec<SymbolDecision>>>;
+
+/// Tone decisions of one FSK symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolDecision {
+    /// Candidate tones per band in the symbol's tone layout
+    pub bins_per_band: usize,
+    /// Chosen tone of each band (its offset within the band)
+    pub selected: Vec<u8>,
+    /// Power of every candidate tone, band after band
+    pub energies: Vec<f32>,
+    /// Bands decided as erasures (bit `band`)
+    pub erased: u8,
+}
+
+impl SymbolDecision {
+    pub fn bands(&self) -> usize {
+        self.selected.len()
+    }
+
+    /// Power of the candidate tones of `band`
+    pub fn band_energies(&self, band: usize) -> &[f32] {
+        &self.energies[band * self.bins_per_band..(band + 1) * self.bins_per_band]
+    }
+
+    pub fn is_erased(&self, band: usize) -> bool {
+        self.erased & (1 << band) != 0
+    }
+}
+
+/// Tone decisions of a frame, in symbol order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolAuditLog {
+    pub symbols: Vec<SymbolDecision>,
+}
+
+impl SymbolAuditLog {
+    /// One row per symbol and band: `symbol,band,selected,erased,energy_0,...`
+    ///
+    /// Bands with fewer candidates than the widest layout in the log leave
+    /// the trailing energy columns empty.
+    pub fn to_csv(&self) -> String {
+        let columns = self.symbols.iter().map(|symbol| symbol.bins_per_band).max().unwrap_or(0);
+        let mut csv = String::from("symbol,band,selected,erased");
+        for bin in 0..columns {
+            let _ = write!(csv, ",energy_{}", bin);
+        }
+        csv.push('\n');
+        for (index, symbol) in self.symbols.iter().enumerate() {
+            for band in 0..symbol.bands() {
+                let _ = write!(csv, "{},{},{},{}", index, band, symbol.selected[band], u8::from(symbol.is_erased(band)));
+                for energy in symbol.band_energies(band) {
+                    let _ = write!(csv, ",{}", energy);
+                }
+                csv.push_str(&",".repeat(columns - symbol.bins_per_band));
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+
+    /// `{"symbols":[{"bins_per_band":16,"selected":[..],"erased":[bands],"energies":[[..],..]},..]}`
+    ///
+    /// Non-finite energies (NaN or infinite power from a corrupt recording) are `null`.
+    pub fn to_json(&self) -> String {
+        let symbols = self.symbols.iter().map(|symbol| {
+            let erased = (0..symbol.bands()).filter(|&band| symbol.is_erased(band));
+            let energies = (0..symbol.bands())
+                .map(|band| format!("[{}]", join(symbol.band_energies(band).iter().map(|&energy| json_number(energy)))));
+            format!(
+                "{{\"bins_per_band\":{},\"selected\":[{}],\"erased\":[{}],\"energies\":[{}]}}",
+                symbol.bins_per_band,
+                join(&symbol.selected),
+                join(erased),
+                join(energies)
+            )
+        });
+        format!("{{\"symbols\":[{}]}}", join(symbols))
+    }
+}
+
+/// `value` as a JSON number, or `null` where JSON has none
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn join<T: std::fmt::Display>(values: impl IntoIterator<Item = T>) -> String {
+    values.into_iter().map(|value| value.to_string()).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DecoderFsk, EncoderFsk, ToneLayout};
+
+    #[test]
+    fn test_symbol_audit_of_decoded_frame() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut decoder = DecoderFsk::new().unwrap();
+        let audio = encoder.encode(b"audit me").unwrap();
+        decoder.decode(&audio).unwrap();
+        assert_eq!(decoder.symbol_audit(), None);
+
+        decoder.set_symbol_audit(true);
+        decoder.decode(&audio).unwrap();
+        let log = decoder.symbol_audit().unwrap();
+        assert_eq!(log.symbols.len(), decoder.stats.symbols_demodulated as usize);
+        for symbol in &log.symbols {
+            assert_eq!((symbol.bands(), symbol.bins_per_band, symbol.erased), (6, 16, 0));
+            for band in 0..symbol.bands() {
+                // A clean recording: the chosen tone is the strongest candidate
+                let energies = symbol.band_energies(band);
+                let strongest = (0..energies.len()).max_by(|&a, &b| energies[a].total_cmp(&energies[b])).unwrap();
+                assert_eq!(symbol.selected[band] as usize, strongest);
+            }
+        }
+
+        let csv = log.to_csv();
+        assert!(csv.starts_with("symbol,band,selected,erased,energy_0,"));
+        assert!(csv.lines().next().unwrap().ends_with(",energy_15"));
+        assert_eq!(csv.lines().count(), 1 + 6 * log.symbols.len());
+        let json = log.to_json();
+        assert!(json.starts_with("{\"symbols\":[{\"bins_per_band\":16,\"selected\":["));
+        assert_eq!(json.matches("bins_per_band").count(), log.symbols.len());
+
+        // Each decode starts a new log; layouts with fewer bins leave columns empty
+        encoder.set_tone_layout(ToneLayout::new(3, 8).unwrap());
+        decoder.decode(&encoder.encode(b"narrow").unwrap()).unwrap();
+        let log = decoder.symbol_audit().unwrap();
+        assert_eq!(log.symbols.len(), decoder.stats.symbols_demodulated as usize);
+        assert_eq!((log.symbols[1].bands(), log.symbols[1].bins_per_band), (3, 8));
+        assert!(log.to_csv().lines().last().unwrap().ends_with(&",".repeat(8)));
+        assert_eq!(SymbolAuditLog::default().to_json(), "{\"symbols\":[]}");
+    }
+
+    #[test]
+    fn test_symbol_audit_json_without_finite_energies() {
+        let log = SymbolAuditLog {
+            symbols: vec![SymbolDecision {
+                bins_per_band: 3,
+                selected: vec![0],
+                energies: vec![1.5, f32::NAN, f32::INFINITY],
+                erased: 1,
+            }],
+        };
+        let json = log.to_json();
+        assert!(json.contains("\"energies\":[[1.5,null,null]]"), "{}", json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["symbols"][0]["erased"], serde_json::json!([0]));
+    }
+}
@@ -1,4 +1,10 @@
-use crate::{fft_correlate_1d, Mode, SAMPLE_RATE};
+use crate::correlation::correlation_backend;
+use crate::error::{AudioModemError, Result};
+use crate::resample::resample_audio;
+use crate::rng::SplitMix64;
+use crate::trig;
+use crate::units::Samples;
+use crate::SAMPLE_RATE;
 use std::f32::consts::PI;
 use log::warn;
 
@@ -18,6 +24,54 @@ pub enum DetectionThreshold {
     Fixed(f32),
 }
 
+impl DetectionThreshold {
+    /// Lowest `Fixed` value
+    pub const MIN_FIXED: f32 = 0.001;
+    /// Highest `Fixed` value
+    pub const MAX_FIXED: f32 = 1.0;
+
+    /// A `Fixed` threshold, rejecting values outside [`Self::MIN_FIXED`, `Self::MAX_FIXED`]
+    pub fn fixed(value: f32) -> Result<Self> {
+        DetectionThreshold::Fixed(value).validate()
+    }
+
+    /// `self`, or `InvalidConfig` for a `Fixed` value out of range (or NaN)
+    pub fn validate(self) -> Result<Self> {
+        match self {
+            DetectionThreshold::Fixed(value) if !(Self::MIN_FIXED..=Self::MAX_FIXED).contains(&value) => {
+                Err(AudioModemError::InvalidConfig(format!(
+                    "fixed detection threshold {} must be in [{}, {}]",
+                    value,
+                    Self::MIN_FIXED,
+                    Self::MAX_FIXED
+                )))
+            }
+            threshold => Ok(threshold),
+        }
+    }
+
+    /// `Fixed` values pulled into range; NaN becomes `MAX_FIXED` (the strictest)
+    ///
+    /// What the detectors and the decoder setters apply, so an out-of-range
+    /// threshold from untrusted input never panics.
+    pub fn clamped(self) -> Self {
+        match self {
+            DetectionThreshold::Fixed(value) if value.is_nan() => DetectionThreshold::Fixed(Self::MAX_FIXED),
+            DetectionThreshold::Fixed(value) => DetectionThreshold::Fixed(value.clamp(Self::MIN_FIXED, Self::MAX_FIXED)),
+            DetectionThreshold::Adaptive => DetectionThreshold::Adaptive,
+        }
+    }
+
+    /// Correlation score a sync signal in `samples` must exceed (what the
+    /// detectors compute internally; Fixed values are returned as is)
+    pub fn value_for(self, samples: &[f32]) -> f32 {
+        match self {
+            DetectionThreshold::Adaptive => compute_threshold_value(&condition_for_sync(samples), self),
+            DetectionThreshold::Fixed(value) => value,
+        }
+    }
+}
+
 // ============================================================================
 // SYNCHRONIZATION SIGNAL TYPE CONFIGURATION
 // ============================================================================
@@ -36,7 +90,21 @@ const SIGNAL_TYPE: SignalType = SignalType::Chirp;
 /// Tuning this parameter affects threshold sensitivity:
 /// - Smaller windows: More responsive to brief bursts but noisier
 /// - Larger windows: More robust to noise but may miss brief signals
-const ADAPTIVE_RMS_WINDOW_LENGTH: usize = 2048;
+pub(crate) const ADAPTIVE_RMS_WINDOW_LENGTH: usize = 2048;
+
+/// Pole of the one-pole DC blocker applied before correlation (~13 Hz corner at 16 kHz),
+/// far below the lowest sync tone so the preamble shape is unaffected
+pub(crate) const DC_BLOCKER_POLE: f32 = 0.995;
+
+/// Samples within this fraction of the peak magnitude are considered to sit on the rail
+const CLIP_RAIL_RATIO: f32 = 0.98;
+
+/// Minimum run of consecutive rail samples that indicates hard clipping
+/// (an unclipped tone >= 800 Hz never stays within 2% of its peak this long)
+const CLIP_MIN_RUN: usize = 3;
+
+/// Fraction of non-silent samples in clipped runs above which sign correlation is used
+const CLIP_FRACTION_THRESHOLD: f32 = 0.005;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[allow(dead_code)]
@@ -111,7 +179,7 @@ fn generate_prn_with_carrier(seed: u32, carrier_freq: f32, duration_samples: usi
         let prn_value = if lfsr & 1 == 1 { 1.0 } else { -1.0 };
 
         // Modulate: PRN controls amplitude of carrier wave
-        samples[n] = amplitude * prn_value * phase_accumulator.sin();
+        samples[n] = amplitude * prn_value * trig::sin(phase_accumulator);
 
         // Update phase accumulator
         phase_accumulator += phase_increment;
@@ -139,7 +207,7 @@ pub fn generate_chirp(
         let t = n as f32 / sample_rate;
         let k = (end_freq - start_freq) / duration;
         let phase = 2.0 * PI * (start_freq * t + k * t * t / 2.0);
-        samples[n] = amplitude * phase.sin();
+        samples[n] = amplitude * trig::sin(phase);
     }
     samples
 }
@@ -153,25 +221,30 @@ fn amplitude_envelope(t: f32, duration: f32) -> f32 {
 
     if t < attack_time {
         // Soft attack: smooth sine-based ramp from 0 to 1
-        (PI * t / (2.0 * attack_time)).sin().powi(2)
+        trig::sin(PI * t / (2.0 * attack_time)).powi(2)
     } else if t < sustain_end {
         // Sustain: full amplitude
         1.0
     } else {
         // Smooth decay: sine-based fade from 1 to 0
         let decay_progress = ((t - sustain_end) / decay_time).clamp(0.0, 1.0);
-        (PI * decay_progress / 2.0).cos().powi(2)
+        trig::cos(PI * decay_progress / 2.0).powi(2)
     }
 }
 
+/// Start and end frequency of the preamble chirp (Hz)
+pub const PREAMBLE_CHIRP_HZ: (f32, f32) = (800.0, 1800.0);
+
+/// Start and end frequency of the postamble chirp (Hz)
+pub const POSTAMBLE_CHIRP_HZ: (f32, f32) = (1000.0, 400.0);
+
 /// Generates preamble chirp with human whistling characteristics
 /// Ascending chirp from 800 Hz to 1800 Hz (human whistling range)
 /// Applies smooth amplitude envelope (soft attack/decay) for natural sound
 pub fn generate_preamble_chirp(duration_samples: usize, amplitude: f32) -> Vec<f32> {
     let sample_rate = SAMPLE_RATE as f32;
     let duration = duration_samples as f32 / sample_rate;
-    let start_freq = 800.0;
-    let end_freq = 1800.0;
+    let (start_freq, end_freq) = PREAMBLE_CHIRP_HZ;
 
     let mut samples = vec![0.0; duration_samples];
     for n in 0..duration_samples {
@@ -179,7 +252,7 @@ pub fn generate_preamble_chirp(duration_samples: usize, amplitude: f32) -> Vec<f
         let k = (end_freq - start_freq) / duration;
         let phase = 2.0 * PI * (start_freq * t + k * t * t / 2.0);
         let envelope = amplitude_envelope(t, duration);
-        samples[n] = amplitude * envelope * phase.sin();
+        samples[n] = amplitude * envelope * trig::sin(phase);
     }
     samples
 }
@@ -190,8 +263,7 @@ pub fn generate_preamble_chirp(duration_samples: usize, amplitude: f32) -> Vec<f
 pub fn generate_postamble_chirp(duration_samples: usize, amplitude: f32) -> Vec<f32> {
     let sample_rate = SAMPLE_RATE as f32;
     let duration = duration_samples as f32 / sample_rate;
-    let start_freq = 1000.0;
-    let end_freq = 400.0;
+    let (start_freq, end_freq) = POSTAMBLE_CHIRP_HZ;
 
     let mut samples = vec![0.0; duration_samples];
     for n in 0..duration_samples {
@@ -199,16 +271,116 @@ pub fn generate_postamble_chirp(duration_samples: usize, amplitude: f32) -> Vec<
         let k = (end_freq - start_freq) / duration;
         let phase = 2.0 * PI * (start_freq * t + k * t * t / 2.0);
         let envelope = amplitude_envelope(t, duration);
-        samples[n] = amplitude * envelope * phase.sin();
+        samples[n] = amplitude * envelope * trig::sin(phase);
     }
     samples
 }
 
+/// Linear chirp with the whistle envelope, for the short-frame sync signals
+fn generate_enveloped_chirp(duration_samples: usize, start_freq: f32, end_freq: f32, amplitude: f32) -> Vec<f32> {
+    let duration = duration_samples as f32 / SAMPLE_RATE as f32;
+    let k = (end_freq - start_freq) / duration;
+    (0..duration_samples)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let phase = 2.0 * PI * (start_freq * t + k * t * t / 2.0);
+            amplitude * amplitude_envelope(t, duration) * trig::sin(phase)
+        })
+        .collect()
+}
+
+/// Short-frame preamble: fast ascending chirp 1400 Hz -> 2600 Hz
+/// (distinct from the standard 800 -> 1800 Hz sweep, so decoders tell the profiles apart)
+pub fn generate_short_preamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(3, duration_samples, amplitude),
+        None => generate_enveloped_chirp(duration_samples, 1400.0, 2600.0, amplitude),
+    }
+}
+
+/// Short-frame postamble: fast descending chirp 2600 Hz -> 1400 Hz
+pub fn generate_short_postamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(4, duration_samples, amplitude),
+        None => generate_enveloped_chirp(duration_samples, 2600.0, 1400.0, amplitude),
+    }
+}
+
+/// Band of the telephony sync signals (Hz), inside the 300-3400 Hz voice band
+pub const TELEPHONY_SYNC_HZ: (f32, f32) = (500.0, 3000.0);
+
+/// Telephony preamble: slow descending chirp 3000 Hz -> 500 Hz over the voice band
+/// (the standard and short profiles start with ascending sweeps); keyed, a
+/// chirp along the key's path through the same band, which a phone call keeps
+pub fn generate_telephony_preamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    let (low, high) = TELEPHONY_SYNC_HZ;
+    match key {
+        Some(key) => key.chirp(5, duration_samples, amplitude, TELEPHONY_SYNC_HZ),
+        None => generate_enveloped_chirp(duration_samples, high, low, amplitude),
+    }
+}
+
+/// Telephony postamble: slow ascending chirp 500 Hz -> 3000 Hz
+pub fn generate_telephony_postamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    let (low, high) = TELEPHONY_SYNC_HZ;
+    match key {
+        Some(key) => key.chirp(6, duration_samples, amplitude, TELEPHONY_SYNC_HZ),
+        None => generate_enveloped_chirp(duration_samples, low, high, amplitude),
+    }
+}
+
+/// Codec-robust preamble: slow ascending chirp 1200 Hz -> 2400 Hz
+pub fn generate_codec_robust_preamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(7, duration_samples, amplitude),
+        None => generate_enveloped_chirp(duration_samples, 1200.0, 2400.0, amplitude),
+    }
+}
+
+/// Codec-robust postamble: slow descending chirp 2400 Hz -> 1200 Hz
+pub fn generate_codec_robust_postamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(8, duration_samples, amplitude),
+        None => generate_enveloped_chirp(duration_samples, 2400.0, 1200.0, amplitude),
+    }
+}
+
+/// Band of the narrowband sync signals (Hz), well inside the 4 kHz of an 8 kHz link
+pub const NARROWBAND_SYNC_HZ: (f32, f32) = (500.0, 1900.0);
+
+/// Narrowband preamble: ascending chirp 500 Hz -> 1900 Hz (steeper than the
+/// standard 800 -> 1800 Hz sweep); keyed, a chirp along the key's path
+/// through the same band, since wideband PRN chips would not fit the link
+pub fn generate_narrowband_preamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    let (low, high) = NARROWBAND_SYNC_HZ;
+    match key {
+        Some(key) => key.chirp(9, duration_samples, amplitude, NARROWBAND_SYNC_HZ),
+        None => generate_enveloped_chirp(duration_samples, low, high, amplitude),
+    }
+}
+
+/// Narrowband postamble: descending chirp 1900 Hz -> 500 Hz
+pub fn generate_narrowband_postamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    let (low, high) = NARROWBAND_SYNC_HZ;
+    match key {
+        Some(key) => key.chirp(10, duration_samples, amplitude, NARROWBAND_SYNC_HZ),
+        None => generate_enveloped_chirp(duration_samples, high, low, amplitude),
+    }
+}
+
 /// Generates a single pure tone with smooth attack/decay envelope
 /// freq: frequency in Hz
 /// duration_samples: total number of samples
 /// amplitude: peak amplitude (0.0 to 1.0)
-fn generate_tone(freq: f32, duration_samples: usize, amplitude: f32) -> Vec<f32> {
+pub(crate) fn generate_tone(freq: f32, duration_samples: usize, amplitude: f32) -> Vec<f32> {
     let sample_rate = SAMPLE_RATE as f32;
     let duration = duration_samples as f32 / sample_rate;
 
@@ -217,7 +389,7 @@ fn generate_tone(freq: f32, duration_samples: usize, amplitude: f32) -> Vec<f32>
         let t = n as f32 / sample_rate;
         let phase = 2.0 * PI * freq * t;
         let envelope = amplitude_envelope(t, duration);
-        samples[n] = amplitude * envelope * phase.sin();
+        samples[n] = amplitude * envelope * trig::sin(phase);
     }
     samples
 }
@@ -313,6 +485,102 @@ pub fn generate_postamble_signal(duration_samples: usize, amplitude: f32) -> Vec
     }
 }
 
+/// Application-provided key that gives a deployment its own sync signals
+///
+/// With a key, the preamble, postamble and fountain preamble become PRN bursts
+/// seeded from the key instead of the shared chirps/whistle (chirps along a
+/// key-chosen path for the band-limited telephony and narrowband profiles). Encoder and decoder
+/// must use the same key; frames sent under other keys (or none) correlate too
+/// weakly to be detected, so separate deployments never see each other's frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct NetworkKey(u32);
+
+/// Each PRN chip is held for this many samples (keeps most energy below 4 kHz)
+const KEYED_CHIP_SAMPLES: usize = 2;
+
+/// Linear legs of a keyed chirp, each between two key-chosen frequencies
+const KEYED_CHIRP_LEGS: usize = 16;
+
+impl NetworkKey {
+    /// Derive a key from arbitrary bytes (e.g. an application name) with FNV-1a
+    pub fn new(key: &[u8]) -> Self {
+        let mut hash: u32 = 0x811C_9DC5;
+        for &byte in key {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+        Self(hash)
+    }
+
+    /// LFSR seed for one sync signal; never zero (a zero LFSR state is stuck)
+    fn seed(&self, role: u32) -> u32 {
+        let mut x = self.0 ^ role.wrapping_mul(0x9E37_79B9);
+        x ^= x >> 16;
+        x = x.wrapping_mul(0x7FEB_352D);
+        x ^= x >> 15;
+        x.max(1)
+    }
+
+    fn prn(&self, role: u32, duration_samples: usize, amplitude: f32) -> Vec<f32> {
+        let chips = generate_prn_noise(self.seed(role), duration_samples.div_ceil(KEYED_CHIP_SAMPLES), 1.0);
+        let duration = duration_samples as f32 / SAMPLE_RATE as f32;
+        (0..duration_samples)
+            .map(|n| {
+                let envelope = amplitude_envelope(n as f32 / SAMPLE_RATE as f32, duration);
+                amplitude * envelope * chips[n / KEYED_CHIP_SAMPLES]
+            })
+            .collect()
+    }
+
+    /// Phase-continuous chirp whose frequency runs through `KEYED_CHIRP_LEGS`
+    /// linear legs between key-chosen points of `band`, so it never leaves it
+    fn chirp(&self, role: u32, duration_samples: usize, amplitude: f32, (low, high): (f32, f32)) -> Vec<f32> {
+        let mut rng = SplitMix64::new(self.seed(role) as u64);
+        let turns: Vec<f32> = (0..=KEYED_CHIRP_LEGS).map(|_| low + (high - low) * rng.next_f32()).collect();
+        let duration = duration_samples as f32 / SAMPLE_RATE as f32;
+        let mut phase = 0.0f32;
+        (0..duration_samples)
+            .map(|n| {
+                let position = (n * KEYED_CHIRP_LEGS) as f32 / duration_samples as f32;
+                let leg = (position as usize).min(KEYED_CHIRP_LEGS - 1);
+                let frequency = turns[leg] + (turns[leg + 1] - turns[leg]) * (position - leg as f32);
+                let envelope = amplitude_envelope(n as f32 / SAMPLE_RATE as f32, duration);
+                let sample = amplitude * envelope * trig::sin(phase);
+                phase = (phase + 2.0 * PI * frequency / SAMPLE_RATE as f32) % (2.0 * PI);
+                sample
+            })
+            .collect()
+    }
+}
+
+/// Preamble for `key`, or the default preamble when there is no key
+pub fn generate_keyed_preamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(0, duration_samples, amplitude),
+        None => generate_preamble(duration_samples, amplitude),
+    }
+}
+
+/// Postamble for `key`, or the default postamble when there is no key
+pub fn generate_keyed_postamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(1, duration_samples, amplitude),
+        None => generate_postamble_signal(duration_samples, amplitude),
+    }
+}
+
+/// Fountain preamble for `key`, or the three-note whistle when there is no key
+pub fn generate_keyed_fountain_preamble(duration: Samples, amplitude: f32, key: Option<NetworkKey>) -> Vec<f32> {
+    let duration_samples = duration.get();
+    match key {
+        Some(key) => key.prn(2, duration_samples, amplitude),
+        None => generate_fountain_preamble(duration_samples, amplitude),
+    }
+}
+
 /// Compute maximum RMS across sliding windows of the signal.
 ///
 /// Uses sliding windows to find the strongest signal region, which helps identify
@@ -344,6 +612,81 @@ fn compute_max_rms_from_windows(samples: &[f32]) -> f32 {
     max_rms
 }
 
+/// Fraction of non-silent samples that belong to runs stuck at the peak magnitude
+fn clipped_fraction(samples: &[f32]) -> f32 {
+    let peak = samples.iter().fold(0.0f32, |acc, &x| acc.max(x.abs()));
+    if peak < 1e-6 {
+        return 0.0;
+    }
+
+    let rail = peak * CLIP_RAIL_RATIO;
+    let mut clipped = 0usize;
+    let mut non_silent = 0usize;
+    let mut run = 0usize;
+    for &x in samples {
+        if x.abs() > 1e-6 {
+            non_silent += 1;
+        }
+        if x.abs() >= rail {
+            run += 1;
+        } else {
+            if run >= CLIP_MIN_RUN {
+                clipped += run;
+            }
+            run = 0;
+        }
+    }
+    if run >= CLIP_MIN_RUN {
+        clipped += run;
+    }
+
+    if non_silent == 0 {
+        0.0
+    } else {
+        clipped as f32 / non_silent as f32
+    }
+}
+
+/// Prepare samples for sync correlation
+///
+/// - Removes DC offset with a one-pole high-pass filter, so a biased recording
+///   does not inflate window energy and depress the normalized correlation
+/// - For hard-clipped recordings (hot mic gain), switches to sign correlation:
+///   the 1-bit waveform is unaffected by flattened peaks and still correlates
+///   strongly (~0.9) with the sync template
+pub fn condition_for_sync(samples: &[f32]) -> Vec<f32> {
+    condition_with_gate(samples, clip_gate(samples))
+}
+
+/// Near-silence gate of sign correlation if `samples` are hard-clipped
+fn clip_gate(samples: &[f32]) -> Option<f32> {
+    (clipped_fraction(samples) > CLIP_FRACTION_THRESHOLD)
+        .then(|| samples.iter().fold(0.0f32, |acc, &x| acc.max(x.abs())) * 0.01)
+}
+
+/// [`condition_for_sync`] with the clipping decision made by the caller, so
+/// windows of a long recording are all conditioned alike
+fn condition_with_gate(samples: &[f32], gate: Option<f32>) -> Vec<f32> {
+    let mut conditioned = Vec::with_capacity(samples.len());
+    let mut prev_x = samples.first().copied().unwrap_or(0.0);
+    let mut prev_y = 0.0f32;
+    for &x in samples {
+        let y = x - prev_x + DC_BLOCKER_POLE * prev_y;
+        conditioned.push(y);
+        prev_x = x;
+        prev_y = y;
+    }
+
+    if let Some(gate) = gate {
+        // Gate near-silence so quiet gaps stay quiet instead of becoming full-scale noise
+        for y in conditioned.iter_mut() {
+            *y = if y.abs() > gate { y.signum() } else { 0.0 };
+        }
+    }
+
+    conditioned
+}
+
 /// Compute the detection threshold value based on the threshold specification
 /// - Adaptive: Adjusts threshold based on maximum signal RMS from sliding windows:
 ///   - Uses sliding windows of ADAPTIVE_RMS_WINDOW_LENGTH samples to find the strongest region
@@ -353,258 +696,623 @@ fn compute_max_rms_from_windows(samples: &[f32]) -> f32 {
 /// - Fixed(value): Returns the provided fixed threshold value
 fn compute_threshold_value(samples: &[f32], threshold: DetectionThreshold) -> f32 {
     match threshold {
-        DetectionThreshold::Adaptive => {
-            let signal_rms: f32 = compute_max_rms_from_windows(samples);
-            if signal_rms > 0.1 {
-                0.4
-            } else if signal_rms > 0.02 {
-                0.35
-            } else {
-                0.3
-            }
-        }
+        DetectionThreshold::Adaptive => adaptive_threshold(compute_max_rms_from_windows(samples)),
         DetectionThreshold::Fixed(value) => value,
     }
 }
 
-/// Detect preamble using efficient FFT-based cross-correlation
-/// Returns the position where the preamble (PRN noise burst) is most likely to start
-/// threshold: Specifies how to determine the detection threshold (Adaptive or Fixed)
-/// Panics if Fixed threshold is invalid (not in range [0.001, 1.0], i.e., must be inclusive of 0.001 and 1.0)
-pub fn detect_preamble(samples: &[f32], threshold: DetectionThreshold) -> Option<usize> {
-    // Validate threshold
-    if let DetectionThreshold::Fixed(value) = threshold {
-        if value < 0.001 || value > 1.0 {
-            panic!("Invalid fixed detection threshold: {}. Must be in range [0.001, 1.0]. Minimum is 0.001 (0.1%)", value);
-        }
+/// Adaptive threshold for the strongest window RMS of the (conditioned) signal
+pub(crate) fn adaptive_threshold(signal_rms: f32) -> f32 {
+    if signal_rms > 0.1 {
+        0.4
+    } else if signal_rms > 0.02 {
+        0.35
+    } else {
+        0.3
     }
+}
 
-    let preamble_samples = crate::PREAMBLE_SAMPLES;
+/// Inputs longer than this (2 minutes) are scanned in windows, since one pass
+/// allocates several correlation buffers the size of the input
+const WINDOWED_SCAN_MIN_SAMPLES: usize = 120 * SAMPLE_RATE;
 
-    if samples.len() < preamble_samples {
-        return None;
-    }
+/// Start positions scored per window of a windowed scan (8 s)
+const SCAN_WINDOW_STEP: usize = 8 * SAMPLE_RATE;
 
-    // Generate expected preamble signal pattern (same seed = same pattern)
-    let template = generate_preamble(preamble_samples, 1.0);
+/// Audio conditioned ahead of each window so the DC blocker has settled (and
+/// every adaptive RMS window lies inside some window's audio)
+const SCAN_WARMUP_SAMPLES: usize = ADAPTIVE_RMS_WINDOW_LENGTH;
+
+/// Number of samples on each side of a selected candidate that are excluded
+/// when picking the next one (keeps candidates from landing on the same peak)
+const CANDIDATE_SEPARATION: usize = crate::PREAMBLE_SAMPLES / 4;
 
-    // Use FFT-based correlation for O(N log N) complexity
-    let fft_correlation = match fft_correlate_1d(samples, &template, Mode::Full) {
+/// A possible sync position with its normalized correlation score
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncCandidate {
+    pub position: usize,
+    pub score: f32,
+}
+
+/// Normalized cross-correlation coefficient for every start position of `template` in `samples`
+///
+/// Correlates with the installed [`crate::correlation::CorrelationBackend`] (FFT by
+/// default, O(N log N)) and a prefix-sum of squared samples for O(1) window energy.
+/// Returns None if the correlation fails.
+pub(crate) fn sync_scores(samples: &[f32], template: &[f32], label: &str) -> Option<Vec<f32>> {
+    let template_len = template.len();
+
+    let backend = correlation_backend();
+    let correlation = match backend.correlate(samples, template) {
         Ok(corr) => corr,
         Err(e) => {
             warn!(
-                "FFT correlation failed during preamble detection: {} (samples={}, template={}, mode=Full)",
+                "Correlation ({}) failed during {} detection: {} (samples={}, template={}, mode=Full)",
+                backend.name(),
+                label,
                 e,
                 samples.len(),
-                template.len()
+                template_len
             );
             return None;
         }
     };
 
-    let mut best_pos = 0;
-    let mut best_correlation = 0.0;
-
     // Calculate template energy once
     let template_energy: f32 = template.iter().map(|x| x * x).sum();
 
     // Build prefix-sum array of squared samples for O(1) window energy computation
-    let mut sq_prefix = vec![0.0; samples.len() + 1];
+    // (in f64: after a loud frame, f32 sums lose the energy of a quiet window)
+    let mut sq_prefix = vec![0.0f64; samples.len() + 1];
     for k in 0..samples.len() {
-        sq_prefix[k + 1] = sq_prefix[k] + samples[k] * samples[k];
+        sq_prefix[k + 1] = sq_prefix[k] + f64::from(samples[k] * samples[k]);
     }
 
-    // Iterate through valid positions and normalize correlation coefficients
-    for i in 0..=samples.len().saturating_sub(preamble_samples) {
-        // FFT correlation output at index (i + preamble_samples - 1) corresponds to window starting at i
-        let fft_index = i + preamble_samples - 1;
-        let raw_correlation = fft_correlation[fft_index];
+    let positions = samples.len().saturating_sub(template_len) + 1;
+    let mut scores = Vec::with_capacity(positions);
+    for i in 0..positions {
+        // Full correlation output at index (i + template_len - 1) corresponds to window starting at i
+        let raw_correlation = correlation[i + template_len - 1];
 
         // Calculate window energy using O(1) prefix-sum lookup
-        let window_energy = sq_prefix[i + preamble_samples] - sq_prefix[i];
+        let window_energy = (sq_prefix[i + template_len] - sq_prefix[i]) as f32;
 
         // Compute normalized correlation coefficient
         let denom = (window_energy * template_energy).sqrt();
-        let normalized_corr = if denom > 1e-10 {
+        scores.push(if denom > 1e-10 {
             (raw_correlation / denom).abs()
         } else {
             0.0
-        };
+        });
+    }
 
-        if normalized_corr > best_correlation {
-            best_correlation = normalized_corr;
+    Some(scores)
+}
+
+/// Best-scoring position if it clears the threshold
+fn detect_best(samples: &[f32], template: &[f32], threshold: DetectionThreshold, label: &str) -> Option<usize> {
+    detect_best_candidate(samples, template, threshold, label).map(|candidate| candidate.position)
+}
+
+/// Best-scoring position and its score if it clears the threshold
+fn detect_best_candidate(
+    samples: &[f32],
+    template: &[f32],
+    threshold: DetectionThreshold,
+    label: &str,
+) -> Option<SyncCandidate> {
+    let threshold = threshold.clamped();
+
+    if samples.len() < template.len() {
+        return None;
+    }
+    if samples.len() > WINDOWED_SCAN_MIN_SAMPLES {
+        return detect_windowed(samples, template, threshold, 1, label, SCAN_WINDOW_STEP).into_iter().next();
+    }
+
+    // Remove DC offset and neutralize clipping before correlating
+    let conditioned = condition_for_sync(samples);
+    let scores = sync_scores(&conditioned, template, label)?;
+
+    let mut best_pos = 0;
+    let mut best_correlation = 0.0;
+    for (i, &score) in scores.iter().enumerate() {
+        if score > best_correlation {
+            best_correlation = score;
             best_pos = i;
         }
     }
 
     // Determine detection threshold
-    let threshold_value = compute_threshold_value(samples, threshold);
+    let threshold_value = compute_threshold_value(&conditioned, threshold);
 
     if best_correlation > threshold_value {
-        Some(best_pos)
+        Some(SyncCandidate { position: best_pos, score: best_correlation })
     } else {
         None
     }
 }
 
-/// Detect postamble using efficient cross-correlation
-/// Returns the position where the postamble (PRN noise burst) is most likely to start
+/// Detect preamble using efficient FFT-based cross-correlation
+/// Returns the position where the preamble (PRN noise burst) is most likely to start
 /// threshold: Specifies how to determine the detection threshold (Adaptive or Fixed)
-/// Panics if Fixed threshold is invalid (not in range [0.001, 1.0])
-pub fn detect_postamble(samples: &[f32], threshold: DetectionThreshold) -> Option<usize> {
-    // Validate threshold
-    if let DetectionThreshold::Fixed(value) = threshold {
-        if value < 0.001 || value > 1.0 {
-            panic!("Invalid fixed detection threshold: {}. Must be in range [0.001, 1.0]. Minimum is 0.001 (0.1%)", value);
-        }
-    }
+/// Fixed thresholds outside [0.001, 1.0] are clamped (see [`DetectionThreshold::clamped`])
+pub fn detect_preamble(samples: &[f32], threshold: DetectionThreshold) -> Option<usize> {
+    detect_keyed_preamble(samples, threshold, None)
+}
+
+/// [`detect_preamble`] for the sync signals of `key`
+pub fn detect_keyed_preamble(samples: &[f32], threshold: DetectionThreshold, key: Option<NetworkKey>) -> Option<usize> {
+    // Generate expected preamble signal pattern (same seed = same pattern)
+    let template = generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, key);
+    detect_best(samples, &template, threshold, "preamble")
+}
+
+/// Detect up to `max_candidates` preamble positions, ranked by correlation score
+///
+/// Each candidate is a local peak that clears the threshold; peaks closer than
+/// a quarter preamble to a higher-ranked candidate are suppressed. Useful when a
+/// false peak outscores the true preamble: callers can try each in order.
+/// Fixed thresholds outside [0.001, 1.0] are clamped
+pub fn detect_preamble_candidates(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+) -> Vec<SyncCandidate> {
+    detect_keyed_preamble_candidates(samples, threshold, max_candidates, None)
+}
+
+/// [`detect_preamble_candidates`] for the sync signals of `key`
+pub fn detect_keyed_preamble_candidates(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    key: Option<NetworkKey>,
+) -> Vec<SyncCandidate> {
+    let template = generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, key);
+    detect_candidates(samples, &template, threshold, max_candidates, "preamble")
+}
 
-    let postamble_samples = crate::POSTAMBLE_SAMPLES;
+/// Shortest preamble tail, left at the start of a clipped recording, that
+/// [`detect_keyed_clipped_preamble`] looks for
+pub const MIN_CLIPPED_PREAMBLE_SAMPLES: usize = crate::PREAMBLE_SAMPLES / 4;
 
-    if samples.len() < postamble_samples {
+/// Detect a preamble cut off by the start of the recording
+///
+/// Correlates the template's tail with the start of `samples` for every
+/// overlap from [`MIN_CLIPPED_PREAMBLE_SAMPLES`] up to one sample short of the
+/// whole preamble (which [`detect_keyed_preamble_candidates`] finds), each
+/// normalized by the energy of its own overlap. The candidate's position is the
+/// number of preamble samples missing before sample 0.
+/// Fixed thresholds outside [0.001, 1.0] are clamped
+pub fn detect_keyed_clipped_preamble(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SyncCandidate> {
+    let threshold = threshold.clamped();
+
+    let template = generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, key);
+    let head = &samples[..samples.len().min(template.len() - 1)];
+    if head.len() < MIN_CLIPPED_PREAMBLE_SAMPLES {
         return None;
     }
 
-    // Generate expected postamble signal pattern (different seed from preamble)
-    let template = generate_postamble_signal(postamble_samples, 1.0);
-
-    // Use FFT-based correlation for O(N log N) complexity
-    let fft_correlation = match fft_correlate_1d(samples, &template, Mode::Full) {
+    let conditioned = condition_for_sync(head);
+    let backend = correlation_backend();
+    let correlation = match backend.correlate(&conditioned, &template) {
         Ok(corr) => corr,
         Err(e) => {
-            warn!(
-                "FFT correlation failed during postamble detection: {} (samples={}, template={}, mode=Full)",
-                e,
-                samples.len(),
-                template.len()
-            );
+            warn!("Correlation ({}) failed during clipped preamble detection: {}", backend.name(), e);
             return None;
         }
     };
 
-    let mut best_pos = 0;
-    let mut best_correlation = 0.0;
+    // Full correlation output at index (overlap - 1) pairs the first `overlap`
+    // samples with the last `overlap` template samples
+    let mut best: Option<SyncCandidate> = None;
+    let (mut head_energy, mut tail_energy) = (0.0f32, 0.0f32);
+    for overlap in 1..=conditioned.len() {
+        head_energy += conditioned[overlap - 1] * conditioned[overlap - 1];
+        tail_energy += template[template.len() - overlap] * template[template.len() - overlap];
+        let denom = (head_energy * tail_energy).sqrt();
+        if overlap < MIN_CLIPPED_PREAMBLE_SAMPLES || denom <= 1e-10 {
+            continue;
+        }
+        let score = (correlation[overlap - 1] / denom).abs();
+        if best.is_none_or(|best| score > best.score) {
+            best = Some(SyncCandidate { position: template.len() - overlap, score });
+        }
+    }
 
-    // Calculate template energy once
-    let template_energy: f32 = template.iter().map(|x| x * x).sum();
+    let threshold_value = compute_threshold_value(&conditioned, threshold);
+    best.filter(|best| best.score > threshold_value)
+}
 
-    // Build prefix-sum array of squared samples for O(1) window energy computation
-    let mut sq_prefix = vec![0.0; samples.len() + 1];
-    for k in 0..samples.len() {
-        sq_prefix[k + 1] = sq_prefix[k] + samples[k] * samples[k];
-    }
+/// Slowest and fastest playback [`estimate_keyed_playback_speed`] looks for,
+/// in thousandths of the normal speed (messenger apps play voice notes at up
+/// to 1.05x)
+pub const MIN_PLAYBACK_SPEED_PERMILLE: u32 = 950;
+pub const MAX_PLAYBACK_SPEED_PERMILLE: u32 = 1050;
 
-    // Iterate through valid positions and normalize correlation coefficients
-    for i in 0..=samples.len().saturating_sub(postamble_samples) {
-        // FFT correlation output at index (i + postamble_samples - 1) corresponds to window starting at i
-        let fft_index = i + postamble_samples - 1;
-        let raw_correlation = fft_correlation[fft_index];
+/// Speed step of the coarse search; the best step is refined to 1/1000
+const PLAYBACK_SPEED_STEP_PERMILLE: u32 = 5;
 
-        // Calculate window energy using O(1) prefix-sum lookup
-        let window_energy = sq_prefix[i + postamble_samples] - sq_prefix[i];
+/// Playback speed of a recording and the preamble found at it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedEstimate {
+    /// Thousandths of the normal speed (1050 = played 5% fast)
+    pub permille: u32,
+    /// Preamble position in the recording and its score at that speed
+    pub candidate: SyncCandidate,
+}
 
-        // Compute normalized correlation coefficient
-        let denom = (window_energy * template_energy).sqrt();
-        let normalized_corr = if denom > 1e-10 {
-            (raw_correlation / denom).abs()
-        } else {
-            0.0
-        };
+/// Estimate how fast a recording was played back from the preamble
+///
+/// Correlates the preamble time-scaled to each speed from
+/// [`MIN_PLAYBACK_SPEED_PERMILLE`] to [`MAX_PLAYBACK_SPEED_PERMILLE`] and keeps
+/// the best score that clears the threshold. A faster playback shortens the
+/// chirp and raises its frequencies, which the scaled template matches.
+/// Fixed thresholds outside [0.001, 1.0] are clamped
+pub fn estimate_keyed_playback_speed(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SpeedEstimate> {
+    let threshold = threshold.clamped();
+
+    let template = generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, key);
+    let conditioned = condition_for_sync(samples);
+    // Played `permille / 1000` times as fast: fewer samples, higher tones
+    let scale = |permille: u32| (SAMPLE_RATE * permille as usize / 1000, SAMPLE_RATE);
+    let estimate = |((from, _), candidate): ((usize, usize), SyncCandidate)| SpeedEstimate {
+        permille: (from * 1000 / SAMPLE_RATE) as u32,
+        candidate,
+    };
 
-        if normalized_corr > best_correlation {
-            best_correlation = normalized_corr;
-            best_pos = i;
-        }
-    }
+    let coarse = (MIN_PLAYBACK_SPEED_PERMILLE..=MAX_PLAYBACK_SPEED_PERMILLE)
+        .step_by(PLAYBACK_SPEED_STEP_PERMILLE as usize)
+        .map(scale);
+    let coarse = estimate(best_scaled_match(&conditioned, &template, coarse, "speed")?);
+    let step = PLAYBACK_SPEED_STEP_PERMILLE - 1;
+    let fine = ((coarse.permille - step).max(MIN_PLAYBACK_SPEED_PERMILLE)
+        ..=(coarse.permille + step).min(MAX_PLAYBACK_SPEED_PERMILLE))
+        .filter(|&permille| permille != coarse.permille)
+        .map(scale);
+    let best = match best_scaled_match(&conditioned, &template, fine, "speed").map(estimate) {
+        Some(fine) if fine.candidate.score > coarse.candidate.score => fine,
+        _ => coarse,
+    };
 
-    // Determine detection threshold
-    let threshold_value = compute_threshold_value(samples, threshold);
+    let threshold_value = compute_threshold_value(&conditioned, threshold);
+    Some(best).filter(|estimate| estimate.candidate.score > threshold_value)
+}
 
-    if best_correlation > threshold_value {
-        Some(best_pos)
-    } else {
-        None
-    }
+/// Best match of `template` resampled from `from` to `to` Hz for each
+/// `(from, to)` of `scales`, not yet checked against a threshold
+///
+/// Scales with a zero rate are skipped; on equal scores the earlier scale wins.
+fn best_scaled_match(
+    conditioned: &[f32],
+    template: &[f32],
+    scales: impl IntoIterator<Item = (usize, usize)>,
+    label: &str,
+) -> Option<((usize, usize), SyncCandidate)> {
+    scales
+        .into_iter()
+        .filter(|&(from, to)| from > 0 && to > 0)
+        .filter_map(|(from, to)| {
+            let scaled = resample_audio(template, from, to);
+            if conditioned.len() < scaled.len() {
+                return None;
+            }
+            let scores = sync_scores(conditioned, &scaled, label)?;
+            let (position, score) = scores
+                .iter()
+                .copied()
+                .enumerate()
+                .fold((0, 0.0f32), |best, (i, score)| if score > best.1 { (i, score) } else { best });
+            Some(((from, to), SyncCandidate { position, score }))
+        })
+        .fold(None, |best: Option<((usize, usize), SyncCandidate)>, scaled| match best {
+            Some(best) if best.1.score >= scaled.1.score => Some(best),
+            _ => Some(scaled),
+        })
 }
 
-/// Detect fountain mode preamble (three-note whistle) using efficient FFT-based cross-correlation
-/// Returns the position where the fountain preamble is most likely to start
-/// threshold: Specifies how to determine the detection threshold (Adaptive or Fixed)
-/// Panics if Fixed threshold is invalid (not in range [0.001, 1.0])
-pub fn detect_fountain_preamble(samples: &[f32], threshold: DetectionThreshold) -> Option<usize> {
-    // Validate threshold
-    if let DetectionThreshold::Fixed(value) = threshold {
-        if value < 0.001 || value > 1.0 {
-            panic!("Invalid fixed detection threshold: {}. Must be in range [0.001, 1.0]. Minimum is 0.001 (0.1%)", value);
-        }
-    }
+/// Capture rates [`estimate_keyed_source_rate`] tries besides the one claimed
+pub const COMMON_SOURCE_RATES: [usize; 5] = [8_000, 16_000, 22_050, 44_100, 48_000];
 
-    let preamble_samples = crate::PREAMBLE_SAMPLES;
+/// Sample rate a recording actually has and the preamble found at it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateEstimate {
+    /// Samples per second
+    pub rate: usize,
+    /// Preamble position in the recording (at `rate`) and its score
+    pub candidate: SyncCandidate,
+}
 
-    if samples.len() < preamble_samples {
-        return None;
-    }
+/// Find which of `rates` a recording was captured at from its preamble
+///
+/// Correlates the preamble resampled to each rate with the recording as is
+/// (a bank of templates instead of one resample of the audio per guess) and
+/// keeps the best score that clears the threshold; on equal scores the
+/// earlier rate wins. Zero rates are skipped.
+/// Fixed thresholds outside [0.001, 1.0] are clamped
+pub fn estimate_keyed_source_rate(
+    samples: &[f32],
+    rates: &[usize],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<RateEstimate> {
+    let threshold = threshold.clamped();
+
+    // A capture at `rate` looks like the preamble played SAMPLE_RATE / rate as fast
+    let template = generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, key);
+    let conditioned = condition_for_sync(samples);
+    let scales = rates.iter().map(|&rate| (SAMPLE_RATE, rate));
+    let ((_, rate), candidate) = best_scaled_match(&conditioned, &template, scales, "source rate")?;
+
+    let threshold_value = compute_threshold_value(&conditioned, threshold);
+    Some(RateEstimate { rate, candidate }).filter(|estimate| estimate.candidate.score > threshold_value)
+}
 
-    // Generate expected fountain preamble signal pattern (three-note whistle)
-    let template = generate_fountain_preamble(preamble_samples, 1.0);
+/// Preamble candidates for the short-frame profile, ranked like [`detect_preamble_candidates`]
+pub fn detect_short_preamble_candidates(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    key: Option<NetworkKey>,
+) -> Vec<SyncCandidate> {
+    let template = generate_short_preamble(Samples(crate::SHORT_SYNC_SAMPLES), 1.0, key);
+    detect_candidates(samples, &template, threshold, max_candidates, "short preamble")
+}
 
-    // Use FFT-based correlation for O(N log N) complexity
-    let fft_correlation = match fft_correlate_1d(samples, &template, Mode::Full) {
-        Ok(corr) => corr,
-        Err(e) => {
-            warn!(
-                "FFT correlation failed during fountain preamble detection: {} (samples={}, template={}, mode=Full)",
-                e,
-                samples.len(),
-                template.len()
-            );
-            return None;
-        }
+/// Preamble candidates for the telephony profile, ranked like [`detect_preamble_candidates`]
+pub fn detect_telephony_preamble_candidates(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    key: Option<NetworkKey>,
+) -> Vec<SyncCandidate> {
+    let template = generate_telephony_preamble(Samples(crate::TELEPHONY_SYNC_SAMPLES), 1.0, key);
+    detect_candidates(samples, &template, threshold, max_candidates, "telephony preamble")
+}
+
+/// Preamble candidates for the codec-robust profile, ranked like [`detect_preamble_candidates`]
+pub fn detect_codec_robust_preamble_candidates(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    key: Option<NetworkKey>,
+) -> Vec<SyncCandidate> {
+    let template = generate_codec_robust_preamble(Samples(crate::CODEC_ROBUST_SYNC_SAMPLES), 1.0, key);
+    detect_candidates(samples, &template, threshold, max_candidates, "codec-robust preamble")
+}
+
+/// Preamble candidates for the narrowband profile, ranked like [`detect_preamble_candidates`]
+pub fn detect_narrowband_preamble_candidates(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    key: Option<NetworkKey>,
+) -> Vec<SyncCandidate> {
+    let template = generate_narrowband_preamble(Samples(crate::NARROWBAND_SYNC_SAMPLES), 1.0, key);
+    detect_candidates(samples, &template, threshold, max_candidates, "narrowband preamble")
+}
+
+/// Up to `max_candidates` separated peaks of `template` that clear the threshold
+fn detect_candidates(
+    samples: &[f32],
+    template: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    label: &str,
+) -> Vec<SyncCandidate> {
+    let threshold = threshold.clamped();
+
+    if samples.len() < template.len() || max_candidates == 0 {
+        return Vec::new();
+    }
+    if samples.len() > WINDOWED_SCAN_MIN_SAMPLES {
+        return detect_windowed(samples, template, threshold, max_candidates, label, SCAN_WINDOW_STEP);
+    }
+
+    let conditioned = condition_for_sync(samples);
+    let Some(mut scores) = sync_scores(&conditioned, template, label) else {
+        return Vec::new();
     };
+    let threshold_value = compute_threshold_value(&conditioned, threshold);
+    pick_peaks(&mut scores, threshold_value, max_candidates)
+}
 
-    let mut best_pos = 0;
-    let mut best_correlation = 0.0;
+/// Up to `max_candidates` separated peaks of `scores` above `threshold_value`,
+/// best first (the scores around each pick are zeroed)
+fn pick_peaks(scores: &mut [f32], threshold_value: f32, max_candidates: usize) -> Vec<SyncCandidate> {
+    let mut candidates = Vec::new();
+    while candidates.len() < max_candidates {
+        let mut best_pos = 0;
+        let mut best_score = 0.0;
+        for (i, &score) in scores.iter().enumerate() {
+            if score > best_score {
+                best_score = score;
+                best_pos = i;
+            }
+        }
+        if best_score <= threshold_value {
+            break;
+        }
 
-    // Calculate template energy once
-    let template_energy: f32 = template.iter().map(|x| x * x).sum();
+        candidates.push(SyncCandidate { position: best_pos, score: best_score });
 
-    // Build prefix-sum array of squared samples for O(1) window energy computation
-    let mut sq_prefix = vec![0.0; samples.len() + 1];
-    for k in 0..samples.len() {
-        sq_prefix[k + 1] = sq_prefix[k] + samples[k] * samples[k];
+        // Suppress the neighbourhood of this peak
+        let start = best_pos.saturating_sub(CANDIDATE_SEPARATION);
+        let end = (best_pos + CANDIDATE_SEPARATION + 1).min(scores.len());
+        scores[start..end].iter_mut().for_each(|s| *s = 0.0);
     }
 
-    // Iterate through valid positions and normalize correlation coefficients
-    for i in 0..=samples.len().saturating_sub(preamble_samples) {
-        // FFT correlation output at index (i + preamble_samples - 1) corresponds to window starting at i
-        let fft_index = i + preamble_samples - 1;
-        let raw_correlation = fft_correlation[fft_index];
+    candidates
+}
 
-        // Calculate window energy using O(1) prefix-sum lookup
-        let window_energy = sq_prefix[i + preamble_samples] - sq_prefix[i];
+/// [`detect_candidates`] in bounded memory: each `step` of start positions is
+/// scored on its own stretch of audio, from `SCAN_WARMUP_SAMPLES` before it to
+/// the end of a template starting at its last position
+///
+/// The clipping decision and the adaptive threshold still cover the whole
+/// input, and the windows' peaks are merged with the same separation, so a
+/// sync signal straddling a window boundary is found as in one pass.
+fn detect_windowed(
+    samples: &[f32],
+    template: &[f32],
+    threshold: DetectionThreshold,
+    max_candidates: usize,
+    label: &str,
+    step: usize,
+) -> Vec<SyncCandidate> {
+    let gate = clip_gate(samples);
+    // Peaks below the lowest threshold the scan could end up with are never needed
+    let floor = match threshold {
+        DetectionThreshold::Adaptive => adaptive_threshold(0.0),
+        DetectionThreshold::Fixed(value) => value,
+    };
 
-        // Compute normalized correlation coefficient
-        let denom = (window_energy * template_energy).sqrt();
-        let normalized_corr = if denom > 1e-10 {
-            (raw_correlation / denom).abs()
-        } else {
-            0.0
+    let positions = samples.len() - template.len() + 1;
+    let mut max_rms = 0.0f32;
+    let mut peaks = Vec::new();
+    for start in (0..positions).step_by(step) {
+        let end = (start + step).min(positions);
+        let audio_start = start.saturating_sub(SCAN_WARMUP_SAMPLES);
+        let conditioned = condition_with_gate(&samples[audio_start..end + template.len() - 1], gate);
+        max_rms = max_rms.max(compute_max_rms_from_windows(&conditioned));
+        let Some(mut scores) = sync_scores(&conditioned, template, label) else {
+            return Vec::new();
         };
+        let owned = &mut scores[start - audio_start..end - audio_start];
+        peaks.extend(
+            pick_peaks(owned, floor, max_candidates)
+                .into_iter()
+                .map(|peak| SyncCandidate { position: start + peak.position, ..peak }),
+        );
+    }
 
-        if normalized_corr > best_correlation {
-            best_correlation = normalized_corr;
-            best_pos = i;
+    let threshold_value = match threshold {
+        DetectionThreshold::Adaptive => adaptive_threshold(max_rms),
+        DetectionThreshold::Fixed(value) => value,
+    };
+    // Stable, so equal scores keep the earlier position as in a single pass
+    peaks.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut candidates: Vec<SyncCandidate> = Vec::new();
+    for peak in peaks {
+        if candidates.len() == max_candidates || peak.score <= threshold_value {
+            break;
+        }
+        if candidates.iter().all(|c| c.position.abs_diff(peak.position) > CANDIDATE_SEPARATION) {
+            candidates.push(peak);
         }
     }
+    candidates
+}
 
-    // Determine detection threshold
-    let threshold_value = compute_threshold_value(samples, threshold);
+/// Detect postamble using efficient cross-correlation
+/// Returns the position where the postamble (PRN noise burst) is most likely to start
+/// threshold: Specifies how to determine the detection threshold (Adaptive or Fixed)
+/// Fixed thresholds outside [0.001, 1.0] are clamped
+pub fn detect_postamble(samples: &[f32], threshold: DetectionThreshold) -> Option<usize> {
+    detect_keyed_postamble(samples, threshold, None)
+}
 
-    if best_correlation > threshold_value {
-        Some(best_pos)
-    } else {
-        None
-    }
+/// [`detect_postamble`] for the sync signals of `key`
+pub fn detect_keyed_postamble(samples: &[f32], threshold: DetectionThreshold, key: Option<NetworkKey>) -> Option<usize> {
+    detect_keyed_postamble_candidate(samples, threshold, key).map(|candidate| candidate.position)
+}
+
+/// [`detect_keyed_postamble`] with the correlation score of the detection
+pub fn detect_keyed_postamble_candidate(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SyncCandidate> {
+    // Generate expected postamble signal pattern (different seed = different pattern)
+    let template = generate_keyed_postamble(Samples(crate::POSTAMBLE_SAMPLES), 1.0, key);
+    detect_best_candidate(samples, &template, threshold, "postamble")
+}
+
+/// Detect the short-frame postamble
+pub fn detect_short_postamble(samples: &[f32], threshold: DetectionThreshold, key: Option<NetworkKey>) -> Option<usize> {
+    detect_short_postamble_candidate(samples, threshold, key).map(|candidate| candidate.position)
+}
+
+/// [`detect_short_postamble`] with the correlation score of the detection
+pub fn detect_short_postamble_candidate(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SyncCandidate> {
+    let template = generate_short_postamble(Samples(crate::SHORT_SYNC_SAMPLES), 1.0, key);
+    detect_best_candidate(samples, &template, threshold, "short postamble")
+}
+
+/// Detect the telephony-profile postamble, with its correlation score
+pub fn detect_telephony_postamble_candidate(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SyncCandidate> {
+    let template = generate_telephony_postamble(Samples(crate::TELEPHONY_SYNC_SAMPLES), 1.0, key);
+    detect_best_candidate(samples, &template, threshold, "telephony postamble")
+}
+
+/// Detect the codec-robust-profile postamble, with its correlation score
+pub fn detect_codec_robust_postamble_candidate(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SyncCandidate> {
+    let template = generate_codec_robust_postamble(Samples(crate::CODEC_ROBUST_SYNC_SAMPLES), 1.0, key);
+    detect_best_candidate(samples, &template, threshold, "codec-robust postamble")
+}
+
+/// Detect the narrowband-profile postamble, with its correlation score
+pub fn detect_narrowband_postamble_candidate(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<SyncCandidate> {
+    let template = generate_narrowband_postamble(Samples(crate::NARROWBAND_SYNC_SAMPLES), 1.0, key);
+    detect_best_candidate(samples, &template, threshold, "narrowband postamble")
+}
+
+/// Detect fountain mode preamble (three-note whistle) using efficient FFT-based cross-correlation
+/// Returns the position where the fountain preamble is most likely to start
+/// threshold: Specifies how to determine the detection threshold (Adaptive or Fixed)
+/// Fixed thresholds outside [0.001, 1.0] are clamped
+pub fn detect_fountain_preamble(samples: &[f32], threshold: DetectionThreshold) -> Option<usize> {
+    detect_keyed_fountain_preamble(samples, threshold, None)
+}
+
+/// [`detect_fountain_preamble`] for the sync signals of `key`
+pub fn detect_keyed_fountain_preamble(
+    samples: &[f32],
+    threshold: DetectionThreshold,
+    key: Option<NetworkKey>,
+) -> Option<usize> {
+    // Generate expected fountain preamble signal pattern (three-note whistle)
+    let template = generate_keyed_fountain_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, key);
+    detect_best(samples, &template, threshold, "fountain preamble")
+}
+
+/// Fountain preamble detection against a prepared `template` (see `crate::melody`)
+pub fn detect_fountain_template(samples: &[f32], template: &[f32], threshold: DetectionThreshold) -> Option<usize> {
+    detect_best(samples, template, threshold, "fountain preamble")
+}
+
+/// [`detect_fountain_template`] with the correlation score of the position found
+pub(crate) fn detect_fountain_template_candidate(
+    samples: &[f32],
+    template: &[f32],
+    threshold: DetectionThreshold,
+) -> Option<SyncCandidate> {
+    detect_best_candidate(samples, template, threshold, "fountain preamble")
 }
 
 #[cfg(test)]
@@ -944,6 +1652,14 @@ mod tests {
         assert!(result.is_some(), "Weak signal should be detected with adaptive threshold");
     }
 
+    #[test]
+    fn test_threshold_value_for() {
+        let preamble = create_preamble(0.5);
+        assert_eq!(DetectionThreshold::Adaptive.value_for(&preamble), 0.4);
+        assert_eq!(DetectionThreshold::Adaptive.value_for(&vec![0.0; 1000]), 0.3);
+        assert_eq!(DetectionThreshold::Fixed(0.25).value_for(&preamble), 0.25);
+    }
+
     #[test]
     fn test_preamble_false_positive_rejection() {
         // Random noise should not trigger false positives
@@ -956,6 +1672,54 @@ mod tests {
         let _ = result;
     }
 
+    #[test]
+    fn test_network_key_sync_isolation() {
+        let key_a = Some(NetworkKey::new(b"app-a"));
+        let key_b = Some(NetworkKey::new(b"app-b"));
+        assert_ne!(key_a, key_b);
+        assert_eq!(key_a, Some(NetworkKey::new(b"app-a")));
+
+        let frame_for = |key: Option<NetworkKey>| {
+            let mut samples = vec![0.0; 3000];
+            samples.extend(generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 0.5, key));
+            samples.extend(vec![0.0; 3000]);
+            samples
+        };
+        let position = detect_keyed_preamble(&frame_for(key_a), DetectionThreshold::Adaptive, key_a);
+        assert!(position.is_some_and(|pos| pos.abs_diff(3000) < 10), "{:?}", position);
+
+        // Other deployments (keyed or not) are invisible, and vice versa
+        for (sent, listening) in [(key_b, key_a), (None, key_a), (key_a, None)] {
+            assert_eq!(detect_keyed_preamble(&frame_for(sent), DetectionThreshold::Adaptive, listening), None);
+        }
+
+        // Pre/postamble and fountain preamble of one key are distinct from each other
+        let postamble = generate_keyed_postamble(Samples(crate::POSTAMBLE_SAMPLES), 0.5, key_a);
+        assert_eq!(detect_keyed_preamble(&postamble, DetectionThreshold::Adaptive, key_a), None);
+        let fountain = generate_keyed_fountain_preamble(Samples(crate::PREAMBLE_SAMPLES), 0.5, key_a);
+        assert_eq!(detect_keyed_postamble(&fountain, DetectionThreshold::Adaptive, key_a), None);
+    }
+
+    #[test]
+    fn test_keyed_narrowband_sync_isolation() {
+        let key_a = Some(NetworkKey::new(b"app-a"));
+        let key_b = Some(NetworkKey::new(b"app-b"));
+        let frame_for = |key: Option<NetworkKey>| {
+            let mut samples = vec![0.0; 3000];
+            samples.extend(generate_narrowband_preamble(Samples(crate::NARROWBAND_SYNC_SAMPLES), 0.5, key));
+            samples.extend(vec![0.0; 3000]);
+            samples
+        };
+        let detect = |samples: &[f32], key| detect_narrowband_preamble_candidates(samples, DetectionThreshold::Adaptive, 1, key);
+        let found = detect(&frame_for(key_a), key_a);
+        assert!(found.first().is_some_and(|c| c.position.abs_diff(3000) < 10), "{:?}", found);
+        for (sent, listening) in [(key_b, key_a), (None, key_a), (key_a, None)] {
+            assert!(detect(&frame_for(sent), listening).is_empty(), "{:?} heard by {:?}", sent, listening);
+        }
+        let postamble = generate_narrowband_postamble(Samples(crate::NARROWBAND_SYNC_SAMPLES), 0.5, key_a);
+        assert!(detect(&postamble, key_a).is_empty());
+    }
+
     #[test]
     fn test_preamble_attenuation_series() {
         // Test series of attenuated signals to verify graceful degradation
@@ -1251,39 +2015,33 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Invalid fixed detection threshold")]
-    fn test_fixed_threshold_below_minimum_panics() {
-        // Test that threshold below 0.001 causes panic
-        let preamble = create_preamble(0.5);
-        let signal = preamble.clone();
-        detect_preamble(&signal, DetectionThreshold::Fixed(0.0005));
-    }
-
-    #[test]
-    #[should_panic(expected = "Invalid fixed detection threshold")]
-    fn test_fixed_threshold_zero_panics() {
-        // Test that threshold of exactly 0.0 causes panic
-        let preamble = create_preamble(0.5);
-        let signal = preamble.clone();
-        detect_preamble(&signal, DetectionThreshold::Fixed(0.0));
-    }
-
-    #[test]
-    #[should_panic(expected = "Invalid fixed detection threshold")]
-    fn test_fixed_threshold_negative_panics() {
-        // Test that negative threshold causes panic
+    fn test_fixed_threshold_out_of_range_is_clamped() {
+        // Out-of-range thresholds behave like the nearest valid one instead of panicking
         let preamble = create_preamble(0.5);
-        let signal = preamble.clone();
-        detect_preamble(&signal, DetectionThreshold::Fixed(-0.1));
+        let mut signal = preamble.clone();
+        signal.extend_from_slice(&vec![0.0; 1000]);
+        for value in [0.0005, 0.0, -0.1] {
+            assert_eq!(
+                detect_preamble(&signal, DetectionThreshold::Fixed(value)),
+                detect_preamble(&signal, DetectionThreshold::Fixed(0.001))
+            );
+        }
+        for value in [1.1, f32::NAN, f32::INFINITY] {
+            assert!(detect_preamble(&signal, DetectionThreshold::Fixed(value)).is_none());
+        }
     }
 
     #[test]
-    #[should_panic(expected = "Invalid fixed detection threshold")]
-    fn test_fixed_threshold_above_maximum_panics() {
-        // Test that threshold > 1.0 causes panic
-        let preamble = create_preamble(0.5);
-        let signal = preamble.clone();
-        detect_preamble(&signal, DetectionThreshold::Fixed(1.1));
+    fn test_fixed_threshold_validation() {
+        assert_eq!(DetectionThreshold::fixed(0.4).unwrap(), DetectionThreshold::Fixed(0.4));
+        assert_eq!(DetectionThreshold::fixed(0.001).unwrap(), DetectionThreshold::Fixed(0.001));
+        assert_eq!(DetectionThreshold::fixed(1.0).unwrap(), DetectionThreshold::Fixed(1.0));
+        for value in [0.0005, 0.0, -0.1, 1.1, f32::NAN] {
+            assert!(matches!(DetectionThreshold::fixed(value), Err(AudioModemError::InvalidConfig(_))), "{}", value);
+        }
+        assert!(DetectionThreshold::Adaptive.validate().is_ok());
+        assert_eq!(DetectionThreshold::Fixed(f32::NAN).clamped(), DetectionThreshold::Fixed(1.0));
+        assert_eq!(DetectionThreshold::Fixed(-3.0).clamped(), DetectionThreshold::Fixed(0.001));
     }
 
     #[test]
@@ -1326,21 +2084,12 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Invalid fixed detection threshold")]
-    fn test_postamble_fixed_threshold_below_minimum_panics() {
-        // Test that postamble also validates minimum threshold
-        let postamble = create_postamble(0.5);
-        let signal = postamble.clone();
-        detect_postamble(&signal, DetectionThreshold::Fixed(0.0005));
-    }
-
-    #[test]
-    #[should_panic(expected = "Invalid fixed detection threshold")]
-    fn test_postamble_fixed_threshold_above_maximum_panics() {
-        // Test that postamble validates maximum threshold
+    fn test_postamble_fixed_threshold_out_of_range_is_clamped() {
         let postamble = create_postamble(0.5);
-        let signal = postamble.clone();
-        detect_postamble(&signal, DetectionThreshold::Fixed(1.5));
+        let mut signal = vec![0.0; 1000];
+        signal.extend_from_slice(&postamble);
+        assert!(detect_postamble(&signal, DetectionThreshold::Fixed(0.0005)).is_some());
+        assert!(detect_postamble(&signal, DetectionThreshold::Fixed(1.5)).is_none());
     }
 
     // ========================================================================
@@ -1469,4 +2218,103 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_preamble_candidates_ranked_and_separated() {
+        let preamble = create_preamble(0.5);
+        let mut signal = vec![0.0; 2000];
+        signal.extend_from_slice(&preamble);
+        signal.extend_from_slice(&vec![0.0; 6000]);
+        signal.extend(preamble.iter().map(|s| s * 0.5));
+        signal.extend_from_slice(&vec![0.0; 2000]);
+        // Smear the second copy so its normalized score drops below the first
+        for (i, s) in signal[12000..16000].iter_mut().enumerate() {
+            *s += 0.05 * ((i * 7919) % 13) as f32 / 13.0 - 0.025;
+        }
+
+        let candidates = detect_preamble_candidates(&signal, DetectionThreshold::Fixed(0.3), 5);
+        assert!(candidates.len() >= 2, "expected both copies, got {:?}", candidates);
+        assert!(candidates[0].position.abs_diff(2000) <= 10);
+        assert!(candidates[1].position.abs_diff(12000) <= 10);
+        assert!(candidates[0].score >= candidates[1].score);
+        for pair in candidates.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+        for (i, a) in candidates.iter().enumerate() {
+            for b in &candidates[i + 1..] {
+                assert!(a.position.abs_diff(b.position) > CANDIDATE_SEPARATION);
+            }
+        }
+
+        // Best candidate agrees with detect_preamble
+        assert_eq!(
+            detect_preamble(&signal, DetectionThreshold::Fixed(0.3)),
+            Some(candidates[0].position)
+        );
+        assert!(detect_preamble_candidates(&signal, DetectionThreshold::Fixed(0.3), 0).is_empty());
+    }
+
+    #[test]
+    fn test_windowed_scan_matches_single_pass() {
+        use rand::{Rng, SeedableRng};
+
+        // Copies of the preamble in noise, the second straddling a window boundary
+        let step = 2 * SAMPLE_RATE;
+        let preamble = create_preamble(0.4);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1680);
+        let mut signal: Vec<f32> = (0..12 * SAMPLE_RATE).map(|_| rng.gen_range(-0.01..0.01f32)).collect();
+        for (start, gain) in [(5000, 1.0), (2 * step - 1500, 0.6), (9 * SAMPLE_RATE, 0.8)] {
+            for (s, p) in signal[start..].iter_mut().zip(&preamble) {
+                *s += gain * p;
+            }
+        }
+
+        let template = generate_keyed_preamble(Samples(crate::PREAMBLE_SAMPLES), 1.0, None);
+        for threshold in [DetectionThreshold::Adaptive, DetectionThreshold::Fixed(0.2)] {
+            let single = detect_candidates(&signal, &template, threshold, 5, "preamble");
+            let windowed = detect_windowed(&signal, &template, threshold, 5, "preamble", step);
+            assert_eq!(single.len(), 3);
+            assert_eq!(windowed.len(), single.len());
+            for (a, b) in single.iter().zip(&windowed) {
+                assert_eq!(a.position, b.position);
+                assert!((a.score - b.score).abs() < 5e-3, "{} vs {}", a.score, b.score);
+            }
+        }
+        let best = detect_windowed(&signal, &template, DetectionThreshold::Adaptive, 1, "preamble", step);
+        assert_eq!(best[0].position, detect_preamble(&signal, DetectionThreshold::Adaptive).unwrap());
+        // Clean copies score about 0.9995, so only the top threshold rejects them all
+        let strict = DetectionThreshold::Fixed(1.0);
+        assert!(detect_candidates(&signal, &template, strict, 5, "preamble").is_empty());
+        assert!(detect_windowed(&signal, &template, strict, 5, "preamble", step).is_empty());
+    }
+
+    #[test]
+    fn test_estimate_playback_speed() {
+        let preamble = generate_preamble(crate::PREAMBLE_SAMPLES, 0.5);
+        for permille in [950, 980, 1000, 1032, 1050] {
+            let mut signal = vec![0.0; 3000];
+            signal.extend(resample_audio(&preamble, SAMPLE_RATE * permille / 1000, SAMPLE_RATE));
+            signal.extend_from_slice(&[0.0; 3000]);
+            let estimate = estimate_keyed_playback_speed(&signal, DetectionThreshold::Adaptive, None).unwrap();
+            assert!(estimate.permille.abs_diff(permille as u32) <= 2, "{} vs {:?}", permille, estimate);
+            assert!(estimate.candidate.position.abs_diff(3000) <= 40, "{:?}", estimate);
+        }
+        assert!(estimate_keyed_playback_speed(&[0.0; 8000], DetectionThreshold::Adaptive, None).is_none());
+    }
+
+    #[test]
+    fn test_estimate_source_rate() {
+        let preamble = generate_preamble(crate::PREAMBLE_SAMPLES, 0.5);
+        for rate in [16_000, 44_100, 48_000] {
+            let mut signal = vec![0.0; 3 * rate / 16];
+            signal.extend(resample_audio(&preamble, SAMPLE_RATE, rate));
+            signal.extend(vec![0.0; 3 * rate / 16]);
+            let estimate =
+                estimate_keyed_source_rate(&signal, &COMMON_SOURCE_RATES, DetectionThreshold::Adaptive, None).unwrap();
+            assert_eq!(estimate.rate, rate, "{:?}", estimate);
+            assert!(estimate.candidate.position.abs_diff(3 * rate / 16) <= rate / 400, "{:?}", estimate);
+        }
+        assert!(estimate_keyed_source_rate(&[0.0; 48_000], &COMMON_SOURCE_RATES, DetectionThreshold::Adaptive, None)
+            .is_none());
+        assert!(estimate_keyed_source_rate(&[0.5; 48_000], &[0], DetectionThreshold::Adaptive, None).is_none());
+    }
 }
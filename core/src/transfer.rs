@@ -0,0 +1,428 @@
+use crate::decoder_fsk::DecoderFsk;
+use crate::encoder_fsk::{EncoderFsk, EncoderPadding};
+use crate::error::{AudioModemError, Result};
+use crate::MAX_PAYLOAD_SIZE;
+
+/// First byte of every transfer part, so stray frames are not mistaken for parts
+const PART_MAGIC: u8 = 0xA7;
+
+/// Part header: magic (1) + transfer ID (4) + part index (2) + part count (2) + part size (2) + total length (4)
+pub const PART_HEADER_SIZE: usize = 15;
+
+/// Largest chunk of file data a single part can carry
+pub const MAX_PART_DATA_SIZE: usize = MAX_PAYLOAD_SIZE - PART_HEADER_SIZE;
+
+/// Default chunk size, short enough that each part is a recording of a few seconds
+pub const DEFAULT_PART_DATA_SIZE: usize = 200;
+
+/// Magic prefix of a serialized [`TransferSession`]
+const STATE_MAGIC: &[u8; 4] = b"TWXS";
+
+/// Transfer parameters shared by every part of one transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TransferInfo {
+    transfer_id: u32,
+    part_count: u16,
+    part_size: u16,
+    total_len: u32,
+}
+
+impl TransferInfo {
+    const SIZE: usize = 12;
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.transfer_id.to_be_bytes());
+        out.extend_from_slice(&self.part_count.to_be_bytes());
+        out.extend_from_slice(&self.part_size.to_be_bytes());
+        out.extend_from_slice(&self.total_len.to_be_bytes());
+    }
+
+    fn read(data: &[u8]) -> Self {
+        Self {
+            transfer_id: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+            part_count: u16::from_be_bytes([data[4], data[5]]),
+            part_size: u16::from_be_bytes([data[6], data[7]]),
+            total_len: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+        }
+    }
+
+    /// Length of the data chunk carried by a part (the last part may be short)
+    fn chunk_len(&self, index: u16) -> usize {
+        let start = index as usize * self.part_size as usize;
+        (self.total_len as usize - start).min(self.part_size as usize)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let parts_needed = (self.total_len as usize).div_ceil(self.part_size.max(1) as usize).max(1);
+        if self.part_size == 0
+            || self.part_size as usize > MAX_PART_DATA_SIZE
+            || self.part_count as usize != parts_needed
+        {
+            return Err(AudioModemError::InvalidTransferPart(
+                "inconsistent part count, size or length".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Derive a stable transfer ID from the file contents (FNV-1a)
+pub fn default_transfer_id(data: &[u8]) -> u32 {
+    data.iter()
+        .fold(0x811C_9DC5u32, |hash, &b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Splits a large payload into numbered parts that can be sent as separate frames
+///
+/// Each part is a regular frame whose payload is
+/// [magic u8][transfer_id u32][part_index u16][part_count u16][part_size u16][total_len u32][chunk]
+/// so parts can be recorded in any order, across any number of sessions.
+pub struct TransferEncoder {
+    info: TransferInfo,
+    data: Vec<u8>,
+    encoder: EncoderFsk,
+}
+
+impl TransferEncoder {
+    pub fn new(transfer_id: u32, data: &[u8], part_size: usize) -> Result<Self> {
+        if part_size == 0 || part_size > MAX_PART_DATA_SIZE {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "part size must be between 1 and {} bytes",
+                MAX_PART_DATA_SIZE
+            )));
+        }
+        let part_count = data.len().div_ceil(part_size).max(1);
+        if part_count > u16::MAX as usize || data.len() > u32::MAX as usize {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "payload of {} bytes needs too many parts",
+                data.len()
+            )));
+        }
+
+        Ok(Self {
+            info: TransferInfo {
+                transfer_id,
+                part_count: part_count as u16,
+                part_size: part_size as u16,
+                total_len: data.len() as u32,
+            },
+            data: data.to_vec(),
+            encoder: EncoderFsk::new()?,
+        })
+    }
+
+    pub fn transfer_id(&self) -> u32 {
+        self.info.transfer_id
+    }
+
+    pub fn part_count(&self) -> u16 {
+        self.info.part_count
+    }
+
+    /// Set the silence padding used around each encoded part
+    pub fn set_padding(&mut self, padding: EncoderPadding) {
+        self.encoder.set_padding(padding);
+    }
+
+    /// Frame payload for one part, header included
+    pub fn part_payload(&self, index: u16) -> Result<Vec<u8>> {
+        if index >= self.info.part_count {
+            return Err(AudioModemError::InvalidConfig(format!(
+                "part {} out of range (transfer has {} parts)",
+                index, self.info.part_count
+            )));
+        }
+        let start = index as usize * self.info.part_size as usize;
+        let chunk = &self.data[start..start + self.info.chunk_len(index)];
+
+        let mut payload = Vec::with_capacity(PART_HEADER_SIZE + chunk.len());
+        payload.push(PART_MAGIC);
+        payload.extend_from_slice(&self.info.transfer_id.to_be_bytes());
+        payload.extend_from_slice(&index.to_be_bytes());
+        payload.extend_from_slice(&self.info.part_count.to_be_bytes());
+        payload.extend_from_slice(&self.info.part_size.to_be_bytes());
+        payload.extend_from_slice(&self.info.total_len.to_be_bytes());
+        payload.extend_from_slice(chunk);
+        Ok(payload)
+    }
+
+    /// Encode one part as a standalone frame
+    pub fn encode_part(&mut self, index: u16) -> Result<Vec<f32>> {
+        let payload = self.part_payload(index)?;
+        self.encoder.encode(&payload)
+    }
+
+    /// Parts the receiver still needs, according to its resume token
+    pub fn missing_parts_from_token(&self, token: &[u8]) -> Result<Vec<u16>> {
+        let bitmap_len = (self.info.part_count as usize).div_ceil(8);
+        if token.len() != 6 + bitmap_len {
+            return Err(AudioModemError::InvalidTransferPart("malformed resume token".to_string()));
+        }
+        let transfer_id = u32::from_be_bytes([token[0], token[1], token[2], token[3]]);
+        let part_count = u16::from_be_bytes([token[4], token[5]]);
+        if transfer_id != self.info.transfer_id || part_count != self.info.part_count {
+            return Err(AudioModemError::InvalidTransferPart(
+                "resume token is for a different transfer".to_string(),
+            ));
+        }
+        let bitmap = &token[6..];
+        Ok((0..part_count)
+            .filter(|&i| bitmap[i as usize / 8] & (1 << (i % 8)) == 0)
+            .collect())
+    }
+}
+
+/// Receiver-side state of a chunked transfer, kept between recording sessions
+///
+/// Feed it decoded frame payloads (or whole recordings) as they arrive, persist it
+/// with [`TransferSession::to_bytes`] between runs, and hand [`TransferSession::resume_token`]
+/// or [`TransferSession::missing_parts`] back to the sender so it replays only what is missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferSession {
+    info: Option<TransferInfo>,
+    parts: Vec<Option<Vec<u8>>>,
+}
+
+impl TransferSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Transfer this session is bound to, once the first part has arrived
+    pub fn transfer_id(&self) -> Option<u32> {
+        self.info.map(|info| info.transfer_id)
+    }
+
+    /// Total number of parts, once the first part has arrived
+    pub fn part_count(&self) -> Option<u16> {
+        self.info.map(|info| info.part_count)
+    }
+
+    pub fn received_count(&self) -> usize {
+        self.parts.iter().filter(|p| p.is_some()).count()
+    }
+
+    /// Indices of the parts not received yet (empty before the first part arrives)
+    pub fn missing_parts(&self) -> Vec<u16> {
+        self.parts
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.is_none())
+            .map(|(i, _)| i as u16)
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.info.is_some() && self.parts.iter().all(Option::is_some)
+    }
+
+    /// Store a decoded frame payload. Returns true if it was a new part,
+    /// false if the part had already been received.
+    pub fn accept_part(&mut self, payload: &[u8]) -> Result<bool> {
+        if payload.len() < PART_HEADER_SIZE || payload[0] != PART_MAGIC {
+            return Err(AudioModemError::InvalidTransferPart("not a transfer part".to_string()));
+        }
+        let index = u16::from_be_bytes([payload[5], payload[6]]);
+        let mut header = payload[1..5].to_vec();
+        header.extend_from_slice(&payload[7..PART_HEADER_SIZE]);
+        let info = TransferInfo::read(&header);
+        info.validate()?;
+
+        match self.info {
+            Some(current) if current != info => {
+                return Err(AudioModemError::InvalidTransferPart(format!(
+                    "part belongs to transfer {:08x}, session is for {:08x}",
+                    info.transfer_id, current.transfer_id
+                )));
+            }
+            Some(_) => {}
+            None => {
+                self.info = Some(info);
+                self.parts = vec![None; info.part_count as usize];
+            }
+        }
+
+        let chunk = &payload[PART_HEADER_SIZE..];
+        if index >= info.part_count || chunk.len() != info.chunk_len(index) {
+            return Err(AudioModemError::InvalidTransferPart(format!("part {} has a bad length", index)));
+        }
+        let slot = &mut self.parts[index as usize];
+        if slot.is_some() {
+            return Ok(false);
+        }
+        *slot = Some(chunk.to_vec());
+        Ok(true)
+    }
+
+    /// Decode a recording of one part and store it. Returns the part index and whether it was new.
+    pub fn decode_recording(&mut self, decoder: &mut DecoderFsk, samples: &[f32]) -> Result<(u16, bool)> {
+        let payload = decoder.decode(samples)?;
+        let is_new = self.accept_part(&payload)?;
+        Ok((u16::from_be_bytes([payload[5], payload[6]]), is_new))
+    }
+
+    /// Reassemble the full payload once every part has arrived
+    pub fn assemble(&self) -> Option<Vec<u8>> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(self.parts.iter().flatten().flatten().copied().collect())
+    }
+
+    /// Compact acknowledgement for the sender: [transfer_id u32][part_count u16][received bitmap]
+    pub fn resume_token(&self) -> Option<Vec<u8>> {
+        let info = self.info?;
+        let mut token = Vec::with_capacity(6 + self.parts.len().div_ceil(8));
+        token.extend_from_slice(&info.transfer_id.to_be_bytes());
+        token.extend_from_slice(&info.part_count.to_be_bytes());
+        token.extend(self.received_bitmap());
+        Some(token)
+    }
+
+    fn received_bitmap(&self) -> Vec<u8> {
+        let mut bitmap = vec![0u8; self.parts.len().div_ceil(8)];
+        for (i, part) in self.parts.iter().enumerate() {
+            if part.is_some() {
+                bitmap[i / 8] |= 1 << (i % 8);
+            }
+        }
+        bitmap
+    }
+
+    /// Serialize the partial state: [magic "TWXS"][info][received bitmap][received chunks in order]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = STATE_MAGIC.to_vec();
+        if let Some(info) = self.info {
+            info.write(&mut out);
+            out.extend(self.received_bitmap());
+            for chunk in self.parts.iter().flatten() {
+                out.extend_from_slice(chunk);
+            }
+        }
+        out
+    }
+
+    /// Restore a session saved with [`TransferSession::to_bytes`]
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let invalid = || AudioModemError::InvalidTransferPart("corrupt session state".to_string());
+        let body = data.strip_prefix(STATE_MAGIC).ok_or_else(invalid)?;
+        if body.is_empty() {
+            return Ok(Self::new());
+        }
+        if body.len() < TransferInfo::SIZE {
+            return Err(invalid());
+        }
+
+        let info = TransferInfo::read(body);
+        info.validate().map_err(|_| invalid())?;
+        let bitmap_len = (info.part_count as usize).div_ceil(8);
+        let bitmap = body.get(TransferInfo::SIZE..TransferInfo::SIZE + bitmap_len).ok_or_else(invalid)?;
+
+        let mut offset = TransferInfo::SIZE + bitmap_len;
+        let mut parts = Vec::with_capacity(info.part_count as usize);
+        for i in 0..info.part_count {
+            if bitmap[i as usize / 8] & (1 << (i % 8)) == 0 {
+                parts.push(None);
+                continue;
+            }
+            let len = info.chunk_len(i);
+            let chunk = body.get(offset..offset + len).ok_or_else(invalid)?;
+            parts.push(Some(chunk.to_vec()));
+            offset += len;
+        }
+        if offset != body.len() {
+            return Err(invalid());
+        }
+
+        Ok(Self { info: Some(info), parts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + 3) as u8).collect()
+    }
+
+    #[test]
+    fn test_parts_reassemble_in_any_order() {
+        let data = test_data(1000);
+        let encoder = TransferEncoder::new(42, &data, 300).unwrap();
+        assert_eq!(encoder.part_count(), 4);
+
+        let mut session = TransferSession::new();
+        for index in [3, 1, 0, 2] {
+            assert!(session.accept_part(&encoder.part_payload(index).unwrap()).unwrap());
+        }
+        assert!(!session.accept_part(&encoder.part_payload(1).unwrap()).unwrap());
+        assert!(session.is_complete());
+        assert_eq!(session.assemble().unwrap(), data);
+    }
+
+    #[test]
+    fn test_session_persists_and_resumes() {
+        let data = test_data(50 * 1024);
+        let encoder = TransferEncoder::new(default_transfer_id(&data), &data, MAX_PART_DATA_SIZE).unwrap();
+        let count = encoder.part_count();
+
+        // First session catches the even parts only
+        let mut session = TransferSession::new();
+        for index in (0..count).step_by(2) {
+            session.accept_part(&encoder.part_payload(index).unwrap()).unwrap();
+        }
+        assert!(!session.is_complete());
+        assert!(session.assemble().is_none());
+
+        let saved = session.to_bytes();
+        let mut restored = TransferSession::from_bytes(&saved).unwrap();
+        assert_eq!(restored, session);
+
+        let token = restored.resume_token().unwrap();
+        let missing = encoder.missing_parts_from_token(&token).unwrap();
+        assert_eq!(missing, restored.missing_parts());
+        assert_eq!(missing, (1..count).step_by(2).collect::<Vec<_>>());
+
+        for index in missing {
+            restored.accept_part(&encoder.part_payload(index).unwrap()).unwrap();
+        }
+        assert_eq!(restored.assemble().unwrap(), data);
+        assert!(TransferSession::from_bytes(&restored.to_bytes()).unwrap().is_complete());
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_malformed_parts() {
+        let first = TransferEncoder::new(1, &test_data(500), 100).unwrap();
+        let second = TransferEncoder::new(2, &test_data(500), 100).unwrap();
+
+        let mut session = TransferSession::new();
+        session.accept_part(&first.part_payload(0).unwrap()).unwrap();
+        assert!(session.accept_part(&second.part_payload(1).unwrap()).is_err());
+        assert!(session.accept_part(b"plain message").is_err());
+
+        let mut truncated = first.part_payload(1).unwrap();
+        truncated.pop();
+        assert!(session.accept_part(&truncated).is_err());
+        assert_eq!(session.missing_parts(), vec![1, 2, 3, 4]);
+
+        assert!(second.missing_parts_from_token(&session.resume_token().unwrap()).is_err());
+        assert!(TransferSession::from_bytes(b"TWXS\x00").is_err());
+        assert_eq!(TransferSession::from_bytes(&TransferSession::new().to_bytes()).unwrap(), TransferSession::new());
+    }
+
+    #[test]
+    fn test_empty_payload_is_single_part() {
+        let mut encoder = TransferEncoder::new(7, &[], DEFAULT_PART_DATA_SIZE).unwrap();
+        assert_eq!(encoder.part_count(), 1);
+        assert!(TransferEncoder::new(7, &[1], MAX_PART_DATA_SIZE + 1).is_err());
+
+        let samples = encoder.encode_part(0).unwrap();
+        let mut session = TransferSession::new();
+        let (index, is_new) = session
+            .decode_recording(&mut DecoderFsk::new().unwrap(), &samples)
+            .unwrap();
+        assert_eq!((index, is_new), (0, true));
+        assert_eq!(session.assemble().unwrap(), Vec::<u8>::new());
+    }
+}
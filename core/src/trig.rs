@@ -0,0 +1,161 @@
+//! Sine, cosine and the other transcendental functions of the signal path
+//!
+//! `f32::sin` comes from the target's libm (glibc, musl, the Rust port used on
+//! wasm32, ...), and those differ in the last bit for some inputs. With the
+//! `deterministic` feature the modulator, sync signals, fade-in, limiter and
+//! pre-emphasis gains use polynomials built from IEEE basic arithmetic only (Rust never fuses
+//! multiply-adds on its own), so encoded samples are bit-identical on every
+//! target, e.g. for golden-file tests shared between native and WASM builds.
+
+/// Sine of `x` radians
+#[inline]
+pub(crate) fn sin(x: f32) -> f32 {
+    #[cfg(feature = "deterministic")]
+    {
+        portable_sin(x as f64, 0) as f32
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        x.sin()
+    }
+}
+
+/// Cosine of `x` radians
+#[inline]
+pub(crate) fn cos(x: f32) -> f32 {
+    #[cfg(feature = "deterministic")]
+    {
+        portable_sin(x as f64, 1) as f32
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        x.cos()
+    }
+}
+
+/// Hyperbolic tangent of `x`
+#[inline]
+pub(crate) fn tanh(x: f32) -> f32 {
+    #[cfg(feature = "deterministic")]
+    {
+        let e = portable_exp(-2.0 * (x as f64).abs());
+        ((1.0 - e) / (1.0 + e)).copysign(x as f64) as f32
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        x.tanh()
+    }
+}
+
+/// Linear amplitude of a level `db` decibels above 1
+#[inline]
+pub(crate) fn db_to_amplitude(db: f32) -> f32 {
+    #[cfg(feature = "deterministic")]
+    {
+        portable_exp(db as f64 * std::f64::consts::LN_10 / 20.0) as f32
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        10f32.powf(db / 20.0)
+    }
+}
+
+/// pi/2 split in two (fdlibm's `pio2_1`/`pio2_1t`): the high part has 33
+/// significant bits, so `k * PIO2_HI` is exact for the phases we generate
+#[cfg(any(feature = "deterministic", test))]
+const PIO2_HI: f64 = 1.570_796_326_734_125_6;
+#[cfg(any(feature = "deterministic", test))]
+const PIO2_LO: f64 = 6.077_100_506_506_192e-11;
+
+/// sin(x + quarter_turns * pi/2)
+///
+/// Reduces x to r in [-pi/4, pi/4] and evaluates Taylor polynomials of sin
+/// and cos on r, which are accurate to well below f32 precision there.
+#[cfg(any(feature = "deterministic", test))]
+fn portable_sin(x: f64, quarter_turns: i64) -> f64 {
+    let k = (x * std::f64::consts::FRAC_2_PI + 0.5).floor();
+    let r = (x - k * PIO2_HI) - k * PIO2_LO;
+    let r2 = r * r;
+    let sin_r = r + r * r2
+        * (-1.0 / 6.0
+            + r2 * (1.0 / 120.0
+                + r2 * (-1.0 / 5040.0
+                    + r2 * (1.0 / 362_880.0
+                        + r2 * (-1.0 / 39_916_800.0 + r2 * (1.0 / 6_227_020_800.0 + r2 * (-1.0 / 1_307_674_368_000.0)))))));
+    let cos_r = 1.0
+        + r2 * (-1.0 / 2.0
+            + r2 * (1.0 / 24.0
+                + r2 * (-1.0 / 720.0
+                    + r2 * (1.0 / 40_320.0
+                        + r2 * (-1.0 / 3_628_800.0 + r2 * (1.0 / 479_001_600.0 + r2 * (-1.0 / 87_178_291_200.0)))))));
+    match (k as i64 + quarter_turns).rem_euclid(4) {
+        0 => sin_r,
+        1 => cos_r,
+        2 => -sin_r,
+        _ => -cos_r,
+    }
+}
+
+/// ln 2 split in two (fdlibm's `ln2_hi`/`ln2_lo`), so `k * LN2_HI` is exact
+#[cfg(any(feature = "deterministic", test))]
+const LN2_HI: f64 = 6.931_471_803_691_238e-1;
+#[cfg(any(feature = "deterministic", test))]
+const LN2_LO: f64 = 1.908_214_929_270_587_7e-10;
+
+/// e^x
+///
+/// Reduces x to r in [-ln2/2, ln2/2] and evaluates the Taylor polynomial of
+/// e^r, then scales by 2^k through the exponent bits.
+#[cfg(any(feature = "deterministic", test))]
+fn portable_exp(x: f64) -> f64 {
+    let x = x.clamp(-708.0, 709.0);
+    let k = (x * std::f64::consts::LOG2_E + 0.5).floor();
+    let r = (x - k * LN2_HI) - k * LN2_LO;
+    let exp_r = (1..=13).rev().fold(1.0, |acc, n| 1.0 + acc * r / n as f64);
+    exp_r * f64::from_bits(((k as i64 + 1023) as u64) << 52)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_sin_accuracy() {
+        for i in -20000..20000 {
+            let x = i as f32 * 0.173;
+            let (s, c) = (portable_sin(x as f64, 0) as f32, portable_sin(x as f64, 1) as f32);
+            assert!((s - (x as f64).sin() as f32).abs() <= 1e-6, "sin({})", x);
+            assert!((c - (x as f64).cos() as f32).abs() <= 1e-6, "cos({})", x);
+        }
+    }
+
+    #[test]
+    fn test_portable_exp_accuracy() {
+        for i in -2000..2000 {
+            let x = i as f64 * 0.0173;
+            let relative = (portable_exp(x) - x.exp()).abs() / x.exp();
+            assert!(relative < 1e-14, "exp({}) off by {}", x, relative);
+        }
+        for i in -400..400 {
+            let x = i as f32 * 0.01;
+            assert!((tanh(x) - x.tanh()).abs() <= 1e-6, "tanh({})", x);
+        }
+        for db in [-40.0f32, -6.0, 0.0, 3.5, 12.0] {
+            let gain = db_to_amplitude(db);
+            assert!((gain - 10f32.powf(db / 20.0)).abs() <= 1e-6 * gain.max(1.0), "{} dB", db);
+        }
+    }
+
+    const GOLDEN_CHECKSUM: u32 = 0x95e8_74f2;
+
+    #[test]
+    fn test_portable_sin_golden() {
+        // Checksum of the f32 bits, computed independently with the same f64
+        // operations; any target must reproduce it exactly
+        let checksum = (0..10000u32).fold(0u32, |acc, i| {
+            let x = i as f32 * 0.37 - 1000.0;
+            acc.rotate_left(5) ^ (portable_sin(x as f64, 0) as f32).to_bits() ^ (portable_sin(x as f64, 1) as f32).to_bits()
+        });
+        assert_eq!(checksum, GOLDEN_CHECKSUM);
+    }
+}
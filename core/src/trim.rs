@@ -0,0 +1,172 @@
+//! Locate a transmission inside a long recording and cut it out
+//!
+//! A five-minute upload may hold ten seconds of modem audio. The recording is
+//! scanned in fixed-size overlapping windows, so correlation memory stays
+//! bounded by the window size however long the file is.
+
+use crate::streaming::DEFAULT_MAX_FRAME_SAMPLES;
+use crate::sync::{detect_keyed_postamble, detect_keyed_preamble, DetectionThreshold, NetworkKey};
+use crate::{POSTAMBLE_SAMPLES, PREAMBLE_SAMPLES, SAMPLE_RATE, SYNC_SILENCE_SAMPLES};
+
+/// Default scan window (10 s)
+pub const DEFAULT_TRIM_WINDOW_SAMPLES: usize = 10 * SAMPLE_RATE;
+
+/// Settings for [`find_transmission`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimOptions {
+    /// Audio kept before the preamble
+    pub margin_before_samples: usize,
+    /// Audio kept after the postamble
+    pub margin_after_samples: usize,
+    /// Samples correlated at a time (at least twice the sync length)
+    pub window_samples: usize,
+    /// Longest expected frame, preamble start to postamble end
+    pub max_frame_samples: usize,
+    pub threshold: DetectionThreshold,
+    pub network_key: Option<NetworkKey>,
+}
+
+impl Default for TrimOptions {
+    fn default() -> Self {
+        Self {
+            margin_before_samples: SYNC_SILENCE_SAMPLES,
+            margin_after_samples: SYNC_SILENCE_SAMPLES,
+            window_samples: DEFAULT_TRIM_WINDOW_SAMPLES,
+            max_frame_samples: DEFAULT_MAX_FRAME_SAMPLES,
+            threshold: DetectionThreshold::Adaptive,
+            network_key: None,
+        }
+    }
+}
+
+impl TrimOptions {
+    /// Set both margins from milliseconds
+    pub fn with_margins_ms(mut self, before_ms: u32, after_ms: u32) -> Self {
+        self.margin_before_samples = before_ms as usize * SAMPLE_RATE / 1000;
+        self.margin_after_samples = after_ms as usize * SAMPLE_RATE / 1000;
+        self
+    }
+}
+
+/// Where a transmission sits in a recording (sample positions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransmissionSpan {
+    /// First sample to keep (preamble minus margin)
+    pub start: usize,
+    /// One past the last sample to keep
+    pub end: usize,
+    pub preamble: usize,
+    /// End of the postamble, if one was found
+    pub postamble_end: Option<usize>,
+}
+
+/// Scan `samples[from..limit]` window by window and return the first hit
+fn scan(
+    samples: &[f32],
+    from: usize,
+    limit: usize,
+    template_len: usize,
+    window: usize,
+    detect: impl Fn(&[f32]) -> Option<usize>,
+) -> Option<usize> {
+    // Consecutive windows overlap by one template so no sync signal straddles a gap
+    let window = window.max(2 * template_len);
+    let step = window - template_len;
+    let limit = limit.min(samples.len());
+    let mut start = from;
+    while start + template_len <= limit {
+        let end = (start + window).min(limit);
+        if let Some(pos) = detect(&samples[start..end]) {
+            return Some(start + pos);
+        }
+        start += step;
+    }
+    None
+}
+
+/// Find the first transmission in a recording
+///
+/// Returns None when no preamble is detected. When the postamble is missing
+/// (cut off, or a fountain stream), the span runs to `max_frame_samples`
+/// after the preamble or the end of the recording.
+pub fn find_transmission(samples: &[f32], options: &TrimOptions) -> Option<TransmissionSpan> {
+    let key = options.network_key;
+    let preamble = scan(samples, 0, samples.len(), PREAMBLE_SAMPLES, options.window_samples, |window| {
+        detect_keyed_preamble(window, options.threshold, key)
+    })?;
+
+    let data_start = preamble + PREAMBLE_SAMPLES + SYNC_SILENCE_SAMPLES;
+    let frame_limit = preamble.saturating_add(options.max_frame_samples);
+    let postamble_end = scan(samples, data_start, frame_limit, POSTAMBLE_SAMPLES, options.window_samples, |window| {
+        detect_keyed_postamble(window, options.threshold, key)
+    })
+    .map(|pos| pos + POSTAMBLE_SAMPLES);
+
+    let frame_end = postamble_end.unwrap_or(frame_limit).min(samples.len());
+    Some(TransmissionSpan {
+        start: preamble.saturating_sub(options.margin_before_samples),
+        end: frame_end.saturating_add(options.margin_after_samples).min(samples.len()),
+        preamble,
+        postamble_end,
+    })
+}
+
+/// The first transmission in a recording with its margins, or None if there is none
+pub fn trim_transmission<'a>(samples: &'a [f32], options: &TrimOptions) -> Option<&'a [f32]> {
+    find_transmission(samples, options).map(|span| &samples[span.start..span.end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+    use crate::{DecoderFsk, EncoderFsk};
+
+    fn noise(len: usize, seed: u64) -> Vec<f32> {
+        let mut rng = SplitMix64::new(seed);
+        (0..len).map(|_| 0.01 * (rng.next_f32() - 0.5)).collect()
+    }
+
+    #[test]
+    fn test_trim_long_recording() {
+        let frame = EncoderFsk::new().unwrap().encode(b"needle").unwrap();
+        let offset = 47 * SAMPLE_RATE + 123;
+        let mut recording = noise(offset, 1);
+        recording.extend(frame.iter().map(|s| s + 0.002));
+        recording.extend(noise(40 * SAMPLE_RATE, 2));
+
+        let options = TrimOptions::default().with_margins_ms(100, 200);
+        let span = find_transmission(&recording, &options).unwrap();
+        // The encoder leads with SYNC_SILENCE_SAMPLES of silence before the preamble
+        let preamble = offset + SYNC_SILENCE_SAMPLES;
+        assert!(span.preamble.abs_diff(preamble) < 10, "{:?}", span);
+        assert_eq!(span.start, span.preamble - SAMPLE_RATE / 10);
+        let postamble_end = span.postamble_end.unwrap();
+        assert!(postamble_end.abs_diff(offset + frame.len() - SYNC_SILENCE_SAMPLES) < 10, "{:?}", span);
+        assert_eq!(span.end, postamble_end + SAMPLE_RATE / 5);
+
+        let trimmed = trim_transmission(&recording, &options).unwrap();
+        assert!(trimmed.len() < frame.len() + SAMPLE_RATE);
+        assert_eq!(DecoderFsk::new().unwrap().decode(trimmed).unwrap(), b"needle");
+    }
+
+    #[test]
+    fn test_trim_preamble_straddling_windows_and_missing_postamble() {
+        let frame = EncoderFsk::new().unwrap().encode(b"edge").unwrap();
+        let options = TrimOptions { window_samples: 3 * PREAMBLE_SAMPLES, ..TrimOptions::default() };
+
+        // Preamble across the boundary of the first two windows
+        let mut recording = vec![0.0f32; 3 * PREAMBLE_SAMPLES - PREAMBLE_SAMPLES / 2 - SYNC_SILENCE_SAMPLES];
+        recording.extend(&frame);
+        let span = find_transmission(&recording, &options).unwrap();
+        assert!(span.preamble.abs_diff(3 * PREAMBLE_SAMPLES - PREAMBLE_SAMPLES / 2) < 10);
+        assert!(span.postamble_end.is_some());
+
+        // Recording cut before the postamble: keep everything after the preamble
+        let cut = span.preamble + PREAMBLE_SAMPLES + 2 * SYNC_SILENCE_SAMPLES;
+        let span = find_transmission(&recording[..cut], &options).unwrap();
+        assert_eq!((span.postamble_end, span.end), (None, cut));
+
+        assert_eq!(find_transmission(&noise(20 * SAMPLE_RATE, 3), &options), None);
+    }
+}
@@ -0,0 +1,287 @@
+//! Transmit-side checks: catch audio that was ruined before it was played
+//!
+//! Encoder output has plenty of headroom, but callers that mix it with music,
+//! apply EQ or gain, or resample it can push it into clipping or bury the
+//! tones. [`validate_tx_audio`] measures the samples about to be played and
+//! names what to fix; [`EncoderFsk::set_safety_limiter`](crate::EncoderFsk::set_safety_limiter)
+//! keeps the encoder's own peaks below a ceiling.
+
+use crate::diagnostics::{median, BAND_SUPPRESSION_DB, LEVEL_EPSILON};
+use crate::fsk::{FskDemodulator, FSK_NIBBLES_PER_SYMBOL, FSK_NUM_BINS, FSK_SYMBOL_SAMPLES};
+use crate::presence::Biquad;
+use crate::trig;
+use crate::SAMPLE_RATE;
+use std::fmt;
+
+/// Samples at or above this magnitude count as clipped
+const CLIP_LEVEL: f32 = 0.999;
+
+/// Share of clipped samples above which clipping is reported
+const CLIP_REPORT_FRACTION: f32 = 1e-4;
+
+/// Peaks closer to full scale than this (in dB) leave too little headroom
+pub(crate) const MIN_HEADROOM_DB: f32 = 1.0;
+
+/// Mean sample value above which a DC offset is reported (-40 dBFS)
+const MAX_DC_OFFSET: f32 = 0.01;
+
+/// Edges of the band holding the sync signals and all tones (400-2700 Hz) with some margin
+const MODEM_BAND_HZ: (f32, f32) = (300.0, 3500.0);
+
+/// Share of the energy inside the modem band below which it is reported
+const MIN_IN_BAND_RATIO: f32 = 0.5;
+
+/// Peaks above this fraction of the limiter ceiling are compressed
+const LIMITER_KNEE_RATIO: f32 = 0.8;
+
+/// Problem found in audio about to be played, with the fix to suggest
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxAudioIssue {
+    /// Share of the samples at full scale
+    Clipped { fraction: f32 },
+    /// Peak less than 1 dB below full scale, without clipping yet
+    LowHeadroom { headroom_db: f32 },
+    /// Mean sample value
+    DcOffset { offset: f32 },
+    /// Share of the energy inside the modem band
+    OutOfBandEnergy { in_band_ratio: f32 },
+    /// Tone bands (0 = lowest) at least [`BAND_SUPPRESSION_DB`] weaker than the rest
+    BandImbalance { bands: Vec<usize> },
+}
+
+impl fmt::Display for TxAudioIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxAudioIssue::Clipped { fraction } => write!(
+                f,
+                "{:.2}% of the samples are clipped: lower the mix level or enable the encoder's safety limiter",
+                fraction * 100.0
+            ),
+            TxAudioIssue::LowHeadroom { headroom_db } => write!(
+                f,
+                "Peaks are only {:.1} dB below full scale: leave at least {} dB of headroom for resampling and playback",
+                headroom_db, MIN_HEADROOM_DB
+            ),
+            TxAudioIssue::DcOffset { offset } => {
+                write!(f, "The audio has a DC offset of {:.3}: high-pass it before playback", offset)
+            }
+            TxAudioIssue::OutOfBandEnergy { in_band_ratio } => write!(
+                f,
+                "Only {:.0}% of the energy is in the modem band ({}-{} Hz): the transmission is quiet next to the \
+                 rest of the mix",
+                in_band_ratio * 100.0,
+                MODEM_BAND_HZ.0,
+                MODEM_BAND_HZ.1
+            ),
+            TxAudioIssue::BandImbalance { bands } => write!(
+                f,
+                "Tone bands {:?} are much weaker than the rest: check the EQ and filters applied to the output",
+                bands
+            ),
+        }
+    }
+}
+
+/// Result of [`validate_tx_audio`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxAudioReport {
+    /// Largest sample magnitude
+    pub peak: f32,
+    /// Distance of the peak from full scale in dB (negative when over it)
+    pub headroom_db: f32,
+    /// Share of the samples at full scale
+    pub clipped_fraction: f32,
+    /// Mean sample value
+    pub dc_offset: f32,
+    /// Share of the energy (DC removed) between 300 and 3500 Hz
+    pub in_band_ratio: f32,
+    /// Energy of each tone band in dB, normalized so the median band is 0
+    pub band_level_db: [f32; FSK_NIBBLES_PER_SYMBOL],
+    /// Empty when the audio looks safe to play
+    pub issues: Vec<TxAudioIssue>,
+}
+
+impl TxAudioReport {
+    /// Whether no issue was found
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check modem audio (at SAMPLE_RATE) after any post-processing, before it is played
+///
+/// # Example
+/// ```
+/// use transmitwave_core::{validate_tx_audio, EncoderFsk};
+/// let audio = EncoderFsk::new()?.encode(b"hello")?;
+/// let mixed: Vec<f32> = audio.iter().map(|x| x * 2.0).collect();
+/// assert!(validate_tx_audio(&audio).is_clean());
+/// assert!(!validate_tx_audio(&mixed).is_clean());
+/// # Ok::<(), transmitwave_core::AudioModemError>(())
+/// ```
+pub fn validate_tx_audio(samples: &[f32]) -> TxAudioReport {
+    let len = samples.len().max(1) as f32;
+    let peak = samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+    let headroom_db = -20.0 * (peak + LEVEL_EPSILON).log10();
+    let clipped_fraction = samples.iter().filter(|x| x.abs() >= CLIP_LEVEL).count() as f32 / len;
+    let dc_offset = samples.iter().sum::<f32>() / len;
+    let in_band_ratio = in_band_ratio(samples, dc_offset);
+    let band_level_db = band_levels(samples);
+
+    let mut issues = Vec::new();
+    if clipped_fraction > CLIP_REPORT_FRACTION {
+        issues.push(TxAudioIssue::Clipped { fraction: clipped_fraction });
+    } else if headroom_db < MIN_HEADROOM_DB {
+        issues.push(TxAudioIssue::LowHeadroom { headroom_db });
+    }
+    if dc_offset.abs() > MAX_DC_OFFSET {
+        issues.push(TxAudioIssue::DcOffset { offset: dc_offset });
+    }
+    if in_band_ratio < MIN_IN_BAND_RATIO {
+        issues.push(TxAudioIssue::OutOfBandEnergy { in_band_ratio });
+    }
+    let bands: Vec<usize> = (0..FSK_NIBBLES_PER_SYMBOL)
+        .filter(|&band| band_level_db[band] <= -BAND_SUPPRESSION_DB)
+        .collect();
+    if !bands.is_empty() {
+        issues.push(TxAudioIssue::BandImbalance { bands });
+    }
+
+    TxAudioReport { peak, headroom_db, clipped_fraction, dc_offset, in_band_ratio, band_level_db, issues }
+}
+
+/// Share of the energy left after band-passing to the modem band (1 for silence)
+fn in_band_ratio(samples: &[f32], dc_offset: f32) -> f32 {
+    let (low, high) = MODEM_BAND_HZ;
+    let mut stages = [
+        Biquad::highpass(low, SAMPLE_RATE),
+        Biquad::highpass(low, SAMPLE_RATE),
+        Biquad::lowpass(high, SAMPLE_RATE),
+        Biquad::lowpass(high, SAMPLE_RATE),
+    ];
+    let mut total = 0.0f32;
+    let mut in_band = 0.0f32;
+    for &x in samples {
+        let x = x - dc_offset;
+        let filtered = stages.iter_mut().fold(x, |acc, stage| stage.process(acc));
+        total += x * x;
+        in_band += filtered * filtered;
+    }
+    if total <= LEVEL_EPSILON {
+        return 1.0;
+    }
+    (in_band / total).min(1.0)
+}
+
+/// Energy per tone band in dB, relative to the median band
+fn band_levels(samples: &[f32]) -> [f32; FSK_NIBBLES_PER_SYMBOL] {
+    let demodulator = FskDemodulator::new();
+    let bins_per_band = FSK_NUM_BINS / FSK_NIBBLES_PER_SYMBOL;
+    let mut energy = [0.0f32; FSK_NIBBLES_PER_SYMBOL];
+    for chunk in samples.chunks_exact(FSK_SYMBOL_SAMPLES) {
+        for (bin, power) in demodulator.bin_powers(chunk).into_iter().enumerate() {
+            energy[bin / bins_per_band] += power;
+        }
+    }
+
+    let levels = energy.map(|energy| 10.0 * (energy + LEVEL_EPSILON).log10());
+    let median = median(&levels);
+    levels.map(|level| level - median)
+}
+
+/// Soft limiter of the encoder: unchanged up to the knee, then compressed so
+/// no sample reaches beyond `ceiling`
+pub(crate) fn soft_limit(x: f32, ceiling: f32) -> f32 {
+    let knee = LIMITER_KNEE_RATIO * ceiling;
+    if x.abs() <= knee {
+        return x;
+    }
+    let range = ceiling - knee;
+    x.signum() * (knee + range * trig::tanh((x.abs() - knee) / range))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AudioModemError;
+    use crate::{DecoderFsk, EncoderFsk};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_encoder_output_is_clean() {
+        let audio = EncoderFsk::new().unwrap().encode(b"tx audio check, nothing added").unwrap();
+        let report = validate_tx_audio(&audio);
+        assert!(report.is_clean(), "{:?}", report.issues);
+        // Multi-tone symbols peak a little above 0.7
+        assert!(report.peak < 0.75 && report.headroom_db > 2.5, "{:?}", report.peak);
+        assert_eq!(report.clipped_fraction, 0.0);
+        assert!(report.dc_offset.abs() < 1e-3);
+        assert!(report.in_band_ratio > 0.9);
+        assert!(report.band_level_db.iter().all(|level| level.abs() < 6.0), "{:?}", report.band_level_db);
+
+        let silence = validate_tx_audio(&[]);
+        assert!(silence.is_clean());
+        assert_eq!((silence.peak, silence.in_band_ratio), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_ruined_mixes_are_reported() {
+        let audio = EncoderFsk::new().unwrap().encode(b"mixed with music").unwrap();
+
+        // Too loud, over a bass line, with a DC offset
+        let mixed: Vec<f32> = audio
+            .iter()
+            .enumerate()
+            .map(|(n, x)| {
+                let bass = 0.6 * (2.0 * PI * 100.0 * n as f32 / SAMPLE_RATE as f32).sin();
+                (1.6 * x + bass + 0.05).clamp(-1.0, 1.0)
+            })
+            .collect();
+        let report = validate_tx_audio(&mixed);
+        assert!(report.clipped_fraction > 0.0 && report.headroom_db <= 0.0);
+        assert!(report.issues.iter().any(|issue| matches!(issue, TxAudioIssue::Clipped { .. })));
+        assert!(report.issues.contains(&TxAudioIssue::DcOffset { offset: report.dc_offset }));
+        assert!(report.issues.contains(&TxAudioIssue::OutOfBandEnergy { in_band_ratio: report.in_band_ratio }));
+        assert!(report.issues.iter().all(|issue| !issue.to_string().is_empty()));
+
+        // Near full scale without clipping
+        let peak = audio.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+        let loud: Vec<f32> = audio.iter().map(|x| x * 0.95 / peak).collect();
+        assert!(matches!(validate_tx_audio(&loud).issues[..], [TxAudioIssue::LowHeadroom { .. }]));
+
+        // An EQ cutting the treble leaves the top bands behind
+        let mut stages = [(); 3].map(|_| Biquad::lowpass(1200.0, SAMPLE_RATE));
+        let muffled: Vec<f32> =
+            audio.iter().map(|&x| stages.iter_mut().fold(x, |acc, stage| stage.process(acc))).collect();
+        let report = validate_tx_audio(&muffled);
+        match report.issues.last() {
+            Some(TxAudioIssue::BandImbalance { bands }) => assert!(bands.contains(&5), "{:?}", bands),
+            other => panic!("expected a band imbalance, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_safety_limiter() {
+        assert_eq!(soft_limit(0.3, 0.5), 0.3);
+        assert!(soft_limit(0.45, 0.5) < 0.45 && soft_limit(0.45, 0.5) > 0.4);
+        assert!(soft_limit(-5.0, 0.5) >= -0.5);
+
+        let mut encoder = EncoderFsk::new().unwrap();
+        let plain = encoder.encode(b"limited").unwrap();
+        encoder.set_safety_limiter(Some(0.5));
+        assert_eq!(encoder.safety_limiter(), Some(0.5));
+        let limited = encoder.encode(b"limited").unwrap();
+        assert_eq!(limited.len(), plain.len());
+        let report = validate_tx_audio(&limited);
+        assert!(report.peak <= 0.5 && report.is_clean(), "{:?}", report);
+        assert_eq!(DecoderFsk::new().unwrap().decode(&limited).unwrap(), b"limited");
+
+        let mut streamed = Vec::new();
+        encoder.encode_into(b"limited", |chunk| streamed.extend_from_slice(chunk)).unwrap();
+        assert_eq!(streamed, limited);
+
+        encoder.set_safety_limiter(Some(1.5));
+        assert!(matches!(encoder.encode(b"limited"), Err(AudioModemError::InvalidConfig(_))));
+        assert!(encoder.airtime(7, false).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Newtypes for sample counts, durations and sample rates
+//!
+//! Sample counts and milliseconds used to be plain integers everywhere, so a
+//! duration passed where a length was expected compiled and misbehaved. The
+//! typed constants here are the source of truth for the `usize` constants in
+//! the crate root; signatures taking a [`Samples`] or [`Millis`] need the
+//! value wrapped, so neither a plain integer nor the other unit gets through.
+//! Converting between the two always goes through a [`SampleRate`].
+
+use crate::error::{AudioModemError, Result};
+use std::ops::{Add, AddAssign, Mul, Sub};
+use std::time::Duration;
+
+/// A number of audio samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Samples(pub usize);
+
+/// A duration in whole milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Millis(pub u32);
+
+/// Samples per second
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SampleRate(u32);
+
+/// Rate the modem generates and expects audio at (`crate::SAMPLE_RATE` in Hz)
+pub const MODEM_RATE: SampleRate = SampleRate(16_000);
+
+/// Preamble and postamble length
+pub const SYNC_DURATION: Millis = Millis(250);
+
+/// Silence before and after each sync signal
+pub const SYNC_SILENCE: Millis = Millis(125);
+
+/// Samples per multi-tone FSK symbol (192 ms)
+pub const FSK_SYMBOL: Samples = Samples(3072);
+
+/// Silence of one symbol gap (50 ms, see `EncoderFsk::set_symbol_gaps`)
+pub const SYMBOL_GAP: Samples = Samples(800);
+
+impl Samples {
+    pub const fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl Millis {
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    pub const fn as_duration(self) -> Duration {
+        Duration::from_millis(self.0 as u64)
+    }
+}
+
+impl SampleRate {
+    pub fn new(hz: u32) -> Result<Self> {
+        if hz == 0 {
+            return Err(AudioModemError::InvalidConfig("sample rate must be positive".to_string()));
+        }
+        Ok(Self(hz))
+    }
+
+    pub const fn hz(self) -> u32 {
+        self.0
+    }
+
+    /// Samples spanning `duration`, rounded down
+    pub const fn samples(self, duration: Millis) -> Samples {
+        Samples(duration.0 as usize * self.0 as usize / 1000)
+    }
+
+    /// Whole milliseconds spanned by `samples`, rounded down
+    pub const fn millis(self, samples: Samples) -> Millis {
+        Millis((samples.0 as u64 * 1000 / self.0 as u64) as u32)
+    }
+
+    /// Exact duration of `samples`
+    pub fn duration(self, samples: Samples) -> Duration {
+        Duration::from_secs_f64(samples.0 as f64 / self.0 as f64)
+    }
+}
+
+impl From<Samples> for usize {
+    fn from(samples: Samples) -> Self {
+        samples.0
+    }
+}
+
+impl From<Millis> for Duration {
+    fn from(ms: Millis) -> Self {
+        ms.as_duration()
+    }
+}
+
+impl Add for Samples {
+    type Output = Samples;
+
+    fn add(self, other: Samples) -> Samples {
+        Samples(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Samples {
+    fn add_assign(&mut self, other: Samples) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Samples {
+    type Output = Samples;
+
+    /// Panics on underflow in debug builds, like `usize`
+    fn sub(self, other: Samples) -> Samples {
+        Samples(self.0 - other.0)
+    }
+}
+
+impl Mul<usize> for Samples {
+    type Output = Samples;
+
+    fn mul(self, count: usize) -> Samples {
+        Samples(self.0 * count)
+    }
+}
+
+impl Add for Millis {
+    type Output = Millis;
+
+    fn add(self, other: Millis) -> Millis {
+        Millis(self.0 + other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(MODEM_RATE.samples(SYNC_DURATION), Samples(4000));
+        assert_eq!(MODEM_RATE.samples(SYNC_SILENCE), Samples(2000));
+        assert_eq!(MODEM_RATE.millis(FSK_SYMBOL), Millis(192));
+        assert_eq!(MODEM_RATE.millis(SYMBOL_GAP), Millis(50));
+        assert_eq!(MODEM_RATE.duration(Samples(24_000)), Duration::from_millis(1500));
+        assert_eq!(SampleRate::new(48_000).unwrap().samples(Millis(10)), Samples(480));
+        assert!(matches!(SampleRate::new(0), Err(AudioModemError::InvalidConfig(_))));
+        assert_eq!(Millis(250).as_duration(), Duration::from_millis(250));
+
+        assert_eq!(FSK_SYMBOL * 2 + SYMBOL_GAP - Samples(6944), Samples(0));
+        assert_eq!(usize::from(Samples(7)), 7);
+    }
+
+    #[test]
+    fn test_root_constants_follow_typed_ones() {
+        assert_eq!(crate::SAMPLE_RATE, MODEM_RATE.hz() as usize);
+        assert_eq!(crate::PREAMBLE_SAMPLES, MODEM_RATE.samples(SYNC_DURATION).get());
+        assert_eq!(crate::POSTAMBLE_SAMPLES, crate::PREAMBLE_SAMPLES);
+        assert_eq!(crate::SYNC_SILENCE_SAMPLES, MODEM_RATE.samples(SYNC_SILENCE).get());
+        assert_eq!(crate::FSK_SYMBOL_SAMPLES, FSK_SYMBOL.get());
+        assert_eq!(crate::SYMBOL_GAP_SAMPLES, SYMBOL_GAP.get());
+    }
+}
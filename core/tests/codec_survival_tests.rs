@@ -0,0 +1,97 @@
+// ============================================================================
+// CODEC SURVIVAL TESTS
+// ============================================================================
+// Round-trips encoded frames through Opus (standing in for the SBC/AAC of
+// Bluetooth speakers and the Opus of VoIP apps) at several bitrates. Perceptual
+// codecs smear energy across neighbouring 20 Hz bins; the codec-robust profile
+// spaces its tones 60 Hz apart and must survive every bitrate asserted here.
+//
+// Needs libopus (or cmake to build it):
+//   cargo test -p transmitwave-core --features codec-tests --test codec_survival_tests --release
+//
+// Tuning mode prints decode margins for every profile and bitrate:
+//   cargo test -p transmitwave-core --features codec-tests --test codec_survival_tests --release -- --ignored --nocapture
+// ============================================================================
+
+use opus::{Application, Bitrate, Channels};
+use transmitwave_core::{resample_audio, DecoderFsk, EncoderFsk, FrameProfile, SAMPLE_RATE};
+
+/// Opus runs at 48 kHz, as in Bluetooth and WebRTC audio paths
+const OPUS_RATE: usize = 48000;
+
+/// 20 ms Opus frames
+const OPUS_FRAME: usize = OPUS_RATE / 50;
+
+/// Largest Opus packet
+const MAX_PACKET_BYTES: usize = 4000;
+
+/// Encode `samples` (16 kHz) with Opus at `bitrate_bps` and decode them again
+fn opus_round_trip(samples: &[f32], bitrate_bps: i32) -> Vec<f32> {
+    let mut encoder = opus::Encoder::new(OPUS_RATE as u32, Channels::Mono, Application::Audio).unwrap();
+    encoder.set_bitrate(Bitrate::Bits(bitrate_bps)).unwrap();
+    let mut decoder = opus::Decoder::new(OPUS_RATE as u32, Channels::Mono).unwrap();
+
+    let mut input = resample_audio(samples, SAMPLE_RATE, OPUS_RATE);
+    input.resize(input.len().next_multiple_of(OPUS_FRAME), 0.0);
+
+    let mut packet = vec![0u8; MAX_PACKET_BYTES];
+    let mut frame = vec![0.0f32; OPUS_FRAME];
+    let mut output = Vec::with_capacity(input.len());
+    for chunk in input.chunks(OPUS_FRAME) {
+        let len = encoder.encode_float(chunk, &mut packet).unwrap();
+        let decoded = decoder.decode_float(&packet[..len], &mut frame, false).unwrap();
+        output.extend_from_slice(&frame[..decoded]);
+    }
+    resample_audio(&output, OPUS_RATE, SAMPLE_RATE)
+}
+
+fn encoder_for(profile: FrameProfile) -> EncoderFsk {
+    let mut encoder = EncoderFsk::new().unwrap();
+    encoder.set_profile(profile);
+    encoder
+}
+
+const PAYLOAD: &[u8] = b"Bluetooth speaker relay test";
+
+#[test]
+fn test_codec_robust_survives_opus() {
+    let samples = encoder_for(FrameProfile::CodecRobust).encode(PAYLOAD).unwrap();
+    let mut decoder = DecoderFsk::new().unwrap();
+    for bitrate in [24_000, 32_000, 64_000] {
+        let received = opus_round_trip(&samples, bitrate);
+        let decoded = decoder.decode(&received).unwrap_or_else(|e| panic!("{} bps: {}", bitrate, e));
+        assert_eq!(decoded, PAYLOAD, "{} bps", bitrate);
+    }
+}
+
+#[test]
+fn test_standard_frame_survives_high_bitrate_opus() {
+    let samples = encoder_for(FrameProfile::Standard).encode(PAYLOAD).unwrap();
+    let received = opus_round_trip(&samples, 64_000);
+    assert_eq!(DecoderFsk::new().unwrap().decode(&received).unwrap(), PAYLOAD);
+}
+
+#[test]
+#[ignore = "tuning sweep, run with --ignored --nocapture"]
+fn tune_profiles_against_opus() {
+    let profiles = [FrameProfile::Standard, FrameProfile::Telephony, FrameProfile::CodecRobust];
+    println!("{:<12} {:>8} {:>8} {:>10} {:>10}", "profile", "kbps", "decoded", "snr_db", "min_margin");
+    for profile in profiles {
+        let samples = encoder_for(profile).encode(PAYLOAD).unwrap();
+        for bitrate in [12_000, 16_000, 24_000, 32_000, 48_000, 64_000] {
+            let received = opus_round_trip(&samples, bitrate);
+            let mut decoder = DecoderFsk::new().unwrap();
+            match decoder.decode_with_metadata(&received) {
+                Ok(message) => println!(
+                    "{:<12} {:>8} {:>8} {:>10.1} {:>10.2}",
+                    format!("{:?}", profile),
+                    bitrate / 1000,
+                    message.payload == PAYLOAD,
+                    message.quality.snr_db,
+                    message.quality.min_symbol_margin
+                ),
+                Err(e) => println!("{:<12} {:>8} {:>8} ({})", format!("{:?}", profile), bitrate / 1000, false, e),
+            }
+        }
+    }
+}
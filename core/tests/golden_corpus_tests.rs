@@ -0,0 +1,151 @@
+// ============================================================================
+// GOLDEN CORPUS TESTS
+// ============================================================================
+// Decodes the reference WAV vectors in core/tests/golden and, for clean
+// vectors, checks that the current encoder still produces the same audio.
+// A failure here means the wire format changed.
+//
+// Regenerate the corpus (only when the format change is intentional):
+//   cargo run --release -p generate-web-constants -- golden-corpus
+// ============================================================================
+
+use std::fs;
+use std::path::PathBuf;
+use transmitwave_core::audio_io::read_wav_file;
+use transmitwave_core::{DecoderFsk, EncoderFsk, FountainConfig};
+
+/// Allowed difference between re-encoded audio and the stored vector (covers 16-bit quantization)
+const WAVEFORM_TOLERANCE: f32 = 1e-3;
+
+struct GoldenVector {
+    file: String,
+    mode: String,
+    block_size: Option<usize>,
+    clean: bool,
+    timestamp_us: Option<u64>,
+    payload: Vec<u8>,
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn parse_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("Invalid payload hex"))
+        .collect()
+}
+
+fn optional<T: std::str::FromStr>(field: &str) -> Option<T> {
+    if field == "-" {
+        None
+    } else {
+        Some(field.parse().ok().expect("Invalid manifest field"))
+    }
+}
+
+fn load_manifest() -> Vec<GoldenVector> {
+    let path = golden_dir().join("manifest.tsv");
+    let manifest = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "{}: {}; generate the corpus with `cargo run --release -p generate-web-constants -- golden-corpus` and commit it",
+            path.display(),
+            e
+        )
+    });
+    manifest
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            assert_eq!(fields.len(), 6, "Malformed manifest line: {}", line);
+            GoldenVector {
+                file: fields[0].to_string(),
+                mode: fields[1].to_string(),
+                block_size: optional(fields[2]),
+                clean: fields[3] == "clean",
+                timestamp_us: optional(fields[4]),
+                payload: parse_hex(fields[5]),
+            }
+        })
+        .collect()
+}
+
+fn reencode(vector: &GoldenVector, len: usize) -> Vec<f32> {
+    let mut encoder = EncoderFsk::new().unwrap();
+    match vector.mode.as_str() {
+        "fsk" => match vector.timestamp_us {
+            Some(timestamp_us) => encoder.encode_with_timestamp(&vector.payload, timestamp_us).unwrap(),
+            None => encoder.encode(&vector.payload).unwrap(),
+        },
+        "fountain" => {
+            let config = FountainConfig {
+                timeout_secs: 0,
+                block_size: vector.block_size.unwrap(),
+                repair_blocks_ratio: 0.5,
+                ..Default::default()
+            };
+            let mut samples = Vec::new();
+            for block in encoder.encode_fountain(&vector.payload, Some(config)).unwrap() {
+                if samples.len() >= len {
+                    break;
+                }
+                samples.extend(block);
+            }
+            samples
+        }
+        other => panic!("Unknown mode {}", other),
+    }
+}
+
+#[test]
+fn test_golden_corpus() {
+    let vectors = load_manifest();
+    assert!(!vectors.is_empty(), "Golden manifest has no vectors");
+
+    for vector in &vectors {
+        let (samples, _) = read_wav_file(golden_dir().join(&vector.file))
+            .unwrap_or_else(|e| panic!("{}: {}", vector.file, e));
+
+        let mut decoder = DecoderFsk::new().unwrap();
+        match vector.mode.as_str() {
+            "fsk" => {
+                let message = decoder
+                    .decode_with_metadata(&samples)
+                    .unwrap_or_else(|e| panic!("{}: decode failed: {}", vector.file, e));
+                assert_eq!(message.payload, vector.payload, "{}: payload mismatch", vector.file);
+                assert_eq!(message.timestamp_us, vector.timestamp_us, "{}: timestamp mismatch", vector.file);
+            }
+            "fountain" => {
+                let config = FountainConfig {
+                    timeout_secs: 30,
+                    block_size: vector.block_size.expect("Fountain vector needs a block size"),
+                    repair_blocks_ratio: 0.5,
+                    ..Default::default()
+                };
+                let payload = decoder
+                    .decode_fountain(&samples, Some(config))
+                    .unwrap_or_else(|e| panic!("{}: fountain decode failed: {}", vector.file, e));
+                assert_eq!(payload, vector.payload, "{}: payload mismatch", vector.file);
+            }
+            other => panic!("{}: unknown mode {}", vector.file, other),
+        }
+
+        if vector.clean {
+            let expected = reencode(vector, samples.len());
+            assert_eq!(expected.len(), samples.len(), "{}: frame length changed", vector.file);
+            let worst = expected
+                .iter()
+                .zip(&samples)
+                .map(|(a, b)| (a.clamp(-1.0, 1.0) - b).abs())
+                .fold(0.0f32, f32::max);
+            assert!(
+                worst <= WAVEFORM_TOLERANCE,
+                "{}: encoder output differs from golden vector by {}",
+                vector.file,
+                worst
+            );
+        }
+    }
+}
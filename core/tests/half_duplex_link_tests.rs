@@ -0,0 +1,139 @@
+// ============================================================================
+// HALF-DUPLEX LINK TESTS
+// ============================================================================
+// End-to-end exercise of the real-time pieces (streaming decoder, carrier
+// sense, stop-and-wait ARQ): two stations share a simulated acoustic channel
+// and chat the way the chat example does over speakers and microphones.
+//
+//   cargo test -p transmitwave-core --test half_duplex_link_tests --release
+// ============================================================================
+
+use std::collections::VecDeque;
+use transmitwave_core::link::{HalfDuplexLink, LinkConfig, LinkEvent};
+use transmitwave_core::SAMPLE_RATE;
+
+/// 100 ms of audio per step, like a typical sound card callback
+const CHUNK: usize = SAMPLE_RATE / 10;
+
+struct Station {
+    link: HalfDuplexLink,
+    playing: VecDeque<f32>,
+    events: Vec<LinkEvent>,
+    transmissions: usize,
+    /// Transmissions (by index) that never reach the air
+    lost: Vec<usize>,
+}
+
+impl Station {
+    fn new(id: u8) -> Self {
+        Self {
+            link: HalfDuplexLink::new(id, LinkConfig::default()).unwrap(),
+            playing: VecDeque::new(),
+            events: Vec::new(),
+            transmissions: 0,
+            lost: Vec::new(),
+        }
+    }
+
+    fn next_chunk(&mut self) -> Vec<f32> {
+        (0..CHUNK).map(|_| self.playing.pop_front().unwrap_or(0.0)).collect()
+    }
+
+    fn process(&mut self, air: &[f32]) {
+        let output = self.link.process(air).unwrap();
+        self.events.extend(output.events);
+        if let Some(audio) = output.transmit {
+            let lost = self.lost.contains(&self.transmissions);
+            self.transmissions += 1;
+            // The link still waits out the airtime of a lost transmission
+            self.playing.extend(audio.iter().map(|&s| if lost { 0.0 } else { s * 0.5 }));
+        }
+    }
+}
+
+/// Run both stations over a shared channel until `done` or the time limit
+fn run(a: &mut Station, b: &mut Station, seconds: usize, done: impl Fn(&Station, &Station) -> bool) {
+    let mut noise_state = 0x1613u32;
+    for _ in 0..seconds * 10 {
+        let from_a = a.next_chunk();
+        let from_b = b.next_chunk();
+        let air: Vec<f32> = from_a
+            .iter()
+            .zip(&from_b)
+            .map(|(x, y)| {
+                noise_state ^= noise_state << 13;
+                noise_state ^= noise_state >> 17;
+                noise_state ^= noise_state << 5;
+                x + y + 0.002 * (noise_state as f32 / u32::MAX as f32 - 0.5)
+            })
+            .collect();
+        a.process(&air);
+        b.process(&air);
+        if done(a, b) {
+            return;
+        }
+    }
+    panic!("Exchange did not finish: a={:?} b={:?}", a.events, b.events);
+}
+
+#[test]
+fn test_chat_exchange_both_directions() {
+    let mut a = Station::new(1);
+    let mut b = Station::new(2);
+    let seq_a = a.link.send(b"hi from a").unwrap();
+    let seq_b = b.link.send(b"hello from b").unwrap();
+
+    run(&mut a, &mut b, 90, |a, b| a.link.outstanding() == 0 && b.link.outstanding() == 0);
+
+    assert!(a.events.contains(&LinkEvent::Delivered { seq: seq_a }), "{:?}", a.events);
+    assert!(b.events.contains(&LinkEvent::Delivered { seq: seq_b }), "{:?}", b.events);
+    assert!(b.events.contains(&LinkEvent::Received { from: 1, payload: b"hi from a".to_vec() }));
+    assert!(a.events.contains(&LinkEvent::Received { from: 2, payload: b"hello from b".to_vec() }));
+}
+
+#[test]
+fn test_lost_data_and_ack_are_retried_without_duplicates() {
+    let mut a = Station::new(1);
+    let mut b = Station::new(2);
+    // First data transmission and the first ACK both vanish
+    a.lost.push(0);
+    b.lost.push(0);
+    let seq = a.link.send(b"retry me").unwrap();
+
+    run(&mut a, &mut b, 90, |a, _| a.link.outstanding() == 0);
+
+    assert!(a.events.contains(&LinkEvent::Delivered { seq }), "{:?}", a.events);
+    let retries = a.events.iter().filter(|e| matches!(e, LinkEvent::Retrying { .. })).count();
+    assert_eq!(retries, 2, "{:?}", a.events);
+    let received = b.events.iter().filter(|e| matches!(e, LinkEvent::Received { .. })).count();
+    assert_eq!(received, 1, "duplicate delivery: {:?}", b.events);
+}
+
+#[test]
+fn test_unanswered_message_fails_after_max_attempts() {
+    let config = LinkConfig { max_attempts: 2, ack_timeout_ms: 2000, ..LinkConfig::default() };
+    let mut link = HalfDuplexLink::new(1, config).unwrap();
+    let seq = link.send(b"anyone?").unwrap();
+
+    let mut events = Vec::new();
+    let mut transmissions = 0;
+    let mut playing = 0usize;
+    for _ in 0..600 {
+        // Silent room: nobody answers, our own audio is ignored while it plays
+        let output = link.process(&[0.0; CHUNK]).unwrap();
+        playing = playing.saturating_sub(CHUNK);
+        if let Some(audio) = output.transmit {
+            assert_eq!(playing, 0, "transmitted over our own audio");
+            playing = audio.len();
+            transmissions += 1;
+        }
+        events.extend(output.events);
+        if link.outstanding() == 0 {
+            break;
+        }
+    }
+
+    assert_eq!(transmissions, 2);
+    assert_eq!(events.last(), Some(&LinkEvent::Failed { seq }));
+    assert!(link.send(&vec![0u8; transmitwave_core::link::MAX_LINK_PAYLOAD + 1]).is_err());
+}
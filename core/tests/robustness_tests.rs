@@ -0,0 +1,136 @@
+//! The public decode and detection API must not panic on untrusted audio
+//!
+//! Every buffer here is garbage of some kind: empty, shorter than a sync
+//! signal, non-finite, clipped, or a real frame cut and corrupted at random.
+//! Errors are fine; a panic fails the test.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use transmitwave_core::{
+    analyze_capture, capture_test_signal, detect_fountain_preamble, detect_postamble, detect_preamble,
+    detect_preamble_candidates, find_transmission, resample_audio, signal_presence, stereo_to_mono, DecoderFsk,
+    DetectionThreshold, EncoderFsk, FskDemodulator, StreamingDecoder, TrimOptions, PREAMBLE_SAMPLES, SAMPLE_RATE,
+};
+
+/// Random cases on top of the fixed ones
+const RANDOM_CASES: usize = 24;
+
+const THRESHOLDS: [DetectionThreshold; 6] = [
+    DetectionThreshold::Adaptive,
+    DetectionThreshold::Fixed(0.4),
+    DetectionThreshold::Fixed(0.0),
+    DetectionThreshold::Fixed(-1.0),
+    DetectionThreshold::Fixed(2.0),
+    DetectionThreshold::Fixed(f32::NAN),
+];
+
+fn fixed_cases() -> Vec<Vec<f32>> {
+    let frame = EncoderFsk::new().unwrap().encode(b"robustness").unwrap();
+    let mut with_nan = frame.clone();
+    with_nan[frame.len() / 2] = f32::NAN;
+    vec![
+        Vec::new(),
+        vec![0.5],
+        vec![0.0; PREAMBLE_SAMPLES - 1],
+        vec![0.0; 2 * SAMPLE_RATE],
+        vec![f32::NAN; SAMPLE_RATE],
+        vec![f32::INFINITY; SAMPLE_RATE],
+        vec![f32::NEG_INFINITY; PREAMBLE_SAMPLES],
+        vec![f32::MAX; SAMPLE_RATE],
+        vec![f32::MIN_POSITIVE / 2.0; SAMPLE_RATE],
+        (0..SAMPLE_RATE).map(|i| if i % 2 == 0 { 1.0 } else { -1.0 }).collect(),
+        frame[..frame.len() / 2].to_vec(),
+        frame[frame.len() / 2..].to_vec(),
+        frame.iter().map(|s| s * 1e6).collect(),
+        with_nan,
+        frame,
+    ]
+}
+
+/// Noise, a frame cut at random points with random bursts, or random raw bit patterns
+fn random_case(rng: &mut StdRng, frame: &[f32]) -> Vec<f32> {
+    match rng.gen_range(0..3) {
+        0 => {
+            let len = rng.gen_range(0..3 * SAMPLE_RATE);
+            let amplitude = 10f32.powi(rng.gen_range(-6..3));
+            (0..len).map(|_| rng.gen_range(-amplitude..=amplitude)).collect()
+        }
+        1 => {
+            let start = rng.gen_range(0..frame.len());
+            let end = rng.gen_range(start..=frame.len());
+            let mut samples = frame[start..end].to_vec();
+            for _ in 0..rng.gen_range(0..8) {
+                if samples.is_empty() {
+                    break;
+                }
+                let at = rng.gen_range(0..samples.len());
+                let burst = rng.gen_range(1..2000).min(samples.len() - at);
+                samples[at..at + burst].iter_mut().for_each(|s| *s = rng.gen_range(-4.0..4.0));
+            }
+            samples
+        }
+        _ => (0..rng.gen_range(0..2 * SAMPLE_RATE)).map(|_| f32::from_bits(rng.gen())).collect(),
+    }
+}
+
+fn exercise(samples: &[f32], reference: &[f32]) {
+    for threshold in THRESHOLDS {
+        let _ = detect_preamble(samples, threshold);
+        let _ = detect_postamble(samples, threshold);
+        let _ = detect_fountain_preamble(samples, threshold);
+        let _ = detect_preamble_candidates(samples, threshold, 4);
+    }
+
+    let mut decoder = DecoderFsk::new().unwrap();
+    let _ = decoder.decode_with_metadata(samples);
+    let _ = decoder.decode_without_preamble_postamble(samples);
+    #[cfg(feature = "fountain")]
+    {
+        let config = transmitwave_core::FountainConfig { timeout_secs: 1, ..Default::default() };
+        let _ = decoder.decode_fountain(samples, Some(config));
+    }
+
+    let mut streaming = StreamingDecoder::new().unwrap();
+    for chunk in samples.chunks(4096) {
+        let _ = streaming.push(chunk);
+    }
+
+    let _ = FskDemodulator::new().demodulate(samples);
+    let _ = find_transmission(samples, &TrimOptions::default());
+    let _ = signal_presence(samples);
+    let _ = analyze_capture(samples, reference);
+    let _ = stereo_to_mono(samples);
+    for (from, to) in [(48_000, SAMPLE_RATE), (SAMPLE_RATE, 0), (0, SAMPLE_RATE)] {
+        let _ = resample_audio(samples, from, to);
+    }
+}
+
+#[test]
+fn test_no_panic_on_garbage_audio() {
+    let reference = capture_test_signal().unwrap();
+    for samples in fixed_cases() {
+        exercise(&samples, &reference);
+    }
+}
+
+#[test]
+fn test_no_panic_on_random_audio() {
+    let reference = capture_test_signal().unwrap();
+    let frame = EncoderFsk::new().unwrap().encode(b"random robustness case").unwrap();
+    let mut rng = StdRng::seed_from_u64(1666);
+    for _ in 0..RANDOM_CASES {
+        exercise(&random_case(&mut rng, &frame), &reference);
+    }
+}
+
+#[test]
+fn test_defaults_and_thresholds_never_panic() {
+    let _ = DecoderFsk::default();
+    let _ = EncoderFsk::default();
+    for value in [f32::NAN, f32::INFINITY, -0.0, 1e-9, 1.5] {
+        let mut decoder = DecoderFsk::default();
+        decoder.set_detection_threshold(DetectionThreshold::Fixed(value));
+        assert!(DetectionThreshold::fixed(value).is_err());
+        assert!(decoder.get_preamble_threshold().validate().is_ok());
+    }
+}
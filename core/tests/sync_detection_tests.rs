@@ -2,7 +2,7 @@ use transmitwave_core::sync::{
     detect_preamble, detect_postamble, generate_chirp, generate_postamble_chirp,
     generate_preamble, generate_postamble_signal, barker_code, DetectionThreshold,
 };
-use transmitwave_core::{PREAMBLE_SAMPLES, POSTAMBLE_SAMPLES, fft_correlate_1d, Mode};
+use transmitwave_core::{fft_correlate, RustFft, PREAMBLE_SAMPLES, POSTAMBLE_SAMPLES};
 use rand::SeedableRng;
 use rand_distr::Normal;
 
@@ -291,7 +291,7 @@ fn test_fft_correlation_index_mapping_with_preamble() {
     signal.extend_from_slice(&vec![0.0; 1000]);
 
     // Compute FFT correlation
-    let fft_result = fft_correlate_1d(&signal, &template, Mode::Full).unwrap();
+    let fft_result = fft_correlate(&RustFft, &signal, &template).unwrap();
 
     // Find peak index
     let peak_idx = fft_result.iter()
@@ -351,7 +351,7 @@ fn test_fft_correlation_index_mapping_with_preamble_noisy() {
     }
 
     // Verify: FFT correlation peak should still be at insert_pos + template_len - 1
-    let fft_result = fft_correlate_1d(&signal, &template, Mode::Full).unwrap();
+    let fft_result = fft_correlate(&RustFft, &signal, &template).unwrap();
 
     let peak_idx = fft_result.iter()
         .enumerate()
@@ -383,3 +383,73 @@ fn test_fft_correlation_index_mapping_with_preamble_noisy() {
         insert_pos, detected_pos
     );
 }
+
+// Simulate an ADC with DC bias and excessive gain: scale, offset, then hard-clip to full scale
+fn clip_with_dc(samples: &[f32], gain: f32, dc_offset: f32) -> Vec<f32> {
+    samples
+        .iter()
+        .map(|&s| (s * gain + dc_offset).clamp(-1.0, 1.0))
+        .collect()
+}
+
+#[test]
+fn test_detect_sync_hard_clipped_noisy() {
+    use rand::distributions::Distribution;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1601);
+    let noise = Normal::new(0.0f32, 0.02).unwrap();
+
+    let mut samples = vec![0.0; 4000];
+    samples.extend_from_slice(&create_test_preamble(0.5));
+    samples.extend_from_slice(&vec![0.0; 8000]);
+    samples.extend_from_slice(&create_test_postamble(0.5));
+    samples.extend_from_slice(&vec![0.0; 4000]);
+    for s in samples.iter_mut() {
+        *s += noise.sample(&mut rng);
+    }
+
+    // 26 dB too hot: nearly every sync sample sits on the rail
+    let clipped = clip_with_dc(&samples, 20.0, 0.0);
+
+    let preamble = detect_preamble(&clipped, DetectionThreshold::Adaptive)
+        .expect("Preamble should be detected in hard-clipped audio");
+    assert!(preamble.abs_diff(4000) <= 20, "Preamble at {}, expected ~4000", preamble);
+
+    let postamble = detect_postamble(&clipped, DetectionThreshold::Adaptive)
+        .expect("Postamble should be detected in hard-clipped audio");
+    let expected_postamble = 4000 + PREAMBLE_SAMPLES + 8000;
+    assert!(
+        postamble.abs_diff(expected_postamble) <= 20,
+        "Postamble at {}, expected ~{}",
+        postamble,
+        expected_postamble
+    );
+}
+
+#[test]
+fn test_detect_preamble_large_dc_offset_with_clipping() {
+    // Signal biased toward the positive rail: the upper half of every cycle is clipped flat
+    let mut samples = vec![0.0; 4000];
+    samples.extend_from_slice(&create_test_preamble(0.5));
+    samples.extend_from_slice(&vec![0.0; 4000]);
+
+    let biased = clip_with_dc(&samples, 1.0, 0.9);
+
+    let detected = detect_preamble(&biased, DetectionThreshold::Adaptive)
+        .expect("Preamble should be detected despite DC offset and one-sided clipping");
+    assert!(detected.abs_diff(4000) <= 20, "Preamble at {}, expected ~4000", detected);
+}
+
+#[test]
+fn test_decode_clipped_frame_roundtrip() {
+    use transmitwave_core::{DecoderFsk, EncoderFsk};
+
+    let mut encoder = EncoderFsk::new().unwrap();
+    let mut decoder = DecoderFsk::new().unwrap();
+    let data = b"hot mic gain";
+
+    let samples = encoder.encode(data).unwrap();
+    let clipped = clip_with_dc(&samples, 10.0, 0.3);
+
+    assert_eq!(decoder.decode(&clipped).unwrap(), data);
+}
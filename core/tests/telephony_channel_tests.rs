@@ -0,0 +1,168 @@
+// ============================================================================
+// TELEPHONY CHANNEL TESTS
+// ============================================================================
+// Sends telephony-profile frames through a simulated phone call: the
+// 300-3400 Hz band limit of narrowband speech codecs (AMR, Opus at low
+// bitrates), 8 kHz sampling, G.711 μ-law quantization, level changes per
+// 20 ms codec frame and line noise.
+//
+//   cargo test -p transmitwave-core --test telephony_channel_tests --release
+// ============================================================================
+
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+use transmitwave_core::{resample_audio, DecoderFsk, EncoderFsk, FrameProfile, NetworkKey, SAMPLE_RATE};
+
+const PHONE_RATE: usize = 8000;
+
+/// Samples per 20 ms codec frame at the phone rate
+const CODEC_FRAME: usize = PHONE_RATE / 50;
+
+/// RBJ cookbook biquad (Butterworth Q)
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    fn new(cutoff_hz: f32, sample_rate: usize, high_pass: bool) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        let b = if high_pass {
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0]
+        } else {
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0]
+        };
+        Self {
+            b: b.map(|v| v / a0),
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// 4th-order 300-3400 Hz band-pass, like the input filter of a narrowband codec
+fn voice_band(samples: &[f32], sample_rate: usize) -> Vec<f32> {
+    let mut stages = [
+        Biquad::new(300.0, sample_rate, true),
+        Biquad::new(300.0, sample_rate, true),
+        Biquad::new(3400.0, sample_rate, false),
+        Biquad::new(3400.0, sample_rate, false),
+    ];
+    samples
+        .iter()
+        .map(|&x| stages.iter_mut().fold(x, |acc, stage| stage.process(acc)))
+        .collect()
+}
+
+/// G.711 μ-law encode then decode (8 bits per sample)
+fn mu_law(x: f32) -> f32 {
+    const MU: f32 = 255.0;
+    let x = x.clamp(-1.0, 1.0);
+    let compressed = x.signum() * (1.0 + MU * x.abs()).ln() / (1.0 + MU).ln();
+    let quantized = (compressed * 127.0).round() / 127.0;
+    quantized.signum() * ((1.0 + MU).powf(quantized.abs()) - 1.0) / MU
+}
+
+/// Phone call from the sender's 16 kHz output to the receiver's 16 kHz input
+fn phone_channel(samples: &[f32], seed: u64) -> Vec<f32> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+
+    // Band limit at 16 kHz, then decimate to the phone rate
+    let filtered = voice_band(samples, SAMPLE_RATE);
+    let mut phone: Vec<f32> = filtered.iter().step_by(SAMPLE_RATE / PHONE_RATE).copied().collect();
+
+    // Codec level handling: up to ±2 dB of gain change per 20 ms frame
+    for frame in phone.chunks_mut(CODEC_FRAME) {
+        let gain = 10f32.powf(rng.gen_range(-2.0..2.0) / 20.0);
+        frame.iter_mut().for_each(|x| *x *= gain);
+    }
+
+    // Line noise around -45 dBFS, μ-law on the wire, and the codec band limit again on the far end
+    let line: Vec<f32> = phone.iter().map(|&x| mu_law(x + rng.gen_range(-0.008..0.008))).collect();
+    let received = voice_band(&line, PHONE_RATE);
+    resample_audio(&received, PHONE_RATE, SAMPLE_RATE)
+}
+
+fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn test_phone_channel_is_band_limited() {
+    let tone = |freq: f32| -> Vec<f32> {
+        (0..SAMPLE_RATE).map(|n| 0.5 * (2.0 * PI * freq * n as f32 / SAMPLE_RATE as f32).sin()).collect()
+    };
+    let level = |freq: f32| {
+        let received = phone_channel(&tone(freq), 1);
+        20.0 * (rms(&received[SAMPLE_RATE / 4..]) / rms(&tone(freq))).log10()
+    };
+
+    assert!(level(1000.0).abs() < 3.0, "1 kHz level {:.1} dB", level(1000.0));
+    assert!(level(120.0) < -15.0, "120 Hz level {:.1} dB", level(120.0));
+    assert!(level(5000.0) < -20.0, "5 kHz level {:.1} dB", level(5000.0));
+}
+
+#[test]
+fn test_telephony_frame_survives_phone_channel() {
+    let mut encoder = EncoderFsk::new().unwrap();
+    encoder.set_profile(FrameProfile::Telephony);
+    let mut decoder = DecoderFsk::new().unwrap();
+
+    for (seed, payload) in [(1u64, b"PIN 4711".to_vec()), (2, (0..48).map(|i| (i * 37) as u8).collect())] {
+        let samples = encoder.encode(&payload).unwrap();
+        let received = phone_channel(&samples, seed);
+        let message = decoder.decode_with_metadata(&received).expect("telephony frame should survive the call");
+        assert_eq!(message.payload, payload);
+        println!("seed {}: {:.1} dB tone SNR, min margin {:.2}", seed, message.quality.snr_db, message.quality.min_symbol_margin);
+    }
+}
+
+#[test]
+fn test_telephony_timestamp_through_phone_channel() {
+    let mut encoder = EncoderFsk::new().unwrap();
+    encoder.set_profile(FrameProfile::Telephony);
+    let samples = encoder.encode_with_timestamp(b"call me", 1_234_567).unwrap();
+
+    let mut decoder = DecoderFsk::new().unwrap();
+    let message = decoder.decode_with_metadata(&phone_channel(&samples, 3)).unwrap();
+    assert_eq!(message.payload, b"call me");
+    assert_eq!(message.timestamp_us, Some(1_234_567));
+}
+
+#[test]
+fn test_keyed_telephony_frame_survives_phone_channel() {
+    let key = Some(NetworkKey::new(b"call center"));
+    let mut encoder = EncoderFsk::new().unwrap();
+    encoder.set_profile(FrameProfile::Telephony);
+    encoder.set_network_key(key);
+    let payload: Vec<u8> = (0..48).map(|i| (i * 53 + 1) as u8).collect();
+    let received = phone_channel(&encoder.encode(&payload).unwrap(), 4);
+
+    // The keyed sync signals stay in the voice band, so the call keeps them
+    let mut decoder = DecoderFsk::new().unwrap();
+    decoder.set_network_key(key);
+    let message = decoder.decode_with_metadata(&received).expect("keyed telephony frame should survive the call");
+    assert_eq!(message.payload, payload);
+
+    // Listeners under another key (or none) still hear nothing
+    for other in [Some(NetworkKey::new(b"front desk")), None] {
+        let mut decoder = DecoderFsk::new().unwrap();
+        decoder.set_network_key(other);
+        assert!(decoder.decode(&received).is_err(), "{:?}", other);
+    }
+}
@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use transmitwave_core::fuzzing::ModemConfig;
+use transmitwave_core::{
+    detect_fountain_preamble, detect_postamble, detect_preamble, detect_preamble_candidates, find_transmission,
+    signal_presence, DetectionThreshold, TrimOptions,
+};
+
+fuzz_target!(|input: (ModemConfig, f32, Vec<u32>)| {
+    // Raw bit patterns, so NaN, infinities and subnormals reach the decoder too
+    let (config, threshold, bits) = input;
+    let samples: Vec<f32> = bits.iter().map(|&b| f32::from_bits(b)).collect();
+
+    // Thresholds straight from the input, out of range or not
+    let threshold = DetectionThreshold::Fixed(threshold);
+    let _ = detect_preamble(&samples, threshold);
+    let _ = detect_postamble(&samples, threshold);
+    let _ = detect_fountain_preamble(&samples, threshold);
+    let _ = detect_preamble_candidates(&samples, threshold, 4);
+    let _ = find_transmission(&samples, &TrimOptions::default());
+    let _ = signal_presence(&samples);
+
+    if let Ok(mut decoder) = config.decoder() {
+        let _ = decoder.decode_with_metadata(&samples);
+        let _ = decoder.decode_without_preamble_postamble(&samples);
+    }
+});
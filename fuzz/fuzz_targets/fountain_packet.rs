@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use transmitwave_core::{parse_fountain_block, FountainReceiver};
+
+fuzz_target!(|blocks: Vec<&[u8]>| {
+    // Feed every block through the same path decode_fountain takes; the CRC-16
+    // is only a filter (corrupted packets pass it one time in 65536), so it is
+    // not checked here
+    let mut receiver: Option<FountainReceiver> = None;
+    for block in blocks {
+        let Some(block) = parse_fountain_block(block) else { continue };
+        if receiver.is_none() {
+            receiver = FountainReceiver::new(&block).ok();
+        }
+        if let Some(receiver) = receiver.as_mut() {
+            let _ = receiver.add_packet(block.packet);
+        }
+    }
+});
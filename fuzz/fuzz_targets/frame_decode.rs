@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use transmitwave_core::FrameDecoder;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = FrameDecoder::decode(data);
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use transmitwave_core::fuzzing::ModemConfig;
+
+fuzz_target!(|input: (ModemConfig, Vec<i16>)| {
+    let (config, samples) = input;
+    let samples: Vec<f32> = samples.iter().map(|&s| s as f32 / i16::MAX as f32).collect();
+
+    let demodulator = config.demodulator();
+    let _ = demodulator.demodulate(&samples);
+    let _ = demodulator.demodulate_amplitude_shaped(&samples);
+});
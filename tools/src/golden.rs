@@ -0,0 +1,221 @@
+//! Golden WAV corpus for wire-format regression tests and third-party reference vectors
+//!
+//! Each vector is written as a WAV plus a line in `manifest.tsv`:
+//! `file<TAB>mode<TAB>block_size<TAB>impairment<TAB>timestamp_us<TAB>payload_hex`
+//! where mode is `fsk` or `fountain` and `-` marks an unused field.
+//! Clean vectors are bit-exact encoder output (up to WAV quantization).
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use transmitwave_core::audio_io::{write_wav_file, WavSampleFormat};
+use transmitwave_core::*;
+
+/// Default corpus location, read by core/tests/golden_corpus_tests.rs
+pub const GOLDEN_DIR: &str = "core/tests/golden";
+
+/// Fixed sender timestamp for the header-extension vector
+const GOLDEN_TIMESTAMP_US: u64 = 1_700_000_000_000_000;
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Fsk,
+    /// Fountain blocks with the given RaptorQ block size
+    Fountain(usize),
+}
+
+#[derive(Clone, Copy)]
+enum Impairment {
+    Clean,
+    /// Uniform noise with the given peak amplitude
+    Noise(f32),
+    /// Scale by a gain (attenuation or overdrive)
+    Gain(f32),
+    /// Overdrive by the gain, then hard clip to full scale
+    Clip(f32),
+    /// Prefix with this many samples of low-level noise
+    Offset(usize),
+}
+
+impl Impairment {
+    fn name(self) -> &'static str {
+        match self {
+            Impairment::Clean => "clean",
+            Impairment::Noise(_) => "noise",
+            Impairment::Gain(_) => "quiet",
+            Impairment::Clip(_) => "clipped",
+            Impairment::Offset(_) => "offset",
+        }
+    }
+
+    fn apply(self, samples: &[f32], rng: &mut XorShift) -> Vec<f32> {
+        match self {
+            Impairment::Clean => samples.to_vec(),
+            Impairment::Noise(level) => samples.iter().map(|s| s + level * rng.next_f32()).collect(),
+            Impairment::Gain(gain) => samples.iter().map(|s| s * gain).collect(),
+            Impairment::Clip(gain) => samples.iter().map(|s| (s * gain).clamp(-1.0, 1.0)).collect(),
+            Impairment::Offset(len) => {
+                let mut out: Vec<f32> = (0..len).map(|_| 0.01 * rng.next_f32()).collect();
+                out.extend_from_slice(samples);
+                out
+            }
+        }
+    }
+}
+
+/// Small deterministic PRNG so the corpus is reproducible without extra dependencies
+struct XorShift(u32);
+
+impl XorShift {
+    /// Uniform in [-1.0, 1.0)
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+fn pattern_payload(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 37 + 11) as u8).collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{:02x}", b);
+        out
+    })
+}
+
+/// One encoded transmission, before impairments
+struct Vector<'a> {
+    name: &'a str,
+    mode: Mode,
+    samples: Vec<f32>,
+    timestamp_us: Option<u64>,
+    payload: &'a [u8],
+}
+
+impl<'a> Vector<'a> {
+    fn fsk(name: &'a str, payload: &'a [u8]) -> Self {
+        Self {
+            name,
+            mode: Mode::Fsk,
+            samples: EncoderFsk::new().unwrap().encode(payload).unwrap(),
+            timestamp_us: None,
+            payload,
+        }
+    }
+}
+
+struct Corpus<'a> {
+    dir: &'a Path,
+    manifest: String,
+    rng: XorShift,
+}
+
+impl Corpus<'_> {
+    fn add(&mut self, vector: &Vector, impairment: Impairment, format: WavSampleFormat) {
+        let file = format!("{}_{}.wav", vector.name, impairment.name());
+        let impaired = impairment.apply(&vector.samples, &mut self.rng);
+        write_wav_file(self.dir.join(&file), &impaired, format).expect("Failed to write golden WAV");
+
+        let (mode, block_size) = match vector.mode {
+            Mode::Fsk => ("fsk", "-".to_string()),
+            Mode::Fountain(block_size) => ("fountain", block_size.to_string()),
+        };
+        let timestamp_us = vector.timestamp_us.map_or("-".to_string(), |t| t.to_string());
+        let _ = writeln!(
+            self.manifest,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            file,
+            mode,
+            block_size,
+            impairment.name(),
+            timestamp_us,
+            hex(vector.payload)
+        );
+        println!("  {}", file);
+    }
+}
+
+pub fn generate(dir: &Path) {
+    fs::create_dir_all(dir).expect("Failed to create corpus directory");
+    let mut corpus = Corpus {
+        dir,
+        manifest: String::from("# file\tmode\tblock_size\timpairment\ttimestamp_us\tpayload_hex\n"),
+        rng: XorShift(0x5EED_1607),
+    };
+
+    // Regular frames: payload sizes chosen to hit each FEC mode and a multi-block RS frame
+    let medium = pattern_payload(30);
+    let full = pattern_payload(100);
+    let two_blocks = pattern_payload(240);
+    for (name, payload) in [
+        ("fsk_empty", &b""[..]),
+        ("fsk_light", &b"Hello"[..]),
+        ("fsk_medium", &medium[..]),
+        ("fsk_full", &full[..]),
+        ("fsk_two_blocks", &two_blocks[..]),
+    ] {
+        corpus.add(&Vector::fsk(name, payload), Impairment::Clean, WavSampleFormat::Int16);
+    }
+
+    // Impairments on a medium frame
+    let impaired = Vector::fsk("fsk_impaired", b"Golden vector: impaired channel");
+    for impairment in [
+        Impairment::Noise(0.1),
+        Impairment::Gain(0.05),
+        Impairment::Clip(4.0),
+        Impairment::Offset(SAMPLE_RATE / 2),
+    ] {
+        corpus.add(&impaired, impairment, WavSampleFormat::Int16);
+    }
+
+    // Non-default WAV sample formats
+    corpus.add(&Vector::fsk("fsk_int24", b"24-bit"), Impairment::Clean, WavSampleFormat::Int24);
+    corpus.add(&Vector::fsk("fsk_float32", b"float"), Impairment::Clean, WavSampleFormat::Float32);
+
+    // Header timestamp extension
+    let payload = b"timestamped";
+    let timestamped = Vector {
+        name: "fsk_timestamp",
+        mode: Mode::Fsk,
+        samples: EncoderFsk::new()
+            .unwrap()
+            .encode_with_timestamp(payload, GOLDEN_TIMESTAMP_US)
+            .unwrap(),
+        timestamp_us: Some(GOLDEN_TIMESTAMP_US),
+        payload,
+    };
+    corpus.add(&timestamped, Impairment::Clean, WavSampleFormat::Int16);
+
+    // Fountain mode: enough blocks for the decoder to finish, with and without noise
+    let block_size = 32;
+    let payload = pattern_payload(64);
+    let config = FountainConfig {
+        timeout_secs: 0,
+        block_size,
+        repair_blocks_ratio: 0.5,
+        ..Default::default()
+    };
+    let fountain = Vector {
+        name: "fountain",
+        mode: Mode::Fountain(block_size),
+        samples: EncoderFsk::new()
+            .unwrap()
+            .encode_fountain(&payload, Some(config))
+            .unwrap()
+            .take(8)
+            .flatten()
+            .collect(),
+        timestamp_us: None,
+        payload: &payload,
+    };
+    for impairment in [Impairment::Clean, Impairment::Noise(0.05)] {
+        corpus.add(&fountain, impairment, WavSampleFormat::Int16);
+    }
+
+    fs::write(dir.join("manifest.tsv"), &corpus.manifest).expect("Failed to write manifest");
+    println!("Generated golden corpus in {}", dir.display());
+}
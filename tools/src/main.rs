@@ -2,34 +2,53 @@ use std::fs;
 use std::path::PathBuf;
 use transmitwave_core::*;
 
+mod golden;
+
 fn main() {
-    let web_constants_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-        .parent()
-        .unwrap()
-        .join("web/src/constants/fountain.ts");
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        // cargo run --manifest-path tools/Cargo.toml -- golden-corpus [DIR]
+        Some("golden-corpus") => {
+            let dir = args.get(2).map(PathBuf::from).unwrap_or_else(|| {
+                PathBuf::from(env!("CARGO_MANIFEST_DIR")).parent().unwrap().join(golden::GOLDEN_DIR)
+            });
+            golden::generate(&dir);
+        }
+        Some(other) => {
+            eprintln!("Unknown command '{}'. Usage: generate-web-constants [golden-corpus [DIR]]", other);
+            std::process::exit(1);
+        }
+        None => generate_web_constants(),
+    }
+}
+
+fn constants_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).parent().unwrap().join("web/src/constants")
+}
 
-    let content = format!(
-        r#"// AUTO-GENERATED FILE - DO NOT EDIT MANUALLY
-// Generated from core/src/lib.rs constants
-// Run `cargo run --manifest-path tools/Cargo.toml` to regenerate
+/// Generated files under `web/src/constants` and their content
+fn web_constants() -> [(&'static str, String); 2] {
+    let params = ModemParams::CURRENT;
+    [("fountain.ts", params.to_fountain_typescript()), ("modem.ts", params.to_typescript())]
+}
 
-export const FOUNTAIN_BLOCK_SIZE_BYTES = {}
-export const MAX_PAYLOAD_BYTES = {}
-export const FSK_BYTES_PER_SYMBOL = {}
-export const FSK_SYMBOL_SAMPLES = {}
-export const PACKET_OVERHEAD_BYTES = {}
-export const MAX_BUFFER_SAMPLES = {}
-"#,
-        FOUNTAIN_BLOCK_SIZE,
-        MAX_PAYLOAD_SIZE,
-        FSK_BYTES_PER_SYMBOL,
-        FSK_SYMBOL_SAMPLES,
-        PACKET_OVERHEAD_BYTES,
-        MAX_BUFFER_SAMPLES
-    );
+fn generate_web_constants() {
+    for (file, content) in web_constants() {
+        let path = constants_dir().join(file);
+        fs::write(&path, content).expect("Failed to write web constants file");
+        println!("Generated: {}", path.display());
+    }
+}
 
-    fs::write(&web_constants_path, content)
-        .expect("Failed to write web constants file");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("Generated: {}", web_constants_path.display());
+    #[test]
+    fn test_web_constants_are_up_to_date() {
+        for (file, content) in web_constants() {
+            let generated = fs::read_to_string(constants_dir().join(file)).expect("web constants file is missing");
+            assert_eq!(generated, content, "stale {}, run `cargo run --manifest-path tools/Cargo.toml`", file);
+        }
+    }
 }
@@ -0,0 +1,157 @@
+use crate::{fixed_threshold, DecodeStats, ModemError, Result};
+use std::sync::{Arc, Mutex};
+use transmitwave_core::{DecoderFsk, EncoderFsk, FountainConfig, FountainStream, Melody, FOUNTAIN_BLOCK_SIZE, SAMPLE_RATE};
+
+// ============================================================================
+// FOUNTAIN CODE ENCODER/DECODER
+// ============================================================================
+
+fn config(block_size: u32, repair_ratio: f32, timeout_secs: u32) -> FountainConfig {
+    FountainConfig {
+        timeout_secs,
+        block_size: block_size as usize,
+        repair_blocks_ratio: repair_ratio,
+        ..Default::default()
+    }
+}
+
+/// Melody of parallel note lists (Hz, ms); empty lists mean the three-note whistle
+fn melody(frequencies: Vec<f32>, durations_ms: Vec<u32>) -> Result<Option<Melody>> {
+    if frequencies.len() != durations_ms.len() {
+        return Err(ModemError::Failed { message: "melody needs one duration per frequency".to_string() });
+    }
+    if frequencies.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Melody::from_notes(frequencies.into_iter().zip(durations_ms))?))
+}
+
+struct EncoderState {
+    encoder: EncoderFsk,
+    stream: Option<FountainStream>,
+}
+
+/// Fountain encoder for continuous broadcast
+#[derive(uniffi::Object)]
+pub struct FountainEncoder {
+    state: Mutex<EncoderState>,
+}
+
+#[uniffi::export]
+impl FountainEncoder {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>> {
+        let state = EncoderState { encoder: EncoderFsk::new()?, stream: None };
+        Ok(Arc::new(FountainEncoder { state: Mutex::new(state) }))
+    }
+
+    /// Open blocks with a custom melody (250 ms in all) instead of the whistle
+    pub fn set_fountain_melody(&self, frequencies: Vec<f32>, durations_ms: Vec<u32>) -> Result<()> {
+        let melody = melody(frequencies, durations_ms)?;
+        self.state.lock().unwrap().encoder.set_fountain_melody(melody);
+        Ok(())
+    }
+
+    /// Encode `data` into `timeout_secs` of fountain blocks, concatenated
+    pub fn encode_fountain(&self, data: Vec<u8>, timeout_secs: u32, block_size: u32, repair_ratio: f32) -> Result<Vec<f32>> {
+        let mut state = self.state.lock().unwrap();
+        let stream = state.encoder.encode_fountain(&data, Some(config(block_size, repair_ratio, timeout_secs)))?;
+        Ok(stream.flatten().collect())
+    }
+
+    /// Begin a stream yielding one block per `next_block` call (timeout 0 = endless)
+    pub fn start_streaming(&self, data: Vec<u8>, block_size: u32, repair_ratio: f32, timeout_secs: u32) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let stream = state.encoder.encode_fountain(&data, Some(config(block_size, repair_ratio, timeout_secs)))?;
+        state.stream = Some(stream);
+        Ok(())
+    }
+
+    /// Audio of the next block, or null once the stream has ended
+    pub fn next_block(&self) -> Option<Vec<f32>> {
+        self.state.lock().unwrap().stream.as_mut().and_then(|stream| stream.next())
+    }
+
+    pub fn stop_streaming(&self) {
+        self.state.lock().unwrap().stream = None;
+    }
+}
+
+/// Default cap for the fountain decoder buffer (2 minutes of audio)
+const DEFAULT_FOUNTAIN_MAX_BUFFER_SAMPLES: usize = SAMPLE_RATE * 120;
+
+struct DecoderState {
+    decoder: DecoderFsk,
+    buffer: Vec<f32>,
+    block_size: usize,
+}
+
+/// Fountain decoder fed with microphone chunks
+#[derive(uniffi::Object)]
+pub struct FountainDecoder {
+    state: Mutex<DecoderState>,
+}
+
+#[uniffi::export]
+impl FountainDecoder {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>> {
+        let state = DecoderState { decoder: DecoderFsk::new()?, buffer: Vec::new(), block_size: FOUNTAIN_BLOCK_SIZE };
+        Ok(Arc::new(FountainDecoder { state: Mutex::new(state) }))
+    }
+
+    /// Look for blocks opened by the sender's melody instead of the whistle
+    pub fn set_fountain_melody(&self, frequencies: Vec<f32>, durations_ms: Vec<u32>) -> Result<()> {
+        let melody = melody(frequencies, durations_ms)?;
+        self.state.lock().unwrap().decoder.set_fountain_melody(melody);
+        Ok(())
+    }
+
+    /// Symbol size in bytes (must match the encoder)
+    pub fn set_block_size(&self, block_size: u32) {
+        self.state.lock().unwrap().block_size = block_size as usize;
+    }
+
+    pub fn set_detection_threshold(&self, fixed_value: f32) {
+        self.state.lock().unwrap().decoder.set_detection_threshold(fixed_threshold(fixed_value));
+    }
+
+    /// Buffer 16 kHz mono samples; returns false when the buffer is full and
+    /// some of them were dropped
+    pub fn feed_chunk(&self, samples: Vec<f32>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let room = DEFAULT_FOUNTAIN_MAX_BUFFER_SAMPLES.saturating_sub(state.buffer.len());
+        let accepted = samples.len().min(room);
+        state.buffer.extend_from_slice(&samples[..accepted]);
+        accepted == samples.len()
+    }
+
+    pub fn sample_count(&self) -> u64 {
+        self.state.lock().unwrap().buffer.len() as u64
+    }
+
+    /// Try to recover the data from the buffered audio
+    pub fn try_decode(&self) -> Result<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        if state.buffer.is_empty() {
+            return Err(ModemError::Failed { message: "No audio data in buffer".to_string() });
+        }
+        let config = config(state.block_size as u32, 0.5, 30);
+        Ok(state.decoder.decode_fountain(&state.buffer, Some(config))?)
+    }
+
+    /// Clear the buffer and the decoder state (the melody is kept)
+    pub fn reset(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.buffer.clear();
+        let mut decoder = DecoderFsk::new()?;
+        decoder.set_fountain_melody(state.decoder.fountain_melody().cloned());
+        state.decoder = decoder;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> DecodeStats {
+        self.state.lock().unwrap().decoder.stats.clone().into()
+    }
+}
@@ -0,0 +1,341 @@
+//! Kotlin/Swift bindings (UniFFI) for mobile apps
+//!
+//! Exposes the same high-level API as the WASM crate: encoder, decoder,
+//! streaming decoder, fountain encoder/decoder and the capture check. Objects
+//! are shared across threads by the generated code, so each one keeps its core
+//! counterpart behind a mutex. Generate bindings with
+//! `uniffi-bindgen generate --library <lib> --language kotlin` (see the README).
+
+use std::sync::{Arc, Mutex};
+use transmitwave_core::decoder_fsk::DecodeStats as CoreDecodeStats;
+use transmitwave_core::sync::DetectionThreshold;
+use transmitwave_core::{AudioModemError, DecoderFsk, EncoderFsk, EncoderPadding, Millis};
+
+#[cfg(feature = "fountain")]
+mod fountain;
+#[cfg(feature = "fountain")]
+pub use fountain::{FountainDecoder, FountainEncoder};
+
+uniffi::setup_scaffolding!();
+
+/// Error surfaced to Kotlin/Swift as an exception
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum ModemError {
+    #[error("{message}")]
+    Failed { message: String },
+    /// A frame failed only its CRC; `payload` is the best-effort, unverified content
+    #[error("Payload CRC mismatch (unverified payload of {} bytes)", payload.len())]
+    Unverified { payload: Vec<u8> },
+}
+
+impl From<AudioModemError> for ModemError {
+    fn from(error: AudioModemError) -> Self {
+        match error {
+            AudioModemError::CrcMismatch { payload } => ModemError::Unverified { payload },
+            other => ModemError::Failed { message: other.to_string() },
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, ModemError>;
+
+fn fixed_threshold(fixed_value: f32) -> DetectionThreshold {
+    DetectionThreshold::Fixed(fixed_value.clamp(0.001, 1.0))
+}
+
+fn fixed_value(threshold: DetectionThreshold) -> Option<f32> {
+    match threshold {
+        DetectionThreshold::Fixed(value) => Some(value),
+        DetectionThreshold::Adaptive => None,
+    }
+}
+
+/// Decode statistics of the last decode call
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct DecodeStats {
+    /// Fountain blocks that passed their CRC
+    pub decoded_blocks: u32,
+    /// Fountain blocks that failed their CRC
+    pub failed_blocks: u32,
+    /// Times a fountain decode switched to a new transmission mid-recording
+    pub fountain_restarts: u32,
+    /// Reed-Solomon blocks of regular frames that decoded
+    pub rs_blocks_decoded: u32,
+    /// Reed-Solomon blocks of regular frames that could not be corrected
+    pub rs_blocks_failed: u32,
+    /// Received bytes the RS decoder corrected (data and parity)
+    pub corrected_bytes: u32,
+    /// Correlation score of the last preamble tried
+    pub preamble_score: Option<f32>,
+    /// Correlation score of the postamble
+    pub postamble_score: Option<f32>,
+    /// FSK symbols demodulated
+    pub symbols_demodulated: u32,
+}
+
+impl From<CoreDecodeStats> for DecodeStats {
+    fn from(stats: CoreDecodeStats) -> Self {
+        DecodeStats {
+            decoded_blocks: stats.decoded_blocks,
+            failed_blocks: stats.failed_blocks,
+            fountain_restarts: stats.fountain_restarts,
+            rs_blocks_decoded: stats.rs_blocks_decoded,
+            rs_blocks_failed: stats.rs_blocks_failed,
+            corrected_bytes: stats.corrected_bytes,
+            preamble_score: stats.preamble_score,
+            postamble_score: stats.postamble_score,
+            symbols_demodulated: stats.symbols_demodulated,
+        }
+    }
+}
+
+/// A frame found by the streaming decoder
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct DecodedMessage {
+    pub payload: Vec<u8>,
+    /// Sender timestamp (microseconds), if the frame carried one
+    pub timestamp_us: Option<u64>,
+    /// Preamble position in samples, counted from the start of the decoder's buffer
+    pub preamble_position: u64,
+    /// Tone power vs. the rest of its band, in dB
+    pub snr_db: f32,
+}
+
+impl From<transmitwave_core::DecodedMessage> for DecodedMessage {
+    fn from(message: transmitwave_core::DecodedMessage) -> Self {
+        DecodedMessage {
+            payload: message.payload,
+            timestamp_us: message.timestamp_us,
+            preamble_position: message.preamble_position as u64,
+            snr_db: message.quality.snr_db,
+        }
+    }
+}
+
+/// Receives encoded audio in chunks from [`Encoder::encode_chunks`]
+#[uniffi::export(callback_interface)]
+pub trait AudioSink: Send + Sync {
+    fn on_samples(&self, samples: Vec<f32>);
+}
+
+// ============================================================================
+// ENCODER/DECODER
+// ============================================================================
+
+/// FSK encoder producing 16 kHz mono samples
+#[derive(uniffi::Object)]
+pub struct Encoder {
+    inner: Mutex<EncoderFsk>,
+}
+
+#[uniffi::export]
+impl Encoder {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>> {
+        Ok(Arc::new(Encoder { inner: Mutex::new(EncoderFsk::new()?) }))
+    }
+
+    /// Encode `data` into one frame of audio
+    pub fn encode(&self, data: Vec<u8>) -> Result<Vec<f32>> {
+        Ok(self.inner.lock().unwrap().encode(&data)?)
+    }
+
+    /// Encode `data` and hand the audio to `sink` chunk by chunk, so playback
+    /// can start before the whole frame exists
+    pub fn encode_chunks(&self, data: Vec<u8>, sink: Box<dyn AudioSink>) -> Result<()> {
+        Ok(self.inner.lock().unwrap().encode_into(&data, |chunk| sink.on_samples(chunk.to_vec()))?)
+    }
+
+    /// Set leading/trailing silence and the fade-in ramp before the preamble (milliseconds)
+    pub fn set_padding(&self, leading_silence_ms: u32, trailing_silence_ms: u32, fade_in_ms: u32) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_padding(EncoderPadding::from_ms(Millis(leading_silence_ms), Millis(trailing_silence_ms), Millis(fade_in_ms)));
+    }
+}
+
+/// FSK decoder for complete recordings
+#[derive(uniffi::Object)]
+pub struct Decoder {
+    inner: Mutex<DecoderFsk>,
+}
+
+#[uniffi::export]
+impl Decoder {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>> {
+        Ok(Arc::new(Decoder { inner: Mutex::new(DecoderFsk::new()?) }))
+    }
+
+    /// Set a fixed detection threshold for both preamble and postamble
+    pub fn set_detection_threshold(&self, fixed_value: f32) {
+        self.inner.lock().unwrap().set_detection_threshold(fixed_threshold(fixed_value));
+    }
+
+    pub fn set_preamble_threshold(&self, fixed_value: f32) {
+        self.inner.lock().unwrap().set_preamble_threshold(fixed_threshold(fixed_value));
+    }
+
+    /// Fixed preamble threshold, or null when adaptive
+    pub fn preamble_threshold(&self) -> Option<f32> {
+        fixed_value(self.inner.lock().unwrap().get_preamble_threshold())
+    }
+
+    pub fn set_postamble_threshold(&self, fixed_value: f32) {
+        self.inner.lock().unwrap().set_postamble_threshold(fixed_threshold(fixed_value));
+    }
+
+    /// Fixed postamble threshold, or null when adaptive
+    pub fn postamble_threshold(&self) -> Option<f32> {
+        fixed_value(self.inner.lock().unwrap().get_postamble_threshold())
+    }
+
+    /// Report frames that fail only their CRC as `ModemError.Unverified`
+    pub fn set_salvage_unverified(&self, enabled: bool) {
+        self.inner.lock().unwrap().set_salvage_unverified(enabled);
+    }
+
+    /// Decode one frame from 16 kHz mono samples
+    pub fn decode(&self, samples: Vec<f32>) -> Result<Vec<u8>> {
+        Ok(self.inner.lock().unwrap().decode(&samples)?)
+    }
+
+    /// Decode samples already trimmed to the FSK data (no preamble/postamble search)
+    pub fn decode_without_preamble_postamble(&self, samples: Vec<f32>) -> Result<Vec<u8>> {
+        Ok(self.inner.lock().unwrap().decode_without_preamble_postamble(&samples)?)
+    }
+
+    /// Statistics of the last decode call
+    pub fn stats(&self) -> DecodeStats {
+        self.inner.lock().unwrap().stats.clone().into()
+    }
+}
+
+/// Decoder for live microphone input: push chunks, get frames as they complete
+#[derive(uniffi::Object)]
+pub struct StreamingDecoder {
+    inner: Mutex<transmitwave_core::StreamingDecoder>,
+}
+
+#[uniffi::export]
+impl StreamingDecoder {
+    #[uniffi::constructor]
+    pub fn new() -> Result<Arc<Self>> {
+        Ok(Arc::new(StreamingDecoder { inner: Mutex::new(transmitwave_core::StreamingDecoder::new()?) }))
+    }
+
+    /// Add 16 kHz mono samples; returns the frames completed by them
+    pub fn push(&self, samples: Vec<f32>) -> Vec<DecodedMessage> {
+        self.inner.lock().unwrap().push(&samples).into_iter().map(DecodedMessage::from).collect()
+    }
+
+    /// Samples held while waiting for a frame to complete
+    pub fn buffered_samples(&self) -> u64 {
+        self.inner.lock().unwrap().buffered_samples() as u64
+    }
+
+    /// Drop buffered audio
+    pub fn reset(&self) {
+        self.inner.lock().unwrap().reset();
+    }
+}
+
+// ============================================================================
+// SYNC DETECTION AND CAPTURE CHECK
+// ============================================================================
+
+/// Preamble position in `samples`; a fixed threshold, or adaptive when null
+#[uniffi::export]
+pub fn detect_preamble(samples: Vec<f32>, fixed_value: Option<f32>) -> Option<u64> {
+    let threshold = fixed_value.map_or(DetectionThreshold::Adaptive, fixed_threshold);
+    transmitwave_core::detect_preamble(&samples, threshold).map(|position| position as u64)
+}
+
+/// Postamble position in `samples`; a fixed threshold, or adaptive when null
+#[uniffi::export]
+pub fn detect_postamble(samples: Vec<f32>, fixed_value: Option<f32>) -> Option<u64> {
+    let threshold = fixed_value.map_or(DetectionThreshold::Adaptive, fixed_threshold);
+    transmitwave_core::detect_postamble(&samples, threshold).map(|position| position as u64)
+}
+
+/// Signs of echo cancellation / noise suppression in a capture
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct CaptureDiagnostics {
+    /// Whether the test transmission was found in the recording
+    pub signal_found: bool,
+    /// Received level of each tone band relative to the median band (dB)
+    pub band_level_db: Vec<f32>,
+    /// Share of the transmission (0.0-1.0) during which the signal dropped out
+    pub gated_fraction: f32,
+    /// Actionable hints for the user, empty when the capture path looks clean
+    pub hints: Vec<String>,
+}
+
+/// Samples of the known test transmission to play during a capture check
+#[uniffi::export]
+pub fn capture_test_signal() -> Result<Vec<f32>> {
+    Ok(transmitwave_core::capture_test_signal()?)
+}
+
+/// Compare a microphone recording of `capture_test_signal()` with what was played
+#[uniffi::export]
+pub fn analyze_capture(recording: Vec<f32>) -> Result<CaptureDiagnostics> {
+    let reference = transmitwave_core::capture_test_signal()?;
+    let diagnostics = transmitwave_core::analyze_capture(&recording, &reference);
+    Ok(CaptureDiagnostics {
+        signal_found: diagnostics.preamble.is_some(),
+        band_level_db: diagnostics.band_level_db.to_vec(),
+        gated_fraction: diagnostics.gated_fraction,
+        hints: diagnostics.hints.iter().map(|hint| hint.to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects what `encode_chunks` hands over, like a Kotlin/Swift sink would
+    struct Collect(Arc<Mutex<Vec<f32>>>);
+
+    impl AudioSink for Collect {
+        fn on_samples(&self, samples: Vec<f32>) {
+            self.0.lock().unwrap().extend(samples);
+        }
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let encoder = Encoder::new().unwrap();
+        let samples = encoder.encode(b"from kotlin".to_vec()).unwrap();
+
+        let chunks = Arc::new(Mutex::new(Vec::new()));
+        encoder.encode_chunks(b"from kotlin".to_vec(), Box::new(Collect(chunks.clone()))).unwrap();
+        assert_eq!(*chunks.lock().unwrap(), samples);
+
+        let decoder = Decoder::new().unwrap();
+        assert_eq!(decoder.decode(samples.clone()).unwrap(), b"from kotlin");
+        let stats = decoder.stats();
+        assert!(stats.symbols_demodulated > 0);
+        assert!(stats.preamble_score.is_some());
+
+        let mut audio = vec![0.0f32; 5000];
+        audio.extend(&samples);
+        audio.extend(vec![0.0f32; 3000]);
+        let streaming = StreamingDecoder::new().unwrap();
+        let messages: Vec<DecodedMessage> = audio.chunks(1600).flat_map(|chunk| streaming.push(chunk.to_vec())).collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].payload, b"from kotlin");
+        assert!((5000..5000 + samples.len() as u64).contains(&messages[0].preamble_position));
+    }
+
+    #[test]
+    fn test_errors_cross_as_modem_errors() {
+        let decoder = Decoder::new().unwrap();
+        assert!(matches!(decoder.decode(vec![0.0; 16000]), Err(ModemError::Failed { .. })));
+        assert!(matches!(
+            ModemError::from(AudioModemError::CrcMismatch { payload: b"maybe".to_vec() }),
+            ModemError::Unverified { payload } if payload == b"maybe"
+        ));
+    }
+}
@@ -0,0 +1,167 @@
+//! `WasmError`: what every binding throws, so JS can branch on `error.code`
+//!
+//! Core errors map one-to-one onto `WasmErrorCode`; the few failures that
+//! only exist in the bindings (bad option bags, empty buffers, throwing
+//! callbacks) get codes of their own. `message` is the text the bindings used
+//! to throw as a plain string, and `toString()` still returns it.
+
+use transmitwave_core::AudioModemError;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Kind of a `WasmError`, one per `AudioModemError` variant plus binding-only failures
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WasmErrorCode {
+    PreambleNotFound,
+    PostambleNotFound,
+    HeaderCrcMismatch,
+    PayloadCrcMismatch,
+    /// Payload failed its CRC with salvage enabled (`take_unverified_payload`)
+    CrcMismatch,
+    FecDecodeFailure,
+    InvalidFrameSize,
+    FftError,
+    CorrelationBackend,
+    InvalidInputSize,
+    PayloadTooLarge,
+    InsufficientData,
+    Squelched,
+    WrongNetwork,
+    /// Frame padding was not all padding bytes (`set_strict_padding`)
+    UnexpectedPadding,
+    FrameNumberMismatch,
+    InvalidConfig,
+    FecError,
+    FountainDecodeFailure,
+    InvalidPacket,
+    InvalidTransferPart,
+    WavError,
+    PayloadEncoding,
+    Timeout,
+    /// An option bag or argument the bindings could not parse
+    InvalidOptions,
+    /// Nothing to decode yet
+    NoData,
+    /// A JS callback threw; `details.cause` holds what it threw
+    Callback,
+    /// A `WasmSession` was used before `connect`
+    NotConnected,
+}
+
+/// Error thrown by the bindings: `code`, `message` and optional `details`
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct WasmError {
+    code: WasmErrorCode,
+    message: String,
+    details: Option<js_sys::Object>,
+}
+
+#[wasm_bindgen]
+impl WasmError {
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> WasmErrorCode {
+        self.code
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+
+    /// Fields of the failure with camelCase keys, e.g. `{ size, max,
+    /// suggestedMode }` for `PayloadTooLarge` (undefined if none)
+    #[wasm_bindgen(getter)]
+    pub fn details(&self) -> Option<js_sys::Object> {
+        self.details.clone()
+    }
+
+    #[wasm_bindgen(js_name = toString)]
+    pub fn describe(&self) -> String {
+        self.message.clone()
+    }
+}
+
+impl WasmError {
+    fn new(code: WasmErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), details: None }
+    }
+
+    fn with_detail(mut self, key: &str, value: JsValue) -> Self {
+        let details = self.details.get_or_insert_with(js_sys::Object::new);
+        let _ = js_sys::Reflect::set(details, &JsValue::from_str(key), &value);
+        self
+    }
+
+    /// `field` of an option bag (or an argument) is invalid
+    pub(crate) fn invalid_option(field: &str, reason: impl std::fmt::Display) -> Self {
+        let reason = reason.to_string();
+        Self::new(WasmErrorCode::InvalidOptions, format!("{}: {}", field, reason))
+            .with_detail("field", JsValue::from_str(field))
+            .with_detail("reason", JsValue::from(reason))
+    }
+
+    pub(crate) fn no_data(message: &str) -> Self {
+        Self::new(WasmErrorCode::NoData, message)
+    }
+
+    pub(crate) fn not_connected() -> Self {
+        Self::new(WasmErrorCode::NotConnected, "session is not connected")
+    }
+
+    /// A JS callback threw `cause`
+    pub(crate) fn callback(cause: JsValue) -> Self {
+        let message = cause.as_string().or_else(|| {
+            cause.dyn_ref::<js_sys::Error>().map(|error| String::from(error.message()))
+        });
+        let message = format!("callback failed: {}", message.unwrap_or_else(|| "exception thrown".to_string()));
+        Self::new(WasmErrorCode::Callback, message).with_detail("cause", cause)
+    }
+}
+
+impl From<AudioModemError> for WasmError {
+    fn from(error: AudioModemError) -> Self {
+        use WasmErrorCode as Code;
+        let message = error.to_string();
+        let reason = |detail: String| vec![("reason", JsValue::from(detail))];
+        let (code, details) = match error {
+            AudioModemError::PreambleNotFound => (Code::PreambleNotFound, vec![]),
+            AudioModemError::PostambleNotFound => (Code::PostambleNotFound, vec![]),
+            AudioModemError::HeaderCrcMismatch => (Code::HeaderCrcMismatch, vec![]),
+            AudioModemError::PayloadCrcMismatch => (Code::PayloadCrcMismatch, vec![]),
+            AudioModemError::CrcMismatch { payload } => {
+                (Code::CrcMismatch, vec![("unverifiedBytes", JsValue::from(payload.len() as f64))])
+            }
+            AudioModemError::FecDecodeFailure => (Code::FecDecodeFailure, vec![]),
+            AudioModemError::InvalidFrameSize => (Code::InvalidFrameSize, vec![]),
+            AudioModemError::FftError(detail) => (Code::FftError, reason(detail)),
+            AudioModemError::CorrelationBackend(detail) => (Code::CorrelationBackend, reason(detail)),
+            AudioModemError::InvalidInputSize => (Code::InvalidInputSize, vec![]),
+            AudioModemError::PayloadTooLarge { size, max, suggested_mode } => (
+                Code::PayloadTooLarge,
+                vec![
+                    ("size", JsValue::from(size as f64)),
+                    ("max", JsValue::from(max as f64)),
+                    ("suggestedMode", JsValue::from(suggested_mode.to_string())),
+                ],
+            ),
+            AudioModemError::InsufficientData => (Code::InsufficientData, vec![]),
+            AudioModemError::Squelched => (Code::Squelched, vec![]),
+            AudioModemError::WrongNetwork => (Code::WrongNetwork, vec![]),
+            AudioModemError::UnexpectedPadding { bytes } => {
+                (Code::UnexpectedPadding, vec![("bytes", JsValue::from(bytes as f64))])
+            }
+            AudioModemError::FrameNumberMismatch => (Code::FrameNumberMismatch, vec![]),
+            AudioModemError::InvalidConfig(detail) => (Code::InvalidConfig, reason(detail)),
+            AudioModemError::FecError(detail) => (Code::FecError, reason(detail)),
+            AudioModemError::FountainDecodeFailure => (Code::FountainDecodeFailure, vec![]),
+            AudioModemError::InvalidPacket(detail) => (Code::InvalidPacket, reason(detail)),
+            AudioModemError::InvalidTransferPart(detail) => (Code::InvalidTransferPart, reason(detail)),
+            AudioModemError::WavError(detail) => (Code::WavError, reason(detail)),
+            AudioModemError::PayloadEncoding(detail) => (Code::PayloadEncoding, reason(detail)),
+            AudioModemError::Timeout => (Code::Timeout, vec![]),
+        };
+        details.into_iter().fold(Self::new(code, message), |error, (key, value)| error.with_detail(key, value))
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::sync::mpsc::{channel, Receiver};
+use transmitwave_core::sync::generate_keyed_preamble;
+use transmitwave_core::{DecodeEvent, DecoderFsk, LevelMeter, Samples, StreamingDecoder, PREAMBLE_SAMPLES};
+use wasm_bindgen::prelude::*;
+use crate::error::WasmError;
+
+// ============================================================================
+// DECODE EVENTS
+// Decoder progress forwarded to a JS callback as plain objects:
+//   { type: "preamble" | "frame" | "frame_failed" | "block" | "block_crc_failed"
+//           | "restart" | "recovered", position, seconds, ...fields }
+// ============================================================================
+
+/// Collects the decoder's events and passes them on to a JS callback
+pub(crate) struct EventForwarder {
+    callback: js_sys::Function,
+    receiver: Receiver<DecodeEvent>,
+    /// Events already forwarded, for decoders that re-read their whole buffer
+    seen: Option<HashSet<(usize, &'static str, u32)>>,
+}
+
+impl EventForwarder {
+    /// Route the events of `decoder` to `callback`, skipping repeats if `deduplicate`
+    pub(crate) fn attach(decoder: &mut DecoderFsk, callback: js_sys::Function, deduplicate: bool) -> Self {
+        let (sender, receiver) = channel();
+        decoder.set_event_sender(Some(sender));
+        Self {
+            callback,
+            receiver,
+            seen: deduplicate.then(HashSet::new),
+        }
+    }
+
+    fn is_new(&mut self, event: &DecodeEvent) -> bool {
+        let Some(seen) = self.seen.as_mut() else { return true };
+        let received = match *event {
+            DecodeEvent::BlockDecoded { received, .. } => received,
+            _ => 0,
+        };
+        seen.insert((event.position(), event.kind(), received))
+    }
+
+    pub(crate) fn into_callback(self) -> js_sys::Function {
+        self.callback
+    }
+
+    /// Forget forwarded events, e.g. when positions start over with a new buffer
+    pub(crate) fn clear(&mut self) {
+        if let Some(seen) = self.seen.as_mut() {
+            seen.clear();
+        }
+        self.receiver.try_iter().for_each(drop);
+    }
+
+    /// Call the callback once per new event; stops at the first callback error
+    pub(crate) fn forward(&mut self) -> Result<(), WasmError> {
+        while let Ok(event) = self.receiver.try_recv() {
+            if self.is_new(&event) {
+                self.callback.call1(&JsValue::NULL, &event_to_js(&event)).map_err(WasmError::callback)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn event_to_js(event: &DecodeEvent) -> JsValue {
+    let object = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| {
+        let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+    };
+    set("type", event.kind().into());
+    set("position", (event.position() as f64).into());
+    set("seconds", event.seconds().into());
+    match event {
+        DecodeEvent::FrameDecoded { payload_len, .. } | DecodeEvent::FountainRecovered { payload_len, .. } => {
+            set("payloadLength", (*payload_len as f64).into());
+        }
+        DecodeEvent::FrameFailed { error, .. } => {
+            let error = WasmError::from(error.clone());
+            set("error", error.message().into());
+            set("errorCode", error.code().into());
+        }
+        DecodeEvent::BlockDecoded { received, needed, .. } => {
+            set("received", (*received).into());
+            set("needed", (*needed).into());
+        }
+        DecodeEvent::PreambleDetected { .. }
+        | DecodeEvent::BlockCrcFailed { .. }
+        | DecodeEvent::StreamRestarted { .. } => {}
+    }
+    object.into()
+}
+
+/// Decoder for live microphone audio: feed chunks, get frames as they complete
+#[wasm_bindgen]
+pub struct WasmStreamingDecoder {
+    inner: StreamingDecoder,
+    events: Option<EventForwarder>,
+    meter: LevelMeter,
+}
+
+#[wasm_bindgen]
+impl WasmStreamingDecoder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<WasmStreamingDecoder, WasmError> {
+        StreamingDecoder::new()
+            .map(|decoder| WasmStreamingDecoder {
+                inner: decoder,
+                events: None,
+                meter: LevelMeter::with_template(generate_keyed_preamble(Samples(PREAMBLE_SAMPLES), 1.0, None)),
+            })
+            .map_err(WasmError::from)
+    }
+
+    /// Call `on_event` with an object per decoder event ({ type, position, seconds, ... })
+    #[wasm_bindgen]
+    pub fn set_event_callback(&mut self, on_event: js_sys::Function) {
+        self.events = Some(EventForwarder::attach(self.inner.decoder_mut(), on_event, false));
+    }
+
+    /// Feed audio and return the payloads of the frames completed by it
+    /// (an array of Uint8Array)
+    #[wasm_bindgen]
+    pub fn push(&mut self, samples: &[f32]) -> Result<js_sys::Array, WasmError> {
+        self.meter.push(samples);
+        let payloads: js_sys::Array = self
+            .inner
+            .push(samples)
+            .iter()
+            .map(|message| js_sys::Uint8Array::from(message.payload.as_slice()))
+            .collect();
+        if let Some(events) = self.events.as_mut() {
+            events.forward()?;
+        }
+        Ok(payloads)
+    }
+
+    /// Drop buffered audio, e.g. while the local speaker is playing
+    #[wasm_bindgen]
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    #[wasm_bindgen]
+    pub fn buffered_samples(&self) -> usize {
+        self.inner.buffered_samples()
+    }
+
+    /// Background noise floor of the audio pushed so far, in dBFS
+    #[wasm_bindgen]
+    pub fn noise_floor_db(&self) -> f32 {
+        self.inner.noise_floor().noise_floor_db()
+    }
+
+    /// Peak-held meter values for a level-meter UI, as a Float32Array:
+    /// [preamble correlation, rms, band 0 level, ..., band 5 level] (linear, full scale 1.0)
+    #[wasm_bindgen]
+    pub fn get_meter(&self) -> Vec<f32> {
+        self.meter.reading().to_array().to_vec()
+    }
+}
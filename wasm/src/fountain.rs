@@ -0,0 +1,466 @@
+use crate::error::WasmError;
+use crate::events::EventForwarder;
+use crate::options::WasmEncoderOptions;
+use crate::{apply_gain, melody_from_js, WasmDecodeStats};
+use wasm_bindgen::prelude::*;
+use transmitwave_core::{
+    AudioModemError, DecoderFsk, EncoderFsk, FountainConfig, FountainListener, FountainStream, FOUNTAIN_BLOCK_SIZE, SAMPLE_RATE,
+};
+use transmitwave_core::decoder_fsk::FountainProgress;
+use transmitwave_core::sync::DetectionThreshold;
+
+// ============================================================================
+// FOUNTAIN CODE ENCODER/DECODER
+// Continuous streaming mode using RaptorQ fountain codes (RFC 6330)
+// ============================================================================
+
+/// Fountain Code Encoder for continuous streaming
+#[wasm_bindgen]
+pub struct WasmFountainEncoder {
+    inner: EncoderFsk,
+    stream: Option<FountainStream>,
+    /// Output gain applied to every block
+    amplitude: f32,
+}
+
+#[wasm_bindgen]
+impl WasmFountainEncoder {
+    /// Create a new fountain encoder; `options` is an optional
+    /// `WasmEncoderOptions` object as taken by `WasmEncoder`
+    #[wasm_bindgen(constructor)]
+    pub fn new(options: Option<js_sys::Object>) -> Result<WasmFountainEncoder, WasmError> {
+        let options = WasmEncoderOptions::from_js(options)?;
+        let mut encoder = WasmFountainEncoder {
+            inner: EncoderFsk::new()?,
+            stream: None,
+            amplitude: 1.0,
+        };
+        options.apply(&mut encoder.inner, &mut encoder.amplitude)?;
+        Ok(encoder)
+    }
+
+    /// Open each block with a custom melody instead of the three-note whistle:
+    /// note frequencies in Hz and durations in ms, adding up to 250 ms. Empty
+    /// arrays go back to the whistle. Listeners need the same melody.
+    #[wasm_bindgen]
+    pub fn set_fountain_melody(&mut self, frequencies: &[f32], durations_ms: &[u32]) -> Result<(), WasmError> {
+        self.inner.set_fountain_melody(melody_from_js(frequencies, durations_ms)?);
+        Ok(())
+    }
+
+    /// Encode data into fountain-coded audio stream
+    /// Returns a flat Float32Array of all audio samples (concatenated blocks)
+    ///
+    /// Synthesizes the whole timeout in one call; UIs that must stay
+    /// responsive use `start_streaming` and `next_block` instead.
+    ///
+    /// Parameters:
+    /// - data: Input data to encode
+    /// - timeout_secs: Audio duration in seconds (e.g., 30)
+    /// - block_size: Symbol size in bytes (e.g., 64)
+    /// - repair_ratio: Repair packet overhead (e.g., 0.5 for 50%)
+    #[wasm_bindgen]
+    pub fn encode_fountain(
+        &mut self,
+        data: &[u8],
+        timeout_secs: u32,
+        block_size: usize,
+        repair_ratio: f32,
+    ) -> Result<Vec<f32>, WasmError> {
+        // Collect all blocks and concatenate into single audio buffer
+        let mut all_samples: Vec<f32> = self.open_stream(data, block_size, repair_ratio, timeout_secs)?.flatten().collect();
+        apply_gain(&mut all_samples, self.amplitude);
+
+        Ok(all_samples)
+    }
+
+    /// Begin a streaming session that yields one fountain block at a time.
+    /// Timeout of 0 seconds disables automatic stopping (infinite stream).
+    #[wasm_bindgen]
+    pub fn start_streaming(
+        &mut self,
+        data: &[u8],
+        block_size: usize,
+        repair_ratio: f32,
+        timeout_secs: u32,
+    ) -> Result<(), WasmError> {
+        self.stream = Some(self.open_stream(data, block_size, repair_ratio, timeout_secs)?);
+        Ok(())
+    }
+
+    /// Next block of the active stream as a plain array, or undefined once it
+    /// has ended; `next_block` is this as a Float32Array or null
+    #[wasm_bindgen]
+    pub fn next_stream_block(&mut self) -> Option<Vec<f32>> {
+        let mut block = self.stream.as_mut().and_then(|stream| stream.next());
+        match block.as_mut() {
+            Some(samples) => apply_gain(samples, self.amplitude),
+            None => self.stream = None,
+        }
+        block
+    }
+
+    /// Synthesize one block of the active stream as a Float32Array, or null
+    /// once the timeout is reached or no stream was started
+    ///
+    /// Cheap enough to call once per animation frame; the stream stays alive
+    /// inside the encoder between calls.
+    #[wasm_bindgen]
+    pub fn next_block(&mut self) -> JsValue {
+        match self.next_stream_block() {
+            Some(block) => js_sys::Float32Array::from(block.as_slice()).into(),
+            None => JsValue::NULL,
+        }
+    }
+
+    /// Whether a stream is active and not yet exhausted
+    #[wasm_bindgen]
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Fraction of the stream's timeout synthesized so far (0 for infinite or no stream)
+    #[wasm_bindgen]
+    pub fn get_stream_progress(&self) -> f32 {
+        self.stream
+            .as_ref()
+            .and_then(|stream| stream.sample_limit().map(|limit| (stream.samples_generated() as f32 / limit as f32).min(1.0)))
+            .unwrap_or(0.0)
+    }
+
+    /// Stop the current stream and release its resources.
+    #[wasm_bindgen]
+    pub fn stop_streaming(&mut self) {
+        self.stream = None;
+    }
+}
+
+impl WasmFountainEncoder {
+    fn open_stream(
+        &mut self,
+        data: &[u8],
+        block_size: usize,
+        repair_ratio: f32,
+        timeout_secs: u32,
+    ) -> Result<FountainStream, WasmError> {
+        let config = FountainConfig {
+            timeout_secs,
+            block_size,
+            repair_blocks_ratio: repair_ratio,
+            ..Default::default()
+        };
+        Ok(self.inner.encode_fountain(data, Some(config))?)
+    }
+}
+
+/// Fountain symbols received so far, for a per-symbol progress grid
+#[wasm_bindgen]
+pub struct WasmFountainProgress {
+    symbol_ids: Vec<u32>,
+    /// Source symbols of the payload (0 before the first valid block)
+    pub source_symbols: u32,
+    /// Estimated symbols still needed
+    pub remaining: u32,
+}
+
+#[wasm_bindgen]
+impl WasmFountainProgress {
+    /// Encoding symbol IDs received, ascending (the ESI, with the RaptorQ
+    /// source block number in the top byte)
+    #[wasm_bindgen(getter)]
+    pub fn symbol_ids(&self) -> Vec<u32> {
+        self.symbol_ids.clone()
+    }
+
+    /// Distinct symbols received
+    #[wasm_bindgen(getter)]
+    pub fn received(&self) -> u32 {
+        self.symbol_ids.len() as u32
+    }
+}
+
+impl From<&FountainProgress> for WasmFountainProgress {
+    fn from(progress: &FountainProgress) -> Self {
+        WasmFountainProgress {
+            symbol_ids: progress.symbol_ids.clone(),
+            source_symbols: progress.source_symbols,
+            remaining: progress.remaining(),
+        }
+    }
+}
+
+/// Default cap for the fountain decoder buffer (2 minutes of audio, ~7.7 MB)
+const DEFAULT_FOUNTAIN_MAX_BUFFER_SAMPLES: usize = SAMPLE_RATE * 120;
+
+/// Fountain Code Decoder for continuous streaming
+#[wasm_bindgen]
+pub struct WasmFountainDecoder {
+    inner: DecoderFsk,
+    buffer: Vec<f32>,
+    block_size: usize,
+    max_buffer_samples: usize,
+    /// Decode in progress of `push_chunk`
+    listener: Option<FountainListener>,
+    events: Option<EventForwarder>,
+}
+
+#[wasm_bindgen]
+impl WasmFountainDecoder {
+    /// Create a new fountain decoder
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Result<WasmFountainDecoder, WasmError> {
+        DecoderFsk::new()
+            .map(|decoder| WasmFountainDecoder {
+                inner: decoder,
+                buffer: Vec::new(),
+                block_size: FOUNTAIN_BLOCK_SIZE,
+                max_buffer_samples: DEFAULT_FOUNTAIN_MAX_BUFFER_SAMPLES,
+                listener: None,
+                events: None,
+            })
+            .map_err(WasmError::from)
+    }
+
+    /// Look for blocks opened by the sender's melody (see
+    /// `WasmFountainEncoder.set_fountain_melody`); empty arrays mean the whistle
+    #[wasm_bindgen]
+    pub fn set_fountain_melody(&mut self, frequencies: &[f32], durations_ms: &[u32]) -> Result<(), WasmError> {
+        self.inner.set_fountain_melody(melody_from_js(frequencies, durations_ms)?);
+        Ok(())
+    }
+
+    /// Set the block size for decoding
+    #[wasm_bindgen]
+    pub fn set_block_size(&mut self, block_size: usize) {
+        self.block_size = block_size;
+    }
+
+    /// Call `on_event` with an object per decoder event ({ type, position,
+    /// seconds, ... }, e.g. `block` with `received`/`needed`); `try_decode`
+    /// reports each event of the buffer once
+    #[wasm_bindgen]
+    pub fn set_event_callback(&mut self, on_event: js_sys::Function) {
+        self.events = Some(EventForwarder::attach(&mut self.inner, on_event, true));
+    }
+
+    /// Set the detection threshold for both preamble and postamble
+    #[wasm_bindgen]
+    pub fn set_detection_threshold(&mut self, fixed_value: f32) {
+        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
+        self.inner.set_detection_threshold(threshold);
+    }
+
+    /// Set the detection threshold for preamble only
+    #[wasm_bindgen]
+    pub fn set_preamble_threshold(&mut self, fixed_value: f32) {
+        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
+        self.inner.set_preamble_threshold(threshold);
+    }
+
+    /// Get the current preamble detection threshold (undefined when adaptive)
+    #[wasm_bindgen]
+    pub fn get_preamble_threshold(&self) -> Option<f32> {
+        match self.inner.get_preamble_threshold() {
+            DetectionThreshold::Fixed(value) => Some(value),
+            DetectionThreshold::Adaptive => None,
+        }
+    }
+
+    /// Set the detection threshold for postamble only
+    #[wasm_bindgen]
+    pub fn set_postamble_threshold(&mut self, fixed_value: f32) {
+        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
+        self.inner.set_postamble_threshold(threshold);
+    }
+
+    /// Get the current postamble detection threshold (undefined when adaptive)
+    #[wasm_bindgen]
+    pub fn get_postamble_threshold(&self) -> Option<f32> {
+        match self.inner.get_postamble_threshold() {
+            DetectionThreshold::Fixed(value) => Some(value),
+            DetectionThreshold::Adaptive => None,
+        }
+    }
+
+    /// Set the maximum number of samples the buffer may hold (minimum 1)
+    #[wasm_bindgen]
+    pub fn set_max_buffer_samples(&mut self, max_samples: usize) {
+        self.max_buffer_samples = max_samples.max(1);
+    }
+
+    /// Get the maximum number of samples the buffer may hold
+    #[wasm_bindgen]
+    pub fn get_max_buffer_samples(&self) -> usize {
+        self.max_buffer_samples
+    }
+
+    /// Check whether the buffer has reached its cap
+    #[wasm_bindgen]
+    pub fn is_buffer_full(&self) -> bool {
+        self.buffer.len() >= self.max_buffer_samples
+    }
+
+    /// Feed audio chunk to the decoder buffer
+    ///
+    /// Returns false (backpressure) when the buffer cap was reached and some or
+    /// all of the chunk was dropped. `try_decode` does not consume the buffer,
+    /// so only `reset` frees room; `push_chunk` decodes without the cap.
+    #[wasm_bindgen]
+    pub fn feed_chunk(&mut self, samples: &[f32]) -> bool {
+        let room = self.max_buffer_samples.saturating_sub(self.buffer.len());
+        let accepted = samples.len().min(room);
+        self.buffer.extend_from_slice(&samples[..accepted]);
+        accepted == samples.len()
+    }
+
+    /// Get the current number of samples in the buffer
+    #[wasm_bindgen]
+    pub fn get_sample_count(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Decode the whole buffer from its start
+    ///
+    /// The buffer is kept, so each call rereads it along with the audio fed
+    /// since. Returns decoded data if successful, or error if decoding fails
+    #[wasm_bindgen]
+    pub fn try_decode(&mut self) -> Result<Vec<u8>, WasmError> {
+        if self.buffer.is_empty() {
+            return Err(WasmError::no_data("No audio data in buffer"));
+        }
+
+        let config = FountainConfig {
+            timeout_secs: 30, // Not enforced in WASM
+            block_size: self.block_size,
+            repair_blocks_ratio: 0.5, // Not used by decoder
+            ..Default::default()
+        };
+
+        let result = self.inner.decode_fountain(&self.buffer, Some(config));
+        self.forward_events()?;
+        result.map_err(WasmError::from)
+    }
+
+    /// Decode audio as it arrives, consuming it as the search passes it
+    ///
+    /// Holds the blocks received so far and about a block of audio instead of
+    /// the whole recording, so the buffer cap does not apply. Returns the
+    /// payload once enough blocks have arrived, undefined until then; the call
+    /// after a payload starts on the next one.
+    #[wasm_bindgen]
+    pub fn push_chunk(&mut self, samples: &[f32]) -> Result<Option<Vec<u8>>, WasmError> {
+        let block_size = self.block_size;
+        let listener = self
+            .listener
+            .get_or_insert_with(|| FountainListener::new(Some(FountainConfig { block_size, ..Default::default() })));
+        let result = self.inner.decode_fountain_push(listener, samples);
+        self.forward_events()?;
+        match result {
+            Ok((payload, _)) => {
+                self.listener = None;
+                Ok(Some(payload))
+            }
+            Err(AudioModemError::FountainDecodeFailure) => Ok(None),
+            Err(e) => Err(WasmError::from(e)),
+        }
+    }
+
+    fn forward_events(&mut self) -> Result<(), WasmError> {
+        self.events.as_mut().map_or(Ok(()), EventForwarder::forward)
+    }
+
+    /// Reset the decoder, clear the buffer and drop a `push_chunk` decode.
+    ///
+    /// Returns an error if decoder initialization fails. On success, both the
+    /// buffer and decoder state are cleared (the melody is kept). On failure,
+    /// the decoder state is left unchanged and the buffer is cleared.
+    #[wasm_bindgen]
+    pub fn reset(&mut self) -> Result<(), WasmError> {
+        self.buffer.clear();
+        self.listener = None;
+        // Create a new inner decoder to reset its state
+        DecoderFsk::new()
+            .map(|mut decoder| {
+                decoder.set_fountain_melody(self.inner.fountain_melody().cloned());
+                self.inner = decoder;
+                if let Some(events) = self.events.take() {
+                    self.events = Some(EventForwarder::attach(&mut self.inner, events.into_callback(), true));
+                }
+            })
+            .map_err(WasmError::from)
+    }
+
+    /// Get the number of successfully decoded blocks
+    #[wasm_bindgen]
+    pub fn get_decoded_blocks(&self) -> u32 {
+        self.inner.stats.decoded_blocks
+    }
+
+    /// Get the number of blocks that failed CRC check
+    #[wasm_bindgen]
+    pub fn get_failed_blocks(&self) -> u32 {
+        self.inner.stats.failed_blocks
+    }
+
+    /// Get all decode statistics as a WasmDecodeStats object
+    #[wasm_bindgen]
+    pub fn get_stats(&self) -> WasmDecodeStats {
+        WasmDecodeStats::from(self.inner.stats.clone())
+    }
+
+    /// Symbols collected by the last `try_decode` or `decode_fountain`, e.g.
+    /// "received 37/52 symbols" from `received` and `source_symbols`
+    #[wasm_bindgen]
+    pub fn get_progress(&self) -> WasmFountainProgress {
+        WasmFountainProgress::from(self.inner.fountain_progress())
+    }
+
+    /// Decode fountain-coded audio stream back to data (non-streaming mode)
+    ///
+    /// Parameters:
+    /// - samples: Audio samples from microphone/recording
+    /// - timeout_secs: Maximum time to spend decoding (e.g., 30)
+    /// - block_size: Symbol size in bytes (must match encoder, e.g., 64)
+    #[wasm_bindgen]
+    pub fn decode_fountain(
+        &mut self,
+        samples: &[f32],
+        timeout_secs: u32,
+        block_size: usize,
+    ) -> Result<Vec<u8>, WasmError> {
+        let config = FountainConfig {
+            timeout_secs,
+            block_size,
+            repair_blocks_ratio: 0.5, // Not used by decoder
+            ..Default::default()
+        };
+
+        if let Some(events) = self.events.as_mut() {
+            events.clear();
+        }
+        let result = self.inner.decode_fountain(samples, Some(config));
+        self.forward_events()?;
+        result.map_err(WasmError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streamed_blocks_match_one_shot_encode() {
+        let mut encoder = WasmFountainEncoder::new(None).unwrap();
+        let all = encoder.encode_fountain(b"streamed", 2, 32, 0.5).unwrap();
+
+        encoder.start_streaming(b"streamed", 32, 0.5, 2).unwrap();
+        assert!(encoder.is_streaming());
+        let mut streamed = Vec::new();
+        while let Some(block) = encoder.next_stream_block() {
+            streamed.extend(block);
+        }
+        assert!(!encoder.is_streaming());
+        assert_eq!(streamed, all);
+        assert_eq!(encoder.next_stream_block(), None);
+    }
+}
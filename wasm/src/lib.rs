@@ -1,8 +1,27 @@
 use wasm_bindgen::prelude::*;
-use transmitwave_core::{DecoderFsk, EncoderFsk, FountainConfig, FountainStream, detect_preamble, detect_postamble, detect_fountain_preamble, FOUNTAIN_BLOCK_SIZE};
+use transmitwave_core::{AudioModemError, ContentType, DecoderFsk, EncoderFsk, EncoderPadding, Melody, Millis, Samples, detect_preamble, detect_postamble};
 use transmitwave_core::decoder_fsk::DecodeStats;
+use transmitwave_core::noise_floor::NoiseFloorEstimator;
+use transmitwave_core::meter::LevelMeter;
+use transmitwave_core::sync::{detect_fountain_template, generate_fountain_preamble, generate_keyed_postamble, generate_keyed_preamble};
+use transmitwave_core::diagnostics::CaptureDiagnostics;
+use transmitwave_core::presence::SignalPresence;
+use transmitwave_core::tx_audio::TxAudioReport;
 use transmitwave_core::sync::DetectionThreshold;
 
+mod error;
+pub use error::{WasmError, WasmErrorCode};
+mod events;
+pub use events::WasmStreamingDecoder;
+mod options;
+use options::WasmEncoderOptions;
+mod session;
+pub use session::WasmSession;
+#[cfg(feature = "fountain")]
+mod fountain;
+#[cfg(feature = "fountain")]
+pub use fountain::{WasmFountainDecoder, WasmFountainEncoder, WasmFountainProgress};
+
 // ============================================================================
 // DECODE STATISTICS
 // ============================================================================
@@ -10,22 +29,24 @@ use transmitwave_core::sync::DetectionThreshold;
 /// Decode statistics exposed to JavaScript
 #[wasm_bindgen]
 pub struct WasmDecodeStats {
-    /// Number of successfully decoded blocks (passed CRC)
+    /// Number of successfully decoded fountain blocks (passed CRC)
     pub decoded_blocks: u32,
-    /// Number of blocks that failed CRC check (corrupted)
+    /// Number of fountain blocks that failed CRC check (corrupted)
     pub failed_blocks: u32,
-}
-
-#[wasm_bindgen]
-impl WasmDecodeStats {
-    /// Create a new decode statistics object
-    #[wasm_bindgen(constructor)]
-    pub fn new(decoded_blocks: u32, failed_blocks: u32) -> WasmDecodeStats {
-        WasmDecodeStats {
-            decoded_blocks,
-            failed_blocks,
-        }
-    }
+    /// Times a fountain decode switched to a new transmission mid-recording
+    pub fountain_restarts: u32,
+    /// Reed-Solomon blocks of regular frames that decoded
+    pub rs_blocks_decoded: u32,
+    /// Reed-Solomon blocks of regular frames that could not be corrected
+    pub rs_blocks_failed: u32,
+    /// Received bytes the RS decoder corrected (data and parity)
+    pub corrected_bytes: u32,
+    /// Correlation score of the last preamble tried (undefined if none)
+    pub preamble_score: Option<f32>,
+    /// Correlation score of the postamble (undefined if not found)
+    pub postamble_score: Option<f32>,
+    /// FSK symbols demodulated
+    pub symbols_demodulated: u32,
 }
 
 impl From<DecodeStats> for WasmDecodeStats {
@@ -33,6 +54,13 @@ impl From<DecodeStats> for WasmDecodeStats {
         WasmDecodeStats {
             decoded_blocks: stats.decoded_blocks,
             failed_blocks: stats.failed_blocks,
+            fountain_restarts: stats.fountain_restarts,
+            rs_blocks_decoded: stats.rs_blocks_decoded,
+            rs_blocks_failed: stats.rs_blocks_failed,
+            corrected_bytes: stats.corrected_bytes,
+            preamble_score: stats.preamble_score,
+            postamble_score: stats.postamble_score,
+            symbols_demodulated: stats.symbols_demodulated,
         }
     }
 }
@@ -46,26 +74,93 @@ impl From<DecodeStats> for WasmDecodeStats {
 #[wasm_bindgen]
 pub struct WasmEncoder {
     inner: EncoderFsk,
+    /// Output gain applied to every sample
+    amplitude: f32,
 }
 
 #[wasm_bindgen]
 impl WasmEncoder {
+    /// Create an encoder; `options` is an optional `WasmEncoderOptions` object
+    /// (profile, rs, amplitude_bits, tone_layout, symbol_gaps, dual_band,
+    /// amplitude, network_key, content_type, leading_silence_ms,
+    /// trailing_silence_ms, fade_in_ms). Omitted fields keep the defaults.
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<WasmEncoder, JsValue> {
-        EncoderFsk::new()
-            .map(|encoder| WasmEncoder {
-                inner: encoder,
-            })
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    pub fn new(options: Option<js_sys::Object>) -> Result<WasmEncoder, WasmError> {
+        let options = WasmEncoderOptions::from_js(options)?;
+        let mut encoder = WasmEncoder {
+            inner: EncoderFsk::new()?,
+            amplitude: 1.0,
+        };
+        options.apply(&mut encoder.inner, &mut encoder.amplitude)?;
+        Ok(encoder)
     }
 
     /// Encode binary data into audio samples with FSK
     /// Takes a Uint8Array and returns Float32Array of audio samples
     #[wasm_bindgen]
-    pub fn encode(&mut self, data: &[u8]) -> Result<Vec<f32>, JsValue> {
+    pub fn encode(&mut self, data: &[u8]) -> Result<Vec<f32>, WasmError> {
+        let mut samples = self.inner.encode(data)?;
+        apply_gain(&mut samples, self.amplitude);
+        Ok(samples)
+    }
+
+    /// Encode like `encode` with `overrides` (a `WasmEncoderOptions` object)
+    /// layered over the constructor options for this call only
+    #[wasm_bindgen]
+    pub fn encode_with(&self, data: &[u8], overrides: Option<js_sys::Object>) -> Result<Vec<f32>, WasmError> {
+        let overrides = WasmEncoderOptions::from_js(overrides)?;
+        let mut encoder = self.inner.clone();
+        let mut amplitude = self.amplitude;
+        overrides.apply(&mut encoder, &mut amplitude)?;
+        let mut samples = encoder.encode(data)?;
+        apply_gain(&mut samples, amplitude);
+        Ok(samples)
+    }
+
+    /// Encode like `encode`, but call `on_chunk` with one Float32Array per
+    /// piece (silence, sync signal or FSK symbol) instead of building the
+    /// whole buffer; chunks can go straight to an AudioWorklet port
+    #[wasm_bindgen]
+    pub fn encode_chunks(&mut self, data: &[u8], on_chunk: &js_sys::Function) -> Result<(), WasmError> {
+        let mut callback_error = None;
+        let amplitude = self.amplitude;
         self.inner
-            .encode(data)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+            .encode_into(data, |chunk| {
+                if callback_error.is_none() {
+                    let array = if amplitude == 1.0 {
+                        js_sys::Float32Array::from(chunk)
+                    } else {
+                        let scaled: Vec<f32> = chunk.iter().map(|s| s * amplitude).collect();
+                        js_sys::Float32Array::from(&scaled[..])
+                    };
+                    if let Err(e) = on_chunk.call1(&JsValue::NULL, &array) {
+                        callback_error = Some(e);
+                    }
+                }
+            })?;
+        callback_error.map_or(Ok(()), |e| Err(WasmError::callback(e)))
+    }
+
+    /// Announce the payload type (a MIME type such as "application/json", or
+    /// text, url, json, binary, cbor, vcard); undefined sends none
+    #[wasm_bindgen]
+    pub fn set_content_type(&mut self, content_type: Option<String>) -> Result<(), WasmError> {
+        let content_type = content_type.map(|mime| mime.parse::<ContentType>()).transpose()?;
+        self.inner.set_content_type(content_type);
+        Ok(())
+    }
+
+    /// Set leading/trailing silence and the fade-in ramp before the preamble (milliseconds)
+    #[wasm_bindgen]
+    pub fn set_padding(&mut self, leading_silence_ms: u32, trailing_silence_ms: u32, fade_in_ms: u32) {
+        self.inner
+            .set_padding(EncoderPadding::from_ms(Millis(leading_silence_ms), Millis(trailing_silence_ms), Millis(fade_in_ms)));
+    }
+}
+
+pub(crate) fn apply_gain(samples: &mut [f32], amplitude: f32) {
+    if amplitude != 1.0 {
+        samples.iter_mut().for_each(|s| *s *= amplitude);
     }
 }
 
@@ -73,17 +168,23 @@ impl WasmEncoder {
 #[wasm_bindgen]
 pub struct WasmDecoder {
     inner: DecoderFsk,
+    /// Payload of the last decode that failed only its CRC (salvage enabled)
+    unverified: Option<Vec<u8>>,
+    /// Content type announced by the last decoded frame
+    content_type: Option<ContentType>,
 }
 
 #[wasm_bindgen]
 impl WasmDecoder {
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<WasmDecoder, JsValue> {
+    pub fn new() -> Result<WasmDecoder, WasmError> {
         DecoderFsk::new()
             .map(|decoder| WasmDecoder {
                 inner: decoder,
+                unverified: None,
+                content_type: None,
             })
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+            .map_err(WasmError::from)
     }
 
     /// Set the detection threshold for both preamble and postamble
@@ -100,12 +201,12 @@ impl WasmDecoder {
         self.inner.set_preamble_threshold(threshold);
     }
 
-    /// Get the current preamble detection threshold
+    /// Get the current preamble detection threshold (undefined when adaptive)
     #[wasm_bindgen]
-    pub fn get_preamble_threshold(&self) -> f32 {
+    pub fn get_preamble_threshold(&self) -> Option<f32> {
         match self.inner.get_preamble_threshold() {
-            DetectionThreshold::Fixed(value) => value,
-            DetectionThreshold::Adaptive => panic!("WASM should only use Fixed threshold, not Adaptive"),
+            DetectionThreshold::Fixed(value) => Some(value),
+            DetectionThreshold::Adaptive => None,
         }
     }
 
@@ -116,22 +217,49 @@ impl WasmDecoder {
         self.inner.set_postamble_threshold(threshold);
     }
 
-    /// Get the current postamble detection threshold
+    /// Get the current postamble detection threshold (undefined when adaptive)
     #[wasm_bindgen]
-    pub fn get_postamble_threshold(&self) -> f32 {
+    pub fn get_postamble_threshold(&self) -> Option<f32> {
         match self.inner.get_postamble_threshold() {
-            DetectionThreshold::Fixed(value) => value,
-            DetectionThreshold::Adaptive => panic!("WASM should only use Fixed threshold, not Adaptive"),
+            DetectionThreshold::Fixed(value) => Some(value),
+            DetectionThreshold::Adaptive => None,
         }
     }
 
     /// Decode audio samples back to binary data with FSK
     /// Takes a Float32Array and returns Uint8Array of decoded data
     #[wasm_bindgen]
-    pub fn decode(&mut self, samples: &[f32]) -> Result<Vec<u8>, JsValue> {
-        self.inner
-            .decode(samples)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    pub fn decode(&mut self, samples: &[f32]) -> Result<Vec<u8>, WasmError> {
+        let result = self.inner.decode_with_metadata(samples);
+        self.content_type = result.as_ref().ok().and_then(|message| message.content_type);
+        self.finish(result.map(|message| message.payload))
+    }
+
+    /// Decode a capture straight from the AudioContext (e.g. 44.1 or 48 kHz)
+    ///
+    /// `source_rate` is usually `audioContext.sampleRate`; the decoder checks it
+    /// against the common capture rates and resamples once
+    #[wasm_bindgen]
+    pub fn decode_autorate(&mut self, samples: &[f32], source_rate: usize) -> Result<Vec<u8>, WasmError> {
+        let result = self.inner.decode_autorate(samples, source_rate);
+        self.content_type = result.as_ref().ok().and_then(|message| message.content_type);
+        self.finish(result.map(|message| message.payload))
+    }
+
+    /// Input rate the last `decode_autorate` used (undefined for other decodes)
+    #[wasm_bindgen]
+    pub fn get_source_rate(&self) -> Option<usize> {
+        self.inner.stats.source_rate
+    }
+
+    /// MIME type the last decoded frame announced (undefined if none); codes
+    /// outside the registry come back as "x-transmitwave/<code>"
+    #[wasm_bindgen]
+    pub fn get_content_type(&self) -> Option<String> {
+        self.content_type.map(|content_type| match content_type.mime() {
+            Some(mime) => mime.to_string(),
+            None => format!("x-transmitwave/{}", content_type.code()),
+        })
     }
 
     /// Decode audio samples without preamble/postamble detection
@@ -141,10 +269,40 @@ impl WasmDecoder {
     /// would cause double-detection issues.
     /// Takes a Float32Array and returns Uint8Array of decoded data
     #[wasm_bindgen]
-    pub fn decode_without_preamble_postamble(&mut self, samples: &[f32]) -> Result<Vec<u8>, JsValue> {
-        self.inner
-            .decode_without_preamble_postamble(samples)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    pub fn decode_without_preamble_postamble(&mut self, samples: &[f32]) -> Result<Vec<u8>, WasmError> {
+        let result = self.inner.decode_without_preamble_postamble(samples);
+        self.content_type = None;
+        self.finish(result)
+    }
+
+    /// Statistics of the last `decode` / `decode_without_preamble_postamble` call
+    #[wasm_bindgen]
+    pub fn get_stats(&self) -> WasmDecodeStats {
+        WasmDecodeStats::from(self.inner.stats.clone())
+    }
+
+    /// Keep the candidate payload of frames that fail only their CRC; decode
+    /// still throws, and `take_unverified_payload` returns the bytes
+    #[wasm_bindgen]
+    pub fn set_salvage_unverified(&mut self, enabled: bool) {
+        self.inner.set_salvage_unverified(enabled);
+    }
+
+    /// Best-effort payload of the last decode that failed its CRC, to show
+    /// flagged as unverified (undefined if none)
+    #[wasm_bindgen]
+    pub fn take_unverified_payload(&mut self) -> Option<Vec<u8>> {
+        self.unverified.take()
+    }
+
+    fn finish(&mut self, result: transmitwave_core::Result<Vec<u8>>) -> Result<Vec<u8>, WasmError> {
+        self.unverified = None;
+        result.map_err(|e| {
+            if let AudioModemError::CrcMismatch { payload } = &e {
+                self.unverified = Some(payload.clone());
+            }
+            WasmError::from(e)
+        })
     }
 }
 
@@ -153,43 +311,96 @@ impl WasmDecoder {
 // SIGNAL DETECTION (PREAMBLE & POSTAMBLE)
 // ============================================================================
 
+/// How a detector picks its threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ThresholdMode {
+    Fixed(f32),
+    /// Recomputed from the buffered audio on every call
+    Adaptive,
+    /// Adaptive until the first detection, then fixed at the value that detected it
+    Auto { locked: Option<f32> },
+}
+
 /// Generic signal detector for preamble/postamble detection
+///
+/// Each detected sync signal is consumed from the buffer, and detections
+/// starting within `holdoff_samples` of the previous one are ignored, so every
+/// physical signal is reported once. Adaptive thresholds come from a running
+/// noise-floor estimate instead of a rescan of the buffer.
 struct SignalDetector<F> {
     audio_buffer: Vec<f32>,
-    threshold: DetectionThreshold,
+    mode: ThresholdMode,
     required_samples: usize,
     detect_fn: F,
+    /// Stream position of `audio_buffer[0]`
+    buffer_start: u64,
+    holdoff_samples: usize,
+    /// Stream position before which detections are suppressed
+    holdoff_until: Option<u64>,
+    noise_floor: NoiseFloorEstimator,
+    /// Peak-held levels and correlation with the signal's template
+    meter: LevelMeter,
 }
 
 impl<F> SignalDetector<F>
 where
     F: Fn(&[f32], DetectionThreshold) -> Option<usize>,
 {
-    fn new(threshold: DetectionThreshold, required_samples: usize, detect_fn: F) -> Self {
+    fn new(fixed_value: f32, required_samples: usize, detect_fn: F, template: Vec<f32>) -> Self {
         SignalDetector {
             audio_buffer: Vec::new(),
-            threshold,
+            mode: ThresholdMode::Fixed(fixed_value.clamp(0.001, 1.0)),
             required_samples,
             detect_fn,
+            buffer_start: 0,
+            holdoff_samples: required_samples,
+            holdoff_until: None,
+            noise_floor: NoiseFloorEstimator::new(),
+            meter: LevelMeter::with_template(template),
+        }
+    }
+
+    fn detection_threshold(&self) -> DetectionThreshold {
+        match self.mode {
+            ThresholdMode::Fixed(value) | ThresholdMode::Auto { locked: Some(value) } => DetectionThreshold::Fixed(value),
+            ThresholdMode::Adaptive | ThresholdMode::Auto { locked: None } => {
+                self.noise_floor.resolve(DetectionThreshold::Adaptive)
+            }
         }
     }
 
     fn add_samples(&mut self, samples: &[f32]) -> i32 {
         self.audio_buffer.extend_from_slice(samples);
+        self.noise_floor.push(samples);
+        self.meter.push(samples);
 
         if self.audio_buffer.len() < self.required_samples {
             return -1;
         }
 
-        match (self.detect_fn)(&self.audio_buffer, self.threshold) {
-            Some(pos) => {
-                let pos_usize = pos as usize;
-                if pos_usize + self.required_samples <= self.audio_buffer.len() {
-                    self.audio_buffer.drain(0..pos_usize);
-                }
-                pos as i32
+        let threshold = self.detection_threshold();
+        let Some(pos) = (self.detect_fn)(&self.audio_buffer, threshold) else {
+            return -1;
+        };
+
+        let detected_at = self.buffer_start + pos as u64;
+        let suppressed = self.holdoff_until.is_some_and(|until| detected_at < until);
+        if !suppressed {
+            if let ThresholdMode::Auto { locked: locked @ None } = &mut self.mode {
+                *locked = Some(self.noise_floor.threshold_value(threshold));
             }
-            None => -1,
+            self.holdoff_until = Some(detected_at + self.holdoff_samples as u64);
+        }
+
+        // Consume the detected signal so later calls cannot find it again
+        let consumed = (pos + self.required_samples).min(self.audio_buffer.len());
+        self.audio_buffer.drain(..consumed);
+        self.buffer_start += consumed as u64;
+
+        if suppressed {
+            -1
+        } else {
+            pos as i32
         }
     }
 
@@ -198,18 +409,50 @@ where
     }
 
     fn clear(&mut self) {
+        self.buffer_start += self.audio_buffer.len() as u64;
         self.audio_buffer.clear();
+        self.holdoff_until = None;
     }
 
+    /// Threshold in effect; adaptive values come from the noise-floor estimate
     fn threshold(&self) -> f32 {
-        match self.threshold {
-            DetectionThreshold::Fixed(v) => v,
-            DetectionThreshold::Adaptive => panic!("WASM should only use Fixed threshold, not Adaptive"),
+        self.noise_floor.threshold_value(self.detection_threshold())
+    }
+
+    fn noise_floor_db(&self) -> f32 {
+        self.noise_floor.noise_floor_db()
+    }
+
+    fn meter(&self) -> Vec<f32> {
+        self.meter.reading().to_array().to_vec()
+    }
+
+    fn threshold_mode(&self) -> String {
+        match self.mode {
+            ThresholdMode::Fixed(_) => "fixed",
+            ThresholdMode::Adaptive => "adaptive",
+            ThresholdMode::Auto { locked: None } => "auto",
+            ThresholdMode::Auto { locked: Some(_) } => "auto-locked",
         }
+        .to_string()
+    }
+
+    /// Look for another signal; the meter starts over with its template
+    fn set_signal(&mut self, detect_fn: F, template: Vec<f32>) {
+        self.detect_fn = detect_fn;
+        self.meter = LevelMeter::with_template(template);
+    }
+
+    fn set_mode(&mut self, mode: ThresholdMode) {
+        self.mode = mode;
     }
 
-    fn set_threshold(&mut self, threshold_enum: DetectionThreshold) {
-        self.threshold = threshold_enum;
+    fn holdoff_samples(&self) -> usize {
+        self.holdoff_samples
+    }
+
+    fn set_holdoff_samples(&mut self, samples: usize) {
+        self.holdoff_samples = samples;
     }
 }
 
@@ -224,14 +467,19 @@ impl PreambleDetector {
     /// Create a new preamble detector with specified threshold
     #[wasm_bindgen(constructor)]
     pub fn new(fixed_value: f32) -> PreambleDetector {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
         PreambleDetector {
-            detector: SignalDetector::new(threshold, transmitwave_core::PREAMBLE_SAMPLES, detect_preamble),
+            detector: SignalDetector::new(
+                fixed_value,
+                transmitwave_core::PREAMBLE_SAMPLES,
+                detect_preamble,
+                generate_keyed_preamble(Samples(transmitwave_core::PREAMBLE_SAMPLES), 1.0, None),
+            ),
         }
     }
 
     /// Add audio samples from microphone to the buffer
-    /// Returns the detected preamble position if found, or -1 if not detected
+    /// Returns the detected preamble position if found, or -1 if not detected; the signal is
+    /// consumed from the buffer, so each one is reported once
     #[wasm_bindgen]
     pub fn add_samples(&mut self, samples: &[f32]) -> i32 {
         self.detector.add_samples(samples)
@@ -255,17 +503,59 @@ impl PreambleDetector {
         self.detector.clear();
     }
 
-    /// Get the current threshold value
+    /// Get the threshold in effect (adaptive modes compute it from the buffered audio)
     #[wasm_bindgen]
     pub fn threshold(&self) -> f32 {
         self.detector.threshold()
     }
 
-    /// Set a new threshold value
+    /// "fixed", "adaptive", "auto" or "auto-locked" (auto after its first detection)
+    #[wasm_bindgen]
+    pub fn threshold_mode(&self) -> String {
+        self.detector.threshold_mode()
+    }
+
+    /// Background noise floor of the audio added so far, in dBFS
+    #[wasm_bindgen]
+    pub fn noise_floor_db(&self) -> f32 {
+        self.detector.noise_floor_db()
+    }
+
+    /// Peak-held meter values for a level-meter UI, as a Float32Array:
+    /// [correlation, rms, band 0 level, ..., band 5 level] (linear, full scale 1.0)
+    #[wasm_bindgen]
+    pub fn get_meter(&self) -> Vec<f32> {
+        self.detector.meter()
+    }
+
+    /// Use a fixed threshold (clamped to 0.001-1.0)
     #[wasm_bindgen]
     pub fn set_threshold(&mut self, fixed_value: f32) {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
-        self.detector.set_threshold(threshold);
+        self.detector.set_mode(ThresholdMode::Fixed(fixed_value.clamp(0.001, 1.0)));
+    }
+
+    /// Derive the threshold from the signal level of the buffered audio
+    #[wasm_bindgen]
+    pub fn set_adaptive(&mut self) {
+        self.detector.set_mode(ThresholdMode::Adaptive);
+    }
+
+    /// Adaptive until the first detection, then fixed at that threshold
+    #[wasm_bindgen]
+    pub fn set_auto(&mut self) {
+        self.detector.set_mode(ThresholdMode::Auto { locked: None });
+    }
+
+    /// Samples after a detection's start during which further detections are ignored
+    #[wasm_bindgen]
+    pub fn holdoff_samples(&self) -> usize {
+        self.detector.holdoff_samples()
+    }
+
+    /// Set the refractory period (defaults to one sync signal length)
+    #[wasm_bindgen]
+    pub fn set_holdoff_samples(&mut self, samples: usize) {
+        self.detector.set_holdoff_samples(samples);
     }
 }
 
@@ -280,14 +570,19 @@ impl PostambleDetector {
     /// Create a new postamble detector with specified threshold
     #[wasm_bindgen(constructor)]
     pub fn new(fixed_value: f32) -> PostambleDetector {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
         PostambleDetector {
-            detector: SignalDetector::new(threshold, transmitwave_core::POSTAMBLE_SAMPLES, detect_postamble),
+            detector: SignalDetector::new(
+                fixed_value,
+                transmitwave_core::POSTAMBLE_SAMPLES,
+                detect_postamble,
+                generate_keyed_postamble(Samples(transmitwave_core::POSTAMBLE_SAMPLES), 1.0, None),
+            ),
         }
     }
 
     /// Add audio samples from microphone to the buffer
-    /// Returns the detected postamble position if found, or -1 if not detected
+    /// Returns the detected postamble position if found, or -1 if not detected; the signal is
+    /// consumed from the buffer, so each one is reported once
     #[wasm_bindgen]
     pub fn add_samples(&mut self, samples: &[f32]) -> i32 {
         self.detector.add_samples(samples)
@@ -311,26 +606,94 @@ impl PostambleDetector {
         self.detector.clear();
     }
 
-    /// Get the current threshold value
+    /// Get the threshold in effect (adaptive modes compute it from the buffered audio)
     #[wasm_bindgen]
     pub fn threshold(&self) -> f32 {
         self.detector.threshold()
     }
 
-    /// Set a new threshold value
+    /// "fixed", "adaptive", "auto" or "auto-locked" (auto after its first detection)
+    #[wasm_bindgen]
+    pub fn threshold_mode(&self) -> String {
+        self.detector.threshold_mode()
+    }
+
+    /// Background noise floor of the audio added so far, in dBFS
+    #[wasm_bindgen]
+    pub fn noise_floor_db(&self) -> f32 {
+        self.detector.noise_floor_db()
+    }
+
+    /// Peak-held meter values for a level-meter UI, as a Float32Array:
+    /// [correlation, rms, band 0 level, ..., band 5 level] (linear, full scale 1.0)
+    #[wasm_bindgen]
+    pub fn get_meter(&self) -> Vec<f32> {
+        self.detector.meter()
+    }
+
+    /// Use a fixed threshold (clamped to 0.001-1.0)
     #[wasm_bindgen]
     pub fn set_threshold(&mut self, fixed_value: f32) {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
-        self.detector.set_threshold(threshold);
+        self.detector.set_mode(ThresholdMode::Fixed(fixed_value.clamp(0.001, 1.0)));
+    }
+
+    /// Derive the threshold from the signal level of the buffered audio
+    #[wasm_bindgen]
+    pub fn set_adaptive(&mut self) {
+        self.detector.set_mode(ThresholdMode::Adaptive);
+    }
+
+    /// Adaptive until the first detection, then fixed at that threshold
+    #[wasm_bindgen]
+    pub fn set_auto(&mut self) {
+        self.detector.set_mode(ThresholdMode::Auto { locked: None });
+    }
+
+    /// Samples after a detection's start during which further detections are ignored
+    #[wasm_bindgen]
+    pub fn holdoff_samples(&self) -> usize {
+        self.detector.holdoff_samples()
     }
+
+    /// Set the refractory period (defaults to one sync signal length)
+    #[wasm_bindgen]
+    pub fn set_holdoff_samples(&mut self, samples: usize) {
+        self.detector.set_holdoff_samples(samples);
+    }
+}
+
+/// Melody of parallel note arrays from JS; empty arrays mean the three-note whistle
+pub(crate) fn melody_from_js(frequencies: &[f32], durations_ms: &[u32]) -> Result<Option<Melody>, WasmError> {
+    if frequencies.len() != durations_ms.len() {
+        return Err(WasmError::invalid_option("melody", "needs one duration per frequency"));
+    }
+    if frequencies.is_empty() {
+        return Ok(None);
+    }
+    let notes = frequencies.iter().copied().zip(durations_ms.iter().copied());
+    Melody::from_notes(notes).map(Some).map_err(|e| WasmError::invalid_option("melody", e))
+}
+
+type FountainDetectFn = Box<dyn Fn(&[f32], DetectionThreshold) -> Option<usize>>;
+
+/// Fountain preamble of `melody` (the whistle without one) and its detector
+fn fountain_signal(melody: Option<&Melody>) -> (FountainDetectFn, Vec<f32>) {
+    let template = match melody {
+        Some(melody) => melody.generate(Samples(transmitwave_core::PREAMBLE_SAMPLES), 1.0),
+        None => generate_fountain_preamble(transmitwave_core::PREAMBLE_SAMPLES, 1.0),
+    };
+    let detect_template = template.clone();
+    let detect: FountainDetectFn =
+        Box::new(move |samples, threshold| detect_fountain_template(samples, &detect_template, threshold));
+    (detect, template)
 }
 
 /// Fountain preamble detector for detecting fountain mode three-note whistle in audio stream
 /// This detector specifically looks for the three-note whistle pattern (800->1200->1600 Hz)
-/// used exclusively by fountain mode transmissions
+/// used exclusively by fountain mode transmissions, or the melody set with `set_melody`
 #[wasm_bindgen]
 pub struct FountainPreambleDetector {
-    detector: SignalDetector<fn(&[f32], DetectionThreshold) -> Option<usize>>,
+    detector: SignalDetector<FountainDetectFn>,
 }
 
 #[wasm_bindgen]
@@ -338,14 +701,26 @@ impl FountainPreambleDetector {
     /// Create a new fountain preamble detector with specified threshold
     #[wasm_bindgen(constructor)]
     pub fn new(fixed_value: f32) -> FountainPreambleDetector {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
+        let (detect, template) = fountain_signal(None);
         FountainPreambleDetector {
-            detector: SignalDetector::new(threshold, transmitwave_core::PREAMBLE_SAMPLES, detect_fountain_preamble),
+            detector: SignalDetector::new(fixed_value, transmitwave_core::PREAMBLE_SAMPLES, detect, template),
         }
     }
 
+    /// Listen for the melody the sender set with `set_fountain_melody`
+    /// (note frequencies in Hz and durations in ms) instead of the whistle;
+    /// empty arrays go back to the whistle. The meter follows it too.
+    #[wasm_bindgen]
+    pub fn set_melody(&mut self, frequencies: &[f32], durations_ms: &[u32]) -> Result<(), WasmError> {
+        let melody = melody_from_js(frequencies, durations_ms)?;
+        let (detect, template) = fountain_signal(melody.as_ref());
+        self.detector.set_signal(detect, template);
+        Ok(())
+    }
+
     /// Add audio samples from microphone to the buffer
-    /// Returns the detected fountain preamble position if found, or -1 if not detected
+    /// Returns the detected fountain preamble position if found, or -1 if not detected; the signal is
+    /// consumed from the buffer, so each one is reported once
     #[wasm_bindgen]
     pub fn add_samples(&mut self, samples: &[f32]) -> i32 {
         self.detector.add_samples(samples)
@@ -369,275 +744,229 @@ impl FountainPreambleDetector {
         self.detector.clear();
     }
 
-    /// Get the current threshold value
+    /// Get the threshold in effect (adaptive modes compute it from the buffered audio)
     #[wasm_bindgen]
     pub fn threshold(&self) -> f32 {
         self.detector.threshold()
     }
 
-    /// Set a new threshold value
+    /// "fixed", "adaptive", "auto" or "auto-locked" (auto after its first detection)
+    #[wasm_bindgen]
+    pub fn threshold_mode(&self) -> String {
+        self.detector.threshold_mode()
+    }
+
+    /// Background noise floor of the audio added so far, in dBFS
+    #[wasm_bindgen]
+    pub fn noise_floor_db(&self) -> f32 {
+        self.detector.noise_floor_db()
+    }
+
+    /// Peak-held meter values for a level-meter UI, as a Float32Array:
+    /// [correlation, rms, band 0 level, ..., band 5 level] (linear, full scale 1.0)
+    #[wasm_bindgen]
+    pub fn get_meter(&self) -> Vec<f32> {
+        self.detector.meter()
+    }
+
+    /// Use a fixed threshold (clamped to 0.001-1.0)
     #[wasm_bindgen]
     pub fn set_threshold(&mut self, fixed_value: f32) {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
-        self.detector.set_threshold(threshold);
+        self.detector.set_mode(ThresholdMode::Fixed(fixed_value.clamp(0.001, 1.0)));
+    }
+
+    /// Derive the threshold from the signal level of the buffered audio
+    #[wasm_bindgen]
+    pub fn set_adaptive(&mut self) {
+        self.detector.set_mode(ThresholdMode::Adaptive);
+    }
+
+    /// Adaptive until the first detection, then fixed at that threshold
+    #[wasm_bindgen]
+    pub fn set_auto(&mut self) {
+        self.detector.set_mode(ThresholdMode::Auto { locked: None });
+    }
+
+    /// Samples after a detection's start during which further detections are ignored
+    #[wasm_bindgen]
+    pub fn holdoff_samples(&self) -> usize {
+        self.detector.holdoff_samples()
+    }
+
+    /// Set the refractory period (defaults to one sync signal length)
+    #[wasm_bindgen]
+    pub fn set_holdoff_samples(&mut self, samples: usize) {
+        self.detector.set_holdoff_samples(samples);
     }
 }
 
 
 // ============================================================================
-// FOUNTAIN CODE ENCODER/DECODER
-// Continuous streaming mode using RaptorQ fountain codes (RFC 6330)
+// CAPTURE DIAGNOSTICS
 // ============================================================================
 
-/// Fountain Code Encoder for continuous streaming
+/// Samples of the known test transmission to play during a capture check
 #[wasm_bindgen]
-pub struct WasmFountainEncoder {
-    inner: EncoderFsk,
-    stream: Option<FountainStream>,
+pub fn capture_test_signal() -> Result<Vec<f32>, WasmError> {
+    transmitwave_core::capture_test_signal().map_err(WasmError::from)
 }
 
+/// Compare a microphone recording of `capture_test_signal()` with what was played
 #[wasm_bindgen]
-impl WasmFountainEncoder {
-    /// Create a new fountain encoder
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<WasmFountainEncoder, JsValue> {
-        EncoderFsk::new()
-            .map(|encoder| WasmFountainEncoder {
-                inner: encoder,
-                stream: None,
-            })
-            .map_err(|e| JsValue::from_str(&e.to_string()))
-    }
-
-    /// Encode data into fountain-coded audio stream
-    /// Returns a flat Float32Array of all audio samples (concatenated blocks)
-    ///
-    /// Parameters:
-    /// - data: Input data to encode
-    /// - timeout_secs: Audio duration in seconds (e.g., 30)
-    /// - block_size: Symbol size in bytes (e.g., 64)
-    /// - repair_ratio: Repair packet overhead (e.g., 0.5 for 50%)
-    #[wasm_bindgen]
-    pub fn encode_fountain(
-        &mut self,
-        data: &[u8],
-        timeout_secs: u32,
-        block_size: usize,
-        repair_ratio: f32,
-    ) -> Result<Vec<f32>, JsValue> {
-        let config = FountainConfig {
-            timeout_secs,
-            block_size,
-            repair_blocks_ratio: repair_ratio,
-        };
-
-        let stream = self.inner
-            .encode_fountain(data, Some(config))
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+pub fn analyze_capture(recording: &[f32]) -> Result<WasmCaptureDiagnostics, WasmError> {
+    let reference = capture_test_signal()?;
+    Ok(WasmCaptureDiagnostics { inner: transmitwave_core::analyze_capture(recording, &reference) })
+}
 
-        // Collect all blocks and concatenate into single audio buffer
-        let all_samples: Vec<f32> = stream
-            .flat_map(|block| block)
-            .collect();
+/// Signs of echo cancellation / noise suppression in a capture
+#[wasm_bindgen]
+pub struct WasmCaptureDiagnostics {
+    inner: CaptureDiagnostics,
+}
 
-        Ok(all_samples)
+#[wasm_bindgen]
+impl WasmCaptureDiagnostics {
+    /// Whether the test transmission was found in the recording
+    pub fn signal_found(&self) -> bool {
+        self.inner.preamble.is_some()
     }
 
-    /// Begin a streaming session that yields one fountain block at a time.
-    /// Timeout of 0 seconds disables automatic stopping (infinite stream).
-    #[wasm_bindgen]
-    pub fn start_streaming(
-        &mut self,
-        data: &[u8],
-        block_size: usize,
-        repair_ratio: f32,
-        timeout_secs: u32,
-    ) -> Result<(), JsValue> {
-        let config = FountainConfig {
-            timeout_secs,
-            block_size,
-            repair_blocks_ratio: repair_ratio,
-        };
-
-        let stream = self
-            .inner
-            .encode_fountain(data, Some(config))
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    /// Whether no suppression was detected
+    pub fn is_clean(&self) -> bool {
+        self.inner.is_clean()
+    }
 
-        self.stream = Some(stream);
-        Ok(())
+    /// Received level of each of the 6 tone bands relative to the median band (dB)
+    pub fn band_level_db(&self) -> Vec<f32> {
+        self.inner.band_level_db.to_vec()
     }
 
-    /// Fetch the next fountain block from the active stream.
-    #[wasm_bindgen]
-    pub fn next_stream_block(&mut self) -> Option<Vec<f32>> {
-        self.stream.as_mut().and_then(|stream| stream.next())
+    /// Share of the transmission (0.0-1.0) during which the signal dropped out
+    pub fn gated_fraction(&self) -> f32 {
+        self.inner.gated_fraction
     }
 
-    /// Stop the current stream and release its resources.
-    #[wasm_bindgen]
-    pub fn stop_streaming(&mut self) {
-        self.stream = None;
+    /// Actionable hints for the user, empty when the capture path looks clean
+    pub fn hints(&self) -> Vec<String> {
+        self.inner.hints.iter().map(|hint| hint.to_string()).collect()
     }
 }
 
-/// Fountain Code Decoder for continuous streaming
+// ============================================================================
+// TRANSMIT AUDIO CHECKS
+// ============================================================================
+
+/// Check encoder output (mono, 16 kHz) after mixing or other processing,
+/// before playing it
 #[wasm_bindgen]
-pub struct WasmFountainDecoder {
-    inner: DecoderFsk,
-    buffer: Vec<f32>,
-    block_size: usize,
+pub fn validate_tx_audio(samples: &[f32]) -> WasmTxAudioReport {
+    WasmTxAudioReport { inner: transmitwave_core::validate_tx_audio(samples) }
 }
 
+/// Result of `validate_tx_audio`
 #[wasm_bindgen]
-impl WasmFountainDecoder {
-    /// Create a new fountain decoder
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<WasmFountainDecoder, JsValue> {
-        DecoderFsk::new()
-            .map(|decoder| WasmFountainDecoder {
-                inner: decoder,
-                buffer: Vec::new(),
-                block_size: FOUNTAIN_BLOCK_SIZE,
-            })
-            .map_err(|e| JsValue::from_str(&e.to_string()))
-    }
+pub struct WasmTxAudioReport {
+    inner: TxAudioReport,
+}
 
-    /// Set the block size for decoding
-    #[wasm_bindgen]
-    pub fn set_block_size(&mut self, block_size: usize) {
-        self.block_size = block_size;
+#[wasm_bindgen]
+impl WasmTxAudioReport {
+    /// Whether the audio looks safe to play
+    pub fn is_clean(&self) -> bool {
+        self.inner.is_clean()
     }
 
-    /// Set the detection threshold for both preamble and postamble
-    #[wasm_bindgen]
-    pub fn set_detection_threshold(&mut self, fixed_value: f32) {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
-        self.inner.set_detection_threshold(threshold);
+    /// Largest sample magnitude
+    pub fn peak(&self) -> f32 {
+        self.inner.peak
     }
 
-    /// Set the detection threshold for preamble only
-    #[wasm_bindgen]
-    pub fn set_preamble_threshold(&mut self, fixed_value: f32) {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
-        self.inner.set_preamble_threshold(threshold);
+    /// Distance of the peak from full scale (dB, negative when clipping)
+    pub fn headroom_db(&self) -> f32 {
+        self.inner.headroom_db
     }
 
-    /// Get the current preamble detection threshold
-    #[wasm_bindgen]
-    pub fn get_preamble_threshold(&self) -> f32 {
-        match self.inner.get_preamble_threshold() {
-            DetectionThreshold::Fixed(value) => value,
-            DetectionThreshold::Adaptive => panic!("WASM should only use Fixed threshold, not Adaptive"),
-        }
+    /// Share of the samples (0.0-1.0) at full scale
+    pub fn clipped_fraction(&self) -> f32 {
+        self.inner.clipped_fraction
     }
 
-    /// Set the detection threshold for postamble only
-    #[wasm_bindgen]
-    pub fn set_postamble_threshold(&mut self, fixed_value: f32) {
-        let threshold = DetectionThreshold::Fixed(fixed_value.max(0.001).min(1.0));
-        self.inner.set_postamble_threshold(threshold);
+    /// Mean sample value
+    pub fn dc_offset(&self) -> f32 {
+        self.inner.dc_offset
     }
 
-    /// Get the current postamble detection threshold
-    #[wasm_bindgen]
-    pub fn get_postamble_threshold(&self) -> f32 {
-        match self.inner.get_postamble_threshold() {
-            DetectionThreshold::Fixed(value) => value,
-            DetectionThreshold::Adaptive => panic!("WASM should only use Fixed threshold, not Adaptive"),
-        }
+    /// Share of the energy (0.0-1.0) inside the modem band
+    pub fn in_band_ratio(&self) -> f32 {
+        self.inner.in_band_ratio
     }
 
-    /// Feed audio chunk to the decoder buffer
-    #[wasm_bindgen]
-    pub fn feed_chunk(&mut self, samples: &[f32]) {
-        self.buffer.extend_from_slice(samples);
+    /// Level of each of the 6 tone bands relative to the median band (dB)
+    pub fn band_level_db(&self) -> Vec<f32> {
+        self.inner.band_level_db.to_vec()
     }
 
-    /// Get the current number of samples in the buffer
-    #[wasm_bindgen]
-    pub fn get_sample_count(&self) -> usize {
-        self.buffer.len()
+    /// Actionable hints for the user, empty when the audio looks clean
+    pub fn hints(&self) -> Vec<String> {
+        self.inner.issues.iter().map(|issue| issue.to_string()).collect()
     }
+}
 
-    /// Try to decode the accumulated audio buffer
-    /// Returns decoded data if successful, or error if decoding fails
-    #[wasm_bindgen]
-    pub fn try_decode(&mut self) -> Result<Vec<u8>, JsValue> {
-        if self.buffer.is_empty() {
-            return Err(JsValue::from_str("No audio data in buffer"));
-        }
+// ============================================================================
+// SIGNAL PRESENCE
+// ============================================================================
 
-        let config = FountainConfig {
-            timeout_secs: 30, // Not enforced in WASM
-            block_size: self.block_size,
-            repair_blocks_ratio: 0.5, // Not used by decoder
-        };
+/// Quick check whether a recording (mono, 16 kHz) likely holds a transmission,
+/// much cheaper than a decode
+#[wasm_bindgen]
+pub fn signal_presence(samples: &[f32]) -> WasmSignalPresence {
+    WasmSignalPresence { inner: transmitwave_core::signal_presence(samples) }
+}
 
-        self.inner
-            .decode_fountain(&self.buffer, Some(config))
-            .map_err(|e| JsValue::from_str(&e.to_string()))
-    }
+/// Result of `signal_presence`
+#[wasm_bindgen]
+pub struct WasmSignalPresence {
+    inner: SignalPresence,
+}
 
-    /// Reset the decoder and clear the buffer.
-    ///
-    /// Returns an error if decoder initialization fails. On success, both the
-    /// buffer and decoder state are cleared. On failure, the decoder state
-    /// is left unchanged and the buffer is cleared.
-    #[wasm_bindgen]
-    pub fn reset(&mut self) -> Result<(), JsValue> {
-        self.buffer.clear();
-        // Create a new inner decoder to reset its state
-        DecoderFsk::new()
-            .map(|decoder| {
-                self.inner = decoder;
-            })
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+#[wasm_bindgen]
+impl WasmSignalPresence {
+    /// Likelihood (0.0-1.0) that the recording holds a transmission
+    pub fn probability(&self) -> f32 {
+        self.inner.probability
     }
 
-    /// Get the number of successfully decoded blocks
-    #[wasm_bindgen]
-    pub fn get_decoded_blocks(&self) -> u32 {
-        self.inner.stats.decoded_blocks
+    /// Whether a decode is worth trying (probability of at least 0.5)
+    pub fn is_likely(&self) -> bool {
+        self.inner.is_likely()
     }
 
-    /// Get the number of blocks that failed CRC check
-    #[wasm_bindgen]
-    pub fn get_failed_blocks(&self) -> u32 {
-        self.inner.stats.failed_blocks
+    /// Best normalized preamble correlation (0.0-1.0)
+    pub fn correlation(&self) -> f32 {
+        self.inner.correlation
     }
 
-    /// Get all decode statistics as a WasmDecodeStats object
-    #[wasm_bindgen]
-    pub fn get_stats(&self) -> WasmDecodeStats {
-        WasmDecodeStats::from(self.inner.stats.clone())
+    /// Sample where the best preamble correlation was found
+    pub fn position(&self) -> usize {
+        self.inner.position
     }
 
-    /// Decode fountain-coded audio stream back to data (non-streaming mode)
-    ///
-    /// Parameters:
-    /// - samples: Audio samples from microphone/recording
-    /// - timeout_secs: Maximum time to spend decoding (e.g., 30)
-    /// - block_size: Symbol size in bytes (must match encoder, e.g., 64)
-    #[wasm_bindgen]
-    pub fn decode_fountain(
-        &mut self,
-        samples: &[f32],
-        timeout_secs: u32,
-        block_size: usize,
-    ) -> Result<Vec<u8>, JsValue> {
-        let config = FountainConfig {
-            timeout_secs,
-            block_size,
-            repair_blocks_ratio: 0.5, // Not used by decoder
-        };
-
-        self.inner
-            .decode_fountain(samples, Some(config))
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    /// Share of the energy inside the FSK band (0.0-1.0)
+    pub fn band_ratio(&self) -> f32 {
+        self.inner.band_ratio
     }
 }
 
+// ============================================================================
+// WIRE PARAMETERS
+// ============================================================================
+
+/// The modem's timing, band and overhead constants as a JSON object
+/// (`{"protocol_revision":1,"sample_rate":16000,...}`, see `ModemParams`)
+#[wasm_bindgen]
+pub fn modem_params_json() -> String {
+    transmitwave_core::ModemParams::CURRENT.to_json()
+}
 
 #[wasm_bindgen(start)]
 pub fn init() {
@@ -0,0 +1,153 @@
+//! `WasmEncoderOptions`: the option bag accepted by `WasmEncoder`
+//!
+//! Field names follow the CLI server's `/encode` request. Every field is
+//! optional and only the ones present change the encoder, so the constructor
+//! options and the per-call overrides of `encode_with` layer on each other.
+
+use serde::Deserialize;
+use transmitwave_core::{ContentType, EncoderFsk, EncoderPadding, FecMode, FrameProfile, Millis, NetworkKey, ToneLayout};
+use crate::error::WasmError;
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct WasmEncoderOptions {
+    /// "standard" (default), "short", "telephony", "codec-robust" or "narrowband"
+    profile: Option<String>,
+    /// Reed-Solomon code as "N,K"; default picks by payload size
+    rs: Option<String>,
+    amplitude_bits: Option<bool>,
+    /// "TONES,BINS"; default 6 tones of 16 bins
+    tone_layout: Option<String>,
+    /// Silence gap after every N data symbols (4-63)
+    symbol_gaps: Option<u8>,
+    /// Send every tone in both halves of the band
+    dual_band: Option<bool>,
+    /// Output gain in (0.0, 1.0] (default 1.0)
+    amplitude: Option<f32>,
+    /// Peak ceiling in (0.0, 1.0] of the encoder's soft limiter
+    safety_limiter: Option<f32>,
+    network_key: Option<String>,
+    content_type: Option<String>,
+    leading_silence_ms: Option<u32>,
+    trailing_silence_ms: Option<u32>,
+    fade_in_ms: Option<u32>,
+}
+
+impl WasmEncoderOptions {
+    /// Read options from a JS object; undefined or null gives no options
+    pub(crate) fn from_js(value: Option<js_sys::Object>) -> Result<Self, WasmError> {
+        match value {
+            Some(object) => serde_wasm_bindgen::from_value(object.into())
+                .map_err(|e| WasmError::invalid_option("invalid encoder options", e)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Apply the options present to `encoder` and `amplitude`
+    pub(crate) fn apply(&self, encoder: &mut EncoderFsk, amplitude: &mut f32) -> Result<(), WasmError> {
+        if let Some(profile) = self.profile.as_deref() {
+            encoder.set_profile(profile.parse::<FrameProfile>().map_err(|e| field_error("profile", e))?);
+        }
+        if let Some(rs) = self.rs.as_deref() {
+            encoder.set_fec_mode(Some(rs.parse::<FecMode>().map_err(|e| field_error("rs", e))?));
+        }
+        if let Some(enabled) = self.amplitude_bits {
+            encoder.set_amplitude_bits(enabled);
+        }
+        if let Some(layout) = self.tone_layout.as_deref() {
+            encoder.set_tone_layout(layout.parse::<ToneLayout>().map_err(|e| field_error("tone_layout", e))?);
+        }
+        if let Some(interval) = self.symbol_gaps {
+            encoder.set_symbol_gaps(Some(interval));
+        }
+        if let Some(enabled) = self.dual_band {
+            encoder.set_dual_band(enabled);
+        }
+        if let Some(gain) = self.amplitude {
+            if !(gain > 0.0 && gain <= 1.0) {
+                return Err(field_error("amplitude", "must be in (0.0, 1.0]"));
+            }
+            *amplitude = gain;
+        }
+        if let Some(ceiling) = self.safety_limiter {
+            if !(ceiling > 0.0 && ceiling <= 1.0) {
+                return Err(field_error("safety_limiter", "must be in (0.0, 1.0]"));
+            }
+            encoder.set_safety_limiter(Some(ceiling));
+        }
+        match self.network_key.as_deref() {
+            Some("") => return Err(field_error("network_key", "must not be empty")),
+            Some(key) => encoder.set_network_key(Some(NetworkKey::new(key.as_bytes()))),
+            None => {}
+        }
+        if let Some(mime) = self.content_type.as_deref() {
+            let content_type = mime.parse::<ContentType>().map_err(|e| field_error("content_type", e))?;
+            encoder.set_content_type(Some(content_type));
+        }
+        if self.leading_silence_ms.is_some() || self.trailing_silence_ms.is_some() || self.fade_in_ms.is_some() {
+            // Fields left out keep the encoder's current padding
+            let mut padding = encoder.padding();
+            let requested = EncoderPadding::from_ms(
+                Millis(self.leading_silence_ms.unwrap_or(0)),
+                Millis(self.trailing_silence_ms.unwrap_or(0)),
+                Millis(self.fade_in_ms.unwrap_or(0)),
+            );
+            if self.leading_silence_ms.is_some() {
+                padding.leading_silence_samples = requested.leading_silence_samples;
+            }
+            if self.trailing_silence_ms.is_some() {
+                padding.trailing_silence_samples = requested.trailing_silence_samples;
+            }
+            if self.fade_in_ms.is_some() {
+                padding.fade_in_samples = requested.fade_in_samples;
+            }
+            encoder.set_padding(padding);
+        }
+        Ok(())
+    }
+}
+
+fn field_error(field: &str, message: impl std::fmt::Display) -> WasmError {
+    WasmError::invalid_option(field, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_options_keep_defaults() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut amplitude = 1.0;
+        WasmEncoderOptions::default().apply(&mut encoder, &mut amplitude).unwrap();
+        assert_eq!(encoder.profile(), FrameProfile::Standard);
+        assert_eq!(encoder.fec_mode(), None);
+        assert_eq!(encoder.tone_layout(), ToneLayout::STANDARD);
+        assert_eq!(amplitude, 1.0);
+    }
+
+    #[test]
+    fn test_options_parse_like_the_server() {
+        let mut encoder = EncoderFsk::new().unwrap();
+        let mut amplitude = 1.0;
+        let options = WasmEncoderOptions {
+            profile: Some("narrowband".to_string()),
+            rs: Some("255,191".to_string()),
+            tone_layout: Some("4, 16".to_string()),
+            amplitude: Some(0.5),
+            ..Default::default()
+        };
+        options.apply(&mut encoder, &mut amplitude).unwrap();
+        assert_eq!(encoder.profile(), FrameProfile::Narrowband);
+        assert_eq!(encoder.fec_mode(), Some(FecMode::custom(255, 191).unwrap()));
+        assert_eq!(encoder.tone_layout(), ToneLayout::new(4, 16).unwrap());
+        assert_eq!(amplitude, 0.5);
+
+        // Overrides only touch the fields they set
+        let overrides = WasmEncoderOptions { trailing_silence_ms: Some(100), ..Default::default() };
+        overrides.apply(&mut encoder, &mut amplitude).unwrap();
+        assert_eq!(encoder.profile(), FrameProfile::Narrowband);
+        assert_eq!(amplitude, 0.5);
+        assert_eq!(encoder.padding().trailing_silence_samples, EncoderPadding::from_ms(Millis(0), Millis(100), Millis(0)).trailing_silence_samples);
+    }
+}
@@ -0,0 +1,175 @@
+//! `WasmSession`: a half-duplex link (carrier sense, turn-taking and
+//! stop-and-wait ARQ from `HalfDuplexLink`) behind two callbacks
+//!
+//! The page feeds microphone audio to `process` and plays whatever the
+//! session hands to its transmit callback; queued messages, ACKs, backoff and
+//! retries are handled inside. The link is clocked by the audio fed, so call
+//! `advance` while no audio is captured (e.g. the microphone is muted) to
+//! keep its timers running.
+
+use crate::error::WasmError;
+use serde::Deserialize;
+use transmitwave_core::link::MAX_LINK_PAYLOAD;
+use transmitwave_core::{HalfDuplexLink, LinkConfig, LinkEvent, SAMPLE_RATE};
+use wasm_bindgen::prelude::*;
+
+/// Option bag of `new WasmSession(station, options)`; omitted fields keep
+/// the `LinkConfig` defaults
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct WasmSessionOptions {
+    max_attempts: Option<u32>,
+    ack_timeout_ms: Option<u32>,
+    clear_ms: Option<u32>,
+    backoff_ms: Option<u32>,
+    turnaround_ms: Option<u32>,
+}
+
+impl WasmSessionOptions {
+    fn link_config(value: Option<js_sys::Object>) -> Result<LinkConfig, WasmError> {
+        let options: Self = match value {
+            Some(object) => serde_wasm_bindgen::from_value(object.into())
+                .map_err(|e| WasmError::invalid_option("invalid session options", e))?,
+            None => Self::default(),
+        };
+        if options.max_attempts == Some(0) {
+            return Err(WasmError::invalid_option("max_attempts", "must be at least 1"));
+        }
+        let defaults = LinkConfig::default();
+        Ok(LinkConfig {
+            max_attempts: options.max_attempts.unwrap_or(defaults.max_attempts),
+            ack_timeout_ms: options.ack_timeout_ms.unwrap_or(defaults.ack_timeout_ms),
+            clear_ms: options.clear_ms.unwrap_or(defaults.clear_ms),
+            backoff_ms: options.backoff_ms.unwrap_or(defaults.backoff_ms),
+            turnaround_ms: options.turnaround_ms.unwrap_or(defaults.turnaround_ms),
+        })
+    }
+}
+
+fn event_to_js(event: &LinkEvent) -> JsValue {
+    let object = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| {
+        let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+    };
+    match event {
+        LinkEvent::Received { from, payload } => {
+            set("type", "received".into());
+            set("from", (*from).into());
+            set("payload", js_sys::Uint8Array::from(payload.as_slice()).into());
+        }
+        LinkEvent::Delivered { seq } => {
+            set("type", "delivered".into());
+            set("seq", (*seq).into());
+        }
+        LinkEvent::Retrying { seq, attempt } => {
+            set("type", "retrying".into());
+            set("seq", (*seq).into());
+            set("attempt", (*attempt).into());
+        }
+        LinkEvent::Failed { seq } => {
+            set("type", "failed".into());
+            set("seq", (*seq).into());
+        }
+    }
+    object.into()
+}
+
+/// Half-duplex messaging session for browser apps
+#[wasm_bindgen]
+pub struct WasmSession {
+    link: HalfDuplexLink,
+    on_transmit: Option<js_sys::Function>,
+    on_event: Option<js_sys::Function>,
+}
+
+#[wasm_bindgen]
+impl WasmSession {
+    /// Session of station `station` (0-254); `options` may set `max_attempts`,
+    /// `ack_timeout_ms`, `clear_ms`, `backoff_ms` and `turnaround_ms`
+    #[wasm_bindgen(constructor)]
+    pub fn new(station: u8, options: Option<js_sys::Object>) -> Result<WasmSession, WasmError> {
+        let config = WasmSessionOptions::link_config(options)?;
+        Ok(WasmSession { link: HalfDuplexLink::new(station, config)?, on_transmit: None, on_event: None })
+    }
+
+    /// Attach the callbacks: `on_transmit` gets a Float32Array to play right
+    /// away (16 kHz), `on_event` an object per link event
+    /// ({ type: "received", from, payload } | { type: "delivered", seq } |
+    /// { type: "retrying", seq, attempt } | { type: "failed", seq })
+    #[wasm_bindgen]
+    pub fn connect(&mut self, on_transmit: js_sys::Function, on_event: js_sys::Function) {
+        self.on_transmit = Some(on_transmit);
+        self.on_event = Some(on_event);
+    }
+
+    /// Detach the callbacks; `process` and `advance` fail until `connect`
+    #[wasm_bindgen]
+    pub fn disconnect(&mut self) {
+        self.on_transmit = None;
+        self.on_event = None;
+    }
+
+    #[wasm_bindgen]
+    pub fn is_connected(&self) -> bool {
+        self.on_transmit.is_some()
+    }
+
+    /// Queue a message (up to `max_message_size()` bytes) for delivery to any
+    /// listening station; returns the `seq` its events report
+    #[wasm_bindgen]
+    pub fn send(&mut self, data: &[u8]) -> Result<u8, WasmError> {
+        self.link.send(data).map_err(WasmError::from)
+    }
+
+    /// Feed captured audio at 16 kHz; may call `on_transmit` and `on_event`
+    #[wasm_bindgen]
+    pub fn process(&mut self, samples: &[f32]) -> Result<(), WasmError> {
+        let (Some(on_transmit), Some(on_event)) = (&self.on_transmit, &self.on_event) else {
+            return Err(WasmError::not_connected());
+        };
+        let output = self.link.process(samples)?;
+        // The link counts the audio as on the air, so it goes out before any event
+        if let Some(audio) = output.transmit {
+            let array = js_sys::Float32Array::from(audio.as_slice());
+            on_transmit.call1(&JsValue::NULL, &array).map_err(WasmError::callback)?;
+        }
+        for event in &output.events {
+            on_event.call1(&JsValue::NULL, &event_to_js(event)).map_err(WasmError::callback)?;
+        }
+        Ok(())
+    }
+
+    /// Run the timers for `ms` of silence, while no audio is captured
+    #[wasm_bindgen]
+    pub fn advance(&mut self, ms: u32) -> Result<(), WasmError> {
+        self.process(&vec![0.0; ms as usize * SAMPLE_RATE / 1000])
+    }
+
+    #[wasm_bindgen]
+    pub fn max_message_size(&self) -> usize {
+        MAX_LINK_PAYLOAD
+    }
+
+    #[wasm_bindgen]
+    pub fn station(&self) -> u8 {
+        self.link.station()
+    }
+
+    /// True while the session's own audio is playing (or echoing)
+    #[wasm_bindgen]
+    pub fn is_transmitting(&self) -> bool {
+        self.link.is_transmitting()
+    }
+
+    /// True while another station is heard
+    #[wasm_bindgen]
+    pub fn is_channel_busy(&self) -> bool {
+        self.link.carrier_sense().is_busy()
+    }
+
+    /// Messages queued or awaiting an ACK
+    #[wasm_bindgen]
+    pub fn outstanding(&self) -> usize {
+        self.link.outstanding()
+    }
+}
@@ -1,10 +1,12 @@
 // AUTO-GENERATED FILE - DO NOT EDIT MANUALLY
-// Generated from core/src/lib.rs constants
+// Generated from ModemParams::CURRENT (core/src/params.rs)
 // Run `cargo run --manifest-path tools/Cargo.toml` to regenerate
 
-export const FOUNTAIN_BLOCK_SIZE_BYTES = 64
-export const MAX_PAYLOAD_BYTES = 1024
-export const FSK_BYTES_PER_SYMBOL = 3
-export const FSK_SYMBOL_SAMPLES = 3072
-export const PACKET_OVERHEAD_BYTES = 14
-export const MAX_BUFFER_SAMPLES = 80000
+export {
+  FOUNTAIN_BLOCK_SIZE as FOUNTAIN_BLOCK_SIZE_BYTES,
+  MAX_PAYLOAD_SIZE as MAX_PAYLOAD_BYTES,
+  FSK_BYTES_PER_SYMBOL,
+  FSK_SYMBOL_SAMPLES,
+  PACKET_OVERHEAD_BYTES,
+  MAX_BUFFER_SAMPLES,
+} from './modem'
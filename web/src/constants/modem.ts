@@ -0,0 +1,42 @@
+// AUTO-GENERATED FILE - DO NOT EDIT MANUALLY
+// Generated from ModemParams::CURRENT (core/src/params.rs)
+// Run `cargo run --manifest-path tools/Cargo.toml` to regenerate
+
+export const PROTOCOL_REVISION = 1
+export const SAMPLE_RATE = 16000
+export const FSK_MIN_FREQUENCY_HZ = 800
+export const FSK_MAX_FREQUENCY_HZ = 2700
+export const FSK_BIN_SPACING_HZ = 20
+export const FSK_NUM_BINS = 96
+export const FSK_TONES = 6
+export const FSK_BINS_PER_BAND = 16
+export const FSK_SYMBOL_SAMPLES = 3072
+export const FSK_BYTES_PER_SYMBOL = 3
+export const PREAMBLE_CHIRP_START_HZ = 800
+export const PREAMBLE_CHIRP_END_HZ = 1800
+export const POSTAMBLE_CHIRP_START_HZ = 1000
+export const POSTAMBLE_CHIRP_END_HZ = 400
+export const PREAMBLE_SAMPLES = 4000
+export const POSTAMBLE_SAMPLES = 4000
+export const SYNC_SILENCE_SAMPLES = 2000
+export const MAX_SYNC_GAP_SAMPLES = 4000
+export const SYMBOL_GAP_SAMPLES = 800
+export const MIN_SYMBOL_GAP_INTERVAL = 4
+export const MAX_SYMBOL_GAP_INTERVAL = 63
+export const FRAME_HEADER_SIZE = 8
+export const MAX_PAYLOAD_SIZE = 1024
+export const RS_TOTAL_BYTES = 255
+export const RS_DATA_BYTES = 223
+export const FOUNTAIN_BLOCK_SIZE = 64
+export const PACKET_OVERHEAD_BYTES = 14
+export const SHORT_SYNC_SAMPLES = 1280
+export const SHORT_SYNC_SILENCE_SAMPLES = 400
+export const SHORT_SYMBOL_SAMPLES = 800
+export const SHORT_MAX_PAYLOAD_SIZE = 64
+export const TELEPHONY_SYNC_SAMPLES = 8000
+export const TELEPHONY_SYMBOL_SAMPLES = 4800
+export const CODEC_ROBUST_SYNC_SAMPLES = 8000
+export const CODEC_ROBUST_SYMBOL_SAMPLES = 4800
+export const NARROWBAND_SYNC_SAMPLES = 4000
+export const NARROWBAND_SYMBOL_SAMPLES = 2400
+export const MAX_BUFFER_SAMPLES = 80000
@@ -80,7 +80,24 @@ const FountainEncodePage: React.FC = () => {
     try {
       const encoder = await createFountainEncoder()
       const data = new TextEncoder().encode(text)
-      const samples = encoder.encode_fountain(data, TIMEOUT_SECS, BLOCK_SIZE, REPAIR_RATIO)
+      encoder.start_streaming(data, BLOCK_SIZE, REPAIR_RATIO, TIMEOUT_SECS)
+
+      // Pull one block per animation frame so the page stays responsive
+      const blocks: Float32Array[] = []
+      for (;;) {
+        const block: Float32Array | null = encoder.next_block()
+        if (!block) {
+          break
+        }
+        blocks.push(block)
+        await new Promise((resolve) => requestAnimationFrame(resolve))
+      }
+      const samples = new Float32Array(blocks.reduce((total, block) => total + block.length, 0))
+      let offset = 0
+      for (const block of blocks) {
+        samples.set(block, offset)
+        offset += block.length
+      }
 
       const blob = createWavBlob(samples, 16000, 1)
       const url = URL.createObjectURL(blob)
@@ -160,7 +177,7 @@ const FountainEncodePage: React.FC = () => {
 
     try {
       while (streamScheduledTimeRef.current - audioContext.currentTime < MIN_BUFFER_SECONDS) {
-        const block = encoder.next_stream_block()
+        const block: Float32Array | null = encoder.next_block()
         if (!block || block.length === 0) {
           setError('Streaming ended (timeout reached)')
           stopStreaming()
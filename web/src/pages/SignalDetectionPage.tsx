@@ -4,8 +4,7 @@ import { PostambleDetector, PreambleDetector } from '../utils/wasm'
 import { resampleAudio } from '../utils/audio'
 import Status from '../components/Status'
 import { getMicProcessorUrl } from '../utils/mic-processor-inline'
-
-const TARGET_SAMPLE_RATE = 16000
+import { MAX_BUFFER_SAMPLES, SAMPLE_RATE as TARGET_SAMPLE_RATE } from '../constants/modem'
 type DetectionMode = 'preamble' | 'postamble'
 
 const SignalDetectionPage: React.FC = () => {
@@ -127,7 +126,6 @@ const SignalDetectionPage: React.FC = () => {
 
         // Periodically clear buffer to prevent unbounded growth (every ~5 seconds at 16kHz)
         // Clear if we've accumulated more than 80k samples without detection
-        const MAX_BUFFER_SAMPLES = 80000
         if (samplesProcessedRef.current > MAX_BUFFER_SAMPLES) {
           detector.clear()
           samplesProcessedRef.current = 0
@@ -3,16 +3,17 @@ import { useNavigate } from 'react-router-dom'
 import { resampleAudio } from '../utils/audio'
 import Status from '../components/Status'
 import { getMicProcessorUrl } from '../utils/mic-processor-inline'
+import {
+  MAX_BUFFER_SAMPLES,
+  POSTAMBLE_SAMPLES,
+  PREAMBLE_SAMPLES,
+  SAMPLE_RATE as TARGET_SAMPLE_RATE,
+} from '../constants/modem'
 
 const MAX_RECORDING_DURATION = 30
-const TARGET_SAMPLE_RATE = 16000
-const MAX_BUFFER_SAMPLES = 80000 // Listening phase buffer cap (~5 seconds at 16kHz)
 const MAX_RECORDING_SAMPLES = 480000 // Recording phase buffer cap (~30 seconds at 16kHz)
-const PREAMBLE_DURATION_MS = 250
-const PREAMBLE_SAMPLES = (TARGET_SAMPLE_RATE * PREAMBLE_DURATION_MS) / 1000 // 4000
 const PRE_ROLL_MS = 100
 const PRE_ROLL_SAMPLES = (TARGET_SAMPLE_RATE * PRE_ROLL_MS) / 1000 // keep ~0.1s before preamble for safety
-const POSTAMBLE_SAMPLES = PREAMBLE_SAMPLES
 const POSTAMBLE_SEARCH_GUARD_SAMPLES = 1000 // skip a short cushion past the detected preamble before scanning for postamble
 const AUTO_GAIN_MIN = 0.3
 const AUTO_GAIN_MAX = 12.0
@@ -480,8 +481,6 @@ const StandardFskListenPage: React.FC = () => {
     if (preambleDetectedRef.current || isRecordingRef.current) return
 
     // Trim buffer to include just a small pre-roll before preamble
-    const PREAMBLE_DURATION_MS = 250
-    const PREAMBLE_SAMPLES = (TARGET_SAMPLE_RATE * PREAMBLE_DURATION_MS) / 1000
     const PRE_ROLL_MS = 100
     const PRE_ROLL_SAMPLES = (TARGET_SAMPLE_RATE * PRE_ROLL_MS) / 1000
 
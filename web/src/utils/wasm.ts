@@ -10,6 +10,9 @@ import init, {
     PreambleDetector,
     PostambleDetector,
     FountainPreambleDetector,
+    WasmError,
+    WasmErrorCode,
+    WasmSession,
 } from 'transmitwave-wasm';
 
 let wasmInitialized = false;
@@ -81,14 +84,49 @@ export {
     PreambleDetector,
     PostambleDetector,
     FountainPreambleDetector,
+    WasmError,
+    WasmErrorCode,
+    WasmSession,
 };
 
+/**
+ * Message of anything a binding (or other code) threw; bindings throw WasmError
+ */
+export function errorMessage(error: unknown, fallback: string): string {
+    if (error instanceof WasmError || error instanceof Error) {
+        return error.message;
+    }
+    return typeof error === 'string' ? error : fallback;
+}
+
+/**
+ * Code of a thrown WasmError, e.g. to tell WasmErrorCode.PreambleNotFound
+ * ("no transmission yet") from WasmErrorCode.FecDecodeFailure ("too noisy")
+ */
+export function wasmErrorCode(error: unknown): WasmErrorCode | undefined {
+    return error instanceof WasmError ? error.code : undefined;
+}
+
 /**
  * Utility types for WASM encoding/decoding
  * FSK-only mode for maximum reliability
  */
 export interface EncoderOptions {
     // FSK is the only supported mode for over-the-air audio transmission
+    // Every field is optional; omitted fields keep the encoder defaults
+    profile?: 'standard' | 'short' | 'telephony' | 'codec-robust' | 'narrowband';
+    rs?: string; // Reed-Solomon code as "N,K"
+    amplitude_bits?: boolean;
+    tone_layout?: string; // "TONES,BINS"
+    symbol_gaps?: number;
+    dual_band?: boolean;
+    amplitude?: number; // output gain in (0, 1]
+    safety_limiter?: number; // soft-limiter peak ceiling in (0, 1]
+    network_key?: string;
+    content_type?: string;
+    leading_silence_ms?: number;
+    trailing_silence_ms?: number;
+    fade_in_ms?: number;
 }
 
 export interface DecoderOptions {
@@ -102,9 +140,9 @@ export interface DecoderOptions {
  * Factory function to create an FSK encoder
  * FSK-only mode ensures maximum reliability for over-the-air audio transmission
  */
-export async function createEncoder(): Promise<WasmEncoder> {
+export async function createEncoder(options: EncoderOptions = {}): Promise<WasmEncoder> {
     await initWasm();
-    return new WasmEncoder();
+    return new WasmEncoder(options);
 }
 
 /**
@@ -129,12 +167,12 @@ export async function createDecoder(
 }
 
 /**
- * Factory function to create a fountain encoder
+ * Factory function to create a fountain encoder; takes the same options as createEncoder
  */
-export async function createFountainEncoder(): Promise<WasmFountainEncoder> {
+export async function createFountainEncoder(options: EncoderOptions = {}): Promise<WasmFountainEncoder> {
     await initWasm();
-        return new WasmFountainEncoder();
-    }
+    return new WasmFountainEncoder(options);
+}
 
 /**
  * Factory function to create a fountain decoder
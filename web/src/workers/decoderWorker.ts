@@ -1,4 +1,4 @@
-import { createDecoder, DetectionThreshold } from '../utils/wasm'
+import { createDecoder, DetectionThreshold, errorMessage, wasmErrorCode } from '../utils/wasm'
 
 interface InitMessage {
   type: 'init'
@@ -72,14 +72,9 @@ self.onmessage = async (event: MessageEvent<WorkerMessage>) => {
           console.log(`Decode succeeded in worker: "${text}"`)
           self.postMessage({ type: 'decode_success', text })
         } catch (error) {
-          let errorMsg = 'Decode failed'
-          if (error instanceof Error) {
-            errorMsg = error.message
-          } else if (typeof error === 'string') {
-            errorMsg = error
-          }
+          const errorMsg = errorMessage(error, 'Decode failed')
           console.error('Decode error in worker:', errorMsg)
-          self.postMessage({ type: 'decode_failed', error: errorMsg })
+          self.postMessage({ type: 'decode_failed', error: errorMsg, code: wasmErrorCode(error) })
         }
         break
       }
@@ -98,14 +93,9 @@ self.onmessage = async (event: MessageEvent<WorkerMessage>) => {
           console.log(`Decode without sync succeeded in worker: "${text}"`)
           self.postMessage({ type: 'decode_success', text })
         } catch (error) {
-          let errorMsg = 'Decode without sync failed'
-          if (error instanceof Error) {
-            errorMsg = error.message
-          } else if (typeof error === 'string') {
-            errorMsg = error
-          }
+          const errorMsg = errorMessage(error, 'Decode without sync failed')
           console.error('Decode without sync error in worker:', errorMsg)
-          self.postMessage({ type: 'decode_failed', error: errorMsg })
+          self.postMessage({ type: 'decode_failed', error: errorMsg, code: wasmErrorCode(error) })
         }
         break
       }